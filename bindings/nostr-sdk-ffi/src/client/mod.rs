@@ -629,7 +629,7 @@ impl Client {
                             .handle(
                                 relay_url.to_string(),
                                 subscription_id.to_string(),
-                                Arc::new((*event).into()),
+                                Arc::new((*event).clone().into()),
                             )
                             .await;
                     }
@@ -125,6 +125,8 @@ pub enum KindEnum {
     EncryptedDirectMessage,
     /// Event Deletion (NIP09)
     EventDeletion,
+    /// Request to Vanish (NIP62)
+    RequestToVanish,
     /// Repost (NIP18)
     Repost,
     /// Generic Repost (NIP18)
@@ -191,6 +193,30 @@ pub enum KindEnum {
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/35.md>
     TorrentComment,
+    /// Classified Listing
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/99.md>
+    ClassifiedListing,
+    /// Draft Classified Listing
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/99.md>
+    DraftClassifiedListing,
+    /// Relay Monitor Announcement
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    RelayMonitorAnnouncement,
+    /// Relay Discovery
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    RelayDiscovery,
+    /// Handler Recommendation
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+    HandlerRecommendation,
+    /// Handler Information
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+    HandlerInformation,
     /// Label
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/32.md>
@@ -364,6 +390,7 @@ impl From<nostr::Kind> for KindEnum {
             nostr::Kind::OpenTimestamps => Self::OpenTimestamps,
             nostr::Kind::EncryptedDirectMessage => Self::EncryptedDirectMessage,
             nostr::Kind::EventDeletion => Self::EventDeletion,
+            nostr::Kind::RequestToVanish => Self::RequestToVanish,
             nostr::Kind::Repost => Self::Repost,
             nostr::Kind::GenericRepost => Self::GenericRepost,
             nostr::Kind::Comment => Self::Comment,
@@ -439,6 +466,12 @@ impl From<nostr::Kind> for KindEnum {
             nostr::Kind::MlsGroupMessage => Self::MlsGroupMessage,
             nostr::Kind::Torrent => Self::Torrent,
             nostr::Kind::TorrentComment => Self::TorrentComment,
+            nostr::Kind::ClassifiedListing => Self::ClassifiedListing,
+            nostr::Kind::DraftClassifiedListing => Self::DraftClassifiedListing,
+            nostr::Kind::RelayMonitorAnnouncement => Self::RelayMonitorAnnouncement,
+            nostr::Kind::RelayDiscovery => Self::RelayDiscovery,
+            nostr::Kind::HandlerRecommendation => Self::HandlerRecommendation,
+            nostr::Kind::HandlerInformation => Self::HandlerInformation,
             nostr::Kind::Regular(u) => Self::Regular { kind: u },
             nostr::Kind::Replaceable(u) => Self::Replaceable { kind: u },
             nostr::Kind::Ephemeral(u) => Self::Ephemeral { kind: u },
@@ -458,6 +491,7 @@ impl From<KindEnum> for nostr::Kind {
             KindEnum::OpenTimestamps => Self::OpenTimestamps,
             KindEnum::EncryptedDirectMessage => Self::EncryptedDirectMessage,
             KindEnum::EventDeletion => Self::EventDeletion,
+            KindEnum::RequestToVanish => Self::RequestToVanish,
             KindEnum::Repost => Self::Repost,
             KindEnum::GenericRepost => Self::GenericRepost,
             KindEnum::Comment => Self::Comment,
@@ -533,6 +567,12 @@ impl From<KindEnum> for nostr::Kind {
             KindEnum::MlsGroupMessage => Self::MlsGroupMessage,
             KindEnum::Torrent => Self::Torrent,
             KindEnum::TorrentComment => Self::TorrentComment,
+            KindEnum::ClassifiedListing => Self::ClassifiedListing,
+            KindEnum::DraftClassifiedListing => Self::DraftClassifiedListing,
+            KindEnum::RelayMonitorAnnouncement => Self::RelayMonitorAnnouncement,
+            KindEnum::RelayDiscovery => Self::RelayDiscovery,
+            KindEnum::HandlerRecommendation => Self::HandlerRecommendation,
+            KindEnum::HandlerInformation => Self::HandlerInformation,
             KindEnum::Regular { kind } => Self::Regular(kind),
             KindEnum::Replaceable { kind } => Self::Replaceable(kind),
             KindEnum::Ephemeral { kind } => Self::Ephemeral(kind),
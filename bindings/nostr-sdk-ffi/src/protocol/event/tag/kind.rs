@@ -103,6 +103,10 @@ pub enum TagKind {
     /// Total participants
     TotalParticipants,
     Tracker,
+    /// Location (NIP99)
+    Location,
+    /// Price (NIP99)
+    Price,
     /// HTTP Method Request
     Method,
     /// Payload HASH
@@ -166,6 +170,8 @@ impl<'a> From<tag::TagKind<'a>> for TagKind {
             tag::TagKind::CurrentParticipants => Self::CurrentParticipants,
             tag::TagKind::TotalParticipants => Self::TotalParticipants,
             tag::TagKind::Tracker => Self::Tracker,
+            tag::TagKind::Location => Self::Location,
+            tag::TagKind::Price => Self::Price,
             tag::TagKind::Method => Self::Method,
             tag::TagKind::Payload => Self::Payload,
             tag::TagKind::Anon => Self::Anon,
@@ -231,6 +237,8 @@ impl<'a> From<TagKind> for tag::TagKind<'a> {
             TagKind::CurrentParticipants => Self::CurrentParticipants,
             TagKind::TotalParticipants => Self::TotalParticipants,
             TagKind::Tracker => Self::Tracker,
+            TagKind::Location => Self::Location,
+            TagKind::Price => Self::Price,
             TagKind::Method => Self::Method,
             TagKind::Payload => Self::Payload,
             TagKind::Anon => Self::Anon,
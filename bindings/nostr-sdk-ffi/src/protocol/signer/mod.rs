@@ -13,8 +13,10 @@ use uniffi::{Enum, Object};
 use crate::connect::NostrConnect;
 
 pub mod custom;
+pub mod nip55;
 
 use self::custom::{CustomNostrSigner, IntermediateCustomNostrSigner};
+use self::nip55::Nip55Signer;
 use super::event::{Event, UnsignedEvent};
 use super::key::PublicKey;
 use crate::error::Result;
@@ -97,6 +99,15 @@ impl NostrSigner {
         }
     }
 
+    /// Android signer application (NIP55)
+    #[uniffi::constructor]
+    pub fn nip55(signer: &Nip55Signer) -> Self {
+        let signer = signer.clone();
+        Self {
+            inner: signer.into_nostr_signer(),
+        }
+    }
+
     #[uniffi::constructor]
     pub fn custom(custom: Arc<dyn CustomNostrSigner>) -> Self {
         let signer = IntermediateCustomNostrSigner { inner: custom };
@@ -0,0 +1,414 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Android signer application (NIP55)
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/55.md>
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use nostr::prelude::*;
+use uniffi::{Object, Record};
+
+use crate::error::NostrSdkError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Nip55Type {
+    GetPublicKey,
+    SignEvent,
+    Nip04Encrypt,
+    Nip04Decrypt,
+    Nip44Encrypt,
+    Nip44Decrypt,
+}
+
+impl Nip55Type {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::GetPublicKey => "get_public_key",
+            Self::SignEvent => "sign_event",
+            Self::Nip04Encrypt => "nip04_encrypt",
+            Self::Nip04Decrypt => "nip04_decrypt",
+            Self::Nip44Encrypt => "nip44_encrypt",
+            Self::Nip44Decrypt => "nip44_decrypt",
+        }
+    }
+}
+
+/// NIP55 request
+///
+/// The same request can be sent through the content resolver or through an intent.
+#[derive(Debug, Clone, Record)]
+pub struct Nip55Request {
+    /// Value of the `type` intent extra (i.e. `sign_event`)
+    pub method: String,
+    /// Content provider URI (i.e. `content://com.example.signer.SIGN_EVENT`)
+    ///
+    /// Available only when the signer package is known.
+    pub uri: Option<String>,
+    /// Intent data is `nostrsigner:<content>`
+    ///
+    /// The unsigned event JSON, the plain text or the ciphertext. Empty for `get_public_key`.
+    pub content: String,
+    /// Public key of the other party, for encryption and decryption
+    pub public_key: Option<String>,
+    /// Logged-in user public key
+    pub current_user: Option<String>,
+    /// Signer package name
+    pub package: Option<String>,
+}
+
+/// NIP55 response
+#[derive(Debug, Clone, Record)]
+pub struct Nip55Response {
+    /// The `result` column or intent extra (public key, signature, ciphertext or plain text)
+    pub result: Option<String>,
+    /// The `event` column or intent extra (signed event JSON)
+    pub event: Option<String>,
+    /// The `package` intent extra
+    pub package: Option<String>,
+    /// The `rejected` column is present or the activity was canceled
+    pub rejected: bool,
+}
+
+/// Bridge to the Android APIs
+///
+/// Must be implemented in Kotlin, using the `ContentResolver` and the `ActivityResultLauncher`.
+#[uniffi::export(with_foreign)]
+#[async_trait::async_trait]
+pub trait Nip55Bridge: Send + Sync {
+    /// Query the signer content provider, without user interaction
+    ///
+    /// Return `None` if the query returned no cursor (the user hasn't granted the permission in background):
+    /// the request will be sent through an intent.
+    async fn content_resolver(
+        &self,
+        request: Nip55Request,
+    ) -> crate::error::Result<Option<Nip55Response>>;
+
+    /// Launch the `nostrsigner:` intent and wait for the activity result
+    async fn intent(&self, request: Nip55Request) -> crate::error::Result<Nip55Response>;
+}
+
+#[derive(Debug)]
+struct Error(String);
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<NostrSdkError> for Error {
+    fn from(e: NostrSdkError) -> Self {
+        Self(e.to_string())
+    }
+}
+
+impl Error {
+    fn missing(field: &str) -> Self {
+        Self(format!("Missing '{field}' in signer response"))
+    }
+}
+
+/// Android signer application (NIP55)
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/55.md>
+#[derive(Clone, Object)]
+pub struct Nip55Signer {
+    bridge: Arc<dyn Nip55Bridge>,
+    package: Arc<Mutex<Option<String>>>,
+    public_key: Arc<Mutex<Option<PublicKey>>>,
+}
+
+impl fmt::Debug for Nip55Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Nip55Signer")
+            .field("package", &self.package)
+            .finish()
+    }
+}
+
+#[uniffi::export]
+impl Nip55Signer {
+    /// Construct NIP55 signer
+    ///
+    /// If the `package` is unknown, the first `get_public_key` request let the user choose the signer application.
+    #[uniffi::constructor]
+    pub fn new(bridge: Arc<dyn Nip55Bridge>, package: Option<String>) -> Self {
+        Self {
+            bridge,
+            package: Arc::new(Mutex::new(package)),
+            public_key: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Signer package name
+    pub fn package(&self) -> Option<String> {
+        self.package.lock().ok()?.clone()
+    }
+}
+
+impl Nip55Signer {
+    async fn send(
+        &self,
+        r#type: Nip55Type,
+        content: String,
+        public_key: Option<&PublicKey>,
+    ) -> Result<Nip55Response, Error> {
+        let package: Option<String> = self.package();
+        let current_user: Option<String> = match r#type {
+            Nip55Type::GetPublicKey => None,
+            _ => self.cached_public_key().map(|p| p.to_hex()),
+        };
+
+        let request = Nip55Request {
+            method: r#type.as_str().to_string(),
+            uri: package
+                .as_ref()
+                .map(|p| format!("content://{p}.{}", r#type.as_str().to_uppercase())),
+            content,
+            public_key: public_key.map(|p| p.to_hex()),
+            current_user,
+            package,
+        };
+
+        // Try in background first
+        let response: Nip55Response = match request.uri {
+            Some(..) => match self.bridge.content_resolver(request.clone()).await? {
+                Some(response) => response,
+                None => self.bridge.intent(request).await?,
+            },
+            None => self.bridge.intent(request).await?,
+        };
+
+        if response.rejected {
+            return Err(Error(String::from("Request rejected by the signer")));
+        }
+
+        // Remember the signer chosen by the user
+        if let Some(package) = &response.package {
+            if let Ok(mut current) = self.package.lock() {
+                current.get_or_insert_with(|| package.clone());
+            }
+        }
+
+        Ok(response)
+    }
+
+    fn cached_public_key(&self) -> Option<PublicKey> {
+        *self.public_key.lock().ok()?
+    }
+
+    async fn _get_public_key(&self) -> Result<PublicKey, Error> {
+        if let Some(public_key) = self.cached_public_key() {
+            return Ok(public_key);
+        }
+
+        let response = self
+            .send(Nip55Type::GetPublicKey, String::new(), None)
+            .await?;
+        let result: String = response.result.ok_or_else(|| Error::missing("result"))?;
+
+        // Signers may return both hex and bech32
+        let public_key: PublicKey = PublicKey::parse(&result).map_err(|e| Error(e.to_string()))?;
+
+        if let Ok(mut current) = self.public_key.lock() {
+            *current = Some(public_key);
+        }
+
+        Ok(public_key)
+    }
+
+    async fn _sign_event(&self, mut unsigned: UnsignedEvent) -> Result<Event, Error> {
+        unsigned.ensure_id();
+
+        let response = self
+            .send(Nip55Type::SignEvent, unsigned.as_json(), None)
+            .await?;
+
+        // Prefer the signed event, if provided
+        if let Some(event) = response.event {
+            let event: Event = Event::from_json(event).map_err(|e| Error(e.to_string()))?;
+            event.verify().map_err(|e| Error(e.to_string()))?;
+
+            // The ID commits to the public key, the timestamp, the kind, the tags and the content
+            if unsigned.id != Some(event.id) {
+                return Err(Error(String::from(
+                    "Signed event not match the unsigned one",
+                )));
+            }
+
+            return Ok(event);
+        }
+
+        let sig: String = response.result.ok_or_else(|| Error::missing("result"))?;
+        let sig: Signature = Signature::from_str(&sig).map_err(|e| Error(e.to_string()))?;
+        unsigned
+            .add_signature(sig)
+            .map_err(|e| Error(e.to_string()))
+    }
+
+    async fn _encrypt_decrypt(
+        &self,
+        r#type: Nip55Type,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, Error> {
+        let response = self
+            .send(r#type, content.to_string(), Some(public_key))
+            .await?;
+        response.result.ok_or_else(|| Error::missing("result"))
+    }
+}
+
+#[async_trait::async_trait]
+impl NostrSigner for Nip55Signer {
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::Custom(Cow::Borrowed("nip55"))
+    }
+
+    async fn get_public_key(&self) -> Result<PublicKey, SignerError> {
+        self._get_public_key().await.map_err(SignerError::backend)
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+        self._sign_event(unsigned)
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self._encrypt_decrypt(Nip55Type::Nip04Encrypt, public_key, content)
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        public_key: &PublicKey,
+        encrypted_content: &str,
+    ) -> Result<String, SignerError> {
+        self._encrypt_decrypt(Nip55Type::Nip04Decrypt, public_key, encrypted_content)
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self._encrypt_decrypt(Nip55Type::Nip44Encrypt, public_key, content)
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: &PublicKey,
+        payload: &str,
+    ) -> Result<String, SignerError> {
+        self._encrypt_decrypt(Nip55Type::Nip44Decrypt, public_key, payload)
+            .await
+            .map_err(SignerError::backend)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy)]
+    enum Mode {
+        Honest,
+        OtherContent,
+        OtherKeys,
+        SignatureOfOtherEvent,
+    }
+
+    struct MockBridge {
+        keys: Keys,
+        mode: Mode,
+    }
+
+    #[async_trait::async_trait]
+    impl Nip55Bridge for MockBridge {
+        async fn content_resolver(
+            &self,
+            _request: Nip55Request,
+        ) -> crate::error::Result<Option<Nip55Response>> {
+            Ok(None)
+        }
+
+        async fn intent(&self, request: Nip55Request) -> crate::error::Result<Nip55Response> {
+            let mut response = Nip55Response {
+                result: None,
+                event: None,
+                package: Some(String::from("com.example.signer")),
+                rejected: false,
+            };
+
+            if request.method == Nip55Type::GetPublicKey.as_str() {
+                response.result = Some(self.keys.public_key().to_bech32().unwrap());
+                return Ok(response);
+            }
+
+            let mut unsigned = UnsignedEvent::from_json(&request.content).unwrap();
+            unsigned.id = None;
+            let keys: Keys = match self.mode {
+                Mode::OtherKeys => Keys::generate(),
+                _ => self.keys.clone(),
+            };
+            unsigned.pubkey = keys.public_key();
+            if let Mode::OtherContent | Mode::SignatureOfOtherEvent = self.mode {
+                unsigned.content = String::from("Something else");
+            }
+
+            let event: Event = unsigned.sign_with_keys(&keys).unwrap();
+            match self.mode {
+                Mode::SignatureOfOtherEvent => response.result = Some(event.sig.to_string()),
+                _ => response.event = Some(event.as_json()),
+            }
+            Ok(response)
+        }
+    }
+
+    async fn sign(mode: Mode) -> Result<Event, Error> {
+        let keys = Keys::generate();
+        let signer = Nip55Signer::new(
+            Arc::new(MockBridge {
+                keys: keys.clone(),
+                mode,
+            }),
+            None,
+        );
+        assert_eq!(signer._get_public_key().await.unwrap(), keys.public_key());
+
+        let unsigned = EventBuilder::text_note("Hello").build(keys.public_key());
+        signer._sign_event(unsigned).await
+    }
+
+    #[tokio::test]
+    async fn test_sign_event() {
+        let event = sign(Mode::Honest).await.unwrap();
+        assert_eq!(event.content, "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_sign_event_not_match() {
+        assert!(sign(Mode::OtherContent).await.is_err());
+        assert!(sign(Mode::OtherKeys).await.is_err());
+        assert!(sign(Mode::SignatureOfOtherEvent).await.is_err());
+    }
+}
@@ -245,6 +245,20 @@ impl Filter {
         builder
     }
 
+    /// Set NIP26 delegator (`#delegation` tag query)
+    pub fn delegator(&self, delegator: &PublicKey) -> Self {
+        let mut builder = self.clone();
+        builder.inner = builder.inner.delegator(**delegator);
+        builder
+    }
+
+    /// Set NIP26 delegators (`#delegation` tag query)
+    pub fn delegators(&self, delegators: &[Arc<PublicKey>]) -> Self {
+        let mut builder = self.clone();
+        builder.inner = builder.inner.delegators(delegators.iter().map(|pk| ***pk));
+        builder
+    }
+
     pub fn hashtag(&self, hashtag: &str) -> Self {
         let mut builder = self.clone();
         builder.inner = builder.inner.hashtag(hashtag);
@@ -451,6 +465,8 @@ pub struct FilterRecord {
     pub limit: Option<u64>,
     /// Generic tag queries
     pub generic_tags: Vec<GenericTag>,
+    /// NIP26 delegators (`#delegation` tag query)
+    pub delegators: Option<Vec<Arc<PublicKey>>>,
 }
 
 impl From<nostr::Filter> for FilterRecord {
@@ -477,6 +493,9 @@ impl From<nostr::Filter> for FilterRecord {
                     value: v.into_iter().map(|v| v.to_string()).collect(),
                 })
                 .collect(),
+            delegators: f
+                .delegators
+                .map(|delegators| delegators.into_iter().map(|v| Arc::new(v.into())).collect()),
         }
     }
 }
@@ -500,6 +519,9 @@ impl From<FilterRecord> for nostr::Filter {
                 .into_iter()
                 .map(|GenericTag { key, value }| (**key, value.into_iter().collect()))
                 .collect(),
+            delegators: f
+                .delegators
+                .map(|delegators| delegators.into_iter().map(|v| **v).collect()),
         }
     }
 }
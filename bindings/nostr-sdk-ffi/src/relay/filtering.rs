@@ -6,8 +6,10 @@ use std::ops::Deref;
 use std::sync::Arc;
 
 use nostr_sdk::pool::relay;
+use nostr_sdk::RelayUrl;
 use uniffi::{Enum, Object};
 
+use crate::error::Result;
 use crate::protocol::{EventId, PublicKey};
 
 #[derive(Enum)]
@@ -83,6 +85,18 @@ impl RelayFiltering {
         self.inner.update_mode(mode.into());
     }
 
+    /// Check if events that mention blacklisted public keys are discarded
+    pub fn is_mentions_filtering_enabled(&self) -> bool {
+        self.inner.is_mentions_filtering_enabled()
+    }
+
+    /// Discard events that mention (`p` tag) blacklisted public keys (default: false)
+    ///
+    /// Note: ignored in whitelist mode!
+    pub fn update_mentions_filtering(&self, enable: bool) {
+        self.inner.update_mentions_filtering(enable);
+    }
+
     /// Add event IDs
     ///
     /// Note: IDs are ignored in whitelist mode!
@@ -142,6 +156,33 @@ impl RelayFiltering {
         self.inner.has_public_key(public_key.deref()).await
     }
 
+    /// Add relay URLs
+    ///
+    /// Blacklisted relays are never connected.
+    ///
+    /// Note: relay URLs are always treated as a blacklist, regardless of the mode!
+    pub async fn add_relay_urls(&self, urls: Vec<String>) -> Result<()> {
+        let urls: Vec<RelayUrl> = urls
+            .iter()
+            .map(|url| RelayUrl::parse(url))
+            .collect::<Result<_, _>>()?;
+        self.inner.add_relay_urls(urls).await;
+        Ok(())
+    }
+
+    /// Remove relay URL
+    pub async fn remove_relay_url(&self, url: &str) -> Result<()> {
+        let url: RelayUrl = RelayUrl::parse(url)?;
+        self.inner.remove_relay_url(&url).await;
+        Ok(())
+    }
+
+    /// Check if has relay URL
+    pub async fn has_relay_url(&self, url: &str) -> Result<bool> {
+        let url: RelayUrl = RelayUrl::parse(url)?;
+        Ok(self.inner.has_relay_url(&url).await)
+    }
+
     /// Remove everything
     pub async fn clear(&self) {
         self.inner.clear().await
@@ -112,6 +112,13 @@ impl RelayOptions {
         builder
     }
 
+    /// Ping interval (default: 55 sec)
+    pub fn ping_interval(&self, interval: Duration) -> Self {
+        let mut builder = self.clone();
+        builder.inner = builder.inner.ping_interval(interval);
+        builder
+    }
+
     /// Enable/disable auto reconnection (default: true)
     pub fn reconnect(&self, reconnect: bool) -> Self {
         let mut builder = self.clone();
@@ -135,6 +142,20 @@ impl RelayOptions {
         builder
     }
 
+    /// Ban the relay after the specified number of consecutive connection failures (default: None)
+    pub fn ban_threshold(&self, failures: Option<u64>) -> Self {
+        let mut builder = self.clone();
+        builder.inner = builder.inner.ban_threshold(failures.map(|f| f as usize));
+        builder
+    }
+
+    /// Ban duration (default: 10 min)
+    pub fn ban_duration(&self, duration: Duration) -> Self {
+        let mut builder = self.clone();
+        builder.inner = builder.inner.ban_duration(duration);
+        builder
+    }
+
     /// Set custom limits
     pub fn limits(&self, limits: &RelayLimits) -> Self {
         let mut builder = self.clone();
@@ -705,7 +705,7 @@ impl JsClient {
                         }
                     }
                     RelayPoolNotification::Event { relay_url, subscription_id, event } => {
-                        let event: JsEvent = (*event).into();
+                        let event: JsEvent = (*event).clone().into();
                         if callback.handle_event(relay_url.to_string(), subscription_id.to_string(), event).await.as_bool().unwrap_or_default() {
                             tracing::info!("Received `true` in `handleEvent`: exiting from `handleNotifications`");
                             return Ok(true);
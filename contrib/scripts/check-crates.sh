@@ -36,6 +36,7 @@ buildargs=(
     "-p nostr --features all-nips" # std + all-nips
     "-p nostr --no-default-features --features alloc" # Only alloc feature
     "-p nostr --no-default-features --features alloc,all-nips" # alloc + all-nips
+    "-p nostr --features performance" # std + SIMD hex and accelerated SHA256
     "-p nostr-database"
     "-p nostr-lmdb"
     "-p nostr-indexeddb --target wasm32-unknown-unknown"
@@ -94,3 +95,29 @@ do
 
     echo
 done
+
+# Single-threaded executors (a cfg and not a feature, since it removes the `Send` bounds)
+cfgargs=(
+    "-p nostr-relay-pool"
+    "-p nostr-sdk"
+)
+
+for arg in "${cfgargs[@]}";
+do
+    echo  "Checking '$arg' [--cfg nostr_local]"
+
+    RUSTFLAGS="--cfg nostr_local" cargo $version check $arg
+    RUSTFLAGS="--cfg nostr_local" cargo $version clippy $arg -- -D warnings
+
+    # The other tests require a multi-threaded runtime
+    if [[ $arg == "-p nostr-relay-pool" ]];
+    then
+        RUSTFLAGS="--cfg nostr_local" cargo $version test $arg --lib spawner
+    fi
+
+    if [ "$is_ci" == true ]; then
+        cargo clean
+    fi
+
+    echo
+done
@@ -41,6 +41,35 @@ pub enum Command {
 pub enum ShellCommand {
     /// Generate random keys
     Generate,
+    /// Convert between hex and bech32 (NIP19)
+    #[command(arg_required_else_help = true)]
+    Convert {
+        /// Hex or bech32 value
+        value: String,
+        /// Entity of the hex value (required, since a hex value can be both a public key and an event ID)
+        #[clap(long, value_enum)]
+        to: Option<ShellConvertTarget>,
+    },
+    /// Publish a text note
+    #[command(arg_required_else_help = true)]
+    Publish {
+        /// Content
+        content: String,
+    },
+    /// Fetch profile metadata
+    #[command(arg_required_else_help = true)]
+    Profile {
+        /// Public key
+        public_key: PublicKey,
+    },
+    /// Send private direct message (NIP17)
+    #[command(arg_required_else_help = true)]
+    SendDm {
+        /// Receiver public key
+        receiver: PublicKey,
+        /// Message
+        message: String,
+    },
     /// Sync public key's event with specified relays (negentropy)
     #[command(arg_required_else_help = true)]
     Sync {
@@ -86,6 +115,8 @@ pub enum ShellCommand {
         #[clap(long)]
         print: bool,
         /// Print result as JSON (require `print` flag!)
+        ///
+        /// When querying relays, the events are streamed as JSONL.
         #[clap(long)]
         json: bool,
     },
@@ -111,6 +142,16 @@ pub enum ShellCommandDatabase {
     Stats,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ShellConvertTarget {
+    /// Public key
+    Npub,
+    /// Secret key
+    Nsec,
+    /// Event ID
+    Note,
+}
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum ShellSyncDirection {
     /// Send events to relay
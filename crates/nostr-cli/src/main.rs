@@ -21,7 +21,7 @@ use tokio::time::Instant;
 mod cli;
 mod util;
 
-use self::cli::{io, parser, Cli, Command, ShellCommand, ShellCommandDatabase};
+use self::cli::{io, parser, Cli, Command, ShellCommand, ShellCommandDatabase, ShellConvertTarget};
 
 #[tokio::main]
 async fn main() {
@@ -161,6 +161,62 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
             println!("Public key: {}", keys.public_key().to_bech32()?);
             Ok(())
         }
+        ShellCommand::Convert { value, to } => {
+            match Nip19::from_bech32(&value) {
+                Ok(Nip19::Secret(secret_key)) => println!("{}", secret_key.to_secret_hex()),
+                Ok(Nip19::Pubkey(public_key)) => println!("{}", public_key.to_hex()),
+                Ok(Nip19::EventId(id)) => println!("{}", id.to_hex()),
+                Ok(Nip19::Profile(profile)) => println!("{}", profile.public_key.to_hex()),
+                Ok(Nip19::Event(event)) => println!("{}", event.event_id.to_hex()),
+                Ok(Nip19::Coordinate(coordinate)) => println!("{coordinate}"),
+                Ok(..) => eprintln!("Unsupported bech32 entity"),
+                // Hex value
+                Err(..) => match to {
+                    Some(ShellConvertTarget::Npub) => {
+                        let public_key: PublicKey = PublicKey::from_hex(&value)?;
+                        println!("{}", public_key.to_bech32()?);
+                    }
+                    Some(ShellConvertTarget::Nsec) => {
+                        let secret_key: SecretKey = SecretKey::from_hex(&value)?;
+                        println!("{}", secret_key.to_bech32()?);
+                    }
+                    Some(ShellConvertTarget::Note) => {
+                        let id: EventId = EventId::from_hex(&value)?;
+                        println!("{}", id.to_bech32()?);
+                    }
+                    None => eprintln!("Specify the entity of the hex value with `--to`"),
+                },
+            }
+
+            Ok(())
+        }
+        ShellCommand::Publish { content } => {
+            let keys: Keys = io::get_keys("Secret key")?;
+
+            let event: Event = EventBuilder::text_note(content).sign_with_keys(&keys)?;
+            let output: Output<EventId> = client.send_event(event).await?;
+
+            util::print_output(output)?;
+
+            Ok(())
+        }
+        ShellCommand::Profile { public_key } => {
+            let metadata: Metadata = client
+                .fetch_metadata(public_key, Duration::from_secs(10))
+                .await?;
+            println!("{}", metadata.as_pretty_json());
+            Ok(())
+        }
+        ShellCommand::SendDm { receiver, message } => {
+            let keys: Keys = io::get_keys("Secret key")?;
+
+            let event: Event = EventBuilder::private_msg(&keys, receiver, message, []).await?;
+            let output: Output<EventId> = client.send_event(event).await?;
+
+            util::print_output(output)?;
+
+            Ok(())
+        }
         ShellCommand::Sync {
             public_key,
             relays,
@@ -293,8 +349,26 @@ async fn handle_command(command: ShellCommand, client: &Client) -> Result<()> {
                     // Print events
                     util::print_events(events, json);
                 }
+            } else if print && json {
+                // Stream events from relays as JSONL
+                let mut stream = client
+                    .stream_events(vec![filter], Duration::from_secs(10))
+                    .await?;
+                while let Some(event) = stream.next().await {
+                    println!("{}", event.as_json());
+                }
             } else {
                 // Query relays
+                let events = client
+                    .fetch_events(vec![filter], Duration::from_secs(10))
+                    .await?;
+
+                println!("{} results", events.len());
+
+                if print {
+                    // Print events
+                    util::print_events(events, false);
+                }
             }
 
             Ok(())
@@ -31,3 +31,17 @@ where
         table.printstd();
     }
 }
+
+pub fn print_output(output: Output<EventId>) -> Result<()> {
+    println!("Event ID: {}", output.id().to_bech32()?);
+
+    for url in output.success.iter() {
+        println!("- Sent to {url}");
+    }
+
+    for (url, e) in output.failed.iter() {
+        println!("- Failed to send to {url}: {e}");
+    }
+
+    Ok(())
+}
@@ -413,7 +413,7 @@ async fn get_remote_signer_public_key(
                 if event.kind == Kind::NostrConnect {
                     // Decrypt content
                     let msg: String =
-                        nip04::decrypt(app_keys.secret_key(), &event.pubkey, event.content)?;
+                        nip04::decrypt(app_keys.secret_key(), &event.pubkey, &event.content)?;
 
                     tracing::debug!("Received Nostr Connect message: '{msg}'");
 
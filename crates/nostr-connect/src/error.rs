@@ -6,7 +6,7 @@
 
 use std::convert::Infallible;
 
-use nostr::event::builder;
+use nostr::event::{self, builder};
 use nostr::nips::{nip04, nip46};
 use nostr::PublicKey;
 use thiserror::Error;
@@ -18,6 +18,9 @@ pub enum Error {
     /// Event builder error
     #[error(transparent)]
     Builder(#[from] builder::Error),
+    /// Event error
+    #[error(transparent)]
+    Event(#[from] event::Error),
     /// NIP04 error
     #[error(transparent)]
     NIP04(#[from] nip04::Error),
@@ -36,6 +39,12 @@ pub enum Error {
     /// Signer public key not found
     #[error("signer public key not found")]
     SignerPublicKeyNotFound,
+    /// I/O error
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    /// External signer disconnected
+    #[error("external signer disconnected")]
+    Disconnected,
     /// Request timeout
     #[error("timeout")]
     Timeout,
@@ -54,6 +63,9 @@ pub enum Error {
         /// The local set user public key
         local: Box<PublicKey>,
     },
+    /// The event signed by the signer doesn't match the request
+    #[error("signed event not match the unsigned one")]
+    SignedEventNotMatch,
     /// Infallible
     #[error(transparent)]
     Infallible(#[from] Infallible),
@@ -0,0 +1,465 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! External signer
+//!
+//! Talk to a signer running in a separate process, over a Unix socket or over the stdio of a child process.
+//!
+//! The protocol reuses the NIP46 messages, without the encryption and the relays:
+//! each request and response is a JSON object on a single line.
+//!
+//! ```text
+//! > {"id":"a1b2","method":"get_public_key","params":[]}
+//! < {"id":"a1b2","result":"79dff8f82963424e0bb02708a22e44b4980893e3a4be0fa3cb60a43b946764e3","error":null}
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use async_utility::time;
+use nostr::nips::nip46::{Message, Request, ResponseResult};
+use nostr::prelude::*;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::process::{Child, Command};
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::error::Error;
+
+/// External signer transport
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalSignerTransport {
+    /// Connect to the Unix socket of a signer daemon
+    #[cfg(unix)]
+    UnixSocket(PathBuf),
+    /// Spawn the signer and talk to it through its stdin and stdout
+    Process {
+        /// Program
+        program: String,
+        /// Arguments
+        args: Vec<String>,
+    },
+}
+
+struct Connection {
+    reader: BufReader<Box<dyn AsyncRead + Send + Unpin>>,
+    writer: Box<dyn AsyncWrite + Send + Unpin>,
+    /// Keep the child process alive (killed on drop)
+    _child: Option<Child>,
+}
+
+impl Connection {
+    async fn open(transport: &ExternalSignerTransport) -> Result<Self, Error> {
+        match transport {
+            #[cfg(unix)]
+            ExternalSignerTransport::UnixSocket(path) => {
+                let stream: UnixStream = UnixStream::connect(path).await?;
+                let (reader, writer) = stream.into_split();
+                Ok(Self {
+                    reader: BufReader::new(Box::new(reader)),
+                    writer: Box::new(writer),
+                    _child: None,
+                })
+            }
+            ExternalSignerTransport::Process { program, args } => {
+                let mut child: Child = Command::new(program)
+                    .args(args)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::inherit())
+                    .kill_on_drop(true)
+                    .spawn()?;
+                let stdin = child.stdin.take().ok_or(Error::Disconnected)?;
+                let stdout = child.stdout.take().ok_or(Error::Disconnected)?;
+                Ok(Self {
+                    reader: BufReader::new(Box::new(stdout)),
+                    writer: Box::new(stdin),
+                    _child: Some(child),
+                })
+            }
+        }
+    }
+
+    async fn send(&mut self, msg: &Message) -> Result<(), Error> {
+        let mut line: String = msg.as_json();
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Result<Message, Error> {
+        loop {
+            let mut line: String = String::new();
+            if self.reader.read_line(&mut line).await? == 0 {
+                return Err(Error::Disconnected);
+            }
+
+            let line: &str = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            return Ok(Message::from_json(line)?);
+        }
+    }
+}
+
+/// External signer
+///
+/// Keep the keys in a separate (hardened) process and forward to it the signing and encryption requests.
+///
+/// The connection is opened at the first request and re-opened if the signer goes away.
+#[derive(Clone)]
+pub struct ExternalSigner {
+    transport: ExternalSignerTransport,
+    timeout: Duration,
+    connection: Arc<Mutex<Option<Connection>>>,
+    public_key: Arc<OnceCell<PublicKey>>,
+}
+
+impl fmt::Debug for ExternalSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalSigner")
+            .field("transport", &self.transport)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl ExternalSigner {
+    /// Construct external signer
+    ///
+    /// The `timeout` is applied to every request, so must leave to the user the time to confirm it.
+    pub fn new(transport: ExternalSignerTransport, timeout: Duration) -> Self {
+        Self {
+            transport,
+            timeout,
+            connection: Arc::new(Mutex::new(None)),
+            public_key: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Get transport
+    #[inline]
+    pub fn transport(&self) -> &ExternalSignerTransport {
+        &self.transport
+    }
+
+    /// Check if the signer is reachable
+    pub async fn ping(&self) -> Result<(), Error> {
+        let res = self.send_request(Request::Ping).await?;
+        Ok(res.to_pong()?)
+    }
+
+    async fn send_request(&self, req: Request) -> Result<ResponseResult, Error> {
+        // Requests are sent one at a time
+        let mut connection = self.connection.lock().await;
+
+        let msg = Message::request(req);
+        tracing::debug!("Sending '{msg}' message to external signer");
+
+        let res = time::timeout(Some(self.timeout), async {
+            // Open connection, if needed
+            if connection.is_none() {
+                *connection = Some(Connection::open(&self.transport).await?);
+            }
+
+            let conn: &mut Connection = connection.as_mut().ok_or(Error::Disconnected)?;
+
+            // Send request
+            conn.send(&msg).await?;
+
+            // Wait for the response
+            loop {
+                let res: Message = conn.recv().await?;
+
+                tracing::debug!("Received '{res}' message from external signer");
+
+                if let Message::Response { id, result, error } = res {
+                    if id != msg.id() {
+                        continue;
+                    }
+
+                    if let Some(result) = result {
+                        return Ok(result);
+                    }
+
+                    return Err(Error::Response(
+                        error.unwrap_or_else(|| String::from("empty response")),
+                    ));
+                }
+            }
+        })
+        .await
+        .unwrap_or(Err(Error::Timeout));
+
+        // Drop the connection if broken or out of sync
+        if let Err(Error::IO(..) | Error::Disconnected | Error::NIP46(..) | Error::Timeout) = &res {
+            *connection = None;
+        }
+
+        res
+    }
+
+    async fn _get_public_key(&self) -> Result<&PublicKey, Error> {
+        self.public_key
+            .get_or_try_init(|| async {
+                let res = self.send_request(Request::GetPublicKey).await?;
+                Ok(res.to_get_public_key()?)
+            })
+            .await
+    }
+
+    async fn _sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, Error> {
+        let res = self
+            .send_request(Request::SignEvent(unsigned.clone()))
+            .await?;
+        let event: Event = res.to_sign_event()?;
+
+        // Don't trust the signer: it may have signed something else
+        check_signed_event(&unsigned, &event)?;
+
+        Ok(event)
+    }
+
+    async fn _nip04_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Error> {
+        let req = Request::Nip04Encrypt {
+            public_key,
+            text: content,
+        };
+        let res = self.send_request(req).await?;
+        Ok(res.to_encrypt_decrypt()?)
+    }
+
+    async fn _nip04_decrypt(
+        &self,
+        public_key: PublicKey,
+        ciphertext: String,
+    ) -> Result<String, Error> {
+        let req = Request::Nip04Decrypt {
+            public_key,
+            ciphertext,
+        };
+        let res = self.send_request(req).await?;
+        Ok(res.to_encrypt_decrypt()?)
+    }
+
+    async fn _nip44_encrypt(
+        &self,
+        public_key: PublicKey,
+        content: String,
+    ) -> Result<String, Error> {
+        let req = Request::Nip44Encrypt {
+            public_key,
+            text: content,
+        };
+        let res = self.send_request(req).await?;
+        Ok(res.to_encrypt_decrypt()?)
+    }
+
+    async fn _nip44_decrypt(
+        &self,
+        public_key: PublicKey,
+        payload: String,
+    ) -> Result<String, Error> {
+        let req = Request::Nip44Decrypt {
+            public_key,
+            ciphertext: payload,
+        };
+        let res = self.send_request(req).await?;
+        Ok(res.to_encrypt_decrypt()?)
+    }
+}
+
+/// Check that the signed event matches the unsigned one and that the signature is valid
+fn check_signed_event(unsigned: &UnsignedEvent, event: &Event) -> Result<(), Error> {
+    if event.pubkey != unsigned.pubkey
+        || event.created_at != unsigned.created_at
+        || event.kind != unsigned.kind
+        || event.tags != unsigned.tags
+        || event.content != unsigned.content
+    {
+        return Err(Error::SignedEventNotMatch);
+    }
+
+    event.verify()?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl NostrSigner for ExternalSigner {
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::Custom(Cow::Borrowed("external"))
+    }
+
+    async fn get_public_key(&self) -> Result<PublicKey, SignerError> {
+        self._get_public_key()
+            .await
+            .copied()
+            .map_err(SignerError::backend)
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+        self._sign_event(unsigned)
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self._nip04_encrypt(*public_key, content.to_string())
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self._nip04_decrypt(*public_key, content.to_string())
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self._nip44_encrypt(*public_key, content.to_string())
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        public_key: &PublicKey,
+        content: &str,
+    ) -> Result<String, SignerError> {
+        self._nip44_decrypt(*public_key, content.to_string())
+            .await
+            .map_err(SignerError::backend)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::path::Path;
+
+    use tokio::net::UnixListener;
+
+    use super::*;
+
+    /// Run a fake signer that signs the requested events after applying `tamper`
+    fn run_signer<F>(path: &Path, keys: Keys, tamper: F)
+    where
+        F: Fn(UnsignedEvent) -> UnsignedEvent + Send + 'static,
+    {
+        let listener = UnixListener::bind(path).unwrap();
+        tokio::spawn(async move {
+            let (stream, ..) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+
+            while let Some(line) = lines.next_line().await.unwrap() {
+                let msg: Message = Message::from_json(line).unwrap();
+                let id: String = msg.id().to_string();
+                let result: ResponseResult = match msg.to_request().unwrap() {
+                    Request::GetPublicKey => ResponseResult::GetPublicKey(keys.public_key()),
+                    Request::SignEvent(unsigned) => {
+                        let event: Event = tamper(unsigned).sign_with_keys(&keys).unwrap();
+                        ResponseResult::SignEvent(Box::new(event))
+                    }
+                    req => panic!("unexpected request: {req:?}"),
+                };
+
+                let mut res: String = Message::response(id, Some(result), None).as_json();
+                res.push('\n');
+                writer.write_all(res.as_bytes()).await.unwrap();
+            }
+        });
+    }
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("nostr-external-{name}-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_sign_event() {
+        let keys = Keys::generate();
+        let path = socket_path("honest");
+        run_signer(&path, keys.clone(), |unsigned| unsigned);
+
+        let signer = ExternalSigner::new(
+            ExternalSignerTransport::UnixSocket(path),
+            Duration::from_secs(5),
+        );
+        let unsigned = EventBuilder::text_note("Hello").build(keys.public_key());
+        let event = signer.sign_event(unsigned.clone()).await.unwrap();
+        assert_eq!(event.pubkey, keys.public_key());
+        assert_eq!(event.content, unsigned.content);
+    }
+
+    #[tokio::test]
+    async fn test_sign_event_tampered_content() {
+        let keys = Keys::generate();
+        let path = socket_path("tampered-content");
+        run_signer(&path, keys.clone(), |mut unsigned| {
+            unsigned.content = String::from("Something else");
+            unsigned.id = None;
+            unsigned
+        });
+
+        let signer = ExternalSigner::new(
+            ExternalSignerTransport::UnixSocket(path),
+            Duration::from_secs(5),
+        );
+        let unsigned = EventBuilder::text_note("Hello").build(keys.public_key());
+        assert!(matches!(
+            signer._sign_event(unsigned).await.unwrap_err(),
+            Error::SignedEventNotMatch
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sign_event_other_public_key() {
+        let keys = Keys::generate();
+        let path = socket_path("other-public-key");
+        run_signer(&path, keys.clone(), |mut unsigned| {
+            unsigned.pubkey = keys.public_key();
+            unsigned.id = None;
+            unsigned
+        });
+
+        let signer = ExternalSigner::new(
+            ExternalSignerTransport::UnixSocket(path),
+            Duration::from_secs(5),
+        );
+        let unsigned = EventBuilder::text_note("Hello").build(Keys::generate().public_key());
+        assert!(matches!(
+            signer._sign_event(unsigned).await.unwrap_err(),
+            Error::SignedEventNotMatch
+        ));
+    }
+}
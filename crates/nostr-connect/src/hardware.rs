@@ -0,0 +1,793 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Hardware signer
+//!
+//! Sign the events on a Trezor device, without exposing the secret key to the host.
+//!
+//! The signer talks to the Nostr app of the Trezor firmware (experimental, must be enabled on the device):
+//! keys are derived at `m/44'/1237'/<account>'/0/0` (NIP06) and, before signing,
+//! the device displays the event kind and content and waits for the user confirmation.
+//!
+//! The device is reached through a [`TrezorTransport`]: use [`TrezorBridge`] to connect through the Trezor Bridge.
+//!
+//! Ledger devices aren't supported, since there isn't a Nostr app for them.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use nostr::prelude::*;
+use nostr::secp256k1::schnorr::Signature;
+use nostr::serde_json::{self, Value};
+use nostr::util::hex;
+use nostr::util::http::{HttpClient, HttpError, HttpRequest, IntoHttpClient};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+/// Default Trezor Bridge URL
+pub const DEFAULT_TREZOR_BRIDGE_URL: &str = "http://127.0.0.1:21325";
+
+const MSG_FAILURE: u16 = 3;
+const MSG_PIN_MATRIX_REQUEST: u16 = 18;
+const MSG_BUTTON_REQUEST: u16 = 26;
+const MSG_BUTTON_ACK: u16 = 27;
+const MSG_PASSPHRASE_REQUEST: u16 = 41;
+const MSG_NOSTR_GET_PUBKEY: u16 = 2001;
+const MSG_NOSTR_PUBKEY: u16 = 2002;
+const MSG_NOSTR_SIGN_EVENT: u16 = 2003;
+const MSG_NOSTR_EVENT_SIGNATURE: u16 = 2004;
+
+/// `Failure_ActionCancelled`
+const FAILURE_ACTION_CANCELLED: u64 = 4;
+
+const PURPOSE: u32 = 44;
+const COIN: u32 = 1237;
+const HARDENED: u32 = 0x8000_0000;
+
+/// Hardware signer error
+#[derive(Debug, Error)]
+pub enum HardwareSignerError {
+    /// Transport error
+    #[error("transport: {0}")]
+    Transport(String),
+    /// HTTP error
+    #[error(transparent)]
+    Http(#[from] HttpError),
+    /// Hex error
+    #[error(transparent)]
+    Hex(#[from] hex::Error),
+    /// Failure returned by the device
+    #[error("device failure: {0}")]
+    Failure(String),
+    /// Request rejected on the device
+    #[error("rejected on the device")]
+    Rejected,
+    /// Device locked
+    #[error("device locked: unlock it before using the signer")]
+    Locked,
+    /// Malformed message
+    #[error("malformed message")]
+    MalformedMessage,
+    /// Unexpected response
+    #[error("unexpected response from the device")]
+    UnexpectedResponse,
+    /// The event signed by the device doesn't match the request
+    #[error("signed event not match the unsigned one")]
+    SignedEventNotMatch,
+    /// Operation not supported by the device
+    #[error("not supported by the hardware signer")]
+    NotSupported,
+    /// Key error
+    #[error(transparent)]
+    Key(#[from] nostr::key::Error),
+    /// Event ID error
+    #[error(transparent)]
+    EventId(#[from] nostr::event::id::Error),
+    /// Unsigned event error
+    #[error(transparent)]
+    Unsigned(#[from] nostr::event::unsigned::Error),
+    /// Secp256k1 error
+    #[error(transparent)]
+    Secp256k1(#[from] nostr::secp256k1::Error),
+}
+
+/// Trezor message
+///
+/// The payload is the protobuf encoding of the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrezorMessage {
+    /// Message type
+    pub kind: u16,
+    /// Protobuf payload
+    pub payload: Vec<u8>,
+}
+
+impl TrezorMessage {
+    /// Construct new message
+    #[inline]
+    pub fn new(kind: u16, payload: Vec<u8>) -> Self {
+        Self { kind, payload }
+    }
+
+    /// Encode as message type (u16 BE), payload length (u32 BE) and payload
+    fn encode(&self) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::with_capacity(6 + self.payload.len());
+        buf.extend_from_slice(&self.kind.to_be_bytes());
+        buf.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&self.payload);
+        buf
+    }
+
+    fn decode(data: &[u8]) -> Result<Self, HardwareSignerError> {
+        if data.len() < 6 {
+            return Err(HardwareSignerError::MalformedMessage);
+        }
+
+        let kind: u16 = u16::from_be_bytes([data[0], data[1]]);
+        let len: usize = u32::from_be_bytes([data[2], data[3], data[4], data[5]]) as usize;
+        let payload: &[u8] = data
+            .get(6..6 + len)
+            .ok_or(HardwareSignerError::MalformedMessage)?;
+
+        Ok(Self::new(kind, payload.to_vec()))
+    }
+}
+
+/// Trezor device transport
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait TrezorTransport: AsyncTraitDeps {
+    /// Send the message to the device and wait for the response
+    async fn call(&self, msg: TrezorMessage) -> Result<TrezorMessage, HardwareSignerError>;
+}
+
+#[doc(hidden)]
+pub trait IntoTrezorTransport {
+    fn into_trezor_transport(self) -> Arc<dyn TrezorTransport>;
+}
+
+impl<T> IntoTrezorTransport for T
+where
+    T: TrezorTransport + 'static,
+{
+    fn into_trezor_transport(self) -> Arc<dyn TrezorTransport> {
+        Arc::new(self)
+    }
+}
+
+/// Trezor Bridge transport
+///
+/// Talk to the first connected device through the Trezor Bridge (or Trezor Suite) HTTP server.
+#[derive(Debug)]
+pub struct TrezorBridge {
+    http: Arc<dyn HttpClient>,
+    url: Url,
+    session: Mutex<Option<String>>,
+}
+
+impl TrezorBridge {
+    /// Construct new Trezor Bridge transport (at [`DEFAULT_TREZOR_BRIDGE_URL`])
+    pub fn new<T>(http: T) -> Self
+    where
+        T: IntoHttpClient,
+    {
+        let url: Url = Url::parse(DEFAULT_TREZOR_BRIDGE_URL).expect("valid url");
+        Self::with_url(http, url)
+    }
+
+    /// Construct new Trezor Bridge transport with a custom URL
+    pub fn with_url<T>(http: T, url: Url) -> Self
+    where
+        T: IntoHttpClient,
+    {
+        Self {
+            http: http.into_http_client(),
+            url,
+            session: Mutex::new(None),
+        }
+    }
+
+    async fn post(&self, path: &str, body: Vec<u8>) -> Result<Vec<u8>, HardwareSignerError> {
+        let url: Url = self
+            .url
+            .join(path)
+            .map_err(|e| HardwareSignerError::Transport(e.to_string()))?;
+        let res = self
+            .http
+            .send(HttpRequest::post(url, body))
+            .await?
+            .error_for_status()?;
+        Ok(res.body)
+    }
+
+    /// Acquire a session on the first connected device
+    async fn acquire(&self) -> Result<String, HardwareSignerError> {
+        let body: Vec<u8> = self.post("enumerate", Vec::new()).await?;
+        let devices: Value =
+            serde_json::from_slice(&body).map_err(|_| HardwareSignerError::UnexpectedResponse)?;
+        let device: &Value = devices
+            .as_array()
+            .and_then(|devices| devices.first())
+            .ok_or_else(|| HardwareSignerError::Transport(String::from("no device connected")))?;
+        let path: &str = device
+            .get("path")
+            .and_then(Value::as_str)
+            .ok_or(HardwareSignerError::UnexpectedResponse)?;
+        let previous: &str = device
+            .get("session")
+            .and_then(Value::as_str)
+            .unwrap_or("null");
+
+        let body: Vec<u8> = self
+            .post(&format!("acquire/{path}/{previous}"), Vec::new())
+            .await?;
+        let session: Value =
+            serde_json::from_slice(&body).map_err(|_| HardwareSignerError::UnexpectedResponse)?;
+        session
+            .get("session")
+            .and_then(Value::as_str)
+            .map(String::from)
+            .ok_or(HardwareSignerError::UnexpectedResponse)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl TrezorTransport for TrezorBridge {
+    async fn call(&self, msg: TrezorMessage) -> Result<TrezorMessage, HardwareSignerError> {
+        let mut session = self.session.lock().await;
+
+        let id: String = match session.as_ref() {
+            Some(id) => id.clone(),
+            None => {
+                let id: String = self.acquire().await?;
+                *session = Some(id.clone());
+                id
+            }
+        };
+
+        let body: Vec<u8> = hex::encode(msg.encode()).into_bytes();
+        match self.post(&format!("call/{id}"), body).await {
+            Ok(res) => {
+                let res: &str =
+                    std::str::from_utf8(&res).map_err(|_| HardwareSignerError::MalformedMessage)?;
+                TrezorMessage::decode(&hex::decode(res.trim())?)
+            }
+            Err(e) => {
+                // The session may have been taken by another application
+                *session = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Hardware signer
+///
+/// Encryption and decryption aren't supported.
+#[derive(Debug, Clone)]
+pub struct HardwareSigner {
+    transport: Arc<dyn TrezorTransport>,
+    account: u32,
+    /// Don't interleave the messages of concurrent requests
+    lock: Arc<Mutex<()>>,
+}
+
+impl HardwareSigner {
+    /// Construct hardware signer
+    #[inline]
+    pub fn new<T>(transport: T, account: u32) -> Self
+    where
+        T: IntoTrezorTransport,
+    {
+        Self {
+            transport: transport.into_trezor_transport(),
+            account,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Get account index
+    #[inline]
+    pub fn account(&self) -> u32 {
+        self.account
+    }
+
+    fn derivation_path(&self) -> [u32; 5] {
+        [
+            PURPOSE | HARDENED,
+            COIN | HARDENED,
+            self.account | HARDENED,
+            0,
+            0,
+        ]
+    }
+
+    /// Call the device, acknowledging the confirmation requests
+    async fn call(&self, msg: TrezorMessage) -> Result<TrezorMessage, HardwareSignerError> {
+        let _lock = self.lock.lock().await;
+
+        let mut msg: TrezorMessage = msg;
+        loop {
+            let res: TrezorMessage = self.transport.call(msg).await?;
+            match res.kind {
+                // The device waits for the user confirmation before replying to the ack
+                MSG_BUTTON_REQUEST => msg = TrezorMessage::new(MSG_BUTTON_ACK, Vec::new()),
+                MSG_PIN_MATRIX_REQUEST | MSG_PASSPHRASE_REQUEST => {
+                    return Err(HardwareSignerError::Locked)
+                }
+                MSG_FAILURE => {
+                    let fields = read_fields(&res.payload)?;
+                    return match varint_field(&fields, 1) {
+                        Some(FAILURE_ACTION_CANCELLED) => Err(HardwareSignerError::Rejected),
+                        _ => Err(HardwareSignerError::Failure(
+                            bytes_field(&fields, 2)
+                                .map(|msg| String::from_utf8_lossy(msg).into_owned())
+                                .unwrap_or_default(),
+                        )),
+                    };
+                }
+                _ => return Ok(res),
+            }
+        }
+    }
+
+    async fn _get_public_key(&self) -> Result<PublicKey, HardwareSignerError> {
+        let mut payload: Vec<u8> = Vec::new();
+        for component in self.derivation_path().into_iter() {
+            write_uint(&mut payload, 1, u64::from(component));
+        }
+
+        let res = self
+            .call(TrezorMessage::new(MSG_NOSTR_GET_PUBKEY, payload))
+            .await?;
+        if res.kind != MSG_NOSTR_PUBKEY {
+            return Err(HardwareSignerError::UnexpectedResponse);
+        }
+
+        let fields = read_fields(&res.payload)?;
+        let public_key: &[u8] =
+            bytes_field(&fields, 1).ok_or(HardwareSignerError::MalformedMessage)?;
+        Ok(PublicKey::from_slice(x_only(public_key))?)
+    }
+
+    async fn _sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, HardwareSignerError> {
+        let created_at: u32 = u32::try_from(unsigned.created_at.as_u64())
+            .map_err(|_| HardwareSignerError::NotSupported)?;
+
+        let mut payload: Vec<u8> = Vec::new();
+        for component in self.derivation_path().into_iter() {
+            write_uint(&mut payload, 1, u64::from(component));
+        }
+        write_uint(&mut payload, 2, u64::from(created_at));
+        write_uint(&mut payload, 3, u64::from(unsigned.kind.as_u16()));
+        for tag in unsigned.tags.iter() {
+            if let Some((key, values)) = tag.as_slice().split_first() {
+                let mut t: Vec<u8> = Vec::new();
+                write_bytes(&mut t, 1, key.as_bytes());
+                if let Some((value, extra)) = values.split_first() {
+                    write_bytes(&mut t, 2, value.as_bytes());
+                    for extra in extra.iter() {
+                        write_bytes(&mut t, 3, extra.as_bytes());
+                    }
+                }
+                write_bytes(&mut payload, 4, &t);
+            }
+        }
+        write_bytes(&mut payload, 5, unsigned.content.as_bytes());
+
+        let res = self
+            .call(TrezorMessage::new(MSG_NOSTR_SIGN_EVENT, payload))
+            .await?;
+        if res.kind != MSG_NOSTR_EVENT_SIGNATURE {
+            return Err(HardwareSignerError::UnexpectedResponse);
+        }
+
+        let fields = read_fields(&res.payload)?;
+        let public_key: &[u8] =
+            bytes_field(&fields, 1).ok_or(HardwareSignerError::MalformedMessage)?;
+        let id: &[u8] = bytes_field(&fields, 2).ok_or(HardwareSignerError::MalformedMessage)?;
+        let sig: &[u8] = bytes_field(&fields, 3).ok_or(HardwareSignerError::MalformedMessage)?;
+
+        // Don't trust the device: it may have signed something else
+        if PublicKey::from_slice(x_only(public_key))? != unsigned.pubkey
+            || EventId::from_slice(id)? != unsigned.id()
+        {
+            return Err(HardwareSignerError::SignedEventNotMatch);
+        }
+
+        let sig: Signature = Signature::from_slice(sig)?;
+        Ok(unsigned.add_signature(sig)?)
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl NostrSigner for HardwareSigner {
+    fn backend(&self) -> SignerBackend {
+        SignerBackend::Custom(Cow::Borrowed("hardware"))
+    }
+
+    async fn get_public_key(&self) -> Result<PublicKey, SignerError> {
+        self._get_public_key().await.map_err(SignerError::backend)
+    }
+
+    async fn sign_event(&self, unsigned: UnsignedEvent) -> Result<Event, SignerError> {
+        self._sign_event(unsigned)
+            .await
+            .map_err(SignerError::backend)
+    }
+
+    async fn nip04_encrypt(
+        &self,
+        _public_key: &PublicKey,
+        _content: &str,
+    ) -> Result<String, SignerError> {
+        Err(SignerError::backend(HardwareSignerError::NotSupported))
+    }
+
+    async fn nip04_decrypt(
+        &self,
+        _public_key: &PublicKey,
+        _encrypted_content: &str,
+    ) -> Result<String, SignerError> {
+        Err(SignerError::backend(HardwareSignerError::NotSupported))
+    }
+
+    async fn nip44_encrypt(
+        &self,
+        _public_key: &PublicKey,
+        _content: &str,
+    ) -> Result<String, SignerError> {
+        Err(SignerError::backend(HardwareSignerError::NotSupported))
+    }
+
+    async fn nip44_decrypt(
+        &self,
+        _public_key: &PublicKey,
+        _payload: &str,
+    ) -> Result<String, SignerError> {
+        Err(SignerError::backend(HardwareSignerError::NotSupported))
+    }
+}
+
+/// Strip the parity byte of a compressed public key, if any
+fn x_only(public_key: &[u8]) -> &[u8] {
+    match public_key.len() {
+        33 => &public_key[1..],
+        _ => public_key,
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn write_uint(buf: &mut Vec<u8>, field: u32, value: u64) {
+    write_varint(buf, u64::from(field) << 3);
+    write_varint(buf, value);
+}
+
+fn write_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    write_varint(buf, (u64::from(field) << 3) | 2);
+    write_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+enum FieldValue<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64, HardwareSignerError> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let byte: u8 = *data
+            .get(*pos)
+            .ok_or(HardwareSignerError::MalformedMessage)?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(HardwareSignerError::MalformedMessage)
+}
+
+/// Read the fields of a protobuf message (the fixed size ones are skipped)
+fn read_fields(data: &[u8]) -> Result<Vec<(u32, FieldValue<'_>)>, HardwareSignerError> {
+    let mut fields = Vec::new();
+    let mut pos: usize = 0;
+
+    while pos < data.len() {
+        let key: u64 = read_varint(data, &mut pos)?;
+        let field: u32 = (key >> 3) as u32;
+        let len: usize = match key & 0x07 {
+            0 => {
+                fields.push((field, FieldValue::Varint(read_varint(data, &mut pos)?)));
+                continue;
+            }
+            1 => 8,
+            2 => read_varint(data, &mut pos)? as usize,
+            5 => 4,
+            _ => return Err(HardwareSignerError::MalformedMessage),
+        };
+
+        let end: usize = pos
+            .checked_add(len)
+            .filter(|end| *end <= data.len())
+            .ok_or(HardwareSignerError::MalformedMessage)?;
+        if key & 0x07 == 2 {
+            fields.push((field, FieldValue::Bytes(&data[pos..end])));
+        }
+        pos = end;
+    }
+
+    Ok(fields)
+}
+
+fn varint_field(fields: &[(u32, FieldValue<'_>)], field: u32) -> Option<u64> {
+    fields.iter().find_map(|(f, value)| match value {
+        FieldValue::Varint(value) if *f == field => Some(*value),
+        _ => None,
+    })
+}
+
+fn bytes_field<'a>(fields: &[(u32, FieldValue<'a>)], field: u32) -> Option<&'a [u8]> {
+    fields.iter().find_map(|(f, value)| match value {
+        FieldValue::Bytes(value) if *f == field => Some(*value),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex as StdMutex;
+
+    use nostr::secp256k1::Message;
+    use nostr::util::http::HttpResponse;
+
+    use super::*;
+
+    /// Emulate the Nostr app of the Trezor firmware
+    #[derive(Debug)]
+    struct MockTrezor {
+        keys: Keys,
+        confirm: bool,
+        pending: StdMutex<Option<UnsignedEvent>>,
+        confirmations: AtomicUsize,
+    }
+
+    impl MockTrezor {
+        fn new(keys: Keys, confirm: bool) -> Self {
+            Self {
+                keys,
+                confirm,
+                pending: StdMutex::new(None),
+                confirmations: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    fn failure(code: u64, message: &str) -> TrezorMessage {
+        let mut payload: Vec<u8> = Vec::new();
+        write_uint(&mut payload, 1, code);
+        write_bytes(&mut payload, 2, message.as_bytes());
+        TrezorMessage::new(MSG_FAILURE, payload)
+    }
+
+    #[async_trait]
+    impl TrezorTransport for MockTrezor {
+        async fn call(&self, msg: TrezorMessage) -> Result<TrezorMessage, HardwareSignerError> {
+            let fields = read_fields(&msg.payload)?;
+            match msg.kind {
+                MSG_NOSTR_GET_PUBKEY => {
+                    let path: Vec<u64> = fields
+                        .iter()
+                        .filter_map(|(f, v)| match v {
+                            FieldValue::Varint(v) if *f == 1 => Some(*v),
+                            _ => None,
+                        })
+                        .collect();
+                    assert_eq!(path, [0x8000_002c, 0x8000_04d5, 0x8000_0000, 0, 0]);
+
+                    let mut payload: Vec<u8> = Vec::new();
+                    write_bytes(&mut payload, 1, &self.keys.public_key().to_bytes());
+                    Ok(TrezorMessage::new(MSG_NOSTR_PUBKEY, payload))
+                }
+                MSG_NOSTR_SIGN_EVENT => {
+                    let tags: Vec<Tag> = fields
+                        .iter()
+                        .filter_map(|(f, v)| match v {
+                            FieldValue::Bytes(t) if *f == 4 => Some(*t),
+                            _ => None,
+                        })
+                        .map(|t| {
+                            let tag: Vec<String> = read_fields(t)
+                                .unwrap()
+                                .into_iter()
+                                .filter_map(|(_, v)| match v {
+                                    FieldValue::Bytes(s) => {
+                                        Some(String::from_utf8(s.to_vec()).unwrap())
+                                    }
+                                    FieldValue::Varint(..) => None,
+                                })
+                                .collect();
+                            Tag::parse(&tag).unwrap()
+                        })
+                        .collect();
+                    let unsigned = UnsignedEvent::new(
+                        self.keys.public_key(),
+                        Timestamp::from(varint_field(&fields, 2).unwrap()),
+                        Kind::from(varint_field(&fields, 3).unwrap() as u16),
+                        tags,
+                        String::from_utf8(bytes_field(&fields, 5).unwrap().to_vec()).unwrap(),
+                    );
+                    *self.pending.lock().unwrap() = Some(unsigned);
+
+                    // Display the event and wait for the confirmation
+                    Ok(TrezorMessage::new(MSG_BUTTON_REQUEST, Vec::new()))
+                }
+                MSG_BUTTON_ACK => {
+                    let unsigned = self.pending.lock().unwrap().take().unwrap();
+                    self.confirmations.fetch_add(1, Ordering::SeqCst);
+
+                    if !self.confirm {
+                        return Ok(failure(FAILURE_ACTION_CANCELLED, "Cancelled"));
+                    }
+
+                    let id: EventId = unsigned.id();
+                    let sig = self.keys.sign_schnorr(&Message::from_digest(id.to_bytes()));
+
+                    let mut payload: Vec<u8> = Vec::new();
+                    write_bytes(&mut payload, 1, &self.keys.public_key().to_bytes());
+                    write_bytes(&mut payload, 2, id.as_bytes());
+                    write_bytes(&mut payload, 3, &sig.serialize());
+                    Ok(TrezorMessage::new(MSG_NOSTR_EVENT_SIGNATURE, payload))
+                }
+                _ => Ok(failure(1, "Unexpected message")),
+            }
+        }
+    }
+
+    #[test]
+    fn test_protobuf() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_uint(&mut buf, 1, 0x8000_002c);
+        write_bytes(&mut buf, 5, b"hello");
+        assert_eq!(
+            buf,
+            [0x08, 0xac, 0x80, 0x80, 0x80, 0x08, 0x2a, 0x05, b'h', b'e', b'l', b'l', b'o']
+        );
+
+        let fields = read_fields(&buf).unwrap();
+        assert_eq!(varint_field(&fields, 1), Some(0x8000_002c));
+        assert_eq!(bytes_field(&fields, 5), Some(&b"hello"[..]));
+
+        // Truncated
+        assert!(read_fields(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_message_framing() {
+        let msg = TrezorMessage::new(MSG_NOSTR_GET_PUBKEY, vec![0x08, 0x01]);
+        let encoded: Vec<u8> = msg.encode();
+        assert_eq!(encoded, [0x07, 0xd1, 0x00, 0x00, 0x00, 0x02, 0x08, 0x01]);
+        assert_eq!(TrezorMessage::decode(&encoded).unwrap(), msg);
+        assert!(TrezorMessage::decode(&encoded[..7]).is_err());
+    }
+
+    /// Emulate the Trezor Bridge, with a single device
+    #[derive(Debug)]
+    struct MockBridge {
+        keys: Keys,
+        requests: StdMutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockBridge {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+            let path: String = request.url.path().to_string();
+            self.requests.lock().unwrap().push(path.clone());
+
+            let body: Vec<u8> = match path.as_str() {
+                "/enumerate" => br#"[{"path":"1","session":null}]"#.to_vec(),
+                "/acquire/1/null" => br#"{"session":"7"}"#.to_vec(),
+                "/call/7" => {
+                    let req: Vec<u8> = hex::decode(request.body.unwrap()).unwrap();
+                    let req: TrezorMessage = TrezorMessage::decode(&req).unwrap();
+                    assert_eq!(req.kind, MSG_NOSTR_GET_PUBKEY);
+
+                    let mut payload: Vec<u8> = Vec::new();
+                    write_bytes(&mut payload, 1, &self.keys.public_key().to_bytes());
+                    hex::encode(TrezorMessage::new(MSG_NOSTR_PUBKEY, payload).encode()).into_bytes()
+                }
+                _ => return Err(HttpError::Status(404)),
+            };
+
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trezor_bridge() {
+        let keys = Keys::generate();
+        let bridge = Arc::new(MockBridge {
+            keys: keys.clone(),
+            requests: StdMutex::new(Vec::new()),
+        });
+        let signer = HardwareSigner::new(TrezorBridge::new(bridge.clone()), 0);
+
+        assert_eq!(signer.get_public_key().await.unwrap(), keys.public_key());
+        assert_eq!(signer.get_public_key().await.unwrap(), keys.public_key());
+
+        // The session is acquired once
+        assert_eq!(
+            *bridge.requests.lock().unwrap(),
+            ["/enumerate", "/acquire/1/null", "/call/7", "/call/7"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_public_key() {
+        let keys = Keys::generate();
+        let signer = HardwareSigner::new(MockTrezor::new(keys.clone(), true), 0);
+        assert_eq!(signer.get_public_key().await.unwrap(), keys.public_key());
+    }
+
+    #[tokio::test]
+    async fn test_sign_event_confirmed_on_device() {
+        let keys = Keys::generate();
+        let device = Arc::new(MockTrezor::new(keys.clone(), true));
+        let signer = HardwareSigner {
+            transport: device.clone(),
+            account: 0,
+            lock: Arc::new(Mutex::new(())),
+        };
+
+        let unsigned = EventBuilder::text_note("Hello from Trezor")
+            .tags([
+                Tag::hashtag("nostr"),
+                Tag::public_key(Keys::generate().public_key()),
+            ])
+            .build(keys.public_key());
+        let event: Event = signer.sign_event(unsigned.clone()).await.unwrap();
+
+        assert_eq!(device.confirmations.load(Ordering::SeqCst), 1);
+        assert_eq!(event.id, unsigned.id());
+        assert_eq!(event.tags, unsigned.tags);
+        event.verify().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sign_event_rejected_on_device() {
+        let keys = Keys::generate();
+        let signer = HardwareSigner::new(MockTrezor::new(keys.clone(), false), 0);
+
+        let unsigned = EventBuilder::text_note("Hello").build(keys.public_key());
+        let err = signer._sign_event(unsigned).await.unwrap_err();
+        assert!(matches!(err, HardwareSignerError::Rejected));
+    }
+
+    #[tokio::test]
+    async fn test_sign_event_of_another_key() {
+        let device = MockTrezor::new(Keys::generate(), true);
+        let signer = HardwareSigner::new(device, 0);
+
+        // The device signs with its own key
+        let unsigned = EventBuilder::text_note("Hello").build(Keys::generate().public_key());
+        let err = signer._sign_event(unsigned).await.unwrap_err();
+        assert!(matches!(err, HardwareSignerError::SignedEventNotMatch));
+    }
+}
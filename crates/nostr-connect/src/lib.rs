@@ -5,6 +5,8 @@
 //! Nostr Connect (NIP46)
 //!
 //! <https://github.com/nostr-protocol/nips/blob/master/46.md>
+//!
+//! Include also an [`ExternalSigner`](crate::external::ExternalSigner), to talk to a signer running in a separate process.
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
@@ -13,5 +15,9 @@
 
 pub mod client;
 pub mod error;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod external;
+#[cfg(feature = "hardware")]
+pub mod hardware;
 pub mod prelude;
 pub mod signer;
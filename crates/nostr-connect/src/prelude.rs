@@ -12,4 +12,8 @@ pub use nostr::prelude::*;
 
 pub use crate::client::*;
 pub use crate::error::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use crate::external::*;
+#[cfg(feature = "hardware")]
+pub use crate::hardware::*;
 pub use crate::signer::*;
@@ -166,7 +166,7 @@ impl NostrConnectRemoteSigner {
                         if let Ok(msg) = nip04::decrypt(
                             self.keys.signer.secret_key(),
                             &event.pubkey,
-                            event.content,
+                            &event.content,
                         ) {
                             tracing::debug!("New Nostr Connect message received: {msg}");
 
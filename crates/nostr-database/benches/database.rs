@@ -0,0 +1,119 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nostr_database::prelude::*;
+use tokio::runtime::Runtime;
+
+/// Number of events in the corpus
+const CORPUS_SIZE: usize = 10_000;
+
+/// Build a corpus similar to a real client cache: text notes, reactions,
+/// metadata and contact lists from a few hundreds authors.
+fn corpus() -> (Vec<Keys>, Vec<Event>) {
+    let authors: Vec<Keys> = (0..300).map(|_| Keys::generate()).collect();
+    let mut events: Vec<Event> = Vec::with_capacity(CORPUS_SIZE);
+
+    for i in 0..CORPUS_SIZE {
+        let keys: &Keys = &authors[i % authors.len()];
+        let mentioned: PublicKey = authors[(i * 7) % authors.len()].public_key();
+
+        let builder: EventBuilder = match i % 10 {
+            0 => EventBuilder::metadata(&Metadata::new().name(format!("user{i}"))),
+            1 => EventBuilder::new(Kind::ContactList, "").tags(
+                authors
+                    .iter()
+                    .skip(i % 50)
+                    .take(50)
+                    .map(|k| Tag::public_key(k.public_key())),
+            ),
+            2 | 3 if !events.is_empty() => EventBuilder::reaction(&events[i / 2], "+"),
+            _ => EventBuilder::text_note(format!("GM nostr! Note #{i}")).tags([
+                Tag::public_key(mentioned),
+                Tag::hashtag(if i % 2 == 0 { "nostr" } else { "bitcoin" }),
+            ]),
+        };
+
+        events.push(
+            builder
+                .custom_created_at(Timestamp::from(1_700_000_000 + i as u64))
+                .sign_with_keys(keys)
+                .unwrap(),
+        );
+    }
+
+    (authors, events)
+}
+
+fn queries(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let (authors, events) = corpus();
+
+    let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+        events: true,
+        max_events: None,
+    });
+    rt.block_on(async {
+        for event in events.iter() {
+            database.save_event(event).await.unwrap();
+        }
+    });
+
+    let follows: Vec<PublicKey> = authors.iter().take(100).map(|k| k.public_key()).collect();
+    let filters: [(&str, Filter); 5] = [
+        ("by_id", Filter::new().id(events[CORPUS_SIZE / 2].id)),
+        (
+            "metadata_of_author",
+            Filter::new()
+                .author(authors[0].public_key())
+                .kind(Kind::Metadata)
+                .limit(1),
+        ),
+        (
+            "home_feed",
+            Filter::new()
+                .authors(follows)
+                .kind(Kind::TextNote)
+                .limit(100),
+        ),
+        (
+            "mentions",
+            Filter::new()
+                .pubkey(authors[1].public_key())
+                .kinds([Kind::TextNote, Kind::Reaction])
+                .limit(50),
+        ),
+        (
+            "hashtag_since",
+            Filter::new()
+                .hashtag("nostr")
+                .since(Timestamp::from(1_700_005_000))
+                .limit(200),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("memory_database_query");
+    for (name, filter) in filters.iter() {
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                rt.block_on(async {
+                    black_box(database.query(vec![filter.clone()]).await.unwrap());
+                })
+            })
+        });
+    }
+    group.finish();
+
+    c.bench_function("memory_database_count", |b| {
+        let filter: Filter = Filter::new().kind(Kind::TextNote);
+        b.iter(|| {
+            rt.block_on(async {
+                black_box(database.count(vec![filter.clone()]).await.unwrap());
+            })
+        })
+    });
+}
+
+criterion_group!(benches, queries);
+criterion_main!(benches);
@@ -0,0 +1,139 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Subscription cursor
+
+use std::collections::BTreeSet;
+
+use nostr::{EventId, Timestamp};
+
+/// Subscription cursor
+///
+/// Keep track of the newest `created_at` received for a subscription
+/// and of the IDs of the events received with that `created_at` (the boundary),
+/// to resume the subscription without gaps or duplicates.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SubscriptionCursor {
+    created_at: Timestamp,
+    ids: BTreeSet<EventId>,
+}
+
+impl SubscriptionCursor {
+    /// New empty cursor
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Newest `created_at` received
+    ///
+    /// Use it as `since` when resuming the subscription.
+    #[inline]
+    pub fn created_at(&self) -> Timestamp {
+        self.created_at
+    }
+
+    /// IDs of the events received with the newest `created_at`
+    #[inline]
+    pub fn ids(&self) -> &BTreeSet<EventId> {
+        &self.ids
+    }
+
+    /// Check if the event is at the boundary and has been already received
+    #[inline]
+    pub fn contains(&self, id: &EventId, created_at: &Timestamp) -> bool {
+        self.created_at == *created_at && self.ids.contains(id)
+    }
+
+    /// Advance the cursor
+    ///
+    /// Return `true` if the cursor has changed.
+    pub fn update(&mut self, id: EventId, created_at: Timestamp) -> bool {
+        if created_at > self.created_at {
+            self.created_at = created_at;
+            self.ids.clear();
+            self.ids.insert(id)
+        } else if created_at == self.created_at {
+            self.ids.insert(id)
+        } else {
+            false
+        }
+    }
+
+    /// Encode the cursor as bytes
+    ///
+    /// Big-endian `created_at` followed by the 32-byte event IDs.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(8 + self.ids.len() * 32);
+        bytes.extend_from_slice(&self.created_at.as_u64().to_be_bytes());
+        for id in self.ids.iter() {
+            bytes.extend_from_slice(id.as_bytes());
+        }
+        bytes
+    }
+
+    /// Decode the cursor from bytes
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 8 || (bytes.len() - 8) % 32 != 0 {
+            return None;
+        }
+
+        let (created_at, ids) = bytes.split_at(8);
+        let created_at: [u8; 8] = created_at.try_into().ok()?;
+
+        Some(Self {
+            created_at: Timestamp::from(u64::from_be_bytes(created_at)),
+            ids: ids
+                .chunks_exact(32)
+                .map(EventId::from_slice)
+                .collect::<Result<_, _>>()
+                .ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_update() {
+        let id1 = EventId::all_zeros();
+        let id2 = EventId::from_byte_array([1; 32]);
+        let id3 = EventId::from_byte_array([2; 32]);
+
+        let mut cursor = SubscriptionCursor::new();
+        assert!(cursor.update(id1, Timestamp::from(10)));
+        assert!(cursor.update(id2, Timestamp::from(10)));
+        assert!(!cursor.update(id2, Timestamp::from(10)));
+        assert!(cursor.contains(&id1, &Timestamp::from(10)));
+
+        // Older event
+        assert!(!cursor.update(id3, Timestamp::from(5)));
+        assert!(!cursor.contains(&id3, &Timestamp::from(5)));
+
+        // Newer event: move the boundary
+        assert!(cursor.update(id3, Timestamp::from(20)));
+        assert_eq!(cursor.created_at(), Timestamp::from(20));
+        assert_eq!(cursor.ids().len(), 1);
+        assert!(!cursor.contains(&id1, &Timestamp::from(10)));
+    }
+
+    #[test]
+    fn test_cursor_encoding() {
+        let mut cursor = SubscriptionCursor::new();
+        cursor.update(EventId::all_zeros(), Timestamp::from(1_700_000_000));
+        cursor.update(
+            EventId::from_byte_array([1; 32]),
+            Timestamp::from(1_700_000_000),
+        );
+
+        let bytes = cursor.encode();
+        assert_eq!(bytes.len(), 8 + 2 * 32);
+        assert_eq!(SubscriptionCursor::decode(&bytes), Some(cursor));
+
+        assert!(SubscriptionCursor::decode(&[0; 7]).is_none());
+        assert!(SubscriptionCursor::decode(&[0; 12]).is_none());
+    }
+}
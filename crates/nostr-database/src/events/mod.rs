@@ -267,6 +267,49 @@ pub trait NostrEventsDatabaseExt: NostrEventsDatabase {
 
         Ok(map)
     }
+
+    /// Resolve a coordinate (`a` tag) to the newest matching replaceable or addressable event
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    async fn event_by_coordinate(
+        &self,
+        coordinate: &Coordinate,
+    ) -> Result<Option<Event>, DatabaseError> {
+        let mut filter: Filter = Filter::new()
+            .kind(coordinate.kind)
+            .author(coordinate.public_key)
+            .limit(1);
+
+        // The empty identifier is valid for the addressable events
+        if coordinate.kind.is_parameterized_replaceable() {
+            filter = filter.identifier(coordinate.identifier.clone());
+        }
+
+        let events: Events = self.query(vec![filter]).await?;
+        Ok(events.first().cloned())
+    }
+
+    /// Compose [`Nip19Event`] using the relays where the event has been seen as relay hints
+    ///
+    /// The author and the kind are included only if the event is stored in the database.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/19.md>
+    async fn nip19_event(&self, event_id: &EventId) -> Result<Nip19Event, DatabaseError> {
+        // Get relays where the event has been seen
+        let relays: HashSet<RelayUrl> = self
+            .event_seen_on_relays(event_id)
+            .await?
+            .unwrap_or_default();
+
+        // Compose entity
+        let nip19: Nip19Event = Nip19Event::new(*event_id, relays.iter().map(|r| r.to_string()));
+
+        // Add author and kind, if event exists
+        match self.event_by_id(event_id).await? {
+            Some(event) => Ok(nip19.author(event.pubkey).kind(event.kind)),
+            None => Ok(nip19),
+        }
+    }
 }
 
 #[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
@@ -0,0 +1,306 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Event reference graph
+//!
+//! Navigate the references between the stored events, using the tag indexes of the database.
+
+use std::collections::{HashMap, HashSet};
+
+use nostr::nips::nip56::Report;
+use nostr::{
+    Alphabet, Coordinate, Event, EventId, Filter, Kind, PublicKey, SingleLetterTag, TagKind,
+    TagStandard,
+};
+
+use crate::{DatabaseError, Events, NostrEventsDatabase};
+
+/// References of an event
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct References {
+    /// Referenced events (`e` and `q` tags)
+    pub events: HashSet<EventId>,
+    /// Referenced replaceable and addressable events (`a` tags)
+    pub coordinates: HashSet<Coordinate>,
+    /// Referenced public keys (`p` tags)
+    pub public_keys: HashSet<PublicKey>,
+}
+
+impl References {
+    /// Extract the references from the event tags
+    pub fn from_event(event: &Event) -> Self {
+        let mut events: HashSet<EventId> = event.tags.event_ids().copied().collect();
+        events.extend(
+            event
+                .tags
+                .filter_standardized(TagKind::q())
+                .filter_map(|t| match t {
+                    TagStandard::Quote { event_id, .. } => Some(*event_id),
+                    _ => None,
+                }),
+        );
+
+        Self {
+            events,
+            coordinates: event.tags.coordinates().cloned().collect(),
+            public_keys: event.tags.public_keys().copied().collect(),
+        }
+    }
+}
+
+/// NIP56 reports about a public key or an event
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/56.md>
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReportSummary {
+    /// Reporters, by report type
+    ///
+    /// Multiple reports of the same type by the same reporter are counted once.
+    pub reporters: HashMap<Report, HashSet<PublicKey>>,
+}
+
+impl ReportSummary {
+    /// Check if there are no reports
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.reporters.is_empty()
+    }
+
+    /// Number of distinct reporters
+    pub fn total(&self) -> usize {
+        self.reporters
+            .values()
+            .flatten()
+            .collect::<HashSet<&PublicKey>>()
+            .len()
+    }
+
+    /// Number of reporters for the report type
+    #[inline]
+    pub fn count(&self, report: &Report) -> usize {
+        self.reporters.get(report).map_or(0, |r| r.len())
+    }
+
+    fn insert(&mut self, report: Report, reporter: PublicKey) {
+        self.reporters.entry(report).or_default().insert(reporter);
+    }
+}
+
+/// Event reference graph over a database
+///
+/// Useful to build threads, notification tabs or analytics from the local events.
+#[derive(Debug)]
+pub struct Graph<'a, T>
+where
+    T: NostrEventsDatabase + ?Sized,
+{
+    database: &'a T,
+}
+
+impl<'a, T> Graph<'a, T>
+where
+    T: NostrEventsDatabase + ?Sized,
+{
+    /// New graph over the database
+    #[inline]
+    pub fn new(database: &'a T) -> Self {
+        Self { database }
+    }
+
+    /// Get the events that reference the event
+    ///
+    /// Include the events that tag it with `e` or `q` tags and, if it's replaceable or addressable,
+    /// the events that tag its coordinate with `a` tags.
+    pub async fn referenced_by(&self, id: &EventId) -> Result<Events, DatabaseError> {
+        let mut filters: Vec<Filter> = vec![
+            Filter::new().event(*id),
+            Filter::new().custom_tag(SingleLetterTag::lowercase(Alphabet::Q), [id.to_hex()]),
+        ];
+
+        if let Some(event) = self.database.event_by_id(id).await? {
+            if let Some(coordinate) = event.coordinate() {
+                filters.push(Filter::new().coordinate(&coordinate));
+            }
+        }
+
+        self.database.query(filters).await
+    }
+
+    /// Get the public keys that interacted with the event (i.e. replies, reactions, reposts or zaps)
+    ///
+    /// These are the authors of the events that reference it (check [`Graph::referenced_by`]).
+    pub async fn interactions(&self, id: &EventId) -> Result<HashSet<PublicKey>, DatabaseError> {
+        let events: Events = self.referenced_by(id).await?;
+        Ok(events.into_iter().map(|e| e.pubkey).collect())
+    }
+
+    /// Get what the event references
+    ///
+    /// Return `None` if the event isn't stored.
+    pub async fn references(&self, id: &EventId) -> Result<Option<References>, DatabaseError> {
+        let event: Option<Event> = self.database.event_by_id(id).await?;
+        Ok(event.as_ref().map(References::from_event))
+    }
+
+    /// Aggregate the stored NIP56 reports about the public key
+    ///
+    /// Only the reports with the report type in the `p` tag are considered.
+    pub async fn reports_about_public_key(
+        &self,
+        public_key: &PublicKey,
+    ) -> Result<ReportSummary, DatabaseError> {
+        let filter: Filter = Filter::new().kind(Kind::Reporting).pubkey(*public_key);
+        let events: Events = self.database.query(vec![filter]).await?;
+
+        let mut summary: ReportSummary = ReportSummary::default();
+        for event in events.into_iter() {
+            for tag in event.tags.iter() {
+                if let Some(TagStandard::PublicKeyReport(pk, report)) = tag.as_standardized() {
+                    if pk == public_key {
+                        summary.insert(report.clone(), event.pubkey);
+                    }
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Aggregate the stored NIP56 reports about the event
+    pub async fn reports_about_event(&self, id: &EventId) -> Result<ReportSummary, DatabaseError> {
+        let filter: Filter = Filter::new().kind(Kind::Reporting).event(*id);
+        let events: Events = self.database.query(vec![filter]).await?;
+
+        let mut summary: ReportSummary = ReportSummary::default();
+        for event in events.into_iter() {
+            for tag in event.tags.iter() {
+                if let Some(TagStandard::EventReport(event_id, report)) = tag.as_standardized() {
+                    if event_id == id {
+                        summary.insert(report.clone(), event.pubkey);
+                    }
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag, Timestamp};
+
+    use super::*;
+    use crate::{MemoryDatabase, MemoryDatabaseOptions};
+
+    #[tokio::test]
+    async fn test_graph() {
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        });
+
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let carol = Keys::generate();
+
+        let note = EventBuilder::text_note("Hello")
+            .sign_with_keys(&alice)
+            .unwrap();
+        let reply = EventBuilder::text_note("Hi")
+            .tags([Tag::event(note.id), Tag::public_key(alice.public_key())])
+            .sign_with_keys(&bob)
+            .unwrap();
+        let reaction = EventBuilder::new(Kind::Reaction, "+")
+            .tags([Tag::event(note.id)])
+            .sign_with_keys(&carol)
+            .unwrap();
+        let other = EventBuilder::text_note("Unrelated")
+            .sign_with_keys(&carol)
+            .unwrap();
+
+        for event in [&note, &reply, &reaction, &other] {
+            database.save_event(event).await.unwrap();
+        }
+
+        let graph = Graph::new(&database);
+
+        let referencing = graph.referenced_by(&note.id).await.unwrap();
+        assert_eq!(referencing.len(), 2);
+        assert!(referencing.contains(&reply));
+        assert!(referencing.contains(&reaction));
+
+        let interactions = graph.interactions(&note.id).await.unwrap();
+        assert_eq!(
+            interactions,
+            HashSet::from([bob.public_key(), carol.public_key()])
+        );
+
+        let references = graph.references(&reply.id).await.unwrap().unwrap();
+        assert_eq!(references.events, HashSet::from([note.id]));
+        assert_eq!(references.public_keys, HashSet::from([alice.public_key()]));
+        assert!(graph
+            .references(&EventId::all_zeros())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reports() {
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        });
+
+        let spammer = Keys::generate();
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+
+        let note = EventBuilder::text_note("Buy now")
+            .sign_with_keys(&spammer)
+            .unwrap();
+        let reports = [
+            EventBuilder::report_public_key(spammer.public_key(), Report::Spam, "")
+                .sign_with_keys(&alice)
+                .unwrap(),
+            EventBuilder::report_public_key(spammer.public_key(), Report::Spam, "Again")
+                .custom_created_at(Timestamp::from(1))
+                .sign_with_keys(&alice)
+                .unwrap(),
+            EventBuilder::report_public_key(spammer.public_key(), Report::Impersonation, "")
+                .sign_with_keys(&bob)
+                .unwrap(),
+            EventBuilder::report_event(note.id, spammer.public_key(), Report::Spam, "")
+                .sign_with_keys(&bob)
+                .unwrap(),
+        ];
+
+        database.save_event(&note).await.unwrap();
+        for report in reports.iter() {
+            database.save_event(report).await.unwrap();
+        }
+
+        let graph = Graph::new(&database);
+
+        let summary = graph
+            .reports_about_public_key(&spammer.public_key())
+            .await
+            .unwrap();
+        assert_eq!(summary.total(), 2);
+        assert_eq!(summary.count(&Report::Spam), 1);
+        assert_eq!(summary.count(&Report::Impersonation), 1);
+        assert_eq!(summary.count(&Report::Illegal), 0);
+
+        let summary = graph.reports_about_event(&note.id).await.unwrap();
+        assert_eq!(summary.total(), 1);
+        assert_eq!(summary.count(&Report::Spam), 1);
+
+        let summary = graph
+            .reports_about_public_key(&alice.public_key())
+            .await
+            .unwrap();
+        assert!(summary.is_empty());
+    }
+}
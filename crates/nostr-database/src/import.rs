@@ -0,0 +1,305 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Event importers
+//!
+//! Warm up the database with the events stored by other clients or relays:
+//! * JSONL dumps (i.e. `strfry export` output);
+//! * other databases (i.e. `nostrdb`, opened with the `nostr-ndb` crate).
+
+use std::sync::Arc;
+
+use nostr::{ClientMessage, Event, Filter, JsonUtil, RelayMessage, Timestamp};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use crate::{DatabaseError, Events, NostrEventsDatabase};
+
+const PAGE_SIZE: usize = 1000;
+
+/// Import statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    /// Number of imported events
+    pub imported: usize,
+    /// Number of events rejected by the database (i.e. duplicated, deleted or replaced)
+    pub rejected: usize,
+    /// Number of invalid entries (i.e. malformed JSON or invalid signature)
+    pub invalid: usize,
+}
+
+/// Event importer
+#[derive(Debug)]
+pub struct Importer<'a, T>
+where
+    T: NostrEventsDatabase + ?Sized,
+{
+    database: &'a T,
+    verify: bool,
+}
+
+impl<'a, T> Importer<'a, T>
+where
+    T: NostrEventsDatabase + ?Sized,
+{
+    /// New importer into the database
+    #[inline]
+    pub fn new(database: &'a T) -> Self {
+        Self {
+            database,
+            verify: true,
+        }
+    }
+
+    /// Verify the event IDs and signatures before importing (default: true)
+    ///
+    /// Disable it only if the source is trusted.
+    #[inline]
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Import a JSONL dump
+    ///
+    /// Every line can be an event or an `EVENT` client or relay message
+    /// (i.e. `["EVENT", <event>]` or `["EVENT", <subscription id>, <event>]`).
+    /// The `strfry export` output uses this format.
+    ///
+    /// The empty lines are skipped.
+    pub async fn jsonl<R>(&self, reader: R) -> Result<ImportStats, DatabaseError>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut stats: ImportStats = ImportStats::default();
+        let mut lines = reader.lines();
+
+        while let Some(line) = lines.next_line().await.map_err(DatabaseError::backend)? {
+            let line: &str = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            match parse_line(line) {
+                Some(event) => self.import(&event, &mut stats).await?,
+                None => stats.invalid += 1,
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Import the events matching the filter from another database
+    ///
+    /// The events are queried in pages, from the newest to the oldest.
+    /// The events of the oldest second of a full page are queried all together,
+    /// since the page may have truncated them, and are imported sorted by ID.
+    pub async fn database<S>(
+        &self,
+        source: &S,
+        filter: Filter,
+    ) -> Result<ImportStats, DatabaseError>
+    where
+        S: NostrEventsDatabase + ?Sized,
+    {
+        let mut stats: ImportStats = ImportStats::default();
+        let mut until: Option<Timestamp> = filter.until;
+
+        loop {
+            let mut page_filter: Filter = filter.clone().limit(PAGE_SIZE);
+            page_filter.until = until;
+
+            let events: Events = source.query(vec![page_filter]).await?;
+            let full: bool = events.len() >= PAGE_SIZE;
+
+            let oldest: Timestamp = match events.last() {
+                Some(event) => event.created_at,
+                None => break,
+            };
+
+            // Last page
+            if !full {
+                for event in events.into_iter() {
+                    self.import(&event, &mut stats).await?;
+                }
+                break;
+            }
+
+            // Import the events of the newer seconds, that are complete
+            for event in events.into_iter().filter(|e| e.created_at > oldest) {
+                self.import(&event, &mut stats).await?;
+            }
+
+            // Import all the events of the oldest second
+            let mut second_filter: Filter = filter.clone().since(oldest).until(oldest);
+            second_filter.limit = None;
+
+            let mut second: Vec<Event> = source
+                .query(vec![second_filter])
+                .await?
+                .into_iter()
+                .collect();
+            second.sort_by(|a, b| a.id.cmp(&b.id));
+
+            for event in second.iter() {
+                self.import(event, &mut stats).await?;
+            }
+
+            if oldest.as_u64() == 0 {
+                break;
+            }
+
+            until = Some(oldest - 1);
+        }
+
+        Ok(stats)
+    }
+
+    async fn import(&self, event: &Event, stats: &mut ImportStats) -> Result<(), DatabaseError> {
+        if self.verify && event.verify().is_err() {
+            stats.invalid += 1;
+            return Ok(());
+        }
+
+        if self.database.save_event(event).await?.is_success() {
+            stats.imported += 1;
+        } else {
+            stats.rejected += 1;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_line(line: &str) -> Option<Event> {
+    if line.starts_with('{') {
+        return Event::from_json(line).ok();
+    }
+
+    if let Ok(ClientMessage::Event(event)) = ClientMessage::from_json(line) {
+        return Some(*event);
+    }
+
+    match RelayMessage::from_json(line) {
+        // Not shared: unwrapped without cloning
+        Ok(RelayMessage::Event { event, .. }) => {
+            Some(Arc::try_unwrap(event).unwrap_or_else(|event| Event::clone(&event)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Kind, SubscriptionId};
+
+    use super::*;
+    use crate::{MemoryDatabase, MemoryDatabaseOptions};
+
+    fn database() -> MemoryDatabase {
+        MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_import_jsonl() {
+        let keys = Keys::generate();
+        let note = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let reaction = EventBuilder::new(Kind::Reaction, "+")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let repost = EventBuilder::new(Kind::Repost, "")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let dump: String = [
+            note.as_json(),
+            String::new(),
+            ClientMessage::event(reaction).as_json(),
+            RelayMessage::event(SubscriptionId::new("sub"), repost).as_json(),
+            note.as_json(),
+            String::from("not an event"),
+        ]
+        .join("\n");
+
+        let database = database();
+        let stats = Importer::new(&database)
+            .jsonl(dump.as_bytes())
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                imported: 3,
+                rejected: 1,
+                invalid: 1,
+            }
+        );
+        assert_eq!(database.count(vec![Filter::new()]).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_import_database() {
+        let keys = Keys::generate();
+        let source = database();
+        for i in 0..5 {
+            let event = EventBuilder::text_note(format!("Note {i}"))
+                .custom_created_at(Timestamp::from(1_000))
+                .sign_with_keys(&keys)
+                .unwrap();
+            source.save_event(&event).await.unwrap();
+        }
+        let metadata = EventBuilder::new(Kind::Metadata, "{}")
+            .sign_with_keys(&keys)
+            .unwrap();
+        source.save_event(&metadata).await.unwrap();
+
+        let target = database();
+        let stats = Importer::new(&target)
+            .database(&source, Filter::new().kind(Kind::TextNote))
+            .await
+            .unwrap();
+        assert_eq!(stats.imported, 5);
+        assert_eq!(target.count(vec![Filter::new()]).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_import_database_paging() {
+        let keys = Keys::generate();
+        let source = database();
+
+        // More events than a page in the same second, plus some pages of other seconds
+        let mut total: usize = 0;
+        for (created_at, count) in [(3_000, 10), (2_000, PAGE_SIZE + 50), (1_000, PAGE_SIZE - 1)] {
+            for i in 0..count {
+                let event = EventBuilder::text_note(format!("Note {created_at} {i}"))
+                    .custom_created_at(Timestamp::from(created_at))
+                    .sign_with_keys(&keys)
+                    .unwrap();
+                source.save_event(&event).await.unwrap();
+                total += 1;
+            }
+        }
+
+        let target = database();
+        let stats = Importer::new(&target)
+            .verify(false)
+            .database(&source, Filter::new().kind(Kind::TextNote))
+            .await
+            .unwrap();
+        assert_eq!(
+            stats,
+            ImportStats {
+                imported: total,
+                rejected: 0,
+                invalid: 0,
+            }
+        );
+        assert_eq!(target.count(vec![Filter::new()]).await.unwrap(), total);
+    }
+}
@@ -13,17 +13,22 @@ use std::sync::Arc;
 
 pub use async_trait::async_trait;
 pub use nostr;
+use nostr::{Event, EventId, RelayUrl, SubscriptionId, Timestamp};
 
 mod collections;
+mod cursor;
 mod error;
 mod events;
 #[cfg(feature = "flatbuf")]
 pub mod flatbuffers;
+pub mod graph;
+pub mod import;
 pub mod memory;
 pub mod prelude;
 pub mod profile;
 
 pub use self::collections::events::Events;
+pub use self::cursor::SubscriptionCursor;
 pub use self::error::DatabaseError;
 pub use self::events::helper::{DatabaseEventResult, DatabaseHelper};
 pub use self::events::{
@@ -32,6 +37,8 @@ pub use self::events::{
 };
 #[cfg(feature = "flatbuf")]
 pub use self::flatbuffers::{FlatBufferBuilder, FlatBufferDecode, FlatBufferEncode};
+pub use self::graph::{Graph, References, ReportSummary};
+pub use self::import::{ImportStats, Importer};
 pub use self::memory::{MemoryDatabase, MemoryDatabaseOptions};
 pub use self::profile::Profile;
 
@@ -99,6 +106,92 @@ pub trait NostrDatabase: NostrEventsDatabase {
 
     /// Wipe all data
     async fn wipe(&self) -> Result<(), DatabaseError>;
+
+    /// Save the [`SubscriptionCursor`] of a subscription to a relay
+    ///
+    /// Return [`DatabaseError::NotSupported`] by default.
+    async fn save_cursor(
+        &self,
+        relay_url: &RelayUrl,
+        id: &SubscriptionId,
+        cursor: &SubscriptionCursor,
+    ) -> Result<(), DatabaseError> {
+        let _ = (relay_url, id, cursor);
+        Err(DatabaseError::NotSupported)
+    }
+
+    /// Get the [`SubscriptionCursor`] of a subscription to a relay
+    ///
+    /// Return [`DatabaseError::NotSupported`] by default.
+    async fn cursor(
+        &self,
+        relay_url: &RelayUrl,
+        id: &SubscriptionId,
+    ) -> Result<Option<SubscriptionCursor>, DatabaseError> {
+        let _ = (relay_url, id);
+        Err(DatabaseError::NotSupported)
+    }
+
+    /// Queue an event in the outbox of a relay, to send it at the next connection
+    ///
+    /// Queuing again the same event must not duplicate it.
+    ///
+    /// Return [`DatabaseError::NotSupported`] by default.
+    async fn save_outbox_event(
+        &self,
+        relay_url: &RelayUrl,
+        event: &Event,
+    ) -> Result<(), DatabaseError> {
+        let _ = (relay_url, event);
+        Err(DatabaseError::NotSupported)
+    }
+
+    /// Get the events queued in the outbox of a relay, from the oldest
+    ///
+    /// Return [`DatabaseError::NotSupported`] by default.
+    async fn outbox(&self, relay_url: &RelayUrl) -> Result<Vec<Event>, DatabaseError> {
+        let _ = relay_url;
+        Err(DatabaseError::NotSupported)
+    }
+
+    /// Remove an event from the outbox of a relay
+    ///
+    /// Return [`DatabaseError::NotSupported`] by default.
+    async fn remove_outbox_event(
+        &self,
+        relay_url: &RelayUrl,
+        event_id: &EventId,
+    ) -> Result<(), DatabaseError> {
+        let _ = (relay_url, event_id);
+        Err(DatabaseError::NotSupported)
+    }
+
+    /// Save an event scheduled to be published at `publish_at`
+    ///
+    /// Return [`DatabaseError::NotSupported`] by default.
+    async fn save_scheduled_event(
+        &self,
+        event: &Event,
+        publish_at: &Timestamp,
+    ) -> Result<(), DatabaseError> {
+        let _ = (event, publish_at);
+        Err(DatabaseError::NotSupported)
+    }
+
+    /// Get the scheduled events, sorted by publishing time
+    ///
+    /// Return [`DatabaseError::NotSupported`] by default.
+    async fn scheduled_events(&self) -> Result<Vec<(Event, Timestamp)>, DatabaseError> {
+        Err(DatabaseError::NotSupported)
+    }
+
+    /// Remove a scheduled event
+    ///
+    /// Return [`DatabaseError::NotSupported`] by default.
+    async fn remove_scheduled_event(&self, event_id: &EventId) -> Result<(), DatabaseError> {
+        let _ = event_id;
+        Err(DatabaseError::NotSupported)
+    }
 }
 
 #[cfg(test)]
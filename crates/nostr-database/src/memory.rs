@@ -13,7 +13,7 @@ use tokio::sync::RwLock;
 
 use crate::{
     Backend, DatabaseError, DatabaseEventResult, DatabaseEventStatus, DatabaseHelper, Events,
-    NostrDatabase, NostrEventsDatabase, RejectedReason, SaveEventStatus,
+    NostrDatabase, NostrEventsDatabase, RejectedReason, SaveEventStatus, SubscriptionCursor,
 };
 
 /// Database options
@@ -48,6 +48,9 @@ impl MemoryDatabaseOptions {
 pub struct MemoryDatabase {
     opts: MemoryDatabaseOptions,
     seen_event_ids: Arc<RwLock<SeenTracker>>,
+    cursors: Arc<RwLock<HashMap<(RelayUrl, SubscriptionId), SubscriptionCursor>>>,
+    outbox: Arc<RwLock<HashMap<RelayUrl, Vec<Event>>>>,
+    scheduled: Arc<RwLock<HashMap<EventId, (Event, Timestamp)>>>,
     helper: DatabaseHelper,
 }
 
@@ -68,6 +71,9 @@ impl MemoryDatabase {
         Self {
             opts,
             seen_event_ids: Arc::new(RwLock::new(SeenTracker::new(opts.max_events))),
+            cursors: Arc::new(RwLock::new(HashMap::new())),
+            outbox: Arc::new(RwLock::new(HashMap::new())),
+            scheduled: Arc::new(RwLock::new(HashMap::new())),
             helper: match opts.max_events {
                 Some(max) => DatabaseHelper::bounded(max),
                 None => DatabaseHelper::unbounded(),
@@ -90,6 +96,91 @@ impl NostrDatabase for MemoryDatabase {
         // Clear
         let mut seen_event_ids = self.seen_event_ids.write().await;
         seen_event_ids.clear();
+
+        let mut cursors = self.cursors.write().await;
+        cursors.clear();
+
+        let mut outbox = self.outbox.write().await;
+        outbox.clear();
+
+        let mut scheduled = self.scheduled.write().await;
+        scheduled.clear();
+        Ok(())
+    }
+
+    async fn save_cursor(
+        &self,
+        relay_url: &RelayUrl,
+        id: &SubscriptionId,
+        cursor: &SubscriptionCursor,
+    ) -> Result<(), DatabaseError> {
+        let mut cursors = self.cursors.write().await;
+        cursors.insert((relay_url.clone(), id.clone()), cursor.clone());
+        Ok(())
+    }
+
+    async fn cursor(
+        &self,
+        relay_url: &RelayUrl,
+        id: &SubscriptionId,
+    ) -> Result<Option<SubscriptionCursor>, DatabaseError> {
+        let cursors = self.cursors.read().await;
+        Ok(cursors.get(&(relay_url.clone(), id.clone())).cloned())
+    }
+
+    async fn save_outbox_event(
+        &self,
+        relay_url: &RelayUrl,
+        event: &Event,
+    ) -> Result<(), DatabaseError> {
+        let mut outbox = self.outbox.write().await;
+        let queue: &mut Vec<Event> = outbox.entry(relay_url.clone()).or_default();
+        if !queue.iter().any(|e| e.id == event.id) {
+            queue.push(event.clone());
+        }
+        Ok(())
+    }
+
+    async fn outbox(&self, relay_url: &RelayUrl) -> Result<Vec<Event>, DatabaseError> {
+        let outbox = self.outbox.read().await;
+        Ok(outbox.get(relay_url).cloned().unwrap_or_default())
+    }
+
+    async fn remove_outbox_event(
+        &self,
+        relay_url: &RelayUrl,
+        event_id: &EventId,
+    ) -> Result<(), DatabaseError> {
+        let mut outbox = self.outbox.write().await;
+        if let Some(queue) = outbox.get_mut(relay_url) {
+            queue.retain(|e| &e.id != event_id);
+            if queue.is_empty() {
+                outbox.remove(relay_url);
+            }
+        }
+        Ok(())
+    }
+
+    async fn save_scheduled_event(
+        &self,
+        event: &Event,
+        publish_at: &Timestamp,
+    ) -> Result<(), DatabaseError> {
+        let mut scheduled = self.scheduled.write().await;
+        scheduled.insert(event.id, (event.clone(), *publish_at));
+        Ok(())
+    }
+
+    async fn scheduled_events(&self) -> Result<Vec<(Event, Timestamp)>, DatabaseError> {
+        let scheduled = self.scheduled.read().await;
+        let mut events: Vec<(Event, Timestamp)> = scheduled.values().cloned().collect();
+        events.sort_by_key(|(event, publish_at)| (*publish_at, event.id));
+        Ok(events)
+    }
+
+    async fn remove_scheduled_event(&self, event_id: &EventId) -> Result<(), DatabaseError> {
+        let mut scheduled = self.scheduled.write().await;
+        scheduled.remove(event_id);
         Ok(())
     }
 }
@@ -253,6 +344,7 @@ impl SeenTracker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::NostrEventsDatabaseExt;
 
     #[test]
     fn test_seen_tracker_without_capacity() {
@@ -307,4 +399,65 @@ mod tests {
         assert!(tracker.contains(&id2));
         assert!(tracker.queue.contains(&id2));
     }
+
+    #[tokio::test]
+    async fn test_nip19_event_with_seen_relays() {
+        let db = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        });
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let relay_url = RelayUrl::parse("wss://relay.damus.io").unwrap();
+
+        db.save_event(&event).await.unwrap();
+        db.event_id_seen(event.id, relay_url.clone()).await.unwrap();
+
+        let nip19 = db.nip19_event(&event.id).await.unwrap();
+        assert_eq!(nip19.event_id, event.id);
+        assert_eq!(nip19.author, Some(keys.public_key()));
+        assert_eq!(nip19.kind, Some(Kind::TextNote));
+        assert_eq!(nip19.relays, vec![relay_url.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_event_by_coordinate() {
+        let db = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        });
+
+        let keys = Keys::generate();
+        let old = EventBuilder::new(Kind::LongFormTextNote, "Old")
+            .tag(Tag::identifier("article"))
+            .custom_created_at(Timestamp::from(1_000))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let new = EventBuilder::new(Kind::LongFormTextNote, "New")
+            .tag(Tag::identifier("article"))
+            .custom_created_at(Timestamp::from(2_000))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let other = EventBuilder::new(Kind::LongFormTextNote, "Other")
+            .tag(Tag::identifier("other"))
+            .custom_created_at(Timestamp::from(3_000))
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        db.save_event(&old).await.unwrap();
+        db.save_event(&new).await.unwrap();
+        db.save_event(&other).await.unwrap();
+
+        let coordinate =
+            Coordinate::new(Kind::LongFormTextNote, keys.public_key()).identifier("article");
+        let event = db.event_by_coordinate(&coordinate).await.unwrap();
+        assert_eq!(event, Some(new));
+
+        let coordinate =
+            Coordinate::new(Kind::LongFormTextNote, keys.public_key()).identifier("unknown");
+        assert!(db.event_by_coordinate(&coordinate).await.unwrap().is_none());
+    }
 }
@@ -57,6 +57,91 @@ impl NostrDatabase for NostrLMDB {
     async fn wipe(&self) -> Result<(), DatabaseError> {
         self.db.wipe().await.map_err(DatabaseError::backend)
     }
+
+    #[inline]
+    async fn save_cursor(
+        &self,
+        relay_url: &RelayUrl,
+        id: &SubscriptionId,
+        cursor: &SubscriptionCursor,
+    ) -> Result<(), DatabaseError> {
+        self.db
+            .save_cursor(relay_url.clone(), id.clone(), cursor.clone())
+            .await
+            .map_err(DatabaseError::backend)
+    }
+
+    #[inline]
+    async fn cursor(
+        &self,
+        relay_url: &RelayUrl,
+        id: &SubscriptionId,
+    ) -> Result<Option<SubscriptionCursor>, DatabaseError> {
+        self.db
+            .cursor(relay_url.clone(), id.clone())
+            .await
+            .map_err(DatabaseError::backend)
+    }
+
+    #[inline]
+    async fn save_outbox_event(
+        &self,
+        relay_url: &RelayUrl,
+        event: &Event,
+    ) -> Result<(), DatabaseError> {
+        self.db
+            .save_outbox_event(relay_url.clone(), event.clone())
+            .await
+            .map_err(DatabaseError::backend)
+    }
+
+    #[inline]
+    async fn outbox(&self, relay_url: &RelayUrl) -> Result<Vec<Event>, DatabaseError> {
+        self.db
+            .outbox(relay_url.clone())
+            .await
+            .map_err(DatabaseError::backend)
+    }
+
+    #[inline]
+    async fn remove_outbox_event(
+        &self,
+        relay_url: &RelayUrl,
+        event_id: &EventId,
+    ) -> Result<(), DatabaseError> {
+        self.db
+            .remove_outbox_event(relay_url.clone(), *event_id)
+            .await
+            .map_err(DatabaseError::backend)
+    }
+
+    #[inline]
+    async fn save_scheduled_event(
+        &self,
+        event: &Event,
+        publish_at: &Timestamp,
+    ) -> Result<(), DatabaseError> {
+        self.db
+            .save_scheduled_event(event.clone(), *publish_at)
+            .await
+            .map_err(DatabaseError::backend)
+    }
+
+    #[inline]
+    async fn scheduled_events(&self) -> Result<Vec<(Event, Timestamp)>, DatabaseError> {
+        self.db
+            .scheduled_events()
+            .await
+            .map_err(DatabaseError::backend)
+    }
+
+    #[inline]
+    async fn remove_scheduled_event(&self, event_id: &EventId) -> Result<(), DatabaseError> {
+        self.db
+            .remove_scheduled_event(*event_id)
+            .await
+            .map_err(DatabaseError::backend)
+    }
 }
 
 #[async_trait]
@@ -497,4 +582,91 @@ mod tests {
 
         assert_eq!(db.count_all().await, 2);
     }
+
+    #[tokio::test]
+    async fn test_subscription_cursor() {
+        let db = TempDatabase::new();
+
+        let url = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let id = SubscriptionId::new("notifications");
+
+        assert!(db.cursor(&url, &id).await.unwrap().is_none());
+
+        let mut cursor = SubscriptionCursor::new();
+        cursor.update(EventId::all_zeros(), Timestamp::from(1_700_000_000));
+        db.save_cursor(&url, &id, &cursor).await.unwrap();
+
+        assert_eq!(db.cursor(&url, &id).await.unwrap(), Some(cursor));
+
+        // Different subscription
+        let other = SubscriptionId::new("other");
+        assert!(db.cursor(&url, &other).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_outbox() {
+        let db = TempDatabase::new();
+
+        let url = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let other = RelayUrl::parse("wss://nos.lol").unwrap();
+
+        let keys = Keys::generate();
+        let newer = EventBuilder::text_note("Newer")
+            .custom_created_at(Timestamp::from(1_700_000_100))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let older = EventBuilder::text_note("Older")
+            .custom_created_at(Timestamp::from(1_700_000_000))
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        db.save_outbox_event(&url, &newer).await.unwrap();
+        db.save_outbox_event(&url, &older).await.unwrap();
+        db.save_outbox_event(&url, &older).await.unwrap();
+
+        // Sorted from the oldest, without duplicates
+        assert_eq!(
+            db.outbox(&url).await.unwrap(),
+            vec![older.clone(), newer.clone()]
+        );
+        assert!(db.outbox(&other).await.unwrap().is_empty());
+
+        db.remove_outbox_event(&url, &older.id).await.unwrap();
+        assert_eq!(db.outbox(&url).await.unwrap(), vec![newer]);
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_events() {
+        let db = TempDatabase::new();
+
+        let keys = Keys::generate();
+        let first = EventBuilder::text_note("First")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let second = EventBuilder::text_note("Second")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        db.save_scheduled_event(&second, &Timestamp::from(2_000_000_000))
+            .await
+            .unwrap();
+        db.save_scheduled_event(&first, &Timestamp::from(1_900_000_000))
+            .await
+            .unwrap();
+
+        // Sorted by publishing time
+        assert_eq!(
+            db.scheduled_events().await.unwrap(),
+            vec![
+                (first.clone(), Timestamp::from(1_900_000_000)),
+                (second.clone(), Timestamp::from(2_000_000_000)),
+            ]
+        );
+
+        db.remove_scheduled_event(&first.id).await.unwrap();
+        assert_eq!(
+            db.scheduled_events().await.unwrap(),
+            vec![(second, Timestamp::from(2_000_000_000))]
+        );
+    }
 }
@@ -5,8 +5,10 @@
 
 use core::{cmp, iter};
 
+use nostr::hashes::sha256::Hash as Sha256Hash;
+use nostr::hashes::{Hash, HashEngine};
 use nostr::prelude::Coordinate;
-use nostr::{EventId, PublicKey, SingleLetterTag, Timestamp};
+use nostr::{EventId, PublicKey, RelayUrl, SingleLetterTag, SubscriptionId, Timestamp};
 
 const CREATED_AT_BE: usize = 8;
 const KIND_BE: usize = 2;
@@ -178,3 +180,46 @@ pub fn make_coordinate_index_key(coordinate: &Coordinate) -> Vec<u8> {
 
     key
 }
+
+/// Make subscription cursor key
+///
+/// The relay URL and the subscription ID can be long, so are hashed to respect the LMDB key size limit.
+///
+/// ## Structure
+///
+/// `sha256(relay_url + \0 + subscription_id)(32)`
+pub fn make_cursor_key(relay_url: &RelayUrl, id: &SubscriptionId) -> Vec<u8> {
+    let mut engine = Sha256Hash::engine();
+    engine.input(relay_url.as_str().as_bytes());
+    engine.input(&[0]);
+    engine.input(id.as_str().as_bytes());
+    let hash: Sha256Hash = Sha256Hash::from_engine(engine);
+    hash.to_byte_array().to_vec()
+}
+
+/// Make outbox key prefix
+///
+/// ## Structure
+///
+/// `sha256(relay_url)(32)`
+pub fn make_outbox_prefix(relay_url: &RelayUrl) -> Vec<u8> {
+    let hash: Sha256Hash = Sha256Hash::hash(relay_url.as_str().as_bytes());
+    hash.to_byte_array().to_vec()
+}
+
+/// Make outbox key (sorted from the oldest event)
+///
+/// ## Structure
+///
+/// `sha256(relay_url)(32)` + `created_at(8)` + `event_id(32)`
+pub fn make_outbox_key(
+    relay_url: &RelayUrl,
+    created_at: &Timestamp,
+    event_id: &[u8; EventId::LEN],
+) -> Vec<u8> {
+    let mut key: Vec<u8> = make_outbox_prefix(relay_url);
+    key.reserve(CREATED_AT_BE + EventId::LEN);
+    key.extend(created_at.as_u64().to_be_bytes());
+    key.extend(event_id);
+    key
+}
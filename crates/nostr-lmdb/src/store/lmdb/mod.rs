@@ -13,7 +13,7 @@ use heed::types::{Bytes, Unit, U64};
 use heed::{Database, Env, EnvFlags, EnvOpenOptions, RoRange, RoTxn, RwTxn};
 use nostr::prelude::*;
 use nostr_database::flatbuffers::FlatBufferDecodeBorrowed;
-use nostr_database::{FlatBufferBuilder, FlatBufferEncode};
+use nostr_database::{FlatBufferBuilder, FlatBufferEncode, SubscriptionCursor};
 
 mod index;
 
@@ -45,6 +45,12 @@ pub(crate) struct Lmdb {
     deleted_ids: Database<Bytes, Unit>, // Event ID
     /// Deleted coordinates
     deleted_coordinates: Database<Bytes, U64<NativeEndian>>, // Coordinate, UNIX timestamp
+    /// Subscription cursors
+    cursors: Database<Bytes, Bytes>, // Relay URL + Subscription ID, Cursor
+    /// Outbox
+    outbox: Database<Bytes, Bytes>, // Relay URL + CreatedAt + ID, Event JSON
+    /// Scheduled events
+    scheduled: Database<Bytes, Bytes>, // Event ID, Publishing timestamp + Event JSON
 }
 
 impl Lmdb {
@@ -64,7 +70,7 @@ impl Lmdb {
         let env: Env = unsafe {
             EnvOpenOptions::new()
                 .flags(EnvFlags::NO_TLS)
-                .max_dbs(9)
+                .max_dbs(12)
                 .map_size(map_size)
                 .open(path)?
         };
@@ -117,6 +123,21 @@ impl Lmdb {
             .types::<Bytes, U64<NativeEndian>>()
             .name("deleted-coordinates")
             .create(&mut txn)?;
+        let cursors = env
+            .database_options()
+            .types::<Bytes, Bytes>()
+            .name("cursors")
+            .create(&mut txn)?;
+        let outbox = env
+            .database_options()
+            .types::<Bytes, Bytes>()
+            .name("outbox")
+            .create(&mut txn)?;
+        let scheduled = env
+            .database_options()
+            .types::<Bytes, Bytes>()
+            .name("scheduled")
+            .create(&mut txn)?;
 
         // Commit changes
         txn.commit()?;
@@ -132,6 +153,9 @@ impl Lmdb {
             ktc_index,
             deleted_ids,
             deleted_coordinates,
+            cursors,
+            outbox,
+            scheduled,
         })
     }
 
@@ -274,6 +298,9 @@ impl Lmdb {
         self.ktc_index.clear(txn)?;
         self.deleted_ids.clear(txn)?;
         self.deleted_coordinates.clear(txn)?;
+        self.cursors.clear(txn)?;
+        self.outbox.clear(txn)?;
+        self.scheduled.clear(txn)?;
         Ok(())
     }
 
@@ -722,6 +749,134 @@ impl Lmdb {
             .map(Timestamp::from_secs))
     }
 
+    pub(crate) fn save_cursor(
+        &self,
+        txn: &mut RwTxn,
+        relay_url: &RelayUrl,
+        id: &SubscriptionId,
+        cursor: &SubscriptionCursor,
+    ) -> Result<(), Error> {
+        let key: Vec<u8> = index::make_cursor_key(relay_url, id);
+        self.cursors.put(txn, &key, &cursor.encode())?;
+        Ok(())
+    }
+
+    pub(crate) fn get_cursor(
+        &self,
+        txn: &RoTxn,
+        relay_url: &RelayUrl,
+        id: &SubscriptionId,
+    ) -> Result<Option<SubscriptionCursor>, Error> {
+        let key: Vec<u8> = index::make_cursor_key(relay_url, id);
+        Ok(self
+            .cursors
+            .get(txn, &key)?
+            .and_then(SubscriptionCursor::decode))
+    }
+
+    pub(crate) fn save_outbox_event(
+        &self,
+        txn: &mut RwTxn,
+        relay_url: &RelayUrl,
+        event: &Event,
+    ) -> Result<(), Error> {
+        let key: Vec<u8> =
+            index::make_outbox_key(relay_url, &event.created_at, event.id.as_bytes());
+        self.outbox.put(txn, &key, event.as_json().as_bytes())?;
+        Ok(())
+    }
+
+    pub(crate) fn get_outbox(
+        &self,
+        txn: &RoTxn,
+        relay_url: &RelayUrl,
+    ) -> Result<Vec<Event>, Error> {
+        let prefix: Vec<u8> = index::make_outbox_prefix(relay_url);
+        let mut events: Vec<Event> = Vec::new();
+        for result in self.outbox.prefix_iter(txn, &prefix)? {
+            let (_, json) = result?;
+            // Skip the malformed events
+            if let Ok(event) = Event::from_json(json) {
+                events.push(event);
+            }
+        }
+        Ok(events)
+    }
+
+    pub(crate) fn remove_outbox_event(
+        &self,
+        txn: &mut RwTxn,
+        relay_url: &RelayUrl,
+        event_id: &EventId,
+    ) -> Result<(), Error> {
+        let prefix: Vec<u8> = index::make_outbox_prefix(relay_url);
+
+        // The key contains the `created_at`: search it
+        let mut key: Option<Vec<u8>> = None;
+        for result in self.outbox.prefix_iter(txn, &prefix)? {
+            let (k, _) = result?;
+            if k.ends_with(event_id.as_bytes()) {
+                key = Some(k.to_vec());
+                break;
+            }
+        }
+
+        if let Some(key) = key {
+            self.outbox.delete(txn, &key)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn save_scheduled_event(
+        &self,
+        txn: &mut RwTxn,
+        event: &Event,
+        publish_at: &Timestamp,
+    ) -> Result<(), Error> {
+        let json: String = event.as_json();
+        let mut value: Vec<u8> = Vec::with_capacity(8 + json.len());
+        value.extend(publish_at.as_u64().to_be_bytes());
+        value.extend(json.as_bytes());
+        self.scheduled.put(txn, event.id.as_bytes(), &value)?;
+        Ok(())
+    }
+
+    pub(crate) fn get_scheduled_events(
+        &self,
+        txn: &RoTxn,
+    ) -> Result<Vec<(Event, Timestamp)>, Error> {
+        let mut events: Vec<(Event, Timestamp)> = Vec::new();
+        for result in self.scheduled.iter(txn)? {
+            let (_, value) = result?;
+
+            if value.len() < 8 {
+                continue;
+            }
+
+            let (publish_at, json) = value.split_at(8);
+            let mut bytes: [u8; 8] = [0; 8];
+            bytes.copy_from_slice(publish_at);
+
+            // Skip the malformed events
+            if let Ok(event) = Event::from_json(json) {
+                events.push((event, Timestamp::from(u64::from_be_bytes(bytes))));
+            }
+        }
+        events.sort_by_key(|(event, publish_at)| (*publish_at, event.id));
+        Ok(events)
+    }
+
+    #[inline]
+    pub(crate) fn remove_scheduled_event(
+        &self,
+        txn: &mut RwTxn,
+        event_id: &EventId,
+    ) -> Result<(), Error> {
+        self.scheduled.delete(txn, event_id.as_bytes())?;
+        Ok(())
+    }
+
     pub(crate) fn ci_iter<'a>(
         &'a self,
         txn: &'a RoTxn,
@@ -330,6 +330,103 @@ impl Store {
         .await?
     }
 
+    pub async fn save_cursor(
+        &self,
+        relay_url: RelayUrl,
+        id: SubscriptionId,
+        cursor: SubscriptionCursor,
+    ) -> Result<(), Error> {
+        self.interact(move |db| {
+            let mut txn = db.write_txn()?;
+            db.save_cursor(&mut txn, &relay_url, &id, &cursor)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn cursor(
+        &self,
+        relay_url: RelayUrl,
+        id: SubscriptionId,
+    ) -> Result<Option<SubscriptionCursor>, Error> {
+        self.interact(move |db| {
+            let txn = db.read_txn()?;
+            let cursor = db.get_cursor(&txn, &relay_url, &id)?;
+            txn.commit()?;
+            Ok(cursor)
+        })
+        .await?
+    }
+
+    pub async fn save_outbox_event(&self, relay_url: RelayUrl, event: Event) -> Result<(), Error> {
+        self.interact(move |db| {
+            let mut txn = db.write_txn()?;
+            db.save_outbox_event(&mut txn, &relay_url, &event)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn outbox(&self, relay_url: RelayUrl) -> Result<Vec<Event>, Error> {
+        self.interact(move |db| {
+            let txn = db.read_txn()?;
+            let events = db.get_outbox(&txn, &relay_url)?;
+            txn.commit()?;
+            Ok(events)
+        })
+        .await?
+    }
+
+    pub async fn remove_outbox_event(
+        &self,
+        relay_url: RelayUrl,
+        event_id: EventId,
+    ) -> Result<(), Error> {
+        self.interact(move |db| {
+            let mut txn = db.write_txn()?;
+            db.remove_outbox_event(&mut txn, &relay_url, &event_id)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn save_scheduled_event(
+        &self,
+        event: Event,
+        publish_at: Timestamp,
+    ) -> Result<(), Error> {
+        self.interact(move |db| {
+            let mut txn = db.write_txn()?;
+            db.save_scheduled_event(&mut txn, &event, &publish_at)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    pub async fn scheduled_events(&self) -> Result<Vec<(Event, Timestamp)>, Error> {
+        self.interact(move |db| {
+            let txn = db.read_txn()?;
+            let events = db.get_scheduled_events(&txn)?;
+            txn.commit()?;
+            Ok(events)
+        })
+        .await?
+    }
+
+    pub async fn remove_scheduled_event(&self, event_id: EventId) -> Result<(), Error> {
+        self.interact(move |db| {
+            let mut txn = db.write_txn()?;
+            db.remove_scheduled_event(&mut txn, &event_id)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
     pub async fn wipe(&self) -> Result<(), Error> {
         self.interact(move |db| {
             let mut txn = db.write_txn()?;
@@ -19,6 +19,8 @@ pub struct DatabaseFilter {
     pub since: Option<Timestamp>,
     pub until: Option<Timestamp>,
     pub generic_tags: BTreeMap<SingleLetterTag, BTreeSet<String>>,
+    /// Hex public keys of the delegators
+    pub delegators: BTreeSet<String>,
 }
 
 impl DatabaseFilter {
@@ -62,6 +64,17 @@ impl DatabaseFilter {
         })
     }
 
+    #[inline]
+    fn delegation_match(&self, event: &DatabaseEvent) -> bool {
+        if self.delegators.is_empty() {
+            return true;
+        }
+
+        event.tags.iter().filter_map(|t| t.data()).any(|tag| {
+            tag.len() >= 2 && tag.get(0) == "delegation" && self.delegators.contains(tag.get(1))
+        })
+    }
+
     #[inline]
     fn kind_match(&self, event: &DatabaseEvent) -> bool {
         self.kinds.is_empty() || self.kinds.contains(&event.kind)
@@ -91,6 +104,7 @@ impl DatabaseFilter {
             && self.since.map_or(true, |t| event.created_at >= t)
             && self.until.map_or(true, |t| event.created_at <= t)
             && self.tag_match(event)
+            && self.delegation_match(event)
             && self.search_match(event)
     }
 }
@@ -127,6 +141,10 @@ impl From<Filter> for DatabaseFilter {
             since: filter.since,
             until: filter.until,
             generic_tags: filter.generic_tags,
+            delegators: filter
+                .delegators
+                .map(|delegators| delegators.into_iter().map(|pk| pk.to_hex()).collect())
+                .unwrap_or_default(),
         }
     }
 }
@@ -14,25 +14,42 @@
 
 pub use async_wsocket::ConnectionMode;
 
+pub mod metrics;
+pub mod policy;
 pub mod pool;
 pub mod prelude;
 pub mod relay;
+pub mod retry;
 #[doc(hidden)]
 mod shared;
+pub mod skew;
+pub mod spam;
+pub mod spawner;
 mod stream;
+mod supervisor;
+pub mod tasks;
+pub mod transport;
 
+pub use self::metrics::{IngestionMetrics, IngestionStage, LatencyPercentiles};
+pub use self::policy::{Admission, AdmitPolicy, DedupPolicy, Deduplication};
 pub use self::pool::options::RelayPoolOptions;
-pub use self::pool::{Output, RelayPool, RelayPoolNotification};
+pub use self::pool::{Output, RelayPool, RelayPoolNotification, RelaySet};
 pub use self::relay::flags::{AtomicRelayServiceFlags, RelayServiceFlags};
 pub use self::relay::limits::RelayLimits;
 pub use self::relay::options::{
     FilterOptions, RelayOptions, SubscribeAutoCloseOptions, SubscribeOptions, SyncDirection,
     SyncOptions,
 };
-pub use self::relay::stats::RelayConnectionStats;
+pub use self::relay::stats::{Bandwidth, RelayConnectionStats};
 pub use self::relay::{
-    Reconciliation, Relay, RelayFiltering, RelayFilteringMode, RelayNotification, RelayStatus,
+    DisconnectReason, Reconciliation, Relay, RelayCloseFrame, RelayFiltering, RelayFilteringMode,
+    RelayNotification, RelayStatus,
 };
+pub use self::retry::{RetryPolicy, Retryable};
+pub use self::skew::{ClockSkew, ClockSkewAlert, ClockSkewOptions};
+pub use self::spam::{SpamFilter, SpamScorer};
+pub use self::spawner::{DefaultSpawner, Spawner};
+pub use self::tasks::{TaskHandle, TaskInfo, TaskState};
 
 // Not public API.
 #[doc(hidden)]
@@ -0,0 +1,253 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Metrics
+//!
+//! Latencies of the ingestion pipeline of the received events.
+//! Not recorded on `wasm32`, since `Instant` isn't available there.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Max number of samples kept for each stage
+const MAX_SAMPLES: usize = 1024;
+
+/// Number of stages
+const STAGES: usize = 6;
+
+/// Stage of the ingestion pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IngestionStage {
+    /// From the WebSocket receive to the composed event (deserialization, limits, filtering and database checks)
+    Parse,
+    /// Signature verification
+    Verify,
+    /// Purge of the local events (only for the requests to vanish, NIP62)
+    Vanish,
+    /// Database save
+    Store,
+    /// Notification to the listeners
+    Notify,
+    /// From the WebSocket receive to notify
+    Total,
+}
+
+impl IngestionStage {
+    #[inline]
+    fn index(&self) -> usize {
+        match self {
+            Self::Parse => 0,
+            Self::Verify => 1,
+            Self::Vanish => 2,
+            Self::Store => 3,
+            Self::Notify => 4,
+            Self::Total => 5,
+        }
+    }
+}
+
+/// Latency percentiles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    /// Number of samples
+    pub samples: usize,
+    /// 50th percentile (median)
+    pub p50: Duration,
+    /// 90th percentile
+    pub p90: Duration,
+    /// 99th percentile
+    pub p99: Duration,
+    /// Max latency
+    pub max: Duration,
+}
+
+impl LatencyPercentiles {
+    fn from_samples(samples: &VecDeque<Duration>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+
+        Some(Self {
+            samples: sorted.len(),
+            p50: percentile(&sorted, 50),
+            p90: percentile(&sorted, 90),
+            p99: percentile(&sorted, 99),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// Nearest-rank percentile of a sorted, not empty, slice
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank: usize = (p * sorted.len() + 99) / 100;
+    sorted[rank.saturating_sub(1)]
+}
+
+/// Ingestion metrics
+///
+/// Keep the latencies of the last events processed by the relays of the pool.
+/// Every stage has its own lock, so the relays recording different stages don't contend.
+#[derive(Debug, Default)]
+pub struct IngestionMetrics {
+    samples: [Mutex<VecDeque<Duration>>; STAGES],
+    saved_verifications: AtomicU64,
+}
+
+impl IngestionMetrics {
+    fn lock(&self, stage: IngestionStage) -> MutexGuard<'_, VecDeque<Duration>> {
+        // Recover from a poisoned lock: the samples are always in a consistent state
+        match self.samples[stage.index()].lock() {
+            Ok(samples) => samples,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    fn record(&self, stage: IngestionStage, latency: Duration) {
+        let mut samples = self.lock(stage);
+
+        if samples.len() >= MAX_SAMPLES {
+            samples.pop_front();
+        }
+
+        samples.push_back(latency);
+    }
+
+    /// Get the latency percentiles of a stage
+    ///
+    /// Return `None` if no event has been processed yet.
+    pub fn percentiles(&self, stage: IngestionStage) -> Option<LatencyPercentiles> {
+        LatencyPercentiles::from_samples(&self.lock(stage))
+    }
+
+    #[inline]
+    pub(crate) fn saved_verification(&self) {
+        self.saved_verifications.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Get the number of signature verifications skipped, since already done for the same event received from another relay
+    #[inline]
+    pub fn saved_verifications(&self) -> u64 {
+        self.saved_verifications.load(Ordering::SeqCst)
+    }
+
+    /// Clear the samples and the counters
+    pub fn reset(&self) {
+        self.saved_verifications.store(0, Ordering::SeqCst);
+
+        for samples in self.samples.iter() {
+            match samples.lock() {
+                Ok(mut samples) => samples.clear(),
+                Err(poisoned) => poisoned.into_inner().clear(),
+            }
+        }
+    }
+}
+
+/// Timer of an event going through the ingestion pipeline
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct EventTimer {
+    received_at: Instant,
+    last: Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EventTimer {
+    /// Start when the WebSocket message is received
+    #[inline]
+    pub fn start() -> Self {
+        let now: Instant = Instant::now();
+        Self {
+            received_at: now,
+            last: now,
+        }
+    }
+
+    /// Record the latency of the stage (since the previous one)
+    pub fn stage(&mut self, metrics: &IngestionMetrics, stage: IngestionStage) {
+        let now: Instant = Instant::now();
+        metrics.record(stage, now.saturating_duration_since(self.last));
+        self.last = now;
+    }
+
+    /// Record the total latency
+    pub fn finish(self, metrics: &IngestionMetrics) -> Duration {
+        let total: Duration = self.received_at.elapsed();
+        metrics.record(IngestionStage::Total, total);
+        total
+    }
+}
+
+/// Timer of an event going through the ingestion pipeline (no-op)
+#[cfg(target_arch = "wasm32")]
+pub(crate) struct EventTimer;
+
+#[cfg(target_arch = "wasm32")]
+impl EventTimer {
+    #[inline]
+    pub fn start() -> Self {
+        Self
+    }
+
+    #[inline]
+    pub fn stage(&mut self, _metrics: &IngestionMetrics, _stage: IngestionStage) {}
+
+    #[inline]
+    pub fn finish(self, _metrics: &IngestionMetrics) -> Duration {
+        Duration::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let metrics = IngestionMetrics::default();
+        assert!(metrics.percentiles(IngestionStage::Verify).is_none());
+
+        for ms in (1..=100).rev() {
+            metrics.record(IngestionStage::Verify, Duration::from_millis(ms));
+        }
+
+        let p = metrics.percentiles(IngestionStage::Verify).unwrap();
+        assert_eq!(p.samples, 100);
+        assert_eq!(p.p50, Duration::from_millis(50));
+        assert_eq!(p.p90, Duration::from_millis(90));
+        assert_eq!(p.p99, Duration::from_millis(99));
+        assert_eq!(p.max, Duration::from_millis(100));
+
+        // Only the last samples are kept
+        for _ in 0..MAX_SAMPLES {
+            metrics.record(IngestionStage::Verify, Duration::from_millis(1));
+        }
+        let p = metrics.percentiles(IngestionStage::Verify).unwrap();
+        assert_eq!(p.samples, MAX_SAMPLES);
+        assert_eq!(p.max, Duration::from_millis(1));
+
+        // The stages are independent
+        assert!(metrics.percentiles(IngestionStage::Store).is_none());
+        metrics.record(IngestionStage::Store, Duration::from_millis(5));
+        assert_eq!(
+            metrics.percentiles(IngestionStage::Store).unwrap().max,
+            Duration::from_millis(5)
+        );
+        assert_eq!(
+            metrics.percentiles(IngestionStage::Verify).unwrap().samples,
+            MAX_SAMPLES
+        );
+
+        metrics.reset();
+        assert!(metrics.percentiles(IngestionStage::Verify).is_none());
+        assert!(metrics.percentiles(IngestionStage::Store).is_none());
+    }
+}
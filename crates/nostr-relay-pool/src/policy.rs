@@ -0,0 +1,140 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Policies
+
+use std::fmt;
+use std::sync::Arc;
+
+use nostr::{Event, RelayUrl};
+use nostr_database::DatabaseEventStatus;
+
+/// Admission status
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Admission {
+    /// The event is accepted
+    Accept,
+    /// The event is rejected
+    Reject {
+        /// Optional reason, used for logging
+        reason: Option<String>,
+    },
+}
+
+impl Admission {
+    /// Reject with reason
+    #[inline]
+    pub fn reject<S>(reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Reject {
+            reason: Some(reason.into()),
+        }
+    }
+
+    /// Check if is accepted
+    #[inline]
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accept)
+    }
+}
+
+/// Admission policy for incoming events
+///
+/// Consulted for every event received from relays, before verifying the signature,
+/// saving it into the database and sending the notification.
+///
+/// Useful to implement custom spam filters, POW requirements, web-of-trust checks or kind allowlists.
+pub trait AdmitPolicy: fmt::Debug + Send + Sync {
+    /// Check if the [`Event`] received from the relay should be admitted
+    fn admit(&self, event: &Event, relay_url: &RelayUrl) -> Admission;
+
+    /// Called for the admitted new [`Event`], after the signature verification
+    ///
+    /// Stateful policies must record the events here and not in [`AdmitPolicy::admit`],
+    /// to not let the forged events alter their state.
+    fn admitted(&self, _event: &Event, _relay_url: &RelayUrl) {}
+}
+
+#[doc(hidden)]
+pub trait IntoAdmitPolicy {
+    fn into_admit_policy(self) -> Arc<dyn AdmitPolicy>;
+}
+
+impl IntoAdmitPolicy for Arc<dyn AdmitPolicy> {
+    fn into_admit_policy(self) -> Arc<dyn AdmitPolicy> {
+        self
+    }
+}
+
+impl<T> IntoAdmitPolicy for T
+where
+    T: AdmitPolicy + Sized + 'static,
+{
+    fn into_admit_policy(self) -> Arc<dyn AdmitPolicy> {
+        Arc::new(self)
+    }
+}
+
+impl<T> IntoAdmitPolicy for Arc<T>
+where
+    T: AdmitPolicy + 'static,
+{
+    fn into_admit_policy(self) -> Arc<dyn AdmitPolicy> {
+        self
+    }
+}
+
+/// Deduplication result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Deduplication {
+    /// Use the default deduplication (by event ID)
+    Default,
+    /// The event is a duplicate: don't save it and don't send the notification
+    Duplicate,
+    /// The event is new: save it and send the notification, also if already seen
+    New,
+}
+
+/// Deduplication policy for incoming events
+///
+/// Allow to replace the default deduplication (by event ID) with a custom merge/dedup logic
+/// (i.e. for app-specific CRDT events).
+/// Return [`Deduplication::Default`] for the kinds not handled by the policy.
+pub trait DedupPolicy: fmt::Debug + Send + Sync {
+    /// Check if the [`Event`] is a duplicate
+    ///
+    /// The `status` is the status of the event ID in the database.
+    fn deduplicate(&self, event: &Event, status: DatabaseEventStatus) -> Deduplication;
+}
+
+#[doc(hidden)]
+pub trait IntoDedupPolicy {
+    fn into_dedup_policy(self) -> Arc<dyn DedupPolicy>;
+}
+
+impl IntoDedupPolicy for Arc<dyn DedupPolicy> {
+    fn into_dedup_policy(self) -> Arc<dyn DedupPolicy> {
+        self
+    }
+}
+
+impl<T> IntoDedupPolicy for T
+where
+    T: DedupPolicy + Sized + 'static,
+{
+    fn into_dedup_policy(self) -> Arc<dyn DedupPolicy> {
+        Arc::new(self)
+    }
+}
+
+impl<T> IntoDedupPolicy for Arc<T>
+where
+    T: DedupPolicy + 'static,
+{
+    fn into_dedup_policy(self) -> Arc<dyn DedupPolicy> {
+        self
+    }
+}
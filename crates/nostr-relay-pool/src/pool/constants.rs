@@ -4,7 +4,13 @@
 
 //! Constants
 
+use std::time::Duration;
+
 pub(super) const MAX_CONNECTING_CHUNK: usize = 100;
+pub(super) const RELAY_TERMINATION_TIMEOUT: Duration = Duration::from_secs(10);
+pub(super) const TASKS_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+/// Max number of events waiting for the event handlers
+pub(super) const HANDLERS_QUEUE_SIZE: usize = 1024;
 
 /// Relay Pool default notification channel size
 pub const DEFAULT_NOTIFICATION_CHANNEL_SIZE: usize = 4096;
@@ -5,7 +5,7 @@
 use std::convert::Infallible;
 use std::fmt;
 
-use nostr::types::url;
+use nostr::types::{preset, url};
 use nostr_database::DatabaseError;
 
 use crate::relay;
@@ -17,6 +17,8 @@ pub enum Error {
     RelayUrl(url::Error),
     /// Relay error
     Relay(relay::Error),
+    /// Filter preset error
+    FilterPreset(preset::Error),
     /// Database error
     Database(DatabaseError),
     /// Infallible
@@ -38,8 +40,21 @@ pub enum Error {
     NegentropyReconciliationFailed,
     /// Relay not found
     RelayNotFound,
+    /// Relay set not found
+    RelaySetNotFound(String),
+    /// Relay URL is blacklisted
+    RelayBlacklisted,
     /// Relay Pool is shutdown
     Shutdown,
+    /// Event vetoed by an outgoing middleware
+    EventVetoed {
+        /// Reason
+        reason: String,
+    },
+    /// Impossible to re-sign the event modified by the outgoing middlewares
+    Middleware(String),
+    /// Invalid pool snapshot
+    InvalidSnapshot(String),
 }
 
 impl std::error::Error for Error {}
@@ -49,6 +64,7 @@ impl fmt::Display for Error {
         match self {
             Self::RelayUrl(e) => write!(f, "{e}"),
             Self::Relay(e) => write!(f, "{e}"),
+            Self::FilterPreset(e) => write!(f, "{e}"),
             Self::Database(e) => write!(f, "{e}"),
             Self::Infallible(e) => write!(f, "{e}"),
             Self::Handler(e) => write!(f, "{e}"),
@@ -58,7 +74,12 @@ impl fmt::Display for Error {
             Self::Failed => write!(f, "completed without success"), // TODO: better error?
             Self::NegentropyReconciliationFailed => write!(f, "negentropy reconciliation failed"),
             Self::RelayNotFound => write!(f, "relay not found"),
+            Self::RelaySetNotFound(name) => write!(f, "relay set '{name}' not found"),
+            Self::RelayBlacklisted => write!(f, "relay is blacklisted"),
             Self::Shutdown => write!(f, "relay pool is shutdown"),
+            Self::EventVetoed { reason } => write!(f, "event vetoed: {reason}"),
+            Self::Middleware(e) => write!(f, "middleware: {e}"),
+            Self::InvalidSnapshot(e) => write!(f, "invalid snapshot: {e}"),
         }
     }
 }
@@ -75,6 +96,12 @@ impl From<relay::Error> for Error {
     }
 }
 
+impl From<preset::Error> for Error {
+    fn from(e: preset::Error) -> Self {
+        Self::FilterPreset(e)
+    }
+}
+
 impl From<DatabaseError> for Error {
     fn from(e: DatabaseError) -> Self {
         Self::Database(e)
@@ -0,0 +1,146 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Event handlers
+
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+
+use nostr::{Event, EventId, Kind, RelayUrl, SubscriptionId};
+
+use crate::transport::BoxedFuture;
+
+/// Max number of dispatched event IDs kept to skip the duplicates
+const MAX_DISPATCHED_IDS: usize = 10_000;
+
+/// Event handler
+///
+/// Register it with [`RelayPool::register_handler`](super::RelayPool::register_handler)
+/// to receive the events of specific kinds, without matching the pool notifications.
+///
+/// Every event is dispatched once, also if received from many relays or subscriptions.
+/// The handlers of an event are called concurrently, so a slow handler doesn't block the others,
+/// but the events are dispatched one at a time by a single worker: a slow handler delays the next events.
+pub trait EventHandler: fmt::Debug + Send + Sync {
+    /// Handle an [`Event`] received from the relay
+    fn handle<'a>(
+        &'a self,
+        relay_url: &'a RelayUrl,
+        subscription_id: &'a SubscriptionId,
+        event: &'a Event,
+    ) -> BoxedFuture<'a, ()>;
+}
+
+#[derive(Debug, Default)]
+pub(super) struct HandlerRegistry {
+    handlers: Vec<(HashSet<Kind>, Arc<dyn EventHandler>)>,
+}
+
+impl HandlerRegistry {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
+    #[inline]
+    pub fn register(&mut self, kinds: HashSet<Kind>, handler: Arc<dyn EventHandler>) {
+        self.handlers.push((kinds, handler));
+    }
+
+    /// Get the handlers interested in the kind
+    ///
+    /// The handlers registered without kinds match every kind.
+    pub fn matching(&self, kind: &Kind) -> Vec<Arc<dyn EventHandler>> {
+        self.handlers
+            .iter()
+            .filter(|(kinds, ..)| kinds.is_empty() || kinds.contains(kind))
+            .map(|(.., handler)| handler.clone())
+            .collect()
+    }
+}
+
+/// Recently dispatched events
+#[derive(Debug, Default)]
+pub(super) struct DispatchedEvents {
+    ids: HashSet<EventId>,
+    order: VecDeque<EventId>,
+}
+
+impl DispatchedEvents {
+    /// Mark the event as dispatched
+    ///
+    /// Return `false` if it has been already dispatched.
+    pub fn insert(&mut self, id: EventId) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+
+        self.order.push_back(id);
+
+        // Forget the oldest
+        if self.order.len() > MAX_DISPATCHED_IDS {
+            if let Some(id) = self.order.pop_front() {
+                self.ids.remove(&id);
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct NoopHandler;
+
+    impl EventHandler for NoopHandler {
+        fn handle<'a>(
+            &'a self,
+            _relay_url: &'a RelayUrl,
+            _subscription_id: &'a SubscriptionId,
+            _event: &'a Event,
+        ) -> BoxedFuture<'a, ()> {
+            Box::pin(async {})
+        }
+    }
+
+    #[test]
+    fn test_handler_registry() {
+        let mut registry = HandlerRegistry::default();
+        assert!(registry.is_empty());
+
+        registry.register(HashSet::from([Kind::ZapReceipt]), Arc::new(NoopHandler));
+        registry.register(
+            HashSet::from([Kind::ZapReceipt, Kind::GiftWrap]),
+            Arc::new(NoopHandler),
+        );
+        registry.register(HashSet::new(), Arc::new(NoopHandler));
+
+        assert_eq!(registry.matching(&Kind::ZapReceipt).len(), 3);
+        assert_eq!(registry.matching(&Kind::GiftWrap).len(), 2);
+        assert_eq!(registry.matching(&Kind::TextNote).len(), 1);
+    }
+
+    #[test]
+    fn test_dispatched_events() {
+        let mut dispatched = DispatchedEvents::default();
+
+        let first = EventId::all_zeros();
+        assert!(dispatched.insert(first));
+        assert!(!dispatched.insert(first));
+
+        for i in 1..=MAX_DISPATCHED_IDS as u64 {
+            let mut bytes = [0u8; 32];
+            bytes[..8].copy_from_slice(&i.to_be_bytes());
+            assert!(dispatched.insert(EventId::from_byte_array(bytes)));
+        }
+
+        // The oldest has been forgotten
+        assert_eq!(dispatched.ids.len(), MAX_DISPATCHED_IDS);
+        assert!(dispatched.insert(first));
+    }
+}
@@ -4,44 +4,80 @@
 
 //! Relay Pool
 
-use std::cmp;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use std::{cmp, mem};
 
 use async_utility::futures_util::{future, StreamExt};
-use async_utility::task;
+use async_utility::time;
 use atomic_destructor::AtomicDestroyer;
 use nostr_database::prelude::*;
 use tokio::sync::{broadcast, mpsc, Mutex, RwLock, RwLockReadGuard};
 
-use super::constants::MAX_CONNECTING_CHUNK;
-use super::options::RelayPoolOptions;
-use super::{Error, Output, RelayPoolNotification};
+use super::constants::{
+    HANDLERS_QUEUE_SIZE, MAX_CONNECTING_CHUNK, RELAY_TERMINATION_TIMEOUT, TASKS_SHUTDOWN_TIMEOUT,
+};
+use super::handler::{DispatchedEvents, EventHandler, HandlerRegistry};
+use super::middleware::OutgoingMiddleware;
+use super::multiplex::{self, LogicalSubscription, Multiplexer, RelayReqs};
+use super::optimizer::{self, RelayContribution, RelayOptimizerOptions, RelayScore, RelaySwap};
+use super::options::{RelayPoolOptions, StreamOrder};
+use super::replay::ReplaySpeed;
+use super::sets::RelaySet;
+use super::snapshot::{RelayPoolSnapshot, RelaySnapshot, SubscriptionSnapshot};
+use super::{Error, NotificationSender, Output, RelayPoolNotification, SequencedNotification};
+use crate::relay::capture::{CaptureDirection, CapturedFrame};
 use crate::relay::options::{FilterOptions, RelayOptions, SyncOptions};
-use crate::relay::{FlagCheck, Reconciliation, Relay};
+use crate::relay::{
+    Bandwidth, FlagCheck, Reconciliation, Relay, RelayConnectionStats, RelayStatus,
+};
 use crate::shared::SharedState;
 use crate::stream::ReceiverStream;
 use crate::{RelayServiceFlags, SubscribeOptions};
 
 type Relays = HashMap<RelayUrl, Relay>;
 
+#[derive(Debug, Default)]
+struct Presets {
+    presets: FilterPresets,
+    values: BTreeMap<String, PresetValue>,
+    /// Subscriptions created from a preset
+    subscriptions: HashMap<SubscriptionId, (String, SubscribeOptions)>,
+}
+
+/// Event waiting for the handlers
+struct HandlerJob {
+    relay_url: RelayUrl,
+    subscription_id: SubscriptionId,
+    event: Arc<Event>,
+    handlers: Vec<Arc<dyn EventHandler>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct InnerRelayPool {
     pub(super) state: SharedState,
     relays: Arc<RwLock<Relays>>,
-    notification_sender: broadcast::Sender<RelayPoolNotification>, // TODO: move to shared state?
+    notification_sender: NotificationSender, // TODO: move to shared state?
     subscriptions: Arc<RwLock<HashMap<SubscriptionId, Vec<Filter>>>>,
+    presets: Arc<RwLock<Presets>>,
+    relay_sets: Arc<RwLock<HashMap<RelaySet, HashSet<RelayUrl>>>>,
+    multiplexer: Arc<RwLock<Multiplexer>>,
+    /// Serialize the updates of the multiplexed REQs
+    multiplexer_update: Arc<Mutex<()>>,
+    handlers: Arc<RwLock<HandlerRegistry>>,
     opts: RelayPoolOptions,
+    /// Relays paused with [`InnerRelayPool::pause`]
+    paused: Arc<Mutex<HashSet<RelayUrl>>>,
     shutdown: Arc<AtomicBool>,
 }
 
 impl AtomicDestroyer for InnerRelayPool {
     fn on_destroy(&self) {
         let pool = self.clone();
-        task::spawn(async move {
-            match pool.shutdown().await {
+        self.state.spawn("pool destroyer", async move {
+            match pool.terminate().await {
                 Ok(()) => tracing::debug!("Relay pool destroyed."),
                 Err(e) => tracing::error!(error = %e, "Impossible to destroy pool."),
             }
@@ -51,35 +87,59 @@ impl AtomicDestroyer for InnerRelayPool {
 
 impl InnerRelayPool {
     pub fn new(opts: RelayPoolOptions, state: SharedState) -> Self {
-        let (notification_sender, _) = broadcast::channel(opts.notification_channel_size);
+        let notification_sender = NotificationSender::new(opts.notification_channel_size);
+
+        // Share the retry policy with the relays
+        let state: SharedState = match opts.retry_policy {
+            Some(policy) => state.with_retry_policy(policy),
+            None => state,
+        };
 
         Self {
             state,
             relays: Arc::new(RwLock::new(HashMap::new())),
             notification_sender,
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            presets: Arc::new(RwLock::new(Presets::default())),
+            relay_sets: Arc::new(RwLock::new(HashMap::new())),
+            multiplexer: Arc::new(RwLock::new(Multiplexer::default())),
+            multiplexer_update: Arc::new(Mutex::new(())),
+            handlers: Arc::new(RwLock::new(HandlerRegistry::default())),
             opts,
+            paused: Arc::new(Mutex::new(HashSet::new())),
             shutdown: Arc::new(AtomicBool::new(false)),
         }
     }
 
     pub async fn shutdown(&self) -> Result<(), Error> {
+        self.terminate().await?;
+
+        // Wait for the background tasks
+        self.state.join_tasks(TASKS_SHUTDOWN_TIMEOUT).await;
+
+        Ok(())
+    }
+
+    /// Shutdown without waiting for the background tasks
+    ///
+    /// Used on drop, since the destroyer is itself a background task.
+    async fn terminate(&self) -> Result<(), Error> {
         // Disconnect and force remove all relays
         self.remove_all_relays(true).await?;
 
         // Send shutdown notification
-        let _ = self
-            .notification_sender
+        self.notification_sender
             .send(RelayPoolNotification::Shutdown);
 
-        // Mark as shutdown
+        // Mark as shutdown and stop the long-lived tasks
         self.shutdown.store(true, Ordering::SeqCst);
+        self.state.signal_shutdown();
 
         Ok(())
     }
 
     #[inline]
-    pub(super) fn is_shutdown(&self) -> bool {
+    pub fn is_shutdown(&self) -> bool {
         self.shutdown.load(Ordering::SeqCst)
     }
 
@@ -87,6 +147,10 @@ impl InnerRelayPool {
         self.notification_sender.subscribe()
     }
 
+    pub fn sequenced_notifications(&self) -> broadcast::Receiver<SequencedNotification> {
+        self.notification_sender.subscribe_sequenced()
+    }
+
     pub async fn all_relays(&self) -> Relays {
         let relays = self.relays.read().await;
         relays.clone()
@@ -120,6 +184,230 @@ impl InnerRelayPool {
             .collect()
     }
 
+    pub async fn bandwidth(&self) -> Bandwidth {
+        let relays = self.relays.read().await;
+        let mut bandwidth: Bandwidth = Bandwidth::default();
+        for relay in relays.values() {
+            bandwidth += relay.stats().bandwidth();
+        }
+        bandwidth
+    }
+
+    pub async fn subscription_bandwidth(&self, id: &SubscriptionId) -> Bandwidth {
+        let relays = self.relays.read().await;
+        let mut bandwidth: Bandwidth = Bandwidth::default();
+        for relay in relays.values() {
+            if let Some(b) = relay.stats().subscription_bandwidth(id) {
+                bandwidth += b;
+            }
+        }
+        bandwidth
+    }
+
+    pub async fn contribution_report(&self) -> Vec<RelayContribution> {
+        let relays = self.relays.read().await;
+        let mut report: Vec<RelayContribution> = relays
+            .iter()
+            .map(|(url, relay)| RelayContribution {
+                url: url.clone(),
+                unique: relay.stats().unique_events(),
+                duplicates: relay.stats().duplicate_events(),
+            })
+            .collect();
+        report.sort_by(|a, b| b.duplicate_ratio().total_cmp(&a.duplicate_ratio()));
+        report
+    }
+
+    pub async fn relay_scores(&self, grace_period: Duration) -> Vec<RelayScore> {
+        let relays: Vec<(RelayUrl, RelayConnectionStats)> = self
+            .relays()
+            .await
+            .into_iter()
+            .map(|(url, relay)| (url, relay.stats().clone()))
+            .collect();
+        let now: Timestamp = self.state.time_provider().now();
+        optimizer::score_relays(relays, grace_period, now)
+    }
+
+    pub async fn suggest_relay_swaps(&self, opts: &RelayOptimizerOptions) -> Vec<RelaySwap> {
+        let scores: Vec<RelayScore> = self.relay_scores(opts.grace_period).await;
+        let in_pool: HashSet<RelayUrl> = self.all_relays().await.into_keys().collect();
+        optimizer::select_swaps(&scores, &in_pool, opts)
+    }
+
+    pub async fn optimize_relays(
+        &self,
+        opts: &RelayOptimizerOptions,
+    ) -> Result<Vec<RelaySwap>, Error> {
+        let swaps: Vec<RelaySwap> = self.suggest_relay_swaps(opts).await;
+        let mut applied: Vec<RelaySwap> = Vec::with_capacity(swaps.len());
+
+        for swap in swaps.into_iter() {
+            // Add and connect the candidate first, so a failure leaves the pool unchanged
+            if let Err(e) = self.add_and_connect_candidate(&swap.add, opts).await {
+                tracing::warn!(
+                    remove = %swap.remove,
+                    add = %swap.add,
+                    error = %e,
+                    "Impossible to swap relay, skipping."
+                );
+                continue;
+            }
+
+            if let Err(e) = self.remove_relay(&swap.remove, false).await {
+                tracing::warn!(
+                    url = %swap.remove,
+                    error = %e,
+                    "Impossible to remove the swapped relay."
+                );
+            }
+
+            tracing::info!(
+                removed = %swap.remove,
+                added = %swap.add,
+                score = %swap.score,
+                "Relay swapped."
+            );
+
+            applied.push(swap);
+        }
+
+        Ok(applied)
+    }
+
+    async fn add_and_connect_candidate(
+        &self,
+        url: &RelayUrl,
+        opts: &RelayOptimizerOptions,
+    ) -> Result<(), Error> {
+        self.add_relay(url, true, opts.relay_opts.clone()).await?;
+
+        if let Err(e) = self.connect_relay(url, None).await {
+            // Roll back
+            let _ = self.remove_relay(url, true).await;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub fn spawn_relay_optimizer(&self, opts: RelayOptimizerOptions) {
+        let this = self.clone();
+        let future = self.state.until_shutdown(async move {
+            loop {
+                time::sleep(opts.interval).await;
+
+                if this.is_shutdown() {
+                    break;
+                }
+
+                if opts.auto_swap {
+                    if let Err(e) = this.optimize_relays(&opts).await {
+                        tracing::error!(error = %e, "Impossible to optimize relays.");
+                    }
+                } else {
+                    for swap in this.suggest_relay_swaps(&opts).await.into_iter() {
+                        tracing::info!(
+                            remove = %swap.remove,
+                            add = %swap.add,
+                            score = %swap.score,
+                            "Suggested relay swap."
+                        );
+                    }
+                }
+            }
+
+            tracing::debug!("Exited from relay optimizer.");
+        });
+        self.state.spawn("relay optimizer", future);
+    }
+
+    pub async fn register_handler(&self, kinds: HashSet<Kind>, handler: Arc<dyn EventHandler>) {
+        let mut handlers = self.handlers.write().await;
+
+        // Spawn the dispatcher with the first handler
+        if handlers.is_empty() {
+            self.spawn_handlers_dispatcher();
+        }
+
+        handlers.register(kinds, handler);
+    }
+
+    #[inline]
+    pub async fn add_outgoing_middleware(&self, middleware: Arc<dyn OutgoingMiddleware>) {
+        self.state.add_outgoing_middleware(middleware).await;
+    }
+
+    fn spawn_handlers_dispatcher(&self) {
+        // Subscribe before spawning, to not miss the events received in the meanwhile
+        let mut notifications = self.notification_sender.subscribe();
+        let handlers = self.handlers.clone();
+
+        // Bounded queue, to not spawn a task for every event
+        let (tx, mut rx) = mpsc::channel::<HandlerJob>(HANDLERS_QUEUE_SIZE);
+
+        self.state.spawn("handlers worker", async move {
+            while let Some(job) = rx.recv().await {
+                // Handle concurrently
+                let futures = job
+                    .handlers
+                    .iter()
+                    .map(|h| h.handle(&job.relay_url, &job.subscription_id, &job.event));
+                future::join_all(futures).await;
+            }
+
+            tracing::debug!("Exited from handlers worker.");
+        });
+
+        self.state.spawn("handlers dispatcher", async move {
+            let mut dispatched: DispatchedEvents = DispatchedEvents::default();
+
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotification::Event {
+                        relay_url,
+                        subscription_id,
+                        event,
+                    }) => {
+                        // Dispatch once, also if notified for many subscriptions (i.e. multiplexed)
+                        if !dispatched.insert(event.id) {
+                            continue;
+                        }
+
+                        let matching: Vec<Arc<dyn EventHandler>> = {
+                            let handlers = handlers.read().await;
+                            handlers.matching(&event.kind)
+                        };
+
+                        if matching.is_empty() {
+                            continue;
+                        }
+
+                        let job: HandlerJob = HandlerJob {
+                            relay_url,
+                            subscription_id,
+                            event,
+                            handlers: matching,
+                        };
+
+                        // Wait for the worker if the queue is full
+                        if tx.send(job).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(RelayPoolNotification::Shutdown) => break,
+                    Ok(..) => {}
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        tracing::warn!("Handlers dispatcher lagged: {n} notifications skipped.")
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+
+            tracing::debug!("Exited from handlers dispatcher.");
+        });
+    }
+
     /// Get relays with `READ` or `WRITE` relays
     async fn relay_urls(&self) -> Vec<RelayUrl> {
         let relays = self.relays.read().await;
@@ -183,11 +471,186 @@ impl InnerRelayPool {
     pub(crate) async fn remove_subscription(&self, id: &SubscriptionId) {
         let mut subscriptions = self.subscriptions.write().await;
         subscriptions.remove(id);
+
+        let mut presets = self.presets.write().await;
+        presets.subscriptions.remove(id);
     }
 
     pub(crate) async fn remove_all_subscriptions(&self) {
         let mut subscriptions = self.subscriptions.write().await;
         subscriptions.clear();
+
+        let mut presets = self.presets.write().await;
+        presets.subscriptions.clear();
+    }
+
+    pub async fn set_relay_set<I, U>(&self, set: RelaySet, urls: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let urls: HashSet<RelayUrl> = urls
+            .into_iter()
+            .map(|u| u.try_into_url())
+            .collect::<Result<_, _>>()?;
+
+        let mut relay_sets = self.relay_sets.write().await;
+        relay_sets.insert(set, urls);
+        Ok(())
+    }
+
+    pub async fn relay_set(&self, set: &RelaySet) -> Option<HashSet<RelayUrl>> {
+        let relay_sets = self.relay_sets.read().await;
+        relay_sets.get(set).cloned()
+    }
+
+    pub async fn relay_sets(&self) -> HashMap<RelaySet, HashSet<RelayUrl>> {
+        let relay_sets = self.relay_sets.read().await;
+        relay_sets.clone()
+    }
+
+    pub async fn remove_relay_set(&self, set: &RelaySet) -> Option<HashSet<RelayUrl>> {
+        let mut relay_sets = self.relay_sets.write().await;
+        relay_sets.remove(set)
+    }
+
+    /// Get the URLs of a relay set
+    ///
+    /// Error if the set doesn't exist.
+    async fn relay_set_urls(&self, set: &RelaySet) -> Result<HashSet<RelayUrl>, Error> {
+        self.relay_set(set)
+            .await
+            .ok_or_else(|| Error::RelaySetNotFound(set.to_string()))
+    }
+
+    pub async fn send_event_to_set(
+        &self,
+        set: &RelaySet,
+        event: Event,
+    ) -> Result<Output<EventId>, Error> {
+        let urls: HashSet<RelayUrl> = self.relay_set_urls(set).await?;
+        self.send_event_to(urls, event).await
+    }
+
+    pub async fn subscribe_to_set(
+        &self,
+        set: &RelaySet,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        let urls: HashSet<RelayUrl> = self.relay_set_urls(set).await?;
+        self.subscribe_to(urls, filters, opts).await
+    }
+
+    pub async fn fetch_events_from_set(
+        &self,
+        set: &RelaySet,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<Events, Error> {
+        let urls: HashSet<RelayUrl> = self.relay_set_urls(set).await?;
+        self.fetch_events_from(urls, filters, timeout, opts).await
+    }
+
+    pub async fn register_filter_preset<S, I>(&self, name: S, templates: I)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = FilterTemplate>,
+    {
+        let mut presets = self.presets.write().await;
+        presets.presets.register(name, templates);
+    }
+
+    pub async fn subscribe_preset(
+        &self,
+        name: &str,
+        opts: SubscribeOptions,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        let filters: Vec<Filter> = {
+            let presets = self.presets.read().await;
+            presets.presets.instantiate(name, &presets.values)?
+        };
+
+        let output: Output<SubscriptionId> = self.subscribe(filters, opts).await?;
+
+        // Auto-closing subscriptions aren't re-subscribed
+        if !opts.is_auto_closing() {
+            let mut presets = self.presets.write().await;
+            presets
+                .subscriptions
+                .insert(output.val.clone(), (name.to_string(), opts));
+        }
+
+        Ok(output)
+    }
+
+    pub async fn set_preset_value<K, V>(&self, key: K, value: V) -> Result<(), Error>
+    where
+        K: Into<String>,
+        V: Into<PresetValue>,
+    {
+        let key: String = key.into();
+        let value: PresetValue = value.into();
+
+        // Instantiate the affected subscriptions with the new value, then update it
+        let (previous, targets) = {
+            let mut presets = self.presets.write().await;
+
+            if presets.values.get(&key) == Some(&value) {
+                return Ok(());
+            }
+
+            let mut values: BTreeMap<String, PresetValue> = presets.values.clone();
+            values.insert(key.clone(), value.clone());
+
+            let mut targets: Vec<(SubscriptionId, Vec<Filter>, SubscribeOptions)> = Vec::new();
+            for (id, (name, opts)) in presets.subscriptions.iter() {
+                if presets.presets.placeholders(name)?.contains(key.as_str()) {
+                    let filters: Vec<Filter> = presets.presets.instantiate(name, &values)?;
+                    targets.push((id.clone(), filters, *opts));
+                }
+            }
+
+            let previous: Option<PresetValue> = presets.values.insert(key.clone(), value);
+            (previous, targets)
+        };
+
+        // Re-subscribe (replace the REQs)
+        let mut done: Vec<(SubscriptionId, Vec<Filter>, SubscribeOptions)> = Vec::new();
+        for (id, filters, opts) in targets.into_iter() {
+            let old: Option<Vec<Filter>> = self.subscription(&id).await;
+
+            if let Err(e) = self.subscribe_with_id(id.clone(), filters, opts).await {
+                // Roll back: restore the previous value and the updated subscriptions
+                {
+                    let mut presets = self.presets.write().await;
+                    match previous {
+                        Some(previous) => presets.values.insert(key, previous),
+                        None => presets.values.remove(&key),
+                    };
+                }
+
+                if let Some(old) = old {
+                    done.push((id, old, opts));
+                }
+
+                for (id, old, opts) in done.into_iter() {
+                    if let Err(e) = self.subscribe_with_id(id.clone(), old, opts).await {
+                        tracing::error!(id = %id, error = %e, "Impossible to restore preset subscription.");
+                    }
+                }
+
+                return Err(e);
+            }
+
+            if let Some(old) = old {
+                done.push((id, old, opts));
+            }
+        }
+
+        Ok(())
     }
 
     pub async fn add_relay<U>(
@@ -208,6 +671,11 @@ impl InnerRelayPool {
             return Err(Error::Shutdown);
         }
 
+        // Check if the relay is blacklisted
+        if self.state.filtering().has_relay_url(&url).await {
+            return Err(Error::RelayBlacklisted);
+        }
+
         // Get relays
         let mut relays = self.relays.write().await;
 
@@ -237,6 +705,9 @@ impl InnerRelayPool {
             for (id, filters) in subscriptions.into_iter() {
                 relay.inner.update_subscription(id, filters, false).await;
             }
+
+            // Set the multiplexed REQs (sent at connection)
+            self.set_multiplexed_reqs(&relay).await;
         }
 
         // Insert relay into map
@@ -245,6 +716,158 @@ impl InnerRelayPool {
         Ok(true)
     }
 
+    /// Set the multiplexed REQs of a new relay (sent at connection)
+    async fn set_multiplexed_reqs(&self, relay: &Relay) {
+        if !relay.flags().has_read() {
+            return;
+        }
+
+        let mut multiplexer = self.multiplexer.write().await;
+        // The NIP11 document isn't available yet: the REQs are split at the next update
+        let reqs: Vec<Vec<Filter>> = multiplex::split_filters(multiplexer.merged_filters(), None);
+        let ids: Vec<SubscriptionId> = multiplexer.physical_ids(reqs.len());
+        for (id, filters) in ids.iter().zip(reqs.into_iter()) {
+            relay
+                .inner
+                .update_subscription(id.clone(), filters, false)
+                .await;
+        }
+        multiplexer.physical.insert(relay.url().clone(), ids);
+    }
+
+    pub async fn snapshot(&self) -> RelayPoolSnapshot {
+        let relays: Vec<Relay> = self.relays.read().await.values().cloned().collect();
+
+        let (multiplexed, physical) = {
+            let multiplexer = self.multiplexer.read().await;
+            let multiplexed: HashMap<SubscriptionId, Vec<Filter>> = multiplexer
+                .logical
+                .iter()
+                .map(|(id, logical)| (id.clone(), logical.filters.clone()))
+                .collect();
+            (multiplexed, multiplexer.physical.clone())
+        };
+
+        let mut snapshots: Vec<RelaySnapshot> = Vec::with_capacity(relays.len());
+        for relay in relays.into_iter() {
+            let stats: &RelayConnectionStats = relay.stats();
+
+            // The multiplexed REQs are composed again at restore
+            let multiplexed_reqs: Option<&Vec<SubscriptionId>> = physical.get(relay.url());
+            let subscriptions: HashMap<SubscriptionId, SubscriptionSnapshot> = relay
+                .inner
+                .subscriptions_with_last_event_at()
+                .await
+                .into_iter()
+                .filter(|(id, ..)| !multiplexed_reqs.map_or(false, |ids| ids.contains(id)))
+                .map(|(id, (filters, last_event_at, cursor))| {
+                    (
+                        id,
+                        SubscriptionSnapshot {
+                            filters,
+                            last_event_at,
+                            cursor,
+                        },
+                    )
+                })
+                .collect();
+
+            snapshots.push(RelaySnapshot {
+                url: relay.url().clone(),
+                flags: relay.flags().get(),
+                status: relay.status(),
+                banned_until: relay.inner.banned_until(),
+                #[cfg(feature = "nip11")]
+                paid: relay.is_paid(),
+                #[cfg(not(feature = "nip11"))]
+                paid: false,
+                attempts: stats.attempts(),
+                success: stats.success(),
+                connected_at: stats.connected_at(),
+                first_connection_at: stats.first_connection_timestamp(),
+                subscriptions,
+            });
+        }
+
+        RelayPoolSnapshot {
+            taken_at: self.state.time_provider().now(),
+            subscriptions: self.subscriptions().await,
+            multiplexed,
+            relays: snapshots,
+        }
+    }
+
+    pub async fn restore(
+        &self,
+        snapshot: RelayPoolSnapshot,
+        opts: RelayOptions,
+    ) -> Result<(), Error> {
+        // Restore pool subscriptions
+        for (id, filters) in snapshot.subscriptions.into_iter() {
+            self.save_subscription(id, filters).await;
+        }
+
+        // Restore the multiplexed subscriptions (the REQs are composed for the added relays)
+        let _update = self.multiplexer_update.lock().await;
+        if !snapshot.multiplexed.is_empty() {
+            let mut multiplexer = self.multiplexer.write().await;
+
+            if !multiplexer.demux {
+                multiplex::spawn_demultiplexer(
+                    &self.state,
+                    self.notifications(),
+                    self.notification_sender.clone(),
+                    Arc::downgrade(&self.multiplexer),
+                );
+                multiplexer.demux = true;
+            }
+
+            for (id, filters) in snapshot.multiplexed.into_iter() {
+                multiplexer
+                    .logical
+                    .entry(id)
+                    .or_insert_with(|| LogicalSubscription::new(filters));
+            }
+        }
+
+        // Restore relays
+        for relay in snapshot.relays.into_iter() {
+            let opts: RelayOptions = opts.clone().flags(relay.flags);
+
+            // Skip the already added relays
+            if !self.add_relay(relay.url.clone(), false, opts).await? {
+                continue;
+            }
+
+            let r: Relay = self.relay(&relay.url).await?;
+            r.stats().restore(
+                relay.attempts,
+                relay.success,
+                relay.connected_at,
+                relay.first_connection_at,
+            );
+
+            // Keep the relay banned until the end of the ban
+            if let (RelayStatus::Banned, Some(until)) = (relay.status, relay.banned_until) {
+                r.inner.ban_until(until);
+            }
+
+            // Don't ask again to pay an already paid relay
+            #[cfg(feature = "nip11")]
+            r.set_paid(relay.paid);
+
+            for (id, sub) in relay.subscriptions.into_iter() {
+                r.inner
+                    .restore_subscription(id, sub.filters, sub.last_event_at, sub.cursor)
+                    .await;
+            }
+
+            self.set_multiplexed_reqs(&r).await;
+        }
+
+        Ok(())
+    }
+
     pub async fn get_or_add_relay(
         &self,
         url: RelayUrl,
@@ -261,12 +884,15 @@ impl InnerRelayPool {
         }
     }
 
-    async fn internal_remove_relay(
+    /// Remove relay from the map
+    ///
+    /// Return the removed relay, if any, to allow the caller to terminate it after releasing the lock.
+    fn internal_remove_relay(
         &self,
         relays: &mut Relays,
         url: RelayUrl,
         force: bool,
-    ) -> Result<(), Error> {
+    ) -> Result<Option<Relay>, Error> {
         // Remove relay
         let relay = relays.remove(&url).ok_or(Error::RelayNotFound)?;
 
@@ -283,14 +909,16 @@ impl InnerRelayPool {
 
                 // Re-insert
                 relays.insert(url, relay);
-                return Ok(());
+                return Ok(None);
             }
         }
 
-        // Disconnect
-        relay.disconnect()?;
+        // The relay no longer contributes to the clock skew
+        if let Some(skew) = self.state.clock_skew() {
+            skew.remove_relay(&url);
+        }
 
-        Ok(())
+        Ok(Some(relay))
     }
 
     pub async fn remove_relay<U>(&self, url: U, force: bool) -> Result<(), Error>
@@ -305,7 +933,17 @@ impl InnerRelayPool {
         let mut relays = self.relays.write().await;
 
         // Remove
-        self.internal_remove_relay(&mut relays, url, force).await
+        let relay: Option<Relay> = self.internal_remove_relay(&mut relays, url, force)?;
+
+        // Drop lock
+        drop(relays);
+
+        // Terminate relay and wait for the connection task to exit
+        if let Some(relay) = relay {
+            terminate_removed_relay(&relay).await;
+        }
+
+        Ok(())
     }
 
     pub async fn remove_all_relays(&self, force: bool) -> Result<(), Error> {
@@ -316,10 +954,20 @@ impl InnerRelayPool {
         let urls: Vec<RelayUrl> = relays.keys().cloned().collect();
 
         // Iter urls and remove relays
+        let mut removed: Vec<Relay> = Vec::with_capacity(urls.len());
         for url in urls.into_iter() {
-            self.internal_remove_relay(&mut relays, url, force).await?;
+            if let Some(relay) = self.internal_remove_relay(&mut relays, url, force)? {
+                removed.push(relay);
+            }
         }
 
+        // Drop lock
+        drop(relays);
+
+        // Terminate relays and wait for the connection tasks to exit
+        let futures = removed.iter().map(terminate_removed_relay);
+        future::join_all(futures).await;
+
         Ok(())
     }
 
@@ -335,7 +983,7 @@ impl InnerRelayPool {
     pub async fn batch_msg_to<I, U>(
         &self,
         urls: I,
-        msgs: Vec<ClientMessage>,
+        mut msgs: Vec<ClientMessage>,
     ) -> Result<Output<()>, Error>
     where
         I: IntoIterator<Item = U>,
@@ -353,6 +1001,17 @@ impl InnerRelayPool {
             return Err(Error::NoRelaysSpecified);
         }
 
+        // Apply outgoing middlewares
+        for msg in msgs.iter_mut() {
+            if let ClientMessage::Event(event) = msg {
+                let processed: Event = self
+                    .state
+                    .apply_outgoing_middlewares(event.as_ref().clone())
+                    .await?;
+                *event = Box::new(processed);
+            }
+        }
+
         // Lock with read shared access
         let relays = self.relays.read().await;
 
@@ -369,6 +1028,9 @@ impl InnerRelayPool {
         for msg in msgs.iter() {
             if let ClientMessage::Event(event) = msg {
                 self.state.database().save_event(event).await?;
+
+                // Purge the events of the local user only when a relay accepts the request to vanish
+                self.state.add_pending_vanish_request(event);
             }
         }
 
@@ -417,6 +1079,9 @@ impl InnerRelayPool {
             return Err(Error::NoRelaysSpecified);
         }
 
+        // Apply outgoing middlewares
+        let event: Event = self.state.apply_outgoing_middlewares(event).await?;
+
         // Lock with read shared access
         let relays = self.relays.read().await;
 
@@ -429,11 +1094,23 @@ impl InnerRelayPool {
             return Err(Error::RelayNotFound);
         }
 
+        // Clone the target relays, to not hold the lock while sending
+        let targets: Vec<(RelayUrl, Relay)> = set
+            .into_iter()
+            .map(|url| {
+                let relay: Relay = self.internal_relay(&relays, &url)?.clone();
+                Ok((url, relay))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        // Drop lock
+        drop(relays);
+
         // Save event into database
         self.state.database().save_event(&event).await?;
 
-        let mut urls: Vec<RelayUrl> = Vec::with_capacity(set.len());
-        let mut futures = Vec::with_capacity(set.len());
+        let mut urls: Vec<RelayUrl> = Vec::with_capacity(targets.len());
+        let mut futures = Vec::with_capacity(targets.len());
         let mut output: Output<EventId> = Output {
             val: event.id,
             success: HashSet::new(),
@@ -441,10 +1118,9 @@ impl InnerRelayPool {
         };
 
         // Compose futures
-        for url in set.into_iter() {
-            let relay: &Relay = self.internal_relay(&relays, &url)?;
+        for (url, relay) in targets.iter() {
             let event: Event = event.clone();
-            urls.push(url);
+            urls.push(url.clone());
             futures.push(relay.send_event(event));
         }
 
@@ -468,6 +1144,9 @@ impl InnerRelayPool {
             return Err(Error::Failed);
         }
 
+        // Purge the events of the local user if the request to vanish has been accepted
+        self.state.purge_vanished(&event).await;
+
         Ok(output)
     }
 
@@ -613,6 +1292,146 @@ impl InnerRelayPool {
         Ok(output)
     }
 
+    pub async fn subscribe_multiplexed(
+        &self,
+        filters: Vec<Filter>,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        if self.is_shutdown() {
+            return Err(Error::Shutdown);
+        }
+
+        let id: SubscriptionId = SubscriptionId::generate();
+
+        let _update = self.multiplexer_update.lock().await;
+
+        {
+            let mut multiplexer = self.multiplexer.write().await;
+
+            // Spawn demultiplexer
+            if !multiplexer.demux {
+                multiplex::spawn_demultiplexer(
+                    &self.state,
+                    self.notifications(),
+                    self.notification_sender.clone(),
+                    Arc::downgrade(&self.multiplexer),
+                );
+                multiplexer.demux = true;
+            }
+
+            multiplexer
+                .logical
+                .insert(id.clone(), LogicalSubscription::new(filters));
+        }
+
+        let output: Output<()> = self.update_multiplexed(Some(&id)).await;
+
+        Ok(Output {
+            val: id,
+            success: output.success,
+            failed: output.failed,
+        })
+    }
+
+    pub async fn unsubscribe_multiplexed(&self, id: &SubscriptionId) -> Output<()> {
+        let _update = self.multiplexer_update.lock().await;
+
+        if self.multiplexer.write().await.logical.remove(id).is_none() {
+            return Output::default();
+        }
+
+        self.update_multiplexed(None).await
+    }
+
+    /// Merge the filters of the logical subscriptions and update the REQs of the relays
+    ///
+    /// The stored events of the new logical subscription (`added`) are forwarded from the replaced REQs.
+    /// Must be called holding the `multiplexer_update` lock, so the REQs are always sent for the latest logical subscriptions.
+    /// The multiplexer lock isn't held while sending, to not block the demultiplexer.
+    async fn update_multiplexed(&self, added: Option<&SubscriptionId>) -> Output<()> {
+        // Clone the relays, to not keep the lock while sending
+        let relays: Vec<(RelayUrl, Relay)> = {
+            let relays = self.relays.read().await;
+            self.internal_relays_with_flag(&relays, RelayServiceFlags::READ, FlagCheck::All)
+                .map(|(url, relay)| (url.clone(), relay.clone()))
+                .collect()
+        };
+
+        // Get the max number of filters per REQ
+        let mut limits: Vec<Option<usize>> = Vec::with_capacity(relays.len());
+        for (_, relay) in relays.iter() {
+            limits.push(max_filters_per_req(relay).await);
+        }
+
+        // Compose the REQs and update the multiplexer state
+        let mut updates: Vec<RelayReqs> = Vec::with_capacity(relays.len());
+        {
+            let mut multiplexer = self.multiplexer.write().await;
+            let filters: Vec<Filter> = multiplexer.merged_filters();
+
+            for ((url, relay), max_filters) in relays.into_iter().zip(limits.into_iter()) {
+                let reqs: Vec<Vec<Filter>> = multiplex::split_filters(filters.clone(), max_filters);
+                let ids: Vec<SubscriptionId> = multiplexer.physical_ids(reqs.len());
+
+                // The REQs that are no longer needed
+                let close: Vec<SubscriptionId> = multiplexer
+                    .physical
+                    .remove(&url)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .skip(reqs.len())
+                    .collect();
+
+                if let Some(added) = added {
+                    multiplexer.backfill(added, url.clone(), ids.clone());
+                }
+
+                multiplexer.physical.insert(url, ids.clone());
+                updates.push(RelayReqs {
+                    relay,
+                    close,
+                    send: ids.into_iter().zip(reqs).collect(),
+                });
+            }
+        }
+
+        let mut output: Output<()> = Output::default();
+
+        for RelayReqs { relay, close, send } in updates.into_iter() {
+            let url: &RelayUrl = relay.url();
+
+            // Close the REQs that are no longer needed
+            for id in close.into_iter() {
+                if let Err(e) = relay.unsubscribe(id).await {
+                    tracing::error!(url = %url, error = %e, "Impossible to close multiplexed REQ.");
+                }
+            }
+
+            // Send (or replace) the REQs
+            let mut failed: Option<String> = None;
+            for (id, filters) in send.into_iter() {
+                if let Err(e) = relay
+                    .subscribe_with_id(id.clone(), filters, SubscribeOptions::default())
+                    .await
+                {
+                    // No stored events will be received
+                    self.multiplexer.write().await.eose(url, &id);
+                    failed = Some(e.to_string());
+                }
+            }
+
+            match failed {
+                Some(e) => {
+                    output.failed.insert(url.clone(), e);
+                }
+                None => {
+                    output.success.insert(url.clone());
+                }
+            }
+        }
+
+        output
+    }
+
     pub async fn unsubscribe(&self, id: SubscriptionId) {
         // Remove subscription from pool
         self.remove_subscription(&id).await;
@@ -793,6 +1612,45 @@ impl InnerRelayPool {
         Ok(events)
     }
 
+    pub async fn fetch_events_windowed(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<Events, Error> {
+        // Lock with read shared access
+        let relays = self.relays.read().await;
+
+        // Check if empty
+        if relays.is_empty() {
+            return Err(Error::NoRelays);
+        }
+
+        let mut urls: Vec<&RelayUrl> = Vec::new();
+        let mut futures = Vec::new();
+
+        // Compose futures
+        for (url, relay) in
+            self.internal_relays_with_flag(&relays, RelayServiceFlags::READ, FlagCheck::All)
+        {
+            urls.push(url);
+            futures.push(relay.fetch_events_windowed(filters.clone(), timeout));
+        }
+
+        // Join futures
+        let list = future::join_all(futures).await;
+
+        // Merge results
+        let mut events: Events = Events::new(&[]);
+        for (url, result) in urls.into_iter().zip(list.into_iter()) {
+            match result {
+                Ok(fetched) => events.extend(fetched),
+                Err(e) => tracing::error!(url = %url, error = %e, "Failed to fetch events."),
+            }
+        }
+
+        Ok(events)
+    }
+
     #[inline]
     pub async fn stream_events(
         &self,
@@ -821,13 +1679,30 @@ impl InnerRelayPool {
         self.stream_events_targeted(targets, timeout, opts).await
     }
 
-    // TODO: change target type to `HashMap<Url, Vec<Filter>>`?
+    #[inline]
     pub async fn stream_events_targeted<I, U>(
         &self,
         targets: I,
         timeout: Duration,
         opts: FilterOptions,
     ) -> Result<ReceiverStream<Event>, Error>
+    where
+        I: IntoIterator<Item = (U, Vec<Filter>)>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        self.stream_events_targeted_ordered(targets, timeout, opts, StreamOrder::Received)
+            .await
+    }
+
+    // TODO: change target type to `HashMap<Url, Vec<Filter>>`?
+    pub async fn stream_events_targeted_ordered<I, U>(
+        &self,
+        targets: I,
+        timeout: Duration,
+        opts: FilterOptions,
+        order: StreamOrder,
+    ) -> Result<ReceiverStream<Event>, Error>
     where
         I: IntoIterator<Item = (U, Vec<Filter>)>,
         U: TryIntoUrl,
@@ -852,49 +1727,50 @@ impl InnerRelayPool {
             return Err(Error::NoRelays);
         }
 
-        // Check if urls set contains ONLY already added relays
-        if !map.keys().all(|url| relays.contains_key(url)) {
-            return Err(Error::RelayNotFound);
+        // Clone the target relays, to not hold the lock while streaming
+        let mut targets: Vec<(RelayUrl, Relay, Vec<Filter>)> = Vec::with_capacity(map.len());
+        for (url, filters) in map.into_iter() {
+            let relay: Relay = self.internal_relay(&relays, &url)?.clone();
+            targets.push((url, relay, filters));
         }
 
         // Drop
         drop(relays);
 
         // Create channel
-        let (tx, rx) = mpsc::channel::<Event>(map.len() * 512);
+        let (tx, rx) = mpsc::channel::<Event>(targets.len() * 512);
 
         // Spawn
-        let this = self.clone();
-        task::spawn(async move {
-            // Lock with read shared access
-            let relays = this.relays.read().await;
-
+        self.state.spawn("events stream", async move {
             let ids: Mutex<HashSet<EventId>> = Mutex::new(HashSet::new());
 
-            let mut urls: Vec<RelayUrl> = Vec::with_capacity(map.len());
-            let mut futures = Vec::with_capacity(map.len());
-
-            // Filter relays and start query
-            for (url, filters) in map.into_iter() {
-                match this.internal_relay(&relays, &url) {
-                    Ok(relay) => {
-                        urls.push(url);
-                        futures.push(relay.fetch_events_with_callback(
-                            filters,
-                            timeout,
-                            opts,
-                            |event| async {
-                                let mut ids = ids.lock().await;
-                                if ids.insert(event.id) {
-                                    drop(ids);
-                                    let _ = tx.try_send(event);
-                                }
-                            },
-                        ));
-                    }
-                    // TODO: remove this
-                    Err(e) => tracing::error!("{e}"),
-                }
+            // Events buffered until the end of the fetches, if must be sorted
+            let buffer: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+
+            let mut urls: Vec<RelayUrl> = Vec::with_capacity(targets.len());
+            let mut futures = Vec::with_capacity(targets.len());
+
+            // Start query
+            for (url, relay, filters) in targets.iter() {
+                urls.push(url.clone());
+                futures.push(relay.fetch_events_with_callback(
+                    filters.clone(),
+                    timeout,
+                    opts,
+                    |event| async {
+                        let mut ids = ids.lock().await;
+                        if ids.insert(event.id) {
+                            drop(ids);
+
+                            if order.is_received() {
+                                // Wait for capacity instead of dropping the event if the channel is full
+                                let _ = tx.send(event).await;
+                            } else {
+                                buffer.lock().await.push(event);
+                            }
+                        }
+                    },
+                ));
             }
 
             // Join futures
@@ -906,6 +1782,20 @@ impl InnerRelayPool {
                     tracing::error!(url = %url, error = %e, "Failed to stream events.");
                 }
             }
+
+            // Sort and send the buffered events
+            let mut events: Vec<Event> = buffer.into_inner();
+            match order {
+                StreamOrder::Received => {}
+                StreamOrder::Ascending => events.sort_by(|a, b| a.created_at.cmp(&b.created_at)),
+                StreamOrder::Descending => events.sort_by(|a, b| b.created_at.cmp(&a.created_at)),
+            }
+
+            for event in events.into_iter() {
+                if tx.send(event).await.is_err() {
+                    break;
+                }
+            }
         });
 
         // Return stream
@@ -942,6 +1832,30 @@ impl InnerRelayPool {
         }
     }
 
+    pub async fn try_connect(&self, timeout: Duration) -> Output<()> {
+        // Connect concurrently and wait for status change
+        self.connect(Some(timeout)).await;
+
+        // Lock with read shared access
+        let relays = self.relays.read().await;
+
+        let mut output: Output<()> = Output::default();
+
+        // Collect connection results
+        for (url, relay) in relays.iter() {
+            let status: RelayStatus = relay.status();
+            if status.is_connected() {
+                output.success.insert(url.clone());
+            } else {
+                output
+                    .failed
+                    .insert(url.clone(), format!("relay status: {status}"));
+            }
+        }
+
+        output
+    }
+
     pub async fn disconnect(&self) -> Result<(), Error> {
         // Lock with read shared access
         let relays = self.relays.read().await;
@@ -954,6 +1868,49 @@ impl InnerRelayPool {
         Ok(())
     }
 
+    pub async fn pause(&self) -> Result<(), Error> {
+        // Lock with read shared access
+        let relays = self.relays.read().await;
+
+        let mut paused = self.paused.lock().await;
+
+        // Skip the relays that have never been connected or that have been already disconnected
+        let mut futures = Vec::new();
+        for (url, relay) in relays.iter().filter(|(_, r)| !r.status().can_connect()) {
+            paused.insert(url.clone());
+            futures.push(relay.inner.pause(RELAY_TERMINATION_TIMEOUT));
+        }
+
+        for result in future::join_all(futures).await.into_iter() {
+            result?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn resume(&self, connection_timeout: Option<Duration>) {
+        let paused: HashSet<RelayUrl> = {
+            let mut paused = self.paused.lock().await;
+            mem::take(&mut *paused)
+        };
+
+        // Lock with read shared access
+        let relays = self.relays.read().await;
+
+        let mut futures = Vec::new();
+        for (url, relay) in relays.iter().filter(|(url, ..)| paused.contains(*url)) {
+            // Don't reconnect the relays banned in the meanwhile
+            if relay.inner.is_banned() {
+                tracing::debug!(url = %url, "Relay banned, skipping resume.");
+                relay.inner.clear_pause().await;
+                continue;
+            }
+
+            futures.push(relay.connect(connection_timeout));
+        }
+        future::join_all(futures).await;
+    }
+
     pub async fn connect_relay<U>(
         &self,
         url: U,
@@ -997,4 +1954,73 @@ impl InnerRelayPool {
 
         Ok(())
     }
+
+    pub async fn replay<I>(&self, frames: I, speed: ReplaySpeed) -> Result<usize, Error>
+    where
+        I: IntoIterator<Item = CapturedFrame>,
+    {
+        if self.is_shutdown() {
+            return Err(Error::Shutdown);
+        }
+
+        let mut previous: Option<u64> = None;
+        let mut replayed: usize = 0;
+
+        for frame in frames.into_iter() {
+            if frame.direction == CaptureDirection::Outbound {
+                continue;
+            }
+
+            // Wait the (scaled) delay from the previous frame
+            if let Some(previous) = previous {
+                if let Some(delay) = speed.delay(previous, frame.timestamp_ms) {
+                    time::sleep(delay).await;
+                }
+            }
+            previous = Some(frame.timestamp_ms);
+
+            // Get relay (not keeping the lock during the delays)
+            let relay: Relay = {
+                let relays = self.relays.read().await;
+                match relays.get(&frame.relay_url) {
+                    Some(relay) => relay.clone(),
+                    None => {
+                        tracing::warn!(url = %frame.relay_url, "Relay not found, skipping replayed frame.");
+                        continue;
+                    }
+                }
+            };
+
+            relay.inner.handle_relay_message(&frame.message).await;
+            replayed += 1;
+        }
+
+        Ok(replayed)
+    }
+}
+
+/// Max number of filters per REQ advertised by the relay
+#[cfg(feature = "nip11")]
+async fn max_filters_per_req(relay: &Relay) -> Option<usize> {
+    relay
+        .document()
+        .await
+        .limitation
+        .and_then(|l| l.max_filters)
+        .and_then(|max| usize::try_from(max).ok())
+}
+
+#[cfg(not(feature = "nip11"))]
+async fn max_filters_per_req(_relay: &Relay) -> Option<usize> {
+    None
+}
+
+/// Terminate a relay already removed from the pool
+///
+/// The relay is gone from the pool also if the connection task doesn't exit in time,
+/// so the failure is only logged.
+async fn terminate_removed_relay(relay: &Relay) {
+    if let Err(e) = relay.terminate(RELAY_TERMINATION_TIMEOUT).await {
+        tracing::warn!(url = %relay.url(), error = %e, "Impossible to terminate the removed relay.");
+    }
 }
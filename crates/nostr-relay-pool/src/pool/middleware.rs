@@ -0,0 +1,79 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Outgoing middlewares
+
+use std::fmt;
+
+use nostr::UnsignedEvent;
+
+use super::Error;
+use crate::transport::BoxedFuture;
+
+/// Decision of an outgoing middleware
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MiddlewareDecision {
+    /// Continue with the (optionally modified) event
+    Continue(UnsignedEvent),
+    /// Don't send the event
+    Veto {
+        /// Reason
+        reason: String,
+    },
+}
+
+impl MiddlewareDecision {
+    /// Veto with reason
+    #[inline]
+    pub fn veto<S>(reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Veto {
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Error of the outgoing middlewares
+#[derive(Debug)]
+pub(crate) enum MiddlewareError {
+    /// Event vetoed
+    Vetoed { reason: String },
+    /// Impossible to re-sign the modified event
+    Sign(String),
+}
+
+impl fmt::Display for MiddlewareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Vetoed { reason } => write!(f, "event vetoed: {reason}"),
+            Self::Sign(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<MiddlewareError> for Error {
+    fn from(e: MiddlewareError) -> Self {
+        match e {
+            MiddlewareError::Vetoed { reason } => Self::EventVetoed { reason },
+            MiddlewareError::Sign(e) => Self::Middleware(e),
+        }
+    }
+}
+
+/// Outgoing event middleware
+///
+/// Register it with [`RelayPool::add_outgoing_middleware`](super::RelayPool::add_outgoing_middleware).
+///
+/// The middlewares are applied in the registration order to every event sent by the pool
+/// (also to the events uploaded by the negentropy sync), before saving it into the database.
+/// They can modify the event (i.e. add a `client`, `alt` or `-` tag, mine the POW) or veto it.
+///
+/// If the event is modified, the pool re-sign it with the signer,
+/// so the events of other authors can't be modified (check the `pubkey` of the event).
+pub trait OutgoingMiddleware: fmt::Debug + Send + Sync {
+    /// Process the outgoing event
+    fn process<'a>(&'a self, event: UnsignedEvent) -> BoxedFuture<'a, MiddlewareDecision>;
+}
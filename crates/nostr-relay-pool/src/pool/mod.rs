@@ -4,7 +4,7 @@
 
 //! Relay Pool
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::sync::Arc;
 use std::time::Duration;
@@ -15,19 +15,42 @@ use tokio::sync::broadcast;
 
 pub mod constants;
 mod error;
+mod handler;
 mod inner;
+mod middleware;
+mod multiplex;
+mod notification;
+mod optimizer;
 pub mod options;
 mod output;
+mod replay;
+pub mod sets;
+mod snapshot;
 
 pub use self::error::Error;
+pub use self::handler::EventHandler;
 use self::inner::InnerRelayPool;
-pub use self::options::RelayPoolOptions;
+pub(crate) use self::middleware::MiddlewareError;
+pub use self::middleware::{MiddlewareDecision, OutgoingMiddleware};
+pub(crate) use self::notification::NotificationSender;
+pub use self::notification::SequencedNotification;
+pub use self::optimizer::{RelayContribution, RelayOptimizerOptions, RelayScore, RelaySwap};
+pub use self::options::{RelayPoolOptions, StreamOrder};
 pub use self::output::Output;
+pub use self::replay::ReplaySpeed;
+pub use self::sets::RelaySet;
+pub use self::snapshot::{RelayPoolSnapshot, RelaySnapshot, SubscriptionSnapshot};
+use crate::metrics::IngestionMetrics;
+use crate::relay::capture::CapturedFrame;
 use crate::relay::flags::FlagCheck;
 use crate::relay::options::{FilterOptions, RelayOptions, SyncOptions};
-use crate::relay::{Relay, RelayFiltering, RelayStatus};
+#[cfg(feature = "nip11")]
+use crate::relay::RelayPayment;
+use crate::relay::{Bandwidth, DisconnectReason, Relay, RelayFiltering, RelayStatus};
 use crate::shared::SharedState;
+use crate::spawner::IntoSpawner;
 use crate::stream::ReceiverStream;
+use crate::tasks::TaskInfo;
 use crate::{Reconciliation, RelayServiceFlags, SubscribeOptions};
 
 /// Relay Pool Notification
@@ -40,7 +63,9 @@ pub enum RelayPoolNotification {
         /// Subscription ID
         subscription_id: SubscriptionId,
         /// Event
-        event: Box<Event>,
+        ///
+        /// Shared across the listeners, to not clone the event for each of them.
+        event: Arc<Event>,
     },
     /// Received a [`RelayMessage`]. Includes messages wrapping events that were sent by this client.
     Message {
@@ -65,11 +90,62 @@ pub enum RelayPoolNotification {
         /// Relay url
         relay_url: RelayUrl,
     },
+    /// The relay requires a payment (advertised in the NIP11 document)
+    ///
+    /// Check [`Relay::set_paid`] to mark the relay as paid after the payment.
+    #[cfg(feature = "nip11")]
+    RelayPaymentRequired {
+        /// Relay url
+        relay_url: RelayUrl,
+        /// Payment requirements
+        payment: RelayPayment,
+    },
+    /// Disconnected from the relay
+    ///
+    /// The reason allows to decide if alert the user or silently wait for the reconnection.
+    RelayDisconnected {
+        /// Relay url
+        relay_url: RelayUrl,
+        /// Reason
+        reason: DisconnectReason,
+    },
+    /// An event queued in the outbox has been sent
+    ///
+    /// Check [`RelayOptions::outbox`].
+    OutboxEventSent {
+        /// Relay url
+        relay_url: RelayUrl,
+        /// Event ID
+        event_id: EventId,
+        /// Accepted by the relay
+        status: bool,
+        /// Message
+        message: String,
+    },
+    /// A background task panicked and has been restarted
+    ///
+    /// Useful to detect a degraded state.
+    ///
+    /// Sent only if the binary is built with `panic = "unwind"` (the default):
+    /// with `panic = "abort"` a panic aborts the process.
+    TaskPanicked {
+        /// Relay url (`None` for the pool tasks)
+        relay_url: Option<RelayUrl>,
+        /// Task name
+        task: String,
+        /// Panic message
+        reason: String,
+    },
     /// Shutdown
     Shutdown,
 }
 
 /// Relay Pool
+///
+/// All methods take `&self`: the pool can be cheaply cloned and shared between tasks,
+/// without wrapping it in a `Mutex`.
+///
+/// When the last clone is dropped, the pool is shut down: all relays are disconnected and removed.
 #[derive(Debug, Clone)]
 pub struct RelayPool {
     inner: AtomicDestructor<InnerRelayPool>,
@@ -95,6 +171,16 @@ impl RelayPool {
         Self::__with_shared_state(opts, SharedState::default())
     }
 
+    /// Create new `RelayPool` with a custom [`Spawner`](crate::spawner::Spawner) for the background tasks
+    ///
+    /// Pass a `tokio::runtime::Handle` to host the connections and the other background tasks on a specific runtime.
+    pub fn with_spawner<T>(opts: RelayPoolOptions, spawner: T) -> Self
+    where
+        T: IntoSpawner,
+    {
+        Self::__with_shared_state(opts, SharedState::default().with_spawner(spawner))
+    }
+
     #[doc(hidden)]
     pub fn __with_shared_state(opts: RelayPoolOptions, state: SharedState) -> Self {
         Self {
@@ -103,19 +189,84 @@ impl RelayPool {
     }
 
     /// Completely shutdown pool
+    ///
+    /// Wait for the background tasks to terminate, aborting the ones still running after some seconds.
     #[inline]
     pub async fn shutdown(&self) -> Result<(), Error> {
         self.inner.shutdown().await
     }
 
+    /// Get the latencies of the ingestion pipeline of the received events (parse, verify, store and notify)
+    ///
+    /// Check [`IngestionMetrics::percentiles`].
+    #[inline]
+    pub fn ingestion_metrics(&self) -> &IngestionMetrics {
+        self.inner.state.ingestion_metrics()
+    }
+
+    /// Get the background tasks (connections, notifications handlers, etc.)
+    ///
+    /// Useful for debugging: the finished tasks are removed, while the panicked ones are kept.
+    #[inline]
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        self.inner.state.tasks()
+    }
+
+    /// Take a snapshot of the relays, of their subscriptions and of the pool subscriptions
+    ///
+    /// Persist it (i.e. [`RelayPoolSnapshot::as_json`]) and call [`RelayPool::restore`] at the next startup
+    /// to resume where the app left off.
+    #[inline]
+    pub async fn snapshot(&self) -> RelayPoolSnapshot {
+        self.inner.snapshot().await
+    }
+
+    /// Restore a snapshot taken with [`RelayPool::snapshot`]
+    ///
+    /// Add the missing relays with the `opts` (the flags are taken from the snapshot),
+    /// restoring their connection stats and subscriptions, and the pool subscriptions.
+    /// No network activity is performed: the subscriptions are sent to the relays when connected.
+    ///
+    /// Enable [`RelayOptions::resubscribe_since`] to resume the subscriptions from the newest events received before the snapshot.
+    #[inline]
+    pub async fn restore(
+        &self,
+        snapshot: RelayPoolSnapshot,
+        opts: RelayOptions,
+    ) -> Result<(), Error> {
+        self.inner.restore(snapshot, opts).await
+    }
+
+    /// Check if the pool has been shutdown
+    #[inline]
+    pub fn is_shutdown(&self) -> bool {
+        self.inner.is_shutdown()
+    }
+
     /// Get new **pool** notification listener
     ///
+    /// Every listener receives all the notifications (fan-out),
+    /// so multiple independent components (i.e. UI, logger, bot logic) can listen at the same time.
+    ///
     /// <div class="warning">When you call this method, you subscribe to the notifications channel from that precise moment. Anything received by relay/s before that moment is not included in the channel!</div>
     #[inline]
     pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
         self.inner.notifications()
     }
 
+    /// Get new **pool** notification listener, with sequence numbers and receive timestamps
+    ///
+    /// The notifications are the same of [`RelayPool::notifications`].
+    /// The sequence numbers are assigned by the pool in the sending order,
+    /// so the consumers can restore the order of the notifications sent concurrently by different relays,
+    /// detect the missed ones and implement exactly-once processing.
+    ///
+    /// <div class="warning">When you call this method, you subscribe to the notifications channel from that precise moment. Anything received by relay/s before that moment is not included in the channel!</div>
+    #[inline]
+    pub fn sequenced_notifications(&self) -> broadcast::Receiver<SequencedNotification> {
+        self.inner.sequenced_notifications()
+    }
+
     /// Get shared state
     #[inline]
     pub fn state(&self) -> &SharedState {
@@ -158,6 +309,101 @@ impl RelayPool {
         self.inner.relays_with_flag(flag, check).await
     }
 
+    /// Get the bandwidth usage of all the relays in the pool
+    ///
+    /// Use [`RelayConnectionStats::bandwidth`](crate::RelayConnectionStats::bandwidth) for the usage of a single relay.
+    #[inline]
+    pub async fn bandwidth(&self) -> Bandwidth {
+        self.inner.bandwidth().await
+    }
+
+    /// Get the bandwidth usage of a subscription, summed over all the relays in the pool
+    #[inline]
+    pub async fn subscription_bandwidth(&self, id: &SubscriptionId) -> Bandwidth {
+        self.inner.subscription_bandwidth(id).await
+    }
+
+    /// Get the unique and the duplicate events received by each relay, from the most redundant
+    ///
+    /// Useful to trim the relays that only send events already received from other relays.
+    #[inline]
+    pub async fn contribution_report(&self) -> Vec<RelayContribution> {
+        self.inner.contribution_report().await
+    }
+
+    /// Score the relays with `READ` or `WRITE` flags, from the best to the worst
+    ///
+    /// The score combines the connection success rate, the unique events contributed,
+    /// the ping latency and the time to `EOSE`.
+    /// The relays connected for less than the `grace_period` are marked as not rated.
+    #[inline]
+    pub async fn relay_scores(&self, grace_period: Duration) -> Vec<RelayScore> {
+        self.inner.relay_scores(grace_period).await
+    }
+
+    /// Suggest which underperforming relays to replace with the candidates
+    ///
+    /// The pinned relays are never suggested for the removal.
+    #[inline]
+    pub async fn suggest_relay_swaps(&self, opts: &RelayOptimizerOptions) -> Vec<RelaySwap> {
+        self.inner.suggest_relay_swaps(opts).await
+    }
+
+    /// Replace the underperforming relays with the candidates and return the applied swaps
+    ///
+    /// The added relays inherit the pool subscriptions and are immediately connected.
+    /// The underperforming relay is removed only after its candidate has been added and connected:
+    /// the swaps that fail are skipped, leaving the relay in the pool.
+    #[inline]
+    pub async fn optimize_relays(
+        &self,
+        opts: &RelayOptimizerOptions,
+    ) -> Result<Vec<RelaySwap>, Error> {
+        self.inner.optimize_relays(opts).await
+    }
+
+    /// Register an handler for the events of specific kinds
+    ///
+    /// The received events of these kinds are dispatched to the handler, concurrently with the other handlers.
+    /// If `kinds` is empty, the handler receives the events of every kind.
+    ///
+    /// Useful to build modular bots (i.e. a zap handler and a DM handler),
+    /// without a giant match on the notifications.
+    ///
+    /// The handlers are called until the pool is shutdown.
+    pub async fn register_handler<I, H>(&self, kinds: I, handler: H)
+    where
+        I: IntoIterator<Item = Kind>,
+        H: EventHandler + 'static,
+    {
+        self.inner
+            .register_handler(kinds.into_iter().collect(), Arc::new(handler))
+            .await
+    }
+
+    /// Add an outgoing middleware
+    ///
+    /// The middlewares are applied, in the registration order, to all the events sent by the pool
+    /// (i.e. [`RelayPool::send_event`], [`RelayPool::send_event_to`] and [`RelayPool::batch_msg_to`]).
+    /// Check [`OutgoingMiddleware`] to learn more.
+    #[inline]
+    pub async fn add_outgoing_middleware<M>(&self, middleware: M)
+    where
+        M: OutgoingMiddleware + 'static,
+    {
+        self.inner
+            .add_outgoing_middleware(Arc::new(middleware))
+            .await
+    }
+
+    /// Periodically optimize the relays in background, until the pool is shutdown
+    ///
+    /// Check [`RelayOptimizerOptions::auto_swap`] and [`RelayOptimizerOptions::interval`].
+    #[inline]
+    pub fn spawn_relay_optimizer(&self, opts: RelayOptimizerOptions) {
+        self.inner.spawn_relay_optimizer(opts)
+    }
+
     /// Get [`Relay`]
     #[inline]
     pub async fn relay<U>(&self, url: U) -> Result<Relay, Error>
@@ -208,6 +454,8 @@ impl RelayPool {
     /// [`RelayServiceFlags::WRITE`] and [`RelayServiceFlags::DISCOVERY`] flags).
     ///
     /// To fore remove a relay use [`RelayPool::force_remove_relay`].
+    ///
+    /// The relay is removed also if its connection task doesn't exit in time: the failure is only logged.
     #[inline]
     pub async fn remove_relay<U>(&self, url: U) -> Result<(), Error>
     where
@@ -250,12 +498,42 @@ impl RelayPool {
         self.inner.connect(connection_timeout).await
     }
 
+    /// Try to connect to all added relays concurrently
+    ///
+    /// Wait for the relays to be connected at most for the specified `timeout`
+    /// and return which relays succeeded and which failed.
+    ///
+    /// The relays that failed to connect will continue to retry in background (if reconnection is enabled).
+    #[inline]
+    pub async fn try_connect(&self, timeout: Duration) -> Output<()> {
+        self.inner.try_connect(timeout).await
+    }
+
     /// Disconnect from all relays
     #[inline]
     pub async fn disconnect(&self) -> Result<(), Error> {
         self.inner.disconnect().await
     }
 
+    /// Pause the connections (i.e. when the app goes in background)
+    ///
+    /// Close the WebSocket connections of the active relays, keeping the subscriptions.
+    /// Call [`RelayPool::resume`] to re-establish them.
+    #[inline]
+    pub async fn pause(&self) -> Result<(), Error> {
+        self.inner.pause().await
+    }
+
+    /// Resume the connections paused with [`RelayPool::pause`]
+    ///
+    /// The subscriptions are sent again with the `since` moved just before the pause,
+    /// to avoid to download again the events already received.
+    /// The relays banned in the meanwhile are not reconnected.
+    #[inline]
+    pub async fn resume(&self, connection_timeout: Option<Duration>) {
+        self.inner.resume(connection_timeout).await
+    }
+
     /// Connect to relay
     #[inline]
     pub async fn connect_relay<U>(
@@ -458,6 +736,143 @@ impl RelayPool {
         self.inner.subscribe_targeted(id, targets, opts).await
     }
 
+    /// Set the relays of a named set
+    ///
+    /// Replace the relays of the set with the same name, if any.
+    ///
+    /// Note: **the relays must be added to the pool before using the set-scoped methods!**
+    #[inline]
+    pub async fn set_relay_set<I, U>(&self, set: RelaySet, urls: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        self.inner.set_relay_set(set, urls).await
+    }
+
+    /// Get the relays of a named set
+    #[inline]
+    pub async fn relay_set(&self, set: &RelaySet) -> Option<HashSet<RelayUrl>> {
+        self.inner.relay_set(set).await
+    }
+
+    /// Get all the relay sets
+    #[inline]
+    pub async fn relay_sets(&self) -> HashMap<RelaySet, HashSet<RelayUrl>> {
+        self.inner.relay_sets().await
+    }
+
+    /// Remove a relay set
+    ///
+    /// The relays aren't removed from the pool.
+    #[inline]
+    pub async fn remove_relay_set(&self, set: &RelaySet) -> Option<HashSet<RelayUrl>> {
+        self.inner.remove_relay_set(set).await
+    }
+
+    /// Send event to the relays of a set
+    #[inline]
+    pub async fn send_event_to_set(
+        &self,
+        set: &RelaySet,
+        event: Event,
+    ) -> Result<Output<EventId>, Error> {
+        self.inner.send_event_to_set(set, event).await
+    }
+
+    /// Subscribe to filters to the relays of a set
+    ///
+    /// The relays are resolved when subscribing: later changes of the set don't affect the subscription.
+    #[inline]
+    pub async fn subscribe_to_set(
+        &self,
+        set: &RelaySet,
+        filters: Vec<Filter>,
+        opts: SubscribeOptions,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        self.inner.subscribe_to_set(set, filters, opts).await
+    }
+
+    /// Fetch events from the relays of a set
+    #[inline]
+    pub async fn fetch_events_from_set(
+        &self,
+        set: &RelaySet,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        opts: FilterOptions,
+    ) -> Result<Events, Error> {
+        self.inner
+            .fetch_events_from_set(set, filters, timeout, opts)
+            .await
+    }
+
+    /// Register a filter preset
+    ///
+    /// Replace the preset with the same name, if any.
+    /// Check [`FilterPresets`] docs to learn more.
+    #[inline]
+    pub async fn register_filter_preset<S, I>(&self, name: S, templates: I)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = FilterTemplate>,
+    {
+        self.inner.register_filter_preset(name, templates).await
+    }
+
+    /// Subscribe to the filters of a preset to all relays with `READ` flag.
+    ///
+    /// The filters are instantiated with the values set with [`RelayPool::set_preset_value`].
+    /// When a value used by the preset changes, the subscription is automatically updated.
+    #[inline]
+    pub async fn subscribe_preset(
+        &self,
+        name: &str,
+        opts: SubscribeOptions,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        self.inner.subscribe_preset(name, opts).await
+    }
+
+    /// Set the value of a preset placeholder (i.e. the public key of the active account)
+    ///
+    /// Re-subscribe the preset subscriptions that use the placeholder.
+    /// If the filters can't be instantiated or a subscription can't be updated,
+    /// the previous value and the previous filters are restored.
+    #[inline]
+    pub async fn set_preset_value<K, V>(&self, key: K, value: V) -> Result<(), Error>
+    where
+        K: Into<String>,
+        V: Into<PresetValue>,
+    {
+        self.inner.set_preset_value(key, value).await
+    }
+
+    /// Multiplexed subscription to all relays with `READ` flag
+    ///
+    /// The filters of all the multiplexed subscriptions are merged (see [`Filter::try_merge`])
+    /// and sent to each relay in as few REQs as possible, respecting the NIP11 `max_filters` limit (`nip11` feature).
+    ///
+    /// The received events are delivered as [`RelayPoolNotification::Event`] with the ID of each matching subscription.
+    /// The notifications with the IDs of the shared REQs are still delivered and can be ignored.
+    ///
+    /// Use [`RelayPool::unsubscribe_multiplexed`] to close the subscription.
+    #[inline]
+    pub async fn subscribe_multiplexed(
+        &self,
+        filters: Vec<Filter>,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        self.inner.subscribe_multiplexed(filters).await
+    }
+
+    /// Close a multiplexed subscription
+    ///
+    /// Update the shared REQs with the filters of the remaining multiplexed subscriptions.
+    #[inline]
+    pub async fn unsubscribe_multiplexed(&self, id: &SubscriptionId) -> Output<()> {
+        self.inner.unsubscribe_multiplexed(id).await
+    }
+
     /// Unsubscribe from subscription
     #[inline]
     pub async fn unsubscribe(&self, id: SubscriptionId) {
@@ -522,6 +937,18 @@ impl RelayPool {
         self.inner.fetch_events(filters, timeout, opts).await
     }
 
+    /// Fetch events from relays with [`RelayServiceFlags::READ`] flag, splitting the `since`/`until` window
+    ///
+    /// Check [`Relay::fetch_events_windowed`] to learn more.
+    #[inline]
+    pub async fn fetch_events_windowed(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<Events, Error> {
+        self.inner.fetch_events_windowed(filters, timeout).await
+    }
+
     /// Fetch events from specific relays
     #[inline]
     pub async fn fetch_events_from<I, U>(
@@ -591,6 +1018,45 @@ impl RelayPool {
             .await
     }
 
+    /// Targeted streaming events, sorted by `created_at`
+    ///
+    /// With [`StreamOrder::Ascending`] or [`StreamOrder::Descending`], the events are sent to the stream
+    /// only when all the relays terminated the fetch.
+    #[inline]
+    pub async fn stream_events_targeted_ordered<I, U>(
+        &self,
+        source: I,
+        timeout: Duration,
+        opts: FilterOptions,
+        order: StreamOrder,
+    ) -> Result<ReceiverStream<Event>, Error>
+    where
+        I: IntoIterator<Item = (U, Vec<Filter>)>,
+        U: TryIntoUrl,
+        Error: From<<U as TryIntoUrl>::Err>,
+    {
+        self.inner
+            .stream_events_targeted_ordered(source, timeout, opts, order)
+            .await
+    }
+
+    /// Replay captured frames
+    ///
+    /// Feed the inbound frames to the ingestion pipeline of the relays (database, filtering, notifications),
+    /// as if they were received from the network, at the original or accelerated timing.
+    /// Outbound frames and frames of relays not in the pool are skipped.
+    ///
+    /// The relays must be added to the pool but don't need to be connected.
+    ///
+    /// Return the number of the replayed frames.
+    #[inline]
+    pub async fn replay<I>(&self, frames: I, speed: ReplaySpeed) -> Result<usize, Error>
+    where
+        I: IntoIterator<Item = CapturedFrame>,
+    {
+        self.inner.replay(frames, speed).await
+    }
+
     /// Handle notifications
     pub async fn handle_notifications<F, Fut>(&self, func: F) -> Result<(), Error>
     where
@@ -613,9 +1079,17 @@ impl RelayPool {
 
 #[cfg(test)]
 mod tests {
+    use async_utility::futures_util::StreamExt;
+    use nostr::nips::nip62::VanishTarget;
+    use nostr_database::MemoryDatabaseOptions;
     use nostr_relay_builder::MockRelay;
 
     use super::*;
+    use crate::metrics::IngestionStage;
+    use crate::relay::capture::CaptureDirection;
+    use crate::relay::options::SyncDirection;
+    use crate::relay::RelayCloseFrame;
+    use crate::transport::BoxedFuture;
 
     #[tokio::test]
     async fn test_shutdown() {
@@ -632,6 +1106,11 @@ mod tests {
 
         tokio::time::sleep(Duration::from_secs(1)).await;
 
+        assert!(pool
+            .tasks()
+            .iter()
+            .any(|task| task.name.ends_with(": connection") && task.state.is_running()));
+
         pool.shutdown().await.unwrap();
 
         assert!(pool.inner.is_shutdown());
@@ -643,4 +1122,1229 @@ mod tests {
             Error::Shutdown
         ));
     }
+
+    #[tokio::test]
+    async fn test_add_blacklisted_relay() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+        pool.filtering().add_relay_urls([url.clone()]).await;
+
+        assert!(matches!(
+            pool.add_relay(&url, RelayOptions::default())
+                .await
+                .unwrap_err(),
+            Error::RelayBlacklisted
+        ));
+        assert!(pool.relays().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_close_frame_notification() {
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+        use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+        // Relay that closes the first connection with a policy violation
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = RelayUrl::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let mut first: bool = true;
+            while let Ok((stream, ..)) = listener.accept().await {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                if first {
+                    first = false;
+                    let frame = CloseFrame {
+                        code: CloseCode::Policy,
+                        reason: "banned".into(),
+                    };
+                    ws.close(Some(frame)).await.unwrap();
+                } else {
+                    tokio::spawn(async move { while let Some(Ok(..)) = ws.next().await {} });
+                }
+            }
+        });
+
+        let pool = RelayPool::default();
+        let mut notifications = pool.notifications();
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.connect(Some(Duration::from_secs(2))).await;
+
+        let expected = RelayCloseFrame {
+            code: 1008,
+            reason: String::from("banned"),
+        };
+
+        // The close frame is reported to the pool listeners
+        let reason = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Ok(RelayPoolNotification::RelayDisconnected { relay_url, reason }) =
+                    notifications.recv().await
+                {
+                    assert_eq!(relay_url, url);
+                    return reason;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            reason,
+            DisconnectReason::Closed {
+                frame: Some(expected)
+            }
+        );
+
+        // Cleared on reconnection
+        let relay = pool.relay(&url).await.unwrap();
+        relay.disconnect().unwrap();
+        relay.connect(Some(Duration::from_secs(2))).await;
+        assert!(relay.is_connected());
+        assert_eq!(relay.close_frame().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_try_connect() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+        let unreachable = RelayUrl::parse("ws://127.0.0.1:1").unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.add_relay(&unreachable, RelayOptions::default())
+            .await
+            .unwrap();
+
+        let output = pool.try_connect(Duration::from_secs(2)).await;
+
+        assert!(output.success.contains(&url));
+        assert!(output.failed.contains_key(&unreachable));
+    }
+
+    #[tokio::test]
+    async fn test_relay_set() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let url1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url1, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.add_relay(&url2, RelayOptions::default())
+            .await
+            .unwrap();
+
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        pool.set_relay_set(RelaySet::DM, [&url2]).await.unwrap();
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        // Unknown set
+        assert!(matches!(
+            pool.send_event_to_set(&RelaySet::SEARCH, event.clone())
+                .await
+                .unwrap_err(),
+            Error::RelaySetNotFound(..)
+        ));
+
+        let output = pool.send_event_to_set(&RelaySet::DM, event).await.unwrap();
+        assert_eq!(output.success, HashSet::from([url2.clone()]));
+        assert!(output.failed.is_empty());
+
+        assert!(pool.remove_relay_set(&RelaySet::DM).await.is_some());
+        assert!(pool.relay_sets().await.is_empty());
+        assert_eq!(pool.relays().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_contribution_report() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let url1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        // Publish the same event to both relays
+        let publisher = RelayPool::default();
+        publisher
+            .add_relay(&url1, RelayOptions::default())
+            .await
+            .unwrap();
+        publisher
+            .add_relay(&url2, RelayOptions::default())
+            .await
+            .unwrap();
+        publisher.connect(Some(Duration::from_secs(1))).await;
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        publisher.send_event(event).await.unwrap();
+
+        let pool = RelayPool::default();
+        pool.add_relay(&url1, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.add_relay(&url2, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        // Fetch from one relay at a time (twice from the second one)
+        let filter = Filter::new().author(keys.public_key());
+        for url in [&url1, &url2, &url2] {
+            pool.fetch_events_from(
+                [url],
+                vec![filter.clone()],
+                Duration::from_secs(2),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        }
+
+        let report = pool.contribution_report().await;
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].url, url2);
+        assert_eq!(report[0].unique, 0);
+        assert_eq!(report[0].duplicates, 1);
+        assert_eq!(report[0].duplicate_ratio(), 1.0);
+        assert_eq!(report[1].url, url1);
+        assert_eq!(report[1].unique, 1);
+        assert_eq!(report[1].duplicates, 0);
+
+        // Only the unique event went through the whole pipeline
+        let metrics = pool.ingestion_metrics();
+        assert_eq!(
+            metrics.percentiles(IngestionStage::Parse).unwrap().samples,
+            3
+        );
+        assert_eq!(
+            metrics.percentiles(IngestionStage::Verify).unwrap().samples,
+            1
+        );
+        assert_eq!(
+            metrics.percentiles(IngestionStage::Total).unwrap().samples,
+            1
+        );
+
+        // Not a request to vanish
+        assert!(metrics.percentiles(IngestionStage::Vanish).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_events_ordered() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let url1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let pool = RelayPool::default();
+        pool.add_relay(&url1, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.add_relay(&url2, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        // Publish the events to different relays, not in order
+        let keys = Keys::generate();
+        for (url, created_at) in [(&url1, 2), (&url2, 3), (&url1, 1), (&url2, 4)] {
+            let event = EventBuilder::text_note("Test")
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(&keys)
+                .unwrap();
+            pool.send_event_to([url], event).await.unwrap();
+        }
+
+        let filter = Filter::new().author(keys.public_key());
+        for (order, expected) in [
+            (StreamOrder::Ascending, [1, 2, 3, 4]),
+            (StreamOrder::Descending, [4, 3, 2, 1]),
+        ] {
+            let stream = pool
+                .stream_events_targeted_ordered(
+                    [(&url1, vec![filter.clone()]), (&url2, vec![filter.clone()])],
+                    Duration::from_secs(2),
+                    FilterOptions::ExitOnEOSE,
+                    order,
+                )
+                .await
+                .unwrap();
+            let timestamps: Vec<u64> = stream
+                .map(|event| event.created_at.as_u64())
+                .collect()
+                .await;
+            assert_eq!(timestamps, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_handle() {
+        fn assert_send_sync<T>()
+        where
+            T: Send + Sync + Clone + 'static,
+        {
+        }
+
+        assert_send_sync::<RelayPool>();
+
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        // Add the same relay from multiple tasks
+        let mut handles = Vec::with_capacity(4);
+        for _ in 0..4 {
+            let pool = pool.clone();
+            let url = url.clone();
+            handles.push(tokio::spawn(async move {
+                pool.add_relay(url, RelayOptions::default()).await.unwrap()
+            }));
+        }
+
+        let mut added: usize = 0;
+        for handle in handles.into_iter() {
+            if handle.await.unwrap() {
+                added += 1;
+            }
+        }
+
+        assert_eq!(added, 1);
+        assert_eq!(pool.relays().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_message_notifications() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let mut notifications = pool.notifications();
+
+        // Send event
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event(event.clone()).await.unwrap();
+
+        // Check that the `OK` message is delivered as notification
+        let found = tokio::time::timeout(Duration::from_secs(1), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Message {
+                    relay_url,
+                    message: RelayMessage::Ok { event_id, .. },
+                } = notification
+                {
+                    return relay_url == url && event_id == event.id;
+                }
+            }
+            false
+        })
+        .await
+        .unwrap();
+        assert!(found);
+    }
+
+    #[tokio::test]
+    async fn test_notifications_fan_out() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        // Multiple listeners
+        let mut listeners = vec![
+            pool.notifications(),
+            pool.notifications(),
+            pool.notifications(),
+        ];
+
+        // Send event
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event(event.clone()).await.unwrap();
+
+        // Check that every listener receives the `OK` message
+        for notifications in listeners.iter_mut() {
+            let found = tokio::time::timeout(Duration::from_secs(1), async {
+                while let Ok(notification) = notifications.recv().await {
+                    if let RelayPoolNotification::Message {
+                        message: RelayMessage::Ok { event_id, .. },
+                        ..
+                    } = notification
+                    {
+                        return event_id == event.id;
+                    }
+                }
+                false
+            })
+            .await
+            .unwrap();
+            assert!(found);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let url = RelayUrl::parse("ws://127.0.0.1:1").unwrap();
+        let other = RelayUrl::parse("ws://127.0.0.1:2").unwrap();
+
+        let pool = RelayPool::default();
+
+        // Not connected
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        let mut notifications = pool.notifications();
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Replayed")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let msg = RelayMessage::event(SubscriptionId::new("sub"), event.clone());
+
+        let frames = vec![
+            CapturedFrame {
+                timestamp_ms: 1000,
+                relay_url: url.clone(),
+                direction: CaptureDirection::Outbound,
+                message: ClientMessage::req(SubscriptionId::new("sub"), vec![Filter::new()])
+                    .as_json(),
+            },
+            CapturedFrame {
+                timestamp_ms: 1100,
+                relay_url: url.clone(),
+                direction: CaptureDirection::Inbound,
+                message: msg.as_json(),
+            },
+            CapturedFrame {
+                timestamp_ms: 1200,
+                relay_url: other,
+                direction: CaptureDirection::Inbound,
+                message: msg.as_json(),
+            },
+        ];
+
+        let replayed = pool
+            .replay(frames, ReplaySpeed::Accelerated(10))
+            .await
+            .unwrap();
+        assert_eq!(replayed, 1);
+
+        let found = tokio::time::timeout(Duration::from_secs(1), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Event {
+                    relay_url,
+                    event: e,
+                    ..
+                } = notification
+                {
+                    return relay_url == url && e.id == event.id;
+                }
+            }
+            false
+        })
+        .await
+        .unwrap();
+        assert!(found);
+    }
+
+    #[tokio::test]
+    async fn test_preset_resubscribe() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        pool.register_filter_preset(
+            "dms-for",
+            [FilterTemplate::new(r##"{"kinds":[4],"#p":["{pubkey}"]}"##)],
+        )
+        .await;
+
+        // Missing value
+        assert!(matches!(
+            pool.subscribe_preset("dms-for", SubscribeOptions::default())
+                .await
+                .unwrap_err(),
+            Error::FilterPreset(..)
+        ));
+
+        let first = Keys::generate().public_key();
+        pool.set_preset_value("pubkey", first).await.unwrap();
+
+        let output = pool
+            .subscribe_preset("dms-for", SubscribeOptions::default())
+            .await
+            .unwrap();
+        let id = output.val;
+
+        assert_eq!(
+            pool.subscription(&id).await.unwrap(),
+            vec![Filter::new()
+                .kind(Kind::EncryptedDirectMessage)
+                .pubkey(first)]
+        );
+
+        // Switch account
+        let second = Keys::generate().public_key();
+        pool.set_preset_value("pubkey", second).await.unwrap();
+
+        let expected = vec![Filter::new()
+            .kind(Kind::EncryptedDirectMessage)
+            .pubkey(second)];
+        assert_eq!(pool.subscription(&id).await.unwrap(), expected);
+
+        let relay = pool.relay(&url).await.unwrap();
+        assert_eq!(relay.subscription(&id).await.unwrap(), expected);
+
+        // A value that can't be instantiated isn't applied
+        pool.register_filter_preset(
+            "limited",
+            [FilterTemplate::new(r#"{"kinds":[1],"limit":{limit}}"#)],
+        )
+        .await;
+        pool.set_preset_value("limit", 10).await.unwrap();
+        let limited = pool
+            .subscribe_preset("limited", SubscribeOptions::default())
+            .await
+            .unwrap()
+            .val;
+        assert!(pool.set_preset_value("limit", "ten").await.is_err());
+        assert_eq!(
+            pool.subscription(&limited).await.unwrap(),
+            vec![Filter::new().kind(Kind::TextNote).limit(10)]
+        );
+        assert_eq!(pool.subscription(&id).await.unwrap(), expected);
+
+        // Not updated anymore after unsubscribing
+        pool.unsubscribe(id.clone()).await;
+        pool.set_preset_value("pubkey", first).await.unwrap();
+        assert!(pool.subscription(&id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_multiplexed() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let mut notifications = pool.notifications();
+
+        let keys = Keys::generate();
+
+        let notes = pool
+            .subscribe_multiplexed(vec![Filter::new()
+                .kind(Kind::TextNote)
+                .author(keys.public_key())])
+            .await
+            .unwrap()
+            .val;
+        let metadata = pool
+            .subscribe_multiplexed(vec![Filter::new()
+                .kind(Kind::Metadata)
+                .author(keys.public_key())])
+            .await
+            .unwrap()
+            .val;
+
+        // Single REQ with the merged filter
+        let relay = pool.relay(&url).await.unwrap();
+        let subscriptions = relay.subscriptions().await;
+        assert_eq!(subscriptions.len(), 1);
+        let (physical, filters) = subscriptions.into_iter().next().unwrap();
+        assert_eq!(
+            filters,
+            vec![Filter::new()
+                .kinds([Kind::TextNote, Kind::Metadata])
+                .author(keys.public_key())]
+        );
+
+        // Demultiplex
+        let event = EventBuilder::text_note("Multiplexed")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let frames = vec![CapturedFrame {
+            timestamp_ms: 1000,
+            relay_url: url.clone(),
+            direction: CaptureDirection::Inbound,
+            message: RelayMessage::event(physical.clone(), event.clone()).as_json(),
+        }];
+        pool.replay(frames, ReplaySpeed::Instant).await.unwrap();
+
+        let received = tokio::time::timeout(Duration::from_secs(1), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Event {
+                    subscription_id, ..
+                } = notification
+                {
+                    if subscription_id == notes {
+                        return subscription_id;
+                    }
+                    assert_ne!(subscription_id, metadata);
+                }
+            }
+            panic!("notifications closed");
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, notes);
+
+        // Update the REQ after unsubscribing
+        pool.unsubscribe_multiplexed(&notes).await;
+        assert_eq!(
+            relay.subscription(&physical).await.unwrap(),
+            vec![Filter::new().kind(Kind::Metadata).author(keys.public_key())]
+        );
+
+        pool.unsubscribe_multiplexed(&metadata).await;
+        assert!(relay.subscriptions().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_multiplexed_stored_events() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        // Store the events, so the ones resent by the relay aren't new
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        });
+        let state = SharedState::new(
+            database.into_nostr_database(),
+            None,
+            crate::RelayFilteringMode::default(),
+            true,
+            0,
+            None,
+            None,
+            Arc::new(crate::transport::DefaultWebsocketTransport),
+        );
+        let pool = RelayPool::__with_shared_state(RelayPoolOptions::default(), state);
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let keys = Keys::generate();
+        let metadata = EventBuilder::metadata(&Metadata::new().name("alice"))
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event(metadata.clone()).await.unwrap();
+
+        let _notes = pool
+            .subscribe_multiplexed(vec![Filter::new()
+                .kind(Kind::TextNote)
+                .author(keys.public_key())])
+            .await
+            .unwrap();
+
+        // Replace the merged REQ: the stored event is forwarded to the new logical subscription
+        let mut notifications = pool.notifications();
+        let id = pool
+            .subscribe_multiplexed(vec![Filter::new()
+                .kind(Kind::Metadata)
+                .author(keys.public_key())])
+            .await
+            .unwrap()
+            .val;
+
+        let received = tokio::time::timeout(Duration::from_secs(2), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayPoolNotification::Event {
+                    subscription_id,
+                    event,
+                    ..
+                } = notification
+                {
+                    if subscription_id == id {
+                        return event.id;
+                    }
+                }
+            }
+            panic!("notifications closed");
+        })
+        .await
+        .unwrap();
+        assert_eq!(received, metadata.id);
+
+        // Relay added after the REQ
+        let other = MockRelay::run().await.unwrap();
+        let other_url = RelayUrl::parse(&other.url()).unwrap();
+        pool.add_relay(&other_url, RelayOptions::default())
+            .await
+            .unwrap();
+        let relay = pool.relay(&url).await.unwrap();
+        let other_relay = pool.relay(&other_url).await.unwrap();
+        assert_eq!(
+            other_relay.subscriptions().await,
+            relay.subscriptions().await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+
+        pool.add_relay(&url, RelayOptions::default().capture(Some(10)))
+            .await
+            .unwrap();
+
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let id = pool
+            .subscribe(vec![filter.clone()], SubscribeOptions::default())
+            .await
+            .unwrap()
+            .val;
+
+        let relay = pool.relay(&url).await.unwrap();
+
+        // Pause
+        pool.pause().await.unwrap();
+        assert_eq!(relay.status(), RelayStatus::Terminated);
+        assert_eq!(relay.subscription(&id).await.unwrap(), vec![filter]);
+
+        // Resume
+        pool.resume(Some(Duration::from_secs(1))).await;
+        assert_eq!(relay.status(), RelayStatus::Connected);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // Subscription sent again with since
+        let reqs: Vec<ClientMessage> = relay
+            .capture()
+            .await
+            .into_iter()
+            .filter(|frame| frame.direction == CaptureDirection::Outbound)
+            .filter_map(|frame| ClientMessage::from_json(frame.message).ok())
+            .filter(|msg| matches!(msg, ClientMessage::Req { .. }))
+            .collect();
+        assert_eq!(reqs.len(), 2);
+        match &reqs[1] {
+            ClientMessage::Req {
+                subscription_id,
+                filters,
+            } => {
+                assert_eq!(subscription_id, &id);
+                assert!(filters[0].since.is_some());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relay_disconnected_notification() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let mut notifications = pool.notifications();
+
+        pool.disconnect_relay(&url).await.unwrap();
+
+        let reason = tokio::time::timeout(Duration::from_secs(2), async {
+            loop {
+                if let Ok(RelayPoolNotification::RelayDisconnected { relay_url, reason }) =
+                    notifications.recv().await
+                {
+                    assert_eq!(relay_url, url);
+                    break reason;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(reason, DisconnectReason::Terminated);
+    }
+
+    #[derive(Debug)]
+    struct NoRepostMiddleware;
+
+    impl OutgoingMiddleware for NoRepostMiddleware {
+        fn process<'a>(&'a self, event: UnsignedEvent) -> BoxedFuture<'a, MiddlewareDecision> {
+            Box::pin(async move {
+                if event.kind == Kind::Repost {
+                    MiddlewareDecision::veto("no reposts")
+                } else {
+                    MiddlewareDecision::Continue(event)
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outgoing_middleware() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        pool.add_outgoing_middleware(NoRepostMiddleware).await;
+
+        let keys = Keys::generate();
+
+        // Unchanged event: sent with the original signature
+        let note = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let output = pool.send_event(note.clone()).await.unwrap();
+        assert_eq!(output.val, note.id);
+
+        // Vetoed event
+        let repost = EventBuilder::new(Kind::Repost, "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(matches!(
+            pool.send_event(repost.clone()).await.unwrap_err(),
+            Error::EventVetoed { .. }
+        ));
+        assert!(pool
+            .database()
+            .event_by_id(&repost.id)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingHandler {
+        handled: Arc<std::sync::Mutex<Vec<EventId>>>,
+    }
+
+    impl EventHandler for CountingHandler {
+        fn handle<'a>(
+            &'a self,
+            _relay_url: &'a RelayUrl,
+            _subscription_id: &'a SubscriptionId,
+            event: &'a Event,
+        ) -> BoxedFuture<'a, ()> {
+            Box::pin(async move {
+                self.handled.lock().unwrap().push(event.id);
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handlers_dispatch() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let pool = RelayPool::default();
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        let handler = CountingHandler::default();
+        let handled = handler.handled.clone();
+        pool.register_handler([Kind::TextNote], handler).await;
+
+        // Overlapping multiplexed subscriptions: the event is notified for every subscription
+        let keys = Keys::generate();
+        pool.subscribe_multiplexed(vec![Filter::new().kind(Kind::TextNote)])
+            .await
+            .unwrap();
+        pool.subscribe_multiplexed(vec![Filter::new().author(keys.public_key())])
+            .await
+            .unwrap();
+
+        // Publish from another pool, so the events are new
+        let publisher = RelayPool::default();
+        publisher
+            .add_relay(&url, RelayOptions::default())
+            .await
+            .unwrap();
+        publisher.connect(Some(Duration::from_secs(1))).await;
+
+        let note = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let reaction = EventBuilder::new(Kind::Reaction, "+")
+            .sign_with_keys(&keys)
+            .unwrap();
+        publisher.send_event(note.clone()).await.unwrap();
+        publisher.send_event(reaction).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while handled.lock().unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // Let the duplicated notifications arrive
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(*handled.lock().unwrap(), vec![note.id]);
+    }
+
+    #[derive(Debug)]
+    struct HashtagMiddleware;
+
+    impl OutgoingMiddleware for HashtagMiddleware {
+        fn process<'a>(&'a self, mut event: UnsignedEvent) -> BoxedFuture<'a, MiddlewareDecision> {
+            Box::pin(async move {
+                let mut tags: Vec<Tag> = event.tags.to_vec();
+                tags.push(Tag::hashtag("middleware"));
+                event.tags = Tags::new(tags);
+                MiddlewareDecision::Continue(event)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_outgoing_middleware_re_sign() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        });
+        let state = SharedState::new(
+            database.into_nostr_database(),
+            None,
+            crate::RelayFilteringMode::default(),
+            true,
+            0,
+            None,
+            None,
+            Arc::new(crate::transport::DefaultWebsocketTransport),
+        );
+        let keys = Keys::generate();
+        state.set_signer(keys.clone()).await;
+
+        let pool = RelayPool::__with_shared_state(RelayPoolOptions::default(), state);
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.connect(Some(Duration::from_secs(1))).await;
+
+        pool.add_outgoing_middleware(HashtagMiddleware).await;
+
+        // Modified event: re-signed with the signer
+        let note = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let output = pool.send_event(note.clone()).await.unwrap();
+        assert_ne!(output.val, note.id);
+
+        let sent: Event = pool
+            .database()
+            .event_by_id(&output.val)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(sent.verify().is_ok());
+        assert_eq!(sent.content, note.content);
+        assert!(sent.tags.hashtags().any(|t| t == "middleware"));
+
+        // The events of other authors can't be re-signed
+        let other = EventBuilder::text_note("Hi")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        assert!(matches!(
+            pool.send_event(other).await.unwrap_err(),
+            Error::Middleware(..)
+        ));
+
+        // The events uploaded by the negentropy sync go through the middlewares too
+        let stored = EventBuilder::text_note("Stored")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.database().save_event(&stored).await.unwrap();
+
+        let relay = pool.relay(&url).await.unwrap();
+        let opts = SyncOptions::default().direction(SyncDirection::Up);
+        let output = relay
+            .sync(Filter::new().author(keys.public_key()), &opts)
+            .await
+            .unwrap();
+        assert!(output.local.contains(&stored.id));
+        assert!(!output.sent.contains(&stored.id));
+        assert_eq!(output.sent.len(), 1);
+
+        let uploaded: EventId = *output.sent.iter().next().unwrap();
+        let uploaded: Event = pool
+            .database()
+            .event_by_id(&uploaded)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(uploaded.content, stored.content);
+        assert!(uploaded.tags.hashtags().any(|t| t == "middleware"));
+    }
+
+    #[tokio::test]
+    async fn test_vanish_request_after_send() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+        let offline = RelayUrl::parse("ws://127.0.0.1:1").unwrap();
+
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        });
+        let state = SharedState::new(
+            database.into_nostr_database(),
+            None,
+            crate::RelayFilteringMode::default(),
+            true,
+            0,
+            None,
+            None,
+            Arc::new(crate::transport::DefaultWebsocketTransport),
+        );
+        let keys = Keys::generate();
+        state.set_signer(keys.clone()).await;
+
+        let pool = RelayPool::__with_shared_state(RelayPoolOptions::default(), state);
+        pool.add_relay(&url, RelayOptions::default()).await.unwrap();
+        pool.add_relay(&offline, RelayOptions::default())
+            .await
+            .unwrap();
+        pool.connect_relay(&url, Some(Duration::from_secs(1)))
+            .await
+            .unwrap();
+
+        let note = EventBuilder::text_note("Hello")
+            .custom_created_at(Timestamp::from(1_000))
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.database().save_event(&note).await.unwrap();
+
+        let is_stored = |id: EventId| {
+            let database = pool.database().clone();
+            async move { database.event_by_id(&id).await.unwrap().is_some() }
+        };
+
+        // Failed send: nothing purged
+        let request = EventBuilder::request_vanish(VanishTarget::AllRelays, "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(pool.send_event_to([&offline], request).await.is_err());
+        assert!(is_stored(note.id).await);
+
+        // Request targeting a single relay: nothing purged
+        let request = EventBuilder::request_vanish(VanishTarget::Relays(vec![url.clone()]), "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event_to([&url], request).await.unwrap();
+        assert!(is_stored(note.id).await);
+
+        // Request targeting all the relays, accepted: purged, except the request itself
+        let request = EventBuilder::request_vanish(VanishTarget::AllRelays, "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.send_event_to([&url], request.clone()).await.unwrap();
+        assert!(!is_stored(note.id).await);
+        assert!(is_stored(request.id).await);
+    }
+
+    #[tokio::test]
+    async fn test_vanish_request_batched() {
+        use async_utility::futures_util::SinkExt;
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Relay that rejects all the events
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rejecting =
+            RelayUrl::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, ..)) = listener.accept().await {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                while let Some(Ok(Message::Text(msg))) = ws.next().await {
+                    if let Ok(ClientMessage::Event(event)) = ClientMessage::from_json(msg) {
+                        let ok = RelayMessage::ok(event.id, false, "blocked: not allowed");
+                        ws.send(Message::Text(ok.as_json())).await.unwrap();
+                    }
+                }
+            }
+        });
+
+        let mock = MockRelay::run().await.unwrap();
+        let accepting = RelayUrl::parse(&mock.url()).unwrap();
+
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        });
+        let state = SharedState::new(
+            database.into_nostr_database(),
+            None,
+            crate::RelayFilteringMode::default(),
+            true,
+            0,
+            None,
+            None,
+            Arc::new(crate::transport::DefaultWebsocketTransport),
+        );
+        let keys = Keys::generate();
+        state.set_signer(keys.clone()).await;
+
+        let pool = RelayPool::__with_shared_state(RelayPoolOptions::default(), state);
+        for url in [&rejecting, &accepting] {
+            pool.add_relay(url, RelayOptions::default()).await.unwrap();
+            pool.connect_relay(url, Some(Duration::from_secs(1)))
+                .await
+                .unwrap();
+        }
+
+        let note = EventBuilder::text_note("Hello")
+            .custom_created_at(Timestamp::from(1_000))
+            .sign_with_keys(&keys)
+            .unwrap();
+        pool.database().save_event(&note).await.unwrap();
+
+        let is_stored = |id: EventId| {
+            let database = pool.database().clone();
+            async move { database.event_by_id(&id).await.unwrap().is_some() }
+        };
+
+        let request = EventBuilder::request_vanish(VanishTarget::AllRelays, "")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        // Queued and rejected: nothing purged
+        pool.batch_msg_to([&rejecting], vec![ClientMessage::event(request.clone())])
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(is_stored(note.id).await);
+
+        // Accepted: purged, except the request itself
+        pool.batch_msg_to([&accepting], vec![ClientMessage::event(request.clone())])
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert!(!is_stored(note.id).await);
+        assert!(is_stored(request.id).await);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_restore() {
+        let url = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let id = SubscriptionId::new("feed");
+        let filters = vec![Filter::new().kind(Kind::TextNote)];
+
+        let pool = RelayPool::default();
+        pool.add_relay(
+            &url,
+            RelayOptions::default().flags(RelayServiceFlags::READ | RelayServiceFlags::PING),
+        )
+        .await
+        .unwrap();
+        pool.inner
+            .save_subscription(id.clone(), filters.clone())
+            .await;
+        let relay = pool.relay(&url).await.unwrap();
+        relay
+            .inner
+            .update_subscription(id.clone(), filters.clone(), false)
+            .await;
+
+        // Not connected: the REQ is sent at connection
+        let multiplexed_filters = vec![Filter::new().kind(Kind::Metadata)];
+        let _ = pool
+            .subscribe_multiplexed(multiplexed_filters.clone())
+            .await
+            .unwrap();
+        assert_eq!(relay.subscriptions().await.len(), 2);
+
+        #[cfg(feature = "nip11")]
+        relay.set_paid(true);
+
+        let mut snapshot = pool.snapshot().await;
+        assert_eq!(snapshot.multiplexed.len(), 1);
+        // The multiplexed REQs aren't included
+        assert_eq!(snapshot.relays[0].subscriptions.len(), 1);
+
+        // Banned relay
+        let banned_until = Timestamp::now() + Duration::from_secs(3600);
+        snapshot.relays[0].status = RelayStatus::Banned;
+        snapshot.relays[0].banned_until = Some(banned_until);
+
+        let snapshot = RelayPoolSnapshot::from_json(snapshot.as_json()).unwrap();
+
+        let restored = RelayPool::default();
+        restored
+            .restore(snapshot, RelayOptions::default())
+            .await
+            .unwrap();
+
+        let relay = restored.relay(&url).await.unwrap();
+        assert!(relay.flags().has_read());
+        #[cfg(feature = "nip11")]
+        assert!(relay.is_paid());
+        assert!(!relay.flags().has_write());
+        assert_eq!(restored.subscription(&id).await, Some(filters.clone()));
+        assert_eq!(relay.subscription(&id).await, Some(filters));
+
+        // The multiplexed subscription is restored, with its REQ
+        let multiplexed: Vec<Vec<Filter>> = restored
+            .snapshot()
+            .await
+            .multiplexed
+            .into_values()
+            .collect();
+        assert_eq!(multiplexed, vec![multiplexed_filters]);
+        assert_eq!(relay.subscriptions().await.len(), 2);
+
+        // Still banned: no connection attempt
+        assert_eq!(relay.inner.banned_until(), Some(banned_until));
+        restored.connect(None).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(relay.status(), RelayStatus::Banned);
+        assert_eq!(relay.stats().attempts(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_long_lived_tasks() {
+        let pool = RelayPool::default();
+        pool.spawn_relay_optimizer(RelayOptimizerOptions::default());
+
+        // Don't wait for the next optimization
+        let start = std::time::Instant::now();
+        pool.shutdown().await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(pool.tasks().iter().all(|task| !task.state.is_running()));
+    }
 }
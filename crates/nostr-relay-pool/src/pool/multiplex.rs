@@ -0,0 +1,327 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! REQ multiplexing
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Weak};
+
+use nostr::{Event, Filter, RelayMessage, RelayUrl, SubscriptionId};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::RwLock;
+
+use super::{NotificationSender, RelayPoolNotification};
+use crate::relay::Relay;
+use crate::shared::SharedState;
+use crate::supervisor;
+
+/// Logical subscription
+#[derive(Debug, Clone, Default)]
+pub(super) struct LogicalSubscription {
+    pub filters: Vec<Filter>,
+    /// Physical REQs, for each relay, of which the stored events are still expected (EOSE not received yet)
+    ///
+    /// The relay resends the stored events when a REQ is replaced: they aren't new,
+    /// so they must be forwarded from the messages and not from the event notifications.
+    backfill: HashMap<RelayUrl, HashSet<SubscriptionId>>,
+}
+
+impl LogicalSubscription {
+    pub fn new(filters: Vec<Filter>) -> Self {
+        Self {
+            filters,
+            backfill: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    fn is_backfilling(&self, relay_url: &RelayUrl) -> bool {
+        self.backfill.contains_key(relay_url)
+    }
+
+    #[inline]
+    fn matches(&self, event: &Event) -> bool {
+        self.filters.iter().any(|f| f.match_event(event))
+    }
+}
+
+/// Update of the multiplexed REQs of a relay
+pub(super) struct RelayReqs {
+    pub relay: Relay,
+    /// REQs to close
+    pub close: Vec<SubscriptionId>,
+    /// REQs to send (or replace)
+    pub send: Vec<(SubscriptionId, Vec<Filter>)>,
+}
+
+#[derive(Debug)]
+pub(super) struct Multiplexer {
+    /// Prefix of the physical subscription IDs
+    prefix: SubscriptionId,
+    /// Logical subscriptions
+    pub logical: HashMap<SubscriptionId, LogicalSubscription>,
+    /// Physical subscriptions (REQs) of each relay
+    pub physical: HashMap<RelayUrl, Vec<SubscriptionId>>,
+    /// Demultiplexer spawned
+    pub demux: bool,
+}
+
+impl Default for Multiplexer {
+    fn default() -> Self {
+        Self {
+            prefix: SubscriptionId::generate(),
+            logical: HashMap::new(),
+            physical: HashMap::new(),
+            demux: false,
+        }
+    }
+}
+
+impl Multiplexer {
+    /// Physical subscription ID
+    fn physical_id(&self, index: usize) -> SubscriptionId {
+        SubscriptionId::new(format!("{}-{index}", self.prefix))
+    }
+
+    /// Physical subscription IDs of `len` REQs
+    pub fn physical_ids(&self, len: usize) -> Vec<SubscriptionId> {
+        (0..len).map(|index| self.physical_id(index)).collect()
+    }
+
+    /// Merge the filters of all the logical subscriptions
+    pub fn merged_filters(&self) -> Vec<Filter> {
+        merge_filters(
+            self.logical
+                .values()
+                .flat_map(|logical| logical.filters.iter())
+                .cloned(),
+        )
+    }
+
+    /// Expect the stored events of the replaced REQs for the logical subscription
+    pub fn backfill(
+        &mut self,
+        id: &SubscriptionId,
+        relay_url: RelayUrl,
+        reqs: Vec<SubscriptionId>,
+    ) {
+        if let Some(logical) = self.logical.get_mut(id) {
+            if !reqs.is_empty() {
+                logical
+                    .backfill
+                    .insert(relay_url, reqs.into_iter().collect());
+            }
+        }
+    }
+
+    fn is_physical(&self, relay_url: &RelayUrl, id: &SubscriptionId) -> bool {
+        self.physical
+            .get(relay_url)
+            .map_or(false, |ids| ids.contains(id))
+    }
+
+    /// The stored events of the physical REQ have been received (or will never be received)
+    pub fn eose(&mut self, relay_url: &RelayUrl, id: &SubscriptionId) {
+        for logical in self.logical.values_mut() {
+            if let Some(reqs) = logical.backfill.get_mut(relay_url) {
+                reqs.remove(id);
+                if reqs.is_empty() {
+                    logical.backfill.remove(relay_url);
+                }
+            }
+        }
+    }
+
+    /// Get the logical subscriptions that must receive the event
+    fn targets(
+        &self,
+        relay_url: &RelayUrl,
+        event: &Event,
+        backfilling: bool,
+    ) -> Vec<SubscriptionId> {
+        self.logical
+            .iter()
+            .filter(|(_, logical)| logical.is_backfilling(relay_url) == backfilling)
+            .filter(|(_, logical)| logical.matches(event))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+/// Merge the compatible filters (see [`Filter::try_merge`])
+pub(super) fn merge_filters<I>(filters: I) -> Vec<Filter>
+where
+    I: IntoIterator<Item = Filter>,
+{
+    let mut merged: Vec<Filter> = Vec::new();
+
+    for filter in filters.into_iter() {
+        let found: Option<(usize, Filter)> = merged
+            .iter()
+            .enumerate()
+            .find_map(|(index, f)| f.try_merge(&filter).map(|m| (index, m)));
+
+        match found {
+            Some((index, m)) => merged[index] = m,
+            None => merged.push(filter),
+        }
+    }
+
+    merged
+}
+
+/// Split the filters in REQs of at most `max_filters` filters
+pub(super) fn split_filters(filters: Vec<Filter>, max_filters: Option<usize>) -> Vec<Vec<Filter>> {
+    if filters.is_empty() {
+        return Vec::new();
+    }
+
+    match max_filters {
+        Some(max) if max > 0 => filters.chunks(max).map(|c| c.to_vec()).collect(),
+        _ => vec![filters],
+    }
+}
+
+/// Forward the events received on the physical subscriptions to the matching logical subscriptions
+///
+/// If the demultiplexer panics, it's restarted.
+pub(super) fn spawn_demultiplexer(
+    state: &SharedState,
+    mut notifications: Receiver<RelayPoolNotification>,
+    sender: NotificationSender,
+    multiplexer: Weak<RwLock<Multiplexer>>,
+) {
+    state.spawn("demultiplexer", async move {
+        while let Err(reason) =
+            supervisor::catch_panic(demultiplex(&mut notifications, &sender, &multiplexer)).await
+        {
+            tracing::error!(reason = %reason, "Demultiplexer panicked, restarting it.");
+            sender.send(RelayPoolNotification::TaskPanicked {
+                relay_url: None,
+                task: String::from("demultiplexer"),
+                reason,
+            });
+        }
+    });
+}
+
+async fn demultiplex(
+    notifications: &mut Receiver<RelayPoolNotification>,
+    sender: &NotificationSender,
+    multiplexer: &Weak<RwLock<Multiplexer>>,
+) {
+    loop {
+        let (relay_url, subscription_id, event, backfilling) = match notifications.recv().await {
+            // New events
+            Ok(RelayPoolNotification::Event {
+                relay_url,
+                subscription_id,
+                event,
+            }) => (relay_url, subscription_id, event, false),
+            // Any event (also the already stored ones)
+            Ok(RelayPoolNotification::Message {
+                relay_url,
+                message:
+                    RelayMessage::Event {
+                        subscription_id,
+                        event,
+                    },
+            }) => {
+                // The message of a not new event isn't verified: check that the ID matches the stored event
+                if !event.verify_id() {
+                    continue;
+                }
+
+                (relay_url, subscription_id, event, true)
+            }
+            Ok(RelayPoolNotification::Message {
+                relay_url,
+                message: RelayMessage::EndOfStoredEvents(subscription_id),
+            }) => {
+                let multiplexer = match multiplexer.upgrade() {
+                    Some(multiplexer) => multiplexer,
+                    None => break,
+                };
+                let mut multiplexer = multiplexer.write().await;
+                if multiplexer.is_physical(&relay_url, &subscription_id) {
+                    multiplexer.eose(&relay_url, &subscription_id);
+                }
+                continue;
+            }
+            Ok(RelayPoolNotification::Shutdown) => break,
+            Ok(..) => continue,
+            Err(RecvError::Lagged(n)) => {
+                tracing::warn!("Demultiplexer lagged: {n} notifications skipped.");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        // Stop if the pool has been dropped
+        let multiplexer = match multiplexer.upgrade() {
+            Some(multiplexer) => multiplexer,
+            None => break,
+        };
+
+        // Collect the targets and release the lock before sending
+        let targets: Vec<SubscriptionId> = {
+            let multiplexer = multiplexer.read().await;
+
+            if !multiplexer.is_physical(&relay_url, &subscription_id) {
+                continue;
+            }
+
+            multiplexer.targets(&relay_url, &event, backfilling)
+        };
+
+        for id in targets.into_iter() {
+            sender.send(RelayPoolNotification::Event {
+                relay_url: relay_url.clone(),
+                subscription_id: id,
+                event: event.clone(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{Keys, Kind};
+
+    use super::*;
+
+    #[test]
+    fn test_merge_and_split_filters() {
+        let pk1 = Keys::generate().public_key();
+        let pk2 = Keys::generate().public_key();
+
+        let filters = vec![
+            Filter::new().kind(Kind::TextNote).author(pk1),
+            Filter::new().kind(Kind::Metadata).author(pk1),
+            Filter::new().kind(Kind::TextNote).author(pk2),
+            Filter::new().kind(Kind::TextNote).limit(10),
+        ];
+
+        let merged = merge_filters(filters);
+        assert_eq!(
+            merged,
+            vec![
+                Filter::new()
+                    .kinds([Kind::TextNote, Kind::Metadata])
+                    .author(pk1),
+                Filter::new().kind(Kind::TextNote).author(pk2),
+                Filter::new().kind(Kind::TextNote).limit(10),
+            ]
+        );
+
+        let reqs = split_filters(merged.clone(), Some(2));
+        assert_eq!(reqs.len(), 2);
+        assert_eq!(reqs[0].len(), 2);
+        assert_eq!(reqs[1].len(), 1);
+
+        assert_eq!(split_filters(merged, None).len(), 1);
+        assert!(split_filters(Vec::new(), Some(2)).is_empty());
+    }
+}
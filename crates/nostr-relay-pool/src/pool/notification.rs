@@ -0,0 +1,147 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Pool notifications
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use nostr::types::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+use super::RelayPoolNotification;
+
+/// Pool notification with sequence number
+///
+/// Check [`RelayPool::sequenced_notifications`](super::RelayPool::sequenced_notifications).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequencedNotification {
+    /// Sequence number
+    ///
+    /// Increased by `1` for every notification sent by the pool, also when nobody is listening,
+    /// so the first received notification can have any number.
+    /// The notifications sent concurrently can be received out of order:
+    /// a number still missing after the following ones means a missed notification (i.e. lagged receiver).
+    pub seq: u64,
+    /// When the notification has been produced (UNIX timestamp in milliseconds)
+    pub received_at_ms: u64,
+    /// Notification
+    pub notification: RelayPoolNotification,
+}
+
+/// Sender of the pool notifications
+#[derive(Debug, Clone)]
+pub(crate) struct NotificationSender {
+    notifications: Sender<RelayPoolNotification>,
+    sequenced: Sender<SequencedNotification>,
+    seq: Arc<AtomicU64>,
+}
+
+impl NotificationSender {
+    pub(crate) fn new(channel_size: usize) -> Self {
+        let (notifications, ..) = broadcast::channel(channel_size);
+        let (sequenced, ..) = broadcast::channel(channel_size);
+        Self {
+            notifications,
+            sequenced,
+            seq: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn subscribe(&self) -> Receiver<RelayPoolNotification> {
+        self.notifications.subscribe()
+    }
+
+    #[inline]
+    pub(crate) fn subscribe_sequenced(&self) -> Receiver<SequencedNotification> {
+        self.sequenced.subscribe()
+    }
+
+    /// Get the inner sender of the notifications without sequence number
+    #[inline]
+    pub(crate) fn into_inner(self) -> Sender<RelayPoolNotification> {
+        self.notifications
+    }
+
+    pub(crate) fn send(&self, notification: RelayPoolNotification) {
+        let seq: u64 = self.seq.fetch_add(1, Ordering::SeqCst) + 1;
+
+        // Clone the notification only if someone is listening
+        if self.sequenced.receiver_count() > 0 {
+            let received_at_ms: u64 = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+
+            let _ = self.sequenced.send(SequencedNotification {
+                seq,
+                received_at_ms,
+                notification: notification.clone(),
+            });
+        }
+
+        let _ = self.notifications.send(notification);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequenced_notifications() {
+        let sender = NotificationSender::new(16);
+        let mut notifications = sender.subscribe();
+
+        // Not listening yet
+        sender.send(RelayPoolNotification::Shutdown);
+
+        let mut sequenced = sender.subscribe_sequenced();
+        sender.send(RelayPoolNotification::Shutdown);
+        sender.send(RelayPoolNotification::Shutdown);
+
+        let first = sequenced.try_recv().unwrap();
+        let second = sequenced.try_recv().unwrap();
+        assert_eq!(first.seq, 2);
+        assert_eq!(second.seq, 3);
+        assert!(second.received_at_ms >= first.received_at_ms);
+        assert!(sequenced.try_recv().is_err());
+
+        for _ in 0..3 {
+            assert_eq!(
+                notifications.try_recv().unwrap(),
+                RelayPoolNotification::Shutdown
+            );
+        }
+    }
+
+    #[test]
+    fn test_sequenced_notifications_concurrent_senders() {
+        let sender = NotificationSender::new(1024);
+        let mut sequenced = sender.subscribe_sequenced();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        sender.send(RelayPoolNotification::Shutdown);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Every number is assigned once
+        let mut seqs: Vec<u64> = Vec::new();
+        while let Ok(notification) = sequenced.try_recv() {
+            seqs.push(notification.seq);
+        }
+        seqs.sort_unstable();
+        assert_eq!(seqs, (1..=400).collect::<Vec<u64>>());
+    }
+}
@@ -0,0 +1,352 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay optimizer
+//!
+//! Score the relays of the pool using their stats and replace the underperforming ones
+//! with relays taken from a candidate pool.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use nostr::{RelayUrl, Timestamp};
+
+use crate::relay::options::RelayOptions;
+use crate::relay::RelayConnectionStats;
+
+const DEFAULT_MIN_SCORE: f64 = 0.3;
+const DEFAULT_MAX_SWAPS: usize = 1;
+const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(60 * 10);
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const MIN_FAILED_ATTEMPTS: usize = 3;
+
+const RELIABILITY_WEIGHT: f64 = 0.35;
+const CONTRIBUTION_WEIGHT: f64 = 0.35;
+const LATENCY_WEIGHT: f64 = 0.15;
+const EOSE_WEIGHT: f64 = 0.15;
+
+/// Relay score
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelayScore {
+    /// Relay URL
+    pub url: RelayUrl,
+    /// Score, from `0.0` (worst) to `1.0` (best)
+    pub score: f64,
+    /// Connection success rate
+    pub success_rate: f64,
+    /// Average ping latency
+    pub latency: Option<Duration>,
+    /// Average time between the `REQ` and the `EOSE`
+    pub eose_latency: Option<Duration>,
+    /// Number of unique events contributed
+    pub unique_events: usize,
+    /// If there are enough data to rate the relay
+    ///
+    /// The relays connected for less than the grace period are not rated.
+    pub rated: bool,
+}
+
+/// Relay swap
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelaySwap {
+    /// Underperforming relay to remove
+    pub remove: RelayUrl,
+    /// Score of the removed relay
+    pub score: f64,
+    /// Candidate relay to add
+    pub add: RelayUrl,
+}
+
+/// Events contributed by a relay
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelayContribution {
+    /// Relay URL
+    pub url: RelayUrl,
+    /// Events not received before from other relays
+    pub unique: usize,
+    /// Events already received from other relays
+    pub duplicates: usize,
+}
+
+impl RelayContribution {
+    /// Total events received
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.unique.saturating_add(self.duplicates)
+    }
+
+    /// Ratio of duplicate events, from `0.0` to `1.0`
+    ///
+    /// A relay with a ratio close to `1.0` is redundant: all its events are already received from other relays.
+    pub fn duplicate_ratio(&self) -> f64 {
+        let total: usize = self.total();
+        if total > 0 {
+            self.duplicates as f64 / total as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Relay optimizer options
+#[derive(Debug, Clone)]
+pub struct RelayOptimizerOptions {
+    pub(super) candidates: Vec<RelayUrl>,
+    pub(super) pinned: HashSet<RelayUrl>,
+    pub(super) min_score: f64,
+    pub(super) max_swaps: usize,
+    pub(super) grace_period: Duration,
+    pub(super) interval: Duration,
+    pub(super) auto_swap: bool,
+    pub(super) relay_opts: RelayOptions,
+}
+
+impl Default for RelayOptimizerOptions {
+    fn default() -> Self {
+        Self {
+            candidates: Vec::new(),
+            pinned: HashSet::new(),
+            min_score: DEFAULT_MIN_SCORE,
+            max_swaps: DEFAULT_MAX_SWAPS,
+            grace_period: DEFAULT_GRACE_PERIOD,
+            interval: DEFAULT_INTERVAL,
+            auto_swap: false,
+            relay_opts: RelayOptions::default(),
+        }
+    }
+}
+
+impl RelayOptimizerOptions {
+    /// New default options
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Candidate relays, in order of preference
+    ///
+    /// The candidates already in the pool are skipped.
+    #[inline]
+    pub fn candidates<I>(mut self, candidates: I) -> Self
+    where
+        I: IntoIterator<Item = RelayUrl>,
+    {
+        self.candidates.extend(candidates);
+        self
+    }
+
+    /// Pinned relays: never removed, whatever their score
+    #[inline]
+    pub fn pinned<I>(mut self, relays: I) -> Self
+    where
+        I: IntoIterator<Item = RelayUrl>,
+    {
+        self.pinned.extend(relays);
+        self
+    }
+
+    /// Relays with a score lower than this are replaced (default: 0.3)
+    #[inline]
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Max number of swaps per round (default: 1)
+    #[inline]
+    pub fn max_swaps(mut self, max_swaps: usize) -> Self {
+        self.max_swaps = max_swaps;
+        self
+    }
+
+    /// Time since the first connection before rating a relay (default: 10 min)
+    #[inline]
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Interval between the rounds of the background optimizer (default: 1 hour)
+    #[inline]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Automatically apply the swaps in the background optimizer (default: false)
+    ///
+    /// If disabled, the suggested swaps are only logged.
+    #[inline]
+    pub fn auto_swap(mut self, enable: bool) -> Self {
+        self.auto_swap = enable;
+        self
+    }
+
+    /// Options of the added relays (default: [`RelayOptions::default`])
+    #[inline]
+    pub fn relay_options(mut self, opts: RelayOptions) -> Self {
+        self.relay_opts = opts;
+        self
+    }
+}
+
+fn latency_score(latency: Option<Duration>, reference: Duration) -> f64 {
+    match latency {
+        Some(latency) => 1.0 / (1.0 + latency.as_secs_f64() / reference.as_secs_f64()),
+        None => 0.5,
+    }
+}
+
+/// Score the relays
+///
+/// `now` is taken from the time provider of the pool.
+pub(super) fn score_relays(
+    relays: Vec<(RelayUrl, RelayConnectionStats)>,
+    grace_period: Duration,
+    now: Timestamp,
+) -> Vec<RelayScore> {
+    let max_unique: usize = relays
+        .iter()
+        .map(|(_, stats)| stats.unique_events())
+        .max()
+        .unwrap_or_default();
+
+    let mut scores: Vec<RelayScore> = relays
+        .into_iter()
+        .map(|(url, stats)| {
+            let attempts: usize = stats.attempts();
+            let success_rate: f64 = stats.success_rate();
+            #[cfg(not(target_arch = "wasm32"))]
+            let (latency, eose_latency) = (stats.latency(), stats.eose_latency());
+            #[cfg(target_arch = "wasm32")]
+            let (latency, eose_latency) = (None, None);
+            let unique_events: usize = stats.unique_events();
+
+            let first_connection: Timestamp = stats.first_connection_timestamp();
+            let rated: bool = if first_connection == Timestamp::zero() {
+                attempts >= MIN_FAILED_ATTEMPTS
+            } else {
+                first_connection + grace_period <= now
+            };
+
+            let reliability: f64 = if attempts > 0 { success_rate } else { 0.5 };
+            let contribution: f64 = if max_unique > 0 {
+                unique_events as f64 / max_unique as f64
+            } else {
+                0.5
+            };
+
+            let score: f64 = reliability * RELIABILITY_WEIGHT
+                + contribution * CONTRIBUTION_WEIGHT
+                + latency_score(latency, Duration::from_millis(500)) * LATENCY_WEIGHT
+                + latency_score(eose_latency, Duration::from_secs(1)) * EOSE_WEIGHT;
+
+            RelayScore {
+                url,
+                score,
+                success_rate,
+                latency,
+                eose_latency,
+                unique_events,
+                rated,
+            }
+        })
+        .collect();
+
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scores
+}
+
+/// Select the swaps, replacing the worst rated relays first
+pub(super) fn select_swaps(
+    scores: &[RelayScore],
+    in_pool: &HashSet<RelayUrl>,
+    opts: &RelayOptimizerOptions,
+) -> Vec<RelaySwap> {
+    let mut underperforming: Vec<&RelayScore> = scores
+        .iter()
+        .filter(|s| s.rated && s.score < opts.min_score && !opts.pinned.contains(&s.url))
+        .collect();
+    underperforming.sort_by(|a, b| a.score.total_cmp(&b.score));
+
+    let candidates = opts.candidates.iter().filter(|url| !in_pool.contains(*url));
+
+    underperforming
+        .into_iter()
+        .zip(candidates)
+        .take(opts.max_swaps)
+        .map(|(score, candidate)| RelaySwap {
+            remove: score.url.clone(),
+            score: score.score,
+            add: candidate.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(url: &str, value: f64, rated: bool) -> RelayScore {
+        RelayScore {
+            url: RelayUrl::parse(url).unwrap(),
+            score: value,
+            success_rate: 1.0,
+            latency: None,
+            eose_latency: None,
+            unique_events: 0,
+            rated,
+        }
+    }
+
+    #[test]
+    fn test_select_swaps() {
+        let scores = vec![
+            score("wss://good.example.com", 0.9, true),
+            score("wss://bad.example.com", 0.1, true),
+            score("wss://worse.example.com", 0.05, true),
+            score("wss://pinned.example.com", 0.0, true),
+            score("wss://new.example.com", 0.0, false),
+        ];
+        let in_pool: HashSet<RelayUrl> = scores.iter().map(|s| s.url.clone()).collect();
+
+        let opts = RelayOptimizerOptions::new()
+            .pinned([RelayUrl::parse("wss://pinned.example.com").unwrap()])
+            .candidates([
+                RelayUrl::parse("wss://good.example.com").unwrap(),
+                RelayUrl::parse("wss://candidate1.example.com").unwrap(),
+                RelayUrl::parse("wss://candidate2.example.com").unwrap(),
+            ])
+            .max_swaps(5);
+
+        let swaps = select_swaps(&scores, &in_pool, &opts);
+        assert_eq!(
+            swaps,
+            vec![
+                RelaySwap {
+                    remove: RelayUrl::parse("wss://worse.example.com").unwrap(),
+                    score: 0.05,
+                    add: RelayUrl::parse("wss://candidate1.example.com").unwrap(),
+                },
+                RelaySwap {
+                    remove: RelayUrl::parse("wss://bad.example.com").unwrap(),
+                    score: 0.1,
+                    add: RelayUrl::parse("wss://candidate2.example.com").unwrap(),
+                },
+            ]
+        );
+
+        // Max swaps
+        let swaps = select_swaps(&scores, &in_pool, &opts.max_swaps(1));
+        assert_eq!(swaps.len(), 1);
+    }
+
+    #[test]
+    fn test_latency_score() {
+        let reference = Duration::from_millis(500);
+        assert_eq!(latency_score(None, reference), 0.5);
+        assert_eq!(latency_score(Some(Duration::ZERO), reference), 1.0);
+        assert_eq!(latency_score(Some(reference), reference), 0.5);
+    }
+}
@@ -5,12 +5,14 @@
 //! Pool options
 
 use super::constants::DEFAULT_NOTIFICATION_CHANNEL_SIZE;
+use crate::retry::RetryPolicy;
 
 /// Relay Pool Options
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct RelayPoolOptions {
     pub(super) max_relays: Option<usize>,
     pub(super) notification_channel_size: usize,
+    pub(super) retry_policy: Option<RetryPolicy>,
 }
 
 impl Default for RelayPoolOptions {
@@ -18,6 +20,7 @@ impl Default for RelayPoolOptions {
         Self {
             max_relays: None,
             notification_channel_size: DEFAULT_NOTIFICATION_CHANNEL_SIZE,
+            retry_policy: None,
         }
     }
 }
@@ -42,4 +45,39 @@ impl RelayPoolOptions {
         self.notification_channel_size = size;
         self
     }
+
+    /// Retry policy shared by the relays (default: None)
+    ///
+    /// Used for the reconnections (replacing the [`RelayOptions`](crate::RelayOptions) retry interval),
+    /// for the publishing (waiting for the `OK`) and for the NIP11 document fetches.
+    /// By default, the publishing and the fetches aren't retried.
+    #[inline]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+}
+
+/// Order of the streamed events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum StreamOrder {
+    /// Stream the events as soon as they are received (default)
+    #[default]
+    Received,
+    /// Oldest first, by `created_at`
+    ///
+    /// The events are buffered until all the relays terminated the fetch (i.e. at EOSE or timeout).
+    Ascending,
+    /// Newest first, by `created_at`
+    ///
+    /// The events are buffered until all the relays terminated the fetch (i.e. at EOSE or timeout).
+    Descending,
+}
+
+impl StreamOrder {
+    /// Check if the events are streamed as soon as they are received
+    #[inline]
+    pub fn is_received(&self) -> bool {
+        matches!(self, Self::Received)
+    }
 }
@@ -0,0 +1,60 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Replay
+
+use std::time::Duration;
+
+/// Replay speed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ReplaySpeed {
+    /// Keep the original timing between the frames
+    #[default]
+    Original,
+    /// Divide the delays between the frames by the factor
+    Accelerated(u32),
+    /// Feed the frames without delays
+    Instant,
+}
+
+impl ReplaySpeed {
+    /// Delay to wait before feeding a frame
+    pub(super) fn delay(&self, previous_ms: u64, current_ms: u64) -> Option<Duration> {
+        let delta: u64 = current_ms.saturating_sub(previous_ms);
+
+        let delta: u64 = match self {
+            Self::Original => delta,
+            Self::Accelerated(factor) => delta / u64::from((*factor).max(1)),
+            Self::Instant => return None,
+        };
+
+        if delta > 0 {
+            Some(Duration::from_millis(delta))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_delay() {
+        assert_eq!(
+            ReplaySpeed::Original.delay(1000, 1500),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            ReplaySpeed::Accelerated(10).delay(1000, 1500),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(ReplaySpeed::Accelerated(1000).delay(1000, 1500), None);
+        assert_eq!(ReplaySpeed::Instant.delay(1000, 1500), None);
+
+        // Out of order frames
+        assert_eq!(ReplaySpeed::Original.delay(1500, 1000), None);
+    }
+}
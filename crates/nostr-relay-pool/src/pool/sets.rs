@@ -0,0 +1,61 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay sets
+
+use std::borrow::Cow;
+use std::fmt;
+
+/// Name of a set of relays
+///
+/// Groups the relays used for a specific purpose (i.e. DMs or search),
+/// so the subscriptions and the events can target the whole group.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RelaySet(Cow<'static, str>);
+
+impl RelaySet {
+    /// Relays where to receive the private direct messages (NIP17 `kind:10050`)
+    pub const DM: Self = Self(Cow::Borrowed("dm-relays"));
+    /// Relays used for search (NIP51 `kind:10007`)
+    pub const SEARCH: Self = Self(Cow::Borrowed("search-relays"));
+    /// Relays that rebroadcast the events to many other relays
+    pub const BLASTR: Self = Self(Cow::Borrowed("blastr"));
+    /// Read relays of the user (NIP65 `kind:10002`)
+    pub const INBOX: Self = Self(Cow::Borrowed("inbox-relays"));
+    /// Write relays of the user (NIP65 `kind:10002`)
+    pub const OUTBOX: Self = Self(Cow::Borrowed("outbox-relays"));
+
+    /// Custom relay set
+    #[inline]
+    pub fn new<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self(Cow::Owned(name.into()))
+    }
+
+    /// Get name
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RelaySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&str> for RelaySet {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for RelaySet {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Pool snapshot
+
+use std::collections::HashMap;
+
+use nostr::serde_json;
+use nostr::{EventId, Filter, RelayUrl, SubscriptionId, Timestamp};
+use nostr_database::SubscriptionCursor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Error;
+use crate::relay::{RelayServiceFlags, RelayStatus};
+
+/// Subscription snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionSnapshot {
+    /// Filters
+    pub filters: Vec<Filter>,
+    /// Newest `created_at` of the events received for the subscription
+    pub last_event_at: Option<Timestamp>,
+    /// Persistent cursor
+    #[serde(default, with = "cursor")]
+    pub cursor: Option<SubscriptionCursor>,
+}
+
+/// Relay snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelaySnapshot {
+    /// Relay URL
+    pub url: RelayUrl,
+    /// Service flags
+    pub flags: RelayServiceFlags,
+    /// Status when the snapshot has been taken
+    pub status: RelayStatus,
+    /// End of the ban, if the relay is [`RelayStatus::Banned`]
+    #[serde(default)]
+    pub banned_until: Option<Timestamp>,
+    /// Marked as paid (check `Relay::set_paid`, only with the `nip11` feature)
+    #[serde(default)]
+    pub paid: bool,
+    /// Number of connection attempts
+    pub attempts: usize,
+    /// Number of successful connections
+    pub success: usize,
+    /// Last connection
+    pub connected_at: Timestamp,
+    /// First connection
+    pub first_connection_at: Timestamp,
+    /// Relay subscriptions
+    ///
+    /// The REQs of the multiplexed subscriptions aren't included: they are composed again at restore.
+    pub subscriptions: HashMap<SubscriptionId, SubscriptionSnapshot>,
+}
+
+/// Relay pool snapshot
+///
+/// Check [`RelayPool::snapshot`](super::RelayPool::snapshot) and [`RelayPool::restore`](super::RelayPool::restore).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayPoolSnapshot {
+    /// When the snapshot has been taken
+    pub taken_at: Timestamp,
+    /// Pool subscriptions
+    pub subscriptions: HashMap<SubscriptionId, Vec<Filter>>,
+    /// Multiplexed subscriptions
+    #[serde(default)]
+    pub multiplexed: HashMap<SubscriptionId, Vec<Filter>>,
+    /// Relays
+    pub relays: Vec<RelaySnapshot>,
+}
+
+impl RelayPoolSnapshot {
+    /// Serialize as JSON
+    pub fn as_json(&self) -> String {
+        // Can't fail: all the keys are strings
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Deserialize from JSON
+    pub fn from_json<T>(json: T) -> Result<Self, Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        serde_json::from_slice(json.as_ref()).map_err(|e| Error::InvalidSnapshot(e.to_string()))
+    }
+}
+
+/// (De)serialize the [`SubscriptionCursor`] as `{"created_at": <timestamp>, "ids": [<hex>]}`
+mod cursor {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct CursorSnapshot {
+        created_at: Timestamp,
+        ids: Vec<EventId>,
+    }
+
+    pub fn serialize<S>(
+        cursor: &Option<SubscriptionCursor>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        cursor
+            .as_ref()
+            .map(|cursor| CursorSnapshot {
+                created_at: cursor.created_at(),
+                ids: cursor.ids().iter().copied().collect(),
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<SubscriptionCursor>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let snapshot: Option<CursorSnapshot> = Option::deserialize(deserializer)?;
+        Ok(snapshot.map(|snapshot| {
+            let mut cursor: SubscriptionCursor = SubscriptionCursor::new();
+            for id in snapshot.ids.into_iter() {
+                cursor.update(id, snapshot.created_at);
+            }
+            cursor
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::Kind;
+
+    use super::*;
+
+    #[test]
+    fn test_snapshot_json() {
+        let id = SubscriptionId::new("feed");
+        let filters = vec![Filter::new().kind(Kind::TextNote).limit(10)];
+
+        let mut cursor = SubscriptionCursor::new();
+        cursor.update(EventId::all_zeros(), Timestamp::from(1_699_999_999));
+        cursor.update(
+            EventId::from_byte_array([1; 32]),
+            Timestamp::from(1_699_999_999),
+        );
+
+        let snapshot = RelayPoolSnapshot {
+            taken_at: Timestamp::from(1_700_000_000),
+            subscriptions: HashMap::from([(id.clone(), filters.clone())]),
+            multiplexed: HashMap::from([(
+                SubscriptionId::new("multiplexed"),
+                vec![Filter::new().kind(Kind::Metadata)],
+            )]),
+            relays: vec![RelaySnapshot {
+                url: RelayUrl::parse("wss://relay.damus.io").unwrap(),
+                flags: RelayServiceFlags::READ | RelayServiceFlags::PING,
+                status: RelayStatus::Banned,
+                banned_until: Some(Timestamp::from(1_700_003_600)),
+                paid: true,
+                attempts: 3,
+                success: 2,
+                connected_at: Timestamp::from(1_699_999_000),
+                first_connection_at: Timestamp::from(1_600_000_000),
+                subscriptions: HashMap::from([(
+                    id,
+                    SubscriptionSnapshot {
+                        filters,
+                        last_event_at: Some(Timestamp::from(1_699_999_999)),
+                        cursor: Some(cursor),
+                    },
+                )]),
+            }],
+        };
+
+        let json = snapshot.as_json();
+        assert_eq!(RelayPoolSnapshot::from_json(json).unwrap(), snapshot);
+
+        assert!(RelayPoolSnapshot::from_json("{}").is_err());
+
+        // Snapshot without the optional fields
+        let json = r#"{"taken_at":1700000000,"subscriptions":{},"relays":[{"url":"wss://relay.damus.io","flags":7,"status":"Connected","attempts":1,"success":1,"connected_at":1700000000,"first_connection_at":1700000000,"subscriptions":{"feed":{"filters":[],"last_event_at":null}}}]}"#;
+        let snapshot = RelayPoolSnapshot::from_json(json).unwrap();
+        assert!(snapshot.multiplexed.is_empty());
+        assert_eq!(snapshot.relays[0].banned_until, None);
+        assert!(!snapshot.relays[0].paid);
+        assert_eq!(
+            snapshot.relays[0].subscriptions[&SubscriptionId::new("feed")].cursor,
+            None
+        );
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Captured relay frames
+//!
+//! Enable the capture with [`RelayOptions::capture`](super::RelayOptions::capture) and get the recorded frames with
+//! [`Relay::capture`](super::Relay::capture).
+//!
+//! A capture is a list of frames exchanged with the relays, stored as JSON lines:
+//!
+//! ```json
+//! [<timestamp millis>, "<relay url>", "<in|out>", "<raw message>"]
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use nostr::serde_json::{self, json, Value};
+use nostr::types::time::{SystemTime, UNIX_EPOCH};
+use nostr::types::url;
+use nostr::RelayUrl;
+use tokio::sync::Mutex;
+
+/// Capture error
+#[derive(Debug)]
+pub enum Error {
+    /// Json error
+    Json(serde_json::Error),
+    /// Url parse error
+    RelayUrl(url::Error),
+    /// Invalid frame
+    InvalidFrame {
+        /// Line number (starting from 1)
+        line: usize,
+    },
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "{e}"),
+            Self::RelayUrl(e) => write!(f, "{e}"),
+            Self::InvalidFrame { line } => write!(f, "invalid frame at line {line}"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl From<url::Error> for Error {
+    fn from(e: url::Error) -> Self {
+        Self::RelayUrl(e)
+    }
+}
+
+/// Frame direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptureDirection {
+    /// Received from the relay
+    Inbound,
+    /// Sent to the relay
+    Outbound,
+}
+
+impl CaptureDirection {
+    /// Get as `&str`
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Inbound => "in",
+            Self::Outbound => "out",
+        }
+    }
+}
+
+/// Captured frame
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CapturedFrame {
+    /// UNIX timestamp in milliseconds
+    pub timestamp_ms: u64,
+    /// Relay url
+    pub relay_url: RelayUrl,
+    /// Direction
+    pub direction: CaptureDirection,
+    /// Raw message
+    pub message: String,
+}
+
+impl CapturedFrame {
+    /// Deserialize from a JSON line
+    pub fn from_json<T>(json: T) -> Result<Self, Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        let value: Value = serde_json::from_slice(json.as_ref())?;
+        Self::from_value(value).ok_or(Error::InvalidFrame { line: 1 })
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        let values: &Vec<Value> = value.as_array()?;
+
+        if values.len() != 4 {
+            return None;
+        }
+
+        let direction: CaptureDirection = match values[2].as_str()? {
+            "in" => CaptureDirection::Inbound,
+            "out" => CaptureDirection::Outbound,
+            _ => return None,
+        };
+
+        Some(Self {
+            timestamp_ms: values[0].as_u64()?,
+            relay_url: RelayUrl::parse(values[1].as_str()?).ok()?,
+            direction,
+            message: values[3].as_str()?.to_string(),
+        })
+    }
+
+    /// Serialize as a JSON line
+    pub fn as_json(&self) -> String {
+        json!([
+            self.timestamp_ms,
+            self.relay_url,
+            self.direction.as_str(),
+            self.message
+        ])
+        .to_string()
+    }
+}
+
+/// Parse a capture (JSON lines)
+///
+/// Empty lines are skipped.
+pub fn parse(capture: &str) -> Result<Vec<CapturedFrame>, Error> {
+    let mut frames: Vec<CapturedFrame> = Vec::new();
+
+    for (index, line) in capture.lines().enumerate() {
+        let line: &str = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let value: Value = serde_json::from_str(line)?;
+        let frame: CapturedFrame =
+            CapturedFrame::from_value(value).ok_or(Error::InvalidFrame { line: index + 1 })?;
+        frames.push(frame);
+    }
+
+    Ok(frames)
+}
+
+/// Serialize the frames as JSON lines
+pub fn serialize<'a, I>(frames: I) -> String
+where
+    I: IntoIterator<Item = &'a CapturedFrame>,
+{
+    let mut capture: String = String::new();
+    for frame in frames.into_iter() {
+        capture.push_str(&frame.as_json());
+        capture.push('\n');
+    }
+    capture
+}
+
+/// Ring buffer of the frames exchanged with a relay
+#[derive(Debug)]
+pub(crate) struct CaptureBuffer {
+    relay_url: RelayUrl,
+    capacity: usize,
+    frames: Mutex<VecDeque<CapturedFrame>>,
+}
+
+impl CaptureBuffer {
+    pub fn new(relay_url: RelayUrl, capacity: usize) -> Self {
+        Self {
+            relay_url,
+            capacity,
+            frames: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub async fn record(&self, direction: CaptureDirection, message: &str) {
+        let timestamp_ms: u64 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let mut frames = self.frames.lock().await;
+
+        // Drop the oldest frames
+        while frames.len() >= self.capacity {
+            frames.pop_front();
+        }
+
+        frames.push_back(CapturedFrame {
+            timestamp_ms,
+            relay_url: self.relay_url.clone(),
+            direction,
+            message: message.to_string(),
+        });
+    }
+
+    pub async fn frames(&self) -> Vec<CapturedFrame> {
+        let frames = self.frames.lock().await;
+        frames.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_json() {
+        let frame = CapturedFrame {
+            timestamp_ms: 1_700_000_000_123,
+            relay_url: RelayUrl::parse("wss://relay.damus.io").unwrap(),
+            direction: CaptureDirection::Inbound,
+            message: String::from(r#"["EOSE","sub"]"#),
+        };
+        let json: String = frame.as_json();
+        assert_eq!(
+            json,
+            r#"[1700000000123,"wss://relay.damus.io","in","[\"EOSE\",\"sub\"]"]"#
+        );
+        assert_eq!(CapturedFrame::from_json(json).unwrap(), frame);
+    }
+
+    #[test]
+    fn test_parse_capture() {
+        let capture = r#"
+[1000,"wss://relay.damus.io","out","[\"REQ\",\"sub\",{}]"]
+
+[1250,"wss://relay.damus.io","in","[\"EOSE\",\"sub\"]"]
+"#;
+        let frames = parse(capture).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].direction, CaptureDirection::Outbound);
+        assert_eq!(frames[1].timestamp_ms, 1250);
+
+        let invalid = "[1000,\"wss://relay.damus.io\",\"in\",\"[]\"]\n[1000,\"wss://relay.damus.io\",\"sideways\",\"[]\"]";
+        assert!(matches!(
+            parse(invalid).unwrap_err(),
+            Error::InvalidFrame { line: 2 }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_capture_buffer() {
+        let url = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let buffer = CaptureBuffer::new(url, 2);
+
+        buffer.record(CaptureDirection::Outbound, "1").await;
+        buffer.record(CaptureDirection::Inbound, "2").await;
+        buffer.record(CaptureDirection::Inbound, "3").await;
+
+        let frames = buffer.frames().await;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].message, "2");
+        assert_eq!(frames[1].message, "3");
+
+        // Round trip
+        assert_eq!(parse(&serialize(&frames)).unwrap(), frames);
+    }
+}
@@ -22,6 +22,8 @@ pub(super) const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(10);
 // Not increase the max retry interval too much.
 // Keep it small avoid huge waits before reconnection if internet was gone for much time and then come back.
 pub(super) const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(60);
+
+pub(super) const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(600);
 pub(super) const JITTER_RANGE: RangeInclusive<i8> = -3..=3;
 
 pub(super) const NEGENTROPY_FRAME_SIZE_LIMIT: u64 = 60_000; // Default frame limit is 128k. Halve that (hex encoding) and subtract a bit (JSON msg overhead)
@@ -29,10 +31,21 @@ pub(super) const NEGENTROPY_HIGH_WATER_UP: usize = 100;
 pub(super) const NEGENTROPY_LOW_WATER_UP: usize = 50;
 pub(super) const NEGENTROPY_BATCH_SIZE_DOWN: usize = 100;
 
+// Used when the relay doesn't advertise the `max_limit` in its NIP11 document
+pub(super) const DEFAULT_WINDOW_LIMIT: usize = 500;
+
 pub(super) const MIN_ATTEMPTS: usize = 1;
 pub(super) const MIN_SUCCESS_RATE: f64 = 0.90;
 
-pub(super) const PING_INTERVAL: Duration = Duration::from_secs(55); // Used also for latency calculation
+// Events are not always received in `created_at` order, so go back a bit when re-subscribing.
+pub(super) const RESUBSCRIBE_SINCE_MARGIN: Duration = Duration::from_secs(60);
+
+pub(super) const DEFAULT_OUTBOX_LIMIT: usize = 1000;
+
+pub(super) const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(55); // Used also for latency calculation
+
+// A lower interval would flood the relay with pings
+pub(super) const MIN_PING_INTERVAL: Duration = Duration::from_secs(1);
 
 pub(super) const WEBSOCKET_TX_TIMEOUT: Duration = Duration::from_secs(10);
 
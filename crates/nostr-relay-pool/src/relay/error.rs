@@ -11,6 +11,7 @@ use nostr::message::MessageHandleError;
 use nostr_database::DatabaseError;
 use tokio::sync::{broadcast, SetError};
 
+use super::limitation::LimitationError;
 use crate::shared::SharedStateError;
 use crate::RelayPoolNotification;
 
@@ -116,6 +117,17 @@ pub enum Error {
     AuthenticationFailed,
     /// Premature exit
     PrematureExit,
+    /// Event queued in the outbox
+    EventQueued,
+    /// Outbox full
+    OutboxFull {
+        /// Max number of queued events
+        limit: usize,
+    },
+    /// NIP11 limitation not respected
+    Limitation(LimitationError),
+    /// Protected event (NIP70) rejected by the relay
+    ProtectedEventRejected(String),
 }
 
 impl std::error::Error for Error {}
@@ -176,20 +188,16 @@ impl fmt::Display for Error {
             ),
             Self::AuthenticationFailed => write!(f, "authentication failed"),
             Self::PrematureExit => write!(f, "premature exit"),
+            Self::EventQueued => write!(f, "event queued in the outbox"),
+            Self::OutboxFull { limit } => write!(f, "outbox full (limit: {limit})"),
+            Self::Limitation(e) => write!(f, "{e}"),
+            Self::ProtectedEventRejected(message) => {
+                write!(f, "protected event rejected: {message}")
+            }
         }
     }
 }
 
-impl Error {
-    #[inline]
-    pub(super) fn websocket<E>(error: E) -> Self
-    where
-        E: std::error::Error + Send + Sync + 'static,
-    {
-        Self::WebSocket(Box::new(error))
-    }
-}
-
 impl From<SharedStateError> for Error {
     fn from(e: SharedStateError) -> Self {
         Self::SharedState(e)
@@ -232,6 +240,12 @@ impl From<negentropy_deprecated::Error> for Error {
     }
 }
 
+impl From<LimitationError> for Error {
+    fn from(e: LimitationError) -> Self {
+        Self::Limitation(e)
+    }
+}
+
 impl From<DatabaseError> for Error {
     fn from(e: DatabaseError) -> Self {
         Self::Database(e)
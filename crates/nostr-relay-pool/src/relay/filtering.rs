@@ -5,17 +5,22 @@
 //! Relay filtering
 
 use std::collections::HashSet;
+use std::iter;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use nostr::{EventId, PartialEvent, PublicKey};
-use tokio::sync::RwLock;
+use nostr::{Event, EventId, PartialEvent, PublicKey, RelayUrl};
+use tokio::sync::{Notify, RwLock};
 
 pub(crate) enum CheckFiltering {
     Allow,
     EventIdBlacklisted(EventId),
     PublicKeyBlacklisted(PublicKey),
     PublicKeyNotInWhitelist(PublicKey),
+    PublicKeyMentioned(PublicKey),
+    ThreadMuted(EventId),
+    HashtagBlacklisted(String),
+    WordBlacklisted(String),
 }
 
 /// Filtering mode
@@ -77,8 +82,15 @@ impl AtomicRelayFilteringMode {
 #[derive(Debug, Default)]
 struct InnerRelayFiltering {
     mode: AtomicRelayFilteringMode,
+    mentions: AtomicBool,
     ids: RwLock<HashSet<EventId>>,
+    threads: RwLock<HashSet<EventId>>,
     public_keys: RwLock<HashSet<PublicKey>>,
+    hashtags: RwLock<HashSet<String>>,
+    words: RwLock<HashSet<String>>,
+    relays: RwLock<HashSet<RelayUrl>>,
+    /// Notified when relay URLs are added
+    relays_added: Notify,
 }
 
 impl InnerRelayFiltering {
@@ -130,6 +142,20 @@ impl RelayFiltering {
         self.inner.mode.update(mode);
     }
 
+    /// Check if events that mention blacklisted public keys are discarded
+    #[inline]
+    pub fn is_mentions_filtering_enabled(&self) -> bool {
+        self.inner.mentions.load(Ordering::SeqCst)
+    }
+
+    /// Discard events that mention (`p` tag) blacklisted public keys (default: false)
+    ///
+    /// Note: ignored in whitelist mode!
+    #[inline]
+    pub fn update_mentions_filtering(&self, enable: bool) {
+        self.inner.mentions.store(enable, Ordering::SeqCst);
+    }
+
     /// Add event IDs
     ///
     /// Note: IDs are ignored in whitelist mode!
@@ -168,6 +194,37 @@ impl RelayFiltering {
         ids.contains(id)
     }
 
+    /// Add muted threads
+    ///
+    /// The thread roots and the events that reference them (`e` tag) are discarded.
+    /// Unlike the event IDs (see [`RelayFiltering::add_ids`]), the references are checked too.
+    ///
+    /// Note: threads are ignored in whitelist mode!
+    pub async fn add_threads<I>(&self, iter: I)
+    where
+        I: IntoIterator<Item = EventId>,
+    {
+        let mut threads = self.inner.threads.write().await;
+        threads.extend(iter);
+    }
+
+    /// Remove muted threads
+    pub async fn remove_threads<'a, I>(&self, iter: I)
+    where
+        I: IntoIterator<Item = &'a EventId>,
+    {
+        let mut threads = self.inner.threads.write().await;
+        for id in iter.into_iter() {
+            threads.remove(id);
+        }
+    }
+
+    /// Check if has muted thread
+    pub async fn has_thread(&self, id: &EventId) -> bool {
+        let threads = self.inner.threads.read().await;
+        threads.contains(id)
+    }
+
     /// Add public keys
     pub async fn add_public_keys<I>(&self, iter: I)
     where
@@ -209,6 +266,127 @@ impl RelayFiltering {
         public_keys.contains(public_key)
     }
 
+    /// Add hashtags
+    ///
+    /// Events with a blacklisted hashtag (`t` tag) are discarded.
+    /// Hashtags are compared case-insensitively.
+    ///
+    /// Note: hashtags are ignored in whitelist mode!
+    pub async fn add_hashtags<I, S>(&self, iter: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut hashtags = self.inner.hashtags.write().await;
+        hashtags.extend(iter.into_iter().map(|t| t.as_ref().to_lowercase()));
+    }
+
+    /// Remove hashtags
+    pub async fn remove_hashtags<I, S>(&self, iter: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut hashtags = self.inner.hashtags.write().await;
+        for hashtag in iter.into_iter() {
+            hashtags.remove(&hashtag.as_ref().to_lowercase());
+        }
+    }
+
+    /// Check if has hashtag
+    pub async fn has_hashtag(&self, hashtag: &str) -> bool {
+        let hashtags = self.inner.hashtags.read().await;
+        hashtags.contains(&hashtag.to_lowercase())
+    }
+
+    /// Add words
+    ///
+    /// Events which content contains a blacklisted word are discarded.
+    /// Words are compared case-insensitively and only as whole words (i.e. `spam` doesn't match `spammer`).
+    ///
+    /// Note: words are ignored in whitelist mode!
+    pub async fn add_words<I, S>(&self, iter: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut words = self.inner.words.write().await;
+        words.extend(iter.into_iter().map(|w| w.as_ref().to_lowercase()));
+    }
+
+    /// Remove words
+    pub async fn remove_words<I, S>(&self, iter: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut words = self.inner.words.write().await;
+        for word in iter.into_iter() {
+            words.remove(&word.as_ref().to_lowercase());
+        }
+    }
+
+    /// Check if has word
+    pub async fn has_word(&self, word: &str) -> bool {
+        let words = self.inner.words.read().await;
+        words.contains(&word.to_lowercase())
+    }
+
+    /// Add relay URLs
+    ///
+    /// Blacklisted relays are never connected and the already connected ones are disconnected.
+    ///
+    /// Note: relay URLs are always treated as a blacklist, regardless of the mode!
+    pub async fn add_relay_urls<I>(&self, iter: I)
+    where
+        I: IntoIterator<Item = RelayUrl>,
+    {
+        let mut relays = self.inner.relays.write().await;
+        relays.extend(iter);
+        drop(relays);
+
+        self.inner.relays_added.notify_waiters();
+    }
+
+    /// Remove relay URLs
+    pub async fn remove_relay_urls<'a, I>(&self, iter: I)
+    where
+        I: IntoIterator<Item = &'a RelayUrl>,
+    {
+        let mut relays = self.inner.relays.write().await;
+        for url in iter.into_iter() {
+            relays.remove(url);
+        }
+    }
+
+    /// Remove relay URL
+    pub async fn remove_relay_url(&self, url: &RelayUrl) {
+        let mut relays = self.inner.relays.write().await;
+        relays.remove(url);
+    }
+
+    /// Check if has relay URL
+    pub async fn has_relay_url(&self, url: &RelayUrl) -> bool {
+        let relays = self.inner.relays.read().await;
+        relays.contains(url)
+    }
+
+    /// Wait until the relay URL is blacklisted
+    pub(crate) async fn wait_for_relay_url(&self, url: &RelayUrl) {
+        loop {
+            // Register before checking, to not miss the additions in between
+            let notified = self.inner.relays_added.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if self.has_relay_url(url).await {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+
     pub(crate) async fn check_partial_event(&self, partial_event: &PartialEvent) -> CheckFiltering {
         match self.inner.mode.load() {
             RelayFilteringMode::Whitelist => {
@@ -230,12 +408,114 @@ impl RelayFiltering {
         CheckFiltering::Allow
     }
 
+    pub(crate) async fn check_mentions(&self, event: &Event) -> CheckFiltering {
+        if self.inner.mode.load().is_blacklist() && self.is_mentions_filtering_enabled() {
+            let public_keys = self.inner.public_keys.read().await;
+            if let Some(public_key) = event
+                .tags
+                .public_keys()
+                .find(|public_key| public_keys.contains(*public_key))
+            {
+                return CheckFiltering::PublicKeyMentioned(*public_key);
+            }
+        }
+
+        CheckFiltering::Allow
+    }
+
+    pub(crate) async fn check_content(&self, event: &Event) -> CheckFiltering {
+        if self.inner.mode.load().is_whitelist() {
+            return CheckFiltering::Allow;
+        }
+
+        // Check muted threads
+        let threads = self.inner.threads.read().await;
+        if !threads.is_empty() {
+            if let Some(id) = iter::once(&event.id)
+                .chain(event.tags.event_ids())
+                .find(|id| threads.contains(*id))
+            {
+                return CheckFiltering::ThreadMuted(*id);
+            }
+        }
+
+        // Check hashtags
+        let hashtags = self.inner.hashtags.read().await;
+        if !hashtags.is_empty() {
+            if let Some(hashtag) = event
+                .tags
+                .hashtags()
+                .map(|t| t.to_lowercase())
+                .find(|t| hashtags.contains(t))
+            {
+                return CheckFiltering::HashtagBlacklisted(hashtag);
+            }
+        }
+
+        // Check words
+        let words = self.inner.words.read().await;
+        if !words.is_empty() {
+            let content: String = event.content.to_lowercase();
+            if let Some(word) = words.iter().find(|w| contains_word(&content, w)) {
+                return CheckFiltering::WordBlacklisted(word.clone());
+            }
+        }
+
+        CheckFiltering::Allow
+    }
+
     /// Remove everything
     pub async fn clear(&self) {
         let mut ids = self.inner.ids.write().await;
         ids.clear();
 
+        let mut threads = self.inner.threads.write().await;
+        threads.clear();
+
         let mut public_keys = self.inner.public_keys.write().await;
         public_keys.clear();
+
+        let mut hashtags = self.inner.hashtags.write().await;
+        hashtags.clear();
+
+        let mut words = self.inner.words.write().await;
+        words.clear();
+
+        let mut relays = self.inner.relays.write().await;
+        relays.clear();
+    }
+}
+
+/// Check if `content` contains `word` not as part of a longer word
+fn contains_word(content: &str, word: &str) -> bool {
+    if word.is_empty() {
+        return false;
+    }
+
+    content.match_indices(word).any(|(start, ..)| {
+        let end: usize = start + word.len();
+        let before: Option<char> = content[..start].chars().next_back();
+        let after: Option<char> = content[end..].chars().next();
+        !before.map_or(false, char::is_alphanumeric) && !after.map_or(false, char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_word() {
+        assert!(contains_word("buy spam now", "spam"));
+        assert!(contains_word("spam", "spam"));
+        assert!(contains_word("spam, again", "spam"));
+        assert!(contains_word("#spam!", "spam"));
+        assert!(!contains_word("spammer", "spam"));
+        assert!(!contains_word("antispam", "spam"));
+        assert!(contains_word("no spam here, spam", "spam"));
+        assert!(!contains_word("spamspam", "spam"));
+        assert!(contains_word("free bitcoin giveaway", "bitcoin giveaway"));
+        assert!(!contains_word("émoji", "moji"));
+        assert!(!contains_word("anything", ""));
     }
 }
@@ -8,6 +8,8 @@ use std::ops::{BitOr, BitOrAssign, BitXor, BitXorAssign};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+
 /// Flag checks
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum FlagCheck {
@@ -18,7 +20,8 @@ pub enum FlagCheck {
 }
 
 /// Relay Service Flags
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct RelayServiceFlags(u64);
 
 impl Default for RelayServiceFlags {
@@ -70,9 +73,14 @@ impl RelayServiceFlags {
     }
 
     #[inline]
-    fn to_u64(self) -> u64 {
+    pub(crate) fn to_u64(self) -> u64 {
         self.0
     }
+
+    #[inline]
+    pub(crate) fn from_u64(flags: u64) -> Self {
+        Self(flags)
+    }
 }
 
 impl BitOr for RelayServiceFlags {
@@ -126,6 +134,12 @@ impl AtomicRelayServiceFlags {
         }
     }
 
+    /// Get current [RelayServiceFlags]
+    #[inline]
+    pub fn get(&self) -> RelayServiceFlags {
+        RelayServiceFlags(self.flags.load(Ordering::SeqCst))
+    }
+
     /// Add [RelayServiceFlags] together.
     pub fn add(&self, other: RelayServiceFlags) {
         let _ = self
@@ -4,41 +4,56 @@
 
 use std::cmp;
 use std::collections::{HashMap, HashSet};
-#[cfg(feature = "nip11")]
-use std::sync::atomic::AtomicU64;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use async_utility::{task, time};
+use async_utility::time;
 use async_wsocket::futures_util::{self, Future, SinkExt, StreamExt};
-use async_wsocket::{connect as wsocket_connect, ConnectionMode, Sink, Stream, WsMessage};
+use async_wsocket::{ConnectionMode, WsMessage};
 use atomic_destructor::AtomicDestroyer;
 use negentropy::{Bytes, Id, Negentropy, NegentropyStorageVector};
 use negentropy_deprecated::{Bytes as BytesDeprecated, Negentropy as NegentropyDeprecated};
 use nostr::event::raw::RawEvent;
 use nostr::secp256k1::rand::{self, Rng};
+#[cfg(feature = "nip11")]
+use nostr::util::http::HttpClient;
 use nostr_database::prelude::*;
 use tokio::sync::mpsc::{self, Receiver, Sender};
-use tokio::sync::{broadcast, watch, Mutex, MutexGuard, OnceCell, RwLock};
+use tokio::sync::{broadcast, oneshot, watch, Mutex, MutexGuard, OnceCell, RwLock, SetError};
 
+use super::capture::{CaptureBuffer, CaptureDirection};
 use super::constants::{
-    BATCH_EVENT_ITERATION_TIMEOUT, DEFAULT_CONNECTION_TIMEOUT, JITTER_RANGE, MAX_RETRY_INTERVAL,
-    MIN_ATTEMPTS, MIN_SUCCESS_RATE, NEGENTROPY_BATCH_SIZE_DOWN, NEGENTROPY_FRAME_SIZE_LIMIT,
-    NEGENTROPY_HIGH_WATER_UP, NEGENTROPY_LOW_WATER_UP, PING_INTERVAL,
-    WAIT_FOR_AUTHENTICATION_TIMEOUT, WEBSOCKET_TX_TIMEOUT,
+    BATCH_EVENT_ITERATION_TIMEOUT, DEFAULT_CONNECTION_TIMEOUT, DEFAULT_WINDOW_LIMIT, JITTER_RANGE,
+    MAX_RETRY_INTERVAL, MIN_ATTEMPTS, MIN_SUCCESS_RATE, NEGENTROPY_BATCH_SIZE_DOWN,
+    NEGENTROPY_FRAME_SIZE_LIMIT, NEGENTROPY_HIGH_WATER_UP, NEGENTROPY_LOW_WATER_UP,
+    RESUBSCRIBE_SINCE_MARGIN, WAIT_FOR_AUTHENTICATION_TIMEOUT, WEBSOCKET_TX_TIMEOUT,
 };
 use super::filtering::CheckFiltering;
 use super::flags::AtomicRelayServiceFlags;
+#[cfg(feature = "nip11")]
+use super::limitation;
 use super::options::{
     FilterOptions, RelayOptions, SubscribeAutoCloseOptions, SubscribeOptions, SyncOptions,
 };
+#[cfg(feature = "nip11")]
+use super::payment::RelayPayment;
 use super::ping::PingTracker;
-use super::stats::RelayConnectionStats;
-use super::{Error, Reconciliation, RelayNotification, RelayStatus, SubscriptionAutoClosedReason};
-use crate::pool::RelayPoolNotification;
+use super::stats::{Bandwidth, RelayConnectionStats};
+use super::{
+    DisconnectReason, Error, Reconciliation, RelayCloseFrame, RelayNotification, RelayStatus,
+    SubscriptionAutoClosedReason,
+};
+use crate::metrics::{EventTimer, IngestionMetrics, IngestionStage};
+use crate::policy::{Admission, Deduplication};
+use crate::pool::{NotificationSender, RelayPoolNotification};
 use crate::relay::status::AtomicRelayStatus;
+use crate::retry::RetryPolicy;
 use crate::shared::SharedState;
+#[cfg(feature = "nip11")]
+use crate::skew::ClockSkew;
+use crate::supervisor;
+use crate::transport::{BoxSink, BoxStream};
 
 #[derive(Debug, Clone, Copy)]
 enum RelayServiceEvent {
@@ -123,8 +138,16 @@ impl RelayChannels {
 struct SubscriptionData {
     pub filters: Vec<Filter>,
     pub subscribed_at: Timestamp,
+    /// Newest `created_at` of the events received for this subscription
+    pub last_event_at: Option<Timestamp>,
     /// Subscription closed by relay
     pub closed: bool,
+    /// EOSE received since the last REQ (the new events are live)
+    pub eose: bool,
+    /// When the connection has been paused
+    pub paused_at: Option<Timestamp>,
+    /// Persistent cursor
+    pub cursor: Option<SubscriptionCursor>,
 }
 
 impl Default for SubscriptionData {
@@ -132,7 +155,11 @@ impl Default for SubscriptionData {
         Self {
             filters: Vec::new(),
             subscribed_at: Timestamp::zero(),
+            last_event_at: None,
             closed: false,
+            eose: false,
+            paused_at: None,
+            cursor: None,
         }
     }
 }
@@ -145,15 +172,30 @@ pub(crate) struct InnerRelay {
     document: Arc<RwLock<RelayInformationDocument>>,
     #[cfg(feature = "nip11")]
     last_document_fetch: Arc<AtomicU64>,
+    #[cfg(feature = "nip11")]
+    paid: Arc<AtomicBool>,
     pub(super) opts: RelayOptions,
     pub(super) flags: AtomicRelayServiceFlags,
     pub(super) stats: RelayConnectionStats,
     pub(super) state: SharedState,
     channels: Arc<RelayChannels>,
     pub(super) internal_notification_sender: broadcast::Sender<RelayNotification>,
-    external_notification_sender: OnceCell<broadcast::Sender<RelayPoolNotification>>,
+    external_notification_sender: OnceCell<NotificationSender>,
     subscriptions: Arc<RwLock<HashMap<SubscriptionId, SubscriptionData>>>,
     running: Arc<AtomicBool>,
+    termination_acks: Arc<Mutex<Vec<oneshot::Sender<()>>>>,
+    outbox: Arc<Mutex<Vec<Event>>>,
+    close_frame: Arc<RwLock<Option<RelayCloseFrame>>>,
+    consecutive_failures: Arc<AtomicUsize>,
+    /// The disconnection has already been notified (reset at the next successful connection)
+    disconnect_notified: Arc<AtomicBool>,
+    /// Last authentication (NIP42) error in the current connection
+    auth_error: Arc<RwLock<Option<String>>>,
+    /// Authenticated (NIP42) in the current connection
+    authenticated: Arc<AtomicBool>,
+    /// End of the ban (UNIX timestamp, `0` if not banned)
+    banned_until: Arc<AtomicU64>,
+    pub(super) capture: Option<Arc<CaptureBuffer>>,
 }
 
 impl AtomicDestroyer for InnerRelay {
@@ -168,6 +210,11 @@ impl InnerRelay {
     pub fn new(url: RelayUrl, state: SharedState, opts: RelayOptions) -> Self {
         let (relay_notification_sender, ..) = broadcast::channel::<RelayNotification>(2048);
 
+        let capture: Option<Arc<CaptureBuffer>> = opts
+            .capture
+            .filter(|capacity| *capacity > 0)
+            .map(|capacity| Arc::new(CaptureBuffer::new(url.clone(), capacity)));
+
         Self {
             url,
             status: Arc::new(AtomicRelayStatus::default()),
@@ -175,6 +222,8 @@ impl InnerRelay {
             document: Arc::new(RwLock::new(RelayInformationDocument::new())),
             #[cfg(feature = "nip11")]
             last_document_fetch: Arc::new(AtomicU64::new(0)),
+            #[cfg(feature = "nip11")]
+            paid: Arc::new(AtomicBool::new(false)),
             flags: AtomicRelayServiceFlags::new(opts.flags),
             opts,
             stats: RelayConnectionStats::default(),
@@ -184,6 +233,15 @@ impl InnerRelay {
             external_notification_sender: OnceCell::new(),
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
             running: Arc::new(AtomicBool::new(false)),
+            termination_acks: Arc::new(Mutex::new(Vec::new())),
+            outbox: Arc::new(Mutex::new(Vec::new())),
+            close_frame: Arc::new(RwLock::new(None)),
+            consecutive_failures: Arc::new(AtomicUsize::new(0)),
+            disconnect_notified: Arc::new(AtomicBool::new(false)),
+            auth_error: Arc::new(RwLock::new(None)),
+            authenticated: Arc::new(AtomicBool::new(false)),
+            banned_until: Arc::new(AtomicU64::new(0)),
+            capture,
         }
     }
 
@@ -207,6 +265,11 @@ impl InnerRelay {
         // Change status
         self.status.set(status);
 
+        // The authentication is bound to the connection
+        if !status.is_connected() {
+            self.authenticated.store(false, Ordering::SeqCst);
+        }
+
         // Log
         if log {
             match status {
@@ -218,6 +281,7 @@ impl InnerRelay {
                 RelayStatus::Terminated => {
                     tracing::info!("Completely disconnected from '{}'", self.url)
                 }
+                RelayStatus::Banned => tracing::info!("Relay '{}' banned", self.url),
             }
         }
 
@@ -230,6 +294,11 @@ impl InnerRelay {
         self.status().is_connected()
     }
 
+    #[inline]
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated.load(Ordering::SeqCst)
+    }
+
     /// Perform health checks
     fn health_check(&self) -> Result<(), Error> {
         let status: RelayStatus = self.status();
@@ -269,6 +338,82 @@ impl InnerRelay {
         document.clone()
     }
 
+    #[inline]
+    #[cfg(feature = "nip11")]
+    pub fn is_paid(&self) -> bool {
+        self.paid.load(Ordering::SeqCst)
+    }
+
+    #[inline]
+    #[cfg(feature = "nip11")]
+    pub fn set_paid(&self, paid: bool) {
+        self.paid.store(paid, Ordering::SeqCst);
+    }
+
+    /// Check if the relay limitations must be enforced and get them
+    #[cfg(feature = "nip11")]
+    async fn limitation_to_enforce(&self) -> Option<Limitation> {
+        if !self.opts.enforce_limitations {
+            return None;
+        }
+
+        let document = self.document.read().await;
+        document.limitation.clone()
+    }
+
+    /// Check if the client can authenticate to the relay (NIP42) and if the relay is paid
+    #[cfg(feature = "nip11")]
+    async fn limitation_access(&self) -> limitation::Access {
+        limitation::Access {
+            can_authenticate: self.state.is_auto_authentication_enabled()
+                && self.state.has_signer().await,
+            paid: self.is_paid(),
+        }
+    }
+
+    /// Check the subscription against the NIP11 limitations
+    #[cfg(feature = "nip11")]
+    async fn check_subscription_limitation(
+        &self,
+        id: &SubscriptionId,
+        filters: &[Filter],
+    ) -> Result<(), Error> {
+        if let Some(limitation) = self.limitation_to_enforce().await {
+            let active: usize = {
+                let subscriptions = self.subscriptions.read().await;
+                subscriptions
+                    .iter()
+                    .filter(|(sub_id, data)| *sub_id != id && !data.closed)
+                    .count()
+            };
+
+            limitation::check_subscription(
+                &limitation,
+                id,
+                filters,
+                active,
+                self.limitation_access().await,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the event against the NIP11 limitations
+    #[cfg(feature = "nip11")]
+    async fn check_event_limitation(&self, event: &Event) -> Result<(), Error> {
+        if let Some(limitation) = self.limitation_to_enforce().await {
+            limitation::check_event(
+                &limitation,
+                event,
+                self.limitation_access().await,
+                self.state.time_provider().now(),
+            )?;
+        }
+
+        Ok(())
+    }
+
     #[cfg(feature = "nip11")]
     fn request_nip11_document(&self) {
         let (allowed, proxy) = match self.opts.connection_mode {
@@ -280,7 +425,7 @@ impl InnerRelay {
         };
 
         if allowed {
-            let now: u64 = Timestamp::now().as_u64();
+            let now: u64 = self.state.time_provider().now().as_u64();
 
             // Check last fetch
             if self.last_document_fetch.load(Ordering::SeqCst) + 3600 < now {
@@ -288,13 +433,63 @@ impl InnerRelay {
                 self.last_document_fetch.store(now, Ordering::SeqCst);
 
                 // Fetch
+                let relay = self.clone();
                 let url = self.url.clone();
                 let d = self.document.clone();
-                task::spawn(async move {
-                    match RelayInformationDocument::get(url.clone().into(), proxy).await {
-                        Ok(document) => {
-                            let mut d = d.write().await;
-                            *d = document
+                let skew: Option<Arc<ClockSkew>> = self.state.clock_skew().cloned();
+                let policy: RetryPolicy = self.retry_policy();
+                // Use the custom HTTP client, through the proxy if any
+                let client: Option<Arc<dyn HttpClient>> = match (self.state.http_client(), proxy) {
+                    (Some(client), None) => Some(client.clone()),
+                    (Some(client), Some(proxy)) => client.proxied(proxy),
+                    (None, ..) => None,
+                };
+                self.state.spawn(format!("{}: nip11 fetch", self.url), async move {
+                    match policy
+                        .retry(|| async {
+                            match &client {
+                                Some(client) => {
+                                    RelayInformationDocument::get_with_client(
+                                        client.as_ref(),
+                                        url.clone().into(),
+                                    )
+                                    .await
+                                }
+                                None => {
+                                    RelayInformationDocument::get_with_server_time(
+                                        url.clone().into(),
+                                        proxy,
+                                    )
+                                    .await
+                                }
+                            }
+                        })
+                        .await
+                    {
+                        Ok((document, server_time)) => {
+                            // Measure the clock skew
+                            if let (Some(skew), Some(server_time)) = (skew, server_time) {
+                                skew.add_server_time(&url, server_time);
+                            }
+
+                            let payment: Option<RelayPayment> =
+                                RelayPayment::from_document(&document);
+
+                            {
+                                let mut d = d.write().await;
+                                *d = document;
+                            }
+
+                            // Notify the payment requirements
+                            if let Some(payment) = payment {
+                                if !relay.is_paid() {
+                                    tracing::debug!(url = %url, "Relay requires a payment.");
+                                    relay.send_notification(
+                                        RelayNotification::PaymentRequired { payment },
+                                        true,
+                                    );
+                                }
+                            }
                         }
                         Err(e) => {
                             tracing::warn!(url = %url, error = %e, "Can't get information document.")
@@ -327,12 +522,171 @@ impl InnerRelay {
         let mut subscriptions = self.subscriptions.write().await;
         let data: &mut SubscriptionData = subscriptions.entry(id).or_default();
         data.filters = filters;
+        data.last_event_at = None;
+        data.paused_at = None;
+        data.cursor = None;
+        data.eose = false;
 
         if update_subscribed_at {
-            data.subscribed_at = Timestamp::now();
+            data.subscribed_at = self.state.time_provider().now();
+        }
+    }
+
+    /// Get the subscriptions, with the newest `created_at` received and the persistent cursor
+    pub(crate) async fn subscriptions_with_last_event_at(
+        &self,
+    ) -> HashMap<SubscriptionId, (Vec<Filter>, Option<Timestamp>, Option<SubscriptionCursor>)> {
+        let subscriptions = self.subscriptions.read().await;
+        subscriptions
+            .iter()
+            .map(|(id, data)| {
+                (
+                    id.clone(),
+                    (
+                        data.filters.clone(),
+                        data.last_event_at,
+                        data.cursor.clone(),
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    /// Restore a subscription, with the newest `created_at` received and the persistent cursor (i.e. from a snapshot)
+    pub(crate) async fn restore_subscription(
+        &self,
+        id: SubscriptionId,
+        filters: Vec<Filter>,
+        last_event_at: Option<Timestamp>,
+        cursor: Option<SubscriptionCursor>,
+    ) {
+        let mut subscriptions = self.subscriptions.write().await;
+        let data: &mut SubscriptionData = subscriptions.entry(id).or_default();
+        data.filters = filters;
+        data.last_event_at = last_event_at;
+        data.cursor = cursor;
+    }
+
+    /// Update the newest `created_at` received for the subscription
+    async fn update_subscription_last_event_at(&self, id: &SubscriptionId, created_at: Timestamp) {
+        let mut subscriptions = self.subscriptions.write().await;
+        if let Some(data) = subscriptions.get_mut(id) {
+            match data.last_event_at {
+                Some(last_event_at) if last_event_at >= created_at => {}
+                _ => data.last_event_at = Some(created_at),
+            }
+        }
+    }
+
+    /// Get filters to use for re-subscription
+    ///
+    /// If `resubscribe_since` option is enabled, the `since` field of the filters is moved
+    /// just before the newest event received for the subscription.
+    /// If the connection has been paused, the `since` is moved at least just before the pause.
+    /// If the subscription has a persistent cursor, the `since` is moved at least to the cursor.
+    pub(crate) async fn resubscription_filters(
+        &self,
+        id: &SubscriptionId,
+        filters: Vec<Filter>,
+    ) -> Vec<Filter> {
+        let subscriptions = self.subscriptions.read().await;
+        let since: Option<Timestamp> = subscriptions.get(id).and_then(|data| {
+            let last_event_at: Option<Timestamp> = if self.opts.resubscribe_since {
+                data.last_event_at
+            } else {
+                None
+            };
+            let newest: Option<Timestamp> = cmp::max(last_event_at, data.paused_at)
+                .map(|newest| newest - RESUBSCRIBE_SINCE_MARGIN);
+            let cursor: Option<Timestamp> = data
+                .cursor
+                .as_ref()
+                .filter(|cursor| !cursor.ids().is_empty())
+                .map(|cursor| cursor.created_at());
+            cmp::max(newest, cursor)
+        });
+
+        match since {
+            Some(since) => filters_since(filters, since),
+            None => filters,
+        }
+    }
+
+    /// Load the persistent cursor of the subscription
+    ///
+    /// Return `None` if the database doesn't support cursors.
+    async fn load_cursor(&self, id: &SubscriptionId) -> Option<SubscriptionCursor> {
+        match self.state.database().cursor(&self.url, id).await {
+            Ok(cursor) => Some(cursor.unwrap_or_default()),
+            Err(e) => {
+                tracing::warn!(url = %self.url, id = %id, error = %e, "Impossible to load subscription cursor.");
+                None
+            }
+        }
+    }
+
+    async fn set_subscription_cursor(&self, id: &SubscriptionId, cursor: SubscriptionCursor) {
+        let mut subscriptions = self.subscriptions.write().await;
+        if let Some(data) = subscriptions.get_mut(id) {
+            data.cursor = Some(cursor);
+        }
+    }
+
+    /// Check if the event has been already received at the boundary of the subscription cursor
+    async fn cursor_contains(&self, id: &SubscriptionId, event: &Event) -> bool {
+        let subscriptions = self.subscriptions.read().await;
+        subscriptions
+            .get(id)
+            .and_then(|data| data.cursor.as_ref())
+            .map_or(false, |cursor| {
+                cursor.contains(&event.id, &event.created_at)
+            })
+    }
+
+    /// Advance and persist the subscription cursor
+    ///
+    /// Must be called only with verified events.
+    async fn advance_cursor(&self, id: &SubscriptionId, event_id: EventId, created_at: Timestamp) {
+        let cursor: SubscriptionCursor = {
+            let mut subscriptions = self.subscriptions.write().await;
+            match subscriptions
+                .get_mut(id)
+                .and_then(|data| data.cursor.as_mut())
+            {
+                Some(cursor) if cursor.update(event_id, created_at) => cursor.clone(),
+                _ => return,
+            }
+        };
+
+        if let Err(e) = self
+            .state
+            .database()
+            .save_cursor(&self.url, id, &cursor)
+            .await
+        {
+            tracing::error!(url = %self.url, id = %id, error = %e, "Impossible to save subscription cursor.");
         }
     }
 
+    /// Mark subscription as live or not (EOSE received)
+    async fn set_subscription_eose(&self, id: &SubscriptionId, eose: bool) {
+        let mut subscriptions = self.subscriptions.write().await;
+        if let Some(data) = subscriptions.get_mut(id) {
+            data.eose = eose;
+
+            // The events missed during the pause have been received
+            if eose {
+                data.paused_at = None;
+            }
+        }
+    }
+
+    /// Check if the EOSE has been received for the subscription
+    async fn is_subscription_live(&self, id: &SubscriptionId) -> bool {
+        let subscriptions = self.subscriptions.read().await;
+        subscriptions.get(id).map_or(false, |data| data.eose)
+    }
+
     /// Mark subscription as closed
     async fn subscription_closed(&self, id: &SubscriptionId) {
         let mut subscriptions = self.subscriptions.write().await;
@@ -376,9 +730,16 @@ impl InnerRelay {
 
     pub(crate) fn set_notification_sender(
         &self,
-        notification_sender: broadcast::Sender<RelayPoolNotification>,
+        notification_sender: NotificationSender,
     ) -> Result<(), Error> {
-        self.external_notification_sender.set(notification_sender)?;
+        self.external_notification_sender
+            .set(notification_sender)
+            .map_err(|e| match e {
+                SetError::AlreadyInitializedError(s) => {
+                    SetError::AlreadyInitializedError(s.into_inner())
+                }
+                SetError::InitializingError(s) => SetError::InitializingError(s.into_inner()),
+            })?;
         Ok(())
     }
 
@@ -408,12 +769,43 @@ impl InnerRelay {
                     RelayNotification::Authenticated => None,
                     RelayNotification::AuthenticationFailed => None,
                     RelayNotification::SubscriptionAutoClosed { .. } => None,
+                    RelayNotification::Closed { .. } => None,
+                    #[cfg(feature = "nip11")]
+                    RelayNotification::PaymentRequired { payment } => {
+                        Some(RelayPoolNotification::RelayPaymentRequired {
+                            relay_url: self.url.clone(),
+                            payment,
+                        })
+                    }
+                    RelayNotification::Disconnected { reason } => {
+                        Some(RelayPoolNotification::RelayDisconnected {
+                            relay_url: self.url.clone(),
+                            reason,
+                        })
+                    }
+                    RelayNotification::OutboxEventSent {
+                        event_id,
+                        status,
+                        message,
+                    } => Some(RelayPoolNotification::OutboxEventSent {
+                        relay_url: self.url.clone(),
+                        event_id,
+                        status,
+                        message,
+                    }),
+                    RelayNotification::TaskPanicked { task, reason } => {
+                        Some(RelayPoolNotification::TaskPanicked {
+                            relay_url: Some(self.url.clone()),
+                            task,
+                            reason,
+                        })
+                    }
                     RelayNotification::Shutdown => Some(RelayPoolNotification::Shutdown),
                 };
 
                 // Send external notification
                 if let Some(notification) = notification {
-                    let _ = external_notification_sender.send(notification);
+                    external_notification_sender.send(notification);
                 }
             }
             _ => {
@@ -429,6 +821,12 @@ impl InnerRelay {
             return;
         }
 
+        // Return if relay is blacklisted
+        if self.state.filtering().has_relay_url(&self.url).await {
+            tracing::debug!(url = %self.url, "Relay is blacklisted, skipping connection.");
+            return;
+        }
+
         // Update status
         // Change it to pending to avoid issues with the health check (initialized check)
         self.set_status(RelayStatus::Pending, false);
@@ -465,7 +863,7 @@ impl InnerRelay {
         }
 
         let relay = self.clone();
-        task::spawn(async move {
+        self.state.spawn(format!("{}: connection", self.url), async move {
             // Set that connection task is running
             relay.running.store(true, Ordering::SeqCst);
 
@@ -474,18 +872,60 @@ impl InnerRelay {
 
             // Auto-connect loop
             loop {
+                // Wait for the end of the ban (also if restored from a snapshot)
+                if let Some(remaining) = relay.remaining_ban() {
+                    relay.set_status(RelayStatus::Banned, true);
+
+                    tokio::select! {
+                        // Sleep until the ban expires
+                        _ = time::sleep(remaining) => {
+                            relay.consecutive_failures.store(0, Ordering::SeqCst);
+                            relay.banned_until.store(0, Ordering::SeqCst);
+                        },
+                        // Handle terminate
+                        _ = relay.handle_terminate(&mut rx_service) => {
+                            // Update status
+                            relay.set_status(RelayStatus::Terminated, true);
+                            break;
+                        }
+                    }
+                }
+
                 // TODO: check in the relays state database if relay can connect (different from the previous check)
                 // TODO: if the relay score is too low, immediately exit.
                 // TODO: at every loop iteration check the score and if it's too low, exit
 
                 tokio::select! {
                     // Connect and run message handler
-                    _ = relay.connect_and_run(connection_timeout) => {},
+                    res = supervisor::catch_panic(relay.connect_and_run(connection_timeout)) => {
+                        if let Err(reason) = res {
+                            relay.task_panicked("connection", reason.clone());
+                            relay.disconnected(DisconnectReason::Panic(reason));
+                        }
+                    },
                     // Handle terminate
                     _ = relay.handle_terminate(&mut rx_service) => {
+                        if relay.is_connected() {
+                            relay.disconnected(DisconnectReason::Terminated);
+                        }
+
+                        // Update status
+                        relay.set_status(RelayStatus::Terminated, true);
+
+                        // Break loop
+                        break;
+                    }
+                    // Handle blacklisting
+                    _ = relay.state.filtering().wait_for_relay_url(&relay.url) => {
+                        if relay.is_connected() {
+                            relay.disconnected(DisconnectReason::Terminated);
+                        }
+
                         // Update status
                         relay.set_status(RelayStatus::Terminated, true);
 
+                        tracing::debug!(url = %relay.url, "Relay blacklisted, disconnected.");
+
                         // Break loop
                         break;
                     }
@@ -499,6 +939,26 @@ impl InnerRelay {
                     break;
                 }
 
+                // If relay has been blacklisted, set status to terminated and break loop.
+                if relay.state.filtering().has_relay_url(&relay.url).await {
+                    relay.set_status(RelayStatus::Terminated, true);
+                    tracing::debug!(url = %relay.url, "Relay blacklisted, breaking loop.");
+                    break;
+                }
+
+                // Check if relay must be banned (the ban is awaited at the next iteration)
+                if relay.reached_ban_threshold() {
+                    let until: Timestamp =
+                        relay.state.time_provider().now() + relay.opts.ban_duration;
+                    relay.ban_until(until);
+                    tracing::warn!(
+                        url = %relay.url,
+                        duration = relay.opts.ban_duration.as_secs(),
+                        "Too many consecutive connection failures, relay banned."
+                    );
+                    continue;
+                }
+
                 // Check if reconnection is enabled
                 if relay.opts.reconnect {
                     // Check if relay is marked as disconnected. If not, update status.
@@ -538,12 +998,95 @@ impl InnerRelay {
             // Set that connection task is no longer running
             relay.running.store(false, Ordering::SeqCst);
 
+            // Notify who is waiting for the termination
+            let mut acks = relay.termination_acks.lock().await;
+            for ack in acks.drain(..) {
+                let _ = ack.send(());
+            }
+            drop(acks);
+
             tracing::debug!(url = %relay.url, "Auto connect loop terminated.");
         });
     }
 
+    /// Send the disconnection notification
+    fn disconnected(&self, reason: DisconnectReason) {
+        self.disconnect_notified.store(true, Ordering::SeqCst);
+        tracing::debug!(url = %self.url, reason = ?reason, "Disconnected.");
+        self.send_notification(RelayNotification::Disconnected { reason }, true);
+    }
+
+    /// Log the panic and send the notification
+    fn task_panicked(&self, task: &str, reason: String) {
+        tracing::error!(url = %self.url, task, reason = %reason, "Relay task panicked, restarting it.");
+        self.send_notification(
+            RelayNotification::TaskPanicked {
+                task: task.to_string(),
+                reason,
+            },
+            true,
+        );
+    }
+
+    /// Ban the relay until the timestamp
+    ///
+    /// The ban is awaited by the connection task.
+    pub(crate) fn ban_until(&self, until: Timestamp) {
+        self.banned_until.store(until.as_u64(), Ordering::SeqCst);
+    }
+
+    /// Get the end of the ban, if banned
+    pub(crate) fn banned_until(&self) -> Option<Timestamp> {
+        match self.banned_until.load(Ordering::SeqCst) {
+            0 => None,
+            until => Some(Timestamp::from(until)),
+        }
+    }
+
+    /// Check if the relay is banned
+    #[inline]
+    pub(crate) fn is_banned(&self) -> bool {
+        self.remaining_ban().is_some()
+    }
+
+    /// Get the remaining ban time, if any
+    fn remaining_ban(&self) -> Option<Duration> {
+        let until: Timestamp = self.banned_until()?;
+        let now: Timestamp = self.state.time_provider().now();
+        if until > now {
+            Some(Duration::from_secs(until.as_u64() - now.as_u64()))
+        } else {
+            self.banned_until.store(0, Ordering::SeqCst);
+            None
+        }
+    }
+
+    /// Check if the consecutive connection failures reached the ban threshold
+    fn reached_ban_threshold(&self) -> bool {
+        match self.opts.ban_threshold {
+            Some(threshold) => self.consecutive_failures.load(Ordering::SeqCst) >= threshold,
+            None => false,
+        }
+    }
+
+    /// Retry policy configured at pool level or no retries
+    #[inline]
+    fn retry_policy(&self) -> RetryPolicy {
+        self.state
+            .retry_policy()
+            .copied()
+            .unwrap_or_else(RetryPolicy::none)
+    }
+
     /// Depending on attempts and success, use default or incremental retry interval
+    ///
+    /// If a retry policy is configured at pool level, it's used instead.
     fn calculate_retry_interval(&self) -> Duration {
+        // Check if pool retry policy is set
+        if let Some(policy) = self.state.retry_policy() {
+            return policy.delay(self.consecutive_failures.load(Ordering::SeqCst));
+        }
+
         // Check if incremental interval is enabled
         if self.opts.adjust_retry_interval {
             // Calculate the difference between attempts and success
@@ -613,13 +1156,38 @@ impl InnerRelay {
         };
 
         // Connect
-        match wsocket_connect((&self.url).into(), &self.opts.connection_mode, timeout).await {
+        match self
+            .state
+            .transport()
+            .connect_with_socket_opts(
+                (&self.url).into(),
+                &self.opts.connection_mode,
+                timeout,
+                &self.opts.socket,
+            )
+            .await
+        {
             Ok((ws_tx, ws_rx)) => {
+                // The close frame belongs to the previous connection
+                let mut close_frame = self.close_frame.write().await;
+                *close_frame = None;
+                drop(close_frame);
+
+                // Same for the authentication error
+                let mut auth_error = self.auth_error.write().await;
+                *auth_error = None;
+                drop(auth_error);
+
+                self.disconnect_notified.store(false, Ordering::SeqCst);
+
                 // Update status
                 self.set_status(RelayStatus::Connected, true);
 
                 // Increment success stats
-                self.stats.new_success();
+                self.stats.new_success(self.state.time_provider().now());
+
+                // Reset consecutive failures
+                self.consecutive_failures.store(0, Ordering::SeqCst);
 
                 // Request information document
                 #[cfg(feature = "nip11")]
@@ -632,13 +1200,21 @@ impl InnerRelay {
                 // Update status
                 self.set_status(RelayStatus::Disconnected, false);
 
+                // Increment consecutive failures
+                self.consecutive_failures.fetch_add(1, Ordering::SeqCst);
+
                 // Log error
                 tracing::error!(url = %self.url, error= %e, "Connection failed.");
+
+                // Notify only the first failure of the outage
+                if !self.disconnect_notified.load(Ordering::SeqCst) {
+                    self.disconnected(DisconnectReason::ConnectionFailed(e.to_string()));
+                }
             }
         }
     }
 
-    async fn run_message_handler(&self, ws_tx: Sink, ws_rx: Stream) {
+    async fn run_message_handler(&self, ws_tx: BoxSink, ws_rx: BoxStream) {
         // (Re)subscribe to relay
         if self.flags.can_read() {
             if let Err(e) = self.resubscribe().await {
@@ -646,28 +1222,72 @@ impl InnerRelay {
             }
         }
 
+        // Send events queued in the outbox
+        if self.opts.outbox {
+            let relay = self.clone();
+            self.state
+                .spawn(format!("{}: outbox flush", self.url), async move {
+                    relay.flush_outbox().await
+                });
+        }
+
         let ping: PingTracker = PingTracker::default();
 
         // Wait that one of the futures terminate/complete
-        tokio::select! {
+        let reason: DisconnectReason = tokio::select! {
             res = self.receiver_message_handler(ws_rx, &ping) => match res {
-                Ok(()) => tracing::trace!(url = %self.url, "Relay received exited."),
-                Err(e) => tracing::error!(url = %self.url, error = %e, "Relay receiver exited with error.")
+                Ok(frame) => {
+                    tracing::trace!(url = %self.url, "Relay received exited.");
+                    DisconnectReason::Closed { frame }
+                }
+                Err(e) => {
+                    tracing::error!(url = %self.url, error = %e, "Relay receiver exited with error.");
+                    DisconnectReason::from_error(&e)
+                }
             },
             res = self.sender_message_handler(ws_tx, &ping) => match res {
-                Ok(()) => tracing::trace!(url = %self.url, "Relay sender exited."),
-                Err(e) => tracing::error!(url = %self.url, error = %e, "Relay sender exited with error.")
+                Ok(()) => {
+                    tracing::trace!(url = %self.url, "Relay sender exited.");
+                    DisconnectReason::Terminated
+                }
+                Err(e) => {
+                    tracing::error!(url = %self.url, error = %e, "Relay sender exited with error.");
+                    DisconnectReason::from_error(&e)
+                }
             },
             res = self.ping_handler(&ping) => match res {
-                Ok(()) => tracing::trace!(url = %self.url, "Relay pinger exited."),
-                Err(e) => tracing::error!(url = %self.url, error = %e, "Relay pinger exited with error.")
+                Ok(()) => {
+                    tracing::trace!(url = %self.url, "Relay pinger exited.");
+                    DisconnectReason::Terminated
+                }
+                Err(e) => {
+                    tracing::error!(url = %self.url, error = %e, "Relay pinger exited with error.");
+                    DisconnectReason::from_error(&e)
+                }
             }
-        }
+        };
+
+        // The pending REQs will never receive the EOSE on this connection
+        #[cfg(not(target_arch = "wasm32"))]
+        self.stats.clear_pending_reqs().await;
+
+        // The relay closed the connection after a failed authentication
+        let reason: DisconnectReason = match reason {
+            DisconnectReason::Closed { .. } | DisconnectReason::Io(..) => {
+                match self.auth_error.write().await.take() {
+                    Some(error) => DisconnectReason::AuthFailed(error),
+                    None => reason,
+                }
+            }
+            reason => reason,
+        };
+
+        self.disconnected(reason);
     }
 
     async fn sender_message_handler(
         &self,
-        mut ws_tx: Sink,
+        mut ws_tx: BoxSink,
         ping: &PingTracker,
     ) -> Result<(), Error> {
         #[cfg(target_arch = "wasm32")]
@@ -682,15 +1302,31 @@ impl InnerRelay {
                 // Nostr channel receiver
                 Some(msgs) = rx_nostr.recv() => {
                     // Serialize messages to JSON and compose WebSocket text messages
+                    let mut subscriptions: Vec<(SubscriptionId, usize, bool)> = Vec::new();
                     let msgs: Vec<WsMessage> = msgs
                         .into_iter()
-                        .map(|msg| WsMessage::Text(msg.as_json()))
+                        .map(|msg| {
+                            let json: String = msg.as_json();
+                            if let Some((id, closing)) = client_msg_subscription_id(&msg) {
+                                subscriptions.push((id.clone(), json.len(), closing));
+                            }
+                            WsMessage::Text(json)
+                        })
                         .collect();
 
                     // Calculate messages size
                     let size: usize = msgs.iter().map(|msg| msg.len()).sum();
                     let len: usize = msgs.len();
 
+                    // Record the frames
+                    if let Some(capture) = &self.capture {
+                        for msg in msgs.iter() {
+                            if let WsMessage::Text(json) = msg {
+                                capture.record(CaptureDirection::Outbound, json).await;
+                            }
+                        }
+                    }
+
                     // Log
                     if len == 1 {
                         let json = &msgs[0]; // SAFETY: len checked above (len == 1)
@@ -704,6 +1340,17 @@ impl InnerRelay {
 
                     // Increase sent bytes
                     self.stats.add_bytes_sent(size);
+                    for (id, sent, closing) in subscriptions.into_iter() {
+                        if closing {
+                            self.stats.remove_subscription_bandwidth(&id);
+
+                            // Closed before the EOSE
+                            #[cfg(not(target_arch = "wasm32"))]
+                            self.stats.req_completed(&id, false).await;
+                        } else {
+                            self.stats.add_subscription_bandwidth(&id, Bandwidth { sent, received: 0 }, true);
+                        }
+                    }
                 }
                 // Ping channel receiver
                 Ok(()) = rx_ping.changed() => {
@@ -734,14 +1381,14 @@ impl InnerRelay {
 
     async fn receiver_message_handler(
         &self,
-        mut ws_rx: Stream,
+        mut ws_rx: BoxStream,
         ping: &PingTracker,
-    ) -> Result<(), Error> {
+    ) -> Result<Option<RelayCloseFrame>, Error> {
         #[cfg(target_arch = "wasm32")]
         let _ping = ping;
 
         while let Some(msg) = ws_rx.next().await {
-            match msg.map_err(Error::websocket)? {
+            match msg.map_err(Error::WebSocket)? {
                 #[cfg(not(target_arch = "wasm32"))]
                 WsMessage::Pong(bytes) => {
                     if self.flags.has_ping() {
@@ -774,16 +1421,65 @@ impl InnerRelay {
                         }
                     }
                 }
-                WsMessage::Text(json) => self.handle_relay_message(&json).await,
+                WsMessage::Text(json) => {
+                    // Start measuring the ingestion latency
+                    let timer: EventTimer = EventTimer::start();
+
+                    if let Some(capture) = &self.capture {
+                        capture.record(CaptureDirection::Inbound, &json).await;
+                    }
+
+                    // Catch the panics, to not stop the processing of the next messages
+                    if let Err(reason) =
+                        supervisor::catch_panic(self.handle_relay_message_with_timer(&json, timer))
+                            .await
+                    {
+                        tracing::error!(
+                            url = %self.url,
+                            message = %ContentPreview::new(&json).max_chars(512),
+                            "Panic while handling relay message."
+                        );
+                        self.task_panicked("message handler", reason);
+                    }
+                }
                 WsMessage::Binary(_) => {
                     tracing::warn!(url = %self.url, "Binary messages aren't supported.");
                 }
                 #[cfg(not(target_arch = "wasm32"))]
+                WsMessage::Close(frame) => {
+                    let frame: Option<RelayCloseFrame> = frame.map(|frame| RelayCloseFrame {
+                        code: u16::from(frame.code),
+                        reason: frame.reason.to_string(),
+                    });
+
+                    match &frame {
+                        Some(RelayCloseFrame { code, reason }) => {
+                            tracing::info!(url = %self.url, code = %code, reason = %reason, "Connection closed by relay.")
+                        }
+                        None => tracing::info!(url = %self.url, "Connection closed by relay."),
+                    }
+
+                    // Save close frame
+                    let mut close_frame = self.close_frame.write().await;
+                    *close_frame = frame.clone();
+                    drop(close_frame);
+
+                    // Send notification
+                    self.send_notification(
+                        RelayNotification::Closed {
+                            frame: frame.clone(),
+                        },
+                        false,
+                    );
+
+                    return Ok(frame);
+                }
+                #[cfg(not(target_arch = "wasm32"))]
                 _ => {}
             }
         }
 
-        Ok(())
+        Ok(None)
     }
 
     async fn ping_handler(&self, ping: &PingTracker) -> Result<(), Error> {
@@ -805,11 +1501,11 @@ impl InnerRelay {
                 self.channels.ping(nonce)?;
 
                 // Sleep
-                time::sleep(PING_INTERVAL).await;
+                time::sleep(self.opts.ping_interval).await;
             }
         } else {
             loop {
-                time::sleep(PING_INTERVAL).await;
+                time::sleep(self.opts.ping_interval).await;
             }
         }
 
@@ -817,13 +1513,20 @@ impl InnerRelay {
         {
             let _ping = ping;
             loop {
-                time::sleep(PING_INTERVAL).await;
+                time::sleep(self.opts.ping_interval).await;
             }
         }
     }
 
-    async fn handle_relay_message(&self, msg: &str) {
-        match self.handle_raw_relay_message(msg).await {
+    #[inline]
+    pub(crate) async fn handle_relay_message(&self, msg: &str) {
+        self.handle_relay_message_with_timer(msg, EventTimer::start())
+            .await
+    }
+
+    /// Handle the message, measuring the ingestion latency from the start of the `timer`
+    async fn handle_relay_message_with_timer(&self, msg: &str, timer: EventTimer) {
+        match self.handle_raw_relay_message(msg, timer).await {
             Ok(Some(message)) => {
                 match &message {
                     RelayMessage::Notice(message) => {
@@ -841,6 +1544,11 @@ impl InnerRelay {
                             msg = %message,
                             "Received OK."
                         );
+
+                        // Purge the local events if a batched request to vanish has been accepted
+                        if *status {
+                            self.state.vanish_request_accepted(event_id).await;
+                        }
                     }
                     RelayMessage::EndOfStoredEvents(id) => {
                         tracing::debug!(
@@ -848,6 +1556,9 @@ impl InnerRelay {
                             id = %id,
                             "Received EOSE."
                         );
+                        self.set_subscription_eose(id, true).await;
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.stats.req_completed(id, true).await;
                     }
                     RelayMessage::Closed {
                         subscription_id,
@@ -860,6 +1571,8 @@ impl InnerRelay {
                             "Subscription closed."
                         );
                         self.subscription_closed(subscription_id).await;
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.stats.req_completed(subscription_id, false).await;
                     }
                     RelayMessage::Auth { challenge } => {
                         tracing::debug!(
@@ -872,10 +1585,14 @@ impl InnerRelay {
                         if self.state.is_auto_authentication_enabled() {
                             let relay = self.clone();
                             let challenge: String = challenge.clone();
-                            task::spawn(async move {
+                            self.state.spawn(format!("{}: auth", self.url), async move {
                                 // Authenticate to relay
                                 match relay.auth(challenge).await {
                                     Ok(..) => {
+                                        let mut auth_error = relay.auth_error.write().await;
+                                        *auth_error = None;
+                                        drop(auth_error);
+
                                         relay.send_notification(
                                             RelayNotification::Authenticated,
                                             false,
@@ -893,6 +1610,10 @@ impl InnerRelay {
                                         }
                                     }
                                     Err(e) => {
+                                        let mut auth_error = relay.auth_error.write().await;
+                                        *auth_error = Some(e.to_string());
+                                        drop(auth_error);
+
                                         relay.send_notification(
                                             RelayNotification::AuthenticationFailed,
                                             false,
@@ -924,7 +1645,11 @@ impl InnerRelay {
         }
     }
 
-    async fn handle_raw_relay_message(&self, msg: &str) -> Result<Option<RelayMessage>, Error> {
+    async fn handle_raw_relay_message(
+        &self,
+        msg: &str,
+        timer: EventTimer,
+    ) -> Result<Option<RelayMessage>, Error> {
         let size: usize = msg.len();
 
         tracing::trace!(url = %self.url, size = %size, msg = %msg, "Received new relay message.");
@@ -940,12 +1665,31 @@ impl InnerRelay {
             }
         }
 
+        let msg: RawRelayMessage = RawRelayMessage::from_json(msg)?;
+
+        // Update subscription bytes received
+        if let Some((id, closing)) = raw_relay_msg_subscription_id(&msg) {
+            let id: SubscriptionId = SubscriptionId::new(id);
+            if closing {
+                self.stats.remove_subscription_bandwidth(&id);
+            } else {
+                self.stats.add_subscription_bandwidth(
+                    &id,
+                    Bandwidth {
+                        sent: 0,
+                        received: size,
+                    },
+                    false,
+                );
+            }
+        }
+
         // Handle msg
-        match RawRelayMessage::from_json(msg)? {
+        match msg {
             RawRelayMessage::Event {
                 subscription_id,
                 event,
-            } => self.handle_raw_event(subscription_id, event).await,
+            } => self.handle_raw_event(subscription_id, event, timer).await,
             m => Ok(Some(RelayMessage::try_from(m)?)),
         }
     }
@@ -954,6 +1698,7 @@ impl InnerRelay {
         &self,
         subscription_id: String,
         event: RawEvent,
+        mut timer: EventTimer,
     ) -> Result<Option<RelayMessage>, Error> {
         let kind: Kind = Kind::from(event.kind);
 
@@ -1003,6 +1748,15 @@ impl InnerRelay {
                 tracing::debug!("Received event authored by non-whitelisted public key: {pubkey}");
                 return Ok(None);
             }
+            CheckFiltering::PublicKeyMentioned(pubkey) => {
+                tracing::debug!("Received event that mention blacklisted public key: {pubkey}");
+                return Ok(None);
+            }
+            CheckFiltering::ThreadMuted(..)
+            | CheckFiltering::HashtagBlacklisted(..)
+            | CheckFiltering::WordBlacklisted(..) => {
+                // Not returned for partial events
+            }
         }
 
         // Check min POW
@@ -1040,6 +1794,9 @@ impl InnerRelay {
             }
         }
 
+        // Check if the relay already sent the event, to track its contribution
+        let first_from_relay: bool = self.stats.event_received(partial_event.id);
+
         // Set event as seen by relay
         if let Err(e) = self
             .state
@@ -1056,23 +1813,101 @@ impl InnerRelay {
         // Compose full event
         let event: Event = partial_event.merge(missing)?;
 
+        let metrics: &IngestionMetrics = self.state.ingestion_metrics();
+        timer.stage(metrics, IngestionStage::Parse);
+
         // Check if it's expired
         if event.is_expired() {
             return Err(Error::EventExpired);
         }
 
+        // Check mentions filtering
+        if let CheckFiltering::PublicKeyMentioned(pubkey) =
+            self.state.filtering().check_mentions(&event).await
+        {
+            tracing::debug!("Received event that mention blacklisted public key: {pubkey}");
+            return Ok(None);
+        }
+
+        // Check content filtering (muted threads, hashtags and words)
+        match self.state.filtering().check_content(&event).await {
+            CheckFiltering::ThreadMuted(id) => {
+                tracing::debug!("Received event of muted thread: {id}");
+                return Ok(None);
+            }
+            CheckFiltering::HashtagBlacklisted(hashtag) => {
+                tracing::debug!("Received event with blacklisted hashtag: {hashtag}");
+                return Ok(None);
+            }
+            CheckFiltering::WordBlacklisted(word) => {
+                tracing::debug!("Received event that contains blacklisted word: {word}");
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        // Check admission policy
+        if let Some(policy) = self.state.admit_policy() {
+            if let Admission::Reject { reason } = policy.admit(&event, &self.url) {
+                tracing::debug!(
+                    "Event {} rejected by admission policy: {}",
+                    event.id,
+                    reason.as_deref().unwrap_or("unknown")
+                );
+                return Ok(None);
+            }
+        }
+
         let subscription_id: SubscriptionId = SubscriptionId::new(subscription_id);
-        let event: Box<Event> = Box::new(event);
+        let event: Arc<Event> = Arc::new(event);
 
         // TODO: check if filter match
 
-        // Check if event exists
-        if let DatabaseEventStatus::NotExistent = status {
-            // Verify event
-            event.verify()?;
+        // Check if event is new, using the custom deduplication policy if set
+        let is_new: bool = match self
+            .state
+            .dedup_policy()
+            .map(|policy| policy.deduplicate(&event, status))
+        {
+            Some(Deduplication::Duplicate) => false,
+            Some(Deduplication::New) => true,
+            Some(Deduplication::Default) | None => {
+                matches!(status, DatabaseEventStatus::NotExistent)
+            }
+        };
+
+        // Check if the event has been already received before the restart
+        let is_new: bool = is_new && !self.cursor_contains(&subscription_id, &event).await;
+
+        if is_new {
+            // Verify event (skipped if already verified for another relay)
+            self.state.verify_event(&event)?;
+            timer.stage(metrics, IngestionStage::Verify);
+
+            // Let the admission policy record the verified event
+            if let Some(policy) = self.state.admit_policy() {
+                policy.admitted(&event, &self.url);
+            }
+
+            // Purge the events of the local user if it's a request to vanish
+            if event.kind == Kind::RequestToVanish {
+                self.state.handle_vanish_request(&event).await?;
+                timer.stage(metrics, IngestionStage::Vanish);
+            }
 
             // Save into database
             self.state.database().save_event(&event).await?;
+            timer.stage(metrics, IngestionStage::Store);
+
+            // Track the events contributed by the relay
+            self.stats.new_unique_event();
+
+            // Use the live events to measure the clock skew
+            if let Some(skew) = self.state.clock_skew() {
+                if self.is_subscription_live(&subscription_id).await {
+                    skew.add_event_sample(&self.url, event.created_at);
+                }
+            }
 
             // Send notification
             self.send_notification(
@@ -1082,6 +1917,35 @@ impl InnerRelay {
                 },
                 true,
             );
+            timer.stage(metrics, IngestionStage::Notify);
+
+            let latency: Duration = timer.finish(metrics);
+            tracing::trace!(url = %self.url, id = %event.id, latency = ?latency, "Event ingested.");
+        } else if first_from_relay {
+            // Already known, so received from another relay.
+            // Not counted if the relay sent it again (i.e. re-subscription)
+            self.stats.new_duplicate_event();
+        }
+
+        // Only the verified events can move the subscription `since`.
+        // The ID of an already stored event is checked to match the received fields,
+        // so a relay can't resend it with a forged `created_at`.
+        let verified: bool =
+            is_new || (matches!(status, DatabaseEventStatus::Saved) && event.verify_id());
+
+        // Don't let a future-dated event move the `since` after now
+        let created_at: Timestamp = cmp::min(event.created_at, self.state.time_provider().now());
+
+        // Track newest event received for the subscription
+        if verified && self.opts.resubscribe_since {
+            self.update_subscription_last_event_at(&subscription_id, created_at)
+                .await;
+        }
+
+        // Advance the subscription cursor
+        if verified {
+            self.advance_cursor(&subscription_id, event.id, created_at)
+                .await;
         }
 
         Ok(Some(RelayMessage::Event {
@@ -1101,6 +1965,55 @@ impl InnerRelay {
         Ok(())
     }
 
+    /// Close the connection, keeping the subscriptions
+    ///
+    /// The subscriptions are sent again, with the `since` moved before the pause, at the next connection.
+    pub(crate) async fn pause(&self, timeout: Duration) -> Result<(), Error> {
+        let now: Timestamp = self.state.time_provider().now();
+
+        let mut subscriptions = self.subscriptions.write().await;
+        for data in subscriptions.values_mut() {
+            data.closed = true;
+            data.paused_at = Some(now);
+        }
+        drop(subscriptions);
+
+        self.terminate(timeout).await
+    }
+
+    /// Forget the pause, so the subscriptions are no longer sent with the `since` moved before it
+    pub(crate) async fn clear_pause(&self) {
+        let mut subscriptions = self.subscriptions.write().await;
+        for data in subscriptions.values_mut() {
+            data.paused_at = None;
+        }
+    }
+
+    /// Disconnect and wait for the connection task to exit
+    pub async fn terminate(&self, timeout: Duration) -> Result<(), Error> {
+        // Register ack before checking if the task is running, to avoid missing the notification
+        let (tx, rx) = oneshot::channel::<()>();
+        let mut acks = self.termination_acks.lock().await;
+        acks.push(tx);
+        drop(acks);
+
+        // Send terminate signal
+        self.disconnect()?;
+
+        // Connection task not running, nothing to wait
+        if !self.is_running() {
+            return Ok(());
+        }
+
+        // Wait for ack
+        time::timeout(Some(timeout), rx)
+            .await
+            .ok_or(Error::Timeout)?
+            .map_err(|_| Error::CantSendChannelMessage {
+                channel: String::from("termination"),
+            })
+    }
+
     #[inline]
     pub fn send_msg(&self, msg: ClientMessage) -> Result<(), Error> {
         self.batch_msg(vec![msg])
@@ -1129,6 +2042,38 @@ impl InnerRelay {
         self.channels.send_client_msgs(msgs)
     }
 
+    /// Get the stored event to upload with the negentropy sync, applying the outgoing middlewares
+    ///
+    /// The event re-signed by the middlewares is saved into the database.
+    async fn prepare_upload(&self, id: &EventId) -> Option<Event> {
+        let event: Event = match self.state.database().event_by_id(id).await {
+            Ok(Some(event)) => event,
+            // Event not found
+            Ok(None) => return None,
+            Err(e) => {
+                tracing::error!(url = %self.url, error = %e, "Couldn't upload event.");
+                return None;
+            }
+        };
+
+        let event: Event = match self.state.apply_outgoing_middlewares(event).await {
+            Ok(event) => event,
+            Err(e) => {
+                tracing::warn!(url = %self.url, %id, error = %e, "Event not uploaded.");
+                return None;
+            }
+        };
+
+        if event.id != *id {
+            if let Err(e) = self.state.database().save_event(&event).await {
+                tracing::error!(url = %self.url, error = %e, "Couldn't save re-signed event.");
+                return None;
+            }
+        }
+
+        Some(event)
+    }
+
     #[inline]
     fn send_neg_msg(&self, id: SubscriptionId, message: String) -> Result<(), Error> {
         self.send_msg(ClientMessage::NegMsg {
@@ -1160,9 +2105,29 @@ impl InnerRelay {
             .await
     }
 
+    /// Send event and wait for the `OK`, retrying according to the pool retry policy
     pub async fn send_event(&self, event: Event) -> Result<EventId, Error> {
+        self.retry_policy()
+            .retry(|| self.send_event_once(event.clone()))
+            .await
+    }
+
+    async fn send_event_once(&self, event: Event) -> Result<EventId, Error> {
         // Health, write permission and number of messages checks are executed in `batch_msg` method.
 
+        // Check the NIP11 limitations
+        #[cfg(feature = "nip11")]
+        self.check_event_limitation(&event).await?;
+
+        // Protected events (NIP70) rely on the NIP42 authentication requested by the relay
+        let protected: bool = event.is_protected();
+
+        // Queue event if relay is not connected
+        if self.opts.outbox && self.flags.can_write() && !self.status().is_connected() {
+            self.queue_event(event).await?;
+            return Err(Error::EventQueued);
+        }
+
         // Subscribe to notifications
         let mut notifications = self.internal_notification_sender.subscribe();
 
@@ -1192,22 +2157,131 @@ impl InnerRelay {
                 return if status {
                     Ok(event_id)
                 } else {
-                    Err(Error::RelayMessage(message))
+                    Err(rejection_error(protected, message))
                 };
             }
         }
 
-        Err(Error::RelayMessage(message))
+        Err(rejection_error(protected, message))
+    }
+
+    /// Queue the event in the outbox
+    ///
+    /// The outbox is saved in the database, or kept in memory if the database doesn't support it.
+    async fn queue_event(&self, event: Event) -> Result<(), Error> {
+        let queued: Vec<Event> = self.outbox_events().await?;
+
+        // Already queued
+        if queued.iter().any(|e| e.id == event.id) {
+            return Ok(());
+        }
+
+        if queued.len() >= self.opts.outbox_limit {
+            return Err(Error::OutboxFull {
+                limit: self.opts.outbox_limit,
+            });
+        }
+
+        match self
+            .state
+            .database()
+            .save_outbox_event(&self.url, &event)
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(DatabaseError::NotSupported) => {
+                let mut outbox = self.outbox.lock().await;
+                outbox.push(event);
+                Ok(())
+            }
+            Err(e) => Err(Error::Database(e)),
+        }
+    }
+
+    /// Get the events queued in the outbox, from the oldest
+    async fn outbox_events(&self) -> Result<Vec<Event>, Error> {
+        match self.state.database().outbox(&self.url).await {
+            Ok(events) => Ok(events),
+            Err(DatabaseError::NotSupported) => {
+                let outbox = self.outbox.lock().await;
+                Ok(outbox.clone())
+            }
+            Err(e) => Err(Error::Database(e)),
+        }
+    }
+
+    async fn remove_outbox_event(&self, id: &EventId) -> Result<(), Error> {
+        match self
+            .state
+            .database()
+            .remove_outbox_event(&self.url, id)
+            .await
+        {
+            Ok(()) => Ok(()),
+            Err(DatabaseError::NotSupported) => {
+                let mut outbox = self.outbox.lock().await;
+                outbox.retain(|e| &e.id != id);
+                Ok(())
+            }
+            Err(e) => Err(Error::Database(e)),
+        }
+    }
+
+    async fn flush_outbox(&self) {
+        let events: Vec<Event> = match self.outbox_events().await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::error!(url = %self.url, error = %e, "Impossible to load the outbox.");
+                return;
+            }
+        };
+
+        for event in events.into_iter() {
+            let id: EventId = event.id;
+            let (status, message): (bool, String) = match self.send_event(event).await {
+                Ok(..) => (true, String::new()),
+                // Rejected by the relay or not respecting the relay limitations: never retry it
+                Err(Error::RelayMessage(message)) => (false, message),
+                Err(e @ Error::Limitation(..)) => (false, e.to_string()),
+                // Relay disconnected again: the events are still queued
+                Err(Error::EventQueued) => break,
+                Err(e) => {
+                    tracing::error!(url = %self.url, id = %id, error = %e, "Can't send queued event, retrying at the next connection.");
+                    break;
+                }
+            };
+
+            tracing::debug!(url = %self.url, id = %id, status, "Queued event sent.");
+
+            if let Err(e) = self.remove_outbox_event(&id).await {
+                tracing::error!(url = %self.url, id = %id, error = %e, "Impossible to remove event from the outbox.");
+            }
+
+            self.send_notification(
+                RelayNotification::OutboxEventSent {
+                    event_id: id,
+                    status,
+                    message,
+                },
+                true,
+            );
+        }
     }
 
     async fn auth(&self, challenge: String) -> Result<(), Error> {
-        // Get signer
-        let signer = self.state.signer().await?;
+        let builder: EventBuilder = EventBuilder::auth(challenge, self.url.clone())
+            .custom_created_at(self.state.time_provider().now());
 
         // Construct event
-        let event: Event = EventBuilder::auth(challenge, self.url.clone())
-            .sign(&signer)
-            .await?;
+        let event: Event = if self.state.is_ephemeral_auth_enabled() && !self.flags.has_write() {
+            // REQ-only session: use throwaway keys
+            let keys: Keys = Keys::generate();
+            builder.sign_with_keys(&keys)?
+        } else {
+            // Get signer
+            let signer = self.state.signer().await?;
+            builder.sign(&signer).await?
+        };
 
         // Subscribe to notifications
         let mut notifications = self.internal_notification_sender.subscribe();
@@ -1224,6 +2298,7 @@ impl InnerRelay {
 
         // Check status
         if status {
+            self.authenticated.store(true, Ordering::SeqCst);
             Ok(())
         } else {
             Err(Error::RelayMessage(message))
@@ -1302,6 +2377,10 @@ impl InnerRelay {
         let subscriptions = self.subscriptions().await;
         for (id, filters) in subscriptions.into_iter() {
             if !filters.is_empty() && self.should_resubscribe(&id).await {
+                let filters: Vec<Filter> = self.resubscription_filters(&id, filters).await;
+                self.set_subscription_eose(&id, false).await;
+                #[cfg(not(target_arch = "wasm32"))]
+                self.stats.req_sent(id.clone()).await;
                 self.send_msg(ClientMessage::req(id, filters))?;
             } else {
                 tracing::debug!("Skip re-subscription of '{id}'");
@@ -1333,19 +2412,42 @@ impl InnerRelay {
             return Err(Error::FiltersEmpty);
         }
 
+        // Check the NIP11 limitations
+        #[cfg(feature = "nip11")]
+        self.check_subscription_limitation(&id, &filters).await?;
+
+        // Load the persistent cursor
+        let cursor: Option<SubscriptionCursor> = if opts.cursor && !opts.is_auto_closing() {
+            self.load_cursor(&id).await
+        } else {
+            None
+        };
+
+        // Resume from the cursor
+        let req_filters: Vec<Filter> = match &cursor {
+            Some(cursor) if !cursor.ids().is_empty() => {
+                filters_since(filters.clone(), cursor.created_at())
+            }
+            _ => filters.clone(),
+        };
+
         // Compose and send REQ message
-        let msg: ClientMessage = ClientMessage::req(id.clone(), filters.clone());
+        let msg: ClientMessage = ClientMessage::req(id.clone(), req_filters);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.stats.req_sent(id.clone()).await;
         self.send_msg(msg)?;
 
         // Check if auto-close condition is set
         match opts.auto_close {
             Some(opts) => {
                 let this = self.clone();
-                task::spawn(async move {
+                self.state.spawn(format!("{}: auto-close {id}", self.url), async move {
                     let sub_id: SubscriptionId = id.clone();
                     let relay = this.clone();
                     let res: Option<(bool, Option<SubscriptionAutoClosedReason>)> = time::timeout(opts.timeout, async move {
                         let mut counter = 0;
+                        let mut total: usize = 0;
+                        let mut max_events_reached: bool = false;
                         let mut received_eose: bool = false;
                         let mut require_resubscription: bool = false;
 
@@ -1357,6 +2459,12 @@ impl InnerRelay {
                                         subscription_id, ..
                                     } => {
                                         if subscription_id == id {
+                                            total += 1;
+                                            if opts.max_events.map_or(false, |max| total >= max) {
+                                                max_events_reached = true;
+                                                break;
+                                            }
+
                                             if let FilterOptions::WaitForEventsAfterEOSE(num) =
                                                 opts.filter
                                             {
@@ -1370,7 +2478,7 @@ impl InnerRelay {
                                         }
                                     }
                                     RelayMessage::EndOfStoredEvents(subscription_id) => {
-                                        if subscription_id == id {
+                                        if subscription_id == id && opts.close_on_eose {
                                             received_eose = true;
                                             if let FilterOptions::ExitOnEOSE
                                             | FilterOptions::WaitDurationAfterEOSE(_) =
@@ -1426,25 +2534,28 @@ impl InnerRelay {
                             }
                         }
 
-                        if let FilterOptions::WaitDurationAfterEOSE(duration) = opts.filter {
-                            time::timeout(Some(duration), async {
-                                while let Ok(notification) = notifications.recv().await {
-                                    match notification {
-                                        RelayNotification::RelayStatus { status } => {
-                                            if status.is_disconnected() {
+                        match opts.filter {
+                            FilterOptions::WaitDurationAfterEOSE(duration) if !max_events_reached => {
+                                time::timeout(Some(duration), async {
+                                    while let Ok(notification) = notifications.recv().await {
+                                        match notification {
+                                            RelayNotification::RelayStatus { status } => {
+                                                if status.is_disconnected() {
+                                                    return Ok(());
+                                                }
+                                            }
+                                            RelayNotification::Shutdown => {
                                                 return Ok(());
                                             }
+                                            _ => (),
                                         }
-                                        RelayNotification::Shutdown => {
-                                            return Ok(());
-                                        }
-                                        _ => (),
                                     }
-                                }
 
-                                Ok::<(), Error>(())
-                            })
-                            .await;
+                                    Ok::<(), Error>(())
+                                })
+                                .await;
+                            }
+                            _ => (),
                         }
 
                         (true, Some(SubscriptionAutoClosedReason::Completed)) // Need to send CLOSE msg
@@ -1483,6 +2594,10 @@ impl InnerRelay {
             None => {
                 // No auto-close subscription: update subscription filters
                 self.update_subscription(id.clone(), filters, true).await;
+
+                if let Some(cursor) = cursor {
+                    self.set_subscription_cursor(&id, cursor).await;
+                }
             }
         };
 
@@ -1545,7 +2660,7 @@ impl InnerRelay {
                         ..
                     } => {
                         if subscription_id == id {
-                            callback(*event).await;
+                            callback(Event::clone(&event)).await;
                         }
                     }
                     RelayNotification::SubscriptionAutoClosed { reason } => {
@@ -1593,6 +2708,109 @@ impl InnerRelay {
         Ok(events.into_inner())
     }
 
+    /// The `max_limit` advertised in the NIP11 document, if any
+    async fn advertised_limit(&self) -> Option<usize> {
+        #[cfg(feature = "nip11")]
+        {
+            let document = self.document.read().await;
+            if let Some(max_limit) = document.limitation.as_ref().and_then(|l| l.max_limit) {
+                if max_limit > 0 {
+                    return Some(max_limit as usize);
+                }
+            }
+        }
+
+        None
+    }
+
+    pub async fn fetch_events_windowed(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<Events, Error> {
+        let advertised: Option<usize> = self.advertised_limit().await;
+
+        let mut events: Events = Events::new(&[]);
+        for filter in filters.into_iter() {
+            self.fetch_filter_windowed(filter, advertised, timeout, &mut events)
+                .await?;
+        }
+
+        Ok(events)
+    }
+
+    async fn fetch_filter_windowed(
+        &self,
+        filter: Filter,
+        advertised: Option<usize>,
+        timeout: Duration,
+        events: &mut Events,
+    ) -> Result<(), Error> {
+        let mut limit: usize = advertised.unwrap_or(DEFAULT_WINDOW_LIMIT);
+
+        // Without an advertised limit, the relay may silently cap the results below the requested one:
+        // check if the windows are complete, until the actual cap is found.
+        let mut verify: bool = advertised.is_none();
+
+        let mut windows: Vec<(Timestamp, Timestamp)> = vec![(
+            filter.since.unwrap_or_else(|| Timestamp::from_secs(0)),
+            filter
+                .until
+                .unwrap_or_else(|| self.state.time_provider().now()),
+        )];
+
+        while let Some((since, until)) = windows.pop() {
+            let window: Filter = filter.clone().since(since).until(until).limit(limit);
+            let fetched: Events = self
+                .fetch_events(vec![window.clone()], timeout, FilterOptions::ExitOnEOSE)
+                .await?;
+
+            // The relay may have truncated the result: split the window in half
+            if fetched.len() >= limit {
+                if since < until {
+                    let middle: Timestamp = Timestamp::from_secs(
+                        since.as_u64() + (until.as_u64() - since.as_u64()) / 2,
+                    );
+                    windows.push((middle + 1, until));
+                    windows.push((since, middle));
+                    continue;
+                }
+
+                tracing::warn!(url = %self.url, timestamp = %since, "Too many events in a single second: some events may be missing.");
+            }
+
+            let oldest: Option<Timestamp> = fetched.last().map(|e| e.created_at);
+            let count: usize = fetched.len();
+            events.extend(fetched);
+
+            // The truncated events are the oldest ones: check if the relay returns more of them
+            if let (true, Some(oldest)) = (verify, oldest) {
+                let rest: Events = self
+                    .fetch_events(
+                        vec![window.until(oldest)],
+                        timeout,
+                        FilterOptions::ExitOnEOSE,
+                    )
+                    .await?;
+
+                let mut truncated: bool = false;
+                for event in rest.into_iter() {
+                    truncated |= events.insert(event);
+                }
+
+                if truncated {
+                    // Relay capped at `count` events: use it as limit and split the rest of the window
+                    tracing::debug!(url = %self.url, cap = count, "Relay caps the results below the requested limit.");
+                    limit = count;
+                    verify = false;
+                    windows.push((since, oldest));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn count_events(
         &self,
         filters: Vec<Filter>,
@@ -1906,20 +3124,10 @@ impl InnerRelay {
                         while !have_ids.is_empty() && in_flight_up.len() < NEGENTROPY_HIGH_WATER_UP
                         {
                             if let Some(id) = have_ids.pop() {
-                                match self.state.database().event_by_id(&id).await {
-                                    Ok(Some(event)) => {
-                                        in_flight_up.insert(id);
-                                        self.send_msg(ClientMessage::event(event))?;
-                                        num_sent += 1;
-                                    }
-                                    Ok(None) => {
-                                        // Event not found
-                                    }
-                                    Err(e) => tracing::error!(
-                                        url = %self.url,
-                                        error = %e,
-                                        "Couldn't upload event."
-                                    ),
+                                if let Some(event) = self.prepare_upload(&id).await {
+                                    in_flight_up.insert(event.id);
+                                    self.send_msg(ClientMessage::event(event))?;
+                                    num_sent += 1;
                                 }
                             }
                         }
@@ -2208,20 +3416,10 @@ impl InnerRelay {
                         while !have_ids.is_empty() && in_flight_up.len() < NEGENTROPY_HIGH_WATER_UP
                         {
                             if let Some(id) = have_ids.pop() {
-                                match self.state.database().event_by_id(&id).await {
-                                    Ok(Some(event)) => {
-                                        in_flight_up.insert(id);
-                                        self.send_msg(ClientMessage::event(event))?;
-                                        num_sent += 1;
-                                    }
-                                    Ok(None) => {
-                                        // Event not found
-                                    }
-                                    Err(e) => tracing::error!(
-                                        url = %self.url,
-                                        error = %e,
-                                        "Couldn't upload event."
-                                    ),
+                                if let Some(event) = self.prepare_upload(&id).await {
+                                    in_flight_up.insert(event.id);
+                                    self.send_msg(ClientMessage::event(event))?;
+                                    num_sent += 1;
                                 }
                             }
                         }
@@ -2298,19 +3496,78 @@ impl InnerRelay {
     }
 }
 
+/// Compose the error of a rejected event
+///
+/// A protected event (NIP70) rejected because not authenticated, blocked or restricted
+/// is reported as [`Error::ProtectedEventRejected`].
+fn rejection_error(protected: bool, message: String) -> Error {
+    match MachineReadablePrefix::parse(&message) {
+        Some(
+            MachineReadablePrefix::AuthRequired
+            | MachineReadablePrefix::Blocked
+            | MachineReadablePrefix::Restricted,
+        ) if protected => Error::ProtectedEventRejected(message),
+        _ => Error::RelayMessage(message),
+    }
+}
+
+/// Move the `since` of the filters forward, if older than `since`
+fn filters_since(filters: Vec<Filter>, since: Timestamp) -> Vec<Filter> {
+    filters
+        .into_iter()
+        .map(|filter| match filter.since {
+            Some(current) if current >= since => filter,
+            _ => filter.since(since),
+        })
+        .collect()
+}
+
 /// Send WebSocket messages with timeout set to [WEBSOCKET_TX_TIMEOUT].
-async fn send_ws_msgs(tx: &mut Sink, msgs: Vec<WsMessage>) -> Result<(), Error> {
+async fn send_ws_msgs(tx: &mut BoxSink, msgs: Vec<WsMessage>) -> Result<(), Error> {
     let mut stream = futures_util::stream::iter(msgs.into_iter().map(Ok));
     match time::timeout(Some(WEBSOCKET_TX_TIMEOUT), tx.send_all(&mut stream)).await {
-        Some(res) => res.map_err(Error::websocket),
+        Some(res) => res.map_err(Error::WebSocket),
         None => Err(Error::Timeout),
     }
 }
 
 /// Send WebSocket messages with timeout set to [WEBSOCKET_TX_TIMEOUT].
-async fn close_ws(tx: &mut Sink) -> Result<(), Error> {
+async fn close_ws(tx: &mut BoxSink) -> Result<(), Error> {
     match time::timeout(Some(WEBSOCKET_TX_TIMEOUT), tx.close()).await {
-        Some(res) => res.map_err(Error::websocket),
+        Some(res) => res.map_err(Error::WebSocket),
         None => Err(Error::Timeout),
     }
 }
+
+/// Get the subscription ID of the client messages accounted in the subscription bandwidth
+/// and if the message closes the subscription
+fn client_msg_subscription_id(msg: &ClientMessage) -> Option<(&SubscriptionId, bool)> {
+    match msg {
+        ClientMessage::Req {
+            subscription_id, ..
+        }
+        | ClientMessage::Count {
+            subscription_id, ..
+        } => Some((subscription_id, false)),
+        ClientMessage::Close(subscription_id) => Some((subscription_id, true)),
+        _ => None,
+    }
+}
+
+/// Get the subscription ID of the relay messages accounted in the subscription bandwidth
+/// and if the message closes the subscription
+fn raw_relay_msg_subscription_id(msg: &RawRelayMessage) -> Option<(&str, bool)> {
+    match msg {
+        RawRelayMessage::Event {
+            subscription_id, ..
+        } => Some((subscription_id, false)),
+        RawRelayMessage::EndOfStoredEvents(subscription_id) => Some((subscription_id, false)),
+        RawRelayMessage::Closed {
+            subscription_id, ..
+        }
+        | RawRelayMessage::Count {
+            subscription_id, ..
+        } => Some((subscription_id, true)),
+        _ => None,
+    }
+}
@@ -0,0 +1,476 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Client-side enforcement of the NIP11 limitations
+//!
+//! Check the subscriptions and the events against the limitations advertised by the relay
+//! in the NIP11 document, to fail before the relay rejects them.
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/11.md>
+
+use std::fmt;
+
+#[cfg(feature = "nip11")]
+use nostr::nips::nip11::Limitation;
+use nostr::Timestamp;
+#[cfg(feature = "nip11")]
+use nostr::{ClientMessage, Event, Filter, JsonUtil, SubscriptionId};
+
+/// NIP11 limitation error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitationError {
+    /// Message too long
+    MessageTooLong {
+        /// Message length (bytes)
+        len: usize,
+        /// Max length (bytes)
+        max: usize,
+    },
+    /// Too many active subscriptions
+    TooManySubscriptions {
+        /// Max subscriptions
+        max: usize,
+    },
+    /// Too many filters in the subscription
+    TooManyFilters {
+        /// Number of filters
+        filters: usize,
+        /// Max filters
+        max: usize,
+    },
+    /// Filter limit greater than the max limit
+    LimitTooHigh {
+        /// Filter limit
+        limit: usize,
+        /// Max limit
+        max: usize,
+    },
+    /// Subscription ID too long
+    SubscriptionIdTooLong {
+        /// Subscription ID length
+        len: usize,
+        /// Max length
+        max: usize,
+    },
+    /// Too many tags in the event
+    TooManyTags {
+        /// Number of tags
+        tags: usize,
+        /// Max tags
+        max: usize,
+    },
+    /// Event content too long
+    ContentTooLong {
+        /// Content length (chars)
+        len: usize,
+        /// Max length (chars)
+        max: usize,
+    },
+    /// Event POW difficulty too low
+    PowTooLow {
+        /// Min difficulty
+        min: u8,
+    },
+    /// Event `created_at` older than the lower limit
+    CreatedAtTooOld {
+        /// Event `created_at`
+        created_at: Timestamp,
+        /// Oldest accepted `created_at`
+        min: Timestamp,
+    },
+    /// Event `created_at` newer than the upper limit
+    CreatedAtTooNew {
+        /// Event `created_at`
+        created_at: Timestamp,
+        /// Newest accepted `created_at`
+        max: Timestamp,
+    },
+    /// The relay requires the NIP42 authentication, but the auto-authentication isn't available
+    AuthRequired,
+    /// The relay requires the payment, but it isn't marked as paid (check `Relay::set_paid`)
+    PaymentRequired,
+}
+
+impl std::error::Error for LimitationError {}
+
+impl fmt::Display for LimitationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MessageTooLong { len, max } => {
+                write!(f, "message too long: len={len}, max={max}")
+            }
+            Self::TooManySubscriptions { max } => {
+                write!(f, "too many subscriptions: max={max}")
+            }
+            Self::TooManyFilters { filters, max } => {
+                write!(f, "too many filters: filters={filters}, max={max}")
+            }
+            Self::LimitTooHigh { limit, max } => {
+                write!(f, "filter limit too high: limit={limit}, max={max}")
+            }
+            Self::SubscriptionIdTooLong { len, max } => {
+                write!(f, "subscription ID too long: len={len}, max={max}")
+            }
+            Self::TooManyTags { tags, max } => {
+                write!(f, "too many tags: tags={tags}, max={max}")
+            }
+            Self::ContentTooLong { len, max } => {
+                write!(f, "content too long: len={len}, max={max}")
+            }
+            Self::PowTooLow { min } => write!(f, "POW difficulty too low (min. {min})"),
+            Self::CreatedAtTooOld { created_at, min } => {
+                write!(f, "created_at too old: created_at={created_at}, min={min}")
+            }
+            Self::CreatedAtTooNew { created_at, max } => {
+                write!(f, "created_at too new: created_at={created_at}, max={max}")
+            }
+            Self::AuthRequired => write!(f, "authentication required"),
+            Self::PaymentRequired => write!(f, "payment required"),
+        }
+    }
+}
+
+/// Get a positive limit
+#[cfg(feature = "nip11")]
+fn positive(value: Option<i32>) -> Option<usize> {
+    value.filter(|v| *v > 0).map(|v| v as usize)
+}
+
+/// What the client can do to satisfy the access requirements of the relay
+#[cfg(feature = "nip11")]
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Access {
+    /// The client can authenticate (NIP42)
+    pub can_authenticate: bool,
+    /// The relay is marked as paid
+    pub paid: bool,
+}
+
+/// Check the access requirements
+#[cfg(feature = "nip11")]
+fn check_access(limitation: &Limitation, access: Access) -> Result<(), LimitationError> {
+    if limitation.payment_required == Some(true) && !access.paid {
+        return Err(LimitationError::PaymentRequired);
+    }
+
+    if limitation.auth_required == Some(true) && !access.can_authenticate {
+        return Err(LimitationError::AuthRequired);
+    }
+
+    Ok(())
+}
+
+/// Check the length of the message to send
+#[cfg(feature = "nip11")]
+fn check_message_length(
+    limitation: &Limitation,
+    msg: impl FnOnce() -> ClientMessage,
+) -> Result<(), LimitationError> {
+    if let Some(max) = positive(limitation.max_message_length) {
+        let len: usize = msg().as_json().len();
+        if len > max {
+            return Err(LimitationError::MessageTooLong { len, max });
+        }
+    }
+
+    Ok(())
+}
+
+/// Check a new subscription
+///
+/// `active` is the number of active subscriptions, excluding the one to check.
+#[cfg(feature = "nip11")]
+pub(super) fn check_subscription(
+    limitation: &Limitation,
+    id: &SubscriptionId,
+    filters: &[Filter],
+    active: usize,
+    access: Access,
+) -> Result<(), LimitationError> {
+    check_access(limitation, access)?;
+
+    if let Some(max) = positive(limitation.max_subscriptions) {
+        if active >= max {
+            return Err(LimitationError::TooManySubscriptions { max });
+        }
+    }
+
+    if let Some(max) = positive(limitation.max_filters) {
+        if filters.len() > max {
+            return Err(LimitationError::TooManyFilters {
+                filters: filters.len(),
+                max,
+            });
+        }
+    }
+
+    if let Some(max) = positive(limitation.max_limit) {
+        if let Some(limit) = filters.iter().filter_map(|f| f.limit).max() {
+            if limit > max {
+                return Err(LimitationError::LimitTooHigh { limit, max });
+            }
+        }
+    }
+
+    if let Some(max) = positive(limitation.max_subid_length) {
+        let len: usize = id.as_str().len();
+        if len > max {
+            return Err(LimitationError::SubscriptionIdTooLong { len, max });
+        }
+    }
+
+    check_message_length(limitation, || {
+        ClientMessage::req(id.clone(), filters.to_vec())
+    })?;
+
+    Ok(())
+}
+
+/// Check an event to publish
+///
+/// The `created_at` limits are relative to `now`.
+#[cfg(feature = "nip11")]
+pub(super) fn check_event(
+    limitation: &Limitation,
+    event: &Event,
+    access: Access,
+    now: Timestamp,
+) -> Result<(), LimitationError> {
+    check_access(limitation, access)?;
+
+    if let Some(max) = positive(limitation.max_event_tags) {
+        let tags: usize = event.tags.len();
+        if tags > max {
+            return Err(LimitationError::TooManyTags { tags, max });
+        }
+    }
+
+    if let Some(max) = positive(limitation.max_content_length) {
+        let len: usize = event.content.chars().count();
+        if len > max {
+            return Err(LimitationError::ContentTooLong { len, max });
+        }
+    }
+
+    if let Some(min) = positive(limitation.min_pow_difficulty) {
+        let min: u8 = min.min(u8::MAX as usize) as u8;
+        if !event.check_pow(min) {
+            return Err(LimitationError::PowTooLow { min });
+        }
+    }
+
+    if let Some(lower) = limitation.created_at_lower_limit {
+        let min: Timestamp = now - lower;
+        if lower.as_u64() > 0 && event.created_at < min {
+            return Err(LimitationError::CreatedAtTooOld {
+                created_at: event.created_at,
+                min,
+            });
+        }
+    }
+
+    if let Some(upper) = limitation.created_at_upper_limit {
+        let max: Timestamp = now + upper;
+        if event.created_at > max {
+            return Err(LimitationError::CreatedAtTooNew {
+                created_at: event.created_at,
+                max,
+            });
+        }
+    }
+
+    check_message_length(limitation, || ClientMessage::event(event.clone()))?;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "nip11"))]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag};
+
+    use super::*;
+
+    #[test]
+    fn test_check_subscription() {
+        let limitation = Limitation {
+            max_subscriptions: Some(2),
+            max_filters: Some(1),
+            max_limit: Some(100),
+            max_subid_length: Some(8),
+            ..Default::default()
+        };
+        let id = SubscriptionId::new("sub");
+        let filter = Filter::new().limit(10);
+
+        assert!(
+            check_subscription(&limitation, &id, &[filter.clone()], 1, Access::default()).is_ok()
+        );
+        assert_eq!(
+            check_subscription(&limitation, &id, &[filter.clone()], 2, Access::default()),
+            Err(LimitationError::TooManySubscriptions { max: 2 })
+        );
+        assert_eq!(
+            check_subscription(
+                &limitation,
+                &id,
+                &[filter.clone(), filter.clone()],
+                0,
+                Access::default()
+            ),
+            Err(LimitationError::TooManyFilters { filters: 2, max: 1 })
+        );
+        assert_eq!(
+            check_subscription(
+                &limitation,
+                &id,
+                &[Filter::new().limit(500)],
+                0,
+                Access::default()
+            ),
+            Err(LimitationError::LimitTooHigh {
+                limit: 500,
+                max: 100
+            })
+        );
+        assert_eq!(
+            check_subscription(
+                &limitation,
+                &SubscriptionId::new("long-subscription-id"),
+                &[filter],
+                0,
+                Access::default()
+            ),
+            Err(LimitationError::SubscriptionIdTooLong { len: 20, max: 8 })
+        );
+    }
+
+    #[test]
+    fn test_check_event() {
+        let keys = Keys::generate();
+        let now = Timestamp::now();
+        let limitation = Limitation {
+            max_event_tags: Some(1),
+            max_content_length: Some(5),
+            ..Default::default()
+        };
+
+        let event = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(check_event(&limitation, &event, Access::default(), now).is_ok());
+
+        let event = EventBuilder::text_note("Hello world")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            check_event(&limitation, &event, Access::default(), now),
+            Err(LimitationError::ContentTooLong { len: 11, max: 5 })
+        );
+
+        let event = EventBuilder::text_note("Hello")
+            .tags([Tag::hashtag("a"), Tag::hashtag("b")])
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            check_event(&limitation, &event, Access::default(), now),
+            Err(LimitationError::TooManyTags { tags: 2, max: 1 })
+        );
+
+        // Access requirements
+        let limitation = Limitation {
+            auth_required: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            check_event(&limitation, &event, Access::default(), now),
+            Err(LimitationError::AuthRequired)
+        );
+        let access = Access {
+            can_authenticate: true,
+            paid: false,
+        };
+        assert!(check_event(&limitation, &event, access, now).is_ok());
+
+        // The payment isn't satisfied by the authentication
+        let limitation = Limitation {
+            payment_required: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            check_event(&limitation, &event, access, now),
+            Err(LimitationError::PaymentRequired)
+        );
+        let access = Access {
+            can_authenticate: false,
+            paid: true,
+        };
+        assert!(check_event(&limitation, &event, access, now).is_ok());
+    }
+
+    #[test]
+    fn test_check_created_at() {
+        let keys = Keys::generate();
+        let now = Timestamp::from(1_700_000_000);
+        let limitation = Limitation {
+            created_at_lower_limit: Some(Timestamp::from(3600)),
+            created_at_upper_limit: Some(Timestamp::from(60)),
+            ..Default::default()
+        };
+
+        let event = |created_at: u64| {
+            EventBuilder::text_note("Hello")
+                .custom_created_at(Timestamp::from(created_at))
+                .sign_with_keys(&keys)
+                .unwrap()
+        };
+
+        assert!(check_event(&limitation, &event(1_700_000_000), Access::default(), now).is_ok());
+        assert!(check_event(&limitation, &event(1_699_996_400), Access::default(), now).is_ok());
+        assert!(check_event(&limitation, &event(1_700_000_060), Access::default(), now).is_ok());
+        assert_eq!(
+            check_event(&limitation, &event(1_699_996_399), Access::default(), now),
+            Err(LimitationError::CreatedAtTooOld {
+                created_at: Timestamp::from(1_699_996_399),
+                min: Timestamp::from(1_699_996_400)
+            })
+        );
+        assert_eq!(
+            check_event(&limitation, &event(1_700_000_061), Access::default(), now),
+            Err(LimitationError::CreatedAtTooNew {
+                created_at: Timestamp::from(1_700_000_061),
+                max: Timestamp::from(1_700_000_060)
+            })
+        );
+    }
+
+    #[test]
+    fn test_check_message_length() {
+        let keys = Keys::generate();
+        let limitation = Limitation {
+            max_message_length: Some(300),
+            ..Default::default()
+        };
+
+        let event = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(check_event(&limitation, &event, Access::default(), Timestamp::now()).is_ok());
+
+        let event = EventBuilder::text_note("a".repeat(300))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let len: usize = ClientMessage::event(event.clone()).as_json().len();
+        assert_eq!(
+            check_event(&limitation, &event, Access::default(), Timestamp::now()),
+            Err(LimitationError::MessageTooLong { len, max: 300 })
+        );
+
+        let id = SubscriptionId::new("sub");
+        let filters = vec![Filter::new().hashtags((0..50).map(|i| format!("hashtag{i}")))];
+        assert!(matches!(
+            check_subscription(&limitation, &id, &filters, 0, Access::default()),
+            Err(LimitationError::MessageTooLong { max: 300, .. })
+        ));
+    }
+}
@@ -17,27 +17,35 @@ use tokio::sync::broadcast;
 
 use crate::shared::SharedState;
 
+pub mod capture;
 pub mod constants;
 mod error;
 mod filtering;
 pub mod flags;
 mod inner;
+pub mod limitation;
 pub mod limits;
 pub mod options;
+#[cfg(feature = "nip11")]
+pub mod payment;
 mod ping;
 pub mod stats;
 mod status;
 
+use self::capture::CapturedFrame;
 pub use self::error::Error;
 pub use self::filtering::{RelayFiltering, RelayFilteringMode};
 pub use self::flags::{AtomicRelayServiceFlags, FlagCheck, RelayServiceFlags};
 use self::inner::InnerRelay;
+pub use self::limitation::LimitationError;
 pub use self::limits::RelayLimits;
 pub use self::options::{
     FilterOptions, RelayOptions, SubscribeAutoCloseOptions, SubscribeOptions, SyncDirection,
     SyncOptions, SyncProgress,
 };
-pub use self::stats::RelayConnectionStats;
+#[cfg(feature = "nip11")]
+pub use self::payment::RelayPayment;
+pub use self::stats::{Bandwidth, RelayConnectionStats};
 pub use self::status::RelayStatus;
 
 /// Subscription auto-closed reason
@@ -51,6 +59,54 @@ pub enum SubscriptionAutoClosedReason {
     Completed,
 }
 
+/// WebSocket close frame received from the relay
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelayCloseFrame {
+    /// Close code
+    pub code: u16,
+    /// Close reason
+    pub reason: String,
+}
+
+/// Relay disconnection reason
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Impossible to connect to the relay
+    ///
+    /// Sent only at the first failed attempt: the following retries of the same outage aren't notified.
+    ConnectionFailed(String),
+    /// The authentication (NIP42) failed and then the relay closed the connection
+    AuthFailed(String),
+    /// I/O, WebSocket or protocol error
+    Io(String),
+    /// Connection closed by the relay
+    Closed {
+        /// Close frame (`None` if the relay sent an empty close frame or closed the stream)
+        frame: Option<RelayCloseFrame>,
+    },
+    /// The relay didn't reply to the ping
+    PingTimeout,
+    /// Disconnected by the user (i.e. relay removed, paused or pool shut down)
+    Terminated,
+    /// The connection task panicked
+    Panic(String),
+}
+
+impl DisconnectReason {
+    fn from_error(error: &Error) -> Self {
+        match error {
+            Error::NotRepliedToPing => Self::PingTimeout,
+            e => Self::Io(e.to_string()),
+        }
+    }
+
+    /// Check if the disconnection was requested by the user
+    #[inline]
+    pub fn is_terminated(&self) -> bool {
+        matches!(self, Self::Terminated)
+    }
+}
+
 /// Relay Notification
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RelayNotification {
@@ -59,7 +115,9 @@ pub enum RelayNotification {
         /// Subscription ID
         subscription_id: SubscriptionId,
         /// Event
-        event: Box<Event>,
+        ///
+        /// Shared across the listeners, to not clone the event for each of them.
+        event: Arc<Event>,
     },
     /// Received a [`RelayMessage`]. Includes messages wrapping events that were sent by this client.
     Message {
@@ -82,6 +140,40 @@ pub enum RelayNotification {
         /// Reason
         reason: SubscriptionAutoClosedReason,
     },
+    /// Connection closed by the relay
+    Closed {
+        /// Close frame (`None` if the relay sent an empty close frame)
+        frame: Option<RelayCloseFrame>,
+    },
+    /// The relay requires a payment (advertised in the NIP11 document)
+    #[cfg(feature = "nip11")]
+    PaymentRequired {
+        /// Payment requirements
+        payment: RelayPayment,
+    },
+    /// Disconnected from the relay
+    Disconnected {
+        /// Reason
+        reason: DisconnectReason,
+    },
+    /// An event queued in the outbox has been sent
+    OutboxEventSent {
+        /// Event ID
+        event_id: EventId,
+        /// Accepted by the relay
+        status: bool,
+        /// Message
+        message: String,
+    },
+    /// A task panicked and has been restarted
+    ///
+    /// Never sent with `panic = "abort"`.
+    TaskPanicked {
+        /// Task name
+        task: String,
+        /// Panic message
+        reason: String,
+    },
     /// Shutdown
     Shutdown,
 }
@@ -120,6 +212,8 @@ impl Reconciliation {
 }
 
 /// Relay
+///
+/// When the last clone is dropped, the relay is disconnected and its background tasks are stopped.
 #[derive(Debug, Clone)]
 pub struct Relay {
     pub(crate) inner: AtomicDestructor<InnerRelay>,
@@ -192,6 +286,14 @@ impl Relay {
         self.inner.status()
     }
 
+    /// Check if authenticated to the relay (NIP42) in the current connection
+    ///
+    /// The protected events (NIP70) are sent only to the authenticated relays.
+    #[inline]
+    pub fn is_authenticated(&self) -> bool {
+        self.inner.is_authenticated()
+    }
+
     /// Get Relay Service Flags
     #[inline]
     pub fn flags(&self) -> &AtomicRelayServiceFlags {
@@ -217,6 +319,52 @@ impl Relay {
         self.inner.document().await
     }
 
+    /// Get the payment requirements of the relay
+    ///
+    /// Return `None` if the relay doesn't require a payment or if the NIP11 document isn't fetched yet.
+    #[inline]
+    #[cfg(feature = "nip11")]
+    pub async fn payment(&self) -> Option<RelayPayment> {
+        let document = self.inner.document().await;
+        RelayPayment::from_document(&document)
+    }
+
+    /// Check if the relay is marked as paid
+    #[inline]
+    #[cfg(feature = "nip11")]
+    pub fn is_paid(&self) -> bool {
+        self.inner.is_paid()
+    }
+
+    /// Mark the relay as paid
+    ///
+    /// The [`RelayNotification::PaymentRequired`] notification is no longer sent for a paid relay.
+    /// The flag is kept only in memory: persist it with the pool snapshot (check [`RelayPool::snapshot`](crate::RelayPool::snapshot)).
+    #[inline]
+    #[cfg(feature = "nip11")]
+    pub fn set_paid(&self, paid: bool) {
+        self.inner.set_paid(paid)
+    }
+
+    /// Get the close frame received from the relay in the last connection
+    ///
+    /// Cleared when the connection is re-established.
+    /// The close frame is also reported by [`RelayPoolNotification::RelayDisconnected`](crate::RelayPoolNotification::RelayDisconnected).
+    #[inline]
+    pub async fn close_frame(&self) -> Option<RelayCloseFrame> {
+        self.inner.close_frame().await
+    }
+
+    /// Get the captured frames (oldest first)
+    ///
+    /// Return an empty list if the capture is not enabled (see [`RelayOptions::capture`]).
+    pub async fn capture(&self) -> Vec<CapturedFrame> {
+        match &self.inner.capture {
+            Some(capture) => capture.frames().await,
+            None => Vec::new(),
+        }
+    }
+
     /// Get subscriptions
     #[inline]
     pub async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<Filter>> {
@@ -267,6 +415,14 @@ impl Relay {
         self.inner.disconnect()
     }
 
+    /// Disconnect from relay and wait for the connection task to exit, at most for the specified `timeout`
+    ///
+    /// The status is set to [`RelayStatus::Terminated`].
+    #[inline]
+    pub async fn terminate(&self, timeout: Duration) -> Result<(), Error> {
+        self.inner.terminate(timeout).await
+    }
+
     /// Send msg to relay
     #[inline]
     pub fn send_msg(&self, msg: ClientMessage) -> Result<(), Error> {
@@ -372,6 +528,26 @@ impl Relay {
         self.inner.fetch_events(filters, timeout, opts).await
     }
 
+    /// Fetch events splitting the `since`/`until` window
+    ///
+    /// Relays cap the number of returned events, silently truncating large queries.
+    /// This method recursively splits the time window of each filter until each window returns
+    /// fewer events than the `max_limit` advertised by the relay in its NIP11 document.
+    ///
+    /// If the relay doesn't advertise it, the windows are requested with a limit of 500
+    /// and each one is checked for more events older than the returned ones:
+    /// if the relay returns them, its actual cap is used to split the rest of the windows.
+    ///
+    /// The `limit` of the filters is ignored.
+    #[inline]
+    pub async fn fetch_events_windowed(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<Events, Error> {
+        self.inner.fetch_events_windowed(filters, timeout).await
+    }
+
     /// Count events
     #[inline]
     pub async fn count_events(
@@ -435,6 +611,9 @@ mod tests {
     use nostr_relay_builder::prelude::*;
 
     use super::{Error, *};
+    use crate::policy::{Admission, AdmitPolicy, DedupPolicy, Deduplication};
+    use crate::relay::capture::CaptureDirection;
+    use crate::transport::DefaultWebsocketTransport;
 
     #[tokio::test]
     async fn test_ok_msg() {
@@ -522,6 +701,157 @@ mod tests {
         assert!(!relay.inner.is_running());
     }
 
+    #[tokio::test]
+    async fn test_terminate() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::new(url);
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        assert_eq!(relay.status(), RelayStatus::Connected);
+
+        relay.terminate(Duration::from_secs(1)).await.unwrap();
+
+        assert_eq!(relay.status(), RelayStatus::Terminated);
+
+        assert!(!relay.inner.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_drop_relay() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::new(url);
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        assert_eq!(relay.status(), RelayStatus::Connected);
+
+        // Keep an handle to the inner relay, without increasing the destructor counter
+        let inner: InnerRelay = InnerRelay::clone(&relay.inner);
+
+        drop(relay);
+
+        time::sleep(Duration::from_millis(500)).await;
+
+        assert_eq!(inner.status(), RelayStatus::Terminated);
+        assert!(!inner.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_ban_after_consecutive_failures() {
+        let url = RelayUrl::parse("wss://127.0.0.1:666").unwrap();
+
+        let opts = RelayOptions::default()
+            .adjust_retry_interval(false)
+            .retry_interval(Duration::from_secs(1))
+            .ban_threshold(Some(2))
+            .ban_duration(Duration::from_secs(60));
+        let relay = Relay::with_opts(url, opts);
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        assert_eq!(relay.status(), RelayStatus::Disconnected);
+
+        // Wait for the second failure
+        time::sleep(Duration::from_secs(2)).await;
+
+        assert_eq!(relay.status(), RelayStatus::Banned);
+
+        relay.disconnect().unwrap();
+
+        time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(relay.status(), RelayStatus::Terminated);
+    }
+
+    #[tokio::test]
+    async fn test_connection_failed_notified_once() {
+        let url = RelayUrl::parse("wss://127.0.0.1:666").unwrap();
+
+        let opts = RelayOptions::default()
+            .adjust_retry_interval(false)
+            .retry_interval(Duration::from_secs(1));
+        let relay = Relay::with_opts(url, opts);
+
+        let mut notifications = relay.notifications();
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        // Wait for some retries
+        time::sleep(Duration::from_secs(3)).await;
+        relay.disconnect().unwrap();
+
+        let mut failures: usize = 0;
+        while let Ok(notification) = notifications.try_recv() {
+            if let RelayNotification::Disconnected {
+                reason: DisconnectReason::ConnectionFailed(..),
+            } = notification
+            {
+                failures += 1;
+            }
+        }
+        assert_eq!(failures, 1);
+        assert!(relay.stats().attempts() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_auth_failed_disconnect_reason() {
+        use async_utility::futures_util::{SinkExt, StreamExt};
+        use tokio::net::TcpListener;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Relay that rejects the authentication and then closes the connection
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = RelayUrl::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, ..)) = listener.accept().await {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let challenge = RelayMessage::auth("challenge").as_json();
+                ws.send(Message::Text(challenge)).await.unwrap();
+
+                while let Some(Ok(Message::Text(msg))) = ws.next().await {
+                    if let Ok(ClientMessage::Auth(event)) = ClientMessage::from_json(msg) {
+                        let ok = RelayMessage::ok(event.id, false, "auth-required: not allowed");
+                        ws.send(Message::Text(ok.as_json())).await.unwrap();
+                        time::sleep(Duration::from_millis(100)).await;
+                        ws.close(None).await.unwrap();
+                        break;
+                    }
+                }
+            }
+        });
+
+        let relay = Relay::new(url);
+        relay.inner.state.automatic_authentication(true);
+        relay.inner.state.set_signer(Keys::generate()).await;
+
+        let mut notifications = relay.notifications();
+
+        relay.connect(Some(Duration::from_secs(1))).await;
+
+        let reason = time::timeout(Some(Duration::from_secs(5)), async {
+            loop {
+                if let Ok(RelayNotification::Disconnected { reason }) = notifications.recv().await {
+                    return reason;
+                }
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            reason,
+            DisconnectReason::AuthFailed(String::from("auth-required: not allowed"))
+        );
+
+        relay.disconnect().unwrap();
+    }
+
     #[tokio::test]
     async fn test_disconnect_non_connected_relay() {
         let url = RelayUrl::parse("wss://127.0.0.1:666").unwrap();
@@ -653,6 +983,43 @@ mod tests {
         assert!(relay.send_event(event).await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_nip42_send_protected_event() {
+        // Mock relay
+        let opts = RelayBuilderNip42 {
+            mode: RelayBuilderNip42Mode::Write,
+        };
+        let builder = RelayBuilder::default().nip42(opts);
+        let mock = LocalRelay::run(builder).await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::new(url);
+
+        let keys = Keys::generate();
+        relay.inner.state.automatic_authentication(true);
+        relay.inner.state.set_signer(keys.clone()).await;
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+        assert!(!relay.is_authenticated());
+
+        // The relay requests the AUTH only when the event is received:
+        // authenticate and resend it
+        let protected = EventBuilder::text_note("Protected")
+            .protected()
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            relay.send_event(protected.clone()).await.unwrap(),
+            protected.id
+        );
+        assert!(relay.is_authenticated());
+
+        // The authentication is bound to the connection
+        relay.disconnect().unwrap();
+        time::sleep(Duration::from_millis(100)).await;
+        assert!(!relay.is_authenticated());
+    }
+
     #[tokio::test]
     async fn test_nip42_fetch_events() {
         // Mock relay
@@ -727,4 +1094,853 @@ mod tests {
             .await;
         assert!(res.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_resubscribe_since() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let opts = RelayOptions::default().resubscribe_since(true);
+        let relay = Relay::with_opts(url, opts);
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        // Send an event
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(event.clone()).await.unwrap();
+
+        // Subscribe
+        let id = SubscriptionId::new("test");
+        let filters = vec![Filter::new().kind(Kind::TextNote)];
+
+        relay
+            .subscribe_with_id(id.clone(), filters.clone(), SubscribeOptions::default())
+            .await
+            .unwrap();
+
+        time::sleep(Duration::from_millis(200)).await;
+
+        // Check re-subscription filters
+        let resubscription_filters = relay.inner.resubscription_filters(&id, filters).await;
+        assert_eq!(
+            resubscription_filters,
+            vec![Filter::new()
+                .kind(Kind::TextNote)
+                .since(event.created_at - Duration::from_secs(60))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_blacklisted_relay() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::new(url.clone());
+
+        relay.filtering().add_relay_urls([url]).await;
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        assert_eq!(relay.status(), RelayStatus::Initialized);
+        assert!(!relay.inner.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_blacklist_connected_relay() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::new(url.clone());
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        assert_eq!(relay.status(), RelayStatus::Connected);
+
+        relay.filtering().add_relay_urls([url]).await;
+
+        time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(relay.status(), RelayStatus::Terminated);
+        assert!(!relay.inner.is_running());
+    }
+
+    #[derive(Debug)]
+    struct RejectTextNotes;
+
+    impl AdmitPolicy for RejectTextNotes {
+        fn admit(&self, event: &Event, _relay_url: &RelayUrl) -> Admission {
+            if event.kind == Kind::TextNote {
+                return Admission::reject("text notes not allowed");
+            }
+            Admission::Accept
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admit_policy() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let state = SharedState::new(
+            MemoryDatabase::new().into_nostr_database(),
+            None,
+            RelayFilteringMode::default(),
+            true,
+            0,
+            Some(Arc::new(RejectTextNotes)),
+            None,
+            Arc::new(DefaultWebsocketTransport),
+        );
+        let relay = Relay::internal_custom(url, state, RelayOptions::default());
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        // Send events
+        let keys = Keys::generate();
+        let text_note = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(text_note).await.unwrap();
+        let reaction = EventBuilder::new(Kind::Reaction, "+")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(reaction.clone()).await.unwrap();
+
+        // Fetch events
+        let events = relay
+            .fetch_events(
+                vec![Filter::new().author(keys.public_key())],
+                Duration::from_secs(2),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.first().unwrap().id, reaction.id);
+    }
+
+    #[tokio::test]
+    async fn test_content_filtering() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::new(url);
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        // Send events
+        let keys = Keys::generate();
+        let root = EventBuilder::text_note("Root")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(root.clone()).await.unwrap();
+        let reply = EventBuilder::text_note_reply("Reply", &root, None, None)
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(reply).await.unwrap();
+        let hashtag = EventBuilder::text_note("Hashtag")
+            .tag(Tag::hashtag("NSFW"))
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(hashtag).await.unwrap();
+        let word = EventBuilder::text_note("Buy SPAM now")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(word).await.unwrap();
+        let not_word = EventBuilder::text_note("Block the spammers")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(not_word.clone()).await.unwrap();
+        let blacklisted = EventBuilder::text_note("Blacklisted")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(blacklisted.clone()).await.unwrap();
+        let not_muted_reply =
+            EventBuilder::text_note_reply("Reply to blacklisted", &blacklisted, None, None)
+                .sign_with_keys(&keys)
+                .unwrap();
+        relay.send_event(not_muted_reply.clone()).await.unwrap();
+
+        // Mute thread, hashtag and word
+        let filtering = relay.filtering();
+        filtering.add_threads([root.id]).await;
+        filtering.add_ids([blacklisted.id]).await;
+        filtering.add_hashtags(["nsfw"]).await;
+        filtering.add_words(["spam"]).await;
+
+        // Fetch events
+        let events = relay
+            .fetch_events(
+                vec![Filter::new().author(keys.public_key())],
+                Duration::from_secs(2),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        // The words match only as whole words and the blacklisted IDs don't mute the threads
+        let ids: HashSet<EventId> = events.iter().map(|e| e.id).collect();
+        assert_eq!(ids, HashSet::from([not_word.id, not_muted_reply.id]));
+    }
+
+    #[derive(Debug)]
+    struct SkipTextNotes;
+
+    impl DedupPolicy for SkipTextNotes {
+        fn deduplicate(&self, event: &Event, _status: DatabaseEventStatus) -> Deduplication {
+            if event.kind == Kind::TextNote {
+                return Deduplication::Duplicate;
+            }
+            Deduplication::Default
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dedup_policy() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let state = SharedState::new(
+            MemoryDatabase::new().into_nostr_database(),
+            None,
+            RelayFilteringMode::default(),
+            true,
+            0,
+            None,
+            Some(Arc::new(SkipTextNotes)),
+            Arc::new(DefaultWebsocketTransport),
+        );
+        let relay = Relay::internal_custom(url, state.clone(), RelayOptions::default());
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        // Send events
+        let keys = Keys::generate();
+        let text_note = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(text_note).await.unwrap();
+        let reaction = EventBuilder::new(Kind::Reaction, "+")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(reaction.clone()).await.unwrap();
+
+        // Fetch events
+        relay
+            .fetch_events(
+                vec![Filter::new().author(keys.public_key())],
+                Duration::from_secs(2),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+
+        // Check that the text note has not been saved
+        let events = state
+            .database()
+            .query(vec![Filter::new().author(keys.public_key())])
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events.first().unwrap().id, reaction.id);
+    }
+
+    /// Merge the text notes, so every received copy is processed
+    #[derive(Debug)]
+    struct MergeTextNotes;
+
+    impl DedupPolicy for MergeTextNotes {
+        fn deduplicate(&self, event: &Event, _status: DatabaseEventStatus) -> Deduplication {
+            if event.kind == Kind::TextNote {
+                return Deduplication::New;
+            }
+            Deduplication::Default
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_skips_verification() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let url1 = RelayUrl::parse(&mock1.url()).unwrap();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let state = SharedState::new(
+            MemoryDatabase::new().into_nostr_database(),
+            None,
+            RelayFilteringMode::default(),
+            true,
+            0,
+            None,
+            Some(Arc::new(MergeTextNotes)),
+            Arc::new(DefaultWebsocketTransport),
+        );
+        let relay1 = Relay::internal_custom(url1, state.clone(), RelayOptions::default());
+        let relay2 = Relay::internal_custom(url2, state.clone(), RelayOptions::default());
+
+        relay1.connect(Some(Duration::from_millis(100))).await;
+        relay2.connect(Some(Duration::from_millis(100))).await;
+
+        // Same event on both relays
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay1.send_event(event.clone()).await.unwrap();
+        relay2.send_event(event.clone()).await.unwrap();
+
+        let filter = Filter::new().id(event.id);
+        let metrics = state.ingestion_metrics();
+
+        // First relay: verified
+        let events = relay1
+            .fetch_events(
+                vec![filter.clone()],
+                Duration::from_secs(2),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(metrics.saved_verifications(), 0);
+
+        // Second relay: already verified
+        let events = relay2
+            .fetch_events(
+                vec![filter],
+                Duration::from_secs(2),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(metrics.saved_verifications(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_outbox() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let opts = RelayOptions::default().outbox(true).outbox_limit(1);
+        let relay = Relay::with_opts(url.clone(), opts);
+
+        // Send event while not connected
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(matches!(
+            relay.send_event(event.clone()).await.unwrap_err(),
+            Error::EventQueued
+        ));
+
+        // Queued in the database
+        let database = relay.inner.state.database();
+        assert_eq!(database.outbox(&url).await.unwrap(), vec![event.clone()]);
+
+        // Outbox full
+        let other = EventBuilder::text_note("Other")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(matches!(
+            relay.send_event(other).await.unwrap_err(),
+            Error::OutboxFull { limit: 1 }
+        ));
+
+        // Connect, the queued event should be sent
+        let mut notifications = relay.notifications();
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let sent = time::timeout(Some(Duration::from_secs(2)), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayNotification::OutboxEventSent {
+                    event_id, status, ..
+                } = notification
+                {
+                    return Some((event_id, status));
+                }
+            }
+            None
+        })
+        .await
+        .flatten();
+        assert_eq!(sent, Some((event.id, true)));
+        assert!(database.outbox(&url).await.unwrap().is_empty());
+
+        let events = relay
+            .fetch_events(
+                vec![Filter::new().id(event.id)],
+                Duration::from_secs(2),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_events_windowed() {
+        use super::constants::DEFAULT_WINDOW_LIMIT;
+
+        let keys = Keys::generate();
+        let time = Arc::new(FixedTimeProvider::new(Timestamp::from(1_700_000_000)));
+
+        // Populate the mock relay database
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        });
+        let now = time.now();
+        let total: usize = DEFAULT_WINDOW_LIMIT + 100;
+        for i in 0..total {
+            let event = EventBuilder::text_note(format!("Test {i}"))
+                .custom_created_at(now - Duration::from_secs(i as u64))
+                .sign_with_keys(&keys)
+                .unwrap();
+            database.save_event(&event).await.unwrap();
+        }
+
+        // Mock relay
+        let builder = RelayBuilder::default().database(database);
+        let mock = LocalRelay::run(builder).await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        // The windows start from the time of the relay
+        let state = SharedState::default().with_time_provider(time);
+        let relay = Relay::internal_custom(url, state, RelayOptions::default());
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+
+        // Single request is truncated by the limit
+        let events = relay
+            .fetch_events(
+                vec![filter.clone().limit(DEFAULT_WINDOW_LIMIT)],
+                Duration::from_secs(5),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+        assert_eq!(events.len(), DEFAULT_WINDOW_LIMIT);
+
+        // Windowed request
+        let events = relay
+            .fetch_events_windowed(vec![filter], Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), total);
+
+        // Each filter is windowed on its own
+        let filters = vec![
+            Filter::new()
+                .kind(Kind::TextNote)
+                .until(now - Duration::from_secs(300)),
+            Filter::new()
+                .kind(Kind::TextNote)
+                .since(now - Duration::from_secs(299)),
+        ];
+        let events = relay
+            .fetch_events_windowed(filters, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), total);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_max_events() {
+        let keys = Keys::generate();
+
+        // Populate the mock relay database
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        });
+        for i in 0..5 {
+            let event = EventBuilder::text_note(format!("Test {i}"))
+                .sign_with_keys(&keys)
+                .unwrap();
+            database.save_event(&event).await.unwrap();
+        }
+
+        // Mock relay
+        let builder = RelayBuilder::default().database(database);
+        let mock = LocalRelay::run(builder).await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::new(url);
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let filters = vec![Filter::new().kind(Kind::TextNote)];
+
+        // Not closed on EOSE: the max number of events isn't reached
+        let mut notifications = relay.notifications();
+        relay
+            .subscribe(filters.clone(), SubscribeOptions::default().max_events(10))
+            .await
+            .unwrap();
+        let closed = time::timeout(Some(Duration::from_millis(500)), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayNotification::SubscriptionAutoClosed { .. } = notification {
+                    return;
+                }
+            }
+        })
+        .await;
+        assert!(closed.is_none());
+
+        // Closed after receiving 2 events
+        let mut notifications = relay.notifications();
+        relay
+            .subscribe(filters, SubscribeOptions::default().max_events(2))
+            .await
+            .unwrap();
+        let reason = time::timeout(Some(Duration::from_secs(2)), async {
+            while let Ok(notification) = notifications.recv().await {
+                if let RelayNotification::SubscriptionAutoClosed { reason } = notification {
+                    return Some(reason);
+                }
+            }
+            None
+        })
+        .await
+        .flatten()
+        .unwrap();
+        assert!(matches!(reason, SubscriptionAutoClosedReason::Completed));
+    }
+
+    #[tokio::test]
+    async fn test_subscription_bandwidth() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::new(url);
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let filter = Filter::new().kind(Kind::TextNote);
+        let id = SubscriptionId::new("bandwidth");
+        let req = ClientMessage::req(id.clone(), vec![filter.clone()]);
+
+        relay
+            .subscribe_with_id(id.clone(), vec![filter], SubscribeOptions::default())
+            .await
+            .unwrap();
+
+        // Wait for EOSE
+        time::sleep(Duration::from_millis(300)).await;
+
+        let bandwidth = relay.stats().subscription_bandwidth(&id).unwrap();
+        assert!(bandwidth.sent >= req.as_json().len());
+        assert!(bandwidth.received > 0);
+        assert!(relay.stats().bandwidth().total() >= bandwidth.total());
+
+        relay.stats().reset_subscriptions_bandwidth();
+        assert!(relay.stats().subscriptions_bandwidth().is_empty());
+
+        // The usage is removed when the subscription is closed
+        let id = SubscriptionId::new("bandwidth-closed");
+        relay
+            .subscribe_with_id(
+                id.clone(),
+                vec![Filter::new().kind(Kind::TextNote)],
+                SubscribeOptions::default(),
+            )
+            .await
+            .unwrap();
+        time::sleep(Duration::from_millis(300)).await;
+        assert!(relay.stats().subscription_bandwidth(&id).is_some());
+
+        relay.unsubscribe(id.clone()).await.unwrap();
+        time::sleep(Duration::from_millis(100)).await;
+        assert!(relay.stats().subscription_bandwidth(&id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_socket_options() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let opts = RelayOptions::default()
+            .tcp_keepalive(Some(Duration::from_secs(15)))
+            .tcp_nodelay(true);
+        let relay = Relay::with_opts(url, opts);
+
+        relay.connect(Some(Duration::from_millis(500))).await;
+
+        assert_eq!(relay.status(), RelayStatus::Connected);
+
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        relay.send_event(event).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subscription_cursor() {
+        let keys = Keys::generate();
+        let time = Arc::new(FixedTimeProvider::new(Timestamp::from(1_700_000_000)));
+        let now = time.now();
+
+        // Populate the mock relay database: two events at the boundary and an older one
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        });
+        for (content, created_at) in [("A", now), ("B", now), ("C", now - Duration::from_secs(10))]
+        {
+            let event = EventBuilder::text_note(content)
+                .custom_created_at(created_at)
+                .sign_with_keys(&keys)
+                .unwrap();
+            database.save_event(&event).await.unwrap();
+        }
+
+        // Mock relay
+        let builder = RelayBuilder::default().database(database);
+        let mock = LocalRelay::run(builder).await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let id = SubscriptionId::new("notes");
+        let filters = vec![Filter::new().kind(Kind::TextNote)];
+        let opts = SubscribeOptions::default().cursor(true);
+
+        let new_relay = |database: Arc<dyn NostrDatabase>| {
+            let state = SharedState::new(
+                database,
+                None,
+                RelayFilteringMode::default(),
+                true,
+                0,
+                None,
+                None,
+                Arc::new(DefaultWebsocketTransport),
+            )
+            .with_time_provider(time.clone());
+            Relay::internal_custom(url.clone(), state, RelayOptions::default())
+        };
+
+        let count_events = |mut notifications: broadcast::Receiver<RelayNotification>| async move {
+            let mut count: usize = 0;
+            time::timeout(Some(Duration::from_millis(500)), async {
+                while let Ok(notification) = notifications.recv().await {
+                    if let RelayNotification::Event { .. } = notification {
+                        count += 1;
+                    }
+                }
+            })
+            .await;
+            count
+        };
+
+        // First run
+        let first_db = MemoryDatabase::new().into_nostr_database();
+        let relay = new_relay(first_db.clone());
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let notifications = relay.notifications();
+        relay
+            .subscribe_with_id(id.clone(), filters.clone(), opts)
+            .await
+            .unwrap();
+        assert_eq!(count_events(notifications).await, 3);
+
+        let cursor = first_db.cursor(&url, &id).await.unwrap().unwrap();
+        assert_eq!(cursor.created_at(), now);
+        assert_eq!(cursor.ids().len(), 2);
+
+        relay.disconnect().unwrap();
+
+        // Restart: only the cursor is persisted
+        let second_db = MemoryDatabase::new().into_nostr_database();
+        second_db.save_cursor(&url, &id, &cursor).await.unwrap();
+
+        let relay = new_relay(second_db);
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let notifications = relay.notifications();
+        relay.subscribe_with_id(id, filters, opts).await.unwrap();
+        assert_eq!(count_events(notifications).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_subscription_cursor_forged_events() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let database = MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            max_events: None,
+        })
+        .into_nostr_database();
+        let state = SharedState::new(
+            database.clone(),
+            None,
+            RelayFilteringMode::default(),
+            true,
+            0,
+            None,
+            None,
+            Arc::new(DefaultWebsocketTransport),
+        );
+        let opts = RelayOptions::default().resubscribe_since(true);
+        let relay = Relay::internal_custom(url.clone(), state, opts);
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let id = SubscriptionId::new("notes");
+        let filters = vec![Filter::new().kind(Kind::TextNote)];
+        relay
+            .subscribe_with_id(
+                id.clone(),
+                filters.clone(),
+                SubscribeOptions::default().cursor(true),
+            )
+            .await
+            .unwrap();
+
+        let keys = Keys::generate();
+        let now = Timestamp::now();
+        let (db, u, i) = (&database, &url, &id);
+        let cursor_created_at = move || async move {
+            db.cursor(u, i)
+                .await
+                .unwrap()
+                .map(|cursor| cursor.created_at())
+        };
+
+        // Genuine event
+        let event = EventBuilder::text_note("Genuine")
+            .custom_created_at(now - Duration::from_secs(10))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let msg = RelayMessage::event(id.clone(), event.clone()).as_json();
+        relay.inner.handle_relay_message(&msg).await;
+        assert_eq!(cursor_created_at().await, Some(event.created_at));
+
+        // Duplicate of the stored event, with a forged `created_at`
+        let forged: String = msg.replace(
+            &format!("\"created_at\":{}", event.created_at),
+            &format!("\"created_at\":{}", now + Duration::from_secs(86_400)),
+        );
+        assert_ne!(forged, msg);
+        relay.inner.handle_relay_message(&forged).await;
+        assert_eq!(cursor_created_at().await, Some(event.created_at));
+
+        // Future-dated event: clamped to now
+        let future = EventBuilder::text_note("Future")
+            .custom_created_at(now + Duration::from_secs(86_400))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let msg = RelayMessage::event(id.clone(), future).as_json();
+        relay.inner.handle_relay_message(&msg).await;
+        let created_at = cursor_created_at().await.unwrap();
+        assert!(created_at >= now && created_at <= Timestamp::now());
+
+        // The re-subscription `since` is clamped too
+        let resubscription_filters = relay.inner.resubscription_filters(&id, filters).await;
+        assert!(resubscription_filters[0].since.unwrap() <= Timestamp::now());
+    }
+
+    #[tokio::test]
+    async fn test_event_shared_with_notifications() {
+        let url = RelayUrl::parse("wss://relay.example.com").unwrap();
+        let relay = Relay::new(url);
+
+        let mut notifications = relay.notifications();
+
+        let event = EventBuilder::text_note("Shared")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let msg = RelayMessage::event(SubscriptionId::new("test"), event.clone()).as_json();
+        relay.inner.handle_relay_message(&msg).await;
+
+        let mut received: Vec<Arc<Event>> = Vec::new();
+        while let Ok(notification) = notifications.try_recv() {
+            match notification {
+                RelayNotification::Event { event, .. } => received.push(event),
+                RelayNotification::Message {
+                    message: RelayMessage::Event { event, .. },
+                } => received.push(event),
+                _ => {}
+            }
+        }
+
+        // Notified as event and as message, sharing the same allocation
+        assert_eq!(received.len(), 2);
+        assert!(Arc::ptr_eq(&received[0], &received[1]));
+        assert_eq!(*received[0], event);
+    }
+
+    #[tokio::test]
+    async fn test_capture() {
+        // Mock relay
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        let relay = Relay::with_opts(url.clone(), RelayOptions::default().capture(Some(10)));
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        relay.send_event(event).await.unwrap();
+
+        let frames = relay.capture().await;
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].relay_url, url);
+        assert_eq!(frames[0].direction, CaptureDirection::Outbound);
+        assert!(frames[0].message.starts_with(r#"["EVENT","#));
+        assert_eq!(frames[1].direction, CaptureDirection::Inbound);
+        assert!(frames[1].message.starts_with(r#"["OK","#));
+        assert!(frames[0].timestamp_ms <= frames[1].timestamp_ms);
+
+        // Capture disabled
+        let relay = Relay::new(url);
+        assert!(relay.capture().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_nip42_ephemeral_auth() {
+        // Mock relay
+        let opts = RelayBuilderNip42 {
+            mode: RelayBuilderNip42Mode::Read,
+        };
+        let builder = RelayBuilder::default().nip42(opts);
+        let mock = LocalRelay::run(builder).await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        // REQ-only relay
+        let relay = Relay::with_opts(url, RelayOptions::default().write(false));
+
+        // No signer configured
+        relay.inner.state.ephemeral_auth(true);
+
+        relay.connect(Some(Duration::from_millis(100))).await;
+
+        let filter = Filter::new().kind(Kind::TextNote).limit(3);
+        relay
+            .fetch_events(
+                vec![filter],
+                Duration::from_secs(5),
+                FilterOptions::ExitOnEOSE,
+            )
+            .await
+            .unwrap();
+    }
 }
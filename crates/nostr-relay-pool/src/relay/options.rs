@@ -4,14 +4,19 @@
 
 //! Relay options
 
+use std::cmp;
 use std::time::Duration;
 
 use async_wsocket::ConnectionMode;
 use tokio::sync::watch::{self, Receiver, Sender};
 
-use super::constants::DEFAULT_RETRY_INTERVAL;
+use super::constants::{
+    DEFAULT_BAN_DURATION, DEFAULT_OUTBOX_LIMIT, DEFAULT_PING_INTERVAL, DEFAULT_RETRY_INTERVAL,
+    MIN_PING_INTERVAL,
+};
 use super::filtering::RelayFilteringMode;
 use super::flags::RelayServiceFlags;
+use crate::transport::SocketOptions;
 use crate::RelayLimits;
 
 /// Relay options
@@ -19,12 +24,22 @@ use crate::RelayLimits;
 pub struct RelayOptions {
     pub(super) connection_mode: ConnectionMode,
     pub(super) flags: RelayServiceFlags,
+    pub(super) ping_interval: Duration,
     pub(super) reconnect: bool,
     pub(super) retry_interval: Duration,
     pub(super) adjust_retry_interval: bool,
+    pub(super) ban_threshold: Option<usize>,
+    pub(super) ban_duration: Duration,
     pub(super) limits: RelayLimits,
     pub(super) max_avg_latency: Option<Duration>,
     pub(super) filtering_mode: RelayFilteringMode,
+    pub(super) resubscribe_since: bool,
+    pub(super) outbox: bool,
+    pub(super) outbox_limit: usize,
+    pub(super) capture: Option<usize>,
+    #[cfg(feature = "nip11")]
+    pub(super) enforce_limitations: bool,
+    pub(super) socket: SocketOptions,
 }
 
 impl Default for RelayOptions {
@@ -32,12 +47,22 @@ impl Default for RelayOptions {
         Self {
             connection_mode: ConnectionMode::default(),
             flags: RelayServiceFlags::default(),
+            ping_interval: DEFAULT_PING_INTERVAL,
             reconnect: true,
             retry_interval: DEFAULT_RETRY_INTERVAL,
             adjust_retry_interval: true,
+            ban_threshold: None,
+            ban_duration: DEFAULT_BAN_DURATION,
             limits: RelayLimits::default(),
             max_avg_latency: None,
             filtering_mode: RelayFilteringMode::default(),
+            resubscribe_since: false,
+            outbox: false,
+            outbox_limit: DEFAULT_OUTBOX_LIMIT,
+            capture: None,
+            #[cfg(feature = "nip11")]
+            enforce_limitations: false,
+            socket: SocketOptions::default(),
         }
     }
 }
@@ -92,6 +117,16 @@ impl RelayOptions {
         self
     }
 
+    /// Ping interval (default: 55 sec, min: 1 sec)
+    ///
+    /// Used also for latency calculation.
+    /// An interval lower than the min (i.e. zero) is clamped to the min.
+    #[inline]
+    pub fn ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = cmp::max(interval, MIN_PING_INTERVAL);
+        self
+    }
+
     /// Minimum POW for received events (default: 0)
     #[deprecated(since = "0.38.0")]
     pub fn pow(self, _difficulty: u8) -> Self {
@@ -128,6 +163,23 @@ impl RelayOptions {
         self
     }
 
+    /// Ban the relay after the specified number of consecutive connection failures (default: None)
+    ///
+    /// The relay status is set to [`RelayStatus::Banned`](crate::RelayStatus::Banned)
+    /// and no connection is attempted until the ban expires (check [`RelayOptions::ban_duration`]).
+    #[inline]
+    pub fn ban_threshold(mut self, failures: Option<usize>) -> Self {
+        self.ban_threshold = failures;
+        self
+    }
+
+    /// Ban duration (default: 10 min)
+    #[inline]
+    pub fn ban_duration(mut self, duration: Duration) -> Self {
+        self.ban_duration = duration;
+        self
+    }
+
     /// Set custom limits
     pub fn limits(mut self, limits: RelayLimits) -> Self {
         self.limits = limits;
@@ -149,13 +201,107 @@ impl RelayOptions {
         self.filtering_mode = mode;
         self
     }
+
+    /// Track the newest event received for each subscription and use it as `since` when re-subscribing (default: false)
+    ///
+    /// Avoid to download again all the events matching the subscription filters after a reconnection.
+    #[inline]
+    pub fn resubscribe_since(mut self, enable: bool) -> Self {
+        self.resubscribe_since = enable;
+        self
+    }
+
+    /// Queue the events in the outbox if the relay is not connected (default: false)
+    ///
+    /// The queued events are sent as soon as the relay (re)connects
+    /// and the result is delivered as [`RelayPoolNotification::OutboxEventSent`](crate::RelayPoolNotification::OutboxEventSent).
+    ///
+    /// The outbox is saved in the database, so it survives the restarts if the database is persistent.
+    /// If the database doesn't support it, the outbox is kept in memory.
+    #[inline]
+    pub fn outbox(mut self, enable: bool) -> Self {
+        self.outbox = enable;
+        self
+    }
+
+    /// Max number of events queued in the outbox (default: 1000)
+    ///
+    /// When the outbox is full, the events are rejected with [`Error::OutboxFull`](crate::relay::Error::OutboxFull).
+    #[inline]
+    pub fn outbox_limit(mut self, limit: usize) -> Self {
+        self.outbox_limit = limit;
+        self
+    }
+
+    /// Record the raw frames exchanged with the relay (default: None)
+    ///
+    /// Keep the last `capacity` inbound and outbound frames in a ring buffer.
+    /// Get them with [`Relay::capture`](super::Relay::capture).
+    #[inline]
+    pub fn capture(mut self, capacity: Option<usize>) -> Self {
+        self.capture = capacity;
+        self
+    }
+
+    /// Enforce the limitations advertised in the NIP11 document of the relay (default: false)
+    ///
+    /// The subscriptions and the events that don't respect them fail with [`Error::Limitation`](super::Error::Limitation)
+    /// before being sent. Nothing is checked until the document has been fetched.
+    ///
+    /// A relay that requires a payment must be marked as paid (check [`Relay::set_paid`](super::Relay::set_paid)).
+    #[inline]
+    #[cfg(feature = "nip11")]
+    pub fn enforce_limitations(mut self, enable: bool) -> Self {
+        self.enforce_limitations = enable;
+        self
+    }
+
+    /// Set TCP keepalive idle time and probes interval (default: None)
+    ///
+    /// Lower values allow to detect dead connections faster (i.e. after a mobile network switch).
+    /// Applied only to direct connections.
+    #[inline]
+    pub fn tcp_keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.socket.tcp_keepalive = interval;
+        self
+    }
+
+    /// Set `TCP_NODELAY` (default: false)
+    ///
+    /// Disable Nagle's algorithm to reduce the latency of small messages.
+    /// Applied only to direct connections.
+    #[inline]
+    pub fn tcp_nodelay(mut self, enable: bool) -> Self {
+        self.socket.tcp_nodelay = enable;
+        self
+    }
+
+    /// Set socket options (default: system defaults)
+    #[inline]
+    pub fn socket(mut self, opts: SocketOptions) -> Self {
+        self.socket = opts;
+        self
+    }
 }
 
 /// Auto-closing subscribe options
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy)]
 pub struct SubscribeAutoCloseOptions {
     pub(super) filter: FilterOptions,
     pub(super) timeout: Option<Duration>,
+    pub(super) close_on_eose: bool,
+    pub(super) max_events: Option<usize>,
+}
+
+impl Default for SubscribeAutoCloseOptions {
+    fn default() -> Self {
+        Self {
+            filter: FilterOptions::default(),
+            timeout: None,
+            close_on_eose: true,
+            max_events: None,
+        }
+    }
 }
 
 impl SubscribeAutoCloseOptions {
@@ -170,12 +316,31 @@ impl SubscribeAutoCloseOptions {
         self.timeout = timeout;
         self
     }
+
+    /// Close subscription when EOSE is received, according to the [FilterOptions] (default: true)
+    ///
+    /// If disabled, the subscription is closed only by the timeout or by the max number of events.
+    #[inline]
+    pub fn close_on_eose(mut self, enable: bool) -> Self {
+        self.close_on_eose = enable;
+        self
+    }
+
+    /// Automatically close subscription after receiving N events (default: None)
+    ///
+    /// Both the stored and the new events are counted.
+    #[inline]
+    pub fn max_events(mut self, max: Option<usize>) -> Self {
+        self.max_events = max;
+        self
+    }
 }
 
 /// Subscribe options
 #[derive(Debug, Clone, Copy, Default)]
 pub struct SubscribeOptions {
     pub(super) auto_close: Option<SubscribeAutoCloseOptions>,
+    pub(super) cursor: bool,
 }
 
 impl SubscribeOptions {
@@ -185,6 +350,52 @@ impl SubscribeOptions {
         self
     }
 
+    /// Close the subscription when EOSE is received
+    ///
+    /// Shorthand for [`SubscribeAutoCloseOptions::close_on_eose`].
+    pub fn close_on_eose(mut self) -> Self {
+        let opts: SubscribeAutoCloseOptions = self.auto_close.unwrap_or_default();
+        self.auto_close = Some(opts.close_on_eose(true));
+        self
+    }
+
+    /// Close the subscription after the `ttl`
+    ///
+    /// Shorthand for [`SubscribeAutoCloseOptions::timeout`].
+    /// If no other auto-close condition is set, the subscription is kept open after EOSE.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.auto_close = Some(self.auto_close_or_live().timeout(Some(ttl)));
+        self
+    }
+
+    /// Close the subscription after receiving `max` events
+    ///
+    /// Shorthand for [`SubscribeAutoCloseOptions::max_events`].
+    /// If no other auto-close condition is set, the subscription is kept open after EOSE.
+    pub fn max_events(mut self, max: usize) -> Self {
+        self.auto_close = Some(self.auto_close_or_live().max_events(Some(max)));
+        self
+    }
+
+    fn auto_close_or_live(&self) -> SubscribeAutoCloseOptions {
+        self.auto_close
+            .unwrap_or_else(|| SubscribeAutoCloseOptions::default().close_on_eose(false))
+    }
+
+    /// Persist a cursor of the received events in the database (default: false)
+    ///
+    /// The cursor (newest `created_at` and IDs of the events at the boundary) is saved per relay and subscription ID,
+    /// and used to resume the subscription, after a restart, without gaps or duplicates.
+    /// Use a stable subscription ID to resume it.
+    ///
+    /// Ignored for auto-closing subscriptions.
+    /// Require a database that supports cursors (see [`NostrDatabase::save_cursor`](nostr_database::NostrDatabase::save_cursor)).
+    #[inline]
+    pub fn cursor(mut self, enable: bool) -> Self {
+        self.cursor = enable;
+        self
+    }
+
     pub(crate) fn is_auto_closing(&self) -> bool {
         self.auto_close.is_some()
     }
@@ -0,0 +1,95 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Paid relays
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/11.md#pay-to-relay>
+
+use nostr::nips::nip11::{FeeSchedule, FeeSchedules, RelayInformationDocument};
+
+/// Payment required by a relay
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RelayPayment {
+    /// Where to pay (i.e. a web page, a lightning address or an LNURL)
+    pub payments_url: Option<String>,
+    /// Fee schedules
+    pub fees: FeeSchedules,
+}
+
+impl RelayPayment {
+    /// Extract the payment requirements from the NIP11 document
+    ///
+    /// Return `None` if the relay doesn't require a payment.
+    pub fn from_document(document: &RelayInformationDocument) -> Option<Self> {
+        let required: bool = document
+            .limitation
+            .as_ref()
+            .and_then(|l| l.payment_required)
+            .unwrap_or_default();
+
+        if !required {
+            return None;
+        }
+
+        Some(Self {
+            payments_url: document.payments_url.clone(),
+            fees: document.fees.clone().unwrap_or_default(),
+        })
+    }
+
+    /// Get the cheapest admission fee, in millisats
+    ///
+    /// Only the fees in `msats` or `sats` are considered.
+    pub fn admission_msats(&self) -> Option<u64> {
+        self.fees.admission.iter().filter_map(fee_msats).min()
+    }
+}
+
+fn fee_msats(fee: &FeeSchedule) -> Option<u64> {
+    let amount: u64 = u64::try_from(fee.amount).ok().filter(|a| *a > 0)?;
+    match fee.unit.to_lowercase().as_str() {
+        "msat" | "msats" => Some(amount),
+        "sat" | "sats" => amount.checked_mul(1000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::nips::nip11::Limitation;
+
+    use super::*;
+
+    fn fee(amount: i32, unit: &str) -> FeeSchedule {
+        FeeSchedule {
+            amount,
+            unit: unit.to_string(),
+            period: None,
+            kinds: None,
+        }
+    }
+
+    #[test]
+    fn test_relay_payment() {
+        let mut document = RelayInformationDocument::new();
+        assert!(RelayPayment::from_document(&document).is_none());
+
+        document.limitation = Some(Limitation {
+            payment_required: Some(true),
+            ..Default::default()
+        });
+        document.payments_url = Some(String::from("https://relay.example.com/join"));
+        document.fees = Some(FeeSchedules {
+            admission: vec![fee(21000, "msats"), fee(10, "sats"), fee(5, "usd")],
+            ..Default::default()
+        });
+
+        let payment = RelayPayment::from_document(&document).unwrap();
+        assert_eq!(
+            payment.payments_url.as_deref(),
+            Some("https://relay.example.com/join")
+        );
+        assert_eq!(payment.admission_msats(), Some(10000));
+    }
+}
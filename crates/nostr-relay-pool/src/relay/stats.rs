@@ -4,16 +4,24 @@
 
 //! Relay Stats
 
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::AddAssign;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{
+    Arc, Mutex as StdMutex, RwLock as StdRwLock, RwLockReadGuard as StdRwLockReadGuard,
+};
 #[cfg(not(target_arch = "wasm32"))]
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use nostr::Timestamp;
+use nostr::{EventId, SubscriptionId, Timestamp};
+use tokio::sync::RwLock;
 
 #[cfg(not(target_arch = "wasm32"))]
 use super::constants::LATENCY_MIN_READS;
 
+/// Max number of received event IDs kept to not count the events sent again as duplicates
+const MAX_RECEIVED_IDS: usize = 10_000;
+
 #[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug, Default)]
 struct AverageLatency {
@@ -23,17 +31,131 @@ struct AverageLatency {
     count: AtomicU64,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+impl AverageLatency {
+    fn add(&self, latency: Duration) {
+        let ms: u128 = latency.as_millis();
+        if ms <= u64::MAX as u128 {
+            self.total.fetch_add(ms as u64, Ordering::SeqCst);
+            self.count.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn average(&self, min_reads: u64) -> Option<Duration> {
+        let total: u64 = self.total.load(Ordering::SeqCst);
+        let count: u64 = self.count.load(Ordering::SeqCst);
+
+        // Check number of reads
+        if count < min_reads {
+            return None;
+        }
+
+        // Calc latency
+        total.checked_div(count).map(Duration::from_millis)
+    }
+}
+
+/// Bandwidth usage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Bandwidth {
+    /// Bytes sent
+    pub sent: usize,
+    /// Bytes received
+    pub received: usize,
+}
+
+impl Bandwidth {
+    /// Total bytes (sent + received)
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.sent.saturating_add(self.received)
+    }
+}
+
+impl AddAssign for Bandwidth {
+    fn add_assign(&mut self, rhs: Self) {
+        self.sent = self.sent.saturating_add(rhs.sent);
+        self.received = self.received.saturating_add(rhs.received);
+    }
+}
+
+#[derive(Debug, Default)]
+struct AtomicBandwidth {
+    sent: AtomicUsize,
+    received: AtomicUsize,
+}
+
+impl AtomicBandwidth {
+    fn add(&self, bandwidth: Bandwidth) {
+        if bandwidth.sent > 0 {
+            self.sent.fetch_add(bandwidth.sent, Ordering::SeqCst);
+        }
+
+        if bandwidth.received > 0 {
+            self.received
+                .fetch_add(bandwidth.received, Ordering::SeqCst);
+        }
+    }
+
+    fn load(&self) -> Bandwidth {
+        Bandwidth {
+            sent: self.sent.load(Ordering::SeqCst),
+            received: self.received.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Events recently received from the relay
+#[derive(Debug, Default)]
+struct ReceivedEvents {
+    ids: HashSet<EventId>,
+    order: VecDeque<EventId>,
+}
+
+impl ReceivedEvents {
+    /// Mark the event as received
+    ///
+    /// Return `false` if it has been already received.
+    fn insert(&mut self, id: EventId) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+
+        self.order.push_back(id);
+
+        // Forget the oldest
+        if self.order.len() > MAX_RECEIVED_IDS {
+            if let Some(id) = self.order.pop_front() {
+                self.ids.remove(&id);
+            }
+        }
+
+        true
+    }
+}
+
 #[derive(Debug, Default)]
 struct InnerRelayConnectionStats {
     attempts: AtomicUsize,
     success: AtomicUsize,
     bytes_sent: AtomicUsize,
     bytes_received: AtomicUsize,
+    /// Bandwidth of the open subscriptions: the write lock is taken only to add or remove a subscription
+    subscriptions: StdRwLock<HashMap<SubscriptionId, AtomicBandwidth>>,
+    unique_events: AtomicUsize,
+    duplicate_events: AtomicUsize,
+    /// Kept in memory to track the contributions without reading the seen relays from the database
+    received_events: StdMutex<ReceivedEvents>,
     // TODO: keep track of msg/event sending attempts and success?
     connected_at: AtomicU64,
     first_connection_at: AtomicU64,
     #[cfg(not(target_arch = "wasm32"))]
     latency: AverageLatency,
+    #[cfg(not(target_arch = "wasm32"))]
+    eose_latency: AverageLatency,
+    /// When the `REQ`s waiting for the `EOSE` have been sent
+    #[cfg(not(target_arch = "wasm32"))]
+    pending_reqs: RwLock<HashMap<SubscriptionId, Instant>>,
 }
 
 /// Relay connection stats
@@ -77,6 +199,53 @@ impl RelayConnectionStats {
         self.inner.bytes_received.load(Ordering::SeqCst)
     }
 
+    /// Bandwidth usage
+    #[inline]
+    pub fn bandwidth(&self) -> Bandwidth {
+        Bandwidth {
+            sent: self.bytes_sent(),
+            received: self.bytes_received(),
+        }
+    }
+
+    /// Bandwidth usage of a subscription
+    ///
+    /// Count the `REQ`, `COUNT` and `CLOSE` messages sent
+    /// and the `EVENT`, `EOSE`, `COUNT` and `CLOSED` messages received for the subscription.
+    ///
+    /// Available only while the subscription is open: the usage is removed when
+    /// the `CLOSE` is sent or the `CLOSED` (or the `COUNT` response) is received.
+    pub fn subscription_bandwidth(&self, id: &SubscriptionId) -> Option<Bandwidth> {
+        let subscriptions = self.subscriptions();
+        subscriptions.get(id).map(AtomicBandwidth::load)
+    }
+
+    /// Bandwidth usage of all the open subscriptions
+    pub fn subscriptions_bandwidth(&self) -> HashMap<SubscriptionId, Bandwidth> {
+        let subscriptions = self.subscriptions();
+        subscriptions
+            .iter()
+            .map(|(id, bandwidth)| (id.clone(), bandwidth.load()))
+            .collect()
+    }
+
+    /// Reset the bandwidth usage of the subscriptions
+    pub fn reset_subscriptions_bandwidth(&self) {
+        let mut subscriptions = match self.inner.subscriptions.write() {
+            Ok(subscriptions) => subscriptions,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        subscriptions.clear();
+    }
+
+    fn subscriptions(&self) -> StdRwLockReadGuard<'_, HashMap<SubscriptionId, AtomicBandwidth>> {
+        // Recover from a poisoned lock: the map is always in a consistent state
+        match self.inner.subscriptions.read() {
+            Ok(subscriptions) => subscriptions,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
     /// Get UNIX timestamp of the last connection
     #[inline]
     pub fn connected_at(&self) -> Timestamp {
@@ -92,16 +261,45 @@ impl RelayConnectionStats {
     /// Calculate latency
     #[cfg(not(target_arch = "wasm32"))]
     pub fn latency(&self) -> Option<Duration> {
-        let total: u64 = self.inner.latency.total.load(Ordering::SeqCst);
-        let count: u64 = self.inner.latency.count.load(Ordering::SeqCst);
+        self.inner.latency.average(LATENCY_MIN_READS)
+    }
 
-        // Check number of reads
-        if count < LATENCY_MIN_READS {
-            return None;
-        }
+    /// Average time between the `REQ` and the `EOSE`
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn eose_latency(&self) -> Option<Duration> {
+        self.inner.eose_latency.average(1)
+    }
 
-        // Calc latency
-        total.checked_div(count).map(Duration::from_millis)
+    /// Number of events received from the relay that weren't already known (i.e. not received from other relays)
+    #[inline]
+    pub fn unique_events(&self) -> usize {
+        self.inner.unique_events.load(Ordering::SeqCst)
+    }
+
+    /// Number of events received from the relay that were already known (i.e. received from other relays)
+    ///
+    /// The events sent again by the same relay (i.e. after a re-subscription) are not counted.
+    #[inline]
+    pub fn duplicate_events(&self) -> usize {
+        self.inner.duplicate_events.load(Ordering::SeqCst)
+    }
+
+    /// Restore the connection counters and timestamps (i.e. from a snapshot)
+    pub(crate) fn restore(
+        &self,
+        attempts: usize,
+        success: usize,
+        connected_at: Timestamp,
+        first_connection_at: Timestamp,
+    ) {
+        self.inner.attempts.store(attempts, Ordering::SeqCst);
+        self.inner.success.store(success, Ordering::SeqCst);
+        self.inner
+            .connected_at
+            .store(connected_at.as_u64(), Ordering::SeqCst);
+        self.inner
+            .first_connection_at
+            .store(first_connection_at.as_u64(), Ordering::SeqCst);
     }
 
     #[inline]
@@ -109,10 +307,10 @@ impl RelayConnectionStats {
         self.inner.attempts.fetch_add(1, Ordering::SeqCst);
     }
 
-    pub(super) fn new_success(&self) {
+    pub(super) fn new_success(&self, now: Timestamp) {
         self.inner.success.fetch_add(1, Ordering::SeqCst);
 
-        let now: u64 = Timestamp::now().as_u64();
+        let now: u64 = now.as_u64();
 
         self.inner.connected_at.store(now, Ordering::SeqCst);
 
@@ -135,15 +333,91 @@ impl RelayConnectionStats {
         }
     }
 
+    /// Add the bandwidth usage of a subscription
+    ///
+    /// The subscription is added only if `open` is true (`REQ` or `COUNT` sent),
+    /// so the frames received after the closing don't add it again.
+    pub(super) fn add_subscription_bandwidth(
+        &self,
+        id: &SubscriptionId,
+        bandwidth: Bandwidth,
+        open: bool,
+    ) {
+        {
+            let subscriptions = self.subscriptions();
+            if let Some(current) = subscriptions.get(id) {
+                current.add(bandwidth);
+                return;
+            }
+        }
+
+        if open {
+            let mut subscriptions = match self.inner.subscriptions.write() {
+                Ok(subscriptions) => subscriptions,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            subscriptions.entry(id.clone()).or_default().add(bandwidth);
+        }
+    }
+
+    /// Remove the bandwidth usage of a closed subscription
+    pub(super) fn remove_subscription_bandwidth(&self, id: &SubscriptionId) {
+        let mut subscriptions = match self.inner.subscriptions.write() {
+            Ok(subscriptions) => subscriptions,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        subscriptions.remove(id);
+    }
+
+    #[inline]
     #[cfg(not(target_arch = "wasm32"))]
     pub(super) fn save_latency(&self, latency: Duration) {
-        let ms: u128 = latency.as_millis();
-        if ms <= u64::MAX as u128 {
-            self.inner
-                .latency
-                .total
-                .fetch_add(ms as u64, Ordering::SeqCst);
-            self.inner.latency.count.fetch_add(1, Ordering::SeqCst);
+        self.inner.latency.add(latency);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) async fn req_sent(&self, id: SubscriptionId) {
+        let mut reqs = self.inner.pending_reqs.write().await;
+        reqs.insert(id, Instant::now());
+    }
+
+    /// Remove the pending `REQ` and, if `eose` is true, save the EOSE latency
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) async fn req_completed(&self, id: &SubscriptionId, eose: bool) {
+        let mut reqs = self.inner.pending_reqs.write().await;
+        if let Some(sent_at) = reqs.remove(id) {
+            if eose {
+                self.inner.eose_latency.add(sent_at.elapsed());
+            }
         }
     }
+
+    /// Remove all the pending `REQ`s (i.e. on disconnection)
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(super) async fn clear_pending_reqs(&self) {
+        let mut reqs = self.inner.pending_reqs.write().await;
+        reqs.clear();
+    }
+
+    #[inline]
+    pub(super) fn new_unique_event(&self) {
+        self.inner.unique_events.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[inline]
+    pub(super) fn new_duplicate_event(&self) {
+        self.inner.duplicate_events.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Mark the event as received from the relay
+    ///
+    /// Return `false` if the relay already sent it (i.e. re-subscription).
+    pub(super) fn event_received(&self, id: EventId) -> bool {
+        // Recover from a poisoned lock: the set is always in a consistent state
+        let mut received = match self.inner.received_events.lock() {
+            Ok(received) => received,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        received.insert(id)
+    }
 }
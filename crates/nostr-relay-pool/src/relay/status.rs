@@ -7,6 +7,8 @@
 use core::fmt;
 use core::sync::atomic::{AtomicU8, Ordering};
 
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug)]
 pub(super) struct AtomicRelayStatus {
     value: AtomicU8,
@@ -40,13 +42,14 @@ impl AtomicRelayStatus {
             3 => RelayStatus::Connected,
             4 => RelayStatus::Disconnected,
             5 => RelayStatus::Terminated,
+            6 => RelayStatus::Banned,
             _ => unreachable!(),
         }
     }
 }
 
 /// Relay connection status
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum RelayStatus {
     /// Initialized
     Initialized = 0,
@@ -60,6 +63,8 @@ pub enum RelayStatus {
     Disconnected = 4,
     /// Completely disconnected
     Terminated = 5,
+    /// Temporarily banned after too many consecutive connection failures, will retry to connect after the ban
+    Banned = 6,
 }
 
 impl fmt::Display for RelayStatus {
@@ -71,6 +76,7 @@ impl fmt::Display for RelayStatus {
             Self::Connected => write!(f, "Connected"),
             Self::Disconnected => write!(f, "Disconnected"),
             Self::Terminated => write!(f, "Terminated"),
+            Self::Banned => write!(f, "Banned"),
         }
     }
 }
@@ -86,10 +92,10 @@ impl RelayStatus {
         matches!(self, Self::Connected)
     }
 
-    /// Check if is `disconnected` or `terminated`
+    /// Check if is `disconnected`, `terminated` or `banned`
     #[inline]
     pub(crate) fn is_disconnected(&self) -> bool {
-        matches!(self, Self::Disconnected | Self::Terminated)
+        matches!(self, Self::Disconnected | Self::Terminated | Self::Banned)
     }
 
     /// Check if is [`RelayStatus::Terminated`]
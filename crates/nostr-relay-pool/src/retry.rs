@@ -0,0 +1,252 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Retry policy
+
+use std::cmp;
+use std::future::Future;
+use std::time::Duration;
+
+use async_utility::time;
+use nostr::message::MachineReadablePrefix;
+#[cfg(feature = "nip11")]
+use nostr::nips::nip11;
+use nostr::secp256k1::rand::{self, Rng};
+
+use crate::relay;
+
+const DEFAULT_MAX_ATTEMPTS: usize = 3;
+const DEFAULT_BASE_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(60);
+const DEFAULT_JITTER: Duration = Duration::from_millis(500);
+
+/// Error classification for the retries
+pub trait Retryable {
+    /// Check if the operation that failed with this error can be retried
+    fn is_retryable(&self) -> bool;
+}
+
+impl Retryable for relay::Error {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::WebSocket(..)
+            | Self::Timeout
+            | Self::NotRepliedToPing
+            | Self::CantSendChannelMessage { .. }
+            | Self::NotReady
+            | Self::NotConnected => true,
+            Self::RelayMessage(message) => matches!(
+                MachineReadablePrefix::parse(message),
+                Some(MachineReadablePrefix::RateLimited | MachineReadablePrefix::Error)
+            ),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(feature = "nip11")]
+impl Retryable for nip11::Error {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::InaccessibleInformationDocument => true,
+            Self::Http(e) => e.is_transient(),
+            Self::InvalidInformationDocument | Self::InvalidScheme => false,
+        }
+    }
+}
+
+/// Retry policy
+///
+/// Exponential backoff with jitter: the delay before the `n`th retry is `base_delay * 2^(n - 1)`,
+/// capped to `max_delay`, plus or minus a random jitter.
+///
+/// Shared by the relay reconnections (where the `max_attempts` is ignored), the publishing
+/// and the HTTP fetches (i.e. NIP11 documents).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            jitter: DEFAULT_JITTER,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// New default policy
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never retry
+    #[inline]
+    pub fn none() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Max number of attempts, including the first one (default: 3)
+    #[inline]
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry (default: 1 sec)
+    #[inline]
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Max delay between the attempts, before the jitter (default: 60 secs)
+    #[inline]
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Max random jitter added to or subtracted from the delay (default: 500 millis)
+    ///
+    /// Avoid that many clients (or relays) retry all at the same time.
+    #[inline]
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Get max attempts
+    #[inline]
+    pub fn get_max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// Delay before the next attempt, after `failures` consecutive failures
+    pub fn delay(&self, failures: usize) -> Duration {
+        let exp: u32 = failures.saturating_sub(1).min(31) as u32;
+        let delay: Duration = self
+            .base_delay
+            .checked_mul(1 << exp)
+            .unwrap_or(self.max_delay);
+        let delay: Duration = cmp::min(delay, self.max_delay);
+
+        let jitter: u64 = self.jitter.as_millis() as u64;
+        if jitter == 0 {
+            return delay;
+        }
+
+        let jitter: i64 = rand::thread_rng().gen_range(-(jitter as i64)..=jitter as i64);
+        if jitter >= 0 {
+            delay.saturating_add(Duration::from_millis(jitter as u64))
+        } else {
+            delay.saturating_sub(Duration::from_millis(jitter.unsigned_abs()))
+        }
+    }
+
+    /// Run the operation, retrying it while the error is retryable and the attempts aren't exhausted
+    pub async fn retry<F, Fut, T, E>(&self, mut operation: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Retryable,
+    {
+        let mut attempts: usize = 0;
+
+        loop {
+            attempts += 1;
+
+            match operation().await {
+                Ok(val) => return Ok(val),
+                Err(e) if e.is_retryable() && attempts < self.max_attempts => {
+                    time::sleep(self.delay(attempts)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new()
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(10))
+            .jitter(Duration::ZERO);
+
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(5), Duration::from_secs(10));
+        assert_eq!(policy.delay(100), Duration::from_secs(10));
+
+        let policy = policy.jitter(Duration::from_millis(200));
+        let delay = policy.delay(2);
+        assert!(delay >= Duration::from_millis(1800) && delay <= Duration::from_millis(2200));
+    }
+
+    #[test]
+    fn test_classification() {
+        assert!(relay::Error::Timeout.is_retryable());
+        assert!(relay::Error::RelayMessage(String::from("rate-limited: slow down")).is_retryable());
+        assert!(!relay::Error::RelayMessage(String::from("blocked: banned")).is_retryable());
+        assert!(!relay::Error::RelayMessage(String::from("invalid: bad sig")).is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let policy = RetryPolicy::new()
+            .max_attempts(3)
+            .base_delay(Duration::from_millis(10))
+            .jitter(Duration::ZERO);
+
+        // Retryable error
+        let calls = AtomicUsize::new(0);
+        let res: Result<(), relay::Error> = policy
+            .retry(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(relay::Error::Timeout)
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // Not retryable error
+        let calls = AtomicUsize::new(0);
+        let res: Result<(), relay::Error> = policy
+            .retry(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(relay::Error::RelayMessage(String::from("invalid: test")))
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Success after a failure
+        let calls = AtomicUsize::new(0);
+        let res: Result<usize, relay::Error> = policy
+            .retry(|| async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(relay::Error::NotConnected),
+                    n => Ok(n),
+                }
+            })
+            .await;
+        assert_eq!(res.unwrap(), 1);
+    }
+}
@@ -2,15 +2,32 @@
 // Copyright (c) 2023-2024 Rust Nostr Developers
 // Distributed under the MIT software license
 
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::future::Future;
+use std::pin::pin;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 
-use nostr::prelude::IntoNostrSigner;
-use nostr::NostrSigner;
-use nostr_database::{IntoNostrDatabase, MemoryDatabase, NostrDatabase};
-use tokio::sync::RwLock;
+use async_wsocket::futures_util::future;
+use nostr::nips::nip62::VanishTarget;
+use nostr::prelude::{IntoNostrSigner, IntoTimeProvider, SystemTimeProvider, TimeProvider};
+use nostr::secp256k1::schnorr::Signature;
+use nostr::util::http::{HttpClient, IntoHttpClient};
+use nostr::{event, Event, EventId, Filter, Kind, NostrSigner, PublicKey, UnsignedEvent};
+use nostr_database::{DatabaseError, IntoNostrDatabase, MemoryDatabase, NostrDatabase};
+use tokio::sync::{watch, RwLock};
 
+use crate::metrics::IngestionMetrics;
+use crate::policy::{AdmitPolicy, DedupPolicy};
+use crate::pool::{MiddlewareDecision, MiddlewareError, OutgoingMiddleware};
+use crate::retry::RetryPolicy;
+use crate::skew::{ClockSkew, ClockSkewOptions};
+use crate::spawner::{DefaultSpawner, IntoSpawner, Spawner};
+use crate::tasks::{TaskHandle, TaskInfo, TaskRegistry};
+use crate::transport::{DefaultWebsocketTransport, WebSocketTransport};
 use crate::{RelayFiltering, RelayFilteringMode};
 
 #[derive(Debug)]
@@ -28,6 +45,43 @@ impl fmt::Display for SharedStateError {
     }
 }
 
+/// Max number of verified signatures kept in the cache
+const VERIFIED_SIGNATURES_CACHE_SIZE: usize = 10_000;
+
+/// Signatures already verified, keyed by event ID
+#[derive(Debug, Default)]
+struct VerifiedSignatures {
+    signatures: HashMap<EventId, Signature>,
+    order: VecDeque<EventId>,
+}
+
+impl VerifiedSignatures {
+    fn contains(&self, event: &Event) -> bool {
+        self.signatures.get(&event.id) == Some(&event.sig)
+    }
+
+    fn insert(&mut self, event: &Event) {
+        if self.signatures.insert(event.id, event.sig).is_none() {
+            self.order.push_back(event.id);
+        }
+
+        // Remove the oldest entries
+        while self.order.len() > VERIFIED_SIGNATURES_CACHE_SIZE {
+            if let Some(id) = self.order.pop_front() {
+                self.signatures.remove(&id);
+            }
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // Recover from a poisoned lock: the guarded data is always in a consistent state
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
 // TODO: add SharedStateBuilder?
 
 #[derive(Debug, Clone)]
@@ -35,8 +89,24 @@ pub struct SharedState {
     pub(crate) database: Arc<dyn NostrDatabase>,
     signer: Arc<RwLock<Option<Arc<dyn NostrSigner>>>>,
     nip42_auto_authentication: Arc<AtomicBool>,
+    ephemeral_auth: Arc<AtomicBool>,
     min_pow_difficulty: Arc<AtomicU8>,
     pub(crate) filtering: RelayFiltering,
+    admit_policy: Option<Arc<dyn AdmitPolicy>>,
+    dedup_policy: Option<Arc<dyn DedupPolicy>>,
+    transport: Arc<dyn WebSocketTransport>,
+    time_provider: Arc<dyn TimeProvider>,
+    clock_skew: Option<Arc<ClockSkew>>,
+    retry_policy: Option<RetryPolicy>,
+    http_client: Option<Arc<dyn HttpClient>>,
+    spawner: Arc<dyn Spawner>,
+    tasks: Arc<TaskRegistry>,
+    ingestion_metrics: Arc<IngestionMetrics>,
+    verified_signatures: Arc<Mutex<VerifiedSignatures>>,
+    /// Requests to vanish sent without waiting for the relays response
+    pending_vanish_requests: Arc<Mutex<HashMap<EventId, Event>>>,
+    shutdown: Arc<watch::Sender<bool>>,
+    middlewares: Arc<RwLock<Vec<Arc<dyn OutgoingMiddleware>>>>,
 }
 
 impl Default for SharedState {
@@ -45,27 +115,228 @@ impl Default for SharedState {
             database: MemoryDatabase::new().into_nostr_database(),
             signer: Arc::new(RwLock::new(None)),
             nip42_auto_authentication: Arc::new(AtomicBool::new(true)),
+            ephemeral_auth: Arc::new(AtomicBool::new(false)),
             min_pow_difficulty: Arc::new(AtomicU8::new(0)),
             filtering: RelayFiltering::default(),
+            admit_policy: None,
+            dedup_policy: None,
+            transport: Arc::new(DefaultWebsocketTransport),
+            time_provider: Arc::new(SystemTimeProvider),
+            clock_skew: None,
+            retry_policy: None,
+            http_client: None,
+            spawner: Arc::new(DefaultSpawner),
+            tasks: Arc::new(TaskRegistry::default()),
+            ingestion_metrics: Arc::new(IngestionMetrics::default()),
+            verified_signatures: Arc::new(Mutex::new(VerifiedSignatures::default())),
+            pending_vanish_requests: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: Arc::new(watch::channel(false).0),
+            middlewares: Arc::new(RwLock::new(Vec::new())),
         }
     }
 }
 
 impl SharedState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         database: Arc<dyn NostrDatabase>,
         signer: Option<Arc<dyn NostrSigner>>,
         filtering_mode: RelayFilteringMode,
         nip42_auto_authentication: bool,
         min_pow_difficulty: u8,
+        admit_policy: Option<Arc<dyn AdmitPolicy>>,
+        dedup_policy: Option<Arc<dyn DedupPolicy>>,
+        transport: Arc<dyn WebSocketTransport>,
     ) -> Self {
         Self {
             database,
             signer: Arc::new(RwLock::new(signer)),
             nip42_auto_authentication: Arc::new(AtomicBool::new(nip42_auto_authentication)),
+            ephemeral_auth: Arc::new(AtomicBool::new(false)),
             filtering: RelayFiltering::new(filtering_mode),
             min_pow_difficulty: Arc::new(AtomicU8::new(min_pow_difficulty)),
+            admit_policy,
+            dedup_policy,
+            transport,
+            time_provider: Arc::new(SystemTimeProvider),
+            clock_skew: None,
+            retry_policy: None,
+            http_client: None,
+            spawner: Arc::new(DefaultSpawner),
+            tasks: Arc::new(TaskRegistry::default()),
+            ingestion_metrics: Arc::new(IngestionMetrics::default()),
+            verified_signatures: Arc::new(Mutex::new(VerifiedSignatures::default())),
+            pending_vanish_requests: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: Arc::new(watch::channel(false).0),
+            middlewares: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Set the [`TimeProvider`] used for the `created_at` of the AUTH events and of the subscriptions
+    ///
+    /// Timeouts and intervals are still measured by the async runtime.
+    pub fn with_time_provider<T>(mut self, provider: T) -> Self
+    where
+        T: IntoTimeProvider,
+    {
+        self.time_provider = provider.into_time_provider();
+        self
+    }
+
+    /// Get time provider
+    #[inline]
+    pub fn time_provider(&self) -> &Arc<dyn TimeProvider> {
+        &self.time_provider
+    }
+
+    /// Enable the clock skew detection
+    ///
+    /// The skew is measured against the current time provider, so this must be called after [`SharedState::with_time_provider`].
+    /// If the auto-correction is enabled, the [`ClockSkew`] becomes the time provider.
+    pub fn with_clock_skew(mut self, opts: ClockSkewOptions) -> Self {
+        let skew: Arc<ClockSkew> = Arc::new(ClockSkew::new(self.time_provider.clone(), opts));
+
+        if opts.is_auto_correct_enabled() {
+            self.time_provider = skew.clone();
         }
+
+        self.clock_skew = Some(skew);
+        self
+    }
+
+    /// Get clock skew detector, if enabled
+    #[inline]
+    pub fn clock_skew(&self) -> Option<&Arc<ClockSkew>> {
+        self.clock_skew.as_ref()
+    }
+
+    /// Set the retry policy of the reconnections, of the publishing and of the HTTP fetches
+    #[inline]
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Get retry policy
+    #[inline]
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    /// Set the [`Spawner`] of the background tasks
+    pub fn with_spawner<T>(mut self, spawner: T) -> Self
+    where
+        T: IntoSpawner,
+    {
+        self.spawner = spawner.into_spawner();
+        self
+    }
+
+    /// Spawn a named background task with the [`Spawner`], tracking it in the task registry
+    #[cfg(not(any(target_arch = "wasm32", nostr_local)))]
+    pub fn spawn<S, F>(&self, name: S, future: F) -> TaskHandle
+    where
+        S: Into<Cow<'static, str>>,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (handle, future) = self.tasks.track(name.into(), future);
+        self.spawner.spawn(Box::pin(future));
+        handle
+    }
+
+    /// Spawn a named background task with the [`Spawner`], tracking it in the task registry
+    #[cfg(any(target_arch = "wasm32", nostr_local))]
+    pub fn spawn<S, F>(&self, name: S, future: F) -> TaskHandle
+    where
+        S: Into<Cow<'static, str>>,
+        F: Future<Output = ()> + 'static,
+    {
+        let (handle, future) = self.tasks.track(name.into(), future);
+        self.spawner.spawn(Box::pin(future));
+        handle
+    }
+
+    /// Wrap the future to stop it at the pool shutdown
+    ///
+    /// Used for the long-lived background tasks, to not stall the shutdown.
+    pub fn until_shutdown<F>(&self, future: F) -> impl Future<Output = ()>
+    where
+        F: Future<Output = ()>,
+    {
+        let mut shutdown = self.shutdown.subscribe();
+        async move {
+            let shutdown = async move {
+                // Return also if the sender has been dropped
+                let _ = shutdown.wait_for(|shutdown| *shutdown).await;
+            };
+            future::select(pin!(future), pin!(shutdown)).await;
+        }
+    }
+
+    /// Signal the shutdown to the tasks wrapped with [`SharedState::until_shutdown`]
+    #[inline]
+    pub(crate) fn signal_shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    /// Verify the [`Event`]
+    ///
+    /// The signature check is skipped if the same signature has already been verified for the event ID
+    /// (i.e. the same event received from another relay) and counted in [`IngestionMetrics::saved_verifications`].
+    pub(crate) fn verify_event(&self, event: &Event) -> Result<(), event::Error> {
+        // Always verify the ID (cheap), so the fields can't be changed under a cached signature
+        if !event.verify_id() {
+            return Err(event::Error::InvalidId);
+        }
+
+        if lock(&self.verified_signatures).contains(event) {
+            self.ingestion_metrics.saved_verification();
+            return Ok(());
+        }
+
+        if !event.verify_signature() {
+            return Err(event::Error::InvalidSignature);
+        }
+
+        lock(&self.verified_signatures).insert(event);
+
+        Ok(())
+    }
+
+    /// Get the latencies of the ingestion pipeline
+    #[inline]
+    pub fn ingestion_metrics(&self) -> &IngestionMetrics {
+        &self.ingestion_metrics
+    }
+
+    /// Get the tracked background tasks
+    #[inline]
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        self.tasks.tasks()
+    }
+
+    /// Wait for the background tasks to terminate, aborting them after the `timeout`
+    #[inline]
+    pub async fn join_tasks(&self, timeout: Duration) {
+        self.tasks.join_all(timeout).await
+    }
+
+    /// Set the [`HttpClient`] used for the HTTP fetches (i.e. NIP11 documents)
+    ///
+    /// The relays with a proxy use the client returned by [`HttpClient::proxied`],
+    /// or their own proxied client if the custom one doesn't support proxies.
+    pub fn with_http_client<T>(mut self, client: T) -> Self
+    where
+        T: IntoHttpClient,
+    {
+        self.http_client = Some(client.into_http_client());
+        self
+    }
+
+    /// Get custom HTTP client, if set
+    #[inline]
+    pub fn http_client(&self) -> Option<&Arc<dyn HttpClient>> {
+        self.http_client.as_ref()
     }
 
     /// Check if auto authentication to relays is enabled
@@ -82,6 +353,20 @@ impl SharedState {
             .store(enable, Ordering::SeqCst);
     }
 
+    /// Check if ephemeral authentication is enabled
+    #[inline]
+    pub fn is_ephemeral_auth_enabled(&self) -> bool {
+        self.ephemeral_auth.load(Ordering::SeqCst)
+    }
+
+    /// Authenticate to the relays without the `WRITE` flag with throwaway keys
+    ///
+    /// A new key is generated for each authentication,
+    /// so the read activity isn't linkable to the identity of the signer.
+    pub fn ephemeral_auth(&self, enable: bool) {
+        self.ephemeral_auth.store(enable, Ordering::SeqCst);
+    }
+
     /// Minimum POW difficulty for received events
     ///
     /// All received events must have a difficulty equal or greater than the set one.
@@ -117,6 +402,60 @@ impl SharedState {
         signer.clone().ok_or(SharedStateError::SignerNotConfigured)
     }
 
+    pub(crate) async fn add_outgoing_middleware(&self, middleware: Arc<dyn OutgoingMiddleware>) {
+        let mut middlewares = self.middlewares.write().await;
+        middlewares.push(middleware);
+    }
+
+    /// Apply the outgoing middlewares, re-signing the event if modified
+    pub(crate) async fn apply_outgoing_middlewares(
+        &self,
+        event: Event,
+    ) -> Result<Event, MiddlewareError> {
+        // Clone the list to not keep the lock while processing
+        let middlewares: Vec<Arc<dyn OutgoingMiddleware>> = self.middlewares.read().await.clone();
+
+        if middlewares.is_empty() {
+            return Ok(event);
+        }
+
+        let mut unsigned: UnsignedEvent = UnsignedEvent::from(event.clone());
+        for middleware in middlewares.iter() {
+            match middleware.process(unsigned).await {
+                MiddlewareDecision::Continue(e) => unsigned = e,
+                MiddlewareDecision::Veto { reason } => {
+                    return Err(MiddlewareError::Vetoed { reason })
+                }
+            }
+        }
+
+        // Re-compute the ID: if unchanged, keep the original signature
+        unsigned.id = None;
+        if unsigned.id() == event.id {
+            return Ok(event);
+        }
+
+        let signer = self
+            .signer()
+            .await
+            .map_err(|e| MiddlewareError::Sign(e.to_string()))?;
+        let public_key: PublicKey = signer
+            .get_public_key()
+            .await
+            .map_err(|e| MiddlewareError::Sign(e.to_string()))?;
+
+        if unsigned.pubkey != public_key {
+            return Err(MiddlewareError::Sign(String::from(
+                "can't re-sign the events of other authors",
+            )));
+        }
+
+        unsigned
+            .sign(&signer)
+            .await
+            .map_err(|e| MiddlewareError::Sign(e.to_string()))
+    }
+
     /// Set nostr signer
     pub async fn set_signer<T>(&self, signer: T)
     where
@@ -137,4 +476,197 @@ impl SharedState {
     pub fn filtering(&self) -> &RelayFiltering {
         &self.filtering
     }
+
+    /// Get admission policy
+    #[inline]
+    pub fn admit_policy(&self) -> Option<&Arc<dyn AdmitPolicy>> {
+        self.admit_policy.as_ref()
+    }
+
+    /// Get deduplication policy
+    #[inline]
+    pub fn dedup_policy(&self) -> Option<&Arc<dyn DedupPolicy>> {
+        self.dedup_policy.as_ref()
+    }
+
+    /// Get WebSocket transport
+    #[inline]
+    pub fn transport(&self) -> &Arc<dyn WebSocketTransport> {
+        &self.transport
+    }
+
+    /// Honor the request to vanish of the signer, purging its events from the database
+    ///
+    /// Delete the events of the signer created before the request.
+    /// The requests of the other public keys and the ones targeting specific relays are ignored.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/62.md>
+    pub(crate) async fn handle_vanish_request(&self, event: &Event) -> Result<(), DatabaseError> {
+        // Purge the local database only if the request targets all the relays
+        if VanishTarget::from_event(event) != Some(VanishTarget::AllRelays) {
+            return Ok(());
+        }
+
+        // Get the public key of the local user
+        let signer: Arc<dyn NostrSigner> = match self.signer().await {
+            Ok(signer) => signer,
+            Err(..) => return Ok(()),
+        };
+        let public_key: PublicKey = match signer.get_public_key().await {
+            Ok(public_key) => public_key,
+            Err(e) => {
+                tracing::warn!("Impossible to check the request to vanish: {e}");
+                return Ok(());
+            }
+        };
+
+        if event.pubkey != public_key {
+            return Ok(());
+        }
+
+        tracing::info!("Request to vanish received: purging the events of {public_key}.");
+
+        let filter: Filter = Filter::new().author(public_key).until(event.created_at);
+        self.database.delete(filter).await?;
+
+        // Keep the request (matched by the filter), to not lose the record of having sent it
+        self.database.save_event(event).await?;
+
+        Ok(())
+    }
+
+    /// Handle the request to vanish, only logging the failures
+    ///
+    /// Used after the relays accepted the request: a local database error mustn't fail the send.
+    pub(crate) async fn purge_vanished(&self, event: &Event) {
+        if let Err(e) = self.handle_vanish_request(event).await {
+            tracing::error!(id = %event.id, error = %e, "Impossible to handle the request to vanish.");
+        }
+    }
+
+    /// Track a request to vanish sent without waiting for the relays response
+    ///
+    /// The local events are purged when a relay accepts it (see [`SharedState::vanish_request_accepted`]).
+    pub(crate) fn add_pending_vanish_request(&self, event: &Event) {
+        if event.kind == Kind::RequestToVanish {
+            lock(&self.pending_vanish_requests).insert(event.id, event.clone());
+        }
+    }
+
+    /// Purge the local events if the accepted event is a pending request to vanish
+    pub(crate) async fn vanish_request_accepted(&self, id: &EventId) {
+        let event: Option<Event> = lock(&self.pending_vanish_requests).remove(id);
+        if let Some(event) = event {
+            self.purge_vanished(&event).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Timestamp};
+    use nostr_database::MemoryDatabaseOptions;
+
+    use super::*;
+    use crate::tasks::TaskState;
+
+    #[test]
+    fn test_verify_event_cache() {
+        let state = SharedState::default();
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Test")
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        state.verify_event(&event).unwrap();
+        assert_eq!(state.ingestion_metrics().saved_verifications(), 0);
+
+        // Same event, received from another relay
+        state.verify_event(&event).unwrap();
+        assert_eq!(state.ingestion_metrics().saved_verifications(), 1);
+
+        // Same ID with another signature
+        let other = EventBuilder::text_note("Other")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let mut forged = event.clone();
+        forged.sig = other.sig;
+        assert!(state.verify_event(&forged).is_err());
+        assert_eq!(state.ingestion_metrics().saved_verifications(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_until_shutdown() {
+        let state = SharedState::default();
+
+        let handle = state.spawn("pending", state.until_shutdown(future::pending()));
+        assert!(handle.state().is_running());
+
+        state.signal_shutdown();
+        assert_eq!(handle.join().await, TaskState::Finished);
+    }
+
+    #[tokio::test]
+    async fn test_handle_vanish_request() {
+        let mut state = SharedState::default();
+        state.database = Arc::new(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+            events: true,
+            ..Default::default()
+        }));
+
+        let keys = Keys::generate();
+        let other = Keys::generate();
+        state.set_signer(keys.clone()).await;
+
+        let note = EventBuilder::text_note("Hello")
+            .custom_created_at(Timestamp::from(1_000))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let other_note = EventBuilder::text_note("Hi")
+            .custom_created_at(Timestamp::from(1_000))
+            .sign_with_keys(&other)
+            .unwrap();
+        state.database.save_event(&note).await.unwrap();
+        state.database.save_event(&other_note).await.unwrap();
+
+        // Request of another public key: ignored
+        let request = EventBuilder::request_vanish(VanishTarget::AllRelays, "")
+            .sign_with_keys(&other)
+            .unwrap();
+        state.handle_vanish_request(&request).await.unwrap();
+        assert_eq!(state.database.count(vec![Filter::new()]).await.unwrap(), 2);
+
+        // Request of the local user targeting a single relay: ignored
+        let relay = nostr::RelayUrl::parse("wss://relay.example.com").unwrap();
+        let request = EventBuilder::request_vanish(VanishTarget::Relays(vec![relay]), "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        state.handle_vanish_request(&request).await.unwrap();
+        assert_eq!(state.database.count(vec![Filter::new()]).await.unwrap(), 2);
+
+        // Request of the local user
+        let request = EventBuilder::request_vanish(VanishTarget::AllRelays, "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        state.handle_vanish_request(&request).await.unwrap();
+        assert!(state
+            .database
+            .event_by_id(&note.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(state
+            .database
+            .event_by_id(&other_note.id)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(state
+            .database
+            .event_by_id(&request.id)
+            .await
+            .unwrap()
+            .is_some());
+    }
 }
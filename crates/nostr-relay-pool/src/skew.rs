@@ -0,0 +1,401 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Clock skew detection
+//!
+//! Estimate the difference between the local clock and the clock of the network.
+//!
+//! The samples are kept for each relay. The skew of a relay is measured with:
+//! * the HTTP `Date` header of the NIP11 document fetches (`nip11` feature), if available;
+//! * otherwise, the median of the `created_at` of the events received after the EOSE (live events),
+//!   so a few authors with a wrong clock don't affect it.
+//!
+//! The skew is the median of the skews of the relays, so a single relay can't move it.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use nostr::types::time::TimeProvider;
+use nostr::{RelayUrl, Timestamp};
+use tokio::sync::broadcast;
+
+const MAX_SAMPLES: usize = 50;
+const DEFAULT_THRESHOLD: Duration = Duration::from_secs(60 * 5);
+const DEFAULT_MIN_SAMPLES: usize = 5;
+const DEFAULT_MAX_CORRECTION: Duration = Duration::from_secs(60 * 60);
+
+/// Clock skew options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSkewOptions {
+    threshold: Duration,
+    min_samples: usize,
+    auto_correct: bool,
+    max_correction: Duration,
+}
+
+impl Default for ClockSkewOptions {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            min_samples: DEFAULT_MIN_SAMPLES,
+            auto_correct: false,
+            max_correction: DEFAULT_MAX_CORRECTION,
+        }
+    }
+}
+
+impl ClockSkewOptions {
+    /// New default options
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skew that triggers the alert and the correction (default: 5 min)
+    #[inline]
+    pub fn threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Min number of event samples before estimating the skew of a relay without the HTTP `Date` (default: 5)
+    #[inline]
+    pub fn min_samples(mut self, min_samples: usize) -> Self {
+        self.min_samples = min_samples.max(1);
+        self
+    }
+
+    /// Correct the `created_at` of the outgoing events when the skew exceeds the threshold (default: false)
+    #[inline]
+    pub fn auto_correct(mut self, enable: bool) -> Self {
+        self.auto_correct = enable;
+        self
+    }
+
+    /// Max correction applied to the local clock (default: 1 hour)
+    ///
+    /// The estimated skew is still reported as is.
+    #[inline]
+    pub fn max_correction(mut self, max: Duration) -> Self {
+        self.max_correction = max;
+        self
+    }
+
+    /// Check if the auto-correction is enabled
+    #[inline]
+    pub fn is_auto_correct_enabled(&self) -> bool {
+        self.auto_correct
+    }
+}
+
+/// Clock skew alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockSkewAlert {
+    /// Estimated skew, in seconds (positive if the local clock is behind)
+    pub skew: i64,
+    /// If the outgoing `created_at` values are being corrected
+    pub corrected: bool,
+}
+
+/// Samples of a relay
+#[derive(Debug, Default)]
+struct RelaySamples {
+    /// Skew measured with the HTTP `Date` header
+    server_time: Option<i64>,
+    /// Skews measured with the live events
+    events: VecDeque<i64>,
+}
+
+impl RelaySamples {
+    fn skew(&self, min_samples: usize) -> Option<i64> {
+        if let Some(skew) = self.server_time {
+            return Some(skew);
+        }
+
+        if self.events.len() < min_samples {
+            return None;
+        }
+
+        let mut sorted: Vec<i64> = self.events.iter().copied().collect();
+        Some(median(&mut sorted))
+    }
+}
+
+/// Median of a not empty list
+fn median(values: &mut [i64]) -> i64 {
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+/// Clock skew detector
+///
+/// When auto-correction is enabled, it's also the [`TimeProvider`] of the relays:
+/// the estimated skew, capped to the [`ClockSkewOptions::max_correction`], is added to the local clock if it exceeds the threshold.
+#[derive(Debug)]
+pub struct ClockSkew {
+    local: Arc<dyn TimeProvider>,
+    opts: ClockSkewOptions,
+    samples: Mutex<HashMap<RelayUrl, RelaySamples>>,
+    skew: AtomicI64,
+    estimated: AtomicBool,
+    alerted: AtomicBool,
+    alert_sender: broadcast::Sender<ClockSkewAlert>,
+}
+
+impl ClockSkew {
+    /// New clock skew detector, measuring the skew of the `local` clock
+    pub fn new(local: Arc<dyn TimeProvider>, opts: ClockSkewOptions) -> Self {
+        let (alert_sender, _) = broadcast::channel(16);
+        Self {
+            local,
+            opts,
+            samples: Mutex::new(HashMap::new()),
+            skew: AtomicI64::new(0),
+            estimated: AtomicBool::new(false),
+            alerted: AtomicBool::new(false),
+            alert_sender,
+        }
+    }
+
+    /// Get options
+    #[inline]
+    pub fn options(&self) -> &ClockSkewOptions {
+        &self.opts
+    }
+
+    /// Get estimated skew, in seconds (positive if the local clock is behind)
+    ///
+    /// Return `None` if there aren't enough samples yet.
+    pub fn skew(&self) -> Option<i64> {
+        if self.estimated.load(Ordering::SeqCst) {
+            Some(self.skew.load(Ordering::SeqCst))
+        } else {
+            None
+        }
+    }
+
+    /// Check if the estimated skew exceeds the threshold
+    pub fn exceeds_threshold(&self) -> bool {
+        match self.skew() {
+            Some(skew) => skew.unsigned_abs() > self.opts.threshold.as_secs(),
+            None => false,
+        }
+    }
+
+    /// Get alert listener
+    ///
+    /// An alert is sent each time the skew exceeds the threshold.
+    #[inline]
+    pub fn alerts(&self) -> broadcast::Receiver<ClockSkewAlert> {
+        self.alert_sender.subscribe()
+    }
+
+    /// Add the server time of a relay (i.e. the HTTP `Date` header)
+    ///
+    /// Preferred to the event samples of the relay.
+    pub fn add_server_time(&self, relay_url: &RelayUrl, remote: Timestamp) {
+        let sample: i64 = self.sample(remote);
+        self.update(relay_url, |samples| samples.server_time = Some(sample));
+    }
+
+    /// Add the `created_at` of a live event received from a relay
+    pub fn add_event_sample(&self, relay_url: &RelayUrl, created_at: Timestamp) {
+        let sample: i64 = self.sample(created_at);
+        self.update(relay_url, |samples| {
+            samples.events.push_back(sample);
+            while samples.events.len() > MAX_SAMPLES {
+                samples.events.pop_front();
+            }
+        });
+    }
+
+    /// Remove the samples of a relay (i.e. removed from the pool)
+    pub fn remove_relay(&self, relay_url: &RelayUrl) {
+        let skew: Option<i64> = {
+            let mut samples = self.lock();
+            samples.remove(relay_url);
+            self.estimate(&samples)
+        };
+        self.set_skew(skew);
+    }
+
+    #[inline]
+    fn sample(&self, remote: Timestamp) -> i64 {
+        let local: Timestamp = self.local.now();
+        remote.as_u64() as i64 - local.as_u64() as i64
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<RelayUrl, RelaySamples>> {
+        match self.samples.lock() {
+            Ok(samples) => samples,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Median of the skews of the relays
+    fn estimate(&self, samples: &HashMap<RelayUrl, RelaySamples>) -> Option<i64> {
+        let mut skews: Vec<i64> = samples
+            .values()
+            .filter_map(|samples| samples.skew(self.opts.min_samples))
+            .collect();
+
+        if skews.is_empty() {
+            return None;
+        }
+
+        Some(median(&mut skews))
+    }
+
+    fn update<F>(&self, relay_url: &RelayUrl, f: F)
+    where
+        F: FnOnce(&mut RelaySamples),
+    {
+        let skew: Option<i64> = {
+            let mut samples = self.lock();
+            f(samples.entry(relay_url.clone()).or_default());
+            self.estimate(&samples)
+        };
+        self.set_skew(skew);
+    }
+
+    fn set_skew(&self, skew: Option<i64>) {
+        let skew: i64 = match skew {
+            Some(skew) => skew,
+            None => {
+                self.estimated.store(false, Ordering::SeqCst);
+                return;
+            }
+        };
+
+        self.skew.store(skew, Ordering::SeqCst);
+        self.estimated.store(true, Ordering::SeqCst);
+
+        if self.exceeds_threshold() {
+            // Alert only when the threshold is crossed
+            if !self.alerted.swap(true, Ordering::SeqCst) {
+                tracing::warn!(
+                    skew = skew,
+                    corrected = self.opts.auto_correct,
+                    "Local clock is skewed."
+                );
+
+                let _ = self.alert_sender.send(ClockSkewAlert {
+                    skew,
+                    corrected: self.opts.auto_correct,
+                });
+            }
+        } else {
+            self.alerted.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+impl TimeProvider for ClockSkew {
+    fn now(&self) -> Timestamp {
+        let now: Timestamp = self.local.now();
+
+        if !self.opts.auto_correct || !self.exceeds_threshold() {
+            return now;
+        }
+
+        // Cap the correction
+        let max: i64 = i64::try_from(self.opts.max_correction.as_secs()).unwrap_or(i64::MAX);
+        let skew: i64 = self.skew.load(Ordering::SeqCst).clamp(-max, max);
+        let secs: u64 = if skew >= 0 {
+            now.as_u64().saturating_add(skew.unsigned_abs())
+        } else {
+            now.as_u64().saturating_sub(skew.unsigned_abs())
+        };
+        Timestamp::from_secs(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::types::time::FixedTimeProvider;
+
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn detector(opts: ClockSkewOptions) -> ClockSkew {
+        let local = Arc::new(FixedTimeProvider::new(Timestamp::from_secs(NOW)));
+        ClockSkew::new(local, opts)
+    }
+
+    fn relay(n: u8) -> RelayUrl {
+        RelayUrl::parse(&format!("wss://relay{n}.example.com")).unwrap()
+    }
+
+    #[test]
+    fn test_skew_estimation() {
+        let skew = detector(ClockSkewOptions::new().min_samples(3));
+        let url = relay(1);
+
+        skew.add_event_sample(&url, Timestamp::from_secs(NOW + 3600));
+        skew.add_event_sample(&url, Timestamp::from_secs(NOW + 3601));
+        assert_eq!(skew.skew(), None);
+
+        // An author with a very wrong clock
+        skew.add_event_sample(&url, Timestamp::from_secs(0));
+        assert_eq!(skew.skew(), Some(3600));
+        assert!(skew.exceeds_threshold());
+
+        // Not corrected
+        assert_eq!(skew.now(), Timestamp::from_secs(NOW));
+
+        // The server time is preferred to the events
+        skew.add_server_time(&url, Timestamp::from_secs(NOW + 10));
+        assert_eq!(skew.skew(), Some(10));
+
+        skew.remove_relay(&url);
+        assert_eq!(skew.skew(), None);
+    }
+
+    #[test]
+    fn test_skew_per_relay() {
+        let skew = detector(ClockSkewOptions::new().min_samples(1));
+
+        // A single relay can't move the skew
+        skew.add_server_time(&relay(1), Timestamp::from_secs(NOW + 1));
+        skew.add_server_time(&relay(2), Timestamp::from_secs(NOW - 1));
+        for _ in 0..MAX_SAMPLES {
+            skew.add_event_sample(&relay(3), Timestamp::from_secs(NOW + 86400));
+        }
+        assert_eq!(skew.skew(), Some(1));
+        assert!(!skew.exceeds_threshold());
+    }
+
+    #[test]
+    fn test_skew_alert_and_correction() {
+        let skew = detector(ClockSkewOptions::new().min_samples(1).auto_correct(true));
+        let mut alerts = skew.alerts();
+        let url = relay(1);
+
+        // Under threshold
+        skew.add_event_sample(&url, Timestamp::from_secs(NOW - 10));
+        assert_eq!(skew.now(), Timestamp::from_secs(NOW));
+        assert!(alerts.try_recv().is_err());
+
+        skew.add_event_sample(&url, Timestamp::from_secs(NOW - 7200));
+        skew.add_event_sample(&url, Timestamp::from_secs(NOW - 7200));
+        assert_eq!(
+            alerts.try_recv().unwrap(),
+            ClockSkewAlert {
+                skew: -7200,
+                corrected: true
+            }
+        );
+
+        // The correction is capped
+        assert_eq!(skew.skew(), Some(-7200));
+        assert_eq!(skew.now(), Timestamp::from_secs(NOW - 3600));
+
+        // Only one alert
+        skew.add_event_sample(&url, Timestamp::from_secs(NOW - 7200));
+        assert!(alerts.try_recv().is_err());
+    }
+}
@@ -0,0 +1,905 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Spam filter
+//!
+//! Heuristic spam detection for the incoming events, built on top of the [`AdmitPolicy`].
+//!
+//! The [`SpamFilter`] runs a list of [`SpamScorer`]s: each scorer returns a score,
+//! and the event is rejected if a score reaches the threshold of its scorer.
+//!
+//! The admission policy is consulted before the signature verification,
+//! so the stateful scorers only record the events in [`SpamScorer::record`],
+//! called after the verification of the admitted events.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use nostr::types::time::{IntoTimeProvider, SystemTimeProvider, TimeProvider};
+use nostr::{Event, EventId, Kind, PublicKey, RelayUrl, Timestamp};
+
+use crate::policy::{Admission, AdmitPolicy};
+
+const DEFAULT_THRESHOLD: f64 = 1.0;
+const DEFAULT_DUPLICATE_WINDOW: Duration = Duration::from_secs(60 * 10);
+const DEFAULT_MAX_COPIES: usize = 3;
+const DEFAULT_MAX_TAGS: usize = 100;
+const DEFAULT_MAX_MENTIONS: usize = 10;
+const DEFAULT_MIN_KEY_AGE: Duration = Duration::from_secs(60 * 60 * 24);
+const DEFAULT_DEDUP_WINDOW: Duration = Duration::from_secs(60 * 60);
+const MAX_TRACKED_ENTRIES: usize = 10_000;
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Spam scorer
+///
+/// The score is `0.0` for the events that look legit and grows with the spam likelihood.
+/// The built-in scorers return the number of units over their limit (i.e. the extra tags).
+///
+/// The `now` argument is the current local time, taken from the time provider of the [`SpamFilter`].
+pub trait SpamScorer: fmt::Debug + Send + Sync {
+    /// Scorer name, used for the rejection reason and the statistics
+    fn name(&self) -> &str;
+
+    /// Score the [`Event`]
+    ///
+    /// Called before the signature verification: must not change the state of the scorer.
+    fn score(&self, event: &Event, now: Timestamp) -> f64;
+
+    /// Record the admitted [`Event`], after the signature verification
+    fn record(&self, _event: &Event, _now: Timestamp) {}
+}
+
+/// Spam scorer statistics
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpamScorerStats {
+    /// Scorer name
+    pub name: String,
+    /// Threshold
+    pub threshold: f64,
+    /// Number of scored events
+    pub checked: u64,
+    /// Number of events that reached the threshold
+    pub flagged: u64,
+}
+
+#[derive(Debug)]
+struct ScorerEntry {
+    scorer: Arc<dyn SpamScorer>,
+    threshold: f64,
+    checked: AtomicU64,
+    flagged: AtomicU64,
+}
+
+/// Spam filter
+///
+/// Implements [`AdmitPolicy`], so can be set as the admission policy of the pool.
+#[derive(Debug)]
+pub struct SpamFilter {
+    scorers: Vec<ScorerEntry>,
+    time_provider: Arc<dyn TimeProvider>,
+}
+
+impl Default for SpamFilter {
+    fn default() -> Self {
+        Self {
+            scorers: Vec::new(),
+            time_provider: Arc::new(SystemTimeProvider),
+        }
+    }
+}
+
+impl SpamFilter {
+    /// New empty spam filter
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the time provider used by the scorers
+    ///
+    /// Use the same time provider of the client. By default [`SystemTimeProvider`] is used.
+    #[inline]
+    pub fn time_provider<T>(mut self, provider: T) -> Self
+    where
+        T: IntoTimeProvider,
+    {
+        self.time_provider = provider.into_time_provider();
+        self
+    }
+
+    /// Spam filter with the default built-in scorers
+    ///
+    /// [`DuplicateContentScorer`], [`ExcessiveTagsScorer`] and [`MassMentionScorer`],
+    /// all with the default options and thresholds.
+    pub fn standard() -> Self {
+        Self::new()
+            .scorer(DuplicateContentScorer::default())
+            .scorer(ExcessiveTagsScorer::default())
+            .scorer(MassMentionScorer::default())
+    }
+
+    /// Add scorer, with the default threshold (1.0)
+    #[inline]
+    pub fn scorer<T>(self, scorer: T) -> Self
+    where
+        T: SpamScorer + 'static,
+    {
+        self.scorer_with_threshold(scorer, DEFAULT_THRESHOLD)
+    }
+
+    /// Add scorer with a custom threshold
+    ///
+    /// The event is rejected when the score is greater than or equal to the threshold.
+    pub fn scorer_with_threshold<T>(mut self, scorer: T, threshold: f64) -> Self
+    where
+        T: SpamScorer + 'static,
+    {
+        self.scorers.push(ScorerEntry {
+            scorer: Arc::new(scorer),
+            threshold,
+            checked: AtomicU64::new(0),
+            flagged: AtomicU64::new(0),
+        });
+        self
+    }
+
+    /// Get the statistics of the scorers, in the same order in which they were added
+    pub fn stats(&self) -> Vec<SpamScorerStats> {
+        self.scorers
+            .iter()
+            .map(|entry| SpamScorerStats {
+                name: entry.scorer.name().to_string(),
+                threshold: entry.threshold,
+                checked: entry.checked.load(Ordering::SeqCst),
+                flagged: entry.flagged.load(Ordering::SeqCst),
+            })
+            .collect()
+    }
+
+    /// Reset the statistics
+    pub fn reset_stats(&self) {
+        for entry in self.scorers.iter() {
+            entry.checked.store(0, Ordering::SeqCst);
+            entry.flagged.store(0, Ordering::SeqCst);
+        }
+    }
+
+    /// Check the [`Event`]
+    ///
+    /// All the scorers are run, to keep complete statistics.
+    /// Return the name of the first scorer that flagged the event, if any.
+    ///
+    /// Doesn't record the event: call [`SpamFilter::record`] after verifying its signature.
+    pub fn check(&self, event: &Event) -> Option<&str> {
+        let now: Timestamp = self.time_provider.now();
+        let mut flagged_by: Option<&str> = None;
+
+        for entry in self.scorers.iter() {
+            entry.checked.fetch_add(1, Ordering::SeqCst);
+
+            if entry.scorer.score(event, now) >= entry.threshold {
+                entry.flagged.fetch_add(1, Ordering::SeqCst);
+                flagged_by.get_or_insert(entry.scorer.name());
+            }
+        }
+
+        flagged_by
+    }
+
+    /// Record the verified [`Event`] in the stateful scorers
+    pub fn record(&self, event: &Event) {
+        let now: Timestamp = self.time_provider.now();
+        for entry in self.scorers.iter() {
+            entry.scorer.record(event, now);
+        }
+    }
+}
+
+impl AdmitPolicy for SpamFilter {
+    fn admit(&self, event: &Event, relay_url: &RelayUrl) -> Admission {
+        match self.check(event) {
+            Some(name) => {
+                tracing::debug!(id = %event.id, %relay_url, scorer = %name, "Event flagged as spam.");
+                Admission::reject(format!("spam: {name}"))
+            }
+            None => Admission::Accept,
+        }
+    }
+
+    #[inline]
+    fn admitted(&self, event: &Event, _relay_url: &RelayUrl) {
+        self.record(event);
+    }
+}
+
+/// Duplicate content scorer
+///
+/// Detect the bursts of events with the same content (i.e. the same message posted by many keys).
+/// The same event received from many relays is counted once.
+/// The time window is checked against the first-seen local time, not the `created_at` chosen by the authors.
+///
+/// Score: number of copies over the `max_copies`, within the time window.
+#[derive(Debug)]
+pub struct DuplicateContentScorer {
+    window: Duration,
+    max_copies: usize,
+    seen: Mutex<HashMap<u64, VecDeque<(EventId, Timestamp)>>>,
+}
+
+impl Default for DuplicateContentScorer {
+    fn default() -> Self {
+        Self::new(DEFAULT_DUPLICATE_WINDOW, DEFAULT_MAX_COPIES)
+    }
+}
+
+impl DuplicateContentScorer {
+    /// New duplicate content scorer
+    ///
+    /// By default, up to 3 copies within 10 min are allowed.
+    pub fn new(window: Duration, max_copies: usize) -> Self {
+        Self {
+            window,
+            max_copies,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl DuplicateContentScorer {
+    /// Hash the trimmed content
+    ///
+    /// Return `None` for the short contents (i.e. reactions), that are often duplicated.
+    fn key(event: &Event) -> Option<u64> {
+        if event.content.chars().count() < 16 {
+            return None;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        event.content.trim().hash(&mut hasher);
+        Some(hasher.finish())
+    }
+}
+
+impl SpamScorer for DuplicateContentScorer {
+    fn name(&self) -> &str {
+        "duplicate-content"
+    }
+
+    fn score(&self, event: &Event, now: Timestamp) -> f64 {
+        let hash: u64 = match Self::key(event) {
+            Some(hash) => hash,
+            None => return 0.0,
+        };
+
+        let since: Timestamp = now - self.window;
+
+        let seen = lock(&self.seen);
+
+        // The other copies seen within the window
+        let copies: usize = seen.get(&hash).map_or(0, |copies| {
+            copies
+                .iter()
+                .filter(|(id, seen_at)| *id != event.id && *seen_at >= since)
+                .count()
+        });
+
+        // Plus this event
+        let copies: usize = copies + 1;
+
+        copies.saturating_sub(self.max_copies) as f64
+    }
+
+    fn record(&self, event: &Event, now: Timestamp) {
+        let hash: u64 = match Self::key(event) {
+            Some(hash) => hash,
+            None => return,
+        };
+
+        let since: Timestamp = now - self.window;
+
+        let mut seen = lock(&self.seen);
+
+        // Cleanup expired entries
+        if seen.len() >= MAX_TRACKED_ENTRIES {
+            seen.retain(|_, copies| copies.back().is_some_and(|(_, t)| *t >= since));
+
+            // Still full: forget everything
+            if seen.len() >= MAX_TRACKED_ENTRIES {
+                seen.clear();
+            }
+        }
+
+        let copies = seen.entry(hash).or_default();
+        copies.retain(|(_, t)| *t >= since);
+
+        if !copies.iter().any(|(id, _)| *id == event.id) {
+            copies.push_back((event.id, now));
+        }
+    }
+}
+
+/// Content deduplication scorer
+///
+/// Secondary deduplication for the near-identical events re-broadcast with unique IDs:
+/// the events are keyed by a hash of the author, the kind and the normalized content
+/// (lowercase, only letters and digits, so whitespaces, punctuation and invisible chars are ignored).
+/// The same event received from many relays isn't a duplicate.
+/// Only the verified events are recorded, so a forged event can't flag the next genuine one.
+///
+/// Replaceable, addressable and ephemeral events, and the events without letters or digits
+/// in the content (i.e. reactions), are skipped.
+///
+/// Score: `1.0` if another event with the same key was seen within the time window, `0.0` otherwise.
+#[derive(Debug)]
+pub struct ContentDedupScorer {
+    window: Duration,
+    seen: Mutex<HashMap<u64, (EventId, Timestamp)>>,
+}
+
+impl Default for ContentDedupScorer {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_WINDOW)
+    }
+}
+
+impl ContentDedupScorer {
+    /// New content deduplication scorer (default window: 1 hour)
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hash the author, the kind and the normalized content
+    ///
+    /// Return `None` for the skipped kinds or if the normalized content is empty.
+    fn key(event: &Event) -> Option<u64> {
+        if event.kind.is_replaceable()
+            || event.kind.is_parameterized_replaceable()
+            || event.kind.is_ephemeral()
+        {
+            return None;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        event.pubkey.hash(&mut hasher);
+        event.kind.hash(&mut hasher);
+
+        let mut empty: bool = true;
+        for c in event
+            .content
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+        {
+            c.hash(&mut hasher);
+            empty = false;
+        }
+
+        if empty {
+            return None;
+        }
+
+        Some(hasher.finish())
+    }
+}
+
+impl SpamScorer for ContentDedupScorer {
+    fn name(&self) -> &str {
+        "content-dedup"
+    }
+
+    fn score(&self, event: &Event, _now: Timestamp) -> f64 {
+        let key: u64 = match Self::key(event) {
+            Some(key) => key,
+            None => return 0.0,
+        };
+
+        let since: Timestamp = event.created_at - self.window;
+        let until: Timestamp = event.created_at + self.window;
+
+        match lock(&self.seen).get(&key) {
+            Some((id, created_at)) if *id != event.id => {
+                if *created_at >= since && *created_at <= until {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn record(&self, event: &Event, _now: Timestamp) {
+        let key: u64 = match Self::key(event) {
+            Some(key) => key,
+            None => return,
+        };
+
+        let since: Timestamp = event.created_at - self.window;
+
+        let mut seen = lock(&self.seen);
+
+        // Keep track of the most recent event
+        if seen
+            .get(&key)
+            .is_some_and(|(_, created_at)| *created_at > event.created_at)
+        {
+            return;
+        }
+
+        // Cleanup expired entries
+        if seen.len() >= MAX_TRACKED_ENTRIES {
+            seen.retain(|_, (_, t)| *t >= since);
+
+            // Still full: forget everything
+            if seen.len() >= MAX_TRACKED_ENTRIES {
+                seen.clear();
+            }
+        }
+
+        seen.insert(key, (event.id, event.created_at));
+    }
+}
+
+/// Excessive tags scorer
+///
+/// Score: number of tags over the `max_tags`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExcessiveTagsScorer {
+    max_tags: usize,
+    exclude_kinds: [Kind; 2],
+}
+
+impl Default for ExcessiveTagsScorer {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TAGS)
+    }
+}
+
+impl ExcessiveTagsScorer {
+    /// New excessive tags scorer (default max tags: 100)
+    ///
+    /// Contact lists and mute lists are excluded, since they legitimately have many tags.
+    pub fn new(max_tags: usize) -> Self {
+        Self {
+            max_tags,
+            exclude_kinds: [Kind::ContactList, Kind::MuteList],
+        }
+    }
+}
+
+impl SpamScorer for ExcessiveTagsScorer {
+    fn name(&self) -> &str {
+        "excessive-tags"
+    }
+
+    fn score(&self, event: &Event, _now: Timestamp) -> f64 {
+        if self.exclude_kinds.contains(&event.kind) {
+            return 0.0;
+        }
+
+        event.tags.len().saturating_sub(self.max_tags) as f64
+    }
+}
+
+/// Known spam patterns scorer
+///
+/// Flag all the events of some kinds or the events of a kind with a known content pattern
+/// (case-insensitive substring).
+///
+/// Score: `1.0` if matches, `0.0` otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KindPatternScorer {
+    kinds: HashSet<Kind>,
+    patterns: Vec<(Option<Kind>, String)>,
+}
+
+impl KindPatternScorer {
+    /// New empty patterns scorer
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag all the events of the kind
+    #[inline]
+    pub fn kind(mut self, kind: Kind) -> Self {
+        self.kinds.insert(kind);
+        self
+    }
+
+    /// Flag the events that contain the pattern
+    ///
+    /// If `kind` is `None`, the pattern is checked for all the kinds.
+    pub fn pattern<S>(mut self, kind: Option<Kind>, pattern: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.patterns.push((kind, pattern.as_ref().to_lowercase()));
+        self
+    }
+}
+
+impl SpamScorer for KindPatternScorer {
+    fn name(&self) -> &str {
+        "kind-pattern"
+    }
+
+    fn score(&self, event: &Event, _now: Timestamp) -> f64 {
+        if self.kinds.contains(&event.kind) {
+            return 1.0;
+        }
+
+        let mut content: Option<String> = None;
+
+        for (kind, pattern) in self.patterns.iter() {
+            if kind.is_some_and(|kind| kind != event.kind) {
+                continue;
+            }
+
+            let content: &str = content.get_or_insert_with(|| event.content.to_lowercase());
+            if content.contains(pattern.as_str()) {
+                return 1.0;
+            }
+        }
+
+        0.0
+    }
+}
+
+/// New key with mass mentions scorer
+///
+/// Flag the keys without history that mention many users (a common pattern of the spam bots).
+/// A key is considered established when its first event has been seen locally at least `min_key_age` ago
+/// (the `created_at` is chosen by the author, so it can't be trusted).
+///
+/// Score: number of mentioned public keys over the `max_mentions`, only for the new keys.
+#[derive(Debug)]
+pub struct MassMentionScorer {
+    max_mentions: usize,
+    min_key_age: Duration,
+    established: Mutex<HashSet<PublicKey>>,
+    first_seen: Mutex<HashMap<PublicKey, Timestamp>>,
+}
+
+impl Default for MassMentionScorer {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MENTIONS, DEFAULT_MIN_KEY_AGE)
+    }
+}
+
+impl MassMentionScorer {
+    /// New mass mention scorer
+    ///
+    /// By default, a key is new if first seen less than 1 day ago and can mention up to 10 users.
+    pub fn new(max_mentions: usize, min_key_age: Duration) -> Self {
+        Self {
+            max_mentions,
+            min_key_age,
+            established: Mutex::new(HashSet::new()),
+            first_seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Mark the public key as established (i.e. a contact)
+    pub fn establish(&self, public_key: PublicKey) {
+        let mut established = lock(&self.established);
+        if established.len() < MAX_TRACKED_ENTRIES {
+            established.insert(public_key);
+        }
+    }
+
+    fn is_established(&self, public_key: &PublicKey, now: Timestamp) -> bool {
+        if lock(&self.established).contains(public_key) {
+            return true;
+        }
+
+        lock(&self.first_seen)
+            .get(public_key)
+            .is_some_and(|first_seen| *first_seen <= now - self.min_key_age)
+    }
+}
+
+impl SpamScorer for MassMentionScorer {
+    fn name(&self) -> &str {
+        "mass-mention"
+    }
+
+    fn score(&self, event: &Event, now: Timestamp) -> f64 {
+        if self.is_established(&event.pubkey, now) {
+            return 0.0;
+        }
+
+        // Contact lists and mute lists aren't mentions
+        if matches!(event.kind, Kind::ContactList | Kind::MuteList) {
+            return 0.0;
+        }
+
+        let mentions: usize = event
+            .tags
+            .public_keys()
+            .filter(|p| **p != event.pubkey)
+            .collect::<HashSet<_>>()
+            .len();
+        mentions.saturating_sub(self.max_mentions) as f64
+    }
+
+    fn record(&self, event: &Event, now: Timestamp) {
+        let mut first_seen = lock(&self.first_seen);
+
+        if first_seen.contains_key(&event.pubkey) {
+            return;
+        }
+
+        // Move the keys old enough to the established ones
+        if first_seen.len() >= MAX_TRACKED_ENTRIES {
+            let until: Timestamp = now - self.min_key_age;
+            let mut established = lock(&self.established);
+            first_seen.retain(|public_key, first_seen| {
+                if *first_seen <= until && established.len() < MAX_TRACKED_ENTRIES {
+                    established.insert(*public_key);
+                    return false;
+                }
+                true
+            });
+
+            // Still full: don't track the new keys
+            if first_seen.len() >= MAX_TRACKED_ENTRIES {
+                return;
+            }
+        }
+
+        first_seen.insert(event.pubkey, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr::{EventBuilder, Keys, Tag};
+
+    use super::*;
+
+    #[test]
+    fn test_duplicate_content() {
+        let scorer = DuplicateContentScorer::new(Duration::from_secs(60), 2);
+        let content = "Buy cheap followers at example.com";
+        let now = Timestamp::from(1_700_000_000);
+
+        let mut scores = Vec::new();
+        for _ in 0..4 {
+            let event = EventBuilder::text_note(content)
+                .sign_with_keys(&Keys::generate())
+                .unwrap();
+            scores.push(scorer.score(&event, now));
+            scorer.record(&event, now);
+
+            // The same event from another relay
+            assert_eq!(scorer.score(&event, now), *scores.last().unwrap());
+        }
+
+        assert_eq!(scores, vec![0.0, 0.0, 1.0, 2.0]);
+
+        // Outside the window (first-seen time, not `created_at`)
+        let event = EventBuilder::text_note(content)
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        assert_eq!(scorer.score(&event, now + Duration::from_secs(120)), 0.0);
+    }
+
+    #[test]
+    fn test_duplicate_content_not_recorded() {
+        let scorer = DuplicateContentScorer::new(Duration::from_secs(60), 1);
+        let content = "Buy cheap followers at example.com";
+        let now = Timestamp::from(1_700_000_000);
+
+        // Scored but not recorded (i.e. forged signatures)
+        for _ in 0..3 {
+            let event = EventBuilder::text_note(content)
+                .sign_with_keys(&Keys::generate())
+                .unwrap();
+            assert_eq!(scorer.score(&event, now), 0.0);
+        }
+    }
+
+    #[test]
+    fn test_content_dedup() {
+        let scorer = ContentDedupScorer::new(Duration::from_secs(60));
+        let keys = Keys::generate();
+        let now = Timestamp::now();
+
+        let event = EventBuilder::text_note("Buy cheap followers at example.com")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&event, now), 0.0);
+        scorer.record(&event, now);
+
+        // The same event from another relay
+        assert_eq!(scorer.score(&event, now), 0.0);
+
+        // Near-identical content, new ID
+        let copy = EventBuilder::text_note("buy  CHEAP followers at example.com!!\u{200B}")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&copy, now), 1.0);
+
+        // Same content from another author
+        let other = EventBuilder::text_note("Buy cheap followers at example.com")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        assert_eq!(scorer.score(&other, now), 0.0);
+
+        // Outside the time window
+        let old = EventBuilder::text_note("Buy cheap followers at example.com")
+            .custom_created_at(event.created_at - Duration::from_secs(120))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&old, now), 0.0);
+
+        // Reactions are skipped
+        let reaction = EventBuilder::new(Kind::Reaction, "+")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&reaction, now), 0.0);
+        scorer.record(&reaction, now);
+        let reaction = EventBuilder::new(Kind::Reaction, "+")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&reaction, now), 0.0);
+    }
+
+    #[test]
+    fn test_content_dedup_not_recorded() {
+        let scorer = ContentDedupScorer::new(Duration::from_secs(60));
+        let keys = Keys::generate();
+        let now = Timestamp::now();
+
+        // Scored but not recorded (i.e. a forged signature)
+        let forged = EventBuilder::text_note("GM nostr, have a nice day")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&forged, now), 0.0);
+
+        // The genuine event isn't flagged
+        let event = EventBuilder::text_note("gm nostr! have a nice day")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&event, now), 0.0);
+    }
+
+    #[test]
+    fn test_excessive_tags() {
+        let keys = Keys::generate();
+        let scorer = ExcessiveTagsScorer::new(2);
+
+        let tags = (0..5).map(|i| Tag::hashtag(format!("tag{i}")));
+        let event = EventBuilder::text_note("Test")
+            .tags(tags.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&event, Timestamp::now()), 3.0);
+
+        let event = EventBuilder::new(Kind::ContactList, "")
+            .tags(tags)
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&event, Timestamp::now()), 0.0);
+    }
+
+    #[test]
+    fn test_kind_pattern() {
+        let keys = Keys::generate();
+        let scorer = KindPatternScorer::new()
+            .kind(Kind::Custom(9999))
+            .pattern(Some(Kind::TextNote), "FREE airdrop");
+
+        let event = EventBuilder::text_note("Claim your free AIRDROP now")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&event, Timestamp::now()), 1.0);
+
+        let event = EventBuilder::new(Kind::Custom(9999), "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&event, Timestamp::now()), 1.0);
+
+        let event = EventBuilder::new(Kind::LongFormTextNote, "free airdrop")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&event, Timestamp::now()), 0.0);
+    }
+
+    #[test]
+    fn test_mass_mention() {
+        let scorer = MassMentionScorer::new(2, Duration::from_secs(60 * 60));
+        let mentions: Vec<Tag> = (0..4)
+            .map(|_| Tag::public_key(Keys::generate().public_key()))
+            .collect();
+        let now = Timestamp::from(1_700_000_000);
+
+        // New key
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hey")
+            .tags(mentions.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&event, now), 2.0);
+
+        // An old `created_at` doesn't make the key established
+        let old = EventBuilder::text_note("Old")
+            .custom_created_at(now - Duration::from_secs(60 * 60 * 24))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(scorer.score(&old, now), 0.0);
+        scorer.record(&old, now);
+        assert_eq!(scorer.score(&event, now), 2.0);
+
+        // Key first seen more than 1 hour ago
+        let later = now + Duration::from_secs(60 * 60);
+        assert_eq!(scorer.score(&event, later), 0.0);
+
+        // Contact
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("Hey")
+            .tags(mentions)
+            .sign_with_keys(&keys)
+            .unwrap();
+        scorer.establish(keys.public_key());
+        assert_eq!(scorer.score(&event, now), 0.0);
+    }
+
+    #[test]
+    fn test_spam_filter() {
+        let url = RelayUrl::parse("wss://relay.example.com").unwrap();
+        let filter = SpamFilter::new()
+            .scorer(KindPatternScorer::new().pattern(None, "spam"))
+            .scorer_with_threshold(ExcessiveTagsScorer::new(0), 3.0);
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("This is spam")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            filter.admit(&event, &url),
+            Admission::reject("spam: kind-pattern")
+        );
+
+        let event = EventBuilder::text_note("Hello")
+            .tags([Tag::hashtag("a"), Tag::hashtag("b")])
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(filter.admit(&event, &url).is_accepted());
+
+        // All the scorers are run
+        let event = EventBuilder::text_note("More spam")
+            .tags([Tag::hashtag("a"), Tag::hashtag("b"), Tag::hashtag("c")])
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            filter.admit(&event, &url),
+            Admission::reject("spam: kind-pattern")
+        );
+
+        let stats = filter.stats();
+        assert_eq!(stats[0].checked, 3);
+        assert_eq!(stats[0].flagged, 2);
+        assert_eq!(stats[1].checked, 3);
+        assert_eq!(stats[1].flagged, 1);
+
+        filter.reset_stats();
+        assert_eq!(filter.stats()[0].checked, 0);
+    }
+}
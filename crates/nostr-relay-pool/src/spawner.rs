@@ -0,0 +1,142 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Spawner
+//!
+//! Control where the background tasks (connections, notifications handlers, etc.) are spawned.
+//!
+//! With the `nostr_local` cfg the futures aren't `Send`, so they can run on single-threaded executors:
+//! on native the [`DefaultSpawner`] uses `tokio::task::spawn_local`, so the pool must be used inside a `tokio::task::LocalSet`.
+//!
+//! It's a cfg and not a feature, since removing the `Send` bounds isn't additive:
+//! enable it for the final binary with `RUSTFLAGS="--cfg nostr_local"`.
+
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(not(all(nostr_local, not(target_arch = "wasm32"))))]
+use async_utility::task;
+
+use crate::transport::BoxedFuture;
+
+/// Spawner of the background tasks
+pub trait Spawner: fmt::Debug + Send + Sync {
+    /// Spawn the future
+    fn spawn(&self, future: BoxedFuture<'static, ()>);
+}
+
+/// Default spawner
+///
+/// Spawn on the ambient runtime (i.e. the current `tokio` runtime or `LocalSet`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultSpawner;
+
+impl Spawner for DefaultSpawner {
+    #[cfg(not(all(nostr_local, not(target_arch = "wasm32"))))]
+    fn spawn(&self, future: BoxedFuture<'static, ()>) {
+        task::spawn(future);
+    }
+
+    #[cfg(all(nostr_local, not(target_arch = "wasm32")))]
+    fn spawn(&self, future: BoxedFuture<'static, ()>) {
+        tokio::task::spawn_local(future);
+    }
+}
+
+/// Spawn on a specific `tokio` runtime
+#[cfg(not(any(target_arch = "wasm32", nostr_local)))]
+impl Spawner for tokio::runtime::Handle {
+    fn spawn(&self, future: BoxedFuture<'static, ()>) {
+        tokio::runtime::Handle::spawn(self, future);
+    }
+}
+
+#[doc(hidden)]
+pub trait IntoSpawner {
+    fn into_spawner(self) -> Arc<dyn Spawner>;
+}
+
+impl IntoSpawner for Arc<dyn Spawner> {
+    fn into_spawner(self) -> Arc<dyn Spawner> {
+        self
+    }
+}
+
+impl<T> IntoSpawner for T
+where
+    T: Spawner + Sized + 'static,
+{
+    fn into_spawner(self) -> Arc<dyn Spawner> {
+        Arc::new(self)
+    }
+}
+
+impl<T> IntoSpawner for Arc<T>
+where
+    T: Spawner + 'static,
+{
+    fn into_spawner(self) -> Arc<dyn Spawner> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct CountingSpawner {
+        spawned: AtomicUsize,
+    }
+
+    impl Spawner for CountingSpawner {
+        fn spawn(&self, future: BoxedFuture<'static, ()>) {
+            self.spawned.fetch_add(1, Ordering::SeqCst);
+            DefaultSpawner.spawn(future);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_spawner() {
+        let spawner = Arc::new(CountingSpawner::default());
+        let dyn_spawner: Arc<dyn Spawner> = spawner.clone().into_spawner();
+
+        // The `LocalSet` is required by the `nostr_local` cfg
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                dyn_spawner.spawn(Box::pin(async move {
+                    let _ = tx.send(());
+                }));
+
+                rx.await.unwrap();
+            })
+            .await;
+
+        assert_eq!(spawner.spawned.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(nostr_local)]
+    #[tokio::test]
+    async fn test_spawn_not_send() {
+        use std::rc::Rc;
+
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                let value: Rc<u8> = Rc::new(7);
+                DefaultSpawner.spawn(Box::pin(async move {
+                    tokio::task::yield_now().await;
+                    let _ = tx.send(*value);
+                }));
+
+                assert_eq!(rx.await.unwrap(), 7);
+            })
+            .await;
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Task supervision
+//!
+//! Catch the panics of the background tasks, so a single malformed message
+//! (or a panicking policy) doesn't silently stop the processing.
+//!
+//! The panics can be caught only if the binary is built with `panic = "unwind"` (the default).
+//! With `panic = "abort"` (i.e. the release profile of this workspace) a panic aborts the whole process:
+//! the tasks are not isolated and the `TaskPanicked` notifications are never sent.
+
+use std::any::Any;
+use std::panic::AssertUnwindSafe;
+
+use async_wsocket::futures_util::{Future, FutureExt};
+
+/// Poll the future, catching the panics
+///
+/// Return the panic message as error.
+/// No-op with `panic = "abort"`: the process is aborted before returning.
+pub(crate) async fn catch_panic<F>(future: F) -> Result<F::Output, String>
+where
+    F: Future,
+{
+    AssertUnwindSafe(future)
+        .catch_unwind()
+        .await
+        .map_err(|payload| panic_message(payload.as_ref()))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        return msg.to_string();
+    }
+
+    if let Some(msg) = payload.downcast_ref::<String>() {
+        return msg.clone();
+    }
+
+    String::from("unknown panic")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_catch_panic() {
+        assert_eq!(catch_panic(async { 42 }).await, Ok(42));
+
+        let res: Result<(), String> = catch_panic(async { panic!("malformed event") }).await;
+        assert_eq!(res, Err(String::from("malformed event")));
+
+        let id: u8 = 1;
+        let res: Result<(), String> = catch_panic(async move { panic!("event {id}") }).await;
+        assert_eq!(res, Err(String::from("event 1")));
+    }
+}
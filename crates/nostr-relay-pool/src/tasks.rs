@@ -0,0 +1,307 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Background tasks
+//!
+//! Every task spawned by the pool, by the relays and by the client is tracked in a registry,
+//! so the tasks can be inspected (i.e. to find leaks) and awaited at shutdown.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use async_utility::time;
+use async_wsocket::futures_util::future::{self, AbortHandle, Abortable};
+use nostr::Timestamp;
+use tokio::sync::watch;
+
+use crate::supervisor;
+
+/// Max number of panicked tasks kept in the registry
+const MAX_PANICKED_TASKS: usize = 100;
+
+/// Task state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskState {
+    /// Running
+    Running,
+    /// Completed
+    Finished,
+    /// Aborted (i.e. at shutdown)
+    Aborted,
+    /// Panicked (only with `panic = "unwind"`)
+    Panicked {
+        /// Panic message
+        reason: String,
+    },
+}
+
+impl TaskState {
+    /// Check if the task is still running
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Running)
+    }
+}
+
+/// Task info
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskInfo {
+    /// Task ID
+    pub id: u64,
+    /// Task name
+    pub name: String,
+    /// When the task has been spawned
+    pub spawned_at: Timestamp,
+    /// Current state
+    pub state: TaskState,
+}
+
+/// Handle of a background task
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    id: u64,
+    state: watch::Receiver<TaskState>,
+    abort: AbortHandle,
+}
+
+impl TaskHandle {
+    /// Task ID
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Current state
+    #[inline]
+    pub fn state(&self) -> TaskState {
+        self.state.borrow().clone()
+    }
+
+    /// Abort the task
+    ///
+    /// The task is stopped at the next `.await` point.
+    #[inline]
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    /// Wait for the task to terminate
+    pub async fn join(mut self) -> TaskState {
+        match self.state.wait_for(|state| !state.is_running()).await {
+            Ok(state) => state.clone(),
+            // The future has been dropped by the runtime before completion
+            Err(..) => TaskState::Aborted,
+        }
+    }
+
+    /// Check if the future has been dropped without updating the state
+    fn is_dropped(&self) -> bool {
+        self.state.has_changed().is_err() && self.state().is_running()
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    name: Cow<'static, str>,
+    spawned_at: Timestamp,
+    handle: TaskHandle,
+}
+
+/// Registry of the background tasks
+///
+/// The finished and aborted tasks are removed, the last panicked ones are kept for diagnosis.
+#[derive(Debug, Default)]
+pub(crate) struct TaskRegistry {
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<u64, Entry>>,
+}
+
+impl TaskRegistry {
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, Entry>> {
+        // A panic can't happen while holding the lock, so recover from a poisoned one
+        match self.tasks.lock() {
+            Ok(tasks) => tasks,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Register a task and wrap the future to track its state
+    ///
+    /// The returned future must be spawned.
+    pub fn track<F>(
+        self: &Arc<Self>,
+        name: Cow<'static, str>,
+        future: F,
+    ) -> (TaskHandle, impl Future<Output = ()>)
+    where
+        F: Future<Output = ()>,
+    {
+        let id: u64 = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = watch::channel(TaskState::Running);
+        let (abort, registration) = AbortHandle::new_pair();
+
+        let handle: TaskHandle = TaskHandle {
+            id,
+            state: rx,
+            abort,
+        };
+
+        self.lock().insert(
+            id,
+            Entry {
+                name,
+                spawned_at: Timestamp::now(),
+                handle: handle.clone(),
+            },
+        );
+
+        let registry: Arc<Self> = Arc::clone(self);
+        let future = async move {
+            let state: TaskState =
+                match supervisor::catch_panic(Abortable::new(future, registration)).await {
+                    Ok(Ok(())) => TaskState::Finished,
+                    Ok(Err(..)) => TaskState::Aborted,
+                    Err(reason) => {
+                        tracing::error!(id = id, reason = %reason, "Background task panicked.");
+                        TaskState::Panicked { reason }
+                    }
+                };
+
+            let mut tasks = registry.lock();
+            if matches!(state, TaskState::Panicked { .. }) {
+                // Keep only the last panicked tasks
+                let mut panicked: Vec<u64> = tasks
+                    .iter()
+                    .filter(|(other, entry)| {
+                        **other != id && matches!(entry.handle.state(), TaskState::Panicked { .. })
+                    })
+                    .map(|(other, ..)| *other)
+                    .collect();
+
+                if panicked.len() >= MAX_PANICKED_TASKS {
+                    panicked.sort_unstable();
+                    for other in panicked
+                        .iter()
+                        .take(panicked.len() + 1 - MAX_PANICKED_TASKS)
+                    {
+                        tasks.remove(other);
+                    }
+                }
+            } else {
+                tasks.remove(&id);
+            }
+            drop(tasks);
+
+            tx.send_replace(state);
+        };
+
+        (handle, future)
+    }
+
+    /// Get the tracked tasks, sorted by ID
+    pub fn tasks(&self) -> Vec<TaskInfo> {
+        let mut tasks = self.lock();
+
+        // Prune the tasks dropped by the runtime
+        tasks.retain(|_, entry| !entry.handle.is_dropped());
+
+        let mut list: Vec<TaskInfo> = tasks
+            .iter()
+            .map(|(id, entry)| TaskInfo {
+                id: *id,
+                name: entry.name.to_string(),
+                spawned_at: entry.spawned_at,
+                state: entry.handle.state(),
+            })
+            .collect();
+        list.sort_by_key(|task| task.id);
+        list
+    }
+
+    /// Wait for the running tasks to terminate, aborting them after the `timeout`
+    pub async fn join_all(&self, timeout: Duration) {
+        let handles: Vec<TaskHandle> = self
+            .lock()
+            .values()
+            .map(|entry| entry.handle.clone())
+            .filter(|handle| handle.state().is_running())
+            .collect();
+
+        if handles.is_empty() {
+            return;
+        }
+
+        let futures = handles.iter().cloned().map(TaskHandle::join);
+        if time::timeout(Some(timeout), future::join_all(futures))
+            .await
+            .is_none()
+        {
+            for handle in handles.iter().filter(|h| h.state().is_running()) {
+                tracing::warn!(
+                    id = handle.id(),
+                    "Background task didn't terminate in time, aborting it."
+                );
+                handle.abort();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_task_registry() {
+        let registry: Arc<TaskRegistry> = Arc::new(TaskRegistry::default());
+
+        // Finished
+        let (handle, fut) = registry.track("finished".into(), async {});
+        tokio::spawn(fut);
+        assert_eq!(handle.join().await, TaskState::Finished);
+
+        // Panicked
+        let (handle, fut) = registry.track("panicked".into(), async { panic!("boom") });
+        tokio::spawn(fut);
+        assert_eq!(
+            handle.join().await,
+            TaskState::Panicked {
+                reason: String::from("boom")
+            }
+        );
+
+        // Running
+        let (handle, fut) = registry.track("running".into(), future::pending::<()>());
+        tokio::spawn(fut);
+
+        let tasks: Vec<TaskInfo> = registry.tasks();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "panicked");
+        assert_eq!(tasks[1].name, "running");
+        assert!(tasks[1].state.is_running());
+
+        // Abort the pending task
+        registry.join_all(Duration::from_millis(100)).await;
+        assert_eq!(handle.join().await, TaskState::Aborted);
+
+        let tasks: Vec<TaskInfo> = registry.tasks();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].name, "panicked");
+
+        // Only the last panicked tasks are kept
+        for _ in 0..MAX_PANICKED_TASKS {
+            let (handle, fut) = registry.track("panicked".into(), async { panic!("boom") });
+            tokio::spawn(fut);
+            handle.join().await;
+        }
+
+        let tasks: Vec<TaskInfo> = registry.tasks();
+        assert_eq!(tasks.len(), MAX_PANICKED_TASKS);
+        assert!(tasks.iter().all(|task| task.id > 1));
+    }
+}
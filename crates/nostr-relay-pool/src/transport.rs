@@ -0,0 +1,520 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Transport
+
+use std::fmt;
+use std::future::Future;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use async_utility::time;
+#[cfg(not(target_arch = "wasm32"))]
+use async_wsocket::futures_util::stream::FuturesUnordered;
+use async_wsocket::futures_util::{self, SinkExt, StreamExt};
+use async_wsocket::{ConnectionMode, WsMessage};
+#[cfg(not(target_arch = "wasm32"))]
+use nostr::types::url::Host;
+use nostr::Url;
+#[cfg(not(target_arch = "wasm32"))]
+use socket2::{SockRef, TcpKeepalive};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::net::TcpStream;
+/// TLS connector (i.e. to use a custom rustls `ClientConfig`)
+#[cfg(not(target_arch = "wasm32"))]
+pub use tokio_tungstenite::Connector as TlsConnector;
+
+/// Transport error
+pub type TransportError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Boxed future
+#[cfg(not(any(target_arch = "wasm32", nostr_local)))]
+pub type BoxedFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+/// Boxed future
+#[cfg(any(target_arch = "wasm32", nostr_local))]
+pub type BoxedFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// WebSocket sink
+#[cfg(not(any(target_arch = "wasm32", nostr_local)))]
+pub type BoxSink = Box<dyn futures_util::Sink<WsMessage, Error = TransportError> + Send + Unpin>;
+/// WebSocket sink
+#[cfg(any(target_arch = "wasm32", nostr_local))]
+pub type BoxSink = Box<dyn futures_util::Sink<WsMessage, Error = TransportError> + Unpin>;
+
+/// WebSocket stream
+#[cfg(not(any(target_arch = "wasm32", nostr_local)))]
+pub type BoxStream =
+    Box<dyn futures_util::Stream<Item = Result<WsMessage, TransportError>> + Send + Unpin>;
+/// WebSocket stream
+#[cfg(any(target_arch = "wasm32", nostr_local))]
+pub type BoxStream =
+    Box<dyn futures_util::Stream<Item = Result<WsMessage, TransportError>> + Unpin>;
+
+/// Socket options
+///
+/// Applied only to direct connections (not through proxy or tor).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SocketOptions {
+    /// TCP keepalive idle time and probes interval (`None` means system default)
+    pub tcp_keepalive: Option<Duration>,
+    /// Disable Nagle's algorithm (`TCP_NODELAY`)
+    pub tcp_nodelay: bool,
+}
+
+impl SocketOptions {
+    #[cfg(not(target_arch = "wasm32"))]
+    const DEFAULT: Self = Self {
+        tcp_keepalive: None,
+        tcp_nodelay: false,
+    };
+
+    /// Check if all the options are set to the system defaults
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        self.tcp_keepalive.is_none() && !self.tcp_nodelay
+    }
+}
+
+/// WebSocket transport
+///
+/// Allow to inject alternative transports (i.e. custom TLS configs, proxies or in-memory pairs for tests).
+pub trait WebSocketTransport: fmt::Debug + Send + Sync {
+    /// Connect to the relay
+    ///
+    /// Return the sink and the stream of the WebSocket connection.
+    fn connect<'a>(
+        &'a self,
+        url: &'a Url,
+        mode: &'a ConnectionMode,
+        timeout: Duration,
+    ) -> BoxedFuture<'a, Result<(BoxSink, BoxStream), TransportError>>;
+
+    /// Connect to the relay applying the [`SocketOptions`]
+    ///
+    /// The default implementation ignores the socket options and calls [`WebSocketTransport::connect`].
+    fn connect_with_socket_opts<'a>(
+        &'a self,
+        url: &'a Url,
+        mode: &'a ConnectionMode,
+        timeout: Duration,
+        socket: &'a SocketOptions,
+    ) -> BoxedFuture<'a, Result<(BoxSink, BoxStream), TransportError>> {
+        let _ = socket;
+        self.connect(url, mode, timeout)
+    }
+}
+
+/// Default WebSocket transport
+///
+/// Host names are resolved with the system resolver and the TLS connections use rustls with the webpki roots.
+/// To use a custom resolver (i.e. DNS-over-HTTPS), to race IPv4/IPv6 addresses (happy eyeballs)
+/// or to use a custom TLS config, use the [`ResolverWebsocketTransport`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultWebsocketTransport;
+
+impl WebSocketTransport for DefaultWebsocketTransport {
+    fn connect<'a>(
+        &'a self,
+        url: &'a Url,
+        mode: &'a ConnectionMode,
+        timeout: Duration,
+    ) -> BoxedFuture<'a, Result<(BoxSink, BoxStream), TransportError>> {
+        Box::pin(async move {
+            let (tx, rx) = async_wsocket::connect(url, mode, timeout).await?;
+            let sink: BoxSink = Box::new(tx.sink_map_err(|e| Box::new(e) as TransportError));
+            let stream: BoxStream =
+                Box::new(rx.map(|res| res.map_err(|e| Box::new(e) as TransportError)));
+            Ok((sink, stream))
+        })
+    }
+
+    fn connect_with_socket_opts<'a>(
+        &'a self,
+        url: &'a Url,
+        mode: &'a ConnectionMode,
+        timeout: Duration,
+        socket: &'a SocketOptions,
+    ) -> BoxedFuture<'a, Result<(BoxSink, BoxStream), TransportError>> {
+        match mode {
+            #[cfg(not(target_arch = "wasm32"))]
+            ConnectionMode::Direct if !socket.is_default() => {
+                Box::pin(connect_direct(url, timeout, socket))
+            }
+            _ => self.connect(url, mode, timeout),
+        }
+    }
+}
+
+/// Open the TCP connection, apply the socket options and perform the WebSocket handshake
+#[cfg(not(target_arch = "wasm32"))]
+async fn connect_direct(
+    url: &Url,
+    timeout: Duration,
+    socket: &SocketOptions,
+) -> Result<(BoxSink, BoxStream), TransportError> {
+    // Not `host_str`, since IPv6 addresses are enclosed in brackets
+    let host: Host<&str> = url.host().ok_or("missing host")?;
+    let port: u16 = url.port_or_known_default().ok_or("missing port")?;
+
+    time::timeout(Some(timeout), async {
+        let stream: TcpStream = match host {
+            Host::Domain(domain) => TcpStream::connect((domain, port)).await?,
+            Host::Ipv4(ip) => TcpStream::connect(SocketAddr::new(ip.into(), port)).await?,
+            Host::Ipv6(ip) => TcpStream::connect(SocketAddr::new(ip.into(), port)).await?,
+        };
+        handshake(url, stream, socket, None).await
+    })
+    .await
+    .ok_or("connection timeout")?
+}
+
+/// Apply the socket options and perform the WebSocket handshake
+///
+/// If the `tls` connector isn't set, rustls with the webpki roots is used.
+#[cfg(not(target_arch = "wasm32"))]
+async fn handshake(
+    url: &Url,
+    stream: TcpStream,
+    socket: &SocketOptions,
+    tls: Option<&TlsConnector>,
+) -> Result<(BoxSink, BoxStream), TransportError> {
+    // Apply socket options
+    stream.set_nodelay(socket.tcp_nodelay)?;
+    if let Some(keepalive) = socket.tcp_keepalive {
+        let keepalive: TcpKeepalive = TcpKeepalive::new()
+            .with_time(keepalive)
+            .with_interval(keepalive);
+        SockRef::from(&stream).set_tcp_keepalive(&keepalive)?;
+    }
+
+    // WebSocket handshake
+    let (ws, _) =
+        tokio_tungstenite::client_async_tls_with_config(url.as_str(), stream, None, tls.cloned())
+            .await?;
+    let (tx, rx) = ws.split();
+
+    let sink: BoxSink = Box::new(tx.sink_map_err(|e| Box::new(e) as TransportError));
+    let stream: BoxStream = Box::new(rx.map(|res| res.map_err(|e| Box::new(e) as TransportError)));
+    Ok((sink, stream))
+}
+
+/// DNS resolver
+///
+/// Allow to resolve the relay host names with a custom resolver (i.e. DNS-over-HTTPS).
+#[cfg(not(target_arch = "wasm32"))]
+pub trait DnsResolver: fmt::Debug + Send + Sync {
+    /// Resolve the host name to the socket addresses
+    fn resolve<'a>(
+        &'a self,
+        host: &'a str,
+        port: u16,
+    ) -> BoxedFuture<'a, Result<Vec<SocketAddr>, TransportError>>;
+}
+
+/// System DNS resolver
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemDnsResolver;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DnsResolver for SystemDnsResolver {
+    fn resolve<'a>(
+        &'a self,
+        host: &'a str,
+        port: u16,
+    ) -> BoxedFuture<'a, Result<Vec<SocketAddr>, TransportError>> {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((host, port)).await?;
+            Ok(addrs.collect())
+        })
+    }
+}
+
+/// WebSocket transport with custom DNS resolution and happy eyeballs
+///
+/// The host names are resolved with the [`DnsResolver`] and the addresses are raced,
+/// alternating IPv6 and IPv4, starting a new attempt every `happy_eyeballs_delay` (RFC 8305).
+///
+/// Applied only to direct connections: proxy and tor connections are delegated to [`DefaultWebsocketTransport`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub struct ResolverWebsocketTransport {
+    resolver: Arc<dyn DnsResolver>,
+    happy_eyeballs_delay: Duration,
+    tls: Option<TlsConnector>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl fmt::Debug for ResolverWebsocketTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResolverWebsocketTransport")
+            .field("resolver", &self.resolver)
+            .field("happy_eyeballs_delay", &self.happy_eyeballs_delay)
+            .field("custom_tls", &self.tls.is_some())
+            .finish()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ResolverWebsocketTransport {
+    fn default() -> Self {
+        Self::new(SystemDnsResolver)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ResolverWebsocketTransport {
+    /// Default delay between the connection attempts (RFC 8305)
+    pub const DEFAULT_HAPPY_EYEBALLS_DELAY: Duration = Duration::from_millis(250);
+
+    /// New transport with a custom resolver
+    pub fn new<T>(resolver: T) -> Self
+    where
+        T: DnsResolver + 'static,
+    {
+        Self {
+            resolver: Arc::new(resolver),
+            happy_eyeballs_delay: Self::DEFAULT_HAPPY_EYEBALLS_DELAY,
+            tls: None,
+        }
+    }
+
+    /// Delay before starting the next connection attempt (default: 250 ms)
+    ///
+    /// A zero delay races all the addresses at once.
+    #[inline]
+    pub fn happy_eyeballs_delay(mut self, delay: Duration) -> Self {
+        self.happy_eyeballs_delay = delay;
+        self
+    }
+
+    /// TLS connector (default: rustls with the webpki roots)
+    ///
+    /// Allow to use a custom rustls `ClientConfig` (i.e. with custom root certificates or pinned keys).
+    #[inline]
+    pub fn tls_connector(mut self, connector: TlsConnector) -> Self {
+        self.tls = Some(connector);
+        self
+    }
+
+    async fn connect_direct(
+        &self,
+        url: &Url,
+        timeout: Duration,
+        socket: &SocketOptions,
+    ) -> Result<(BoxSink, BoxStream), TransportError> {
+        let host: Host<&str> = url.host().ok_or("missing host")?;
+        let port: u16 = url.port_or_known_default().ok_or("missing port")?;
+
+        time::timeout(Some(timeout), async {
+            // Resolve the host name, if isn't an IP address
+            let addrs: Vec<SocketAddr> = match host {
+                Host::Domain(domain) => self.resolver.resolve(domain, port).await?,
+                Host::Ipv4(ip) => vec![SocketAddr::new(ip.into(), port)],
+                Host::Ipv6(ip) => vec![SocketAddr::new(ip.into(), port)],
+            };
+
+            let stream: TcpStream =
+                happy_eyeballs_connect(interleave_addrs(addrs), self.happy_eyeballs_delay).await?;
+            handshake(url, stream, socket, self.tls.as_ref()).await
+        })
+        .await
+        .ok_or("connection timeout")?
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WebSocketTransport for ResolverWebsocketTransport {
+    fn connect<'a>(
+        &'a self,
+        url: &'a Url,
+        mode: &'a ConnectionMode,
+        timeout: Duration,
+    ) -> BoxedFuture<'a, Result<(BoxSink, BoxStream), TransportError>> {
+        self.connect_with_socket_opts(url, mode, timeout, &SocketOptions::DEFAULT)
+    }
+
+    fn connect_with_socket_opts<'a>(
+        &'a self,
+        url: &'a Url,
+        mode: &'a ConnectionMode,
+        timeout: Duration,
+        socket: &'a SocketOptions,
+    ) -> BoxedFuture<'a, Result<(BoxSink, BoxStream), TransportError>> {
+        match mode {
+            ConnectionMode::Direct => Box::pin(self.connect_direct(url, timeout, socket)),
+            _ => {
+                Box::pin(async move { DefaultWebsocketTransport.connect(url, mode, timeout).await })
+            }
+        }
+    }
+}
+
+/// Sort the addresses alternating the IP families, starting from the first one returned by the resolver
+#[cfg(not(target_arch = "wasm32"))]
+fn interleave_addrs(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let prefer_ipv6: bool = addrs.first().map(|a| a.is_ipv6()).unwrap_or(true);
+    let (first, second): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == prefer_ipv6);
+
+    let mut list: Vec<SocketAddr> = Vec::with_capacity(first.len() + second.len());
+    let mut first = first.into_iter();
+    let mut second = second.into_iter();
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => break,
+            (a, b) => list.extend(a.into_iter().chain(b)),
+        }
+    }
+    list
+}
+
+/// Race the connection attempts, starting the next one after the `delay` or when the previous fails
+#[cfg(not(target_arch = "wasm32"))]
+async fn happy_eyeballs_connect(addrs: Vec<SocketAddr>, delay: Duration) -> io::Result<TcpStream> {
+    let mut remaining = addrs.into_iter();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error: Option<io::Error> = None;
+
+    loop {
+        // Start the next attempt
+        if let Some(addr) = remaining.next() {
+            attempts.push(TcpStream::connect(addr));
+        }
+
+        if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "no address resolved")
+            }));
+        }
+
+        let has_more: bool = remaining.len() > 0;
+
+        tokio::select! {
+            res = attempts.next() => match res {
+                Some(Ok(stream)) => return Ok(stream),
+                Some(Err(e)) => last_error = Some(e),
+                None => {}
+            },
+            _ = time::sleep(delay), if has_more => {}
+        }
+    }
+}
+
+#[doc(hidden)]
+pub trait IntoWebSocketTransport {
+    fn into_transport(self) -> Arc<dyn WebSocketTransport>;
+}
+
+impl IntoWebSocketTransport for Arc<dyn WebSocketTransport> {
+    fn into_transport(self) -> Arc<dyn WebSocketTransport> {
+        self
+    }
+}
+
+impl<T> IntoWebSocketTransport for T
+where
+    T: WebSocketTransport + Sized + 'static,
+{
+    fn into_transport(self) -> Arc<dyn WebSocketTransport> {
+        Arc::new(self)
+    }
+}
+
+impl<T> IntoWebSocketTransport for Arc<T>
+where
+    T: WebSocketTransport + 'static,
+{
+    fn into_transport(self) -> Arc<dyn WebSocketTransport> {
+        self
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_interleave_addrs() {
+        let v4_1: SocketAddr = "127.0.0.1:80".parse().unwrap();
+        let v4_2: SocketAddr = "127.0.0.2:80".parse().unwrap();
+        let v6_1: SocketAddr = "[::1]:80".parse().unwrap();
+        let v6_2: SocketAddr = "[::2]:80".parse().unwrap();
+
+        assert_eq!(
+            interleave_addrs(vec![v6_1, v6_2, v4_1, v4_2]),
+            vec![v6_1, v4_1, v6_2, v4_2]
+        );
+        assert_eq!(
+            interleave_addrs(vec![v4_1, v4_2, v6_1]),
+            vec![v4_1, v6_1, v4_2]
+        );
+        assert!(interleave_addrs(Vec::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_direct_ipv6() {
+        // IPv6 may be not available
+        let listener = match TcpListener::bind("[::1]:0").await {
+            Ok(listener) => listener,
+            Err(..) => return,
+        };
+        let addr: SocketAddr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, ..) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(..)) = ws.next().await {}
+        });
+
+        // The brackets of the host must be removed
+        let url = Url::parse(&format!("ws://{addr}")).unwrap();
+        assert_eq!(url.host_str(), Some("[::1]"));
+
+        let socket = SocketOptions {
+            tcp_keepalive: None,
+            tcp_nodelay: true,
+        };
+        assert!(connect_direct(&url, Duration::from_secs(5), &socket)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_happy_eyeballs_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr: SocketAddr = listener.local_addr().unwrap();
+
+        // Closed port
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr: SocketAddr = closed.local_addr().unwrap();
+        drop(closed);
+
+        // The failed attempt doesn't wait the delay
+        let stream = time::timeout(
+            Some(Duration::from_secs(5)),
+            happy_eyeballs_connect(vec![closed_addr, addr], Duration::from_secs(60)),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addr);
+
+        // All failed
+        assert!(happy_eyeballs_connect(vec![closed_addr], Duration::ZERO)
+            .await
+            .is_err());
+        assert!(happy_eyeballs_connect(Vec::new(), Duration::ZERO)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Account deletion
+
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+
+use super::{Client, Error};
+
+/// Max number of events referenced by a single deletion event
+const MAX_DELETION_TAGS: usize = 500;
+
+/// Account deletion step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccountDeletionStep {
+    /// Blank metadata
+    Metadata,
+    /// Empty contact list
+    ContactList,
+    /// Deletion events (NIP09)
+    Deletion,
+    /// Request to vanish (NIP62)
+    Vanish,
+    /// Local database wipe
+    Wipe,
+}
+
+/// Account deletion output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountDeletion {
+    /// Blank metadata
+    pub metadata: Option<EventId>,
+    /// Empty contact list
+    pub contact_list: Option<EventId>,
+    /// Published deletion events
+    pub deletions: Vec<EventId>,
+    /// Request to vanish
+    pub vanish: Option<Output<EventId>>,
+    /// Whether the local database has been wiped
+    pub wiped: bool,
+    /// Failed steps, with the error
+    pub failed: Vec<(AccountDeletionStep, String)>,
+}
+
+impl AccountDeletion {
+    /// Check if all the steps succeeded
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl Client {
+    /// Delete the account
+    ///
+    /// **This is destructive and can't be undone!**
+    ///
+    /// To confirm, the `confirmation` must be the `npub` of the signer.
+    ///
+    /// Steps:
+    /// 1. publish an empty metadata and an empty contact list;
+    /// 2. publish the deletion events (NIP09) for all the own events stored in the database;
+    /// 3. publish a request to vanish from all the relays (NIP62);
+    /// 4. wipe the database.
+    ///
+    /// A failed step doesn't stop the deletion: it's reported in [`AccountDeletion::failed`].
+    /// The database is wiped only if all the previous steps succeeded,
+    /// so the own events are still known when retrying.
+    ///
+    /// Relays aren't required to honor the deletion events or the request to vanish,
+    /// so the events may still be available somewhere.
+    pub async fn nuke_account<S>(&self, confirmation: S) -> Result<AccountDeletion, Error>
+    where
+        S: AsRef<str>,
+    {
+        let signer = self.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+
+        // Check confirmation
+        if PublicKey::from_bech32(confirmation.as_ref()).ok() != Some(public_key) {
+            return Err(Error::AccountDeletionNotConfirmed);
+        }
+
+        // Get the known own events (before publishing the new ones)
+        let filter: Filter = Filter::new().author(public_key);
+        let events: Events = self.database().query(vec![filter]).await?;
+
+        let mut deletion: AccountDeletion = AccountDeletion::default();
+
+        // Blank metadata and contact list
+        match self
+            .send_event_builder(EventBuilder::metadata(&Metadata::new()))
+            .await
+        {
+            Ok(output) => deletion.metadata = Some(output.val),
+            Err(e) => deletion
+                .failed
+                .push((AccountDeletionStep::Metadata, e.to_string())),
+        }
+        match self
+            .send_event_builder(EventBuilder::contact_list([]))
+            .await
+        {
+            Ok(output) => deletion.contact_list = Some(output.val),
+            Err(e) => deletion
+                .failed
+                .push((AccountDeletionStep::ContactList, e.to_string())),
+        }
+
+        // Request the deletion of the known events
+        // The metadata and the contact list have been already replaced.
+        let events: Vec<Event> = events
+            .into_iter()
+            .filter(|e| {
+                !matches!(
+                    e.kind,
+                    Kind::Metadata | Kind::ContactList | Kind::EventDeletion
+                )
+            })
+            .collect();
+
+        for chunk in events.chunks(MAX_DELETION_TAGS) {
+            let targets = chunk.iter().map(|e| match e.coordinate() {
+                Some(coordinate) => EventIdOrCoordinate::Coordinate(coordinate),
+                None => EventIdOrCoordinate::Id(e.id),
+            });
+            match self.send_event_builder(EventBuilder::delete(targets)).await {
+                Ok(output) => deletion.deletions.push(output.val),
+                Err(e) => deletion
+                    .failed
+                    .push((AccountDeletionStep::Deletion, e.to_string())),
+            }
+        }
+
+        // Request to vanish from all the relays
+        match self
+            .send_event_builder(EventBuilder::request_vanish(VanishTarget::AllRelays, ""))
+            .await
+        {
+            Ok(output) => deletion.vanish = Some(output),
+            Err(e) => deletion
+                .failed
+                .push((AccountDeletionStep::Vanish, e.to_string())),
+        }
+
+        // Clear local storage
+        if deletion.is_complete() {
+            match self.database().wipe().await {
+                Ok(()) => deletion.wiped = true,
+                Err(e) => deletion
+                    .failed
+                    .push((AccountDeletionStep::Wipe, e.to_string())),
+            }
+        }
+
+        Ok(deletion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_nuke_account() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let keys = Keys::generate();
+        let client = Client::builder()
+            .signer(keys.clone())
+            .database(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+                events: true,
+                max_events: None,
+            }))
+            .build();
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        for i in 0..3 {
+            client
+                .send_event_builder(EventBuilder::text_note(format!("Note #{i}")))
+                .await
+                .unwrap();
+        }
+
+        // Not confirmed
+        assert!(matches!(
+            client.nuke_account("npub").await.unwrap_err(),
+            Error::AccountDeletionNotConfirmed
+        ));
+
+        // A failed step doesn't stop the deletion and the database isn't wiped
+        client.disconnect().await.unwrap();
+        let npub: String = keys.public_key().to_bech32().unwrap();
+        let deletion = client.nuke_account(&npub).await.unwrap();
+        assert!(!deletion.is_complete());
+        assert!(!deletion.wiped);
+        assert!(deletion
+            .failed
+            .iter()
+            .any(|(step, ..)| *step == AccountDeletionStep::Vanish));
+        let filter = Filter::new().author(keys.public_key()).kind(Kind::TextNote);
+        assert_eq!(client.database().count(vec![filter]).await.unwrap(), 3);
+
+        // Retry
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+        let deletion = client.nuke_account(&npub).await.unwrap();
+        assert!(deletion.is_complete(), "{:?}", deletion.failed);
+        assert!(deletion.metadata.is_some());
+        assert!(deletion.contact_list.is_some());
+        assert_eq!(deletion.deletions.len(), 1);
+        assert!(deletion.vanish.is_some());
+        assert!(deletion.wiped);
+        assert_eq!(
+            client.database().count(vec![Filter::new()]).await.unwrap(),
+            0
+        );
+    }
+}
@@ -6,9 +6,18 @@
 
 use std::sync::Arc;
 
+use nostr::event::content_policy::{ContentPolicy, IntoContentPolicy};
 use nostr::signer::{IntoNostrSigner, NostrSigner};
+use nostr::types::time::{IntoTimeProvider, SystemTimeProvider, TimeProvider};
+use nostr::util::http::{HttpClient, IntoHttpClient};
 use nostr_database::memory::MemoryDatabase;
 use nostr_database::{IntoNostrDatabase, NostrDatabase};
+use nostr_relay_pool::policy::{AdmitPolicy, DedupPolicy, IntoAdmitPolicy, IntoDedupPolicy};
+use nostr_relay_pool::skew::ClockSkewOptions;
+use nostr_relay_pool::spawner::{DefaultSpawner, IntoSpawner, Spawner};
+use nostr_relay_pool::transport::{
+    DefaultWebsocketTransport, IntoWebSocketTransport, WebSocketTransport,
+};
 #[cfg(feature = "nip57")]
 use nostr_zapper::{DynNostrZapper, IntoNostrZapper};
 
@@ -24,6 +33,22 @@ pub struct ClientBuilder {
     pub zapper: Option<Arc<DynNostrZapper>>,
     /// Database
     pub database: Arc<dyn NostrDatabase>,
+    /// Admission policy
+    pub admit_policy: Option<Arc<dyn AdmitPolicy>>,
+    /// Deduplication policy
+    pub dedup_policy: Option<Arc<dyn DedupPolicy>>,
+    /// WebSocket transport
+    pub websocket_transport: Arc<dyn WebSocketTransport>,
+    /// Time provider
+    pub time_provider: Arc<dyn TimeProvider>,
+    /// Clock skew detection
+    pub clock_skew: Option<ClockSkewOptions>,
+    /// HTTP client
+    pub http_client: Option<Arc<dyn HttpClient>>,
+    /// Spawner of the background tasks
+    pub spawner: Arc<dyn Spawner>,
+    /// Outbound content policy
+    pub content_policy: Option<Arc<dyn ContentPolicy>>,
     /// Client options
     pub opts: Options,
 }
@@ -35,6 +60,14 @@ impl Default for ClientBuilder {
             #[cfg(feature = "nip57")]
             zapper: None,
             database: Arc::new(MemoryDatabase::default()),
+            admit_policy: None,
+            dedup_policy: None,
+            websocket_transport: Arc::new(DefaultWebsocketTransport),
+            time_provider: Arc::new(SystemTimeProvider),
+            clock_skew: None,
+            http_client: None,
+            spawner: Arc::new(DefaultSpawner),
+            content_policy: None,
             opts: Options::default(),
         }
     }
@@ -87,6 +120,104 @@ impl ClientBuilder {
         self
     }
 
+    /// Set admission policy
+    ///
+    /// The policy is consulted for every event received from relays.
+    #[inline]
+    pub fn admit_policy<T>(mut self, policy: T) -> Self
+    where
+        T: IntoAdmitPolicy,
+    {
+        self.admit_policy = Some(policy.into_admit_policy());
+        self
+    }
+
+    /// Set a deduplication policy
+    ///
+    /// Allow to replace the default deduplication (by event ID) for custom protocols.
+    #[inline]
+    pub fn dedup_policy<T>(mut self, policy: T) -> Self
+    where
+        T: IntoDedupPolicy,
+    {
+        self.dedup_policy = Some(policy.into_dedup_policy());
+        self
+    }
+
+    /// Set custom WebSocket transport
+    ///
+    /// By default [`DefaultWebsocketTransport`] is used.
+    #[inline]
+    pub fn websocket_transport<T>(mut self, transport: T) -> Self
+    where
+        T: IntoWebSocketTransport,
+    {
+        self.websocket_transport = transport.into_transport();
+        self
+    }
+
+    /// Set custom time provider
+    ///
+    /// Used for the `created_at` of the events built by the client.
+    /// By default [`SystemTimeProvider`] is used.
+    #[inline]
+    pub fn time_provider<T>(mut self, provider: T) -> Self
+    where
+        T: IntoTimeProvider,
+    {
+        self.time_provider = provider.into_time_provider();
+        self
+    }
+
+    /// Enable clock skew detection
+    ///
+    /// The skew of the time provider is measured against the relays
+    /// and, if the auto-correction is enabled, the `created_at` of the outgoing events is corrected.
+    #[inline]
+    pub fn clock_skew(mut self, opts: ClockSkewOptions) -> Self {
+        self.clock_skew = Some(opts);
+        self
+    }
+
+    /// Set custom HTTP client
+    ///
+    /// Used for the HTTP requests (i.e. NIP11 documents), so they can go through a custom stack or proxy.
+    /// The relays with a proxy (check [`RelayOptions::connection_mode`](nostr_relay_pool::RelayOptions::connection_mode)) use the client returned by [`HttpClient::proxied`],
+    /// or their own proxied client if the custom one doesn't support proxies.
+    #[inline]
+    pub fn http_client<T>(mut self, client: T) -> Self
+    where
+        T: IntoHttpClient,
+    {
+        self.http_client = Some(client.into_http_client());
+        self
+    }
+
+    /// Set the spawner of the background tasks (default: spawn on the ambient runtime)
+    ///
+    /// Pass a `tokio::runtime::Handle` to host the connections, the notification handlers
+    /// and the other background tasks on a specific runtime.
+    #[inline]
+    pub fn spawner<T>(mut self, spawner: T) -> Self
+    where
+        T: IntoSpawner,
+    {
+        self.spawner = spawner.into_spawner();
+        self
+    }
+
+    /// Set outbound content policy
+    ///
+    /// Applied to all the events built and signed by the client (check [`Client::sign_event_builder`]).
+    #[inline]
+    pub fn content_policy<T>(mut self, policy: T) -> Self
+    where
+        T: IntoContentPolicy,
+    {
+        self.content_policy = Some(policy.into_content_policy());
+        self
+    }
+
     /// Set opts
     #[inline]
     pub fn opts(mut self, opts: Options) -> Self {
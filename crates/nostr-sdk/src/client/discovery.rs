@@ -0,0 +1,137 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay discovery
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+
+use super::{Client, Error};
+
+const RELAY_LISTS_LIMIT: usize = 1000;
+const MONITOR_REPORTS_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// Relay found by [`Client::discover_relays`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiscoveredRelay {
+    /// Relay URL
+    pub url: RelayUrl,
+    /// Number of NIP65 relay lists that include the relay (popularity)
+    pub relay_lists: usize,
+    /// Number of recent NIP66 monitor reports for the relay (liveness)
+    pub monitor_reports: usize,
+}
+
+impl DiscoveredRelay {
+    #[inline]
+    fn new(url: RelayUrl) -> Self {
+        Self {
+            url,
+            relay_lists: 0,
+            monitor_reports: 0,
+        }
+    }
+
+    /// Ranking score
+    #[inline]
+    pub fn score(&self) -> usize {
+        self.relay_lists + self.monitor_reports
+    }
+}
+
+impl Client {
+    /// Discover public relays
+    ///
+    /// Crawl the NIP65 relay lists and the recent NIP66 relay monitor reports from the connected relays
+    /// and rank the found relays by popularity and liveness (check [`DiscoveredRelay::score`]).
+    /// Local relays are skipped.
+    ///
+    /// Useful to bootstrap the relay list of new users.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/65.md>
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    pub async fn discover_relays(
+        &self,
+        limit: usize,
+        timeout: Duration,
+    ) -> Result<Vec<DiscoveredRelay>, Error> {
+        let filters: Vec<Filter> = vec![
+            Filter::new().kind(Kind::RelayList).limit(RELAY_LISTS_LIMIT),
+            Filter::new()
+                .kind(Kind::RelayDiscovery)
+                .since(self.time_provider().now() - MONITOR_REPORTS_MAX_AGE),
+        ];
+        let events: Events = self
+            .pool
+            .fetch_events(filters, timeout, FilterOptions::ExitOnEOSE)
+            .await?;
+
+        let mut relays: HashMap<RelayUrl, DiscoveredRelay> = HashMap::new();
+
+        for event in events.into_iter() {
+            match event.kind {
+                Kind::RelayList => {
+                    for (url, ..) in nip65::extract_relay_list(&event) {
+                        relays
+                            .entry(url.clone())
+                            .or_insert_with(|| DiscoveredRelay::new(url.clone()))
+                            .relay_lists += 1;
+                    }
+                }
+                Kind::RelayDiscovery => {
+                    if let Ok(report) = RelayDiscovery::try_from(&event) {
+                        relays
+                            .entry(report.relay_url.clone())
+                            .or_insert_with(|| DiscoveredRelay::new(report.relay_url))
+                            .monitor_reports += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Rank relays
+        let mut relays: Vec<DiscoveredRelay> = relays
+            .into_values()
+            .filter(|r| !r.url.is_local_addr())
+            .collect();
+        relays.sort_by(|a, b| b.score().cmp(&a.score()).then_with(|| a.url.cmp(&b.url)));
+        relays.truncate(limit);
+
+        Ok(relays)
+    }
+
+    /// Compose a NIP66 relay discovery report from the connection stats of a pool relay
+    ///
+    /// The average ping latency is reported as read round-trip time.
+    /// Publish it with [`EventBuilder::relay_discovery`].
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    pub async fn relay_discovery_report<U>(&self, url: U) -> Result<RelayDiscovery, Error>
+    where
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let relay: Relay = self.pool.relay(url).await?;
+
+        let mut report = RelayDiscovery::new(relay.url().clone());
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            report.rtt_read = relay.stats().latency();
+        }
+
+        report.network = Some(if relay.url().is_onion() {
+            RelayNetwork::Tor
+        } else {
+            RelayNetwork::Clearnet
+        });
+
+        Ok(report)
+    }
+}
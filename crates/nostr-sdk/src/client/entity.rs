@@ -0,0 +1,238 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Fetch by NIP19 entity
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+
+use super::{Client, Error};
+
+/// Entity fetched by [`Client::fetch`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchedEntity {
+    /// Profile (`npub` or `nprofile`)
+    Profile {
+        /// Public key
+        public_key: PublicKey,
+        /// Metadata
+        metadata: Metadata,
+    },
+    /// Event (`note`, `nevent` or `naddr`)
+    Event(Box<Event>),
+}
+
+impl Client {
+    /// Fetch a NIP19 entity
+    ///
+    /// Run the query for the entity:
+    /// * `npub` and `nprofile`: the profile metadata;
+    /// * `note` and `nevent`: the event;
+    /// * `naddr`: the newest replaceable or addressable event.
+    ///
+    /// The relay hints embedded in the entity, if any, are queried together with the `READ` relays.
+    /// The hints not already in the pool are added only for the query and removed after it;
+    /// the hints that can't be added or connected are skipped.
+    ///
+    /// Return `None` if the entity is not found.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/19.md>
+    pub async fn fetch(
+        &self,
+        entity: Nip19,
+        timeout: Duration,
+    ) -> Result<Option<FetchedEntity>, Error> {
+        let is_profile: bool = matches!(entity, Nip19::Pubkey(..) | Nip19::Profile(..));
+
+        let (filter, hints): (Filter, Vec<RelayUrl>) = match entity {
+            Nip19::Pubkey(public_key) => (profile_filter(public_key), Vec::new()),
+            Nip19::Profile(profile) => (profile_filter(profile.public_key), profile.relays),
+            Nip19::EventId(id) => (Filter::new().id(id), Vec::new()),
+            Nip19::Event(event) => (
+                nevent_filter(&event),
+                event
+                    .relays
+                    .iter()
+                    .filter_map(|url| RelayUrl::parse(url).ok())
+                    .collect(),
+            ),
+            Nip19::Coordinate(coordinate) => {
+                (Filter::from(&coordinate).limit(1), coordinate.relays)
+            }
+            _ => return Err(Error::UnsupportedNip19Entity),
+        };
+
+        let events: Events = self.fetch_with_hints(filter, hints, timeout).await?;
+        let event: Event = match events.first() {
+            Some(event) => event.clone(),
+            None => return Ok(None),
+        };
+
+        if is_profile {
+            let metadata: Metadata = Metadata::try_from(&event)?;
+            return Ok(Some(FetchedEntity::Profile {
+                public_key: event.pubkey,
+                metadata,
+            }));
+        }
+
+        Ok(Some(FetchedEntity::Event(Box::new(event))))
+    }
+
+    async fn fetch_with_hints(
+        &self,
+        filter: Filter,
+        hints: Vec<RelayUrl>,
+        timeout: Duration,
+    ) -> Result<Events, Error> {
+        // No hints: standard query
+        if hints.is_empty() {
+            return self.fetch_events(vec![filter], timeout).await;
+        }
+
+        // Add the relay hints not already in the pool as temporary relays
+        let mut urls: HashSet<RelayUrl> = HashSet::with_capacity(hints.len());
+        let mut temporary: Vec<RelayUrl> = Vec::new();
+        for url in hints.into_iter() {
+            match self.add_temporary_relay(&url).await {
+                Ok(added) => {
+                    if added {
+                        temporary.push(url.clone());
+                    }
+
+                    urls.insert(url);
+                }
+                Err(e) => {
+                    tracing::warn!(url = %url, error = %e, "Impossible to use relay hint, skipping.")
+                }
+            }
+        }
+
+        // Query the relay hints and the READ relays
+        urls.extend(
+            self.pool
+                .relays_with_flag(RelayServiceFlags::READ, FlagCheck::All)
+                .await
+                .into_keys(),
+        );
+
+        let res = self
+            .pool
+            .fetch_events_from(urls, vec![filter], timeout, FilterOptions::ExitOnEOSE)
+            .await;
+
+        // Remove the temporary relays
+        for url in temporary.into_iter() {
+            if let Err(e) = self.pool.force_remove_relay(&url).await {
+                tracing::warn!(url = %url, error = %e, "Impossible to remove temporary relay.");
+            }
+        }
+
+        Ok(res?)
+    }
+
+    /// Add and connect a relay, without flags, if not already in the pool
+    ///
+    /// Return `true` if the relay has been added.
+    async fn add_temporary_relay(&self, url: &RelayUrl) -> Result<bool, Error> {
+        let opts: RelayOptions = self
+            .compose_relay_opts(url)
+            .await
+            .flags(RelayServiceFlags::NONE);
+
+        // Already in the pool: don't change its flags
+        if self
+            .pool
+            .__get_or_add_relay(url.clone(), false, opts)
+            .await?
+            .is_some()
+        {
+            return Ok(false);
+        }
+
+        if let Err(e) = self.pool.connect_relay(url, None).await {
+            let _ = self.pool.force_remove_relay(url).await;
+            return Err(e.into());
+        }
+
+        Ok(true)
+    }
+}
+
+/// Filter for the `nevent`, with the author and the kind if available
+fn nevent_filter(event: &Nip19Event) -> Filter {
+    let mut filter: Filter = Filter::new().id(event.event_id);
+
+    if let Some(author) = event.author {
+        filter = filter.author(author);
+    }
+
+    if let Some(kind) = event.kind {
+        filter = filter.kind(kind);
+    }
+
+    filter
+}
+
+#[inline]
+fn profile_filter(public_key: PublicKey) -> Filter {
+    Filter::new()
+        .author(public_key)
+        .kind(Kind::Metadata)
+        .limit(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fetch_with_temporary_hints() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = RelayUrl::parse(&mock.url()).unwrap();
+
+        // Publish the event
+        let keys = Keys::generate();
+        let publisher = Client::new(keys.clone());
+        publisher.add_relay(&url).await.unwrap();
+        publisher.connect_with_timeout(Duration::from_secs(2)).await;
+        let output = publisher
+            .send_event_builder(EventBuilder::text_note("Hello"))
+            .await
+            .unwrap();
+        let id: EventId = *output.id();
+
+        let client = Client::default();
+
+        // An unreachable hint doesn't abort the fetch
+        let nevent = Nip19Event::new(id, ["ws://127.0.0.1:1", url.as_str()])
+            .author(keys.public_key())
+            .kind(Kind::TextNote);
+        let fetched = client
+            .fetch(Nip19::Event(nevent), Duration::from_secs(2))
+            .await
+            .unwrap();
+        match fetched {
+            Some(FetchedEntity::Event(event)) => assert_eq!(event.id, id),
+            other => panic!("Unexpected entity: {other:?}"),
+        }
+
+        // The hints are removed after the fetch
+        assert!(client.pool().all_relays().await.is_empty());
+
+        // The author and the kind of the nevent are part of the filter
+        let nevent = Nip19Event::new(id, [url.as_str()]).kind(Kind::Reaction);
+        let fetched = client
+            .fetch(Nip19::Event(nevent), Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert!(fetched.is_none());
+    }
+}
@@ -5,6 +5,8 @@
 use std::fmt;
 
 use nostr::prelude::*;
+#[cfg(feature = "nip57")]
+use nostr::util::lnurl;
 use nostr_database::prelude::*;
 use nostr_relay_pool::__private::SharedStateError;
 use nostr_relay_pool::prelude::*;
@@ -22,6 +24,9 @@ pub enum Error {
     Database(DatabaseError),
     /// Signer error
     Signer(SignerError),
+    /// JSON error
+    #[cfg(feature = "nip44")]
+    Json(nostr::serde_json::Error),
     /// Zapper error
     #[cfg(feature = "nip57")]
     Zapper(ZapperError),
@@ -31,12 +36,14 @@ pub enum Error {
     Metadata(metadata::Error),
     /// Shared state error
     SharedState(SharedStateError),
+    /// NIP21 error
+    NIP21(nip21::Error),
     /// NIP57 error
     #[cfg(feature = "nip57")]
     NIP57(nip57::Error),
-    /// LNURL Pay
+    /// LNURL error
     #[cfg(feature = "nip57")]
-    LnUrlPay(lnurl_pay::Error),
+    LnUrl(lnurl::Error),
     /// NIP59
     #[cfg(feature = "nip59")]
     NIP59(nip59::Error),
@@ -47,12 +54,19 @@ pub enum Error {
     EventNotFound(EventId),
     /// Impossible to zap
     ImpossibleToZap(String),
+    /// Impossible to pay the relay
+    #[cfg(all(feature = "nip11", feature = "nip57"))]
+    ImpossibleToPayRelay(String),
     /// Broken down filters for gossip are empty
     GossipFiltersEmpty,
     /// DMs relays not found
     DMsRelaysNotFound,
     /// Metadata not found
     MetadataNotFound,
+    /// The NIP19 entity can't be fetched (i.e. a secret key)
+    UnsupportedNip19Entity,
+    /// The account deletion hasn't been confirmed
+    AccountDeletionNotConfirmed,
 }
 
 impl std::error::Error for Error {}
@@ -64,15 +78,18 @@ impl fmt::Display for Error {
             Self::RelayPool(e) => write!(f, "{e}"),
             Self::Database(e) => write!(f, "{e}"),
             Self::Signer(e) => write!(f, "{e}"),
+            #[cfg(feature = "nip44")]
+            Self::Json(e) => write!(f, "{e}"),
             #[cfg(feature = "nip57")]
             Self::Zapper(e) => write!(f, "{e}"),
             Self::EventBuilder(e) => write!(f, "{e}"),
             Self::Metadata(e) => write!(f, "{e}"),
             Self::SharedState(e) => write!(f, "{e}"),
+            Self::NIP21(e) => write!(f, "{e}"),
             #[cfg(feature = "nip57")]
             Self::NIP57(e) => write!(f, "{e}"),
             #[cfg(feature = "nip57")]
-            Self::LnUrlPay(e) => write!(f, "{e}"),
+            Self::LnUrl(e) => write!(f, "{e}"),
             #[cfg(feature = "nip59")]
             Self::NIP59(e) => write!(f, "{e}"),
             #[cfg(feature = "nip57")]
@@ -85,11 +102,17 @@ impl fmt::Display for Error {
             Self::ImpossibleToZap(id) => {
                 write!(f, "impossible to send zap: {id}")
             }
+            #[cfg(all(feature = "nip11", feature = "nip57"))]
+            Self::ImpossibleToPayRelay(reason) => {
+                write!(f, "impossible to pay relay: {reason}")
+            }
             Self::GossipFiltersEmpty => {
                 write!(f, "gossip broken down filters are empty")
             }
             Self::DMsRelaysNotFound => write!(f, "DMs relays not found"),
             Self::MetadataNotFound => write!(f, "metadata not found"),
+            Self::UnsupportedNip19Entity => write!(f, "unsupported NIP19 entity"),
+            Self::AccountDeletionNotConfirmed => write!(f, "account deletion not confirmed"),
         }
     }
 }
@@ -112,6 +135,13 @@ impl From<DatabaseError> for Error {
     }
 }
 
+#[cfg(feature = "nip44")]
+impl From<nostr::serde_json::Error> for Error {
+    fn from(e: nostr::serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
 impl From<SignerError> for Error {
     fn from(e: SignerError) -> Self {
         Self::Signer(e)
@@ -143,6 +173,12 @@ impl From<SharedStateError> for Error {
     }
 }
 
+impl From<nip21::Error> for Error {
+    fn from(e: nip21::Error) -> Self {
+        Self::NIP21(e)
+    }
+}
+
 #[cfg(feature = "nip57")]
 impl From<nip57::Error> for Error {
     fn from(e: nip57::Error) -> Self {
@@ -151,9 +187,9 @@ impl From<nip57::Error> for Error {
 }
 
 #[cfg(feature = "nip57")]
-impl From<lnurl_pay::Error> for Error {
-    fn from(e: lnurl_pay::Error) -> Self {
-        Self::LnUrlPay(e)
+impl From<lnurl::Error> for Error {
+    fn from(e: lnurl::Error) -> Self {
+        Self::LnUrl(e)
     }
 }
 
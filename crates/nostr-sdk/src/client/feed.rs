@@ -0,0 +1,456 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Feed
+
+use std::collections::HashSet;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::RwLock;
+
+use super::{Client, Error};
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Feed options
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedOptions {
+    page_size: usize,
+    timeout: Duration,
+    hide_replies: bool,
+    fold_reposts: bool,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        Self {
+            page_size: DEFAULT_PAGE_SIZE,
+            timeout: DEFAULT_TIMEOUT,
+            hide_replies: false,
+            fold_reposts: true,
+        }
+    }
+}
+
+impl FeedOptions {
+    /// New default options
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of events requested for each page (default: 50)
+    #[inline]
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Timeout for fetching the contact list and the pages (default: 10 secs)
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Hide the replies (default: false)
+    #[inline]
+    pub fn hide_replies(mut self, hide: bool) -> Self {
+        self.hide_replies = hide;
+        self
+    }
+
+    /// Fold the reposts (default: true)
+    ///
+    /// Show each note only once: skip the reposts of the notes already in the timeline.
+    #[inline]
+    pub fn fold_reposts(mut self, fold: bool) -> Self {
+        self.fold_reposts = fold;
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Timeline {
+    authors: HashSet<PublicKey>,
+    events: Events,
+    // IDs of the notes already in the timeline (directly or as reposts)
+    notes: HashSet<EventId>,
+    // `until` of the next page (exclusive of the oldest second already loaded)
+    until: Option<Timestamp>,
+}
+
+impl Timeline {
+    fn new(authors: HashSet<PublicKey>) -> Self {
+        Self {
+            authors,
+            events: Events::new(&[]),
+            notes: HashSet::new(),
+            until: None,
+        }
+    }
+
+    /// Insert the event, applying the folding options. Return `true` if inserted.
+    fn insert(&mut self, event: Event, opts: &FeedOptions) -> bool {
+        // Events matched by the `#delegation` filter: keep them only if the delegation is valid
+        if !self.authors.contains(&event.pubkey)
+            && !self.authors.contains(&event.effective_author())
+        {
+            return false;
+        }
+
+        match event.kind {
+            Kind::TextNote => {
+                if opts.hide_replies && nip10::parent_id(&event).is_some() {
+                    return false;
+                }
+
+                // Already in the timeline as a repost
+                if !self.notes.insert(event.id) && opts.fold_reposts {
+                    return false;
+                }
+
+                self.events.insert(event)
+            }
+            Kind::Repost => {
+                if opts.fold_reposts {
+                    if let Some(id) = event.tags.event_ids().next() {
+                        if !self.notes.insert(*id) {
+                            return false;
+                        }
+                    }
+                }
+
+                self.events.insert(event)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Feed
+///
+/// Reverse-chronological timeline of the notes and reposts of the followed public keys,
+/// with pagination and live updates.
+///
+/// Events published on behalf of a followed public key via NIP26 delegation are included too:
+/// they are kept only if the delegation is valid (see [`Event::effective_author`]).
+///
+/// Call [`Feed::close`] to close the live subscription.
+#[derive(Debug, Clone)]
+pub struct Feed {
+    client: Client,
+    authors: Vec<PublicKey>,
+    subscription_id: Option<SubscriptionId>,
+    timeline: Arc<RwLock<Timeline>>,
+    opts: FeedOptions,
+}
+
+impl Feed {
+    /// Build the feed from the contact list of the user
+    ///
+    /// Fetch the contact list, the first page of the timeline and subscribe to the new events.
+    pub async fn new(client: &Client, opts: FeedOptions) -> Result<Self, Error> {
+        let authors: Vec<PublicKey> = client.get_contact_list_public_keys(opts.timeout).await?;
+        Self::with_authors(client, authors, opts).await
+    }
+
+    /// Build the feed for the specified public keys
+    pub async fn with_authors<I>(
+        client: &Client,
+        authors: I,
+        opts: FeedOptions,
+    ) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = PublicKey>,
+    {
+        let authors: Vec<PublicKey> = authors.into_iter().collect();
+        let mut feed = Self {
+            client: client.clone(),
+            timeline: Arc::new(RwLock::new(Timeline::new(
+                authors.iter().copied().collect(),
+            ))),
+            authors,
+            subscription_id: None,
+            opts,
+        };
+
+        // An empty authors list would match every event
+        if feed.authors.is_empty() {
+            return Ok(feed);
+        }
+
+        // Get notifications before subscribing, to not miss any event
+        let notifications = client.notifications();
+
+        // Subscribe to new events
+        let now: Timestamp = client.time_provider().now();
+        let filters: Vec<Filter> = feed.filters(|f| f.since(now));
+        let output: Output<SubscriptionId> = client.subscribe(filters, None).await?;
+
+        spawn_handler(
+            client,
+            notifications,
+            output.val.clone(),
+            Arc::downgrade(&feed.timeline),
+            opts,
+        );
+
+        feed.subscription_id = Some(output.val);
+
+        // Load first page
+        feed.load_more().await?;
+
+        Ok(feed)
+    }
+
+    /// Filters of the events of the authors and of the events delegated by them
+    fn filters<F>(&self, f: F) -> Vec<Filter>
+    where
+        F: Fn(Filter) -> Filter,
+    {
+        let kinds = [Kind::TextNote, Kind::Repost];
+        vec![
+            f(Filter::new()
+                .authors(self.authors.iter().copied())
+                .kinds(kinds)),
+            f(Filter::new()
+                .delegators(self.authors.iter().copied())
+                .kinds(kinds)),
+        ]
+    }
+
+    /// Get the timeline (reverse-chronological order)
+    pub async fn events(&self) -> Vec<Event> {
+        let timeline = self.timeline.read().await;
+        timeline.events.iter().cloned().collect()
+    }
+
+    /// Load the next (older) page
+    ///
+    /// Return the number of the events added to the timeline.
+    pub async fn load_more(&self) -> Result<usize, Error> {
+        if self.authors.is_empty() {
+            return Ok(0);
+        }
+
+        let until: Option<Timestamp> = self.timeline.read().await.until;
+
+        let filters: Vec<Filter> = self.filters(|f| {
+            let f: Filter = f.limit(self.opts.page_size);
+            match until {
+                Some(until) => f.until(until),
+                None => f,
+            }
+        });
+
+        // The events of both the filters are merged: keep only the newest page
+        let page: Vec<Event> = self
+            .client
+            .fetch_events(filters, self.opts.timeout)
+            .await?
+            .into_iter()
+            .take(self.opts.page_size)
+            .collect();
+
+        let oldest: Timestamp = match page.last() {
+            Some(event) => event.created_at,
+            None => return Ok(0),
+        };
+
+        // A full page may not include all the events of its oldest second:
+        // fetch the whole second, so the next page can start just before it.
+        let boundary: Option<Events> = if page.len() >= self.opts.page_size {
+            let filters: Vec<Filter> = self.filters(|f| f.since(oldest).until(oldest));
+            Some(self.client.fetch_events(filters, self.opts.timeout).await?)
+        } else {
+            None
+        };
+
+        // Events are sorted from the newest: the most recent repost of a note is kept
+        let mut timeline = self.timeline.write().await;
+        let mut added: usize = 0;
+        for event in page.into_iter().chain(boundary.into_iter().flatten()) {
+            if timeline.insert(event, &self.opts) {
+                added += 1;
+            }
+        }
+
+        timeline.until = Some(oldest - Duration::from_secs(1));
+
+        Ok(added)
+    }
+
+    /// Close the live subscription
+    #[inline]
+    pub async fn close(&self) {
+        if let Some(id) = &self.subscription_id {
+            self.client.unsubscribe(id.clone()).await;
+        }
+    }
+}
+
+fn spawn_handler(
+    client: &Client,
+    mut notifications: Receiver<RelayPoolNotification>,
+    subscription_id: SubscriptionId,
+    timeline: Weak<RwLock<Timeline>>,
+    opts: FeedOptions,
+) {
+    client.state().spawn("feed handler", async move {
+        loop {
+            match notifications.recv().await {
+                Ok(RelayPoolNotification::Event {
+                    subscription_id: id,
+                    event,
+                    ..
+                }) => {
+                    if id != subscription_id {
+                        continue;
+                    }
+
+                    // Stop if the feed has been dropped
+                    let timeline = match timeline.upgrade() {
+                        Some(timeline) => timeline,
+                        None => break,
+                    };
+                    let mut timeline = timeline.write().await;
+                    timeline.insert((*event).clone(), &opts);
+                }
+                Ok(RelayPoolNotification::Shutdown) => break,
+                Ok(..) => {}
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!("Feed handler lagged: {n} notifications skipped.")
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_timeline_fold_reposts() {
+        let author = Keys::generate();
+        let reposter = Keys::generate();
+
+        let note = EventBuilder::text_note("Hello")
+            .sign_with_keys(&author)
+            .unwrap();
+        let repost = EventBuilder::repost(&note, None)
+            .sign_with_keys(&reposter)
+            .unwrap();
+
+        let authors = HashSet::from([author.public_key(), reposter.public_key()]);
+
+        // Repost first, then the note
+        let opts = FeedOptions::default();
+        let mut timeline = Timeline::new(authors.clone());
+        assert!(timeline.insert(repost.clone(), &opts));
+        assert!(!timeline.insert(note.clone(), &opts));
+        assert_eq!(timeline.events.len(), 1);
+
+        // Note first, then the repost
+        let mut timeline = Timeline::new(authors.clone());
+        assert!(timeline.insert(note.clone(), &opts));
+        assert!(!timeline.insert(repost.clone(), &opts));
+        assert_eq!(timeline.events.len(), 1);
+
+        // Not folded
+        let opts = FeedOptions::default().fold_reposts(false);
+        let mut timeline = Timeline::new(authors);
+        assert!(timeline.insert(repost, &opts));
+        assert!(timeline.insert(note, &opts));
+        assert_eq!(timeline.events.len(), 2);
+    }
+
+    #[test]
+    fn test_timeline_delegation() {
+        let delegator = Keys::generate();
+        let delegatee = Keys::generate();
+        let other = Keys::generate();
+
+        let delegated = |signer: &Keys| {
+            let tag = DelegationTag::new(&delegator, &signer.public_key(), Conditions::new());
+            EventBuilder::text_note("Delegated").tag(Tag::from_standardized(
+                TagStandard::Delegation {
+                    delegator: tag.delegator_pubkey(),
+                    conditions: tag.conditions(),
+                    sig: tag.signature(),
+                },
+            ))
+        };
+
+        let opts = FeedOptions::default();
+        let mut timeline = Timeline::new(HashSet::from([delegator.public_key()]));
+
+        // Valid delegation
+        let event = delegated(&delegatee).sign_with_keys(&delegatee).unwrap();
+        assert!(timeline.insert(event, &opts));
+
+        // Delegation signed for another public key
+        let event = delegated(&delegatee).sign_with_keys(&other).unwrap();
+        assert!(!timeline.insert(event, &opts));
+
+        // Not delegated
+        let event = EventBuilder::text_note("Hello")
+            .sign_with_keys(&other)
+            .unwrap();
+        assert!(!timeline.insert(event, &opts));
+    }
+
+    #[tokio::test]
+    async fn test_feed_pagination() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        // 3 notes in the same second, 2 in an older one
+        let author = Keys::generate();
+        let publisher = Client::new(author.clone());
+        publisher.add_relay(&url).await.unwrap();
+        publisher.connect_with_timeout(Duration::from_secs(2)).await;
+        for (i, created_at) in [1_000, 1_000, 1_000, 900, 900].into_iter().enumerate() {
+            let builder = EventBuilder::text_note(format!("Note #{i}"))
+                .custom_created_at(Timestamp::from(created_at));
+            publisher.send_event_builder(builder).await.unwrap();
+        }
+
+        let client = Client::default();
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        // The first page includes the whole boundary second
+        let opts = FeedOptions::default()
+            .page_size(2)
+            .timeout(Duration::from_secs(2));
+        let feed = Feed::with_authors(&client, [author.public_key()], opts)
+            .await
+            .unwrap();
+        assert_eq!(feed.events().await.len(), 3);
+
+        // The boundary isn't fetched again
+        assert_eq!(feed.load_more().await.unwrap(), 2);
+        assert_eq!(feed.load_more().await.unwrap(), 0);
+
+        let events: Vec<Event> = feed.events().await;
+        assert_eq!(events.len(), 5);
+        assert!(events
+            .windows(2)
+            .all(|w| w[0].created_at >= w[1].created_at));
+
+        feed.close().await;
+    }
+}
@@ -0,0 +1,554 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Inbox
+
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use async_utility::time;
+use nostr::prelude::*;
+use nostr::serde_json::{self, json, Value};
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::sync::{mpsc, RwLock};
+
+use super::{Client, Error};
+
+/// `d` tag of the NIP78 event used to store the read state
+const READ_STATE_IDENTIFIER: &str = "nostr-sdk/inbox";
+const STREAM_CHANNEL_SIZE: usize = 1024;
+/// Max number of IDs kept in the read state
+const MAX_READ_IDS: usize = 5_000;
+/// Delay before storing the read state, to not sign a new event for every change
+const SAVE_READ_STATE_DELAY: Duration = Duration::from_secs(2);
+/// Max randomization of the `created_at` in the past (NIP59 gift wraps)
+const CREATED_AT_TWEAK: Duration = Duration::from_secs(2 * 24 * 60 * 60);
+
+/// Kinds received in the inbox
+const INBOX_KINDS: [Kind; 6] = [
+    Kind::TextNote,
+    Kind::Repost,
+    Kind::Reaction,
+    Kind::ZapReceipt,
+    Kind::EncryptedDirectMessage,
+    Kind::GiftWrap,
+];
+
+/// Read state
+///
+/// The events created before `until` and the events in `ids` are read.
+///
+/// The `created_at` is chosen by the author (and randomized up to 2 days in the past for the NIP59 gift wraps),
+/// so marking all the events as read moves `until` 2 days before the local time
+/// and keeps the IDs of the events of the last 2 days.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ReadState {
+    until: Timestamp,
+    /// Read events, with their `created_at` (to prune the ones created before `until`)
+    ids: HashMap<EventId, Timestamp>,
+    /// `created_at` of the last stored read state event
+    updated_at: Timestamp,
+}
+
+impl ReadState {
+    fn from_event(event: &Event) -> Option<Self> {
+        let value: Value = serde_json::from_str(&event.content).ok()?;
+        let until: Timestamp = Timestamp::from(value.get("until")?.as_u64()?);
+        let ids: HashMap<EventId, Timestamp> = value
+            .get("ids")?
+            .as_array()?
+            .iter()
+            .filter_map(|item| {
+                let item = item.as_array()?;
+                let id: EventId = EventId::from_hex(item.first()?.as_str()?).ok()?;
+                let created_at: Timestamp = Timestamp::from(item.get(1)?.as_u64()?);
+                Some((id, created_at))
+            })
+            .collect();
+        Some(Self {
+            until,
+            ids,
+            updated_at: event.created_at,
+        })
+    }
+
+    fn as_json(&self) -> String {
+        let ids: Vec<Value> = self
+            .ids
+            .iter()
+            .map(|(id, created_at)| json!([id.to_hex(), created_at.as_u64()]))
+            .collect();
+        json!({ "until": self.until.as_u64(), "ids": ids }).to_string()
+    }
+
+    #[inline]
+    fn is_read(&self, event: &Event) -> bool {
+        event.created_at <= self.until || self.ids.contains_key(&event.id)
+    }
+
+    /// Mark an event as read
+    ///
+    /// Return `false` if it was already read.
+    fn mark(&mut self, id: EventId, created_at: Timestamp) -> bool {
+        if created_at <= self.until || self.ids.insert(id, created_at).is_some() {
+            return false;
+        }
+
+        self.prune();
+        true
+    }
+
+    /// Mark as read all the events created before `now` (minus the tweak) and the `unread` ones
+    fn mark_all<'a, I>(&mut self, now: Timestamp, unread: I)
+    where
+        I: IntoIterator<Item = &'a Event>,
+    {
+        self.until = self.until.max(now - CREATED_AT_TWEAK);
+        for event in unread.into_iter() {
+            self.ids.insert(event.id, event.created_at);
+        }
+        self.prune();
+    }
+
+    /// Remove the IDs covered by `until` and cap their number
+    ///
+    /// If there are too many IDs, `until` is moved forward, marking as read the oldest events.
+    fn prune(&mut self) {
+        if self.ids.len() > MAX_READ_IDS {
+            let mut created_at: Vec<Timestamp> = self.ids.values().copied().collect();
+            created_at.sort_unstable();
+            self.until = created_at[created_at.len() - MAX_READ_IDS - 1];
+        }
+
+        let until: Timestamp = self.until;
+        self.ids.retain(|_, created_at| *created_at > until);
+    }
+}
+
+#[derive(Debug, Default)]
+struct InboxState {
+    read: ReadState,
+    unread: HashMap<EventId, Event>,
+    /// The read state has changed since the last store
+    dirty: bool,
+    /// A store of the read state has been scheduled
+    save_scheduled: bool,
+}
+
+/// Inbox
+///
+/// Subscribe to the events that mention the user (`p` tag),
+/// like replies, reposts, reactions, zaps and direct messages,
+/// and keep track of the unread ones.
+///
+/// The read state is stored in the database, as a NIP78 event signed by the user
+/// (not published to relays), so it's restored when the inbox is re-opened.
+///
+/// Call [`Inbox::close`] to close the subscription.
+#[derive(Debug, Clone)]
+pub struct Inbox {
+    client: Client,
+    public_key: PublicKey,
+    subscription_id: SubscriptionId,
+    state: Arc<RwLock<InboxState>>,
+    new_events: Sender<Event>,
+}
+
+impl Inbox {
+    /// Subscribe to the inbox of the signer public key
+    ///
+    /// The unread events already stored in the database are loaded immediately.
+    pub async fn subscribe(client: &Client) -> Result<Self, Error> {
+        let signer = client.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+
+        // Load read state
+        let filter: Filter = Filter::new()
+            .author(public_key)
+            .kind(Kind::ApplicationSpecificData)
+            .identifier(READ_STATE_IDENTIFIER)
+            .limit(1);
+        let events: Events = client.database().query(vec![filter]).await?;
+        let read: ReadState = events
+            .first()
+            .and_then(ReadState::from_event)
+            .unwrap_or_default();
+
+        // Load the unread events from database
+        let filter: Filter = inbox_filter(public_key).since(read.until);
+        let unread: HashMap<EventId, Event> = client
+            .database()
+            .query(vec![filter])
+            .await?
+            .into_iter()
+            .filter(|e| e.pubkey != public_key && !read.is_read(e))
+            .map(|e| (e.id, e))
+            .collect();
+
+        let state: Arc<RwLock<InboxState>> = Arc::new(RwLock::new(InboxState {
+            read,
+            unread,
+            ..Default::default()
+        }));
+        let (new_events, ..) = broadcast::channel::<Event>(STREAM_CHANNEL_SIZE);
+
+        // Get notifications before subscribing, to not miss any event
+        let notifications = client.notifications();
+
+        let output: Output<SubscriptionId> = client
+            .subscribe(vec![inbox_filter(public_key)], None)
+            .await?;
+
+        // Spawn handler
+        spawn_handler(
+            client,
+            notifications,
+            output.val.clone(),
+            public_key,
+            Arc::downgrade(&state),
+            new_events.clone(),
+        );
+
+        Ok(Self {
+            client: client.clone(),
+            public_key,
+            subscription_id: output.val,
+            state,
+            new_events,
+        })
+    }
+
+    /// Get the unread events, newest first
+    pub async fn unread(&self) -> Vec<Event> {
+        let state = self.state.read().await;
+        let mut events: Vec<Event> = state.unread.values().cloned().collect();
+        events.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        events
+    }
+
+    /// Get the number of unread events
+    pub async fn unread_count(&self) -> usize {
+        let state = self.state.read().await;
+        state.unread.len()
+    }
+
+    /// Mark an event as read
+    ///
+    /// The read state is stored with a small delay, to group the changes.
+    pub async fn mark_read(&self, id: &EventId) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+
+        let created_at: Timestamp = match state.unread.remove(id) {
+            Some(event) => event.created_at,
+            // Not received yet
+            None => self.client.time_provider().now(),
+        };
+
+        if state.read.mark(*id, created_at) {
+            self.schedule_save(&mut state);
+        }
+
+        Ok(())
+    }
+
+    /// Mark all the events as read
+    ///
+    /// The read state is stored with a small delay, to group the changes.
+    pub async fn mark_all_read(&self) -> Result<(), Error> {
+        let mut state = self.state.write().await;
+
+        let now: Timestamp = self.client.time_provider().now();
+        let InboxState { read, unread, .. } = &mut *state;
+        read.mark_all(now, unread.values());
+        unread.clear();
+
+        self.schedule_save(&mut state);
+
+        Ok(())
+    }
+
+    /// Store the read state, if changed
+    ///
+    /// Call it to not wait for the delayed store (i.e. before exiting).
+    pub async fn flush(&self) -> Result<(), Error> {
+        let read: ReadState = {
+            let mut state = self.state.write().await;
+            state.save_scheduled = false;
+
+            if !state.dirty {
+                return Ok(());
+            }
+
+            state.dirty = false;
+            state.read.clone()
+        };
+
+        // Don't keep the lock while signing
+        match self.save_read_state(&read).await {
+            Ok(updated_at) => {
+                let mut state = self.state.write().await;
+                state.read.updated_at = state.read.updated_at.max(updated_at);
+                Ok(())
+            }
+            Err(e) => {
+                self.state.write().await.dirty = true;
+                Err(e)
+            }
+        }
+    }
+
+    fn schedule_save(&self, state: &mut InboxState) {
+        state.dirty = true;
+
+        if state.save_scheduled {
+            return;
+        }
+
+        state.save_scheduled = true;
+
+        let inbox: Self = self.clone();
+        self.client.state().spawn("inbox read state", async move {
+            time::sleep(SAVE_READ_STATE_DELAY).await;
+            if let Err(e) = inbox.flush().await {
+                tracing::error!(error = %e, "Impossible to store inbox read state.");
+            }
+        });
+    }
+
+    /// Stream of the new unread events
+    ///
+    /// Only the events received after this call are streamed.
+    pub fn stream(&self) -> ReceiverStream<Event> {
+        let mut new_events: Receiver<Event> = self.new_events.subscribe();
+        let (tx, rx) = mpsc::channel::<Event>(STREAM_CHANNEL_SIZE);
+
+        let future = self.client.state().until_shutdown(async move {
+            loop {
+                match new_events.recv().await {
+                    Ok(event) => {
+                        // Stop if the stream has been dropped
+                        if tx.send(event).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("Inbox stream lagged: {n} events skipped.")
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        self.client.state().spawn("inbox stream", future);
+
+        ReceiverStream::new(rx)
+    }
+
+    /// Close the subscription
+    ///
+    /// The pending read state is stored and the inbox is no longer updated.
+    pub async fn close(&self) {
+        if let Err(e) = self.flush().await {
+            tracing::error!(error = %e, "Impossible to store inbox read state.");
+        }
+
+        self.client.unsubscribe(self.subscription_id.clone()).await;
+    }
+
+    /// Sign and store the read state
+    ///
+    /// Return the `created_at` of the stored event.
+    async fn save_read_state(&self, read: &ReadState) -> Result<Timestamp, Error> {
+        // Always increase the `created_at`, to replace the previous read state
+        let created_at: Timestamp = self.client.time_provider().now().max(read.updated_at + 1);
+
+        let signer = self.client.signer().await?;
+        let event: Event = EventBuilder::application_data(READ_STATE_IDENTIFIER, read.as_json())
+            .custom_created_at(created_at)
+            .build(self.public_key)
+            .sign(&signer)
+            .await
+            .map_err(|e| Error::EventBuilder(e.into()))?;
+        self.client.database().save_event(&event).await?;
+
+        Ok(created_at)
+    }
+}
+
+#[inline]
+fn inbox_filter(public_key: PublicKey) -> Filter {
+    Filter::new().pubkey(public_key).kinds(INBOX_KINDS)
+}
+
+fn spawn_handler(
+    client: &Client,
+    mut notifications: Receiver<RelayPoolNotification>,
+    subscription_id: SubscriptionId,
+    public_key: PublicKey,
+    state: Weak<RwLock<InboxState>>,
+    new_events: Sender<Event>,
+) {
+    client.state().spawn("inbox handler", async move {
+        loop {
+            match notifications.recv().await {
+                Ok(RelayPoolNotification::Event {
+                    subscription_id: id,
+                    event,
+                    ..
+                }) => {
+                    if id != subscription_id || event.pubkey == public_key {
+                        continue;
+                    }
+
+                    // Stop if the inbox has been dropped
+                    let state = match state.upgrade() {
+                        Some(state) => state,
+                        None => break,
+                    };
+                    let mut state = state.write().await;
+
+                    if state.read.is_read(&event) || state.unread.contains_key(&event.id) {
+                        continue;
+                    }
+
+                    let event: Event = (*event).clone();
+                    state.unread.insert(event.id, event.clone());
+
+                    // Send to the streams (ignore error if there are no streams)
+                    let _ = new_events.send(event);
+                }
+                Ok(RelayPoolNotification::Shutdown) => break,
+                Ok(..) => {}
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!("Inbox handler lagged: {n} notifications skipped.")
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+
+    fn note(keys: &Keys, created_at: u64) -> Event {
+        EventBuilder::text_note("Hello")
+            .custom_created_at(Timestamp::from(created_at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_read_state() {
+        let keys = Keys::generate();
+        let read_event = note(&keys, 1_000);
+        let unread_event = note(&keys, 2_000);
+
+        let read = ReadState {
+            until: Timestamp::from(1_500),
+            ids: HashMap::from([(EventId::all_zeros(), Timestamp::from(1_600))]),
+            updated_at: Timestamp::from(3_000),
+        };
+        assert!(read.is_read(&read_event));
+        assert!(!read.is_read(&unread_event));
+
+        let event = EventBuilder::application_data(READ_STATE_IDENTIFIER, read.as_json())
+            .custom_created_at(read.updated_at)
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(ReadState::from_event(&event), Some(read));
+    }
+
+    #[test]
+    fn test_read_state_mark_all() {
+        let keys = Keys::generate();
+        let now = Timestamp::from(1_000_000);
+
+        let unread = note(&keys, now.as_u64() - 60);
+        let old = note(&keys, 1_000);
+
+        let mut read = ReadState::default();
+        read.mark_all(now, [&unread, &old]);
+        assert!(read.is_read(&unread));
+        assert!(read.is_read(&old));
+
+        // The covered IDs are pruned
+        assert_eq!(read.ids.len(), 1);
+
+        // A gift wrap received later, with a `created_at` in the past, is still unread
+        let gift_wrap = note(&keys, now.as_u64() - 60 * 60);
+        assert!(!read.is_read(&gift_wrap));
+
+        assert!(read.mark(gift_wrap.id, gift_wrap.created_at));
+        assert!(!read.mark(gift_wrap.id, gift_wrap.created_at));
+        assert!(read.is_read(&gift_wrap));
+    }
+
+    #[test]
+    fn test_read_state_max_ids() {
+        let mut read = ReadState::default();
+        for i in 1..=(MAX_READ_IDS as u64 + 10) {
+            let mut bytes = [0u8; 32];
+            bytes[..8].copy_from_slice(&i.to_be_bytes());
+            read.mark(EventId::from_byte_array(bytes), Timestamp::from(i));
+        }
+        assert_eq!(read.ids.len(), MAX_READ_IDS);
+        assert_eq!(read.until, Timestamp::from(10));
+    }
+
+    #[tokio::test]
+    async fn test_inbox() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let keys = Keys::generate();
+        let client = Client::builder()
+            .signer(keys.clone())
+            .database(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+                events: true,
+                max_events: None,
+            }))
+            .build();
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        let inbox = Inbox::subscribe(&client).await.unwrap();
+        let mut stream = inbox.stream();
+
+        // Mention from another user
+        let other = Client::new(Keys::generate());
+        other.add_relay(&url).await.unwrap();
+        other.connect_with_timeout(Duration::from_secs(2)).await;
+        let builder = EventBuilder::text_note("Hello").tag(Tag::public_key(keys.public_key()));
+        other.send_event_builder(builder).await.unwrap();
+
+        let event: Event = time::timeout(Some(Duration::from_secs(5)), stream.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(inbox.unread_count().await, 1);
+
+        // Stored at close, without waiting for the delayed save
+        inbox.mark_read(&event.id).await.unwrap();
+        assert_eq!(inbox.unread_count().await, 0);
+        inbox.close().await;
+
+        let filter = Filter::new()
+            .author(keys.public_key())
+            .kind(Kind::ApplicationSpecificData)
+            .identifier(READ_STATE_IDENTIFIER);
+        assert_eq!(client.database().count(vec![filter]).await.unwrap(), 1);
+
+        // Reload the read state
+        let inbox = Inbox::subscribe(&client).await.unwrap();
+        assert!(inbox.state.read().await.read.ids.contains_key(&event.id));
+        assert_eq!(inbox.unread_count().await, 0);
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Interactions
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Weak};
+
+use nostr::prelude::*;
+use nostr_relay_pool::prelude::*;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::RwLock;
+
+use super::{Client, Error};
+
+type Counts = Arc<RwLock<HashMap<EventId, InteractionCounts>>>;
+
+/// Interaction counts of a note
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InteractionCounts {
+    /// Number of direct replies
+    pub replies: usize,
+    /// Number of reposts
+    pub reposts: usize,
+    /// Number of reactions, by emoji
+    ///
+    /// Likes with empty content are counted as `+`.
+    pub reactions: HashMap<String, usize>,
+    /// Number of zaps
+    ///
+    /// Only the valid zap receipts are counted (requires the `nip57` feature).
+    pub zaps: usize,
+    /// Zaps total (millisats)
+    ///
+    /// Calculated from the amount of the invoices of the zap receipts.
+    pub zap_msats: u64,
+}
+
+impl InteractionCounts {
+    /// Total number of reactions
+    #[inline]
+    pub fn total_reactions(&self) -> usize {
+        self.reactions.values().sum()
+    }
+
+    fn handle(&mut self, event: &Event) {
+        match event.kind {
+            Kind::TextNote => self.replies += 1,
+            Kind::Repost => self.reposts += 1,
+            Kind::Reaction => {
+                let emoji: &str = if event.content.is_empty() {
+                    "+"
+                } else {
+                    &event.content
+                };
+                *self.reactions.entry(emoji.to_string()).or_default() += 1;
+            }
+            Kind::ZapReceipt => {
+                if let Some(msats) = zap_amount(event) {
+                    self.zaps += 1;
+                    self.zap_msats = self.zap_msats.saturating_add(msats);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Live interactions
+///
+/// Subscribe to the replies, reposts, reactions and zaps referencing a set of notes
+/// and keep the counts updated as new events arrive.
+///
+/// Call [`Interactions::close`] to close the subscription.
+#[derive(Debug, Clone)]
+pub struct Interactions {
+    client: Client,
+    subscription_id: SubscriptionId,
+    counts: Counts,
+}
+
+impl Interactions {
+    /// Subscribe to the interactions of the notes
+    pub async fn subscribe<I>(client: &Client, ids: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = EventId>,
+    {
+        let ids: HashSet<EventId> = ids.into_iter().collect();
+        let counts: Counts = Arc::new(RwLock::new(
+            ids.iter()
+                .map(|id| (*id, InteractionCounts::default()))
+                .collect(),
+        ));
+
+        // Get notifications before subscribing, to not miss any event
+        let notifications = client.notifications();
+
+        let filter: Filter = Filter::new()
+            .kinds([
+                Kind::TextNote,
+                Kind::Repost,
+                Kind::Reaction,
+                Kind::ZapReceipt,
+            ])
+            .events(ids);
+        let output: Output<SubscriptionId> = client.subscribe(vec![filter], None).await?;
+
+        // Spawn handler
+        spawn_handler(
+            client,
+            notifications,
+            output.val.clone(),
+            Arc::downgrade(&counts),
+        );
+
+        Ok(Self {
+            client: client.clone(),
+            subscription_id: output.val,
+            counts,
+        })
+    }
+
+    /// Get the interaction counts of a note
+    pub async fn get(&self, id: &EventId) -> Option<InteractionCounts> {
+        let counts = self.counts.read().await;
+        counts.get(id).cloned()
+    }
+
+    /// Get the interaction counts of all the notes
+    pub async fn all(&self) -> HashMap<EventId, InteractionCounts> {
+        let counts = self.counts.read().await;
+        counts.clone()
+    }
+
+    /// Close the subscription
+    ///
+    /// The counts are no longer updated.
+    #[inline]
+    pub async fn close(&self) {
+        self.client.unsubscribe(self.subscription_id.clone()).await;
+    }
+}
+
+fn spawn_handler(
+    client: &Client,
+    mut notifications: Receiver<RelayPoolNotification>,
+    subscription_id: SubscriptionId,
+    counts: Weak<RwLock<HashMap<EventId, InteractionCounts>>>,
+) {
+    client.state().spawn("interactions handler", async move {
+        loop {
+            match notifications.recv().await {
+                Ok(RelayPoolNotification::Event {
+                    subscription_id: id,
+                    event,
+                    ..
+                }) => {
+                    if id != subscription_id {
+                        continue;
+                    }
+
+                    // Stop if the interactions have been dropped
+                    let counts = match counts.upgrade() {
+                        Some(counts) => counts,
+                        None => break,
+                    };
+                    let mut counts = counts.write().await;
+
+                    for target in targets(&event).iter() {
+                        if let Some(c) = counts.get_mut(target) {
+                            c.handle(&event);
+                        }
+                    }
+                }
+                Ok(RelayPoolNotification::Shutdown) => break,
+                Ok(..) => {}
+                Err(RecvError::Lagged(n)) => {
+                    tracing::warn!("Interactions handler lagged: {n} notifications skipped.")
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Get the notes the event is related to
+fn targets(event: &Event) -> HashSet<EventId> {
+    match event.kind {
+        // Replies are related only to the parent, not to the root of the thread
+        Kind::TextNote => nip10::parent_id(event).into_iter().collect(),
+        // Reactions are related to the last `e` tag
+        Kind::Reaction => event.tags.event_ids().last().copied().into_iter().collect(),
+        _ => event.tags.event_ids().copied().collect(),
+    }
+}
+
+/// Validate the zap receipt and get the amount of its invoice
+///
+/// The `amount` tag of the zap request isn't trusted: anyone can publish a receipt.
+#[cfg(feature = "nip57")]
+fn zap_amount(event: &Event) -> Option<u64> {
+    let data: ZapReceiptData = nip57::validate_zap_receipt(event).ok()?;
+    Some(data.amount_msats().unwrap_or_default())
+}
+
+/// The zap receipts can't be validated without the `nip57` feature
+#[cfg(not(feature = "nip57"))]
+fn zap_amount(_event: &Event) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply_targets() {
+        let keys = Keys::generate();
+        let root = EventId::all_zeros();
+        let parent = EventId::from_byte_array([1; 32]);
+
+        // Reply to a reply: not counted for the root
+        let reply = EventBuilder::text_note("Reply")
+            .tags([
+                Tag::parse(["e", &root.to_hex(), "", "root"]).unwrap(),
+                Tag::parse(["e", &parent.to_hex(), "", "reply"]).unwrap(),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(targets(&reply), HashSet::from([parent]));
+
+        // Direct reply to the root
+        let reply = EventBuilder::text_note("Reply")
+            .tag(Tag::parse(["e", &root.to_hex(), "", "root"]).unwrap())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(targets(&reply), HashSet::from([root]));
+    }
+
+    #[test]
+    #[cfg(feature = "nip57")]
+    fn test_zap_amount() {
+        // Zap receipt of the NIP57 example
+        let bolt11 = "lnbc10u1p3unwfusp5t9r3yymhpfqculx78u027lxspgxcr2n2987mx2j55nnfs95nxnzqpp5jmrh92pfld78spqs78v9euf2385t83uvpwk9ldrlvf6ch7tpascqhp5zvkrmemgth3tufcvflmzjzfvjt023nazlhljz2n9hattj4f8jq8qxqyjw5qcqpjrzjqtc4fc44feggv7065fqe5m4ytjarg3repr5j9el35xhmtfexc42yczarjuqqfzqqqqqqqqlgqqqqqqgq9q9qxpqysgq079nkq507a5tw7xgttmj4u990j7wfggtrasah5gd4ywfr2pjcn29383tphp4t48gquelz9z78p4cq7ml3nrrphw5w6eckhjwmhezhnqpy6gyf0";
+        let description = r#"{"pubkey":"32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245","content":"","id":"d9cc14d50fcb8c27539aacf776882942c1a11ea4472f8cdec1dea82fab66279d","created_at":1674164539,"sig":"77127f636577e9029276be060332ea565deaf89ff215a494ccff16ae3f757065e2bc59b2e8c113dd407917a010b3abd36c8d7ad84c0e3ab7dab3a0b0caa9835d","kind":9734,"tags":[["e","3624762a1274dd9636e0c552b53086d70bc88c165bc4dc0f9e836a1eaf86c3b8"],["p","32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245"],["relays","wss://relay.damus.io","wss://nostr-relay.wlvs.space","wss://nostr.fmt.wiz.biz","wss://relay.nostr.bg","wss://nostr.oxtr.dev","wss://nostr.v0l.io","wss://brb.io","wss://nostr.bitcoiner.social","ws://monad.jb55.com:8080","wss://relay.snort.social"]]}"#;
+        let zapped =
+            EventId::from_hex("3624762a1274dd9636e0c552b53086d70bc88c165bc4dc0f9e836a1eaf86c3b8")
+                .unwrap();
+
+        let keys = Keys::generate();
+        let receipt = EventBuilder::new(Kind::ZapReceipt, "")
+            .tags([
+                Tag::event(zapped),
+                Tag::parse(["bolt11", bolt11]).unwrap(),
+                Tag::parse(["description", description]).unwrap(),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(targets(&receipt), HashSet::from([zapped]));
+
+        let mut counts = InteractionCounts::default();
+        counts.handle(&receipt);
+        assert_eq!(counts.zaps, 1);
+        assert_eq!(counts.zap_msats, 1_000_000);
+
+        // Spoofed amount: the zap request doesn't match the invoice
+        let spoofed =
+            description.replace(r#"["relays","#, r#"["amount","21000000000"],["relays","#);
+        let receipt = EventBuilder::new(Kind::ZapReceipt, "")
+            .tags([
+                Tag::event(zapped),
+                Tag::parse(["bolt11", bolt11]).unwrap(),
+                Tag::parse(["description", spoofed.as_str()]).unwrap(),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+        counts.handle(&receipt);
+        assert_eq!(counts.zaps, 1);
+        assert_eq!(counts.zap_msats, 1_000_000);
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Identity migration
+
+use std::time::Duration;
+
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+
+use super::{Client, Error};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_HISTORY_LIMIT: usize = 500;
+
+/// Identity migration options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationOptions {
+    timeout: Duration,
+    history: Vec<Kind>,
+    history_limit: usize,
+    notice: Option<String>,
+}
+
+impl Default for MigrationOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            history: Vec::new(),
+            history_limit: DEFAULT_HISTORY_LIMIT,
+            notice: None,
+        }
+    }
+}
+
+impl MigrationOptions {
+    /// New default options
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timeout for fetching the events of the old identity (default: 10 secs)
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Kinds of the history events to republish with the new identity (default: none)
+    ///
+    /// Only the regular and the addressable events can be republished:
+    /// encrypted messages, seals, gift wraps, zap receipts and deletions are always skipped.
+    #[inline]
+    pub fn history<I>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = Kind>,
+    {
+        self.history = kinds.into_iter().collect();
+        self
+    }
+
+    /// Max number of history events to republish (default: 500)
+    #[inline]
+    pub fn history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self
+    }
+
+    /// Custom content of the migration notice published by the new identity
+    #[inline]
+    pub fn notice<S>(mut self, content: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.notice = Some(content.into());
+        self
+    }
+}
+
+/// Identity migration step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MigrationStep {
+    /// Copy of a replaceable event of the old identity
+    Copy(Kind),
+    /// Notice published by the new identity
+    Notice,
+    /// Notice published by the old identity
+    MovedNotice,
+    /// Fetch of the history events
+    History,
+}
+
+/// Identity migration output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Migration {
+    /// Copied relay list
+    pub relay_list: Option<EventId>,
+    /// Copied metadata
+    pub metadata: Option<EventId>,
+    /// Copied contact list
+    pub contact_list: Option<EventId>,
+    /// Copied mute list (the private entries are re-encrypted for the new identity)
+    pub mute_list: Option<EventId>,
+    /// Copied NIP17 inbox relays
+    pub inbox_relays: Option<EventId>,
+    /// Notice published by the new identity, referencing the old one
+    pub notice: Option<EventId>,
+    /// Notice published by the old identity, pointing to the new one
+    pub moved_notice: Option<EventId>,
+    /// Republished history events (old ID, new ID)
+    pub republished: Vec<(EventId, EventId)>,
+    /// History events that can't be republished
+    pub skipped: Vec<EventId>,
+    /// Failed steps, with the error
+    pub failed: Vec<(MigrationStep, String)>,
+}
+
+impl Migration {
+    /// Check if all the steps succeeded
+    ///
+    /// The skipped history events aren't considered failures.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Replaceable events copied to the new identity
+const COPIED_KINDS: [Kind; 5] = [
+    Kind::RelayList,
+    Kind::Metadata,
+    Kind::ContactList,
+    Kind::MuteList,
+    Kind::InboxRelays,
+];
+
+/// Check if the event can be re-signed by another identity
+fn is_republishable(event: &Event) -> bool {
+    let kind: Kind = event.kind;
+
+    if !kind.is_regular() && !kind.is_parameterized_replaceable() {
+        return false;
+    }
+
+    !matches!(
+        kind,
+        Kind::EncryptedDirectMessage
+            | Kind::PrivateDirectMessage
+            | Kind::Seal
+            | Kind::GiftWrap
+            | Kind::ZapReceipt
+            | Kind::EventDeletion
+    )
+}
+
+/// Decrypt the private content of the old identity and encrypt it for the new one
+///
+/// Requires the `nip04` or `nip44` feature, depending on the encryption of the content.
+async fn reencrypt(old_keys: &Keys, new_keys: &Keys, content: &str) -> Result<String, Error> {
+    let old_public_key: PublicKey = old_keys.public_key();
+    let new_public_key: PublicKey = new_keys.public_key();
+
+    if content.contains("?iv=") {
+        let plain: String = old_keys.nip04_decrypt(&old_public_key, content).await?;
+        Ok(new_keys.nip04_encrypt(&new_public_key, &plain).await?)
+    } else {
+        let plain: String = old_keys.nip44_decrypt(&old_public_key, content).await?;
+        Ok(new_keys.nip44_encrypt(&new_public_key, &plain).await?)
+    }
+}
+
+impl Client {
+    /// Migrate to a new identity
+    ///
+    /// Publish with the `new_keys`:
+    /// * the copies of the relay list, metadata, contact list, mute list and NIP17 inbox relays of the old identity;
+    /// * a notice referencing the old identity;
+    /// * the events of the history selected with [`MigrationOptions::history`], re-signed (the original `created_at` is kept).
+    ///
+    /// Publish also a notice, signed with the `old_keys`, pointing to the new identity.
+    ///
+    /// A failed step doesn't stop the migration: it's reported in [`Migration::failed`],
+    /// so the migration can be checked with [`Migration::is_complete`] and the failed steps retried.
+    ///
+    /// The re-signed events have new IDs: replies, reactions and reposts of the other users will keep referencing the old ones.
+    pub async fn migrate_identity(
+        &self,
+        old_keys: &Keys,
+        new_keys: &Keys,
+        opts: MigrationOptions,
+    ) -> Result<Migration, Error> {
+        let old_public_key: PublicKey = old_keys.public_key();
+        let new_public_key: PublicKey = new_keys.public_key();
+
+        let mut migration: Migration = Migration::default();
+
+        // Copy replaceable events
+        let filter: Filter = Filter::new().author(old_public_key).kinds(COPIED_KINDS);
+        match self.fetch_events(vec![filter], opts.timeout).await {
+            Ok(events) => {
+                for kind in COPIED_KINDS {
+                    // Get first event (result of `fetch_events` is sorted DESC by timestamp)
+                    let event: &Event = match events.iter().find(|e| e.kind == kind) {
+                        Some(event) => event,
+                        None => continue,
+                    };
+
+                    match self.copy_event(old_keys, new_keys, event).await {
+                        Ok(id) => {
+                            let id: Option<EventId> = Some(id);
+                            match kind {
+                                Kind::RelayList => migration.relay_list = id,
+                                Kind::Metadata => migration.metadata = id,
+                                Kind::ContactList => migration.contact_list = id,
+                                Kind::MuteList => migration.mute_list = id,
+                                _ => migration.inbox_relays = id,
+                            }
+                        }
+                        Err(e) => migration
+                            .failed
+                            .push((MigrationStep::Copy(kind), e.to_string())),
+                    }
+                }
+            }
+            Err(e) => {
+                let e: String = e.to_string();
+                migration.failed.extend(
+                    COPIED_KINDS
+                        .into_iter()
+                        .map(|kind| (MigrationStep::Copy(kind), e.clone())),
+                );
+            }
+        }
+
+        // Notice of the new identity
+        let content: String = match opts.notice {
+            Some(content) => content,
+            None => format!("Migrated from {}", old_public_key.to_nostr_uri()?),
+        };
+        let notice: Event = EventBuilder::text_note(content)
+            .tag(Tag::public_key(old_public_key))
+            .sign_with_keys(new_keys)?;
+        match self.send_event(notice).await {
+            Ok(output) => migration.notice = Some(output.val),
+            Err(e) => migration
+                .failed
+                .push((MigrationStep::Notice, e.to_string())),
+        }
+
+        // Notice of the old identity
+        let content: String = format!(
+            "This account has moved to {}",
+            new_public_key.to_nostr_uri()?
+        );
+        let moved: Event = EventBuilder::text_note(content)
+            .tag(Tag::public_key(new_public_key))
+            .sign_with_keys(old_keys)?;
+        match self.send_event(moved).await {
+            Ok(output) => migration.moved_notice = Some(output.val),
+            Err(e) => migration
+                .failed
+                .push((MigrationStep::MovedNotice, e.to_string())),
+        }
+
+        // Republish history
+        if !opts.history.is_empty() && opts.history_limit > 0 {
+            let filter: Filter = Filter::new()
+                .author(old_public_key)
+                .kinds(opts.history)
+                .limit(opts.history_limit);
+            match self.fetch_events(vec![filter], opts.timeout).await {
+                Ok(events) => {
+                    for event in events.into_iter() {
+                        if !is_republishable(&event) {
+                            migration.skipped.push(event.id);
+                            continue;
+                        }
+
+                        let copy: Event = EventBuilder::new(event.kind, &event.content)
+                            .tags(event.tags.clone())
+                            .custom_created_at(event.created_at)
+                            .sign_with_keys(new_keys)?;
+
+                        match self.send_event(copy).await {
+                            Ok(output) => migration.republished.push((event.id, output.val)),
+                            Err(e) => {
+                                tracing::warn!("Impossible to republish '{}': {e}", event.id);
+                                migration.skipped.push(event.id);
+                            }
+                        }
+                    }
+                }
+                Err(e) => migration
+                    .failed
+                    .push((MigrationStep::History, e.to_string())),
+            }
+        }
+
+        Ok(migration)
+    }
+
+    /// Copy a replaceable event of the old identity
+    async fn copy_event(
+        &self,
+        old_keys: &Keys,
+        new_keys: &Keys,
+        event: &Event,
+    ) -> Result<EventId, Error> {
+        // The private entries are encrypted to self
+        let content: String = if event.kind == Kind::MuteList && !event.content.is_empty() {
+            reencrypt(old_keys, new_keys, &event.content).await?
+        } else {
+            event.content.clone()
+        };
+
+        let copy: Event = EventBuilder::new(event.kind, content)
+            .tags(event.tags.clone())
+            .sign_with_keys(new_keys)?;
+        Ok(self.send_event(copy).await?.val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+
+    #[cfg(feature = "nip44")]
+    #[tokio::test]
+    async fn test_migrate_identity() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let old_keys = Keys::generate();
+        let new_keys = Keys::generate();
+
+        // Publish the lists of the old identity
+        let client = Client::new(old_keys.clone());
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        client
+            .send_event_builder(EventBuilder::metadata(&Metadata::new().name("old")))
+            .await
+            .unwrap();
+        client
+            .send_event_builder(
+                EventBuilder::new(Kind::InboxRelays, "")
+                    .tag(Tag::relay_metadata(RelayUrl::parse(&url).unwrap(), None)),
+            )
+            .await
+            .unwrap();
+        let private: String = old_keys
+            .nip44_encrypt(&old_keys.public_key(), "[[\"word\",\"spam\"]]")
+            .await
+            .unwrap();
+        client
+            .send_event_builder(EventBuilder::new(Kind::MuteList, private))
+            .await
+            .unwrap();
+
+        let migration = client
+            .migrate_identity(&old_keys, &new_keys, MigrationOptions::new())
+            .await
+            .unwrap();
+        assert!(migration.is_complete(), "{:?}", migration.failed);
+        assert!(migration.metadata.is_some());
+        assert!(migration.inbox_relays.is_some());
+        assert!(migration.relay_list.is_none());
+        assert!(migration.contact_list.is_none());
+
+        // The private entries of the mute list are re-encrypted for the new identity
+        let filter = Filter::new()
+            .author(new_keys.public_key())
+            .kind(Kind::MuteList);
+        let events = client
+            .fetch_events(vec![filter], Duration::from_secs(2))
+            .await
+            .unwrap();
+        let mute_list = events.first().unwrap();
+        assert_eq!(Some(mute_list.id), migration.mute_list);
+        let content: String = new_keys
+            .nip44_decrypt(&new_keys.public_key(), &mute_list.content)
+            .await
+            .unwrap();
+        assert_eq!(content, "[[\"word\",\"spam\"]]");
+
+        // A failed step doesn't stop the migration
+        client.disconnect().await.unwrap();
+        let migration = client
+            .migrate_identity(
+                &old_keys,
+                &new_keys,
+                MigrationOptions::new().timeout(Duration::from_millis(100)),
+            )
+            .await
+            .unwrap();
+        assert!(!migration.is_complete());
+        assert!(migration
+            .failed
+            .iter()
+            .any(|(step, ..)| *step == MigrationStep::Notice));
+        assert!(migration
+            .failed
+            .iter()
+            .any(|(step, ..)| *step == MigrationStep::MovedNotice));
+    }
+}
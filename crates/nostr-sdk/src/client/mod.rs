@@ -7,34 +7,67 @@
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::future::Future;
 use std::iter;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use async_utility::futures_util::{future, stream, StreamExt};
+use atomic_destructor::StealthClone;
 use nostr::prelude::*;
+use nostr::util::http::HttpClient;
 use nostr_database::prelude::*;
 use nostr_relay_pool::__private::SharedState;
 use nostr_relay_pool::prelude::*;
 #[cfg(feature = "nip57")]
 use nostr_zapper::{DynNostrZapper, IntoNostrZapper};
-use tokio::sync::broadcast;
 #[cfg(feature = "nip57")]
 use tokio::sync::RwLock;
+use tokio::sync::{broadcast, mpsc, Mutex};
 
+mod account;
 pub mod builder;
+mod discovery;
+mod entity;
 mod error;
+mod feed;
+mod inbox;
+mod interactions;
+mod migration;
+mod mute;
 pub mod options;
+mod relay_sets;
+mod republish;
+mod schedule;
+#[cfg(feature = "nip44")]
+pub mod settings;
 #[cfg(feature = "nip57")]
 mod zapper;
 
+pub use self::account::{AccountDeletion, AccountDeletionStep};
 pub use self::builder::ClientBuilder;
+pub use self::discovery::DiscoveredRelay;
+pub use self::entity::FetchedEntity;
 pub use self::error::Error;
+pub use self::feed::{Feed, FeedOptions};
+pub use self::inbox::Inbox;
+pub use self::interactions::{InteractionCounts, Interactions};
+pub use self::migration::{Migration, MigrationOptions, MigrationStep};
+use self::mute::AppliedMuteList;
 pub use self::options::Options;
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::options::{Connection, ConnectionTarget};
+#[cfg(feature = "nip44")]
+pub use self::settings::{Settings, SettingsSync};
 #[cfg(feature = "nip57")]
 pub use self::zapper::{ZapDetails, ZapEntity};
 use crate::gossip::graph::GossipGraph;
 
+/// Max number of levels of missing parents fetched by [`Client::get_thread`]
+const MAX_THREAD_PARENT_FETCHES: usize = 5;
+
+/// Max number of events sent concurrently by [`Client::rebroadcast`]
+const MAX_REBROADCAST_CONCURRENCY: usize = 16;
+
 /// Nostr client
 #[derive(Debug, Clone)]
 pub struct Client {
@@ -42,6 +75,12 @@ pub struct Client {
     #[cfg(feature = "nip57")]
     zapper: Arc<RwLock<Option<Arc<DynNostrZapper>>>>,
     gossip_graph: GossipGraph,
+    content_policy: Option<Arc<dyn ContentPolicy>>,
+    republisher: Arc<AtomicBool>,
+    mute_list: Arc<Mutex<AppliedMuteList>>,
+    mute_list_sync: Arc<AtomicBool>,
+    scheduled: Arc<Mutex<HashSet<EventId>>>,
+    scheduled_restored: Arc<AtomicBool>,
     opts: Options,
 }
 
@@ -98,13 +137,25 @@ impl Client {
 
     fn from_builder(builder: ClientBuilder) -> Self {
         // Construct shared state
-        let state = SharedState::new(
+        let mut state = SharedState::new(
             builder.database,
             builder.signer,
             builder.opts.filtering_mode,
             builder.opts.nip42_auto_authentication,
             builder.opts.min_pow_difficulty,
-        );
+            builder.admit_policy,
+            builder.dedup_policy,
+            builder.websocket_transport,
+        )
+        .with_time_provider(builder.time_provider)
+        .with_spawner(builder.spawner);
+        if let Some(opts) = builder.clock_skew {
+            state = state.with_clock_skew(opts);
+        }
+        if let Some(client) = builder.http_client {
+            state = state.with_http_client(client);
+        }
+        state.ephemeral_auth(builder.opts.ephemeral_auth);
 
         // Construct client
         Self {
@@ -112,6 +163,12 @@ impl Client {
             #[cfg(feature = "nip57")]
             zapper: Arc::new(RwLock::new(builder.zapper)),
             gossip_graph: GossipGraph::new(),
+            content_policy: builder.content_policy,
+            republisher: Arc::new(AtomicBool::new(false)),
+            mute_list: Arc::new(Mutex::new(AppliedMuteList::default())),
+            mute_list_sync: Arc::new(AtomicBool::new(false)),
+            scheduled: Arc::new(Mutex::new(HashSet::new())),
+            scheduled_restored: Arc::new(AtomicBool::new(false)),
             opts: builder.opts,
         }
     }
@@ -125,6 +182,54 @@ impl Client {
         self.pool.state()
     }
 
+    /// Start the republisher, if enabled and not already started
+    fn start_republisher(&self) {
+        if let Some(interval) = self.opts.republish_interval {
+            if !self.republisher.swap(true, Ordering::SeqCst) {
+                self.spawn_republisher(interval);
+            }
+        }
+    }
+
+    /// Clone the client without keeping the pool alive
+    ///
+    /// Used by the background tasks: the pool is shutdown when the last non-stealth clone is dropped.
+    fn stealth_clone(&self) -> Self {
+        Self {
+            pool: self.pool.stealth_clone(),
+            ..self.clone()
+        }
+    }
+
+    /// Start the mute list sync, if enabled and not already started
+    fn start_mute_list_sync(&self) {
+        if self.opts.mute_list && !self.mute_list_sync.swap(true, Ordering::SeqCst) {
+            self.spawn_mute_list_sync();
+        }
+    }
+
+    /// Get time provider
+    #[inline]
+    pub fn time_provider(&self) -> &Arc<dyn TimeProvider> {
+        self.state().time_provider()
+    }
+
+    /// Get clock skew detector
+    ///
+    /// Available only if enabled with [`ClientBuilder::clock_skew`].
+    #[inline]
+    pub fn clock_skew(&self) -> Option<&Arc<ClockSkew>> {
+        self.state().clock_skew()
+    }
+
+    /// Get custom HTTP client
+    ///
+    /// Available only if set with [`ClientBuilder::http_client`].
+    #[inline]
+    pub fn http_client(&self) -> Option<&Arc<dyn HttpClient>> {
+        self.state().http_client()
+    }
+
     /// Update minimum POW difficulty for received events
     ///
     /// Events with a POW lower than the current value will be ignored to prevent resources exhaustion.
@@ -139,6 +244,13 @@ impl Client {
         self.state().automatic_authentication(enable);
     }
 
+    /// Authenticate to the relays without the `WRITE` flag with throwaway keys (default: false)
+    ///
+    /// Check [`Options::ephemeral_auth`] docs to learn more.
+    pub fn ephemeral_auth(&self, enable: bool) {
+        self.state().ephemeral_auth(enable);
+    }
+
     /// Check if signer is configured
     #[inline]
     pub async fn has_signer(&self) -> bool {
@@ -252,12 +364,22 @@ impl Client {
 
     /// Get new notification listener
     ///
+    /// Every listener receives all the notifications.
+    ///
     /// <div class="warning">When you call this method, you subscribe to the notifications channel from that precise moment. Anything received by relay/s before that moment is not included in the channel!</div>
     #[inline]
     pub fn notifications(&self) -> broadcast::Receiver<RelayPoolNotification> {
         self.pool.notifications()
     }
 
+    /// Get new notification listener, with sequence numbers and receive timestamps
+    ///
+    /// Check [`RelayPool::sequenced_notifications`] to learn more.
+    #[inline]
+    pub fn sequenced_notifications(&self) -> broadcast::Receiver<SequencedNotification> {
+        self.pool.sequenced_notifications()
+    }
+
     /// Get relays with [`RelayServiceFlags::READ`] or [`RelayServiceFlags::WRITE`] flags
     ///
     /// Call [`RelayPool::all_relays`] to get all relays
@@ -307,9 +429,15 @@ impl Client {
             },
         };
 
+        // Set socket options
+        #[cfg(not(target_arch = "wasm32"))]
+        let opts: RelayOptions = opts.socket(self.opts.connection.socket);
+
         // Set limits
         opts.limits(self.opts.relay_limits.clone())
             .max_avg_latency(self.opts.max_avg_latency)
+            .resubscribe_since(self.opts.resubscribe_since)
+            .outbox(self.opts.outbox)
     }
 
     /// If return `false` means that already existed
@@ -348,6 +476,7 @@ impl Client {
                 // Connect if `autoconnect` is enabled
                 if self.opts.autoconnect {
                     self.connect_relay::<RelayUrl>(url).await?;
+                    self.start_republisher();
                 }
 
                 Ok(true)
@@ -516,6 +645,9 @@ impl Client {
     #[inline]
     pub async fn connect(&self) {
         self.pool.connect(None).await;
+        self.start_republisher();
+        self.start_mute_list_sync();
+        self.start_scheduled_publishing();
     }
 
     /// Connect to all added relays
@@ -524,7 +656,23 @@ impl Client {
     /// The code continues if the `timeout` is reached or if all relays connect.
     #[inline]
     pub async fn connect_with_timeout(&self, timeout: Duration) {
-        self.pool.connect(Some(timeout)).await
+        self.pool.connect(Some(timeout)).await;
+        self.start_republisher();
+        self.start_mute_list_sync();
+        self.start_scheduled_publishing();
+    }
+
+    /// Try to connect to all added relays concurrently
+    ///
+    /// Wait for the relays to be connected at most for the specified `timeout`
+    /// and return which relays succeeded and which failed.
+    #[inline]
+    pub async fn try_connect(&self, timeout: Duration) -> Output<()> {
+        let output: Output<()> = self.pool.try_connect(timeout).await;
+        self.start_republisher();
+        self.start_mute_list_sync();
+        self.start_scheduled_publishing();
+        output
     }
 
     /// Disconnect from all relays
@@ -533,6 +681,22 @@ impl Client {
         Ok(self.pool.disconnect().await?)
     }
 
+    /// Pause the connections (i.e. when the app goes in background)
+    ///
+    /// Check [`RelayPool::pause`] to learn more.
+    #[inline]
+    pub async fn pause(&self) -> Result<(), Error> {
+        Ok(self.pool.pause().await?)
+    }
+
+    /// Resume the connections paused with [`Client::pause`]
+    ///
+    /// Check [`RelayPool::resume`] to learn more.
+    #[inline]
+    pub async fn resume(&self) {
+        self.pool.resume(None).await
+    }
+
     /// Get pool subscriptions
     #[inline]
     pub async fn subscriptions(&self) -> HashMap<SubscriptionId, Vec<Filter>> {
@@ -777,6 +941,62 @@ impl Client {
             .await?)
     }
 
+    /// Fetch events from relays, grouped by filter
+    ///
+    /// Each filter is sent as its own `REQ`, concurrently, and the events are **not** merged:
+    /// the returned list contains the events of each filter, in the same order of the filters
+    /// (i.e. to fetch a profile, its notes and its relay list with a single call).
+    ///
+    /// If `gossip` is enabled (see [`Options::gossip`]) the events will be requested also to
+    /// NIP65 relays (automatically discovered) of public keys included in filters (if any).
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # use nostr_sdk::prelude::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<()> {
+    /// #   let keys = Keys::generate();
+    /// #   let client = Client::new(keys.clone());
+    /// let public_key = keys.public_key();
+    /// let filters = vec![
+    ///     Filter::new().author(public_key).kind(Kind::Metadata).limit(1),
+    ///     Filter::new().author(public_key).kind(Kind::TextNote).limit(20),
+    ///     Filter::new().author(public_key).kind(Kind::RelayList).limit(1),
+    /// ];
+    ///
+    /// let groups: Vec<Events> = client
+    ///     .fetch_events_grouped(filters, Duration::from_secs(10))
+    ///     .await?;
+    /// let (profile, notes, relay_list) = (&groups[0], &groups[1], &groups[2]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fetch_events_grouped(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<Vec<Events>, Error> {
+        let futures = filters
+            .into_iter()
+            .map(|filter| self.fetch_events(vec![filter], timeout));
+        future::join_all(futures).await.into_iter().collect()
+    }
+
+    /// Fetch events from relays, splitting the `since`/`until` window
+    ///
+    /// Useful for large syncs: the window of each filter is recursively split until each relay returns
+    /// fewer events than its limit.
+    /// Check [`Relay::fetch_events_windowed`] to learn more.
+    #[inline]
+    pub async fn fetch_events_windowed(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<Events, Error> {
+        Ok(self.pool.fetch_events_windowed(filters, timeout).await?)
+    }
+
     /// Fetch events from specific relays
     #[inline]
     pub async fn fetch_events_from<I, U>(
@@ -907,6 +1127,142 @@ impl Client {
             .await?)
     }
 
+    /// Stream events from relays, sorted by `created_at`
+    ///
+    /// The stream terminates when all the relays sent EOSE or after the `timeout`.
+    /// With [`StreamOrder::Ascending`] or [`StreamOrder::Descending`],
+    /// the events are buffered until the stream terminates.
+    ///
+    /// If `gossip` is enabled (see [`Options::gossip`]) the events will be streamed also from
+    /// NIP65 relays (automatically discovered) of public keys included in filters (if any).
+    pub async fn stream_events_ordered(
+        &self,
+        filters: Vec<Filter>,
+        timeout: Duration,
+        order: StreamOrder,
+    ) -> Result<ReceiverStream<Event>, Error> {
+        let targets: HashMap<RelayUrl, Vec<Filter>> = if self.opts.gossip {
+            self.break_down_filters(filters).await?
+        } else {
+            self.pool
+                .relays_with_flag(RelayServiceFlags::READ, FlagCheck::All)
+                .await
+                .into_keys()
+                .map(|url| (url, filters.clone()))
+                .collect()
+        };
+
+        Ok(self
+            .pool
+            .stream_events_targeted_ordered(targets, timeout, FilterOptions::ExitOnEOSE, order)
+            .await?)
+    }
+
+    /// Paginate events from relays
+    ///
+    /// Walk backwards through the history, a page at time: each filter is requested with `limit` set to `page_size`
+    /// and `until` set to the `created_at` of the oldest event it returned in the previous page,
+    /// so a filter matching more recent events doesn't make the others skip their history.
+    /// The events are deduplicated across relays, filters and pages.
+    ///
+    /// The stream ends when no filter finds more events or after the first error.
+    /// The next page is requested only when the previous one has been consumed.
+    pub fn paginate(
+        &self,
+        filters: Vec<Filter>,
+        page_size: usize,
+        timeout: Duration,
+    ) -> ReceiverStream<Result<Events, Error>> {
+        let (tx, rx) = mpsc::channel::<Result<Events, Error>>(1);
+
+        let client: Self = self.clone();
+        self.state().spawn("paginated fetch", async move {
+            // Filter, `until` of its next page and if it's exhausted
+            let mut cursors: Vec<(Filter, Option<Timestamp>, bool)> = filters
+                .into_iter()
+                .map(|filter| (filter, None, false))
+                .collect();
+
+            // IDs of the already sent events
+            let mut sent: HashSet<EventId> = HashSet::new();
+
+            'pages: loop {
+                let mut page: Events = Events::new(&[]);
+
+                // Fetch until a page has new events or all the filters are exhausted
+                while page.is_empty() {
+                    let active: Vec<usize> = cursors
+                        .iter()
+                        .enumerate()
+                        .filter(|(.., (.., exhausted))| !exhausted)
+                        .map(|(i, ..)| i)
+                        .collect();
+
+                    if active.is_empty() {
+                        break 'pages;
+                    }
+
+                    let futures = active.iter().map(|i| {
+                        let (filter, until, ..) = &cursors[*i];
+                        let filter: Filter = filter.clone().limit(page_size);
+                        let filter: Filter = match until {
+                            Some(until) => filter.until(*until),
+                            None => filter,
+                        };
+                        client.fetch_events(vec![filter], timeout)
+                    });
+                    let results: Vec<Result<Events, Error>> = future::join_all(futures).await;
+
+                    for (i, result) in active.into_iter().zip(results.into_iter()) {
+                        let events: Events = match result {
+                            Ok(events) => events,
+                            Err(e) => {
+                                let _ = tx.send(Err(e)).await;
+                                break 'pages;
+                            }
+                        };
+
+                        let (_, until, exhausted) = &mut cursors[i];
+
+                        let oldest: Timestamp = match events.last() {
+                            Some(event) => event.created_at,
+                            None => {
+                                *exhausted = true;
+                                continue;
+                            }
+                        };
+                        let fetched: usize = events.len();
+
+                        let mut new: usize = 0;
+                        for event in events.into_iter() {
+                            if sent.insert(event.id) {
+                                page.insert(event);
+                                new += 1;
+                            }
+                        }
+
+                        if new > 0 {
+                            // Keep the oldest second: it may have more events
+                            *until = Some(oldest);
+                        } else if fetched >= page_size && oldest.as_u64() > 0 {
+                            // Full page of already sent events with the same timestamp: skip the second
+                            *until = Some(oldest - Duration::from_secs(1));
+                        } else {
+                            *exhausted = true;
+                        }
+                    }
+                }
+
+                // Send page (stop if the stream has been dropped)
+                if tx.send(Ok(page)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+
     /// Send client message to a **specific relays**
     #[inline]
     pub async fn send_msg_to<I, U>(&self, urls: I, msg: ClientMessage) -> Result<Output<()>, Error>
@@ -980,9 +1336,24 @@ impl Client {
     }
 
     /// Build, sign and return [`Event`]
+    ///
+    /// The `created_at` is taken from the time provider (check [`ClientBuilder::time_provider`]),
+    /// if not already set in the builder.
+    /// The content policy, if any, is applied before signing (check [`ClientBuilder::content_policy`]).
     pub async fn sign_event_builder(&self, builder: EventBuilder) -> Result<Event, Error> {
+        let builder: EventBuilder = match &self.content_policy {
+            Some(policy) => builder.apply_policy(policy.as_ref())?,
+            None => builder,
+        };
+
         let signer = self.signer().await?;
-        Ok(builder.sign(&signer).await?)
+        let public_key: PublicKey = signer.get_public_key().await?;
+        let unsigned: UnsignedEvent =
+            builder.build_with_provider(self.time_provider().as_ref(), public_key);
+        unsigned
+            .sign(&signer)
+            .await
+            .map_err(|e| Error::EventBuilder(e.into()))
     }
 
     /// Take an [`EventBuilder`], sign it by using the [`NostrSigner`] and broadcast to relays (check [`Client::send_event`] from more details).
@@ -1015,6 +1386,67 @@ impl Client {
         self.send_event_to(urls, event).await
     }
 
+    /// Rebroadcast the events stored in the database to specific relays
+    ///
+    /// Query the events matching the filters from the database and send them to the relays,
+    /// returning an [`Output`] for every event, with the `OK` result of every relay.
+    /// Useful to populate a relay just added or to recover the events lost by a relay.
+    ///
+    /// The events are sent like [`Client::send_event_to`], so the outgoing middlewares are applied,
+    /// with at most 16 events in flight. The outputs are in the same order of the queried events.
+    /// The relays must be already added to the pool.
+    pub async fn rebroadcast<I, U>(
+        &self,
+        filters: Vec<Filter>,
+        urls: I,
+    ) -> Result<Vec<Output<EventId>>, Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let urls: HashSet<RelayUrl> = urls
+            .into_iter()
+            .map(|u| u.try_into_url())
+            .collect::<Result<_, _>>()
+            .map_err(pool::Error::from)?;
+
+        if urls.is_empty() {
+            return Err(Error::RelayPool(pool::Error::NoRelaysSpecified));
+        }
+
+        // Check that the relays have been added
+        for url in urls.iter() {
+            self.pool.relay(url).await?;
+        }
+
+        let events: Events = self.database().query(filters).await?;
+
+        // Send through the pool, to apply the outgoing middlewares and handle the requests to vanish
+        let futures = events.into_iter().map(|event| {
+            let urls: &HashSet<RelayUrl> = &urls;
+            async move {
+                let id: EventId = event.id;
+                match self.pool.send_event_to(urls.iter().cloned(), event).await {
+                    Ok(output) => output,
+                    Err(e) => Output {
+                        val: id,
+                        success: HashSet::new(),
+                        failed: urls
+                            .iter()
+                            .map(|url| (url.clone(), e.to_string()))
+                            .collect(),
+                    },
+                }
+            }
+        });
+
+        Ok(stream::iter(futures)
+            .buffered(MAX_REBROADCAST_CONCURRENCY)
+            .collect()
+            .await)
+    }
+
     /// Fetch the newest public key metadata from relays.
     ///
     /// If you only want to consult stored data,
@@ -1037,6 +1469,23 @@ impl Client {
         }
     }
 
+    /// Fetch the newest replaceable or addressable event from relays by [`Coordinate`]
+    ///
+    /// If the coordinate has an identifier, the events are queried with the `d` tag.
+    ///
+    /// Return `None` if the event is not found.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    pub async fn fetch_event_by_coordinate(
+        &self,
+        coordinate: &Coordinate,
+        timeout: Duration,
+    ) -> Result<Option<Event>, Error> {
+        let filter: Filter = Filter::from(coordinate).limit(1);
+        let events: Events = self.fetch_events(vec![filter], timeout).await?;
+        Ok(events.first().cloned())
+    }
+
     /// Update metadata
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
@@ -1064,6 +1513,101 @@ impl Client {
         self.send_event_builder(builder).await
     }
 
+    /// Set application-specific data
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/78.md>
+    #[inline]
+    pub async fn set_app_data<I, S>(
+        &self,
+        identifier: I,
+        content: S,
+    ) -> Result<Output<EventId>, Error>
+    where
+        I: Into<String>,
+        S: Into<String>,
+    {
+        let builder = EventBuilder::application_data(identifier, content);
+        self.send_event_builder(builder).await
+    }
+
+    /// Set private application-specific data
+    ///
+    /// The content is encrypted to self with NIP44 (i.e. for private app settings sync).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/78.md>
+    #[cfg(feature = "nip44")]
+    pub async fn set_private_app_data<I, S>(
+        &self,
+        identifier: I,
+        content: S,
+    ) -> Result<Output<EventId>, Error>
+    where
+        I: Into<String>,
+        S: AsRef<str>,
+    {
+        let signer = self.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+        let content: String = signer.nip44_encrypt(&public_key, content.as_ref()).await?;
+        let builder = EventBuilder::application_data(identifier, content);
+        self.send_event_builder(builder).await
+    }
+
+    /// Fetch application-specific data of the current user from relays
+    ///
+    /// Return `None` if not found.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/78.md>
+    pub async fn fetch_app_data<S>(
+        &self,
+        identifier: S,
+        timeout: Duration,
+    ) -> Result<Option<String>, Error>
+    where
+        S: Into<String>,
+    {
+        let event: Option<Event> = self.fetch_app_data_event(identifier, timeout).await?;
+        Ok(event.map(|e| e.content))
+    }
+
+    /// Fetch and decrypt private application-specific data of the current user from relays
+    ///
+    /// Return `None` if not found.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/78.md>
+    #[cfg(feature = "nip44")]
+    pub async fn fetch_private_app_data<S>(
+        &self,
+        identifier: S,
+        timeout: Duration,
+    ) -> Result<Option<String>, Error>
+    where
+        S: Into<String>,
+    {
+        match self.fetch_app_data_event(identifier, timeout).await? {
+            Some(event) => {
+                let signer = self.signer().await?;
+                let content: String = signer.nip44_decrypt(&event.pubkey, &event.content).await?;
+                Ok(Some(content))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn fetch_app_data_event<S>(
+        &self,
+        identifier: S,
+        timeout: Duration,
+    ) -> Result<Option<Event>, Error>
+    where
+        S: Into<String>,
+    {
+        let signer = self.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+        let coordinate: Coordinate =
+            Coordinate::new(Kind::ApplicationSpecificData, public_key).identifier(identifier);
+        self.fetch_event_by_coordinate(&coordinate, timeout).await
+    }
+
     /// Update relay list
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/65.md>
@@ -1124,6 +1668,44 @@ impl Client {
         Ok(vec![filter])
     }
 
+    /// Get thread
+    ///
+    /// Fetch the root event and all the replies (`#e` filter), fetch the missing parents (if any, up to 5 levels)
+    /// and order the replies into a tree using the NIP10 markers (see [`Thread`]).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/10.md>
+    pub async fn get_thread(&self, root_id: EventId, timeout: Duration) -> Result<Thread, Error> {
+        let filters: Vec<Filter> = vec![
+            Filter::new().id(root_id),
+            Filter::new().kind(Kind::TextNote).event(root_id),
+        ];
+        let mut events: Events = self.fetch_events(filters, timeout).await?;
+
+        // Fetch the missing parents, level by level (their parents may be missing too)
+        let mut requested: HashSet<EventId> = HashSet::new();
+        for _ in 0..MAX_THREAD_PARENT_FETCHES {
+            let known: HashSet<EventId> = events.iter().map(|e| e.id).collect();
+            let missing: HashSet<EventId> = events
+                .iter()
+                .filter_map(nip10::parent_id)
+                .filter(|id| !known.contains(id) && !requested.contains(id))
+                .collect();
+
+            if missing.is_empty() {
+                break;
+            }
+
+            requested.extend(missing.iter().copied());
+
+            let parents: Events = self
+                .fetch_events(vec![Filter::new().ids(missing)], timeout)
+                .await?;
+            events = events.merge(parents);
+        }
+
+        Ok(Thread::new(root_id, events))
+    }
+
     /// Get contact list from relays.
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/02.md>
@@ -1446,6 +2028,27 @@ impl Client {
         self.internal_zap(to, satoshi, details).await
     }
 
+    /// Pay the admission fee of a paid relay
+    ///
+    /// The invoice is requested to the payment URL advertised in the NIP11 document,
+    /// that must be a lightning address, an LNURL or a LNURL-pay endpoint, and paid with the zapper (i.e. NWC).
+    /// If the payment URL is a web page, [`Error::ImpossibleToPayRelay`] is returned:
+    /// pay there and then mark the relay as paid with [`Relay::set_paid`].
+    /// After the payment, the relay is marked as paid and reconnected.
+    ///
+    /// Use it when receiving a [`RelayPoolNotification::RelayPaymentRequired`] notification.
+    /// Note that most paid relays identify the payer through the NIP42 authentication.
+    #[inline]
+    #[cfg(all(feature = "nip11", feature = "nip57"))]
+    pub async fn pay_relay<U>(&self, url: U) -> Result<(), Error>
+    where
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let url: RelayUrl = url.try_into_url().map_err(pool::Error::from)?;
+        self.internal_pay_relay(url).await
+    }
+
     /// Construct Gift Wrap and send to relays
     ///
     /// Check [`Client::send_event`] to know how sending events works.
@@ -1580,6 +2183,16 @@ impl Client {
                 .fetch_events_from(relays, vec![filter], Duration::from_secs(10))
                 .await?;
 
+            // Check if the relay lists of the signer are outdated
+            let signer_public_key: Option<PublicKey> = match self.signer().await {
+                Ok(signer) => signer
+                    .get_public_key()
+                    .await
+                    .ok()
+                    .filter(|p| outdated_public_keys.contains(p)),
+                Err(..) => None,
+            };
+
             // Update last check for these public keys
             self.gossip_graph
                 .update_last_check(outdated_public_keys)
@@ -1588,6 +2201,14 @@ impl Client {
             // Merge database and relays events
             let merged: Events = events.merge(stored_events);
 
+            // Keep the relay sets of the signer updated
+            if let Some(public_key) = signer_public_key {
+                // Don't abort the update of the gossip graph
+                if let Err(e) = self.update_relay_sets(public_key, &merged).await {
+                    tracing::warn!(error = %e, "Impossible to update the relay sets.");
+                }
+            }
+
             // Update gossip graph
             self.gossip_graph.update(merged).await;
         }
@@ -1730,10 +2351,16 @@ impl Client {
                 .gossip_graph
                 .get_nip65_outbox_relays(&[event.pubkey])
                 .await;
-            let inbox = self
-                .gossip_graph
-                .get_nip65_inbox_relays(event.tags.public_keys())
-                .await;
+            // Protected events are accepted only by relays where the author can authenticate,
+            // so don't send them to the INBOX relays of the other users.
+            // https://github.com/nostr-protocol/nips/blob/master/70.md
+            let inbox = if event.is_protected() {
+                HashSet::new()
+            } else {
+                self.gossip_graph
+                    .get_nip65_inbox_relays(event.tags.public_keys())
+                    .await
+            };
 
             // Add outbox and inbox relays
             for url in outbox.iter().chain(inbox.iter()) {
@@ -1836,3 +2463,269 @@ impl Client {
         Ok(self.pool.sync_targeted(filters, opts).await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_thread_missing_parents() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let keys = Keys::generate();
+        let client = Client::new(keys.clone());
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        // Only the last reply references the root: its parents must be fetched level by level
+        let root = EventBuilder::text_note("Root")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let unrelated = EventBuilder::text_note("Unrelated")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let grandparent = EventBuilder::text_note("Grandparent")
+            .tag(Tag::event(unrelated.id))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let parent = EventBuilder::text_note("Parent")
+            .tag(Tag::event(grandparent.id))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let reply = EventBuilder::text_note("Reply")
+            .tags([Tag::event(root.id), Tag::event(parent.id)])
+            .sign_with_keys(&keys)
+            .unwrap();
+        for event in [&root, &unrelated, &grandparent, &parent, &reply] {
+            client.send_event(event.clone()).await.unwrap();
+        }
+
+        let thread = client
+            .get_thread(root.id, Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert_eq!(thread.root, Some(root));
+        assert_eq!(thread.len(), 3);
+        assert_eq!(thread.orphans[0].event, grandparent);
+        assert_eq!(thread.orphans[0].replies[0].event, parent);
+        assert_eq!(thread.orphans[0].replies[0].replies[0].event, reply);
+    }
+
+    #[tokio::test]
+    async fn test_paginate_cursor_per_filter() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        // Recent notes of an author, old notes of another one
+        let recent = Keys::generate();
+        let old = Keys::generate();
+        for (keys, timestamps) in [(&recent, [1_000, 999, 998]), (&old, [100, 99, 98])] {
+            let client = Client::new(keys.clone());
+            client.add_relay(&url).await.unwrap();
+            client.connect_with_timeout(Duration::from_secs(2)).await;
+            for created_at in timestamps {
+                let builder = EventBuilder::text_note(format!("Note at {created_at}"))
+                    .custom_created_at(Timestamp::from(created_at));
+                client.send_event_builder(builder).await.unwrap();
+            }
+        }
+
+        let client = Client::default();
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        let filters = vec![
+            Filter::new().author(recent.public_key()),
+            Filter::new().author(old.public_key()),
+        ];
+        let mut stream = client.paginate(filters, 2, Duration::from_secs(2));
+
+        let mut events: Vec<Event> = Vec::new();
+        while let Some(page) = stream.next().await {
+            events.extend(page.unwrap());
+        }
+
+        // The recent author isn't skipped after the first page
+        assert_eq!(events.len(), 6);
+        let ids: HashSet<EventId> = events.iter().map(|e| e.id).collect();
+        assert_eq!(ids.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_events_grouped() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let keys = Keys::generate();
+        let client = Client::new(keys.clone());
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        let metadata = Metadata::new().name("grouped");
+        client.set_metadata(&metadata).await.unwrap();
+        for i in 0..5 {
+            client
+                .send_event_builder(EventBuilder::text_note(format!("Note #{i}")))
+                .await
+                .unwrap();
+        }
+
+        let public_key: PublicKey = keys.public_key();
+        let filters = vec![
+            Filter::new()
+                .author(public_key)
+                .kind(Kind::TextNote)
+                .limit(3),
+            Filter::new()
+                .author(public_key)
+                .kind(Kind::Metadata)
+                .limit(1),
+            Filter::new()
+                .author(public_key)
+                .kind(Kind::RelayList)
+                .limit(1),
+        ];
+        let groups: Vec<Events> = client
+            .fetch_events_grouped(filters, Duration::from_secs(2))
+            .await
+            .unwrap();
+
+        // One group per filter, in the same order, each with its own limit
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].len(), 3);
+        assert!(groups[0].iter().all(|e| e.kind == Kind::TextNote));
+        assert_eq!(groups[1].len(), 1);
+        assert_eq!(
+            Metadata::from_json(&groups[1].first().unwrap().content).unwrap(),
+            metadata
+        );
+        assert!(groups[2].is_empty());
+
+        // No filters
+        let groups: Vec<Events> = client
+            .fetch_events_grouped(Vec::new(), Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert!(groups.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_event_by_coordinate() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let keys = Keys::generate();
+        let client = Client::new(keys.clone());
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        // Two versions of the same addressable event and another identifier
+        let now = Timestamp::now();
+        let old = EventBuilder::new(Kind::LongFormTextNote, "Old version")
+            .tag(Tag::identifier("article"))
+            .custom_created_at(now - 10)
+            .sign_with_keys(&keys)
+            .unwrap();
+        let new = EventBuilder::new(Kind::LongFormTextNote, "New version")
+            .tag(Tag::identifier("article"))
+            .custom_created_at(now)
+            .sign_with_keys(&keys)
+            .unwrap();
+        let other = EventBuilder::new(Kind::LongFormTextNote, "Other article")
+            .tag(Tag::identifier("other"))
+            .custom_created_at(now)
+            .sign_with_keys(&keys)
+            .unwrap();
+        for event in [&old, &new, &other] {
+            client.send_event(event.clone()).await.unwrap();
+        }
+
+        // The newest version is returned
+        let coordinate =
+            Coordinate::new(Kind::LongFormTextNote, keys.public_key()).identifier("article");
+        let event = client
+            .fetch_event_by_coordinate(&coordinate, Duration::from_secs(2))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(event.id, new.id);
+
+        // Not matching identifier
+        let coordinate =
+            Coordinate::new(Kind::LongFormTextNote, keys.public_key()).identifier("missing");
+        let event = client
+            .fetch_event_by_coordinate(&coordinate, Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert!(event.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rebroadcast() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let url1 = mock1.url();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let keys = Keys::generate();
+        let client = Client::builder()
+            .signer(keys.clone())
+            .database(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+                events: true,
+                max_events: None,
+            }))
+            .build();
+        client.add_relay(&url1).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        // More events than the max concurrency
+        let total: usize = MAX_REBROADCAST_CONCURRENCY * 2 + 1;
+        for i in 0..total {
+            client
+                .send_event_builder(EventBuilder::text_note(format!("Note #{i}")))
+                .await
+                .unwrap();
+        }
+
+        // Not added relay
+        assert!(client
+            .rebroadcast(vec![Filter::new()], [url2.clone()])
+            .await
+            .is_err());
+
+        client.add_relay(&url2).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        let filter = Filter::new().author(keys.public_key()).kind(Kind::TextNote);
+        let outputs = client
+            .rebroadcast(vec![filter.clone()], [url2.clone()])
+            .await
+            .unwrap();
+        assert_eq!(outputs.len(), total);
+        assert!(outputs
+            .iter()
+            .all(|output| output.success.contains(&url2) && output.failed.is_empty()));
+
+        // Same order of the stored events
+        let stored: Vec<EventId> = client
+            .database()
+            .query(vec![filter.clone()])
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        let sent: Vec<EventId> = outputs.iter().map(|output| output.val).collect();
+        assert_eq!(sent, stored);
+
+        // The events are available on the new relay
+        let events = client
+            .fetch_events_from([url2], vec![filter], Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), total);
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Mute list
+
+use std::time::Duration;
+
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::{Client, Error};
+
+/// Timeout for fetching the mute list at the first connection
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The applied mute list
+#[derive(Debug, Default)]
+pub(super) struct AppliedMuteList {
+    created_at: Option<Timestamp>,
+    list: MuteList,
+}
+
+impl Client {
+    /// Fetch the mute list and apply it to the relay filtering
+    ///
+    /// The events authored by muted public keys, of muted threads, with muted hashtags or
+    /// containing muted words are discarded and never reach the notifications, the database or the `fetch_*`/`stream_*` methods.
+    /// The private entries are decrypted with the signer (requires `nip04` or `nip44` feature).
+    ///
+    /// The entries replace the ones of the previously applied mute list (the other entries of the filtering are kept).
+    /// Remove them from the filtering (see [`Client::filtering`]) to explicitly request the muted content.
+    /// Return the applied mute list, if found.
+    ///
+    /// Check [`Options::mute_list`](crate::Options::mute_list) to apply it automatically.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/51.md>
+    pub async fn apply_mute_list(&self, timeout: Duration) -> Result<Option<MuteList>, Error> {
+        let signer = self.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+
+        let filter: Filter = Filter::new()
+            .author(public_key)
+            .kind(Kind::MuteList)
+            .limit(1);
+        let events: Events = self.fetch_events(vec![filter], timeout).await?;
+
+        // Get first event (result of `fetch_events` is sorted DESC by timestamp)
+        match events.into_iter().next() {
+            Some(event) => self.apply_mute_list_event(&event).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Apply the mute list event, if newer than the applied one
+    async fn apply_mute_list_event(&self, event: &Event) -> Result<Option<MuteList>, Error> {
+        let signer = self.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+
+        if event.pubkey != public_key || event.kind != Kind::MuteList {
+            return Ok(None);
+        }
+
+        let mut list: MuteList = MuteList::from_iter(event.tags.iter());
+
+        // Decrypt private entries
+        if !event.content.is_empty() {
+            #[allow(unused_variables)]
+            let is_nip04: bool = event.content.contains("?iv=");
+
+            #[allow(unused_mut)]
+            let mut content: Option<String> = None;
+
+            #[cfg(feature = "nip04")]
+            if is_nip04 {
+                content = signer.nip04_decrypt(&public_key, &event.content).await.ok();
+            }
+
+            #[cfg(feature = "nip44")]
+            if !is_nip04 {
+                content = signer.nip44_decrypt(&public_key, &event.content).await.ok();
+            }
+
+            match content.map(|c| nostr::serde_json::from_str::<Vec<Tag>>(&c)) {
+                Some(Ok(tags)) => {
+                    let private: MuteList = MuteList::from_iter(tags.iter());
+                    list.public_keys.extend(private.public_keys);
+                    list.hashtags.extend(private.hashtags);
+                    list.event_ids.extend(private.event_ids);
+                    list.words.extend(private.words);
+                }
+                Some(Err(e)) => tracing::warn!("Impossible to parse private mute list: {e}"),
+                None => tracing::warn!("Impossible to decrypt private mute list"),
+            }
+        }
+
+        let mut applied = self.mute_list.lock().await;
+
+        // Skip older lists
+        if applied.created_at.map_or(false, |t| t > event.created_at) {
+            return Ok(None);
+        }
+
+        let filtering: &RelayFiltering = self.filtering();
+
+        // Remove the previous entries
+        let previous: &MuteList = &applied.list;
+        filtering.remove_public_keys(&previous.public_keys).await;
+        filtering.remove_threads(&previous.event_ids).await;
+        filtering.remove_hashtags(&previous.hashtags).await;
+        filtering.remove_words(&previous.words).await;
+
+        // Apply
+        let mut entries: MuteList = list.clone();
+
+        // In whitelist mode the public keys would be allowed instead of muted
+        if filtering.mode().is_whitelist() {
+            tracing::warn!("Muted public keys not applied: the filtering is in whitelist mode.");
+            entries.public_keys.clear();
+        }
+
+        filtering
+            .add_public_keys(entries.public_keys.iter().copied())
+            .await;
+        filtering
+            .add_threads(entries.event_ids.iter().copied())
+            .await;
+        filtering.add_hashtags(entries.hashtags.iter()).await;
+        filtering.add_words(entries.words.iter()).await;
+
+        applied.created_at = Some(event.created_at);
+        applied.list = entries;
+
+        Ok(Some(list))
+    }
+
+    /// Fetch and apply the mute list, then keep it updated
+    pub(super) fn spawn_mute_list_sync(&self) {
+        let client: Self = self.clone();
+        let future = self.state().until_shutdown(async move {
+            // Get notifications before subscribing, to not miss any event
+            let mut notifications = client.notifications();
+
+            let public_key: PublicKey = match client.signer().await {
+                Ok(signer) => match signer.get_public_key().await {
+                    Ok(public_key) => public_key,
+                    Err(e) => {
+                        tracing::error!(error = %e, "Impossible to sync mute list.");
+                        return;
+                    }
+                },
+                Err(e) => {
+                    tracing::error!(error = %e, "Impossible to sync mute list.");
+                    return;
+                }
+            };
+
+            // Subscribe to the newer mute lists (i.e. published by other clients)
+            let since: Timestamp = client.time_provider().now();
+            let filter: Filter = Filter::new()
+                .author(public_key)
+                .kind(Kind::MuteList)
+                .since(since);
+            if let Err(e) = client.subscribe(vec![filter], None).await {
+                tracing::error!(error = %e, "Impossible to subscribe to mute list.");
+            }
+
+            if let Err(e) = client.apply_mute_list(FETCH_TIMEOUT).await {
+                tracing::error!(error = %e, "Impossible to apply mute list.");
+            }
+
+            loop {
+                match notifications.recv().await {
+                    Ok(RelayPoolNotification::Event { event, .. }) => {
+                        if event.pubkey == public_key && event.kind == Kind::MuteList {
+                            if let Err(e) = client.apply_mute_list_event(&event).await {
+                                tracing::error!(error = %e, "Impossible to apply mute list.");
+                            }
+                        }
+                    }
+                    Ok(RelayPoolNotification::Shutdown) => break,
+                    Ok(..) => {}
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("Mute list sync lagged: {n} notifications skipped.")
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+        self.state().spawn("mute list sync", future);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_utility::time;
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+    use crate::Options;
+
+    fn mute_list(word: &str) -> MuteList {
+        MuteList {
+            words: vec![word.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mute_list_sync() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let keys = Keys::generate();
+
+        // Publish the mute list
+        let publisher = Client::new(keys.clone());
+        publisher.add_relay(&url).await.unwrap();
+        publisher.connect_with_timeout(Duration::from_secs(2)).await;
+        let builder = EventBuilder::mute_list(mute_list("spam"))
+            .custom_created_at(Timestamp::from(1_700_000_000));
+        publisher.send_event_builder(builder).await.unwrap();
+
+        // Applied at the first connection
+        let client = Client::builder()
+            .signer(keys.clone())
+            .opts(Options::new().mute_list(true))
+            .build();
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+        time::sleep(Duration::from_secs(1)).await;
+        assert!(client.filtering().has_word("spam").await);
+
+        // Replaced by the newer one
+        publisher
+            .send_event_builder(EventBuilder::mute_list(mute_list("scam")))
+            .await
+            .unwrap();
+        time::sleep(Duration::from_secs(1)).await;
+        assert!(!client.filtering().has_word("spam").await);
+        assert!(client.filtering().has_word("scam").await);
+
+        // The older ones are ignored
+        let old = EventBuilder::mute_list(mute_list("spam"))
+            .custom_created_at(Timestamp::from(1_600_000_000))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(client.apply_mute_list_event(&old).await.unwrap().is_none());
+        assert!(client.filtering().has_word("scam").await);
+    }
+}
@@ -11,6 +11,8 @@ use std::path::Path;
 use std::time::Duration;
 
 use nostr_relay_pool::prelude::*;
+#[cfg(not(target_arch = "wasm32"))]
+use nostr_relay_pool::transport::SocketOptions;
 
 /// Options
 #[derive(Debug, Clone)]
@@ -19,12 +21,17 @@ pub struct Options {
     pub(super) min_pow_difficulty: u8,
     pub(super) req_filters_chunk_size: u8,
     pub(super) nip42_auto_authentication: bool,
+    pub(super) ephemeral_auth: bool,
     pub(super) gossip: bool,
     #[cfg(not(target_arch = "wasm32"))]
     pub(super) connection: Connection,
     pub(super) relay_limits: RelayLimits,
     pub(super) max_avg_latency: Option<Duration>,
     pub(super) filtering_mode: RelayFilteringMode,
+    pub(super) resubscribe_since: bool,
+    pub(super) outbox: bool,
+    pub(super) republish_interval: Option<Duration>,
+    pub(super) mute_list: bool,
     pub(super) pool: RelayPoolOptions,
 }
 
@@ -35,12 +42,17 @@ impl Default for Options {
             min_pow_difficulty: 0,
             req_filters_chunk_size: 10,
             nip42_auto_authentication: true,
+            ephemeral_auth: false,
             gossip: false,
             #[cfg(not(target_arch = "wasm32"))]
             connection: Connection::default(),
             relay_limits: RelayLimits::default(),
             max_avg_latency: None,
             filtering_mode: RelayFilteringMode::default(),
+            resubscribe_since: false,
+            outbox: false,
+            republish_interval: None,
+            mute_list: false,
             pool: RelayPoolOptions::default(),
         }
     }
@@ -107,6 +119,18 @@ impl Options {
         self
     }
 
+    /// Authenticate to the relays without the `WRITE` flag with throwaway keys (default: false)
+    ///
+    /// A new key is generated for each authentication,
+    /// so the read activity (REQ-only sessions) isn't linkable to the user identity.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/42.md>
+    #[inline]
+    pub fn ephemeral_auth(mut self, enable: bool) -> Self {
+        self.ephemeral_auth = enable;
+        self
+    }
+
     /// Enable gossip model (default: false)
     #[inline]
     pub fn gossip(mut self, enable: bool) -> Self {
@@ -145,12 +169,63 @@ impl Options {
         self
     }
 
+    /// Use the newest received event as `since` when re-subscribing after a reconnection (default: false)
+    #[inline]
+    pub fn resubscribe_since(mut self, enable: bool) -> Self {
+        self.resubscribe_since = enable;
+        self
+    }
+
+    /// Queue events when relays are not connected and send them on (re)connection (default: false)
+    ///
+    /// The outbox is saved in the database, if supported. Check [`RelayOptions::outbox`].
+    #[inline]
+    pub fn outbox(mut self, enable: bool) -> Self {
+        self.outbox = enable;
+        self
+    }
+
+    /// Periodically republish the own metadata, contact list and relay list (default: None)
+    ///
+    /// The newest events stored in the database are sent to the `WRITE` relays every `interval`,
+    /// so the relays added later learn the user profile without any manual action.
+    /// The background task is started by the first connection and runs until the client is shutdown or dropped.
+    ///
+    /// Check [`Client::republish_own_events`](crate::Client::republish_own_events) to republish them manually.
+    #[inline]
+    pub fn republish_interval(mut self, interval: Option<Duration>) -> Self {
+        self.republish_interval = interval;
+        self
+    }
+
+    /// Automatically apply the mute list (default: false)
+    ///
+    /// The mute list is fetched and applied to the relay filtering at the first connection,
+    /// then replaced by the newer ones received from the relays (i.e. published by other clients).
+    /// Requires a signer.
+    ///
+    /// Check [`Client::apply_mute_list`](crate::Client::apply_mute_list) to learn more.
+    #[inline]
+    pub fn mute_list(mut self, enable: bool) -> Self {
+        self.mute_list = enable;
+        self
+    }
+
     /// Notification channel size (default: [`DEFAULT_NOTIFICATION_CHANNEL_SIZE`])
     #[inline]
     pub fn notification_channel_size(mut self, size: usize) -> Self {
         self.pool = self.pool.notification_channel_size(size);
         self
     }
+
+    /// Retry policy of the reconnections, of the publishing and of the NIP11 document fetches (default: None)
+    ///
+    /// Check [`RelayPoolOptions::retry_policy`] to learn more.
+    #[inline]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.pool = self.pool.retry_policy(policy);
+        self
+    }
 }
 
 /// Connection target
@@ -172,6 +247,8 @@ pub struct Connection {
     pub mode: ConnectionMode,
     /// Target
     pub target: ConnectionTarget,
+    /// Socket options
+    pub socket: SocketOptions,
 }
 
 #[allow(clippy::derivable_impls)]
@@ -183,6 +260,7 @@ impl Default for Connection {
             Self {
                 mode: ConnectionMode::tor(),
                 target: ConnectionTarget::Onion,
+                socket: SocketOptions::default(),
             }
         }
 
@@ -193,6 +271,7 @@ impl Default for Connection {
         Self {
             mode: ConnectionMode::default(),
             target: ConnectionTarget::default(),
+            socket: SocketOptions::default(),
         }
     }
 }
@@ -205,6 +284,7 @@ impl Connection {
         Self {
             mode: ConnectionMode::default(),
             target: ConnectionTarget::default(),
+            socket: SocketOptions::default(),
         }
     }
 
@@ -256,4 +336,22 @@ impl Connection {
         self.mode = ConnectionMode::tor_with_path(path);
         self
     }
+
+    /// Set TCP keepalive idle time and probes interval (default: None)
+    ///
+    /// Applied only to direct connections.
+    #[inline]
+    pub fn tcp_keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.socket.tcp_keepalive = interval;
+        self
+    }
+
+    /// Set `TCP_NODELAY` (default: false)
+    ///
+    /// Applied only to direct connections.
+    #[inline]
+    pub fn tcp_nodelay(mut self, enable: bool) -> Self {
+        self.socket.tcp_nodelay = enable;
+        self
+    }
 }
@@ -0,0 +1,274 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Relay sets
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use nostr::nips::{nip17, nip65};
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+
+use super::{Client, Error};
+
+impl Client {
+    /// Set the relays of a named set
+    ///
+    /// The relays not already in the pool are added without the `READ` and `WRITE` flags,
+    /// so they are used only by the set-scoped methods.
+    ///
+    /// The relays that are no longer in the set are removed from the pool,
+    /// if not used by another set or for another service.
+    pub async fn set_relay_set<I, U>(&self, set: RelaySet, urls: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = U>,
+        U: TryIntoUrl,
+        pool::Error: From<<U as TryIntoUrl>::Err>,
+    {
+        let urls: HashSet<RelayUrl> = urls
+            .into_iter()
+            .map(|u| u.try_into_url())
+            .collect::<Result<_, _>>()
+            .map_err(pool::Error::from)?;
+
+        for url in urls.iter() {
+            self.get_or_add_relay_with_flag(url, false, RelayServiceFlags::PING)
+                .await?;
+        }
+
+        let old: Option<HashSet<RelayUrl>> = self.pool.relay_set(&set).await;
+
+        self.pool.set_relay_set(set, urls.clone()).await?;
+
+        // Remove the relays that are no longer in the set
+        if let Some(old) = old {
+            self.remove_stale_relays(old.difference(&urls)).await;
+        }
+
+        Ok(())
+    }
+
+    /// Remove the relays added only for the sets, if no longer used by any set
+    async fn remove_stale_relays<'a, I>(&self, urls: I)
+    where
+        I: Iterator<Item = &'a RelayUrl>,
+    {
+        let sets: HashMap<RelaySet, HashSet<RelayUrl>> = self.pool.relay_sets().await;
+
+        for url in urls {
+            if sets.values().any(|set| set.contains(url)) {
+                continue;
+            }
+
+            let relay: Relay = match self.pool.relay(url).await {
+                Ok(relay) => relay,
+                Err(..) => continue,
+            };
+
+            // Used for another service
+            if relay.flags().has_any(
+                RelayServiceFlags::READ
+                    | RelayServiceFlags::WRITE
+                    | RelayServiceFlags::GOSSIP
+                    | RelayServiceFlags::DISCOVERY,
+            ) {
+                continue;
+            }
+
+            if let Err(e) = self.pool.remove_relay(url).await {
+                tracing::warn!(url = %url, error = %e, "Impossible to remove stale relay.");
+            }
+        }
+    }
+
+    /// Get the relays of a named set
+    #[inline]
+    pub async fn relay_set(&self, set: &RelaySet) -> Option<HashSet<RelayUrl>> {
+        self.pool.relay_set(set).await
+    }
+
+    /// Remove a relay set
+    ///
+    /// The relays aren't removed from the pool.
+    #[inline]
+    pub async fn remove_relay_set(&self, set: &RelaySet) -> Option<HashSet<RelayUrl>> {
+        self.pool.remove_relay_set(set).await
+    }
+
+    /// Send event to the relays of a set
+    #[inline]
+    pub async fn send_event_to_set(
+        &self,
+        set: &RelaySet,
+        event: Event,
+    ) -> Result<Output<EventId>, Error> {
+        Ok(self.pool.send_event_to_set(set, event).await?)
+    }
+
+    /// Build, sign and send event to the relays of a set
+    pub async fn send_event_builder_to_set(
+        &self,
+        set: &RelaySet,
+        builder: EventBuilder,
+    ) -> Result<Output<EventId>, Error> {
+        let event: Event = self.sign_event_builder(builder).await?;
+        self.send_event_to_set(set, event).await
+    }
+
+    /// Subscribe to filters to the relays of a set
+    #[inline]
+    pub async fn subscribe_to_set(
+        &self,
+        set: &RelaySet,
+        filters: Vec<Filter>,
+        opts: Option<SubscribeAutoCloseOptions>,
+    ) -> Result<Output<SubscriptionId>, Error> {
+        let opts: SubscribeOptions = SubscribeOptions::default().close_on(opts);
+        Ok(self.pool.subscribe_to_set(set, filters, opts).await?)
+    }
+
+    /// Fetch events from the relays of a set
+    #[inline]
+    pub async fn fetch_events_from_set(
+        &self,
+        set: &RelaySet,
+        filters: Vec<Filter>,
+        timeout: Duration,
+    ) -> Result<Events, Error> {
+        Ok(self
+            .pool
+            .fetch_events_from_set(set, filters, timeout, FilterOptions::ExitOnEOSE)
+            .await?)
+    }
+
+    /// Update the well-known relay sets from the relay lists of a user
+    ///
+    /// * [`RelaySet::INBOX`] and [`RelaySet::OUTBOX`] from the NIP65 relay list;
+    /// * [`RelaySet::DM`] from the NIP17 inbox relays;
+    /// * [`RelaySet::SEARCH`] from the NIP51 search relays.
+    ///
+    /// The sets of the missing lists are left untouched.
+    /// A set that can't be updated doesn't prevent the update of the others: the first error is returned.
+    /// If gossip is enabled (see [`Options::gossip`](crate::Options::gossip)), the sets of the signer are kept updated automatically.
+    pub async fn sync_relay_sets(
+        &self,
+        public_key: PublicKey,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let filter: Filter = Filter::new().author(public_key).kinds([
+            Kind::RelayList,
+            Kind::InboxRelays,
+            Kind::SearchRelays,
+        ]);
+        let events: Events = self.fetch_events(vec![filter], timeout).await?;
+        self.update_relay_sets(public_key, &events).await
+    }
+
+    /// Update the well-known relay sets from the relay lists in `events`
+    pub(super) async fn update_relay_sets(
+        &self,
+        public_key: PublicKey,
+        events: &Events,
+    ) -> Result<(), Error> {
+        // Events are sorted DESC by timestamp: take the newest one of each kind
+        let newest = |kind: Kind| {
+            events
+                .iter()
+                .find(|e| e.pubkey == public_key && e.kind == kind)
+        };
+
+        let mut result: Result<(), Error> = Ok(());
+        let mut update = |res: Result<(), Error>| {
+            if let Err(e) = res {
+                tracing::warn!(error = %e, "Impossible to update relay set.");
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        };
+
+        if let Some(event) = newest(Kind::RelayList) {
+            let mut inbox: HashSet<&RelayUrl> = HashSet::new();
+            let mut outbox: HashSet<&RelayUrl> = HashSet::new();
+
+            for (url, metadata) in nip65::extract_relay_list(event) {
+                match metadata {
+                    Some(RelayMetadata::Read) => {
+                        inbox.insert(url);
+                    }
+                    Some(RelayMetadata::Write) => {
+                        outbox.insert(url);
+                    }
+                    None => {
+                        inbox.insert(url);
+                        outbox.insert(url);
+                    }
+                }
+            }
+
+            update(self.set_relay_set(RelaySet::INBOX, inbox).await);
+            update(self.set_relay_set(RelaySet::OUTBOX, outbox).await);
+        }
+
+        if let Some(event) = newest(Kind::InboxRelays) {
+            update(
+                self.set_relay_set(RelaySet::DM, nip17::extract_relay_list(event))
+                    .await,
+            );
+        }
+
+        if let Some(event) = newest(Kind::SearchRelays) {
+            let urls = event
+                .tags
+                .iter()
+                .filter_map(|tag| match tag.as_standardized() {
+                    Some(TagStandard::Relay(url)) => Some(url),
+                    _ => None,
+                });
+            update(self.set_relay_set(RelaySet::SEARCH, urls).await);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_relay_set_removes_stale_relays() {
+        let client = Client::default();
+
+        let url1 = RelayUrl::parse("wss://relay1.example.com").unwrap();
+        let url2 = RelayUrl::parse("wss://relay2.example.com").unwrap();
+        let url3 = RelayUrl::parse("wss://relay3.example.com").unwrap();
+        let url4 = RelayUrl::parse("wss://relay4.example.com").unwrap();
+
+        // Relay used also for reading
+        client.add_relay(&url3).await.unwrap();
+
+        client
+            .set_relay_set(RelaySet::INBOX, [&url1, &url2, &url3, &url4])
+            .await
+            .unwrap();
+        client.set_relay_set(RelaySet::DM, [&url4]).await.unwrap();
+        assert_eq!(client.pool().all_relays().await.len(), 4);
+
+        client
+            .set_relay_set(RelaySet::INBOX, [&url2])
+            .await
+            .unwrap();
+
+        let relays = client.pool().all_relays().await;
+        assert!(!relays.contains_key(&url1));
+        assert!(relays.contains_key(&url2));
+        // Used for reading
+        assert!(relays.contains_key(&url3));
+        // Used by another set
+        assert!(relays.contains_key(&url4));
+    }
+}
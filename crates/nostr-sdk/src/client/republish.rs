@@ -0,0 +1,132 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Republish of the own replaceable events
+
+use std::time::Duration;
+
+use async_utility::time;
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+use nostr_relay_pool::prelude::*;
+
+use super::{Client, Error};
+
+/// Kinds of the own events to republish
+const REPUBLISH_KINDS: [Kind; 3] = [Kind::Metadata, Kind::ContactList, Kind::RelayList];
+
+impl Client {
+    /// Republish the own metadata, contact list and relay list
+    ///
+    /// Send the newest events stored in the database to the `WRITE` relays
+    /// (and to the NIP65 relays if gossip is enabled).
+    /// The missing events are skipped.
+    ///
+    /// Check [`Options::republish_interval`] to republish them periodically.
+    pub async fn republish_own_events(&self) -> Result<Vec<Output<EventId>>, Error> {
+        let signer = self.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+
+        let mut outputs: Vec<Output<EventId>> = Vec::with_capacity(REPUBLISH_KINDS.len());
+
+        for kind in REPUBLISH_KINDS.into_iter() {
+            let filter: Filter = Filter::new().author(public_key).kind(kind).limit(1);
+            let events: Events = self.database().query(vec![filter]).await?;
+
+            if let Some(event) = events.into_iter().next() {
+                outputs.push(self.send_event(event).await?);
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    pub(super) fn spawn_republisher(&self, interval: Duration) {
+        // Don't keep the client alive: the task is stopped by the pool shutdown once the client is dropped
+        let client: Self = self.stealth_clone();
+        let future = self.state().until_shutdown(async move {
+            loop {
+                time::sleep(interval).await;
+
+                if client.pool.is_shutdown() {
+                    break;
+                }
+
+                // Skip if the signer isn't set (i.e. after a reset)
+                if !client.has_signer().await {
+                    continue;
+                }
+
+                match client.republish_own_events().await {
+                    Ok(outputs) => {
+                        tracing::debug!("Republished {} own events.", outputs.len())
+                    }
+                    Err(e) => tracing::error!(error = %e, "Impossible to republish own events."),
+                }
+            }
+        });
+        self.state().spawn("republisher", future);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atomic_destructor::StealthClone;
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+    use crate::client::Options;
+
+    #[tokio::test]
+    async fn test_republisher() {
+        let mock1 = MockRelay::run().await.unwrap();
+        let url1 = mock1.url();
+        let mock2 = MockRelay::run().await.unwrap();
+        let url2 = RelayUrl::parse(&mock2.url()).unwrap();
+
+        let keys = Keys::generate();
+        let opts = Options::new().republish_interval(Some(Duration::from_millis(200)));
+        let client = Client::builder()
+            .signer(keys.clone())
+            .database(MemoryDatabase::with_opts(MemoryDatabaseOptions {
+                events: true,
+                max_events: None,
+            }))
+            .opts(opts)
+            .build();
+        client.add_relay(&url1).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        let metadata = Metadata::new().name("republisher");
+        client.set_metadata(&metadata).await.unwrap();
+
+        // The relay added later receives the metadata
+        client.add_relay(&url2).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+        time::sleep(Duration::from_millis(500)).await;
+
+        let filter = Filter::new().author(keys.public_key()).kind(Kind::Metadata);
+        let events = client
+            .fetch_events_from([url2], vec![filter], Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+
+        // The task doesn't keep the client alive
+        let pool: RelayPool = client.pool().stealth_clone();
+        assert!(pool
+            .tasks()
+            .iter()
+            .any(|task| task.name == "republisher" && task.state.is_running()));
+
+        drop(client);
+        time::sleep(Duration::from_millis(500)).await;
+
+        assert!(pool.is_shutdown());
+        assert!(!pool
+            .tasks()
+            .iter()
+            .any(|task| task.name == "republisher" && task.state.is_running()));
+    }
+}
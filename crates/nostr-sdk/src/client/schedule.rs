@@ -0,0 +1,167 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Scheduled publishing
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use async_utility::time;
+use nostr::prelude::*;
+use nostr_database::prelude::*;
+
+use super::{Client, Error};
+
+impl Client {
+    /// Publish the [`Event`] at the specified [`Timestamp`]
+    ///
+    /// The event is sent to relays (check [`Client::send_event`]) when the timestamp is reached.
+    /// If the timestamp is in the past, the event is sent immediately.
+    ///
+    /// The schedule is saved in the database, so the events not yet published
+    /// are scheduled again at the first connection after a restart.
+    /// If the database doesn't support it, the event is held only in memory.
+    pub async fn publish_at(&self, event: Event, timestamp: Timestamp) -> Result<(), Error> {
+        match self
+            .database()
+            .save_scheduled_event(&event, &timestamp)
+            .await
+        {
+            Ok(()) => {}
+            Err(DatabaseError::NotSupported) => {
+                tracing::warn!(id = %event.id, "The database doesn't support scheduled events, the schedule will be lost at restart.");
+            }
+            Err(e) => return Err(e.into()),
+        }
+
+        self.spawn_scheduled_publish(event, timestamp).await;
+
+        Ok(())
+    }
+
+    /// Schedule again the events saved in the database, if not already done
+    pub(super) fn start_scheduled_publishing(&self) {
+        if self.scheduled_restored.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let client: Self = self.clone();
+        self.state().spawn("scheduled events restore", async move {
+            match client.database().scheduled_events().await {
+                Ok(events) => {
+                    for (event, timestamp) in events.into_iter() {
+                        client.spawn_scheduled_publish(event, timestamp).await;
+                    }
+                }
+                Err(DatabaseError::NotSupported) => {}
+                Err(e) => tracing::error!(error = %e, "Impossible to load scheduled events."),
+            }
+        });
+    }
+
+    async fn spawn_scheduled_publish(&self, event: Event, timestamp: Timestamp) {
+        // Already scheduled in this session
+        let mut scheduled = self.scheduled.lock().await;
+        if !scheduled.insert(event.id) {
+            return;
+        }
+        drop(scheduled);
+
+        let client: Self = self.clone();
+        let future = self.state().until_shutdown(async move {
+            // Wait until the timestamp is reached
+            let now: Timestamp = client.time_provider().now();
+            let delay: u64 = timestamp.as_u64().saturating_sub(now.as_u64());
+            time::sleep(Duration::from_secs(delay)).await;
+
+            // Send event
+            let id: EventId = event.id;
+            let sent: bool = match client.send_event(event).await {
+                Ok(output) => {
+                    tracing::debug!(
+                        "Scheduled event {id} sent to {} relays",
+                        output.success.len()
+                    );
+                    !output.success.is_empty()
+                }
+                Err(e) => {
+                    tracing::error!("Impossible to send scheduled event {id}: {e}");
+                    false
+                }
+            };
+
+            // Keep the schedule in the database if not sent, to retry it at the next restart
+            if sent {
+                match client.database().remove_scheduled_event(&id).await {
+                    Ok(()) | Err(DatabaseError::NotSupported) => {}
+                    Err(e) => {
+                        tracing::error!(id = %id, error = %e, "Impossible to remove scheduled event.")
+                    }
+                }
+            }
+
+            client.scheduled.lock().await.remove(&id);
+        });
+        self.state().spawn("scheduled publish", future);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_publish_at_persistent() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let keys = Keys::generate();
+        let database = MemoryDatabase::new();
+
+        let client = Client::builder()
+            .signer(keys.clone())
+            .database(database.clone())
+            .build();
+
+        // Saved in the database
+        let later = EventBuilder::text_note("Later")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let publish_at = client.time_provider().now() + Duration::from_secs(3600);
+        client.publish_at(later.clone(), publish_at).await.unwrap();
+        assert_eq!(
+            database.scheduled_events().await.unwrap(),
+            vec![(later, publish_at)]
+        );
+
+        // An event scheduled before a restart and expired in the meanwhile
+        let expired = EventBuilder::text_note("Expired")
+            .sign_with_keys(&keys)
+            .unwrap();
+        database
+            .save_scheduled_event(&expired, &Timestamp::from(1_700_000_000))
+            .await
+            .unwrap();
+
+        // Restart
+        let client = Client::builder()
+            .signer(keys.clone())
+            .database(database.clone())
+            .build();
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        time::sleep(Duration::from_secs(1)).await;
+
+        // Published and removed from the schedule
+        let events = client
+            .fetch_events(vec![Filter::new().id(expired.id)], Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(database.scheduled_events().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,222 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Encrypted settings sync
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use nostr::prelude::*;
+use nostr::serde_json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::{Client, Error};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings with the last update timestamp
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings<T> {
+    /// Settings value
+    pub value: T,
+    /// Last update timestamp
+    pub updated_at: Timestamp,
+}
+
+impl<T> Settings<T> {
+    /// New settings updated now
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            updated_at: Timestamp::now(),
+        }
+    }
+}
+
+/// Encrypted settings sync
+///
+/// Serialize the settings to JSON, encrypt them to self with NIP44
+/// and publish them as NIP78 application-specific data,
+/// so the same settings can be shared between multiple devices.
+///
+/// The settings are replaced as a whole (the newest win) and the remote updates aren't watched:
+/// call [`SettingsSync::sync`] (i.e. at startup) to get the changes made on the other devices.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/78.md>
+pub struct SettingsSync<T> {
+    client: Client,
+    identifier: String,
+    timeout: Duration,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> fmt::Debug for SettingsSync<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SettingsSync")
+            .field("identifier", &self.identifier)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+// Not derived, to not require `T: Clone`
+impl<T> Clone for SettingsSync<T> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            identifier: self.identifier.clone(),
+            timeout: self.timeout,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> SettingsSync<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// New settings sync
+    ///
+    /// The `identifier` is the `d` tag of the NIP78 event (i.e. `com.example.app/settings`).
+    pub fn new<S>(client: &Client, identifier: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            client: client.clone(),
+            identifier: identifier.into(),
+            timeout: DEFAULT_TIMEOUT,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Timeout for fetching the settings from relays (default: 10 secs)
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Encrypt and publish the settings
+    ///
+    /// The `updated_at` timestamp is used as `created_at` of the event.
+    pub async fn publish(&self, settings: &Settings<T>) -> Result<Output<EventId>, Error> {
+        let json: String = serde_json::to_string(&settings.value)?;
+
+        let signer = self.client.signer().await?;
+        let public_key: PublicKey = signer.get_public_key().await?;
+        let content: String = signer.nip44_encrypt(&public_key, &json).await?;
+
+        let builder = EventBuilder::application_data(self.identifier.clone(), content)
+            .custom_created_at(settings.updated_at);
+        self.client.send_event_builder(builder).await
+    }
+
+    /// Fetch and decrypt the newest settings from relays
+    ///
+    /// Return `None` if not found.
+    pub async fn fetch(&self) -> Result<Option<Settings<T>>, Error> {
+        match self
+            .client
+            .fetch_app_data_event(self.identifier.clone(), self.timeout)
+            .await?
+        {
+            Some(event) => {
+                let signer = self.client.signer().await?;
+                let json: String = signer.nip44_decrypt(&event.pubkey, &event.content).await?;
+                Ok(Some(Settings {
+                    value: serde_json::from_str(&json)?,
+                    updated_at: event.created_at,
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reconcile the local settings with the remote ones
+    ///
+    /// The settings aren't merged field by field: the newest ones (by `created_at`) win.
+    /// * if the remote settings are newer, they are returned;
+    /// * if the local settings are newer, or not published yet, they are published and returned;
+    /// * otherwise, the local settings are returned.
+    pub async fn sync(&self, local: Settings<T>) -> Result<Settings<T>, Error> {
+        match self.fetch().await? {
+            Some(remote) if remote.updated_at > local.updated_at => Ok(remote),
+            Some(remote) if remote.updated_at == local.updated_at => Ok(local),
+            _ => {
+                self.publish(&local).await?;
+                Ok(local)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use nostr_relay_builder::prelude::*;
+
+    use super::*;
+
+    type Prefs = BTreeMap<String, String>;
+
+    fn prefs(theme: &str) -> Prefs {
+        let mut prefs = Prefs::new();
+        prefs.insert(String::from("theme"), theme.to_string());
+        prefs
+    }
+
+    #[tokio::test]
+    async fn test_settings_sync() {
+        let mock = MockRelay::run().await.unwrap();
+        let url = mock.url();
+
+        let keys = Keys::generate();
+        let client = Client::new(keys);
+        client.add_relay(&url).await.unwrap();
+        client.connect_with_timeout(Duration::from_secs(2)).await;
+
+        let sync: SettingsSync<Prefs> =
+            SettingsSync::new(&client, "com.example.app/settings").timeout(Duration::from_secs(2));
+        assert_eq!(sync.fetch().await.unwrap(), None);
+
+        // Not published yet: the local settings are published
+        let local = Settings {
+            value: prefs("dark"),
+            updated_at: Timestamp::from(1_000),
+        };
+        assert_eq!(sync.sync(local.clone()).await.unwrap(), local);
+        assert_eq!(sync.fetch().await.unwrap(), Some(local.clone()));
+
+        // The content is encrypted
+        let event = client
+            .fetch_app_data_event("com.example.app/settings", Duration::from_secs(2))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!event.content.contains("dark"));
+
+        // Older local settings: the remote ones win
+        let older = Settings {
+            value: prefs("light"),
+            updated_at: Timestamp::from(900),
+        };
+        assert_eq!(sync.sync(older).await.unwrap(), local);
+
+        // Newer local settings: published
+        let newer = Settings {
+            value: prefs("light"),
+            updated_at: Timestamp::from(1_100),
+        };
+        assert_eq!(sync.sync(newer.clone()).await.unwrap(), newer);
+
+        // Seen from another device
+        let other = sync.clone();
+        assert_eq!(other.fetch().await.unwrap(), Some(newer));
+    }
+}
@@ -2,12 +2,16 @@
 // Copyright (c) 2023-2024 Rust Nostr Developers
 // Distributed under the MIT software license
 
-use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 
-use lnurl_pay::api::Lud06OrLud16;
-use lnurl_pay::{LightningAddress, LnUrl};
+use nostr::util::http::{HttpClient, ReqwestHttpClient};
+use nostr::util::lnurl::{self, InvoiceRequest, Lud06OrLud16};
+#[cfg(feature = "nip11")]
+use nostr::util::lnurl::{LightningAddress, LnUrl, PayParams};
 use nostr_database::prelude::*;
+#[cfg(feature = "nip11")]
+use nostr_relay_pool::relay::{Relay, RelayPayment};
 
 use super::{Client, Error};
 
@@ -116,18 +120,18 @@ impl Client {
         };
 
         // Parse lud
-        let lud: Lud06OrLud16 = if let Some(lud16) = &metadata.lud16 {
-            let address: LightningAddress = LightningAddress::parse(lud16)?;
-            tracing::debug!(%address, "Zap to lightning address.");
-            address.into()
-        } else if let Some(lud06) = &metadata.lud06 {
-            let lnurl: LnUrl = LnUrl::from_str(lud06)?;
-            tracing::debug!(lnurl = &lud06, "Zap to lnurl.");
-            lnurl.into()
-        } else {
-            return Err(Error::ImpossibleToZap(String::from("LUD06/LUD16 not set")));
+        let lud: Lud06OrLud16 = match Lud06OrLud16::from_metadata(&metadata)? {
+            Some(lud) => lud,
+            None => return Err(Error::ImpossibleToZap(String::from("LUD06/LUD16 not set"))),
         };
 
+        match &lud {
+            Lud06OrLud16::Lud16(address) => {
+                tracing::debug!(%address, "Zap to lightning address.")
+            }
+            Lud06OrLud16::Lud06(lnurl) => tracing::debug!(lnurl = %lnurl.url(), "Zap to lnurl."),
+        }
+
         // Compose zap split and get invoices
         let invoice: String = self
             .compose_zap(public_key, lud, satoshi, details, to.event_id())
@@ -139,6 +143,82 @@ impl Client {
         Ok(())
     }
 
+    /// Steps
+    /// 1. Get the payment requirements from the NIP11 document
+    /// 2. Pay the admission fee
+    /// 3. Mark the relay as paid and reconnect
+    #[cfg(feature = "nip11")]
+    pub(super) async fn internal_pay_relay(&self, url: RelayUrl) -> Result<(), Error> {
+        let relay: Relay = self.relay(&url).await?;
+
+        // Get payment requirements
+        let payment: RelayPayment = relay
+            .payment()
+            .await
+            .ok_or_else(|| Error::ImpossibleToPayRelay(String::from("payment not required")))?;
+
+        tracing::debug!(url = %url, "Paying relay admission fee.");
+
+        self.pay_admission(&payment).await?;
+
+        // Mark as paid and retry the connection
+        relay.set_paid(true);
+        relay.terminate(TIMEOUT).await?;
+        relay.connect(None).await;
+
+        Ok(())
+    }
+
+    /// Steps
+    /// 1. Check if zapper is set
+    /// 2. Get the pay parameters from the payment URL
+    /// 3. Get the invoice of the admission fee
+    /// 4. Send payment
+    #[cfg(feature = "nip11")]
+    async fn pay_admission(&self, payment: &RelayPayment) -> Result<(), Error> {
+        // Check if zapper is set
+        if !self.has_zapper().await {
+            return Err(Error::ZapperNotConfigured);
+        }
+
+        let msats: u64 = payment
+            .admission_msats()
+            .ok_or_else(|| Error::ImpossibleToPayRelay(String::from("admission fee not found")))?;
+
+        // Parse payment URL
+        let payments_url: &str = payment
+            .payments_url
+            .as_deref()
+            .ok_or_else(|| Error::ImpossibleToPayRelay(String::from("payment URL not set")))?;
+        let lud: Lud06OrLud16 = relay_payment_target(payments_url).ok_or_else(|| {
+            Error::ImpossibleToPayRelay(format!("unsupported payment URL: {payments_url}"))
+        })?;
+
+        // Get pay parameters
+        // The payment URL is usually a web page and not a LNURL-pay endpoint
+        let client: Arc<dyn HttpClient> = match self.http_client() {
+            Some(client) => client.clone(),
+            None => Arc::new(ReqwestHttpClient::new()),
+        };
+        let params: PayParams = lnurl::fetch_pay_params(client.as_ref(), &lud)
+            .await
+            .map_err(|e| {
+                Error::ImpossibleToPayRelay(format!(
+                    "{payments_url} isn't a LNURL-pay endpoint, pay there: {e}"
+                ))
+            })?;
+
+        // Get invoice
+        let request: InvoiceRequest = InvoiceRequest::new(msats);
+        let invoice: String = lnurl::request_invoice(client.as_ref(), &params, &request).await?;
+
+        // Pay
+        let zapper = self.zapper().await?;
+        zapper.pay(invoice).await?;
+
+        Ok(())
+    }
+
     /// Compose zap and get invoice
     async fn compose_zap(
         &self,
@@ -178,10 +258,175 @@ impl Client {
             None => None,
         };
 
+        let mut request: InvoiceRequest = InvoiceRequest::new(msats);
+        if let Some(zap_request) = zap_request {
+            request = request.zap_request(zap_request);
+        }
+
         // Get invoice
-        let invoice: String =
-            lnurl_pay::api::get_invoice(lud, msats, None, zap_request, None).await?;
+        let client: Arc<dyn HttpClient> = match self.http_client() {
+            Some(client) => client.clone(),
+            None => Arc::new(ReqwestHttpClient::new()),
+        };
+        let invoice: String = lnurl::get_invoice(client.as_ref(), &lud, &request).await?;
 
         Ok(invoice)
     }
 }
+
+/// Get the LNURL-pay target of the payment URL of a relay
+///
+/// The LNURLs, the lightning addresses and the HTTP(S) URLs (used as LNURL-pay endpoints) are supported.
+#[cfg(feature = "nip11")]
+fn relay_payment_target(payments_url: &str) -> Option<Lud06OrLud16> {
+    let payments_url: &str = payments_url.trim();
+    let payments_url: &str = payments_url
+        .strip_prefix("lightning:")
+        .unwrap_or(payments_url);
+
+    if let Ok(lnurl) = LnUrl::decode(payments_url) {
+        return Some(Lud06OrLud16::Lud06(lnurl));
+    }
+
+    if let Ok(url) = Url::parse(payments_url) {
+        return match url.scheme() {
+            "http" | "https" => Some(Lud06OrLud16::Lud06(LnUrl::new(url))),
+            _ => None,
+        };
+    }
+
+    LightningAddress::parse(payments_url)
+        .ok()
+        .map(Lud06OrLud16::Lud16)
+}
+
+#[cfg(all(test, feature = "nip11"))]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use nostr::nips::nip11::{FeeSchedule, FeeSchedules};
+    use nostr::util::http::{HttpError, HttpRequest, HttpResponse};
+    use nostr_zapper::{NostrZapper, ZapperBackend, ZapperError};
+
+    use super::*;
+
+    const METADATA: &str = r#"[["text/plain","Relay admission"]]"#;
+    // 21 sats, with the description hash of the metadata (signature not checked)
+    const INVOICE: &str = "lnbc210n1pj48ugqpp5qqqsyqcyq5rqwzqfpg9scrgwpugpzysnzs23v9ccrydpk8qarc0shp5klyypjhpfg033flcjyr5sevs3j754lrgrykds46ygucuhtqatrjqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq50ua02";
+
+    /// LNURL-pay service at `pay.example.com`, with a web page at `/join`
+    #[derive(Debug)]
+    struct MockHttpClient;
+
+    #[async_trait]
+    impl HttpClient for MockHttpClient {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+            let body: String = match request.url.path() {
+                "/lnurlp" | "/.well-known/lnurlp/admission" => nostr::serde_json::json!({
+                    "callback": "https://pay.example.com/lnurlp/callback",
+                    "minSendable": 1000,
+                    "maxSendable": 100000000,
+                    "metadata": METADATA,
+                    "tag": "payRequest",
+                })
+                .to_string(),
+                "/lnurlp/callback" => nostr::serde_json::json!({ "pr": INVOICE }).to_string(),
+                _ => String::from("<html><body>Pay to join</body></html>"),
+            };
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: body.into_bytes(),
+            })
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct MockZapper {
+        paid: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl NostrZapper for MockZapper {
+        fn backend(&self) -> ZapperBackend {
+            ZapperBackend::Custom(String::from("mock"))
+        }
+
+        async fn pay(&self, invoice: String) -> Result<(), ZapperError> {
+            self.paid.lock().unwrap().push(invoice);
+            Ok(())
+        }
+    }
+
+    fn payment(payments_url: &str) -> RelayPayment {
+        RelayPayment {
+            payments_url: Some(payments_url.to_string()),
+            fees: FeeSchedules {
+                admission: vec![FeeSchedule {
+                    amount: 21,
+                    unit: String::from("sats"),
+                    period: None,
+                    kinds: None,
+                }],
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_relay_payment_target() {
+        assert!(matches!(
+            relay_payment_target("https://pay.example.com/lnurlp"),
+            Some(Lud06OrLud16::Lud06(..))
+        ));
+        assert!(matches!(
+            relay_payment_target("admission@pay.example.com"),
+            Some(Lud06OrLud16::Lud16(..))
+        ));
+        assert!(relay_payment_target("ftp://pay.example.com").is_none());
+        assert!(relay_payment_target("not a payment url").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pay_admission() {
+        // Zapper not configured
+        let client = Client::builder().http_client(MockHttpClient).build();
+        let err = client
+            .pay_admission(&payment("https://pay.example.com/lnurlp"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ZapperNotConfigured));
+
+        let zapper = Arc::new(MockZapper::default());
+        let client = Client::builder()
+            .http_client(MockHttpClient)
+            .zapper(zapper.clone())
+            .build();
+
+        // Web page: must be paid there
+        let err = client
+            .pay_admission(&payment("https://pay.example.com/join"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ImpossibleToPayRelay(..)));
+        assert!(zapper.paid.lock().unwrap().is_empty());
+
+        // LNURL-pay endpoint
+        client
+            .pay_admission(&payment("https://pay.example.com/lnurlp"))
+            .await
+            .unwrap();
+
+        // Lightning address
+        client
+            .pay_admission(&payment("admission@pay.example.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            zapper.paid.lock().unwrap().as_slice(),
+            [INVOICE.to_string(), INVOICE.to_string()]
+        );
+    }
+}
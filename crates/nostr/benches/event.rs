@@ -0,0 +1,174 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use nostr::prelude::*;
+
+/// Number of events in the corpus
+const CORPUS_SIZE: usize = 1_000;
+
+/// Build a corpus similar to a real feed: text notes with mentions and hashtags,
+/// reactions, reposts, metadata and long-form articles, from a few hundreds authors.
+fn corpus() -> Vec<Event> {
+    let authors: Vec<Keys> = (0..200).map(|_| Keys::generate()).collect();
+    let mut events: Vec<Event> = Vec::with_capacity(CORPUS_SIZE);
+
+    for i in 0..CORPUS_SIZE {
+        let keys: &Keys = &authors[i % authors.len()];
+        let mentioned: PublicKey = authors[(i * 7) % authors.len()].public_key();
+
+        let builder: EventBuilder = match i % 10 {
+            0 => EventBuilder::metadata(
+                &Metadata::new()
+                    .name(format!("user{i}"))
+                    .about("Bitcoin, nostr and open source. Building stuff.")
+                    .picture(Url::parse("https://example.com/avatar.jpg").unwrap()),
+            ),
+            1 | 2 if !events.is_empty() => {
+                EventBuilder::reaction(&events[i / 2], if i % 3 == 0 { "+" } else { "🤙" })
+            }
+            3 if !events.is_empty() => EventBuilder::repost(&events[i / 3], None),
+            4 => EventBuilder::long_form_text_note("Lorem ipsum dolor sit amet. ".repeat(200))
+                .tags([
+                    Tag::identifier(format!("article-{i}")),
+                    Tag::hashtag("nostr"),
+                    Tag::hashtag("bitcoin"),
+                ]),
+            _ => EventBuilder::text_note(format!(
+                "GM nostr! Note #{i}, with some unicode: ⚡️🫂 and an \"escaped\" string.\n{}",
+                "Just a normal sized message. ".repeat(i % 8)
+            ))
+            .tags([
+                Tag::public_key(mentioned),
+                Tag::hashtag(if i % 2 == 0 { "nostr" } else { "grownostr" }),
+            ]),
+        };
+
+        events.push(
+            builder
+                .custom_created_at(Timestamp::from(1_700_000_000 + i as u64))
+                .sign_with_keys(keys)
+                .unwrap(),
+        );
+    }
+
+    events
+}
+
+fn event_id(c: &mut Criterion) {
+    let events: Vec<Event> = corpus();
+
+    let mut group = c.benchmark_group("event_id");
+    group.throughput(Throughput::Elements(events.len() as u64));
+    group.bench_function("compute", |b| {
+        b.iter(|| {
+            for event in events.iter() {
+                black_box(EventId::new(
+                    &event.pubkey,
+                    &event.created_at,
+                    &event.kind,
+                    event.tags.as_slice(),
+                    &event.content,
+                ));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn verification(c: &mut Criterion) {
+    let events: Vec<Event> = corpus();
+
+    let mut group = c.benchmark_group("verification");
+    group.throughput(Throughput::Elements(events.len() as u64));
+    group.bench_function("id", |b| {
+        b.iter(|| {
+            for event in events.iter() {
+                black_box(event.verify_id());
+            }
+        })
+    });
+    group.bench_function("signature", |b| {
+        b.iter(|| {
+            for event in events.iter() {
+                black_box(event.verify_signature());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn relay_message_parsing(c: &mut Criterion) {
+    let messages: Vec<String> = corpus()
+        .into_iter()
+        .map(|event| RelayMessage::event(SubscriptionId::new("feed"), event).as_json())
+        .collect();
+    let bytes: u64 = messages.iter().map(|m| m.len() as u64).sum();
+
+    let mut group = c.benchmark_group("relay_message");
+    group.throughput(Throughput::Bytes(bytes));
+    group.bench_function("from_json", |b| {
+        b.iter_batched(
+            || messages.clone(),
+            |messages| {
+                for msg in messages.into_iter() {
+                    black_box(RelayMessage::from_json(msg).unwrap());
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("raw_from_json", |b| {
+        b.iter(|| {
+            for msg in messages.iter() {
+                black_box(RawRelayMessage::from_json(msg).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn filter_matching(c: &mut Criterion) {
+    let events: Vec<Event> = corpus();
+    let authors: Vec<PublicKey> = events.iter().take(50).map(|e| e.pubkey).collect();
+
+    let filters: [(&str, Filter); 4] = [
+        ("kind", Filter::new().kind(Kind::TextNote)),
+        (
+            "authors_kinds",
+            Filter::new()
+                .authors(authors)
+                .kinds([Kind::TextNote, Kind::Repost]),
+        ),
+        ("hashtag", Filter::new().hashtag("nostr")),
+        (
+            "mention_since",
+            Filter::new()
+                .pubkey(events[0].pubkey)
+                .since(Timestamp::from(1_700_000_500)),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("filter_match_event");
+    group.throughput(Throughput::Elements(events.len() as u64));
+    for (name, filter) in filters.iter() {
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                for event in events.iter() {
+                    black_box(filter.match_event(event));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    event_id,
+    verification,
+    relay_message_parsing,
+    filter_matching
+);
+criterion_main!(benches);
@@ -0,0 +1,96 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Hex and SHA256 benches
+//!
+//! Run them without and with the `performance` feature to compare the implementations:
+//!
+//! ```bash
+//! cargo bench -p nostr --bench hex -- --save-baseline scalar
+//! cargo bench -p nostr --bench hex --features performance -- --baseline scalar
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use nostr::prelude::*;
+use nostr::util::hex;
+
+/// IDs, public keys and signatures of a bulk ingestion
+fn hex_corpus() -> Vec<String> {
+    (0..1_000)
+        .map(|i| {
+            let keys = Keys::generate();
+            let event: Event = EventBuilder::text_note(format!("Note #{i}"))
+                .sign_with_keys(&keys)
+                .unwrap();
+            match i % 3 {
+                0 => event.id.to_hex(),
+                1 => event.pubkey.to_hex(),
+                _ => event.sig.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn hex_encode_decode(c: &mut Criterion) {
+    let strings: Vec<String> = hex_corpus();
+    let bytes: Vec<Vec<u8>> = strings.iter().map(|s| hex::decode(s).unwrap()).collect();
+    let size: u64 = strings.iter().map(|s| s.len() as u64).sum();
+
+    let mut group = c.benchmark_group("hex");
+    group.throughput(Throughput::Bytes(size));
+    group.bench_function("encode", |b| {
+        b.iter(|| {
+            for data in bytes.iter() {
+                black_box(hex::encode(data));
+            }
+        })
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| {
+            for s in strings.iter() {
+                black_box(hex::decode(s).unwrap());
+            }
+        })
+    });
+    group.bench_function("decode_to_slice", |b| {
+        let mut out = [0u8; 64];
+        b.iter(|| {
+            for s in strings.iter() {
+                hex::decode_to_slice(s, &mut out[..s.len() / 2]).unwrap();
+                black_box(&out);
+            }
+        })
+    });
+    group.finish();
+}
+
+fn sha256(c: &mut Criterion) {
+    let keys = Keys::generate();
+
+    let mut group = c.benchmark_group("event_id_from_canonical_json");
+    for (name, content) in [
+        ("reaction", String::from("+")),
+        ("text_note", "Just a normal sized message. ".repeat(10)),
+        ("long_form", "Lorem ipsum dolor sit amet. ".repeat(500)),
+    ] {
+        let tags: Vec<Tag> = vec![Tag::public_key(keys.public_key()), Tag::hashtag("nostr")];
+        let json: String = canonical_json(
+            &keys.public_key(),
+            &Timestamp::from(1_700_000_000),
+            &Kind::TextNote,
+            &tags,
+            &content,
+            CanonicalEscaping::default(),
+        );
+
+        group.throughput(Throughput::Bytes(json.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter(|| black_box(EventId::from_canonical_json(&json)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, hex_encode_decode, sha256);
+criterion_main!(benches);
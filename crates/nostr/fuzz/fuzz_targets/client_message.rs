@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nostr::prelude::*;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(msg) = ClientMessage::from_json(data) {
+        // Re-serialization must not panic
+        let _ = msg.as_json();
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nostr::prelude::*;
+
+fuzz_target!(|data: &[u8]| {
+    let secret_key =
+        SecretKey::from_hex("6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e")
+            .unwrap();
+    let public_key =
+        PublicKey::from_hex("f572561e79d30c334e7ce864a2055e887eafdf5dad71242adc0c43567cc269f8")
+            .unwrap();
+
+    if let Ok(payload) = core::str::from_utf8(data) {
+        let _ = nip04::decrypt_to_bytes(&secret_key, &public_key, payload);
+    }
+});
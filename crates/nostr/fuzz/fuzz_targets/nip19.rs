@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nostr::prelude::*;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = core::str::from_utf8(data) {
+        if let Ok(nip19) = Nip19::from_bech32(s) {
+            // Re-encoding must not panic
+            let _ = nip19.to_bech32();
+        }
+
+        let _ = PublicKey::parse(s);
+        let _ = EventId::parse(s);
+        let _ = Coordinate::parse(s);
+        let _ = Nip21::parse(s);
+    }
+});
@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nostr::prelude::*;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(msg) = RelayMessage::from_json(data) {
+        // Re-serialization must not panic
+        let _ = msg.as_json();
+
+        if let RelayMessage::Event { event, .. } = msg {
+            let _ = event.verify_id();
+            let _ = event.canonical_json();
+            for tag in event.tags.iter() {
+                let _ = tag.as_standardized();
+            }
+        }
+    }
+
+    let _ = RawRelayMessage::from_json(data);
+});
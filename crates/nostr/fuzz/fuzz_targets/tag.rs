@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nostr::prelude::*;
+
+fuzz_target!(|data: &[u8]| {
+    // Tag values are separated by NUL bytes
+    let values: Vec<String> = data
+        .split(|b| *b == 0)
+        .map(|v| String::from_utf8_lossy(v).to_string())
+        .collect();
+
+    if let Ok(tag) = Tag::parse(values) {
+        let _ = tag.kind();
+        let _ = tag.content();
+        if let Some(standardized) = tag.as_standardized() {
+            // Converting back must not panic
+            let _ = standardized.clone().to_vec();
+        }
+    }
+});
@@ -65,6 +65,8 @@ pub enum Error {
         /// The expected kind (single or range)
         expected: WrongKindError,
     },
+    /// Content policy error
+    ContentPolicy(ContentPolicyError),
 }
 
 #[cfg(feature = "std")]
@@ -87,6 +89,7 @@ impl fmt::Display for Error {
             Self::WrongKind { received, expected } => {
                 write!(f, "Wrong kind: received={received}, expected={expected}")
             }
+            Self::ContentPolicy(e) => write!(f, "{e}"),
         }
     }
 }
@@ -103,6 +106,12 @@ impl From<super::unsigned::Error> for Error {
     }
 }
 
+impl From<ContentPolicyError> for Error {
+    fn from(e: ContentPolicyError) -> Self {
+        Self::ContentPolicy(e)
+    }
+}
+
 #[cfg(feature = "nip03")]
 impl From<nostr_ots::Error> for Error {
     fn from(e: nostr_ots::Error) -> Self {
@@ -200,6 +209,29 @@ impl EventBuilder {
         self
     }
 
+    /// Mark the event as protected
+    ///
+    /// Relays should accept the event only if published by the author (NIP42 authenticated).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/70.md>
+    #[inline]
+    pub fn protected(self) -> Self {
+        self.tag(Tag::protected())
+    }
+
+    /// Add a short human-readable summary of the event (`alt` tag)
+    ///
+    /// Recommended for the custom kinds, so the clients that don't support them can show something meaningful.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/31.md>
+    #[inline]
+    pub fn alt<S>(self, summary: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.tag(Tag::alt(summary))
+    }
+
     /// Set POW difficulty
     ///
     /// Only values `> 0` are accepted!
@@ -211,6 +243,18 @@ impl EventBuilder {
         self
     }
 
+    /// Apply a [`ContentPolicy`] to the content and the tags
+    ///
+    /// Must be called before building the event.
+    #[inline]
+    pub fn apply_policy<P>(mut self, policy: &P) -> Result<Self, Error>
+    where
+        P: ContentPolicy + ?Sized,
+    {
+        policy.apply(self.kind, &mut self.content, &mut self.tags)?;
+        Ok(self)
+    }
+
     /// Build unsigned event
     pub fn build_with_ctx<T>(self, supplier: &T, public_key: PublicKey) -> UnsignedEvent
     where
@@ -292,6 +336,21 @@ impl EventBuilder {
         self.build_with_ctx(&Instant::now(), pubkey)
     }
 
+    /// Build unsigned event, taking the `created_at` from a [`TimeProvider`]
+    ///
+    /// The `created_at` set with [`EventBuilder::custom_created_at`] has the precedence.
+    #[inline]
+    #[cfg(feature = "std")]
+    pub fn build_with_provider<T>(mut self, provider: &T, pubkey: PublicKey) -> UnsignedEvent
+    where
+        T: TimeProvider + ?Sized,
+    {
+        if self.custom_created_at.is_none() {
+            self.custom_created_at = Some(provider.now());
+        }
+        self.build(pubkey)
+    }
+
     /// Build, sign and return [`Event`]
     ///
     /// Shortcut for `builder.build(public_key).sign(signer)`.
@@ -901,6 +960,57 @@ impl EventBuilder {
         Self::new(Kind::Reporting, content).tags(tags)
     }
 
+    /// Request to vanish
+    ///
+    /// Request the targeted relays to delete all the events of the author,
+    /// created before the request.
+    /// The `reason` is an optional human-readable explanation (can be empty).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/62.md>
+    pub fn request_vanish<S>(target: VanishTarget, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let tags: Vec<Tag> = match target {
+            VanishTarget::AllRelays => vec![Tag::custom(TagKind::Relay, [ALL_RELAYS])],
+            VanishTarget::Relays(relays) => relays
+                .into_iter()
+                .map(|url| Tag::from_standardized_without_cell(TagStandard::Relay(url)))
+                .collect(),
+        };
+        Self::new(Kind::RequestToVanish, reason).tags(tags)
+    }
+
+    /// Report a public key
+    ///
+    /// The `reason` is an optional human-readable explanation (can be empty).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/56.md>
+    #[inline]
+    pub fn report_public_key<S>(public_key: PublicKey, report: Report, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::report([Tag::public_key_report(public_key, report)], reason)
+    }
+
+    /// Report an event
+    ///
+    /// The `author` is the public key of the reported event author.
+    /// The `reason` is an optional human-readable explanation (can be empty).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/56.md>
+    #[inline]
+    pub fn report_event<S>(event_id: EventId, author: PublicKey, report: Report, reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::report(
+            [Tag::event_report(event_id, report), Tag::public_key(author)],
+            reason,
+        )
+    }
+
     /// Create **public** zap request event
     ///
     /// **This event MUST NOT be broadcasted to relays**, instead must be sent to a recipient's LNURL pay callback url.
@@ -1701,6 +1811,16 @@ impl EventBuilder {
         patch.to_event_builder()
     }
 
+    /// Git Status
+    ///
+    /// Set the status of a git patch or issue.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/34.md>
+    #[inline]
+    pub fn git_status(status: GitStatus) -> Self {
+        status.to_event_builder()
+    }
+
     /// Torrent metadata
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/35.md>
@@ -1709,6 +1829,66 @@ impl EventBuilder {
         metadata.to_event_builder()
     }
 
+    /// Classified listing
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/99.md>
+    #[inline]
+    pub fn classified_listing(listing: ClassifiedListing) -> Self {
+        listing.to_event_builder(Kind::ClassifiedListing)
+    }
+
+    /// Draft or inactive classified listing
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/99.md>
+    #[inline]
+    pub fn draft_classified_listing(listing: ClassifiedListing) -> Self {
+        listing.to_event_builder(Kind::DraftClassifiedListing)
+    }
+
+    /// Handler recommendation
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+    #[inline]
+    pub fn handler_recommendation(recommendation: HandlerRecommendation) -> Self {
+        recommendation.to_event_builder()
+    }
+
+    /// Handler information
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/89.md>
+    #[inline]
+    pub fn handler_information(info: HandlerInformation) -> Self {
+        info.to_event_builder()
+    }
+
+    /// Relay discovery
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    #[inline]
+    pub fn relay_discovery(discovery: RelayDiscovery) -> Self {
+        discovery.to_event_builder()
+    }
+
+    /// Relay monitor announcement
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/66.md>
+    #[inline]
+    pub fn relay_monitor_announcement(announcement: RelayMonitorAnnouncement) -> Self {
+        announcement.to_event_builder()
+    }
+
+    /// Application-specific data
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/78.md>
+    #[inline]
+    pub fn application_data<I, S>(identifier: I, content: S) -> Self
+    where
+        I: Into<String>,
+        S: Into<String>,
+    {
+        Self::new(Kind::ApplicationSpecificData, content).tag(Tag::identifier(identifier))
+    }
+
     // TODO: add `torrent_comment`
 }
 
@@ -1973,6 +2153,46 @@ mod tests {
         assert_eq!(profile_badges.kind, Kind::ProfileBadges);
         assert_eq!(profile_badges.tags, example_event.tags);
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_protected_event_builder() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("hello")
+            .protected()
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(event.is_protected());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_report_event_builder() {
+        let keys = Keys::generate();
+        let author = Keys::generate().public_key();
+        let event_id = EventId::all_zeros();
+
+        let event = EventBuilder::report_public_key(author, Report::Impersonation, "Fake profile")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::Reporting);
+        assert_eq!(event.content, "Fake profile");
+        assert_eq!(
+            event.tags.to_vec(),
+            vec![Tag::public_key_report(author, Report::Impersonation)]
+        );
+
+        let event = EventBuilder::report_event(event_id, author, Report::Nudity, "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            event.tags.to_vec(),
+            vec![
+                Tag::event_report(event_id, Report::Nudity),
+                Tag::public_key(author),
+            ]
+        );
+    }
 }
 
 #[cfg(bench)]
@@ -0,0 +1,212 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Outbound content policy
+//!
+//! Check and sanitize the content and the tags of the events before signing them.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::{Kind, Tag};
+use crate::signer::{SendOutsideWasm, SyncOutsideWasm};
+
+/// Content policy error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentPolicyError {
+    /// Content too long
+    ContentTooLong {
+        /// Content length (bytes)
+        len: usize,
+        /// Max allowed length (bytes)
+        max: usize,
+    },
+    /// Invalid tag
+    InvalidTag(String),
+    /// Rejected by a custom policy
+    Rejected(String),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ContentPolicyError {}
+
+impl fmt::Display for ContentPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ContentTooLong { len, max } => {
+                write!(f, "content too long: len={len} bytes, max={max} bytes")
+            }
+            Self::InvalidTag(reason) => write!(f, "invalid tag: {reason}"),
+            Self::Rejected(reason) => write!(f, "rejected: {reason}"),
+        }
+    }
+}
+
+/// Outbound content policy
+///
+/// Applied to the [`EventBuilder`](super::EventBuilder) before building and signing the event
+/// (check [`EventBuilder::apply_policy`](super::EventBuilder::apply_policy)).
+pub trait ContentPolicy: fmt::Debug + SendOutsideWasm + SyncOutsideWasm {
+    /// Check and/or rewrite the content and the tags
+    fn apply(
+        &self,
+        kind: Kind,
+        content: &mut String,
+        tags: &mut Vec<Tag>,
+    ) -> Result<(), ContentPolicyError>;
+}
+
+#[doc(hidden)]
+pub trait IntoContentPolicy {
+    fn into_content_policy(self) -> Arc<dyn ContentPolicy>;
+}
+
+impl IntoContentPolicy for Arc<dyn ContentPolicy> {
+    fn into_content_policy(self) -> Arc<dyn ContentPolicy> {
+        self
+    }
+}
+
+impl<T> IntoContentPolicy for T
+where
+    T: ContentPolicy + 'static,
+{
+    fn into_content_policy(self) -> Arc<dyn ContentPolicy> {
+        Arc::new(self)
+    }
+}
+
+impl<T> IntoContentPolicy for Arc<T>
+where
+    T: ContentPolicy + 'static,
+{
+    fn into_content_policy(self) -> Arc<dyn ContentPolicy> {
+        self
+    }
+}
+
+/// Standard content policy
+///
+/// By default, nothing is changed or rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StandardContentPolicy {
+    max_content_len: Option<usize>,
+    strip_control_chars: bool,
+    validate_tags: bool,
+}
+
+impl StandardContentPolicy {
+    /// New standard content policy
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Max content length, in bytes (default: no limit)
+    ///
+    /// The length is checked after stripping the control chars.
+    #[inline]
+    pub fn max_content_len(mut self, max: usize) -> Self {
+        self.max_content_len = Some(max);
+        self
+    }
+
+    /// Strip the control chars from the content, except the newlines and the tabs (default: false)
+    #[inline]
+    pub fn strip_control_chars(mut self, enable: bool) -> Self {
+        self.strip_control_chars = enable;
+        self
+    }
+
+    /// Reject the tags with an empty name, a NUL char or a U+FFFD replacement char (default: false)
+    ///
+    /// The replacement char is the trace of a lossy decoding of invalid UTF-8 data.
+    #[inline]
+    pub fn validate_tags(mut self, enable: bool) -> Self {
+        self.validate_tags = enable;
+        self
+    }
+}
+
+impl ContentPolicy for StandardContentPolicy {
+    fn apply(
+        &self,
+        _kind: Kind,
+        content: &mut String,
+        tags: &mut Vec<Tag>,
+    ) -> Result<(), ContentPolicyError> {
+        if self.strip_control_chars {
+            content.retain(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'));
+        }
+
+        if let Some(max) = self.max_content_len {
+            if content.len() > max {
+                return Err(ContentPolicyError::ContentTooLong {
+                    len: content.len(),
+                    max,
+                });
+            }
+        }
+
+        if self.validate_tags {
+            for tag in tags.iter() {
+                let values: &[String] = tag.as_slice();
+
+                if values.first().map_or(true, |name| name.is_empty()) {
+                    return Err(ContentPolicyError::InvalidTag(String::from("empty name")));
+                }
+
+                if values.iter().any(|value| {
+                    value.contains(|c: char| c == '\0' || c == char::REPLACEMENT_CHARACTER)
+                }) {
+                    return Err(ContentPolicyError::InvalidTag(format!(
+                        "invalid chars in `{}` tag",
+                        values[0]
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_content_policy() {
+        let policy = StandardContentPolicy::new()
+            .max_content_len(11)
+            .strip_control_chars(true)
+            .validate_tags(true);
+
+        let mut content = String::from("hello\u{7}\nworld");
+        let mut tags = vec![Tag::hashtag("nostr")];
+        policy
+            .apply(Kind::TextNote, &mut content, &mut tags)
+            .unwrap();
+        assert_eq!(content, "hello\nworld");
+
+        let mut content = String::from("hello world!");
+        assert_eq!(
+            policy
+                .apply(Kind::TextNote, &mut content, &mut tags)
+                .unwrap_err(),
+            ContentPolicyError::ContentTooLong { len: 12, max: 11 }
+        );
+
+        let mut content = String::new();
+        let mut tags = vec![Tag::parse(["t", "nos\u{FFFD}tr"]).unwrap()];
+        assert!(matches!(
+            policy
+                .apply(Kind::TextNote, &mut content, &mut tags)
+                .unwrap_err(),
+            ContentPolicyError::InvalidTag(..)
+        ));
+    }
+}
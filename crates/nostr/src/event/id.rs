@@ -5,13 +5,16 @@
 //! Event Id
 
 use alloc::string::{String, ToString};
-use core::fmt;
+use core::fmt::{self, Write};
 use core::str::FromStr;
 
+#[cfg(not(feature = "performance"))]
 use bitcoin::hashes::sha256::Hash as Sha256Hash;
+#[cfg(not(feature = "performance"))]
 use bitcoin::hashes::Hash;
 use serde::{Deserialize, Deserializer, Serialize};
-use serde_json::{json, Value};
+#[cfg(feature = "performance")]
+use sha2::{Digest, Sha256};
 
 use super::{Kind, Tag};
 use crate::nips::nip13;
@@ -47,6 +50,87 @@ impl From<hex::Error> for Error {
     }
 }
 
+/// Escaping of the strings in the canonical JSON serialization
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CanonicalEscaping {
+    /// Escape `"`, `\\`, line break, carriage return, tab, backspace and form feed,
+    /// plus the other control characters as `\u00XX` (default)
+    ///
+    /// Same output of `JSON.stringify` and of most of the implementations.
+    #[default]
+    Json,
+    /// Escape only the characters listed in NIP01 (`"`, `\\`, line break, carriage return, tab, backspace and form feed)
+    ///
+    /// All the other characters are included verbatim.
+    Strict,
+}
+
+/// Serialize the event data in the canonical form used to compute the [`EventId`]
+///
+/// `[0,<pubkey>,<created_at>,<kind>,<tags>,<content>]`, UTF-8 encoded and without whitespaces.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+pub fn canonical_json(
+    public_key: &PublicKey,
+    created_at: &Timestamp,
+    kind: &Kind,
+    tags: &[Tag],
+    content: &str,
+    escaping: CanonicalEscaping,
+) -> String {
+    let mut json: String = String::with_capacity(128 + content.len());
+
+    json.push_str("[0,\"");
+    json.push_str(&public_key.to_hex());
+    json.push_str("\",");
+    json.push_str(&created_at.as_u64().to_string());
+    json.push(',');
+    json.push_str(&kind.as_u16().to_string());
+    json.push_str(",[");
+    for (i, tag) in tags.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+
+        json.push('[');
+        for (j, value) in tag.as_slice().iter().enumerate() {
+            if j > 0 {
+                json.push(',');
+            }
+            push_escaped_str(&mut json, value, escaping);
+        }
+        json.push(']');
+    }
+    json.push_str("],");
+    push_escaped_str(&mut json, content, escaping);
+    json.push(']');
+
+    json
+}
+
+fn push_escaped_str(json: &mut String, value: &str, escaping: CanonicalEscaping) {
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            '\u{08}' => json.push_str("\\b"),
+            '\u{0C}' => json.push_str("\\f"),
+            '\u{00}'..='\u{1F}' if escaping == CanonicalEscaping::Json => {
+                // Writing to a `String` can't fail
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
 /// Event ID
 ///
 /// 32-bytes lowercase hex-encoded sha256 of the serialized event data
@@ -66,6 +150,7 @@ impl EventId {
     pub const LEN: usize = 32;
 
     /// Generate [`EventId`]
+    #[inline]
     pub fn new(
         public_key: &PublicKey,
         created_at: &Timestamp,
@@ -73,10 +158,33 @@ impl EventId {
         tags: &[Tag],
         content: &str,
     ) -> Self {
-        let json: Value = json!([0, public_key, created_at, kind, tags, content]);
-        let event_str: String = json.to_string();
-        let hash: Sha256Hash = Sha256Hash::hash(event_str.as_bytes());
-        Self::from_byte_array(hash.to_byte_array())
+        Self::new_with_escaping(
+            public_key,
+            created_at,
+            kind,
+            tags,
+            content,
+            CanonicalEscaping::default(),
+        )
+    }
+
+    /// Generate [`EventId`] using a specific [`CanonicalEscaping`]
+    pub fn new_with_escaping(
+        public_key: &PublicKey,
+        created_at: &Timestamp,
+        kind: &Kind,
+        tags: &[Tag],
+        content: &str,
+        escaping: CanonicalEscaping,
+    ) -> Self {
+        let json: String = canonical_json(public_key, created_at, kind, tags, content, escaping);
+        Self::from_canonical_json(&json)
+    }
+
+    /// Compute [`EventId`] from the canonical JSON serialization (see [`canonical_json`])
+    #[inline]
+    pub fn from_canonical_json(json: &str) -> Self {
+        Self::from_byte_array(sha256(json.as_bytes()))
     }
 
     /// Construct event ID from 32-byte array
@@ -225,9 +333,149 @@ impl<'de> Deserialize<'de> for EventId {
     }
 }
 
+#[cfg(not(feature = "performance"))]
+#[inline]
+fn sha256(data: &[u8]) -> [u8; EventId::LEN] {
+    Sha256Hash::hash(data).to_byte_array()
+}
+
+/// SHA256 with the `sha2` crate (CPU intrinsics or assembly, when available)
+#[cfg(feature = "performance")]
+#[inline]
+fn sha256(data: &[u8]) -> [u8; EventId::LEN] {
+    Sha256::digest(data).into()
+}
+
 #[cfg(test)]
 mod tests {
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    use serde_json::json;
+
     use super::*;
+    use crate::{Event, JsonUtil};
+
+    const PUBLIC_KEY: &str = "e8ed3798c6ffebffa08501ac39e271662bfd160f688f94c45d692d8767dd345a";
+
+    fn assert_same_as_serde_json(tags: &[Tag], content: &str) {
+        let public_key = PublicKey::from_hex(PUBLIC_KEY).unwrap();
+        let created_at = Timestamp::from(1703184271);
+        let kind = Kind::TextNote;
+        let expected: String = json!([0, public_key, created_at, kind, tags, content]).to_string();
+        let json: String = canonical_json(
+            &public_key,
+            &created_at,
+            &kind,
+            tags,
+            content,
+            CanonicalEscaping::Json,
+        );
+        assert_eq!(json, expected);
+    }
+
+    #[test]
+    fn test_canonical_json_reference_vectors() {
+        let event = Event::from_json(r#"{"content":"Think about this.\n\nThe most powerful centralized institutions in the world have been replaced by a protocol that protects the individual. #bitcoin\n\nDo you doubt that we can replace everything else?\n\nBullish on the future of humanity\nnostr:nevent1qqs9ljegkuk2m2ewfjlhxy054n6ld5dfngwzuep0ddhs64gc49q0nmqpzdmhxue69uhhyetvv9ukzcnvv5hx7un8qgsw3mfhnrr0l6ll5zzsrtpeufckv2lazc8k3ru5c3wkjtv8vlwngksrqsqqqqqpttgr27","created_at":1703184271,"id":"38acf9b08d06859e49237688a9fd6558c448766f47457236c2331f93538992c6","kind":1,"pubkey":"e8ed3798c6ffebffa08501ac39e271662bfd160f688f94c45d692d8767dd345a","sig":"f76d5ecc8e7de688ac12b9d19edaacdcffb8f0c8fa2a44c00767363af3f04dbc069542ddc5d2f63c94cb5e6ce701589d538cf2db3b1f1211a96596fabb6ecafe","tags":[["e","5fcb28b72cadab2e4cbf7311f4acf5f6d1a99a1c2e642f6b6f0d5518a940f9ec","","mention"],["p","e8ed3798c6ffebffa08501ac39e271662bfd160f688f94c45d692d8767dd345a","","mention"],["t","bitcoin"],["t","bitcoin"]]}"#).unwrap();
+        let json: String = event.canonical_json();
+        assert!(json.starts_with(r#"[0,"e8ed3798c6ffebffa08501ac39e271662bfd160f688f94c45d692d8767dd345a",1703184271,1,[["e","#));
+        assert_eq!(EventId::from_canonical_json(&json), event.id);
+
+        let event = Event::from_json(r#"{"content":"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA==","created_at":1640839235,"id":"2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45","kind":4,"pubkey":"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785","sig":"a5d9290ef9659083c490b303eb7ee41356d8778ff19f2f91776c8dc4443388a64ffcf336e61af4c25c05ac3ae952d1ced889ed655b67790891222aaa15b99fdd","tags":[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]]}"#).unwrap();
+        assert_eq!(
+            event.canonical_json(),
+            r#"[0,"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785",1640839235,4,[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]],"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA=="]"#
+        );
+        assert_eq!(
+            EventId::from_canonical_json(&event.canonical_json()),
+            event.id
+        );
+    }
+
+    #[test]
+    fn test_sha256_vectors() {
+        // FIPS 180-2 test vectors, to check the `performance` implementation too
+        assert_eq!(
+            hex::encode(sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex::encode(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex::encode(sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+
+        assert_eq!(
+            hex::encode(sha256(&[b'a'; 1_000])),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_canonical_json_escaping() {
+        let public_key = PublicKey::from_hex(PUBLIC_KEY).unwrap();
+        let created_at = Timestamp::from(1);
+        let content = "\"\\\n\r\t\u{08}\u{0C}\u{01}\u{7F}é/";
+
+        assert_eq!(
+            canonical_json(
+                &public_key,
+                &created_at,
+                &Kind::TextNote,
+                &[],
+                content,
+                CanonicalEscaping::Json
+            ),
+            format!("[0,\"{PUBLIC_KEY}\",1,1,[],\"\\\"\\\\\\n\\r\\t\\b\\f\\u0001\u{7F}é/\"]")
+        );
+        assert_eq!(
+            canonical_json(
+                &public_key,
+                &created_at,
+                &Kind::TextNote,
+                &[],
+                content,
+                CanonicalEscaping::Strict
+            ),
+            format!("[0,\"{PUBLIC_KEY}\",1,1,[],\"\\\"\\\\\\n\\r\\t\\b\\f\u{01}\u{7F}é/\"]")
+        );
+    }
+
+    #[test]
+    fn test_canonical_json_same_as_serde_json() {
+        // Every ASCII char
+        for c in '\u{00}'..='\u{7F}' {
+            let value: String = c.to_string();
+            let tag = Tag::parse(["t", value.as_str()]).unwrap();
+            assert_same_as_serde_json(&[tag], &value);
+        }
+
+        // Pseudo-random strings (xorshift), mixing control, ASCII and multi-byte chars
+        let alphabet: Vec<char> = ('\u{00}'..='\u{7F}')
+            .chain(['é', 'ß', '€', '字', '🦀', '\u{2028}', '\u{FEFF}'])
+            .collect();
+        let mut state: u64 = 0x9E3779B97F4A7C15;
+        for _ in 0..1000 {
+            let mut next = || {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state
+            };
+
+            let len: usize = (next() % 32) as usize;
+            let content: String = (0..len)
+                .map(|_| alphabet[(next() % alphabet.len() as u64) as usize])
+                .collect();
+            let tag = Tag::parse(["subject", content.as_str(), ""]).unwrap();
+            assert_same_as_serde_json(&[tag], &content);
+        }
+    }
 
     #[test]
     fn test_check_pow() {
@@ -98,6 +98,7 @@ kind_variants! {
     OpenTimestamps => 1040, "OpenTimestamps Attestations (NIP03)",
     EncryptedDirectMessage => 4, "Encrypted Direct Messages (NIP04)",
     EventDeletion => 5, "Event Deletion (NIP09)",
+    RequestToVanish => 62, "Request to Vanish (NIP62)",
     Repost => 6, "Repost (NIP18)",
     GenericRepost => 16, "Generic Repost (NIP18)",
     Comment => 1111, "Comment (NIP22)",
@@ -171,6 +172,12 @@ kind_variants! {
     ApplicationSpecificData => 30078, "Application-specific Data (NIP78)",
     Torrent => 2003, "Torrent (NIP35)",
     TorrentComment => 2004, "Torrent Comment (NIP35)",
+    ClassifiedListing => 30402, "Classified Listing (NIP99)",
+    DraftClassifiedListing => 30403, "Draft or inactive Classified Listing (NIP99)",
+    RelayMonitorAnnouncement => 10166, "Relay Monitor Announcement (NIP66)",
+    RelayDiscovery => 30166, "Relay Discovery (NIP66)",
+    HandlerRecommendation => 31989, "Handler Recommendation (NIP89)",
+    HandlerInformation => 31990, "Handler Information (NIP89)",
 }
 
 impl PartialEq<Kind> for Kind {
@@ -20,6 +20,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 
 pub mod builder;
+pub mod content_policy;
 pub mod id;
 pub mod kind;
 pub mod partial;
@@ -28,16 +29,19 @@ pub mod tag;
 pub mod unsigned;
 
 pub use self::builder::EventBuilder;
-pub use self::id::EventId;
+pub use self::content_policy::{ContentPolicy, ContentPolicyError, StandardContentPolicy};
+pub use self::id::{CanonicalEscaping, EventId};
 pub use self::kind::Kind;
 pub use self::partial::{MissingPartialEvent, PartialEvent};
 pub use self::tag::{Tag, TagKind, TagStandard, Tags};
 pub use self::unsigned::UnsignedEvent;
 use crate::nips::nip01::Coordinate;
+use crate::nips::nip26::{self, EventProperties};
 use crate::types::metadata;
 #[cfg(feature = "std")]
 use crate::types::time::Instant;
 use crate::types::time::TimeSupplier;
+use crate::util::ContentPreview;
 #[cfg(feature = "std")]
 use crate::SECP256K1;
 use crate::{JsonUtil, Metadata, PublicKey, Timestamp};
@@ -216,6 +220,27 @@ impl Event {
         Ok(())
     }
 
+    /// Serialize the event in the canonical form used to compute the [`EventId`]
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    #[inline]
+    pub fn canonical_json(&self) -> String {
+        self.canonical_json_with_escaping(CanonicalEscaping::default())
+    }
+
+    /// Serialize the event in the canonical form, using a specific [`CanonicalEscaping`]
+    #[inline]
+    pub fn canonical_json_with_escaping(&self, escaping: CanonicalEscaping) -> String {
+        id::canonical_json(
+            &self.pubkey,
+            &self.created_at,
+            &self.kind,
+            self.tags.as_slice(),
+            &self.content,
+            escaping,
+        )
+    }
+
     /// Verify if the [`EventId`] it's composed correctly
     pub fn verify_id(&self) -> bool {
         let id: EventId = EventId::new(
@@ -314,6 +339,69 @@ impl Event {
     pub fn is_protected(&self) -> bool {
         self.tags.find_standardized(TagKind::Protected).is_some()
     }
+
+    /// Get the human-readable summary of the event (`alt` tag), if set
+    ///
+    /// Useful to render the events of unknown kinds.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/31.md>
+    #[inline]
+    pub fn alt(&self) -> Option<&str> {
+        self.tags.alt()
+    }
+
+    /// Get a single-line, truncated preview of the content, safe to include in the logs
+    #[inline]
+    pub fn content_preview(&self) -> ContentPreview<'_> {
+        ContentPreview::new(&self.content)
+    }
+
+    /// Get the effective author
+    ///
+    /// If the event has a valid NIP26 delegation tag (signature and conditions), return the delegator public key.
+    /// Otherwise, return the event author.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    #[inline]
+    #[cfg(feature = "std")]
+    pub fn effective_author(&self) -> PublicKey {
+        self.effective_author_with_ctx(&SECP256K1)
+    }
+
+    /// Get the effective author
+    ///
+    /// If the event has a valid NIP26 delegation tag (signature and conditions), return the delegator public key.
+    /// Otherwise, return the event author.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    pub fn effective_author_with_ctx<C>(&self, secp: &Secp256k1<C>) -> PublicKey
+    where
+        C: Verification,
+    {
+        if let Some(TagStandard::Delegation {
+            delegator,
+            conditions,
+            sig,
+        }) = self.tags.find_standardized(TagKind::Delegation)
+        {
+            let properties: EventProperties = EventProperties::from_event(self);
+            let is_valid: bool = nip26::verify_delegation_signature_with_ctx(
+                secp,
+                delegator,
+                *sig,
+                &self.pubkey,
+                conditions,
+            )
+            .is_ok()
+                && conditions.evaluate(&properties).is_ok();
+
+            if is_valid {
+                return *delegator;
+            }
+        }
+
+        self.pubkey
+    }
 }
 
 impl JsonUtil for Event {
@@ -659,6 +747,22 @@ mod tests {
         let event = Event::from_json(json).unwrap();
         assert!(!event.is_protected());
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_event_alt() {
+        let keys = Keys::generate();
+        let event = EventBuilder::new(Kind::Custom(33333), "")
+            .alt("A custom event")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.alt(), Some("A custom event"));
+
+        let event = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.alt(), None);
+    }
 }
 
 #[cfg(bench)]
@@ -65,6 +65,8 @@ pub enum TagKind<'a> {
     Image,
     /// Lnurl
     Lnurl,
+    /// Location
+    Location,
     /// Magnet
     Magnet,
     /// Maintainers
@@ -87,6 +89,8 @@ pub enum TagKind<'a> {
     Payload,
     /// Preimage
     Preimage,
+    /// Price
+    Price,
     /// Protected event
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/70.md>
@@ -252,6 +256,7 @@ impl<'a> TagKind<'a> {
             Self::File => "file",
             Self::Image => "image",
             Self::Lnurl => "lnurl",
+            Self::Location => "location",
             Self::Magnet => "magnet",
             Self::Maintainers => "maintainers",
             Self::Method => "method",
@@ -262,6 +267,7 @@ impl<'a> TagKind<'a> {
             Self::Nonce => "nonce",
             Self::Payload => "payload",
             Self::Preimage => "preimage",
+            Self::Price => "price",
             Self::Protected => "-",
             Self::Proxy => "proxy",
             Self::PublishedAt => "published_at",
@@ -320,6 +326,7 @@ impl<'a> From<&'a str> for TagKind<'a> {
             "file" => Self::File,
             "image" => Self::Image,
             "lnurl" => Self::Lnurl,
+            "location" => Self::Location,
             "magnet" => Self::Magnet,
             "maintainers" => Self::Maintainers,
             "method" => Self::Method,
@@ -330,6 +337,7 @@ impl<'a> From<&'a str> for TagKind<'a> {
             "nonce" => Self::Nonce,
             "payload" => Self::Payload,
             "preimage" => Self::Preimage,
+            "price" => Self::Price,
             "proxy" => Self::Proxy,
             "published_at" => Self::PublishedAt,
             "recording" => Self::Recording,
@@ -389,6 +397,12 @@ mod tests {
         assert_eq!(TagKind::from("maintainers"), TagKind::Maintainers);
         assert_eq!(TagKind::Maintainers.to_string(), "maintainers");
 
+        assert_eq!(TagKind::from("location"), TagKind::Location);
+        assert_eq!(TagKind::Location.to_string(), "location");
+
+        assert_eq!(TagKind::from("price"), TagKind::Price);
+        assert_eq!(TagKind::Price.to_string(), "price");
+
         assert_eq!(TagKind::from("tracker"), TagKind::Tracker);
         assert_eq!(TagKind::Tracker.to_string(), "tracker");
 
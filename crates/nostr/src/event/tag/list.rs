@@ -159,6 +159,17 @@ impl Tags {
         }
     }
 
+    /// Extract the human-readable summary (`alt` tag), if exists.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/31.md>
+    #[inline]
+    pub fn alt(&self) -> Option<&str> {
+        match self.find_standardized(TagKind::Alt)? {
+            TagStandard::Alt(summary) => Some(summary),
+            _ => None,
+        }
+    }
+
     /// Get [`Timestamp`] expiration, if set.
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/40.md>
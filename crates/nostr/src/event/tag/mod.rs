@@ -204,6 +204,17 @@ impl Tag {
         })
     }
 
+    /// Compose `["a", "<coordinate>", "<relay-url>"]` tag
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    pub fn coordinate_with_relay(coordinate: Coordinate, relay_url: RelayUrl) -> Self {
+        Self::from_standardized_without_cell(TagStandard::Coordinate {
+            coordinate,
+            relay_url: Some(relay_url),
+            uppercase: false,
+        })
+    }
+
     /// Compose `["nonce", "<nonce>", "<difficulty>"]` tag
     ///
     /// <https://github.com/nostr-protocol/nips/blob/master/13.md>
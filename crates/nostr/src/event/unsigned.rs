@@ -13,6 +13,7 @@ use bitcoin::secp256k1::rand::{CryptoRng, Rng};
 use bitcoin::secp256k1::schnorr::Signature;
 use bitcoin::secp256k1::{Message, Secp256k1, Signing, Verification};
 
+use super::id::{self, CanonicalEscaping};
 use crate::{Event, EventId, JsonUtil, Keys, Kind, PublicKey, SignerError, Tag, Tags, Timestamp};
 #[cfg(feature = "std")]
 use crate::{NostrSigner, SECP256K1};
@@ -100,6 +101,14 @@ impl UnsignedEvent {
         }
     }
 
+    /// Get the [`EventId`]
+    ///
+    /// If `id` is `None`, compute it (without setting it).
+    #[inline]
+    pub fn id(&self) -> EventId {
+        self.id.unwrap_or_else(|| self.compute_id())
+    }
+
     /// Ensure to set [EventId]
     ///
     /// If `id` is `None`, compute and set it.
@@ -110,6 +119,29 @@ impl UnsignedEvent {
         }
     }
 
+    /// Serialize the event in the canonical form used to compute the [`EventId`]
+    ///
+    /// Useful for external signing flows.
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+    #[inline]
+    pub fn canonical_json(&self) -> String {
+        self.canonical_json_with_escaping(CanonicalEscaping::default())
+    }
+
+    /// Serialize the event in the canonical form, using a specific [`CanonicalEscaping`]
+    #[inline]
+    pub fn canonical_json_with_escaping(&self, escaping: CanonicalEscaping) -> String {
+        id::canonical_json(
+            &self.pubkey,
+            &self.created_at,
+            &self.kind,
+            self.tags.as_slice(),
+            &self.content,
+            escaping,
+        )
+    }
+
     #[inline]
     fn compute_id(&self) -> EventId {
         EventId::new(
@@ -166,7 +198,7 @@ impl UnsignedEvent {
         R: Rng + CryptoRng,
     {
         let verify_id: bool = self.id.is_some();
-        let id: EventId = self.id.unwrap_or_else(|| self.compute_id());
+        let id: EventId = self.id();
         let message: Message = Message::from_digest(id.to_bytes());
         let sig: Signature = keys.sign_schnorr_with_ctx(secp, &message, rng);
         self.internal_add_signature(secp, id, sig, verify_id, false)
@@ -194,7 +226,7 @@ impl UnsignedEvent {
         C: Verification,
     {
         let verify_id: bool = self.id.is_some();
-        let id: EventId = self.id.unwrap_or_else(|| self.compute_id());
+        let id: EventId = self.id();
         self.internal_add_signature(secp, id, sig, verify_id, true)
     }
 
@@ -233,6 +265,22 @@ impl UnsignedEvent {
     }
 }
 
+/// Redacted representation: the content and the tags (i.e. of a NIP59 rumor) aren't displayed
+impl fmt::Display for UnsignedEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "UnsignedEvent(id={}, pubkey={}, kind={}, created_at={}, tags={}, content=<{} bytes>)",
+            self.id(),
+            self.pubkey,
+            self.kind,
+            self.created_at,
+            self.tags.len(),
+            self.content.len()
+        )
+    }
+}
+
 impl JsonUtil for UnsignedEvent {
     type Err = Error;
 }
@@ -280,4 +328,56 @@ mod tests {
         );
         assert_eq!(unsigned.kind, Kind::EncryptedDirectMessage);
     }
+
+    #[test]
+    fn test_redacted_display() {
+        let keys = Keys::generate();
+        let unsigned = UnsignedEvent::new(
+            keys.public_key(),
+            Timestamp::from(1640839235),
+            Kind::PrivateDirectMessage,
+            [],
+            "Secret message",
+        );
+        let display: String = unsigned.to_string();
+        assert!(display.contains(&unsigned.id().to_hex()));
+        assert!(!display.contains("Secret message"));
+    }
+
+    #[test]
+    fn test_add_signature() {
+        let keys = Keys::generate();
+        let unsigned = UnsignedEvent::new(
+            keys.public_key(),
+            Timestamp::from(1640839235),
+            Kind::TextNote,
+            [],
+            "Test",
+        );
+
+        // External signing flow
+        let id: EventId = unsigned.id();
+        let sig: Signature = keys.sign_schnorr(&Message::from_digest(id.to_bytes()));
+        let event: Event = unsigned.clone().add_signature(sig).unwrap();
+        assert_eq!(event.id, id);
+        assert!(event.verify().is_ok());
+
+        // Signature of another event
+        let mut other = unsigned.clone();
+        other.content = String::from("Other");
+        assert!(matches!(
+            other.add_signature(sig).unwrap_err(),
+            Error::Event(super::super::Error::InvalidSignature)
+        ));
+
+        // Tampered content with stale ID
+        let mut tampered = unsigned;
+        tampered.ensure_id();
+        tampered.content = String::from("Tampered");
+        assert!(tampered.verify_id().is_err());
+        assert!(matches!(
+            tampered.sign_with_keys(&keys).unwrap_err(),
+            Error::Event(super::super::Error::InvalidId)
+        ));
+    }
 }
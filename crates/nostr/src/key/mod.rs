@@ -85,6 +85,13 @@ impl fmt::Debug for Keys {
     }
 }
 
+/// Display the public key only: the secret key is never displayed
+impl fmt::Display for Keys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Keys({}, secret_key: <redacted>)", self.public_key)
+    }
+}
+
 impl PartialEq for Keys {
     fn eq(&self, other: &Self) -> bool {
         self.public_key == other.public_key
@@ -347,6 +354,14 @@ mod tests {
             Error::InvalidSecretKey
         );
     }
+
+    #[test]
+    fn test_redacted_display() {
+        let keys = Keys::parse(SECRET_KEY_HEX).unwrap();
+        let display: String = keys.to_string();
+        assert!(display.contains(&keys.public_key.to_hex()));
+        assert!(!display.contains(SECRET_KEY_HEX));
+    }
 }
 
 #[cfg(bench)]
@@ -31,7 +31,7 @@ extern crate alloc;
 extern crate serde;
 
 #[doc(hidden)]
-#[cfg(any(feature = "nip04", feature = "nip44"))]
+#[cfg(any(feature = "nip03", feature = "nip04", feature = "nip44"))]
 pub use base64;
 #[doc(hidden)]
 #[cfg(feature = "nip06")]
@@ -47,6 +47,7 @@ pub mod message;
 pub mod nips;
 pub mod prelude;
 pub mod signer;
+pub mod test_vectors;
 pub mod types;
 pub mod util;
 
@@ -72,6 +73,9 @@ pub use self::types::{
 #[doc(hidden)]
 pub use self::util::JsonUtil;
 #[doc(hidden)]
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub use self::util::BinaryUtil;
+#[doc(hidden)]
 #[cfg(feature = "std")]
 pub use self::util::SECP256K1;
 
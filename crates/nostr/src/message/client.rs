@@ -337,9 +337,11 @@ impl ClientMessage {
             if v_len == 5 {
                 let subscription_id: SubscriptionId = serde_json::from_value(v[1].clone())?;
                 let filter: Filter = Filter::from_json(v[2].to_string())?;
+                let id_size: u64 = v[3]
+                    .as_u64()
+                    .ok_or(MessageHandleError::InvalidMessageFormat)?;
                 let id_size: u8 =
-                    v[3].as_u64()
-                        .ok_or(MessageHandleError::InvalidMessageFormat)? as u8;
+                    u8::try_from(id_size).map_err(|_| MessageHandleError::InvalidMessageFormat)?;
                 let initial_message: String = serde_json::from_value(v[4].clone())?;
                 return Ok(Self::NegOpen {
                     subscription_id,
@@ -443,4 +445,19 @@ mod tests {
             r##"["REQ","test",{"kinds":[22]},{"#p":["379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe"]}]"##
         );
     }
+
+    #[test]
+    fn test_client_message_neg_open_invalid_id_size() {
+        let msg = ClientMessage::from_json(r#"["NEG-OPEN","test",{},32,"6100"]"#).unwrap();
+        assert!(matches!(
+            msg,
+            ClientMessage::NegOpen {
+                id_size: Some(32),
+                ..
+            }
+        ));
+
+        // ID size overflow
+        assert!(ClientMessage::from_json(r#"["NEG-OPEN","test",{},288,"6100"]"#).is_err());
+    }
 }
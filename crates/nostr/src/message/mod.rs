@@ -4,8 +4,10 @@
 
 //! Messages
 
-use alloc::string::{String, ToString};
+use alloc::string::String;
+use core::convert::Infallible;
 use core::fmt;
+use core::str::FromStr;
 
 use bitcoin::hashes::sha256::Hash as Sha256Hash;
 use bitcoin::hashes::Hash;
@@ -115,6 +117,28 @@ impl SubscriptionId {
         // Cut the hash and encode to hex
         Self::new(hex::encode(&hash[..16]))
     }
+
+    /// Get as `&str`
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for SubscriptionId {
+    type Err = Infallible;
+
+    #[inline]
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(id))
+    }
+}
+
+impl AsRef<str> for SubscriptionId {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
 }
 
 impl fmt::Display for SubscriptionId {
@@ -128,7 +152,7 @@ impl Serialize for SubscriptionId {
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        serializer.serialize_str(self.as_str())
     }
 }
 
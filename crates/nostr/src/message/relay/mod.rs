@@ -5,8 +5,8 @@
 
 //! Relay messages
 
-use alloc::boxed::Box;
 use alloc::string::String;
+use alloc::sync::Arc;
 use core::fmt;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -97,7 +97,9 @@ pub enum RelayMessage {
         /// Subscription ID
         subscription_id: SubscriptionId,
         /// Event
-        event: Box<Event>,
+        ///
+        /// Shared, to not clone the event when the message is cloned (i.e. sent to many listeners).
+        event: Arc<Event>,
     },
     /// `["OK", <event_id>, <true|false>, <message>]`
     ///
@@ -189,7 +191,7 @@ impl RelayMessage {
     pub fn event(subscription_id: SubscriptionId, event: Event) -> Self {
         Self::Event {
             subscription_id,
-            event: Box::new(event),
+            event: Arc::new(event),
         }
     }
 
@@ -327,7 +329,7 @@ impl TryFrom<RawRelayMessage> for RelayMessage {
                 event,
             } => Ok(Self::Event {
                 subscription_id: SubscriptionId::new(subscription_id),
-                event: Box::new(event.try_into()?),
+                event: Arc::new(event.try_into()?),
             }),
             RawRelayMessage::Ok {
                 event_id,
@@ -7,6 +7,8 @@
 //! See all at <https://github.com/nostr-protocol/nips>
 
 pub mod nip01;
+#[cfg(feature = "nip03")]
+pub mod nip03;
 #[cfg(feature = "nip04")]
 pub mod nip04;
 #[cfg(all(feature = "std", feature = "nip05"))]
@@ -44,8 +46,12 @@ pub mod nip57;
 pub mod nip58;
 #[cfg(feature = "nip59")]
 pub mod nip59;
+pub mod nip62;
 pub mod nip65;
+pub mod nip66;
 pub mod nip73;
+pub mod nip89;
 pub mod nip90;
 pub mod nip94;
 pub mod nip98;
+pub mod nip99;
@@ -17,7 +17,7 @@ use super::nip19::FromBech32;
 use super::nip21::NostrURI;
 use crate::event::id;
 use crate::types::RelayUrl;
-use crate::{key, Filter, Kind, PublicKey, Tag, TagStandard};
+use crate::{key, Event, Filter, Kind, PublicKey, Tag, TagStandard};
 
 /// Raw Event error
 #[derive(Debug, PartialEq, Eq)]
@@ -154,6 +154,43 @@ impl Coordinate {
     pub fn has_identifier(&self) -> bool {
         !self.identifier.is_empty()
     }
+
+    /// Extract the coordinate from an `a` tag
+    ///
+    /// The relay hint, if any, is added to the relays.
+    pub fn from_tag(tag: &Tag) -> Option<Self> {
+        match tag.as_standardized()? {
+            TagStandard::Coordinate {
+                coordinate,
+                relay_url,
+                ..
+            } => {
+                let mut coordinate: Self = coordinate.clone();
+                if let Some(relay_url) = relay_url {
+                    if !coordinate.relays.contains(relay_url) {
+                        coordinate.relays.push(relay_url.clone());
+                    }
+                }
+                Some(coordinate)
+            }
+            _ => None,
+        }
+    }
+
+    /// Check if the event is addressed by this coordinate
+    ///
+    /// Kind, author and `d` tag identifier must match.
+    pub fn matches(&self, event: &Event) -> bool {
+        if event.kind != self.kind || event.pubkey != self.public_key {
+            return false;
+        }
+
+        if self.kind.is_parameterized_replaceable() {
+            return event.tags.identifier().unwrap_or_default() == self.identifier;
+        }
+
+        true
+    }
 }
 
 impl From<Coordinate> for Tag {
@@ -234,6 +271,43 @@ mod tests {
         assert_eq!(coordinate.public_key, expected_public_key);
         assert_eq!(coordinate.identifier, "");
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_coordinate_tag() {
+        use crate::{EventBuilder, Keys, RelayUrl};
+
+        let keys = Keys::generate();
+        let article = EventBuilder::new(Kind::LongFormTextNote, "Lorem ipsum")
+            .tag(Tag::identifier("ipsum"))
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let coordinate =
+            Coordinate::new(Kind::LongFormTextNote, keys.public_key()).identifier("ipsum");
+        assert!(coordinate.matches(&article));
+        assert!(!coordinate.clone().identifier("other").matches(&article));
+        assert_eq!(article.coordinate(), Some(coordinate.clone()));
+
+        // Build and parse tag
+        let relay_url = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let tag = Tag::coordinate_with_relay(coordinate.clone(), relay_url.clone());
+        assert_eq!(
+            tag.as_slice(),
+            &[
+                String::from("a"),
+                coordinate.to_string(),
+                relay_url.to_string()
+            ]
+        );
+
+        let parsed = Coordinate::from_tag(&tag).unwrap();
+        assert_eq!(parsed.kind, coordinate.kind);
+        assert_eq!(parsed.identifier, coordinate.identifier);
+        assert_eq!(parsed.relays, vec![relay_url]);
+
+        assert!(Coordinate::from_tag(&Tag::identifier("ipsum")).is_none());
+    }
 }
 
 #[cfg(bench)]
@@ -0,0 +1,225 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP03: OpenTimestamps Attestations for Events
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/03.md>
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use base64::engine::{general_purpose, Engine};
+
+use crate::{Event, EventId, Kind};
+
+/// OTS file header magic bytes
+const HEADER_MAGIC: &[u8; 31] =
+    b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+/// Supported OTS file version
+const VERSION: u8 = 0x01;
+/// SHA256 operation tag
+const OP_SHA256: u8 = 0x08;
+
+/// NIP03 error
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Error while decoding from base64
+    Base64Decode(base64::DecodeError),
+    /// Invalid OTS file header
+    InvalidHeader,
+    /// Unsupported OTS file version
+    UnsupportedVersion(u8),
+    /// Unsupported file hash operation (only SHA256 is supported)
+    UnsupportedHashOp(u8),
+    /// Unexpected end of the OTS file
+    UnexpectedEnd,
+    /// Wrong event kind
+    WrongKind,
+    /// Referenced event ID missing (no `e` tag)
+    EventIdMissing,
+    /// The OTS proof digest doesn't match the referenced event ID
+    EventIdMismatch,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Base64Decode(e) => write!(f, "Error while decoding from base64: {e}"),
+            Self::InvalidHeader => write!(f, "Invalid OTS file header"),
+            Self::UnsupportedVersion(v) => write!(f, "Unsupported OTS file version: {v}"),
+            Self::UnsupportedHashOp(op) => write!(f, "Unsupported file hash operation: {op:#04x}"),
+            Self::UnexpectedEnd => write!(f, "Unexpected end of the OTS file"),
+            Self::WrongKind => write!(f, "Wrong event kind"),
+            Self::EventIdMissing => write!(f, "Referenced event ID missing"),
+            Self::EventIdMismatch => {
+                write!(f, "OTS proof digest doesn't match the referenced event ID")
+            }
+        }
+    }
+}
+
+impl From<base64::DecodeError> for Error {
+    fn from(e: base64::DecodeError) -> Self {
+        Self::Base64Decode(e)
+    }
+}
+
+/// OpenTimestamps proof
+///
+/// Only the header and the timestamped digest are parsed:
+/// the Bitcoin attestations must be verified with an OpenTimestamps client.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpenTimestampsProof {
+    /// Timestamped SHA256 digest
+    pub digest: [u8; 32],
+    /// Raw OTS file
+    pub raw: Vec<u8>,
+}
+
+impl OpenTimestampsProof {
+    /// Parse base64-encoded OTS file
+    #[inline]
+    pub fn from_base64<T>(content: T) -> Result<Self, Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        let raw: Vec<u8> = general_purpose::STANDARD.decode(content)?;
+        Self::from_bytes(raw)
+    }
+
+    /// Parse OTS file
+    pub fn from_bytes(raw: Vec<u8>) -> Result<Self, Error> {
+        // Check header
+        let rest: &[u8] = raw
+            .strip_prefix(HEADER_MAGIC.as_slice())
+            .ok_or(Error::InvalidHeader)?;
+
+        // Check version and file hash operation
+        let (version, op, rest) = match rest {
+            [version, op, rest @ ..] => (*version, *op, rest),
+            _ => return Err(Error::UnexpectedEnd),
+        };
+
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        if op != OP_SHA256 {
+            return Err(Error::UnsupportedHashOp(op));
+        }
+
+        // Get digest
+        let digest: [u8; 32] = rest
+            .get(..32)
+            .and_then(|d| d.try_into().ok())
+            .ok_or(Error::UnexpectedEnd)?;
+
+        Ok(Self { digest, raw })
+    }
+}
+
+/// OpenTimestamps attestation
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpenTimestampsAttestation {
+    /// Referenced event ID
+    pub event_id: EventId,
+    /// OTS proof
+    pub proof: OpenTimestampsProof,
+}
+
+impl TryFrom<&Event> for OpenTimestampsAttestation {
+    type Error = Error;
+
+    /// Parse the [`Kind::OpenTimestamps`] event and check that the OTS proof matches the referenced event ID
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::OpenTimestamps {
+            return Err(Error::WrongKind);
+        }
+
+        let event_id: EventId = *event.tags.event_ids().next().ok_or(Error::EventIdMissing)?;
+        let proof: OpenTimestampsProof = OpenTimestampsProof::from_base64(&event.content)?;
+
+        if &proof.digest != event_id.as_bytes() {
+            return Err(Error::EventIdMismatch);
+        }
+
+        Ok(Self { event_id, proof })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+
+    fn ots_file(digest: &[u8; 32]) -> Vec<u8> {
+        let mut raw: Vec<u8> = Vec::new();
+        raw.extend_from_slice(HEADER_MAGIC);
+        raw.push(VERSION);
+        raw.push(OP_SHA256);
+        raw.extend_from_slice(digest);
+        raw
+    }
+
+    #[test]
+    fn test_parse_proof() {
+        let event_id =
+            EventId::from_hex("2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45")
+                .unwrap();
+        let content: String = general_purpose::STANDARD.encode(ots_file(event_id.as_bytes()));
+
+        let proof = OpenTimestampsProof::from_base64(content).unwrap();
+        assert_eq!(&proof.digest, event_id.as_bytes());
+
+        // Invalid header
+        assert_eq!(
+            OpenTimestampsProof::from_bytes(vec![0x00, 0x01]).unwrap_err(),
+            Error::InvalidHeader
+        );
+
+        // Truncated digest
+        let mut raw = ots_file(event_id.as_bytes());
+        raw.truncate(raw.len() - 1);
+        assert_eq!(
+            OpenTimestampsProof::from_bytes(raw).unwrap_err(),
+            Error::UnexpectedEnd
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_attestation() {
+        use crate::{EventBuilder, Keys, Tag};
+
+        let keys = Keys::generate();
+        let event_id =
+            EventId::from_hex("2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45")
+                .unwrap();
+        let content: String = general_purpose::STANDARD.encode(ots_file(event_id.as_bytes()));
+
+        let event = EventBuilder::new(Kind::OpenTimestamps, content.clone())
+            .tag(Tag::event(event_id))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let attestation = OpenTimestampsAttestation::try_from(&event).unwrap();
+        assert_eq!(attestation.event_id, event_id);
+
+        // Proof for another event
+        let other_id =
+            EventId::from_hex("70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5")
+                .unwrap();
+        let event = EventBuilder::new(Kind::OpenTimestamps, content)
+            .tag(Tag::event(other_id))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            OpenTimestampsAttestation::try_from(&event).unwrap_err(),
+            Error::EventIdMismatch
+        );
+    }
+}
@@ -132,7 +132,9 @@ where
         .map_err(|_| Error::Base64Decode)?;
     let key: [u8; 32] = util::generate_shared_key(secret_key, public_key);
 
-    let cipher = Aes256CbcDec::new(&key.into(), iv.as_slice().into());
+    // Check IV len, to avoid panic
+    let cipher =
+        Aes256CbcDec::new_from_slices(&key, &iv).map_err(|_| Error::InvalidContentFormat)?;
     let result = cipher
         .decrypt_padded_vec_mut::<Pkcs7>(&encrypted_content)
         .map_err(|_| Error::WrongBlockMode)?;
@@ -219,6 +221,17 @@ mod tests {
             Error::Base64Decode
         );
 
+        // Invalid IV len
+        assert_eq!(
+            decrypt(
+                sender_keys.secret_key(),
+                &receiver_pk,
+                "dJc+WbBgaFCD2/kfg1XCWJParplBDxnZIdJGZ6FCTOg=?iv=AAAA"
+            )
+            .unwrap_err(),
+            Error::InvalidContentFormat
+        );
+
         // Content encrypted with aes256 using GCM mode
         assert_eq!(
             decrypt(
@@ -11,12 +11,10 @@ use alloc::vec::Vec;
 use core::fmt;
 use std::net::SocketAddr;
 
-#[cfg(not(target_arch = "wasm32"))]
-use reqwest::Proxy;
-use reqwest::{Client, Response};
 use serde_json::Value;
 
-use crate::{key, PublicKey, RelayUrl};
+use crate::util::http::{HttpClient, HttpError, HttpRequest, HttpResponse, ReqwestHttpClient};
+use crate::{key, PublicKey, RelayUrl, Url};
 
 /// `NIP05` error
 #[derive(Debug)]
@@ -25,8 +23,8 @@ pub enum Error {
     InvalidFormat,
     /// Impossible to verify
     ImpossibleToVerify,
-    /// Reqwest error
-    Reqwest(reqwest::Error),
+    /// HTTP error
+    Http(HttpError),
     /// Error deserializing JSON data
     Json(serde_json::Error),
     /// Keys error
@@ -41,16 +39,16 @@ impl fmt::Display for Error {
         match self {
             Self::InvalidFormat => write!(f, "invalid format"),
             Self::ImpossibleToVerify => write!(f, "impossible to verify"),
-            Self::Reqwest(e) => write!(f, "{e}"),
+            Self::Http(e) => write!(f, "{e}"),
             Self::Json(e) => write!(f, "impossible to deserialize NIP05 data: {e}"),
             Self::Keys(e) => write!(f, "{e}"),
         }
     }
 }
 
-impl From<reqwest::Error> for Error {
-    fn from(e: reqwest::Error) -> Self {
-        Self::Reqwest(e)
+impl From<HttpError> for Error {
+    fn from(e: HttpError) -> Self {
+        Self::Http(e)
     }
 }
 
@@ -126,24 +124,15 @@ fn verify_from_json(public_key: &PublicKey, json: &Value, name: &str) -> bool {
     false
 }
 
-async fn make_req(nip05: &str, _proxy: Option<SocketAddr>) -> Result<(Value, &str), Error> {
+async fn make_req<'a>(client: &dyn HttpClient, nip05: &'a str) -> Result<(Value, &'a str), Error> {
     let (url, name) = compose_url(nip05)?;
+    let url: Url = Url::parse(&url).map_err(|_| Error::InvalidFormat)?;
 
-    #[cfg(not(target_arch = "wasm32"))]
-    let client: Client = {
-        let mut builder = Client::builder();
-        if let Some(proxy) = _proxy {
-            let proxy = format!("socks5h://{proxy}");
-            builder = builder.proxy(Proxy::all(proxy)?);
-        }
-        builder.build()?
-    };
-
-    #[cfg(target_arch = "wasm32")]
-    let client: Client = Client::new();
-
-    let res: Response = client.get(url).send().await?;
-    let json: Value = res.json().await?;
+    let res: HttpResponse = client
+        .send(HttpRequest::get(url))
+        .await?
+        .error_for_status()?;
+    let json: Value = serde_json::from_slice(&res.body)?;
 
     Ok((json, name))
 }
@@ -156,12 +145,27 @@ async fn make_req(nip05: &str, _proxy: Option<SocketAddr>) -> Result<(Value, &st
 pub async fn verify<S>(
     public_key: &PublicKey,
     nip05: S,
-    _proxy: Option<SocketAddr>,
+    proxy: Option<SocketAddr>,
 ) -> Result<bool, Error>
 where
     S: AsRef<str>,
 {
-    let (json, name) = make_req(nip05.as_ref(), _proxy).await?;
+    let client = ReqwestHttpClient::with_optional_proxy(proxy)?;
+    verify_with_client(&client, public_key, nip05).await
+}
+
+/// Verify NIP05 using a custom [`HttpClient`]
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/05.md>
+pub async fn verify_with_client<S>(
+    client: &dyn HttpClient,
+    public_key: &PublicKey,
+    nip05: S,
+) -> Result<bool, Error>
+where
+    S: AsRef<str>,
+{
+    let (json, name) = make_req(client, nip05.as_ref()).await?;
     Ok(verify_from_json(public_key, &json, name))
 }
 
@@ -170,11 +174,25 @@ where
 /// **Proxy is ignored for WASM targets!**
 ///
 /// <https://github.com/nostr-protocol/nips/blob/master/05.md>
-pub async fn profile<S>(nip05: S, _proxy: Option<SocketAddr>) -> Result<Nip05Profile, Error>
+pub async fn profile<S>(nip05: S, proxy: Option<SocketAddr>) -> Result<Nip05Profile, Error>
+where
+    S: AsRef<str>,
+{
+    let client = ReqwestHttpClient::with_optional_proxy(proxy)?;
+    profile_with_client(&client, nip05).await
+}
+
+/// Get NIP05 profile using a custom [`HttpClient`]
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/05.md>
+pub async fn profile_with_client<S>(
+    client: &dyn HttpClient,
+    nip05: S,
+) -> Result<Nip05Profile, Error>
 where
     S: AsRef<str>,
 {
-    let (json, name) = make_req(nip05.as_ref(), _proxy).await?;
+    let (json, name) = make_req(client, nip05.as_ref()).await?;
 
     let public_key: PublicKey = get_key_from_json(&json, name).ok_or(Error::ImpossibleToVerify)?;
     let relays: Vec<RelayUrl> = get_relays_from_json(&json, &public_key);
@@ -6,9 +6,16 @@
 //!
 //! <https://github.com/nostr-protocol/nips/blob/master/10.md>
 
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
 use core::fmt;
 use core::str::FromStr;
 
+use crate::{Event, EventId, TagStandard};
+
+/// Max depth of the thread tree: deeper replies are attached to the node at this depth
+pub const MAX_THREAD_DEPTH: usize = 64;
+
 /// NIP10 error
 #[derive(Debug, PartialEq, Eq)]
 pub enum Error {
@@ -62,3 +69,321 @@ impl FromStr for Marker {
         }
     }
 }
+
+/// Get the root event ID of a reply
+///
+/// Use the `root` marked `e` tag or, for the deprecated positional `e` tags, the first one.
+pub fn root_id(event: &Event) -> Option<EventId> {
+    let tags: Vec<(EventId, Option<Marker>)> = event_tags(event);
+
+    // Marked tags
+    if let Some((id, ..)) = tags.iter().find(|(_, m)| *m == Some(Marker::Root)) {
+        return Some(*id);
+    }
+
+    // Positional tags
+    if tags.iter().all(|(_, m)| m.is_none()) {
+        return tags.first().map(|(id, ..)| *id);
+    }
+
+    None
+}
+
+/// Get the parent event ID of a reply
+///
+/// Use the `reply` marked `e` tag, falling back to the `root` one for direct replies to the root.
+/// For the deprecated positional `e` tags, the last one is used.
+pub fn parent_id(event: &Event) -> Option<EventId> {
+    let tags: Vec<(EventId, Option<Marker>)> = event_tags(event);
+
+    // Marked tags
+    if let Some((id, ..)) = tags.iter().find(|(_, m)| *m == Some(Marker::Reply)) {
+        return Some(*id);
+    }
+
+    if let Some((id, ..)) = tags.iter().find(|(_, m)| *m == Some(Marker::Root)) {
+        return Some(*id);
+    }
+
+    // Positional tags
+    if tags.iter().all(|(_, m)| m.is_none()) {
+        return tags.last().map(|(id, ..)| *id);
+    }
+
+    None
+}
+
+fn event_tags(event: &Event) -> Vec<(EventId, Option<Marker>)> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_standardized() {
+            Some(TagStandard::Event {
+                event_id,
+                marker,
+                uppercase: false,
+                ..
+            }) if *marker != Some(Marker::Mention) => Some((*event_id, *marker)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Thread node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadNode {
+    /// Event
+    pub event: Event,
+    /// Direct replies, in chronological order
+    pub replies: Vec<ThreadNode>,
+}
+
+impl ThreadNode {
+    fn count(&self) -> usize {
+        1 + self.replies.iter().map(|r| r.count()).sum::<usize>()
+    }
+}
+
+/// Thread
+///
+/// Replies are ordered into a tree using the NIP10 markers.
+/// The tree is at most [`MAX_THREAD_DEPTH`] levels deep: the deeper replies are flattened into the last level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thread {
+    /// Root event ID
+    pub root_id: EventId,
+    /// Root event, if found
+    pub root: Option<Event>,
+    /// Direct replies to the root, in chronological order
+    pub replies: Vec<ThreadNode>,
+    /// Replies whose parent has not been found, in chronological order
+    pub orphans: Vec<ThreadNode>,
+}
+
+impl Thread {
+    /// Assemble the thread from the root and the reply events
+    ///
+    /// Events not related to the thread are ignored.
+    pub fn new<I>(root_id: EventId, events: I) -> Self
+    where
+        I: IntoIterator<Item = Event>,
+    {
+        let mut root: Option<Event> = None;
+        let mut ids: BTreeSet<EventId> = BTreeSet::new();
+        let mut children: BTreeMap<EventId, Vec<Event>> = BTreeMap::new();
+
+        for event in events.into_iter() {
+            if event.id == root_id {
+                root = Some(event);
+                continue;
+            }
+
+            if ids.contains(&event.id) {
+                continue;
+            }
+
+            if let Some(parent) = parent_id(&event) {
+                ids.insert(event.id);
+                children.entry(parent).or_default().push(event);
+            }
+        }
+
+        let replies: Vec<ThreadNode> = build_nodes(&mut children, &root_id);
+
+        // Replies whose parent is missing
+        let mut orphans: Vec<ThreadNode> = Vec::new();
+        let missing: Vec<EventId> = children
+            .keys()
+            .filter(|parent| !ids.contains(parent))
+            .copied()
+            .collect();
+        for parent in missing.into_iter() {
+            orphans.extend(build_nodes(&mut children, &parent));
+        }
+        sort_chronologically(&mut orphans);
+
+        Self {
+            root_id,
+            root,
+            replies,
+            orphans,
+        }
+    }
+
+    /// Number of replies (orphans included)
+    pub fn len(&self) -> usize {
+        self.replies
+            .iter()
+            .chain(self.orphans.iter())
+            .map(|r| r.count())
+            .sum()
+    }
+
+    /// Check if the thread has no replies
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.replies.is_empty() && self.orphans.is_empty()
+    }
+}
+
+/// Build the tree of the replies to `parent`, without recursion
+fn build_nodes(children: &mut BTreeMap<EventId, Vec<Event>>, parent: &EventId) -> Vec<ThreadNode> {
+    // Nodes in DFS order, with the index of the node they are attached to
+    let mut nodes: Vec<(Option<ThreadNode>, Option<usize>)> = Vec::new();
+
+    // ID of the event whose replies must be attached, index of the node to attach them to and their depth
+    let mut stack: Vec<(EventId, Option<usize>, usize)> = Vec::from([(*parent, None, 1)]);
+
+    while let Some((id, attach_to, depth)) = stack.pop() {
+        if let Some(events) = children.remove(&id) {
+            for event in events.into_iter() {
+                let index: usize = nodes.len();
+                let event_id: EventId = event.id;
+                nodes.push((
+                    Some(ThreadNode {
+                        event,
+                        replies: Vec::new(),
+                    }),
+                    attach_to,
+                ));
+
+                if depth < MAX_THREAD_DEPTH {
+                    stack.push((event_id, Some(index), depth + 1));
+                } else {
+                    // Too deep: attach the replies as siblings
+                    stack.push((event_id, attach_to, depth));
+                }
+            }
+        }
+    }
+
+    // The nodes are always after the one they are attached to: build the tree from the last one
+    let mut top: Vec<ThreadNode> = Vec::new();
+    for index in (0..nodes.len()).rev() {
+        let (node, attach_to) = &mut nodes[index];
+        let attach_to: Option<usize> = *attach_to;
+        let mut node: ThreadNode = match node.take() {
+            Some(node) => node,
+            None => continue,
+        };
+        sort_chronologically(&mut node.replies);
+
+        match attach_to.and_then(|i| nodes[i].0.as_mut()) {
+            Some(parent) => parent.replies.push(node),
+            None => top.push(node),
+        }
+    }
+
+    sort_chronologically(&mut top);
+    top
+}
+
+fn sort_chronologically(nodes: &mut [ThreadNode]) {
+    nodes.sort_by(|a, b| {
+        a.event
+            .created_at
+            .cmp(&b.event.created_at)
+            .then(a.event.id.cmp(&b.event.id))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_thread() {
+        use crate::{EventBuilder, Keys, Timestamp};
+
+        let keys = Keys::generate();
+
+        let root = EventBuilder::text_note("Root")
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let reply1 = EventBuilder::text_note_reply("Reply 1", &root, None, None)
+            .custom_created_at(Timestamp::from(2))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let reply2 = EventBuilder::text_note_reply("Reply 2", &root, None, None)
+            .custom_created_at(Timestamp::from(3))
+            .sign_with_keys(&keys)
+            .unwrap();
+        let nested = EventBuilder::text_note_reply("Nested", &reply1, Some(&root), None)
+            .custom_created_at(Timestamp::from(4))
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        assert_eq!(root_id(&nested), Some(root.id));
+        assert_eq!(parent_id(&nested), Some(reply1.id));
+
+        let thread = Thread::new(
+            root.id,
+            [nested.clone(), reply2.clone(), root.clone(), reply1.clone()],
+        );
+        assert_eq!(thread.root, Some(root));
+        assert_eq!(thread.len(), 3);
+        assert!(thread.orphans.is_empty());
+        assert_eq!(thread.replies[0].event, reply1);
+        assert_eq!(thread.replies[0].replies[0].event, nested);
+        assert_eq!(thread.replies[1].event, reply2);
+
+        // Missing parent
+        let thread = Thread::new(root.id, [nested.clone(), reply2.clone()]);
+        assert_eq!(thread.root, None);
+        assert_eq!(thread.replies.len(), 1);
+        assert_eq!(thread.orphans[0].event, nested);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_thread_max_depth() {
+        use crate::{EventBuilder, Keys, Timestamp};
+
+        let keys = Keys::generate();
+
+        let root = EventBuilder::text_note("Root")
+            .custom_created_at(Timestamp::from(1))
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        // Chain of replies, each one to the previous
+        let total: usize = MAX_THREAD_DEPTH + 10;
+        let mut events: Vec<Event> = Vec::with_capacity(total);
+        let mut parent: Event = root.clone();
+        for i in 0..total {
+            let reply = EventBuilder::text_note_reply("Reply", &parent, Some(&root), None)
+                .custom_created_at(Timestamp::from(2 + i as u64))
+                .sign_with_keys(&keys)
+                .unwrap();
+            events.push(reply.clone());
+            parent = reply;
+        }
+
+        let thread = Thread::new(root.id, events.clone());
+        assert_eq!(thread.len(), total);
+
+        // Walk down to the last level
+        let mut depth: usize = 1;
+        let mut node: &ThreadNode = &thread.replies[0];
+        while let Some(reply) = node.replies.first() {
+            node = reply;
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_THREAD_DEPTH);
+
+        // The deeper replies are flattened into the last level, in chronological order
+        let mut last_level: Vec<&ThreadNode> = Vec::new();
+        let mut node: &ThreadNode = &thread.replies[0];
+        for _ in 1..MAX_THREAD_DEPTH - 1 {
+            node = &node.replies[0];
+        }
+        last_level.extend(node.replies.iter());
+        assert_eq!(last_level.len(), total - MAX_THREAD_DEPTH + 1);
+        assert!(last_level
+            .windows(2)
+            .all(|w| w[0].event.created_at < w[1].event.created_at));
+        assert_eq!(last_level.last().unwrap().event, events[total - 1]);
+    }
+}
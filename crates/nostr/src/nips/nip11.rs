@@ -12,10 +12,7 @@ use alloc::vec::Vec;
 use core::fmt;
 use std::net::SocketAddr;
 
-use reqwest::Client;
-#[cfg(not(target_arch = "wasm32"))]
-use reqwest::Proxy;
-
+use crate::util::http::{HttpClient, HttpError, HttpRequest, HttpResponse, ReqwestHttpClient};
 use crate::{Timestamp, Url};
 
 /// `NIP11` error
@@ -27,8 +24,8 @@ pub enum Error {
     InaccessibleInformationDocument,
     /// Provided URL scheme is not valid
     InvalidScheme,
-    /// Reqwest error
-    Reqwest(reqwest::Error),
+    /// HTTP error
+    Http(HttpError),
 }
 
 impl std::error::Error for Error {}
@@ -43,14 +40,14 @@ impl fmt::Display for Error {
                 write!(f, "The relay information document is not accessible")
             }
             Self::InvalidScheme => write!(f, "Provided URL scheme is not valid"),
-            Self::Reqwest(e) => write!(f, "{e}"),
+            Self::Http(e) => write!(f, "{e}"),
         }
     }
 }
 
-impl From<reqwest::Error> for Error {
-    fn from(e: reqwest::Error) -> Self {
-        Self::Reqwest(e)
+impl From<HttpError> for Error {
+    fn from(e: HttpError) -> Self {
+        Self::Http(e)
     }
 }
 
@@ -189,46 +186,61 @@ impl RelayInformationDocument {
     /// Get Relay Information Document
     ///
     /// **Proxy is ignored for WASM targets!**
-    pub async fn get(mut url: Url, _proxy: Option<SocketAddr>) -> Result<Self, Error> {
-        #[cfg(not(target_arch = "wasm32"))]
-        let client: Client = {
-            let mut builder = Client::builder();
-            if let Some(proxy) = _proxy {
-                let proxy = format!("socks5h://{proxy}");
-                builder = builder.proxy(Proxy::all(proxy)?);
-            }
-            builder.build()?
-        };
+    #[inline]
+    pub async fn get(url: Url, proxy: Option<SocketAddr>) -> Result<Self, Error> {
+        let (document, _) = Self::get_with_server_time(url, proxy).await?;
+        Ok(document)
+    }
 
-        #[cfg(target_arch = "wasm32")]
-        let client: Client = Client::new();
+    /// Get Relay Information Document and the relay server time
+    ///
+    /// The server time is taken from the HTTP `Date` header, if present and valid.
+    ///
+    /// **Proxy is ignored for WASM targets!**
+    pub async fn get_with_server_time(
+        url: Url,
+        proxy: Option<SocketAddr>,
+    ) -> Result<(Self, Option<Timestamp>), Error> {
+        let client = ReqwestHttpClient::with_optional_proxy(proxy)?;
+        Self::get_with_client(&client, url).await
+    }
 
-        let url: &str = Self::with_http_scheme(&mut url)?;
-        let req = client.get(url).header("Accept", "application/nostr+json");
-        match req.send().await {
-            Ok(response) => {
-                let json: String = response.text().await?;
-                match serde_json::from_slice(json.as_bytes()) {
-                    Ok(json) => Ok(json),
-                    Err(_) => Err(Error::InvalidInformationDocument),
-                }
-            }
-            Err(_) => Err(Error::InaccessibleInformationDocument),
+    /// Get Relay Information Document and the relay server time using a custom [`HttpClient`]
+    ///
+    /// The server time is taken from the HTTP `Date` header, if present and valid.
+    pub async fn get_with_client(
+        client: &dyn HttpClient,
+        mut url: Url,
+    ) -> Result<(Self, Option<Timestamp>), Error> {
+        Self::with_http_scheme(&mut url)?;
+        let req = HttpRequest::get(url).header("Accept", "application/nostr+json");
+        let response: HttpResponse = client.send(req).await?;
+        let server_time: Option<Timestamp> = response.header("Date").and_then(parse_http_date);
+        match serde_json::from_slice(&response.body) {
+            Ok(json) => Ok((json, server_time)),
+            Err(_) => Err(Error::InvalidInformationDocument),
         }
     }
 
-    /// Returns new URL with scheme substituted to HTTP(S) if WS(S) was provided,
+    /// Substitute the URL scheme to HTTP(S) if WS(S) was provided,
     /// other schemes leaves untouched.
-    fn with_http_scheme(url: &mut Url) -> Result<&str, Error> {
+    fn with_http_scheme(url: &mut Url) -> Result<(), Error> {
         match url.scheme() {
             "wss" => url.set_scheme("https").map_err(|_| Error::InvalidScheme)?,
             "ws" => url.set_scheme("http").map_err(|_| Error::InvalidScheme)?,
             _ => {}
         }
-        Ok(url.as_str())
+        Ok(())
     }
 }
 
+/// Parse HTTP date (i.e. `Sun, 06 Nov 1994 08:49:37 GMT`)
+fn parse_http_date(date: &str) -> Option<Timestamp> {
+    let time = httpdate::parse_http_date(date).ok()?;
+    let secs: u64 = time.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+    Some(Timestamp::from_secs(secs))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -260,4 +272,13 @@ mod tests {
 
         assert_eq!(got, expected, "got: {:?}, expected: {:?}", got, expected);
     }
+
+    #[test]
+    fn test_parse_http_date() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(Timestamp::from_secs(784111777))
+        );
+        assert_eq!(parse_http_date("invalid"), None);
+    }
 }
@@ -465,7 +465,7 @@ impl Conditions {
     }
 
     /// Evaluate whether an event satisfies all these conditions
-    fn evaluate(&self, ep: &EventProperties) -> Result<(), ValidationError> {
+    pub(crate) fn evaluate(&self, ep: &EventProperties) -> Result<(), ValidationError> {
         for c in &self.0 {
             c.evaluate(ep)?;
         }
@@ -977,4 +977,41 @@ mod tests {
             ValidationError::CreatedTooLate
         );
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_event_effective_author() {
+        use crate::{EventBuilder, Kind, Tag, TagStandard};
+
+        let delegator_keys = Keys::generate();
+        let delegatee_keys = Keys::generate();
+        let conditions = Conditions::from_str("kind=1").unwrap();
+        let sig =
+            sign_delegation(&delegator_keys, &delegatee_keys.public_key(), &conditions).unwrap();
+        let tag = Tag::from_standardized(TagStandard::Delegation {
+            delegator: delegator_keys.public_key(),
+            conditions,
+            sig,
+        });
+
+        // Valid delegation
+        let event = EventBuilder::text_note("Delegated")
+            .tag(tag.clone())
+            .sign_with_keys(&delegatee_keys)
+            .unwrap();
+        assert_eq!(event.effective_author(), delegator_keys.public_key());
+
+        // Conditions not satisfied
+        let event = EventBuilder::new(Kind::Reaction, "+")
+            .tag(tag)
+            .sign_with_keys(&delegatee_keys)
+            .unwrap();
+        assert_eq!(event.effective_author(), delegatee_keys.public_key());
+
+        // No delegation
+        let event = EventBuilder::text_note("Not delegated")
+            .sign_with_keys(&delegatee_keys)
+            .unwrap();
+        assert_eq!(event.effective_author(), delegatee_keys.public_key());
+    }
 }
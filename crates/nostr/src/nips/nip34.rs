@@ -12,13 +12,15 @@ use alloc::borrow::Cow;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::fmt;
+use core::str::FromStr;
 
+use bitcoin::hashes::hex::HexToArrayError;
 use bitcoin::hashes::sha1::Hash as Sha1Hash;
 
 use crate::nips::nip01::Coordinate;
 use crate::nips::nip10::Marker;
 use crate::types::url::Url;
-use crate::{EventBuilder, EventId, Kind, PublicKey, Tag, TagKind, TagStandard, Timestamp};
+use crate::{Event, EventBuilder, EventId, Kind, PublicKey, Tag, TagKind, TagStandard, Timestamp};
 
 /// Earlier unique commit ID
 pub const EUC: &str = "euc";
@@ -27,6 +29,58 @@ const GIT_REPO_ANNOUNCEMENT_ALT: &str = "git repository";
 const GIT_ISSUE_ALT: &str = "git issue";
 const GIT_PATCH_ALT: &str = "git patch";
 const GIT_PATCH_COVER_LETTER_ALT: &str = "git patch cover letter";
+const GIT_STATUS_OPEN_ALT: &str = "git status: open";
+const GIT_STATUS_APPLIED_ALT: &str = "git status: applied";
+const GIT_STATUS_CLOSED_ALT: &str = "git status: closed";
+const GIT_STATUS_DRAFT_ALT: &str = "git status: draft";
+
+/// NIP34 Error
+#[derive(Debug)]
+pub enum Error {
+    /// Hex decoding error
+    Hex(HexToArrayError),
+    /// Wrong event kind
+    WrongKind,
+    /// Repository ID missing from event (no `d` tag)
+    IdentifierMissing,
+    /// Repository address missing from event (no `a` tag)
+    RepositoryMissing,
+    /// Earliest unique commit ID missing from event
+    EucMissing,
+    /// Root event missing from event (no `e` tag with `root` marker)
+    RootMissing,
+    /// Commit or parent commit missing from patch
+    CommitMissing,
+    /// Committer missing from patch
+    CommitterMissing,
+    /// Malformed cover letter
+    InvalidCoverLetter,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hex(e) => write!(f, "{e}"),
+            Self::WrongKind => write!(f, "Wrong event kind"),
+            Self::IdentifierMissing => write!(f, "Event missing the repository ID"),
+            Self::RepositoryMissing => write!(f, "Event missing the repository address"),
+            Self::EucMissing => write!(f, "Event missing the earliest unique commit ID"),
+            Self::RootMissing => write!(f, "Event missing the root event"),
+            Self::CommitMissing => write!(f, "Patch missing the commit or the parent commit"),
+            Self::CommitterMissing => write!(f, "Patch missing the committer"),
+            Self::InvalidCoverLetter => write!(f, "Invalid cover letter"),
+        }
+    }
+}
+
+impl From<HexToArrayError> for Error {
+    fn from(e: HexToArrayError) -> Self {
+        Self::Hex(e)
+    }
+}
 
 /// Git Repository Announcement
 ///
@@ -119,6 +173,52 @@ impl GitRepositoryAnnouncement {
     }
 }
 
+impl TryFrom<&Event> for GitRepositoryAnnouncement {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::GitRepoAnnouncement {
+            return Err(Error::WrongKind);
+        }
+
+        let id: &str = event.tags.identifier().ok_or(Error::IdentifierMissing)?;
+
+        let mut announcement = Self {
+            id: id.to_string(),
+            name: None,
+            description: None,
+            web: Vec::new(),
+            clone: Vec::new(),
+            relays: Vec::new(),
+            euc: None,
+            maintainers: Vec::new(),
+        };
+
+        for tag in event.tags.iter() {
+            match tag.as_standardized() {
+                Some(TagStandard::Name(name)) => announcement.name = Some(name.clone()),
+                Some(TagStandard::Description(description)) => {
+                    announcement.description = Some(description.clone())
+                }
+                Some(TagStandard::Web(urls)) => announcement.web.extend(urls.iter().cloned()),
+                Some(TagStandard::GitClone(urls)) => {
+                    announcement.clone.extend(urls.iter().cloned())
+                }
+                Some(TagStandard::Relays(urls)) => announcement.relays.extend(urls.iter().cloned()),
+                Some(TagStandard::GitEarliestUniqueCommitId(euc)) => {
+                    announcement.euc = Some(euc.clone())
+                }
+                Some(TagStandard::GitMaintainers(public_keys)) => {
+                    announcement.maintainers.extend(public_keys.iter().copied())
+                }
+                _ => {}
+            }
+        }
+
+        Ok(announcement)
+    }
+}
+
 /// Git Issue
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct GitIssue {
@@ -162,6 +262,45 @@ impl GitIssue {
     }
 }
 
+impl TryFrom<&Event> for GitIssue {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::GitIssue {
+            return Err(Error::WrongKind);
+        }
+
+        let mut repository: Option<Coordinate> = None;
+        let mut public_keys: Vec<PublicKey> = Vec::new();
+        let mut subject: Option<String> = None;
+        let mut labels: Vec<String> = Vec::new();
+
+        for tag in event.tags.iter() {
+            match tag.as_standardized() {
+                Some(TagStandard::Coordinate { coordinate, .. }) if repository.is_none() => {
+                    repository = Some(coordinate.clone())
+                }
+                Some(TagStandard::PublicKey {
+                    public_key,
+                    uppercase: false,
+                    ..
+                }) => public_keys.push(*public_key),
+                Some(TagStandard::Subject(s)) => subject = Some(s.clone()),
+                Some(TagStandard::Hashtag(label)) => labels.push(label.clone()),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            content: event.content.clone(),
+            repository: repository.ok_or(Error::RepositoryMissing)?,
+            public_keys,
+            subject,
+            labels,
+        })
+    }
+}
+
 /// Git Patch Committer
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct GitPatchCommitter {
@@ -220,6 +359,34 @@ impl fmt::Display for GitPatchContent {
     }
 }
 
+impl GitPatchContent {
+    fn parse_cover_letter(content: &str) -> Result<Self, Error> {
+        let (header, description) = content
+            .split_once("\n\n")
+            .ok_or(Error::InvalidCoverLetter)?;
+        let (from, subject) = header.split_once('\n').ok_or(Error::InvalidCoverLetter)?;
+
+        // Parse `From <last commit> Mon Sep 17 00:00:00 2001`
+        let last_commit: &str = from
+            .strip_prefix("From ")
+            .and_then(|from| from.split(' ').next())
+            .ok_or(Error::InvalidCoverLetter)?;
+
+        // Parse `Subject: [PATCH 0/<commits len>] <title>`
+        let (commits_len, title) = subject
+            .strip_prefix("Subject: [PATCH 0/")
+            .and_then(|subject| subject.split_once("] "))
+            .ok_or(Error::InvalidCoverLetter)?;
+
+        Ok(Self::CoverLetter {
+            title: title.to_string(),
+            description: description.to_string(),
+            last_commit: Sha1Hash::from_str(last_commit)?,
+            commits_len: commits_len.parse().map_err(|_| Error::InvalidCoverLetter)?,
+        })
+    }
+}
+
 /// Git Patch
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct GitPatch {
@@ -318,3 +485,515 @@ impl GitPatch {
         EventBuilder::new(Kind::GitPatch, content).tags(tags)
     }
 }
+
+impl TryFrom<&Event> for GitPatch {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::GitPatch {
+            return Err(Error::WrongKind);
+        }
+
+        let mut repo_id: Option<String> = None;
+        let mut maintainers: Vec<PublicKey> = Vec::new();
+        let mut euc: Option<String> = None;
+        let mut root_proposal_id: Option<EventId> = None;
+        let mut cover_letter: bool = false;
+        let mut commit: Option<Sha1Hash> = None;
+        let mut parent_commit: Option<Sha1Hash> = None;
+        let mut commit_pgp_sig: Option<String> = None;
+        let mut committer: Option<GitPatchCommitter> = None;
+
+        for tag in event.tags.iter() {
+            match tag.as_standardized() {
+                Some(TagStandard::Coordinate { coordinate, .. })
+                    if coordinate.kind == Kind::GitRepoAnnouncement && repo_id.is_none() =>
+                {
+                    repo_id = Some(coordinate.identifier.clone())
+                }
+                Some(TagStandard::PublicKey {
+                    public_key,
+                    uppercase: false,
+                    ..
+                }) => maintainers.push(*public_key),
+                // The first reference is the EUC, the next one (if any) the commit
+                Some(TagStandard::Reference(reference)) if euc.is_none() => {
+                    euc = Some(reference.clone())
+                }
+                Some(TagStandard::Hashtag(hashtag)) if hashtag == "cover-letter" => {
+                    cover_letter = true
+                }
+                Some(TagStandard::Event {
+                    event_id,
+                    marker: Some(Marker::Reply),
+                    ..
+                }) => root_proposal_id = Some(*event_id),
+                Some(TagStandard::GitCommit(hash)) => commit = Some(*hash),
+                Some(..) => {}
+                None => match tag.as_slice() {
+                    [kind, value] if kind == "parent-commit" => {
+                        parent_commit = Some(Sha1Hash::from_str(value)?)
+                    }
+                    [kind, value] if kind == "commit-pgp-sig" && !value.is_empty() => {
+                        commit_pgp_sig = Some(value.clone())
+                    }
+                    [kind, name, email, timestamp, offset_minutes] if kind == "committer" => {
+                        if let (Ok(timestamp), Ok(offset_minutes)) =
+                            (Timestamp::from_str(timestamp), offset_minutes.parse())
+                        {
+                            committer = Some(GitPatchCommitter {
+                                name: (!name.is_empty()).then(|| name.clone()),
+                                email: (!email.is_empty()).then(|| email.clone()),
+                                timestamp,
+                                offset_minutes,
+                            });
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+
+        let content: GitPatchContent = if cover_letter {
+            GitPatchContent::parse_cover_letter(&event.content)?
+        } else {
+            GitPatchContent::Patch {
+                content: event.content.clone(),
+                commit: commit.ok_or(Error::CommitMissing)?,
+                parent_commit: parent_commit.ok_or(Error::CommitMissing)?,
+                commit_pgp_sig,
+                committer: committer.ok_or(Error::CommitterMissing)?,
+            }
+        };
+
+        Ok(Self {
+            repo_id: repo_id.ok_or(Error::RepositoryMissing)?,
+            content,
+            maintainers,
+            euc: euc.ok_or(Error::EucMissing)?,
+            root_proposal_id,
+        })
+    }
+}
+
+/// Git Status Kind
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GitStatusKind {
+    /// Open
+    Open,
+    /// Applied / Merged for patches, resolved for issues
+    Applied {
+        /// Applied or merged patches
+        patches: Vec<EventId>,
+        /// Merge commit
+        merge_commit: Option<Sha1Hash>,
+        /// Commits the patches were applied as
+        applied_as_commits: Vec<Sha1Hash>,
+    },
+    /// Closed
+    Closed,
+    /// Draft
+    Draft,
+}
+
+impl GitStatusKind {
+    /// Get [`Kind`]
+    pub fn kind(&self) -> Kind {
+        match self {
+            Self::Open => Kind::GitStatusOpen,
+            Self::Applied { .. } => Kind::GitStatusApplied,
+            Self::Closed => Kind::GitStatusClosed,
+            Self::Draft => Kind::GitStatusDraft,
+        }
+    }
+
+    fn alt(&self) -> &'static str {
+        match self {
+            Self::Open => GIT_STATUS_OPEN_ALT,
+            Self::Applied { .. } => GIT_STATUS_APPLIED_ALT,
+            Self::Closed => GIT_STATUS_CLOSED_ALT,
+            Self::Draft => GIT_STATUS_DRAFT_ALT,
+        }
+    }
+}
+
+/// Git Status
+///
+/// Status of a git patch or issue
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GitStatus {
+    /// Status
+    pub status: GitStatusKind,
+    /// Status content (markdown)
+    pub content: String,
+    /// Issue or original root patch ID
+    pub root: EventId,
+    /// Accepted revision root ID
+    pub accepted_revision_root: Option<EventId>,
+    /// Repository owner and other users
+    pub public_keys: Vec<PublicKey>,
+    /// The repository address
+    pub repository: Option<Coordinate>,
+    /// Earliest unique commit ID of repo
+    pub euc: Option<String>,
+}
+
+impl GitStatus {
+    pub(crate) fn to_event_builder(self) -> EventBuilder {
+        let kind: Kind = self.status.kind();
+
+        let mut tags: Vec<Tag> = Vec::with_capacity(2);
+
+        // Add root
+        tags.push(Tag::from_standardized_without_cell(TagStandard::Event {
+            event_id: self.root,
+            relay_url: None,
+            marker: Some(Marker::Root),
+            public_key: None,
+            uppercase: false,
+        }));
+
+        // Add accepted revision root
+        if let Some(accepted_revision_root) = self.accepted_revision_root {
+            tags.push(Tag::from_standardized_without_cell(TagStandard::Event {
+                event_id: accepted_revision_root,
+                relay_url: None,
+                marker: Some(Marker::Reply),
+                public_key: None,
+                uppercase: false,
+            }));
+        }
+
+        // Add public keys
+        tags.extend(self.public_keys.into_iter().map(Tag::public_key));
+
+        // Add coordinate
+        if let Some(repository) = self.repository {
+            tags.push(Tag::coordinate(repository));
+        }
+
+        // Add EUC (as reference, not with `euc` marker)
+        if let Some(euc) = self.euc {
+            tags.push(Tag::reference(euc));
+        }
+
+        // Add alt tag
+        tags.push(Tag::alt(self.status.alt()));
+
+        // Handle applied status
+        if let GitStatusKind::Applied {
+            patches,
+            merge_commit,
+            applied_as_commits,
+        } = self.status
+        {
+            // Add applied patches
+            tags.extend(patches.into_iter().map(|event_id| {
+                Tag::from_standardized_without_cell(TagStandard::Quote {
+                    event_id,
+                    relay_url: None,
+                    public_key: None,
+                })
+            }));
+
+            // Add merge commit
+            if let Some(merge_commit) = merge_commit {
+                tags.reserve_exact(2);
+                tags.push(Tag::reference(merge_commit.to_string()));
+                tags.push(Tag::custom(
+                    TagKind::Custom(Cow::Borrowed("merge-commit")),
+                    vec![merge_commit.to_string()],
+                ));
+            }
+
+            // Add applied as commits
+            if !applied_as_commits.is_empty() {
+                tags.reserve_exact(applied_as_commits.len() + 1);
+                tags.extend(
+                    applied_as_commits
+                        .iter()
+                        .map(|commit| Tag::reference(commit.to_string())),
+                );
+                tags.push(Tag::custom(
+                    TagKind::Custom(Cow::Borrowed("applied-as-commits")),
+                    applied_as_commits.into_iter().map(|c| c.to_string()),
+                ));
+            }
+        }
+
+        // Build
+        EventBuilder::new(kind, self.content).tags(tags)
+    }
+}
+
+impl TryFrom<&Event> for GitStatus {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        let mut status: GitStatusKind = match event.kind {
+            Kind::GitStatusOpen => GitStatusKind::Open,
+            Kind::GitStatusApplied => GitStatusKind::Applied {
+                patches: Vec::new(),
+                merge_commit: None,
+                applied_as_commits: Vec::new(),
+            },
+            Kind::GitStatusClosed => GitStatusKind::Closed,
+            Kind::GitStatusDraft => GitStatusKind::Draft,
+            _ => return Err(Error::WrongKind),
+        };
+
+        let mut root: Option<EventId> = None;
+        let mut accepted_revision_root: Option<EventId> = None;
+        let mut public_keys: Vec<PublicKey> = Vec::new();
+        let mut repository: Option<Coordinate> = None;
+        let mut references: Vec<&str> = Vec::new();
+
+        for tag in event.tags.iter() {
+            match tag.as_standardized() {
+                Some(TagStandard::Event {
+                    event_id,
+                    marker: Some(Marker::Root),
+                    ..
+                }) => root = Some(*event_id),
+                Some(TagStandard::Event {
+                    event_id,
+                    marker: Some(Marker::Reply),
+                    ..
+                }) => accepted_revision_root = Some(*event_id),
+                Some(TagStandard::PublicKey {
+                    public_key,
+                    uppercase: false,
+                    ..
+                }) => public_keys.push(*public_key),
+                Some(TagStandard::Coordinate { coordinate, .. }) if repository.is_none() => {
+                    repository = Some(coordinate.clone())
+                }
+                Some(TagStandard::Reference(reference)) => references.push(reference),
+                Some(TagStandard::Quote { event_id, .. }) => {
+                    if let GitStatusKind::Applied { patches, .. } = &mut status {
+                        patches.push(*event_id);
+                    }
+                }
+                Some(..) => {}
+                None => match (&mut status, tag.as_slice()) {
+                    (GitStatusKind::Applied { merge_commit, .. }, [kind, value])
+                        if kind == "merge-commit" =>
+                    {
+                        *merge_commit = Some(Sha1Hash::from_str(value)?);
+                    }
+                    (
+                        GitStatusKind::Applied {
+                            applied_as_commits, ..
+                        },
+                        [kind, commits @ ..],
+                    ) if kind == "applied-as-commits" => {
+                        for commit in commits.iter() {
+                            applied_as_commits.push(Sha1Hash::from_str(commit)?);
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+
+        // The applied commits are referenced too: the EUC is the remaining reference
+        let commits: Vec<String> = match &status {
+            GitStatusKind::Applied {
+                merge_commit,
+                applied_as_commits,
+                ..
+            } => merge_commit
+                .iter()
+                .chain(applied_as_commits.iter())
+                .map(|commit| commit.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+        let euc: Option<String> = references
+            .into_iter()
+            .find(|reference| !commits.iter().any(|commit| commit == reference))
+            .map(|reference| reference.to_string());
+
+        Ok(Self {
+            status,
+            content: event.content.clone(),
+            root: root.ok_or(Error::RootMissing)?,
+            accepted_revision_root,
+            public_keys,
+            repository,
+            euc,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EUC_HEX: &str = "59429cfb7e6c7e3e2d6c5dc7d0dbb3b3e0b0f8b0";
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_git_repository_announcement_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+
+        let announcement = GitRepositoryAnnouncement {
+            id: String::from("rust-nostr"),
+            name: Some(String::from("Rust Nostr")),
+            description: Some(String::from("Nostr protocol implementation")),
+            web: vec![Url::parse("https://rust-nostr.org").unwrap()],
+            clone: vec![Url::parse("https://github.com/rust-nostr/nostr.git").unwrap()],
+            relays: vec![Url::parse("wss://relay.damus.io").unwrap()],
+            euc: Some(String::from(EUC_HEX)),
+            maintainers: vec![Keys::generate().public_key()],
+        };
+
+        let event = EventBuilder::git_repository_announcement(announcement.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            GitRepositoryAnnouncement::try_from(&event).unwrap(),
+            announcement
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_git_issue_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+        let owner = Keys::generate().public_key();
+
+        let issue = GitIssue {
+            content: String::from("The build fails on wasm"),
+            repository: Coordinate::new(Kind::GitRepoAnnouncement, owner).identifier("rust-nostr"),
+            public_keys: vec![owner],
+            subject: Some(String::from("Build failure")),
+            labels: vec![String::from("bug"), String::from("wasm")],
+        };
+
+        let event = EventBuilder::git_issue(issue.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(GitIssue::try_from(&event).unwrap(), issue);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_git_patch_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+        let maintainer = Keys::generate().public_key();
+
+        let patch = GitPatch {
+            repo_id: String::from("rust-nostr"),
+            content: GitPatchContent::Patch {
+                content: String::from("diff --git a/README.md b/README.md"),
+                commit: Sha1Hash::from_str("b1fc4e1a1c9e7a3d8c5e2f7a6b4d3c2e1f0a9b8c").unwrap(),
+                parent_commit: Sha1Hash::from_str(EUC_HEX).unwrap(),
+                commit_pgp_sig: None,
+                committer: GitPatchCommitter {
+                    name: Some(String::from("Alice")),
+                    email: Some(String::from("alice@example.com")),
+                    timestamp: Timestamp::from_secs(1_700_000_000),
+                    offset_minutes: 60,
+                },
+            },
+            maintainers: vec![maintainer],
+            euc: String::from(EUC_HEX),
+            root_proposal_id: Some(EventId::all_zeros()),
+        };
+
+        let event = EventBuilder::git_patch(patch.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(GitPatch::try_from(&event).unwrap(), patch);
+
+        // Cover letter
+        let cover_letter = GitPatch {
+            content: GitPatchContent::CoverLetter {
+                title: String::from("Add NIP34 parsers"),
+                description: String::from("Parse the git events.\n\nWith tests."),
+                last_commit: Sha1Hash::from_str(EUC_HEX).unwrap(),
+                commits_len: 3,
+            },
+            root_proposal_id: None,
+            ..patch
+        };
+
+        let event = EventBuilder::git_patch(cover_letter.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(GitPatch::try_from(&event).unwrap(), cover_letter);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_git_status_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+        let owner = Keys::generate().public_key();
+
+        let status = GitStatus {
+            status: GitStatusKind::Applied {
+                patches: vec![EventId::all_zeros()],
+                merge_commit: Some(
+                    Sha1Hash::from_str("b1fc4e1a1c9e7a3d8c5e2f7a6b4d3c2e1f0a9b8c").unwrap(),
+                ),
+                applied_as_commits: vec![Sha1Hash::from_str(
+                    "c2ad5f2b2d0f8b4e9d6f3a8b7c5e4d3f2a1b0c9d",
+                )
+                .unwrap()],
+            },
+            content: String::from("Merged, thanks!"),
+            root: EventId::all_zeros(),
+            accepted_revision_root: None,
+            public_keys: vec![owner],
+            repository: Some(
+                Coordinate::new(Kind::GitRepoAnnouncement, owner).identifier("rust-nostr"),
+            ),
+            euc: Some(String::from(EUC_HEX)),
+        };
+
+        let event = EventBuilder::git_status(status.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(GitStatus::try_from(&event).unwrap(), status);
+
+        let closed = GitStatus {
+            status: GitStatusKind::Closed,
+            euc: None,
+            ..status
+        };
+
+        let event = EventBuilder::git_status(closed.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::GitStatusClosed);
+        assert_eq!(GitStatus::try_from(&event).unwrap(), closed);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_parse_git_event_wrong_kind() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(matches!(
+            GitStatus::try_from(&event).unwrap_err(),
+            Error::WrongKind
+        ));
+        assert!(matches!(
+            GitPatch::try_from(&event).unwrap_err(),
+            Error::WrongKind
+        ));
+    }
+}
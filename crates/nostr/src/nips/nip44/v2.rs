@@ -355,10 +355,9 @@ mod tests {
 
     use super::*;
     use crate::nips::nip44;
+    use crate::test_vectors::nip44::JSON as JSON_VECTORS;
     use crate::Keys;
 
-    const JSON_VECTORS: &str = include_str!("nip44.vectors.json");
-
     fn val(c: u8, idx: usize) -> u8 {
         match c {
             b'A'..=b'F' => c - b'A' + 10,
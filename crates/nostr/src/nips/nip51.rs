@@ -50,6 +50,35 @@ impl From<MuteList> for Vec<Tag> {
     }
 }
 
+impl<'a> FromIterator<&'a Tag> for MuteList {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Tag>,
+    {
+        let mut list = Self::default();
+
+        for tag in iter.into_iter() {
+            match tag.as_standardized() {
+                Some(TagStandard::PublicKey {
+                    public_key,
+                    uppercase: false,
+                    ..
+                }) => list.public_keys.push(*public_key),
+                Some(TagStandard::Hashtag(hashtag)) => list.hashtags.push(hashtag.clone()),
+                Some(TagStandard::Event {
+                    event_id,
+                    uppercase: false,
+                    ..
+                }) => list.event_ids.push(*event_id),
+                Some(TagStandard::Word(word)) => list.words.push(word.clone()),
+                _ => {}
+            }
+        }
+
+        list
+    }
+}
+
 /// Uncategorized, "global" list of things a user wants to save
 #[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Bookmarks {
@@ -159,3 +188,27 @@ impl From<ArticlesCuration> for Vec<Tag> {
         tags
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mute_list_from_tags() {
+        let list = MuteList {
+            public_keys: vec![PublicKey::from_hex(
+                "68d81165918100b7da43fc28f7d1fc12554466e1115886b9e7bb326f65ec4272",
+            )
+            .unwrap()],
+            hashtags: vec![String::from("nsfw")],
+            event_ids: vec![EventId::from_hex(
+                "70b10f70c1318967eddf12527799411b1a9780ad9c43858f5e5fcd45486a13a5",
+            )
+            .unwrap()],
+            words: vec![String::from("spam")],
+        };
+
+        let tags: Vec<Tag> = list.clone().into();
+        assert_eq!(MuteList::from_iter(tags.iter()), list);
+    }
+}
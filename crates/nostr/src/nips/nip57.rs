@@ -28,11 +28,12 @@ use crate::key::Error as KeyError;
 #[cfg(feature = "std")]
 use crate::types::time::Instant;
 use crate::types::time::TimeSupplier;
+use crate::util::bolt11::{self, Bolt11Invoice};
 #[cfg(feature = "std")]
 use crate::SECP256K1;
 use crate::{
     event, util, Event, EventBuilder, EventId, JsonUtil, Keys, Kind, PublicKey, SecretKey, Tag,
-    TagStandard, Timestamp, Url,
+    TagKind, TagStandard, Timestamp, Url,
 };
 
 type Aes256CbcEnc = Encryptor<Aes256>;
@@ -57,6 +58,23 @@ pub enum Error {
     WrongBech32PrefixOrVariant,
     /// Wrong encryption block mode
     WrongBlockMode,
+    /// BOLT11 error
+    Bolt11(bolt11::Error),
+    /// Wrong event kind
+    WrongKind {
+        /// Expected kind
+        expected: Kind,
+        /// Found kind
+        found: Kind,
+    },
+    /// Missing `bolt11` tag
+    MissingBolt11,
+    /// Missing `description` tag
+    MissingDescription,
+    /// The description hash of the invoice doesn't match the zap request
+    DescriptionHashMismatch,
+    /// The amount of the invoice doesn't match the zap request
+    AmountMismatch,
 }
 
 #[cfg(feature = "std")]
@@ -79,6 +97,14 @@ impl fmt::Display for Error {
                 f,
                 "Wrong encryption block mode. The content must be encrypted using CBC mode!"
             ),
+            Self::Bolt11(e) => write!(f, "{e}"),
+            Self::WrongKind { expected, found } => {
+                write!(f, "Wrong kind: expected={expected}, found={found}")
+            }
+            Self::MissingBolt11 => write!(f, "Missing bolt11 tag"),
+            Self::MissingDescription => write!(f, "Missing description tag"),
+            Self::DescriptionHashMismatch => write!(f, "Description hash mismatch"),
+            Self::AmountMismatch => write!(f, "Amount mismatch"),
         }
     }
 }
@@ -125,6 +151,12 @@ impl From<secp256k1::Error> for Error {
     }
 }
 
+impl From<bolt11::Error> for Error {
+    fn from(e: bolt11::Error) -> Self {
+        Self::Bolt11(e)
+    }
+}
+
 /// Zap Type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ZapType {
@@ -264,6 +296,80 @@ impl From<ZapRequestData> for Vec<Tag> {
     }
 }
 
+/// Zap receipt data
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ZapReceiptData {
+    /// Invoice
+    pub invoice: Bolt11Invoice,
+    /// Zap request
+    pub zap_request: Event,
+}
+
+impl ZapReceiptData {
+    /// Amount (millisats)
+    #[inline]
+    pub fn amount_msats(&self) -> Option<u64> {
+        self.invoice.amount_msats
+    }
+}
+
+/// Validate zap receipt against its zap request
+///
+/// Check that the description hash of the invoice is the SHA256 of the zap request
+/// and that the amount of the invoice matches the `amount` of the zap request, if any.
+///
+/// **The signatures of the invoice and of the zap request are NOT verified!**
+pub fn validate_zap_receipt(receipt: &Event) -> Result<ZapReceiptData, Error> {
+    if receipt.kind != Kind::ZapReceipt {
+        return Err(Error::WrongKind {
+            expected: Kind::ZapReceipt,
+            found: receipt.kind,
+        });
+    }
+
+    let bolt11: &str = receipt
+        .tags
+        .find(TagKind::Bolt11)
+        .and_then(|t| t.content())
+        .ok_or(Error::MissingBolt11)?;
+    let description: &str = receipt
+        .tags
+        .find(TagKind::Description)
+        .and_then(|t| t.content())
+        .ok_or(Error::MissingDescription)?;
+
+    let invoice: Bolt11Invoice = Bolt11Invoice::parse(bolt11)?;
+
+    if invoice.description_hash != Some(Sha256Hash::hash(description.as_bytes())) {
+        return Err(Error::DescriptionHashMismatch);
+    }
+
+    let zap_request: Event = Event::from_json(description)?;
+
+    if zap_request.kind != Kind::ZapRequest {
+        return Err(Error::WrongKind {
+            expected: Kind::ZapRequest,
+            found: zap_request.kind,
+        });
+    }
+
+    if let Some(amount) = zap_request
+        .tags
+        .find(TagKind::Amount)
+        .and_then(|t| t.content())
+    {
+        let amount: u64 = amount.parse().map_err(|_| Error::AmountMismatch)?;
+        if invoice.amount_msats != Some(amount) {
+            return Err(Error::AmountMismatch);
+        }
+    }
+
+    Ok(ZapReceiptData {
+        invoice,
+        zap_request,
+    })
+}
+
 /// Create **anonymous** zap request
 #[cfg(feature = "std")]
 pub fn anonymous_zap_request(data: ZapRequestData) -> Result<Event, Error> {
@@ -421,8 +527,9 @@ fn decrypt_private_zap_message(key: [u8; 32], private_zap_event: &Event) -> Resu
         return Err(Error::WrongBech32PrefixOrVariant);
     }
 
-    // Decrypt
-    let cipher = Aes256CbcDec::new(&key.into(), iv.as_slice().into());
+    // Decrypt (check IV len, to avoid panic)
+    let cipher =
+        Aes256CbcDec::new_from_slices(&key, &iv).map_err(|_| Error::InvalidPrivateZapMessage)?;
     let result: Vec<u8> = cipher
         .decrypt_padded_vec_mut::<Pkcs7>(&msg)
         .map_err(|_| Error::WrongBlockMode)?;
@@ -436,6 +543,51 @@ fn decrypt_private_zap_message(key: [u8; 32], private_zap_event: &Event) -> Resu
 mod tests {
     use super::*;
 
+    // Invoice and zap request of the zap receipt of the NIP57 example
+    const ZAP_INVOICE: &str = "lnbc10u1p3unwfusp5t9r3yymhpfqculx78u027lxspgxcr2n2987mx2j55nnfs95nxnzqpp5jmrh92pfld78spqs78v9euf2385t83uvpwk9ldrlvf6ch7tpascqhp5zvkrmemgth3tufcvflmzjzfvjt023nazlhljz2n9hattj4f8jq8qxqyjw5qcqpjrzjqtc4fc44feggv7065fqe5m4ytjarg3repr5j9el35xhmtfexc42yczarjuqqfzqqqqqqqqlgqqqqqqgq9q9qxpqysgq079nkq507a5tw7xgttmj4u990j7wfggtrasah5gd4ywfr2pjcn29383tphp4t48gquelz9z78p4cq7ml3nrrphw5w6eckhjwmhezhnqpy6gyf0";
+    const ZAP_REQUEST: &str = r#"{"pubkey":"32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245","content":"","id":"d9cc14d50fcb8c27539aacf776882942c1a11ea4472f8cdec1dea82fab66279d","created_at":1674164539,"sig":"77127f636577e9029276be060332ea565deaf89ff215a494ccff16ae3f757065e2bc59b2e8c113dd407917a010b3abd36c8d7ad84c0e3ab7dab3a0b0caa9835d","kind":9734,"tags":[["e","3624762a1274dd9636e0c552b53086d70bc88c165bc4dc0f9e836a1eaf86c3b8"],["p","32e1827635450ebb3c5a7d12c1f8e7b2b514439ac10a67eef3d9fd9c5c68e245"],["relays","wss://relay.damus.io","wss://nostr-relay.wlvs.space","wss://nostr.fmt.wiz.biz","wss://relay.nostr.bg","wss://nostr.oxtr.dev","wss://nostr.v0l.io","wss://brb.io","wss://nostr.bitcoiner.social","ws://monad.jb55.com:8080","wss://relay.snort.social"]]}"#;
+
+    fn zap_receipt(bolt11: &str, description: &str) -> Event {
+        // The signature of the receipt isn't checked by the validator
+        let keys = Keys::generate();
+        EventBuilder::new(Kind::ZapReceipt, "")
+            .tags([
+                Tag::parse(["bolt11", bolt11]).unwrap(),
+                Tag::parse(["description", description]).unwrap(),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_validate_zap_receipt() {
+        let data = validate_zap_receipt(&zap_receipt(ZAP_INVOICE, ZAP_REQUEST)).unwrap();
+        assert_eq!(data.amount_msats(), Some(1_000_000));
+        assert_eq!(data.zap_request.kind, Kind::ZapRequest);
+        assert_eq!(
+            data.zap_request.id.to_hex(),
+            "d9cc14d50fcb8c27539aacf776882942c1a11ea4472f8cdec1dea82fab66279d"
+        );
+
+        // Tampered zap request
+        let tampered =
+            ZAP_REQUEST.replace(r#"["relays","#, r#"["amount","21000000000"],["relays","#);
+        assert!(matches!(
+            validate_zap_receipt(&zap_receipt(ZAP_INVOICE, &tampered)).unwrap_err(),
+            Error::DescriptionHashMismatch
+        ));
+
+        // Not a zap receipt
+        let keys = Keys::generate();
+        let note = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(matches!(
+            validate_zap_receipt(&note).unwrap_err(),
+            Error::WrongKind { .. }
+        ));
+    }
+
     #[test]
     fn test_encrypt_decrypt_private_zap_message() {
         let alice_keys = Keys::generate();
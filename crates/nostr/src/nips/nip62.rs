@@ -0,0 +1,96 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP62: Request to Vanish
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/62.md>
+
+use alloc::vec::Vec;
+
+use crate::{Event, Kind, RelayUrl, TagKind};
+
+/// Value of the `relay` tag to request the vanish from all the relays
+pub const ALL_RELAYS: &str = "ALL_RELAYS";
+
+/// Relays targeted by a request to vanish
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum VanishTarget {
+    /// All the relays
+    AllRelays,
+    /// Specific relays
+    Relays(Vec<RelayUrl>),
+}
+
+impl VanishTarget {
+    /// Extract the target from a request to vanish
+    ///
+    /// Return `None` if the event isn't a request to vanish.
+    pub fn from_event(event: &Event) -> Option<Self> {
+        if event.kind != Kind::RequestToVanish {
+            return None;
+        }
+
+        let mut relays: Vec<RelayUrl> = Vec::new();
+        for tag in event.tags.iter() {
+            if tag.kind() != TagKind::Relay {
+                continue;
+            }
+
+            match tag.content() {
+                Some(ALL_RELAYS) => return Some(Self::AllRelays),
+                Some(url) => {
+                    if let Ok(url) = RelayUrl::parse(url) {
+                        relays.push(url);
+                    }
+                }
+                None => {}
+            }
+        }
+
+        Some(Self::Relays(relays))
+    }
+
+    /// Check if the request targets the relay
+    pub fn contains(&self, url: &RelayUrl) -> bool {
+        match self {
+            Self::AllRelays => true,
+            Self::Relays(relays) => relays.contains(url),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{EventBuilder, Keys};
+
+    #[test]
+    fn test_vanish_target() {
+        let keys = Keys::generate();
+        let relay = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let other = RelayUrl::parse("wss://nos.lol").unwrap();
+
+        let event = EventBuilder::request_vanish(VanishTarget::Relays(vec![relay.clone()]), "")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let target = VanishTarget::from_event(&event).unwrap();
+        assert_eq!(target, VanishTarget::Relays(vec![relay.clone()]));
+        assert!(target.contains(&relay));
+        assert!(!target.contains(&other));
+
+        let event = EventBuilder::request_vanish(VanishTarget::AllRelays, "Goodbye")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::RequestToVanish);
+        assert_eq!(event.content, "Goodbye");
+        let target = VanishTarget::from_event(&event).unwrap();
+        assert_eq!(target, VanishTarget::AllRelays);
+        assert!(target.contains(&other));
+
+        let event = EventBuilder::text_note("Hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(VanishTarget::from_event(&event).is_none());
+    }
+}
@@ -0,0 +1,412 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP66: Relay Discovery and Liveness Monitoring
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/66.md>
+
+#![allow(clippy::wrong_self_convention)]
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+
+use crate::types::url;
+use crate::{Event, EventBuilder, Filter, Kind, RelayUrl, Tag, TagKind, TagStandard, Timestamp};
+
+/// NIP66 Error
+#[derive(Debug)]
+pub enum Error {
+    /// Relay URL error
+    RelayUrl(url::Error),
+    /// Wrong event kind
+    WrongKind,
+    /// Relay URL missing from event (no `d` tag)
+    RelayUrlMissing,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RelayUrl(e) => write!(f, "Relay URL: {e}"),
+            Self::WrongKind => write!(f, "Wrong event kind"),
+            Self::RelayUrlMissing => write!(f, "Event missing the relay URL"),
+        }
+    }
+}
+
+impl From<url::Error> for Error {
+    fn from(e: url::Error) -> Self {
+        Self::RelayUrl(e)
+    }
+}
+
+/// Relay network
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RelayNetwork {
+    /// Clearnet
+    Clearnet,
+    /// Tor
+    Tor,
+    /// I2P
+    I2p,
+    /// Lokinet
+    Loki,
+    /// Custom
+    Custom(String),
+}
+
+impl fmt::Display for RelayNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl RelayNetwork {
+    /// Get as `&str`
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Clearnet => "clearnet",
+            Self::Tor => "tor",
+            Self::I2p => "i2p",
+            Self::Loki => "loki",
+            Self::Custom(s) => s.as_str(),
+        }
+    }
+}
+
+impl<S> From<S> for RelayNetwork
+where
+    S: Into<String>,
+{
+    fn from(s: S) -> Self {
+        let s: String = s.into();
+        match s.as_str() {
+            "clearnet" => Self::Clearnet,
+            "tor" => Self::Tor,
+            "i2p" => Self::I2p,
+            "loki" => Self::Loki,
+            _ => Self::Custom(s),
+        }
+    }
+}
+
+/// Relay discovery
+///
+/// Published by relay monitors to report the liveness and the characteristics of a relay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayDiscovery {
+    /// Relay URL
+    pub relay_url: RelayUrl,
+    /// Round-trip time of the connection opening
+    pub rtt_open: Option<Duration>,
+    /// Round-trip time of a read (`REQ`)
+    pub rtt_read: Option<Duration>,
+    /// Round-trip time of a write (`EVENT`)
+    pub rtt_write: Option<Duration>,
+    /// Network
+    pub network: Option<RelayNetwork>,
+    /// Relay type (ex. `PublicOutbox`, `PrivateInbox`)
+    pub relay_type: Option<String>,
+    /// Supported NIPs
+    pub supported_nips: Vec<u16>,
+    /// Requirements (ex. `auth`, `payment`, `!pow`)
+    pub requirements: Vec<String>,
+    /// Topics
+    pub topics: Vec<String>,
+    /// Geohash
+    pub geohash: Option<String>,
+}
+
+impl RelayDiscovery {
+    /// New relay discovery report
+    #[inline]
+    pub fn new(relay_url: RelayUrl) -> Self {
+        Self {
+            relay_url,
+            rtt_open: None,
+            rtt_read: None,
+            rtt_write: None,
+            network: None,
+            relay_type: None,
+            supported_nips: Vec::new(),
+            requirements: Vec::new(),
+            topics: Vec::new(),
+            geohash: None,
+        }
+    }
+
+    pub(crate) fn to_event_builder(self) -> EventBuilder {
+        let mut tags: Vec<Tag> = Vec::with_capacity(
+            1 + self.supported_nips.len() + self.requirements.len() + self.topics.len(),
+        );
+
+        // Add relay URL
+        tags.push(Tag::identifier(self.relay_url.to_string()));
+
+        // Add round-trip times
+        let rtts = [
+            ("rtt-open", self.rtt_open),
+            ("rtt-read", self.rtt_read),
+            ("rtt-write", self.rtt_write),
+        ];
+        for (kind, rtt) in rtts.into_iter() {
+            if let Some(rtt) = rtt {
+                tags.push(Tag::custom(
+                    TagKind::custom(kind),
+                    [rtt.as_millis().to_string()],
+                ));
+            }
+        }
+
+        // Add network and relay type
+        if let Some(network) = self.network {
+            tags.push(Tag::custom(TagKind::custom("n"), [network.to_string()]));
+        }
+
+        if let Some(relay_type) = self.relay_type {
+            tags.push(Tag::custom(TagKind::custom("T"), [relay_type]));
+        }
+
+        // Add supported NIPs, requirements and topics
+        tags.extend(
+            self.supported_nips
+                .into_iter()
+                .map(|nip| Tag::custom(TagKind::custom("N"), [nip.to_string()])),
+        );
+        tags.extend(
+            self.requirements
+                .into_iter()
+                .map(|r| Tag::custom(TagKind::custom("R"), [r])),
+        );
+        tags.extend(self.topics.into_iter().map(Tag::hashtag));
+
+        // Add geohash
+        if let Some(geohash) = self.geohash {
+            tags.push(Tag::from_standardized_without_cell(TagStandard::Geohash(
+                geohash,
+            )));
+        }
+
+        // Build
+        EventBuilder::new(Kind::RelayDiscovery, "").tags(tags)
+    }
+}
+
+impl TryFrom<&Event> for RelayDiscovery {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::RelayDiscovery {
+            return Err(Error::WrongKind);
+        }
+
+        let relay_url: &str = event.tags.identifier().ok_or(Error::RelayUrlMissing)?;
+        let mut discovery = Self::new(RelayUrl::parse(relay_url)?);
+
+        for tag in event.tags.iter() {
+            if let [kind, value, ..] = tag.as_slice() {
+                match kind.as_str() {
+                    "rtt-open" => discovery.rtt_open = parse_millis(value),
+                    "rtt-read" => discovery.rtt_read = parse_millis(value),
+                    "rtt-write" => discovery.rtt_write = parse_millis(value),
+                    "n" => discovery.network = Some(RelayNetwork::from(value.as_str())),
+                    "T" => discovery.relay_type = Some(value.to_string()),
+                    "N" => {
+                        if let Ok(nip) = value.parse() {
+                            discovery.supported_nips.push(nip);
+                        }
+                    }
+                    "R" => discovery.requirements.push(value.to_string()),
+                    "t" => discovery.topics.push(value.to_string()),
+                    "g" => discovery.geohash = Some(value.to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(discovery)
+    }
+}
+
+/// Relay monitor announcement
+///
+/// Published by relay monitors to announce their intent to publish [`Kind::RelayDiscovery`] events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelayMonitorAnnouncement {
+    /// Frequency of the checks
+    pub frequency: Duration,
+    /// Timeouts, per check type
+    pub timeouts: BTreeMap<String, Duration>,
+    /// Performed checks (ex. `open`, `read`, `write`, `nip11`)
+    pub checks: Vec<String>,
+    /// Geohash
+    pub geohash: Option<String>,
+}
+
+impl RelayMonitorAnnouncement {
+    /// New relay monitor announcement
+    #[inline]
+    pub fn new(frequency: Duration) -> Self {
+        Self {
+            frequency,
+            timeouts: BTreeMap::new(),
+            checks: Vec::new(),
+            geohash: None,
+        }
+    }
+
+    pub(crate) fn to_event_builder(self) -> EventBuilder {
+        let mut tags: Vec<Tag> = Vec::with_capacity(1 + self.timeouts.len() + self.checks.len());
+
+        // Add frequency
+        tags.push(Tag::custom(
+            TagKind::custom("frequency"),
+            [self.frequency.as_secs().to_string()],
+        ));
+
+        // Add timeouts
+        tags.extend(self.timeouts.into_iter().map(|(check, timeout)| {
+            Tag::custom(
+                TagKind::custom("timeout"),
+                [check, timeout.as_millis().to_string()],
+            )
+        }));
+
+        // Add checks
+        tags.extend(
+            self.checks
+                .into_iter()
+                .map(|c| Tag::custom(TagKind::custom("c"), [c])),
+        );
+
+        // Add geohash
+        if let Some(geohash) = self.geohash {
+            tags.push(Tag::from_standardized_without_cell(TagStandard::Geohash(
+                geohash,
+            )));
+        }
+
+        // Build
+        EventBuilder::new(Kind::RelayMonitorAnnouncement, "").tags(tags)
+    }
+}
+
+impl TryFrom<&Event> for RelayMonitorAnnouncement {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::RelayMonitorAnnouncement {
+            return Err(Error::WrongKind);
+        }
+
+        let mut announcement = Self::new(Duration::ZERO);
+
+        for tag in event.tags.iter() {
+            match tag.as_slice() {
+                [kind, value] if kind == "frequency" => {
+                    if let Ok(secs) = value.parse() {
+                        announcement.frequency = Duration::from_secs(secs);
+                    }
+                }
+                [kind, check, value] if kind == "timeout" => {
+                    if let Some(timeout) = parse_millis(value) {
+                        announcement.timeouts.insert(check.to_string(), timeout);
+                    }
+                }
+                [kind, value, ..] if kind == "c" => announcement.checks.push(value.to_string()),
+                [kind, value, ..] if kind == "g" => announcement.geohash = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        Ok(announcement)
+    }
+}
+
+/// Construct a [`Filter`] to find the relay discovery reports published since the specified timestamp
+///
+/// The returned filter matches [`Kind::RelayDiscovery`] events.
+/// Add [`Filter::authors`] to limit the results to trusted monitors.
+pub fn filter_relay_discoveries(since: Timestamp) -> Filter {
+    Filter::new().kind(Kind::RelayDiscovery).since(since)
+}
+
+fn parse_millis(value: &str) -> Option<Duration> {
+    value.parse().ok().map(Duration::from_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_relay_discovery_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+
+        let discovery = RelayDiscovery {
+            relay_url: RelayUrl::parse("wss://relay.example.com").unwrap(),
+            rtt_open: Some(Duration::from_millis(234)),
+            rtt_read: None,
+            rtt_write: Some(Duration::from_millis(150)),
+            network: Some(RelayNetwork::Clearnet),
+            relay_type: Some(String::from("PublicOutbox")),
+            supported_nips: vec![1, 11, 42],
+            requirements: vec![String::from("!payment"), String::from("auth")],
+            topics: vec![String::from("nsfw")],
+            geohash: Some(String::from("ww8p1r4t8")),
+        };
+
+        let event = EventBuilder::relay_discovery(discovery.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.tags.identifier(), Some("wss://relay.example.com"));
+        assert_eq!(RelayDiscovery::try_from(&event).unwrap(), discovery);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_relay_monitor_announcement_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+
+        let mut announcement = RelayMonitorAnnouncement::new(Duration::from_secs(3600));
+        announcement
+            .timeouts
+            .insert(String::from("open"), Duration::from_millis(5000));
+        announcement.checks = vec![String::from("open"), String::from("read")];
+
+        let event = EventBuilder::relay_monitor_announcement(announcement.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            RelayMonitorAnnouncement::try_from(&event).unwrap(),
+            announcement
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_parse_relay_discovery_wrong_kind() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("test")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(matches!(
+            RelayDiscovery::try_from(&event).unwrap_err(),
+            Error::WrongKind
+        ));
+    }
+}
@@ -0,0 +1,432 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP89: Recommended Application Handlers
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/89.md>
+
+#![allow(clippy::wrong_self_convention)]
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::nips::nip01::{self, Coordinate};
+use crate::types::metadata::{self, Metadata};
+use crate::{
+    Alphabet, Event, EventBuilder, Filter, JsonUtil, Kind, RelayUrl, SingleLetterTag, Tag, TagKind,
+    TagStandard,
+};
+
+/// NIP89 Error
+#[derive(Debug)]
+pub enum Error {
+    /// Metadata error
+    Metadata(metadata::Error),
+    /// NIP01 error
+    NIP01(nip01::Error),
+    /// Wrong event kind
+    WrongKind,
+    /// Identifier missing from event (no `d` tag)
+    IdentifierMissing,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Metadata(e) => write!(f, "Metadata: {e}"),
+            Self::NIP01(e) => write!(f, "NIP01: {e}"),
+            Self::WrongKind => write!(f, "Wrong event kind"),
+            Self::IdentifierMissing => write!(f, "Event missing an identifier"),
+        }
+    }
+}
+
+impl From<metadata::Error> for Error {
+    fn from(e: metadata::Error) -> Self {
+        Self::Metadata(e)
+    }
+}
+
+impl From<nip01::Error> for Error {
+    fn from(e: nip01::Error) -> Self {
+        Self::NIP01(e)
+    }
+}
+
+/// Handler platform
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HandlerPlatform {
+    /// Web
+    Web,
+    /// iOS
+    Ios,
+    /// Android
+    Android,
+    /// Custom
+    Custom(String),
+}
+
+impl fmt::Display for HandlerPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl HandlerPlatform {
+    /// Get as `&str`
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Web => "web",
+            Self::Ios => "ios",
+            Self::Android => "android",
+            Self::Custom(s) => s.as_str(),
+        }
+    }
+}
+
+impl<S> From<S> for HandlerPlatform
+where
+    S: Into<String>,
+{
+    fn from(s: S) -> Self {
+        let s: String = s.into();
+        match s.as_str() {
+            "web" => Self::Web,
+            "ios" => Self::Ios,
+            "android" => Self::Android,
+            _ => Self::Custom(s),
+        }
+    }
+}
+
+/// Handler endpoint
+///
+/// JSON: `["<platform>", "<url>", "<entity>"]`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HandlerEndpoint {
+    /// Platform
+    pub platform: HandlerPlatform,
+    /// URL template
+    ///
+    /// The `<bech32>` placeholder is replaced by clients with the NIP19-encoded entity.
+    pub url: String,
+    /// NIP19 entity type handled by the URL (ex. `npub`, `nevent`, `naddr`)
+    pub entity: Option<String>,
+}
+
+/// Handler information
+///
+/// Published by the application that can handle the listed kinds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandlerInformation {
+    /// Unique handler ID
+    pub id: String,
+    /// Optional application metadata
+    ///
+    /// If not set, clients should use the author's kind `0`.
+    pub metadata: Option<Metadata>,
+    /// Supported event kinds
+    pub kinds: Vec<Kind>,
+    /// Platform endpoints
+    pub endpoints: Vec<HandlerEndpoint>,
+}
+
+impl HandlerInformation {
+    pub(crate) fn to_event_builder(self) -> EventBuilder {
+        let mut tags: Vec<Tag> = Vec::with_capacity(1 + self.kinds.len() + self.endpoints.len());
+
+        // Add handler ID
+        tags.push(Tag::identifier(self.id));
+
+        // Add supported kinds
+        tags.extend(self.kinds.into_iter().map(|kind| {
+            Tag::from_standardized_without_cell(TagStandard::Kind {
+                kind,
+                uppercase: false,
+            })
+        }));
+
+        // Add endpoints
+        tags.extend(self.endpoints.into_iter().map(|endpoint| {
+            let mut values: Vec<String> = Vec::with_capacity(2);
+            values.push(endpoint.url);
+            if let Some(entity) = endpoint.entity {
+                values.push(entity);
+            }
+            Tag::custom(TagKind::custom(endpoint.platform.to_string()), values)
+        }));
+
+        // Compose content
+        let content: String = match self.metadata {
+            Some(metadata) => metadata.as_json(),
+            None => String::new(),
+        };
+
+        // Build
+        EventBuilder::new(Kind::HandlerInformation, content).tags(tags)
+    }
+}
+
+impl TryFrom<&Event> for HandlerInformation {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::HandlerInformation {
+            return Err(Error::WrongKind);
+        }
+
+        let id: &str = event.tags.identifier().ok_or(Error::IdentifierMissing)?;
+
+        let metadata: Option<Metadata> = if event.content.is_empty() {
+            None
+        } else {
+            Some(Metadata::from_json(&event.content)?)
+        };
+
+        let mut info = Self {
+            id: id.to_string(),
+            metadata,
+            ..Default::default()
+        };
+
+        for tag in event.tags.iter() {
+            match tag.as_standardized() {
+                Some(TagStandard::Kind {
+                    kind,
+                    uppercase: false,
+                }) => info.kinds.push(*kind),
+                // Skip the other standard tags (i.e. `d`, `alt`, `client`)
+                Some(..) => {}
+                // Any other tag is a platform endpoint (also the custom ones)
+                None => {
+                    if let [platform, url, rest @ ..] = tag.as_slice() {
+                        info.endpoints.push(HandlerEndpoint {
+                            platform: HandlerPlatform::from(platform.as_str()),
+                            url: url.to_string(),
+                            entity: rest.first().cloned(),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(info)
+    }
+}
+
+/// Recommended handler
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RecommendedHandler {
+    /// Coordinate of the [`Kind::HandlerInformation`] event
+    pub coordinate: Coordinate,
+    /// Relay hint
+    pub relay_url: Option<RelayUrl>,
+    /// Platform
+    pub platform: Option<HandlerPlatform>,
+}
+
+/// Handler recommendation
+///
+/// Published by users to recommend applications that handle a specific kind.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HandlerRecommendation {
+    /// Supported event kind
+    pub kind: Kind,
+    /// Recommended handlers
+    pub handlers: Vec<RecommendedHandler>,
+}
+
+impl HandlerRecommendation {
+    pub(crate) fn to_event_builder(self) -> EventBuilder {
+        let mut tags: Vec<Tag> = Vec::with_capacity(1 + self.handlers.len());
+
+        // Add supported kind
+        tags.push(Tag::identifier(self.kind.to_string()));
+
+        // Add handlers
+        tags.extend(self.handlers.into_iter().map(|handler| {
+            let mut values: Vec<String> = Vec::with_capacity(3);
+            values.push(handler.coordinate.to_string());
+            match (handler.relay_url, handler.platform) {
+                (Some(relay_url), Some(platform)) => {
+                    values.push(relay_url.to_string());
+                    values.push(platform.to_string());
+                }
+                (Some(relay_url), None) => values.push(relay_url.to_string()),
+                (None, Some(platform)) => {
+                    values.push(String::new());
+                    values.push(platform.to_string());
+                }
+                (None, None) => {}
+            }
+            Tag::custom(TagKind::a(), values)
+        }));
+
+        // Build
+        EventBuilder::new(Kind::HandlerRecommendation, "").tags(tags)
+    }
+}
+
+impl TryFrom<&Event> for HandlerRecommendation {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::HandlerRecommendation {
+            return Err(Error::WrongKind);
+        }
+
+        let kind: Kind = event
+            .tags
+            .identifier()
+            .and_then(|id| Kind::from_str(id).ok())
+            .ok_or(Error::IdentifierMissing)?;
+
+        let mut handlers: Vec<RecommendedHandler> = Vec::new();
+
+        for tag in event.tags.filter(TagKind::a()) {
+            if let [_, coordinate, rest @ ..] = tag.as_slice() {
+                // Skip malformed coordinates
+                let coordinate: Coordinate = match Coordinate::parse(coordinate) {
+                    Ok(coordinate) => coordinate,
+                    Err(..) => continue,
+                };
+
+                // Skip coordinates that don't point to an handler information event
+                if coordinate.kind != Kind::HandlerInformation {
+                    continue;
+                }
+
+                handlers.push(RecommendedHandler {
+                    coordinate,
+                    relay_url: rest.first().and_then(|url| RelayUrl::parse(url).ok()),
+                    platform: rest
+                        .get(1)
+                        .filter(|p| !p.is_empty())
+                        .map(|p| HandlerPlatform::from(p.as_str())),
+                });
+            }
+        }
+
+        Ok(Self { kind, handlers })
+    }
+}
+
+/// Construct a [`Filter`] to find the applications that handle the specified kind
+///
+/// The returned filter matches [`Kind::HandlerInformation`] events.
+pub fn filter_handlers_for_kind(kind: Kind) -> Filter {
+    Filter::new()
+        .kind(Kind::HandlerInformation)
+        .custom_tag(SingleLetterTag::lowercase(Alphabet::K), [kind.to_string()])
+}
+
+/// Construct a [`Filter`] to find the handler recommendations for the specified kind
+///
+/// The returned filter matches [`Kind::HandlerRecommendation`] events.
+/// Add [`Filter::authors`] to limit the results to the recommendations of followed users.
+pub fn filter_recommendations_for_kind(kind: Kind) -> Filter {
+    Filter::new()
+        .kind(Kind::HandlerRecommendation)
+        .identifier(kind.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_handler_information_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+
+        let info = HandlerInformation {
+            id: String::from("abcd"),
+            metadata: Some(Metadata::new().name("App")),
+            kinds: vec![Kind::TextNote, Kind::LongFormTextNote],
+            endpoints: vec![
+                HandlerEndpoint {
+                    platform: HandlerPlatform::Web,
+                    url: String::from("https://example.com/a/<bech32>"),
+                    entity: Some(String::from("nevent")),
+                },
+                HandlerEndpoint {
+                    platform: HandlerPlatform::Ios,
+                    url: String::from(".../<bech32>"),
+                    entity: None,
+                },
+                HandlerEndpoint {
+                    platform: HandlerPlatform::Custom(String::from("linux")),
+                    url: String::from("app://<bech32>"),
+                    entity: Some(String::from("nprofile")),
+                },
+            ],
+        };
+
+        let event = EventBuilder::handler_information(info.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(HandlerInformation::try_from(&event).unwrap(), info);
+
+        // The other standard tags aren't endpoints
+        let event = EventBuilder::handler_information(info.clone())
+            .tag(Tag::from_standardized_without_cell(TagStandard::Alt(
+                String::from("Handler"),
+            )))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(HandlerInformation::try_from(&event).unwrap(), info);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_handler_recommendation_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+
+        let recommendation = HandlerRecommendation {
+            kind: Kind::LongFormTextNote,
+            handlers: vec![
+                RecommendedHandler {
+                    coordinate: Coordinate::new(Kind::HandlerInformation, keys.public_key())
+                        .identifier("abcd"),
+                    relay_url: Some(RelayUrl::parse("wss://relay.example.com").unwrap()),
+                    platform: Some(HandlerPlatform::Web),
+                },
+                RecommendedHandler {
+                    coordinate: Coordinate::new(Kind::HandlerInformation, keys.public_key())
+                        .identifier("efgh"),
+                    relay_url: None,
+                    platform: Some(HandlerPlatform::Android),
+                },
+            ],
+        };
+
+        let event = EventBuilder::handler_recommendation(recommendation.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.tags.identifier(), Some("30023"));
+        assert_eq!(
+            HandlerRecommendation::try_from(&event).unwrap(),
+            recommendation
+        );
+
+        // A malformed `a` tag is skipped
+        let event = EventBuilder::handler_recommendation(recommendation.clone())
+            .tag(Tag::custom(TagKind::a(), ["invalid"]))
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            HandlerRecommendation::try_from(&event).unwrap(),
+            recommendation
+        );
+    }
+}
@@ -0,0 +1,350 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! NIP99: Classified Listings
+//!
+//! <https://github.com/nostr-protocol/nips/blob/master/99.md>
+
+#![allow(clippy::wrong_self_convention)]
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::types::url::Url;
+use crate::{
+    Event, EventBuilder, Filter, ImageDimensions, Kind, Tag, TagKind, TagStandard, Timestamp,
+};
+
+/// NIP99 Error
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Wrong event kind
+    WrongKind,
+    /// Identifier missing from event (no `d` tag)
+    IdentifierMissing,
+    /// Invalid price tag
+    InvalidPrice,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongKind => write!(f, "Wrong event kind"),
+            Self::IdentifierMissing => write!(f, "Event missing an identifier"),
+            Self::InvalidPrice => write!(f, "Invalid price tag"),
+        }
+    }
+}
+
+/// Classified Listing Status
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ClassifiedListingStatus {
+    /// Active
+    Active,
+    /// Sold
+    Sold,
+    /// Custom
+    Custom(String),
+}
+
+impl fmt::Display for ClassifiedListingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Active => write!(f, "active"),
+            Self::Sold => write!(f, "sold"),
+            Self::Custom(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl<S> From<S> for ClassifiedListingStatus
+where
+    S: Into<String>,
+{
+    fn from(s: S) -> Self {
+        let s: String = s.into();
+        match s.as_str() {
+            "active" => Self::Active,
+            "sold" => Self::Sold,
+            _ => Self::Custom(s),
+        }
+    }
+}
+
+/// Classified Listing Price
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Price {
+    /// Amount in numeric format (kept as string to avoid precision loss)
+    pub amount: String,
+    /// Currency in 3-letter ISO 4217 format (ex. `EUR`, `USD`) or crypto ticker (ex. `BTC`, `SATS`)
+    pub currency: String,
+    /// Optional frequency (ex. `hour`, `day`, `week`, `month`, `year`)
+    pub frequency: Option<String>,
+}
+
+impl Price {
+    fn parse(tag: &[String]) -> Result<Self, Error> {
+        match tag {
+            [_, amount, currency] => Ok(Self {
+                amount: amount.to_string(),
+                currency: currency.to_string(),
+                frequency: None,
+            }),
+            [_, amount, currency, frequency, ..] => Ok(Self {
+                amount: amount.to_string(),
+                currency: currency.to_string(),
+                frequency: Some(frequency.to_string()),
+            }),
+            _ => Err(Error::InvalidPrice),
+        }
+    }
+
+    fn to_tag(self) -> Tag {
+        let mut values: Vec<String> = Vec::with_capacity(3);
+        values.push(self.amount);
+        values.push(self.currency);
+        if let Some(frequency) = self.frequency {
+            values.push(frequency);
+        }
+        Tag::custom(TagKind::Price, values)
+    }
+}
+
+/// Classified Listing
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClassifiedListing {
+    /// Unique listing ID
+    pub id: String,
+    /// Listing description (markdown)
+    pub description: String,
+    /// Title of the listing
+    pub title: Option<String>,
+    /// Short tagline or summary for the listing
+    pub summary: Option<String>,
+    /// Timestamp of the first time the listing was published
+    pub published_at: Option<Timestamp>,
+    /// Location
+    pub location: Option<String>,
+    /// Price
+    pub price: Option<Price>,
+    /// Status
+    pub status: Option<ClassifiedListingStatus>,
+    /// Images
+    pub images: Vec<(Url, Option<ImageDimensions>)>,
+    /// Hashtags (categories)
+    pub hashtags: Vec<String>,
+    /// Geohash
+    pub geohash: Option<String>,
+}
+
+impl ClassifiedListing {
+    /// Create a new classified listing
+    pub fn new<S1, S2>(id: S1, description: S2) -> Self
+    where
+        S1: Into<String>,
+        S2: Into<String>,
+    {
+        Self {
+            id: id.into(),
+            description: description.into(),
+            title: None,
+            summary: None,
+            published_at: None,
+            location: None,
+            price: None,
+            status: None,
+            images: Vec::new(),
+            hashtags: Vec::new(),
+            geohash: None,
+        }
+    }
+
+    pub(crate) fn to_event_builder(self, kind: Kind) -> EventBuilder {
+        let mut tags: Vec<Tag> = Vec::with_capacity(1);
+
+        // Add listing ID
+        tags.push(Tag::identifier(self.id));
+
+        // Add title
+        if let Some(title) = self.title {
+            tags.push(Tag::title(title));
+        }
+
+        // Add summary
+        if let Some(summary) = self.summary {
+            tags.push(Tag::from_standardized_without_cell(TagStandard::Summary(
+                summary,
+            )));
+        }
+
+        // Add published at
+        if let Some(published_at) = self.published_at {
+            tags.push(Tag::from_standardized_without_cell(
+                TagStandard::PublishedAt(published_at),
+            ));
+        }
+
+        // Add location
+        if let Some(location) = self.location {
+            tags.push(Tag::custom(TagKind::Location, [location]));
+        }
+
+        // Add price
+        if let Some(price) = self.price {
+            tags.push(price.to_tag());
+        }
+
+        // Add status
+        if let Some(status) = self.status {
+            tags.push(Tag::custom(TagKind::Status, [status.to_string()]));
+        }
+
+        // Add images
+        tags.extend(
+            self.images
+                .into_iter()
+                .map(|(url, dim)| Tag::image(url, dim)),
+        );
+
+        // Add hashtags
+        tags.extend(self.hashtags.into_iter().map(Tag::hashtag));
+
+        // Add geohash
+        if let Some(geohash) = self.geohash {
+            tags.push(Tag::from_standardized_without_cell(TagStandard::Geohash(
+                geohash,
+            )));
+        }
+
+        // Build
+        EventBuilder::new(kind, self.description).tags(tags)
+    }
+}
+
+impl TryFrom<&Event> for ClassifiedListing {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self, Self::Error> {
+        if event.kind != Kind::ClassifiedListing && event.kind != Kind::DraftClassifiedListing {
+            return Err(Error::WrongKind);
+        }
+
+        let id: &str = event.tags.identifier().ok_or(Error::IdentifierMissing)?;
+
+        let mut listing = Self::new(id, event.content.as_str());
+
+        for tag in event.tags.iter() {
+            match tag.kind() {
+                TagKind::Location => listing.location = tag.content().map(|l| l.to_string()),
+                TagKind::Price => listing.price = Some(Price::parse(tag.as_slice())?),
+                TagKind::Status => {
+                    listing.status = tag.content().map(ClassifiedListingStatus::from)
+                }
+                _ => match tag.as_standardized() {
+                    Some(TagStandard::Title(title)) => listing.title = Some(title.clone()),
+                    Some(TagStandard::Summary(summary)) => listing.summary = Some(summary.clone()),
+                    Some(TagStandard::PublishedAt(timestamp)) => {
+                        listing.published_at = Some(*timestamp)
+                    }
+                    Some(TagStandard::Image(url, dim)) => listing.images.push((url.clone(), *dim)),
+                    Some(TagStandard::Hashtag(hashtag)) => listing.hashtags.push(hashtag.clone()),
+                    Some(TagStandard::Geohash(geohash)) => listing.geohash = Some(geohash.clone()),
+                    _ => {}
+                },
+            }
+        }
+
+        Ok(listing)
+    }
+}
+
+/// Construct a [`Filter`] to browse classified listings tagged with **any** of the hashtags
+///
+/// Draft and inactive listings (kind `30403`) are not included.
+pub fn filter_by_hashtags<I, S>(hashtags: I) -> Filter
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    Filter::new()
+        .kind(Kind::ClassifiedListing)
+        .hashtags(hashtags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_parse() {
+        let tag = vec![
+            String::from("price"),
+            String::from("100"),
+            String::from("EUR"),
+        ];
+        assert_eq!(
+            Price::parse(&tag).unwrap(),
+            Price {
+                amount: String::from("100"),
+                currency: String::from("EUR"),
+                frequency: None,
+            }
+        );
+
+        let tag = vec![
+            String::from("price"),
+            String::from("15"),
+            String::from("USD"),
+            String::from("month"),
+        ];
+        assert_eq!(
+            Price::parse(&tag).unwrap(),
+            Price {
+                amount: String::from("15"),
+                currency: String::from("USD"),
+                frequency: Some(String::from("month")),
+            }
+        );
+
+        let tag = vec![String::from("price"), String::from("15")];
+        assert_eq!(Price::parse(&tag).unwrap_err(), Error::InvalidPrice);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_classified_listing_roundtrip() {
+        use crate::Keys;
+
+        let keys = Keys::generate();
+
+        let mut listing = ClassifiedListing::new("bike", "Lightly used road bike");
+        listing.title = Some(String::from("Road bike"));
+        listing.location = Some(String::from("Milan"));
+        listing.price = Some(Price {
+            amount: String::from("450"),
+            currency: String::from("EUR"),
+            frequency: None,
+        });
+        listing.status = Some(ClassifiedListingStatus::Active);
+        listing.hashtags = vec![String::from("bike"), String::from("sport")];
+
+        let event = EventBuilder::classified_listing(listing.clone())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(event.kind, Kind::ClassifiedListing);
+        assert_eq!(ClassifiedListing::try_from(&event).unwrap(), listing);
+
+        let event = EventBuilder::text_note("hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            ClassifiedListing::try_from(&event).unwrap_err(),
+            Error::WrongKind
+        );
+    }
+}
@@ -28,6 +28,8 @@ pub use crate::key::{self, *};
 pub use crate::message::{self, *};
 // NIPs
 pub use crate::nips::nip01::{self, *};
+#[cfg(feature = "nip03")]
+pub use crate::nips::nip03::{self, *};
 #[cfg(feature = "nip04")]
 pub use crate::nips::nip04;
 #[cfg(all(feature = "std", feature = "nip05"))]
@@ -65,10 +67,14 @@ pub use crate::nips::nip57::{self, *};
 pub use crate::nips::nip58;
 #[cfg(feature = "nip59")]
 pub use crate::nips::nip59::{self, *};
+pub use crate::nips::nip62::{self, *};
 pub use crate::nips::nip65::{self, *};
+pub use crate::nips::nip66::{self, *};
+pub use crate::nips::nip89::{self, *};
 pub use crate::nips::nip90::{self, *};
 pub use crate::nips::nip94::{self, *};
 pub use crate::nips::nip98::{self, *};
+pub use crate::nips::nip99::{self, *};
 pub use crate::signer::{self, *};
 pub use crate::types::*;
 pub use crate::util::{self, *};
@@ -0,0 +1,263 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Reference test vectors
+//!
+//! Vectors published in the NIPs (or used by the reference implementations),
+//! exposed so other implementations can check their compatibility.
+
+/// NIP04 vectors
+///
+/// NIP04 doesn't define official vectors: these are compatible with the reference implementations.
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/04.md>
+pub mod nip04 {
+    /// NIP04 vector
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Vector {
+        /// Sender secret key (hex)
+        pub sender_secret_key: &'static str,
+        /// Receiver secret key (hex)
+        pub receiver_secret_key: &'static str,
+        /// Plaintext
+        pub plaintext: &'static str,
+        /// Payload (`<base64 ciphertext>?iv=<base64 iv>`)
+        pub payload: &'static str,
+    }
+
+    /// Decryption vectors
+    pub const VECTORS: &[Vector] = &[Vector {
+        sender_secret_key: "6b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e",
+        receiver_secret_key: "7b911fd37cdf5c81d4c0adb1ab7fa822ed253ab0ad9aa18d77257c88b29b718e",
+        plaintext: "Saturn, bringer of old age",
+        payload: "dJc+WbBgaFCD2/kfg1XCWJParplBDxnZIdJGZ6FCTOg=?iv=M6VxRPkMZu7aIdD+10xPuw==",
+    }];
+}
+
+/// NIP06 vectors
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/06.md>
+pub mod nip06 {
+    /// NIP06 vector
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Vector {
+        /// BIP39 mnemonic
+        pub mnemonic: &'static str,
+        /// Derived secret key (hex)
+        pub secret_key: &'static str,
+        /// Derived public key (hex)
+        pub public_key: &'static str,
+    }
+
+    /// Derivation vectors (account `0`, no passphrase)
+    pub const VECTORS: &[Vector] = &[
+        Vector {
+            mnemonic: "leader monkey parrot ring guide accident before fence cannon height naive bean",
+            secret_key: "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a",
+            public_key: "17162c921dc4d2518f9a101db33695df1afb56ab82f5ff3e5da6eec3ca5cd917",
+        },
+        Vector {
+            mnemonic: "what bleak badge arrange retreat wolf trade produce cricket blur garlic valid proud rude strong choose busy staff weather area salt hollow arm fade",
+            secret_key: "c15d739894c81a2fcfd3a2df85a0d2c0dbc47a280d092799f144d73d7ae78add",
+            public_key: "d41b22899549e1f3d335a31002cfd382174006e166d3e658e3a5eecdb6463573",
+        },
+    ];
+}
+
+/// NIP19 vectors
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/19.md>
+pub mod nip19 {
+    /// NIP19 vector for the bare entities (`npub`, `nsec` and `note`)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Vector {
+        /// Bech32 encoded entity
+        pub bech32: &'static str,
+        /// Hex encoded entity
+        pub hex: &'static str,
+    }
+
+    /// NIP19 vector for `nprofile`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ProfileVector {
+        /// Bech32 encoded profile
+        pub bech32: &'static str,
+        /// Public key (hex)
+        pub public_key: &'static str,
+        /// Relays
+        pub relays: &'static [&'static str],
+    }
+
+    /// `npub` vectors
+    pub const NPUB: &[Vector] = &[
+        Vector {
+            bech32: "npub10elfcs4fr0l0r8af98jlmgdh9c8tcxjvz9qkw038js35mp4dma8qzvjptg",
+            hex: "7e7e9c42a91bfef19fa929e5fda1b72e0ebc1a4c1141673e2794234d86addf4e",
+        },
+        Vector {
+            bech32: "npub14f8usejl26twx0dhuxjh9cas7keav9vr0v8nvtwtrjqx3vycc76qqh9nsy",
+            hex: "aa4fc8665f5696e33db7e1a572e3b0f5b3d615837b0f362dcb1c8068b098c7b4",
+        },
+    ];
+
+    /// `nsec` vectors
+    pub const NSEC: &[Vector] = &[
+        Vector {
+            bech32: "nsec1vl029mgpspedva04g90vltkh6fvh240zqtv9k0t9af8935ke9laqsnlfe5",
+            hex: "67dea2ed018072d675f5415ecfaed7d2597555e202d85b3d65ea4e58d2d92ffa",
+        },
+        Vector {
+            bech32: "nsec1j4c6269y9w0q2er2xjw8sv2ehyrtfxq3jwgdlxj6qfn8z4gjsq5qfvfk99",
+            hex: "9571a568a42b9e05646a349c783159b906b498119390df9a5a02667155128028",
+        },
+    ];
+
+    /// `note` vectors
+    pub const NOTE: &[Vector] = &[Vector {
+        bech32: "note1m99r7nwc0wdrkzldrqan96gklg5usqspq7z9696j6unf0ljnpxjspqfw99",
+        hex: "d94a3f4dd87b9a3b0bed183b32e916fa29c8020107845d1752d72697fe5309a5",
+    }];
+
+    /// `nprofile` vectors
+    pub const NPROFILE: &[ProfileVector] = &[ProfileVector {
+        bech32: "nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p",
+        public_key: "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d",
+        relays: &["wss://r.x.com", "wss://djbas.sadkb.com"],
+    }];
+}
+
+/// NIP44 vectors
+///
+/// <https://github.com/paulmillr/nip44/blob/main/nip44.vectors.json>
+pub mod nip44 {
+    /// Official NIP44 vectors (JSON)
+    pub const JSON: &str = include_str!("nips/nip44/nip44.vectors.json");
+}
+
+/// Event ID and signature vectors
+///
+/// <https://github.com/nostr-protocol/nips/blob/master/01.md>
+pub mod event {
+    /// Event vector
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Vector {
+        /// Signed event JSON
+        pub json: &'static str,
+        /// Canonical serialization used to compute the ID
+        pub canonical_json: &'static str,
+        /// Event ID (hex)
+        pub id: &'static str,
+    }
+
+    /// Event vectors
+    pub const VECTORS: &[Vector] = &[
+        Vector {
+            json: r#"{"content":"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA==","created_at":1640839235,"id":"2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45","kind":4,"pubkey":"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785","sig":"a5d9290ef9659083c490b303eb7ee41356d8778ff19f2f91776c8dc4443388a64ffcf336e61af4c25c05ac3ae952d1ced889ed655b67790891222aaa15b99fdd","tags":[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]]}"#,
+            canonical_json: r#"[0,"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785",1640839235,4,[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]],"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA=="]"#,
+            id: "2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45",
+        },
+        Vector {
+            json: r#"{"content":"Think about this.\n\nThe most powerful centralized institutions in the world have been replaced by a protocol that protects the individual. #bitcoin\n\nDo you doubt that we can replace everything else?\n\nBullish on the future of humanity\nnostr:nevent1qqs9ljegkuk2m2ewfjlhxy054n6ld5dfngwzuep0ddhs64gc49q0nmqpzdmhxue69uhhyetvv9ukzcnvv5hx7un8qgsw3mfhnrr0l6ll5zzsrtpeufckv2lazc8k3ru5c3wkjtv8vlwngksrqsqqqqqpttgr27","created_at":1703184271,"id":"38acf9b08d06859e49237688a9fd6558c448766f47457236c2331f93538992c6","kind":1,"pubkey":"e8ed3798c6ffebffa08501ac39e271662bfd160f688f94c45d692d8767dd345a","sig":"f76d5ecc8e7de688ac12b9d19edaacdcffb8f0c8fa2a44c00767363af3f04dbc069542ddc5d2f63c94cb5e6ce701589d538cf2db3b1f1211a96596fabb6ecafe","tags":[["e","5fcb28b72cadab2e4cbf7311f4acf5f6d1a99a1c2e642f6b6f0d5518a940f9ec","","mention"],["p","e8ed3798c6ffebffa08501ac39e271662bfd160f688f94c45d692d8767dd345a","","mention"],["t","bitcoin"],["t","bitcoin"]]}"#,
+            canonical_json: r#"[0,"e8ed3798c6ffebffa08501ac39e271662bfd160f688f94c45d692d8767dd345a",1703184271,1,[["e","5fcb28b72cadab2e4cbf7311f4acf5f6d1a99a1c2e642f6b6f0d5518a940f9ec","","mention"],["p","e8ed3798c6ffebffa08501ac39e271662bfd160f688f94c45d692d8767dd345a","","mention"],["t","bitcoin"],["t","bitcoin"]],"Think about this.\n\nThe most powerful centralized institutions in the world have been replaced by a protocol that protects the individual. #bitcoin\n\nDo you doubt that we can replace everything else?\n\nBullish on the future of humanity\nnostr:nevent1qqs9ljegkuk2m2ewfjlhxy054n6ld5dfngwzuep0ddhs64gc49q0nmqpzdmhxue69uhhyetvv9ukzcnvv5hx7un8qgsw3mfhnrr0l6ll5zzsrtpeufckv2lazc8k3ru5c3wkjtv8vlwngksrqsqqqqqpttgr27"]"#,
+            id: "38acf9b08d06859e49237688a9fd6558c448766f47457236c2331f93538992c6",
+        },
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::str::FromStr;
+
+    use super::*;
+    use crate::nips::nip19::{FromBech32, Nip19Profile, ToBech32};
+    use crate::{Event, EventId, JsonUtil, PublicKey, RelayUrl, SecretKey};
+
+    #[test]
+    #[cfg(all(feature = "std", feature = "nip04"))]
+    fn test_nip04_vectors() {
+        use crate::nips::nip04;
+        use crate::Keys;
+
+        for vector in nip04::VECTORS.iter() {
+            let sender = Keys::new(SecretKey::from_str(vector.sender_secret_key).unwrap());
+            let receiver = Keys::new(SecretKey::from_str(vector.receiver_secret_key).unwrap());
+
+            let plaintext: String =
+                nip04::decrypt(receiver.secret_key(), &sender.public_key(), vector.payload)
+                    .unwrap();
+            assert_eq!(plaintext, vector.plaintext);
+
+            let plaintext: String =
+                nip04::decrypt(sender.secret_key(), &receiver.public_key(), vector.payload)
+                    .unwrap();
+            assert_eq!(plaintext, vector.plaintext);
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", feature = "nip06"))]
+    fn test_nip06_vectors() {
+        use crate::nips::nip06::FromMnemonic;
+        use crate::Keys;
+
+        for vector in nip06::VECTORS.iter() {
+            let keys = Keys::from_mnemonic(vector.mnemonic, None).unwrap();
+            assert_eq!(keys.secret_key().to_secret_hex(), vector.secret_key);
+            assert_eq!(keys.public_key().to_hex(), vector.public_key);
+        }
+    }
+
+    #[test]
+    fn test_nip19_vectors() {
+        for vector in nip19::NPUB.iter() {
+            let public_key = PublicKey::from_bech32(vector.bech32).unwrap();
+            assert_eq!(public_key.to_hex(), vector.hex);
+            assert_eq!(public_key.to_bech32().unwrap(), vector.bech32);
+        }
+
+        for vector in nip19::NSEC.iter() {
+            let secret_key = SecretKey::from_bech32(vector.bech32).unwrap();
+            assert_eq!(secret_key.to_secret_hex(), vector.hex);
+            assert_eq!(secret_key.to_bech32().unwrap(), vector.bech32);
+        }
+
+        for vector in nip19::NOTE.iter() {
+            let id = EventId::from_bech32(vector.bech32).unwrap();
+            assert_eq!(id.to_hex(), vector.hex);
+            assert_eq!(id.to_bech32().unwrap(), vector.bech32);
+        }
+
+        for vector in nip19::NPROFILE.iter() {
+            let profile = Nip19Profile::from_bech32(vector.bech32).unwrap();
+            assert_eq!(profile.public_key.to_hex(), vector.public_key);
+            assert_eq!(
+                profile.relays,
+                vector
+                    .relays
+                    .iter()
+                    .map(|r| RelayUrl::parse(r).unwrap())
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_event_vectors() {
+        for vector in event::VECTORS.iter() {
+            let event = Event::from_json(vector.json).unwrap();
+            assert_eq!(event.canonical_json(), vector.canonical_json);
+            assert_eq!(
+                EventId::from_canonical_json(vector.canonical_json).to_hex(),
+                vector.id
+            );
+            assert_eq!(event.id.to_hex(), vector.id);
+            assert!(event.verify_id());
+
+            #[cfg(feature = "std")]
+            assert!(event.verify_signature());
+        }
+    }
+}
@@ -17,7 +17,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::event::tag::list::TagsIndexes;
 use crate::nips::nip01::Coordinate;
-use crate::{Event, EventId, JsonUtil, Kind, PublicKey, Timestamp};
+use crate::{Event, EventId, JsonUtil, Kind, PublicKey, TagKind, TagStandard, Timestamp};
 
 type GenericTags = BTreeMap<SingleLetterTag, BTreeSet<String>>;
 
@@ -354,6 +354,13 @@ pub struct Filter {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub limit: Option<usize>,
+    /// NIP26 delegators (`#delegation` tag query)
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    #[serde(rename = "#delegation")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub delegators: Option<BTreeSet<PublicKey>>,
     /// Generic tag queries
     #[serde(
         flatten,
@@ -413,6 +420,30 @@ impl Filter {
         self
     }
 
+    /// Add NIP26 delegator
+    ///
+    /// Match the events delegated by the public key (the delegation isn't verified).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    #[inline]
+    pub fn delegator(self, delegator: PublicKey) -> Self {
+        self.delegators([delegator])
+    }
+
+    /// Add NIP26 delegators
+    ///
+    /// Match the events delegated by the public keys (the delegation isn't verified).
+    ///
+    /// <https://github.com/nostr-protocol/nips/blob/master/26.md>
+    #[inline]
+    pub fn delegators<I>(mut self, delegators: I) -> Self
+    where
+        I: IntoIterator<Item = PublicKey>,
+    {
+        self.delegators = extend_or_collect(self.delegators, delegators);
+        self
+    }
+
     /// Remove authors
     #[inline]
     pub fn remove_authors<I>(mut self, authors: I) -> Self
@@ -762,6 +793,82 @@ impl Filter {
         self == &Filter::default()
     }
 
+    /// Try to merge with another filter
+    ///
+    /// Return the merged filter only if it matches exactly the union of the events matched by the two filters:
+    /// the filters must be equal except for one of `ids`, `authors`, `kinds` or a generic tag.
+    /// Filters with a `limit` are never merged.
+    pub fn try_merge(&self, other: &Filter) -> Option<Filter> {
+        if self.limit.is_some() || other.limit.is_some() {
+            return None;
+        }
+
+        if self == other {
+            return Some(self.clone());
+        }
+
+        if self.search != other.search
+            || self.since != other.since
+            || self.until != other.until
+            || self.delegators != other.delegators
+        {
+            return None;
+        }
+
+        let mut merged: Filter = self.clone();
+        let mut diff: usize = 0;
+
+        if self.ids != other.ids {
+            merged.ids = union_set(&self.ids, &other.ids);
+            diff += 1;
+        }
+
+        if self.authors != other.authors {
+            merged.authors = union_set(&self.authors, &other.authors);
+            diff += 1;
+        }
+
+        if self.kinds != other.kinds {
+            merged.kinds = union_set(&self.kinds, &other.kinds);
+            diff += 1;
+        }
+
+        let tags: BTreeSet<&SingleLetterTag> = self
+            .generic_tags
+            .keys()
+            .chain(other.generic_tags.keys())
+            .collect();
+
+        for tag in tags.into_iter() {
+            let a: Option<&BTreeSet<String>> = self.generic_tags.get(tag);
+            let b: Option<&BTreeSet<String>> = other.generic_tags.get(tag);
+
+            if a == b {
+                continue;
+            }
+
+            match (a, b) {
+                (Some(a), Some(b)) => {
+                    merged
+                        .generic_tags
+                        .insert(*tag, a.union(b).cloned().collect());
+                }
+                // A missing tag matches any value
+                _ => {
+                    merged.generic_tags.remove(tag);
+                }
+            }
+
+            diff += 1;
+        }
+
+        if diff <= 1 {
+            Some(merged)
+        } else {
+            None
+        }
+    }
+
     /// Extract **all** public keys (both from `authors` and `#p`)
     pub fn extract_public_keys(&self) -> BTreeSet<PublicKey> {
         let mut public_keys: BTreeSet<PublicKey> = BTreeSet::new();
@@ -791,6 +898,16 @@ impl Filter {
         })
     }
 
+    fn delegation_match(&self, event: &Event) -> bool {
+        self.delegators.as_ref().map_or(true, |delegators| {
+            delegators.is_empty()
+                || matches!(
+                    event.tags.find_standardized(TagKind::Delegation),
+                    Some(TagStandard::Delegation { delegator, .. }) if delegators.contains(delegator)
+                )
+        })
+    }
+
     fn tag_match(&self, event: &Event) -> bool {
         if self.generic_tags.is_empty() {
             return true;
@@ -840,6 +957,7 @@ impl Filter {
             && self.since.map_or(true, |t| event.created_at >= t)
             && self.until.map_or(true, |t| event.created_at <= t)
             && self.tag_match(event)
+            && self.delegation_match(event)
             && self.search_match(event)
     }
 }
@@ -848,6 +966,17 @@ impl JsonUtil for Filter {
     type Err = serde_json::Error;
 }
 
+/// Union of two sets, where `None` (or an empty set) matches any value
+fn union_set<T>(a: &Option<BTreeSet<T>>, b: &Option<BTreeSet<T>>) -> Option<BTreeSet<T>>
+where
+    T: Ord + Clone,
+{
+    match (a, b) {
+        (Some(a), Some(b)) if !a.is_empty() && !b.is_empty() => Some(a.union(b).cloned().collect()),
+        _ => None,
+    }
+}
+
 fn serialize_generic_tags<S>(generic_tags: &GenericTags, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -1227,6 +1356,92 @@ mod tests {
         let filter = Filter::new().search("Yuki kishi");
         assert!(filter.match_event(&event));
     }
+
+    #[test]
+    fn test_filter_try_merge() {
+        let pk1 =
+            PublicKey::from_hex("379e863e8357163b5bce5d2688dc4f1dcc2d505222fb8d74db600f30535dfdfe")
+                .unwrap();
+        let pk2 =
+            PublicKey::from_hex("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+
+        // Differ only by authors
+        let a = Filter::new().kind(Kind::TextNote).author(pk1);
+        let b = Filter::new().kind(Kind::TextNote).author(pk2);
+        assert_eq!(
+            a.try_merge(&b),
+            Some(Filter::new().kind(Kind::TextNote).authors([pk1, pk2]))
+        );
+
+        // Equal
+        assert_eq!(a.try_merge(&a), Some(a.clone()));
+
+        // Differ by authors and kinds: the union would match more events
+        let c = Filter::new().kind(Kind::Metadata).author(pk2);
+        assert_eq!(a.try_merge(&c), None);
+
+        // Missing field matches any value
+        let d = Filter::new().author(pk1);
+        assert_eq!(a.try_merge(&d), Some(d.clone()));
+
+        // Generic tags
+        let e = Filter::new().kind(Kind::TextNote).hashtag("nostr");
+        let f = Filter::new().kind(Kind::TextNote).hashtag("bitcoin");
+        assert_eq!(
+            e.try_merge(&f),
+            Some(
+                Filter::new()
+                    .kind(Kind::TextNote)
+                    .hashtags(["nostr", "bitcoin"])
+            )
+        );
+        assert_eq!(e.try_merge(&a), None);
+
+        // Limit and since
+        assert_eq!(a.clone().limit(10).try_merge(&b), None);
+        assert_eq!(a.clone().since(Timestamp::from(10)).try_merge(&b), None);
+
+        // Delegators
+        assert_eq!(a.clone().delegator(pk2).try_merge(&b), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_filter_delegation() {
+        use crate::nips::nip26::{Conditions, DelegationTag};
+        use crate::{EventBuilder, Keys, Tag};
+
+        let delegator = Keys::generate();
+        let delegatee = Keys::generate();
+
+        let tag = DelegationTag::new(&delegator, &delegatee.public_key(), Conditions::new());
+        let event = EventBuilder::text_note("Delegated")
+            .tag(Tag::from_standardized(TagStandard::Delegation {
+                delegator: tag.delegator_pubkey(),
+                conditions: tag.conditions(),
+                sig: tag.signature(),
+            }))
+            .sign_with_keys(&delegatee)
+            .unwrap();
+
+        let filter = Filter::new().delegator(delegator.public_key());
+        assert!(filter.match_event(&event));
+        assert!(!Filter::new()
+            .delegator(delegatee.public_key())
+            .match_event(&event));
+
+        // Not delegated
+        let event = EventBuilder::text_note("Hello")
+            .sign_with_keys(&delegator)
+            .unwrap();
+        assert!(!filter.match_event(&event));
+
+        // Serialization
+        let json = format!(r##"{{"#delegation":["{}"]}}"##, delegator.public_key());
+        assert_eq!(filter.as_json(), json);
+        assert_eq!(Filter::from_json(json).unwrap(), filter);
+    }
 }
 
 #[cfg(bench)]
@@ -11,6 +11,7 @@ pub mod contact;
 pub mod filter;
 pub mod image;
 pub mod metadata;
+pub mod preset;
 pub mod time;
 pub mod url;
 
@@ -18,5 +19,6 @@ pub use self::contact::*;
 pub use self::filter::*;
 pub use self::image::*;
 pub use self::metadata::*;
+pub use self::preset::*;
 pub use self::time::*;
 pub use self::url::*;
@@ -0,0 +1,409 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Filter presets
+//!
+//! Named filter templates with `{placeholder}` values, instantiated when subscribing.
+//!
+//! ```rust
+//! use std::collections::BTreeMap;
+//!
+//! use nostr::types::preset::{FilterPresets, FilterTemplate, PresetValue};
+//! use nostr::Kind;
+//!
+//! let mut presets = FilterPresets::new();
+//! presets.register(
+//!     "dms-for",
+//!     [FilterTemplate::new(r##"{"kinds":[4],"#p":["{pubkey}"],"since":{since}}"##)],
+//! );
+//!
+//! let mut values = BTreeMap::new();
+//! values.insert(
+//!     String::from("pubkey"),
+//!     PresetValue::from("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"),
+//! );
+//! values.insert(String::from("since"), PresetValue::from(1_700_000_000));
+//!
+//! let filters = presets.instantiate("dms-for", &values).unwrap();
+//! assert!(filters[0].kinds.as_ref().unwrap().contains(&Kind::EncryptedDirectMessage));
+//! ```
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::types::Filter;
+use crate::util::JsonUtil;
+use crate::{EventId, Kind, PublicKey, Timestamp};
+
+/// Filter preset error
+#[derive(Debug)]
+pub enum Error {
+    /// Json error
+    Json(serde_json::Error),
+    /// Preset not found
+    PresetNotFound(String),
+    /// Missing placeholder value
+    MissingValue(String),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "{e}"),
+            Self::PresetNotFound(name) => write!(f, "preset '{name}' not found"),
+            Self::MissingValue(name) => write!(f, "missing value for '{name}' placeholder"),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+/// Value of a placeholder
+///
+/// The values are always substituted as a single JSON value, so they can't alter the structure of the filter:
+/// inside a JSON string the values are escaped, outside a string the [`PresetValue::String`] values are quoted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PresetValue {
+    /// String (i.e. public key or event ID hex, hashtag, search query)
+    String(String),
+    /// Number (i.e. timestamp, kind, limit)
+    Number(u64),
+}
+
+impl From<String> for PresetValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for PresetValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<u64> for PresetValue {
+    fn from(value: u64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<Timestamp> for PresetValue {
+    fn from(timestamp: Timestamp) -> Self {
+        Self::Number(timestamp.as_u64())
+    }
+}
+
+impl From<Kind> for PresetValue {
+    fn from(kind: Kind) -> Self {
+        Self::Number(kind.as_u16() as u64)
+    }
+}
+
+impl From<PublicKey> for PresetValue {
+    fn from(public_key: PublicKey) -> Self {
+        Self::String(public_key.to_hex())
+    }
+}
+
+impl From<EventId> for PresetValue {
+    fn from(id: EventId) -> Self {
+        Self::String(id.to_hex())
+    }
+}
+
+/// Filter template
+///
+/// JSON filter with `{placeholder}` values (ASCII alphanumeric chars and `_`).
+/// Check [`PresetValue`] to learn how the values are substituted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FilterTemplate {
+    template: String,
+}
+
+impl From<Filter> for FilterTemplate {
+    fn from(filter: Filter) -> Self {
+        Self {
+            template: filter.as_json(),
+        }
+    }
+}
+
+impl FilterTemplate {
+    /// New filter template
+    #[inline]
+    pub fn new<S>(template: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    /// Get the template
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Get the placeholder names
+    pub fn placeholders(&self) -> BTreeSet<&str> {
+        let mut placeholders: BTreeSet<&str> = BTreeSet::new();
+        let mut rest: &str = &self.template;
+
+        while let Some(start) = rest.find('{') {
+            rest = &rest[start + 1..];
+            if let Some(name) = placeholder_name(rest) {
+                placeholders.insert(name);
+            }
+        }
+
+        placeholders
+    }
+
+    /// Instantiate the filter
+    pub fn instantiate(&self, values: &BTreeMap<String, PresetValue>) -> Result<Filter, Error> {
+        let template: &str = &self.template;
+        let mut json: String = String::with_capacity(template.len());
+
+        // Track if the current char is inside a JSON string
+        let mut in_string: bool = false;
+        let mut escaped: bool = false;
+
+        let mut chars = template.char_indices();
+        while let Some((index, c)) = chars.next() {
+            if c == '{' && !escaped {
+                if let Some(name) = placeholder_name(&template[index + 1..]) {
+                    let value: &PresetValue = values
+                        .get(name)
+                        .ok_or_else(|| Error::MissingValue(name.to_string()))?;
+
+                    match (value, in_string) {
+                        (PresetValue::Number(n), ..) => json.push_str(&n.to_string()),
+                        (PresetValue::String(s), true) => {
+                            // Escape value (without the surrounding quotes)
+                            let escaped: String = serde_json::to_string(s)?;
+                            json.push_str(&escaped[1..escaped.len() - 1]);
+                        }
+                        (PresetValue::String(s), false) => {
+                            json.push_str(&serde_json::to_string(s)?)
+                        }
+                    }
+
+                    // Skip the name and the closing brace
+                    for _ in 0..=name.len() {
+                        chars.next();
+                    }
+                    continue;
+                }
+            }
+
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == '"' {
+                    in_string = false;
+                }
+            } else if c == '"' {
+                in_string = true;
+            }
+
+            json.push(c);
+        }
+
+        Ok(Filter::from_json(json)?)
+    }
+}
+
+/// Get the placeholder name at the beginning of `s` (after the opening brace)
+fn placeholder_name(s: &str) -> Option<&str> {
+    let end: usize = s.find('}')?;
+    let name: &str = &s[..end];
+
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Filter presets
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterPresets {
+    presets: BTreeMap<String, Vec<FilterTemplate>>,
+}
+
+impl FilterPresets {
+    /// New empty registry
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a preset
+    ///
+    /// Replace the preset with the same name, if any.
+    pub fn register<S, I>(&mut self, name: S, templates: I)
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = FilterTemplate>,
+    {
+        self.presets
+            .insert(name.into(), templates.into_iter().collect());
+    }
+
+    /// Remove a preset
+    #[inline]
+    pub fn remove(&mut self, name: &str) -> Option<Vec<FilterTemplate>> {
+        self.presets.remove(name)
+    }
+
+    /// Get preset templates
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&[FilterTemplate]> {
+        self.presets.get(name).map(|t| t.as_slice())
+    }
+
+    /// Get preset names
+    #[inline]
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.keys().map(|n| n.as_str())
+    }
+
+    /// Get the placeholder names used by a preset
+    pub fn placeholders(&self, name: &str) -> Result<BTreeSet<&str>, Error> {
+        let templates = self
+            .presets
+            .get(name)
+            .ok_or_else(|| Error::PresetNotFound(name.to_string()))?;
+        Ok(templates.iter().flat_map(|t| t.placeholders()).collect())
+    }
+
+    /// Instantiate the filters of a preset
+    pub fn instantiate(
+        &self,
+        name: &str,
+        values: &BTreeMap<String, PresetValue>,
+    ) -> Result<Vec<Filter>, Error> {
+        let templates = self
+            .presets
+            .get(name)
+            .ok_or_else(|| Error::PresetNotFound(name.to_string()))?;
+        templates.iter().map(|t| t.instantiate(values)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Kind, PublicKey, Timestamp};
+
+    const PUBKEY: &str = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    #[test]
+    fn test_template_placeholders() {
+        let template = FilterTemplate::new(r##"{"kinds":[4],"#p":["{pubkey}"],"since":{since}}"##);
+        let placeholders: Vec<&str> = template.placeholders().into_iter().collect();
+        assert_eq!(placeholders, vec!["pubkey", "since"]);
+
+        let template = FilterTemplate::from(Filter::new().kind(Kind::TextNote));
+        assert!(template.placeholders().is_empty());
+    }
+
+    #[test]
+    fn test_instantiate_preset() {
+        let mut presets = FilterPresets::new();
+        presets.register(
+            "dms-for",
+            [
+                FilterTemplate::new(r##"{"kinds":[4],"#p":["{pubkey}"],"since":{since}}"##),
+                FilterTemplate::new(r#"{"kinds":[4],"authors":["{pubkey}"]}"#),
+            ],
+        );
+
+        let mut values = BTreeMap::new();
+        values.insert(String::from("pubkey"), PresetValue::from(PUBKEY));
+        values.insert(String::from("since"), PresetValue::from(1_700_000_000));
+
+        let public_key = PublicKey::from_hex(PUBKEY).unwrap();
+        let filters = presets.instantiate("dms-for", &values).unwrap();
+        assert_eq!(
+            filters,
+            vec![
+                Filter::new()
+                    .kind(Kind::EncryptedDirectMessage)
+                    .pubkey(public_key)
+                    .since(Timestamp::from(1_700_000_000)),
+                Filter::new()
+                    .kind(Kind::EncryptedDirectMessage)
+                    .author(public_key),
+            ]
+        );
+
+        values.remove("since");
+        assert!(matches!(
+            presets.instantiate("dms-for", &values).unwrap_err(),
+            Error::MissingValue(name) if name == "since"
+        ));
+        assert!(matches!(
+            presets.instantiate("unknown", &values).unwrap_err(),
+            Error::PresetNotFound(..)
+        ));
+    }
+
+    #[test]
+    fn test_instantiate_escaping() {
+        let template = FilterTemplate::new(r#"{"search":"{query}"}"#);
+
+        let mut values = BTreeMap::new();
+        values.insert(String::from("query"), PresetValue::from("say \"hi\""));
+
+        let filter = template.instantiate(&values).unwrap();
+        assert_eq!(filter.search.as_deref(), Some("say \"hi\""));
+    }
+
+    #[test]
+    fn test_instantiate_injection() {
+        // Unquoted placeholder: the string is quoted, not injected as JSON
+        let template = FilterTemplate::new(r#"{"kinds":[1],"limit":{limit}}"#);
+
+        let mut values = BTreeMap::new();
+        values.insert(
+            String::from("limit"),
+            PresetValue::from(r#"1,"authors":["evil"]"#),
+        );
+        assert!(matches!(
+            template.instantiate(&values).unwrap_err(),
+            Error::Json(..)
+        ));
+
+        values.insert(String::from("limit"), PresetValue::from(10));
+        let filter = template.instantiate(&values).unwrap();
+        assert_eq!(filter, Filter::new().kind(Kind::TextNote).limit(10));
+
+        // Unquoted string placeholder
+        let template = FilterTemplate::new(r#"{"search":{query}}"#);
+        values.insert(String::from("query"), PresetValue::from(r#"a","limit":1"#));
+        let filter = template.instantiate(&values).unwrap();
+        assert_eq!(filter.search.as_deref(), Some(r#"a","limit":1"#));
+        assert_eq!(filter.limit, None);
+
+        // A placeholder after an escaped quote is still inside the string
+        let template = FilterTemplate::new(r#"{"search":"\"{query}\""}"#);
+        let filter = template.instantiate(&values).unwrap();
+        assert_eq!(filter.search.as_deref(), Some(r#""a","limit":1""#));
+    }
+}
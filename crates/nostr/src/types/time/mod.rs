@@ -14,8 +14,12 @@ use core::time::Duration;
 use bitcoin::secp256k1::rand::rngs::OsRng;
 use bitcoin::secp256k1::rand::Rng;
 
+mod provider;
 mod supplier;
 
+pub use self::provider::{FixedTimeProvider, IntoTimeProvider, TimeProvider};
+#[cfg(feature = "std")]
+pub use self::provider::{OffsetTimeProvider, SystemTimeProvider};
 pub use self::supplier::TimeSupplier;
 #[cfg(feature = "std")]
 pub use self::supplier::{Instant, SystemTime, UNIX_EPOCH};
@@ -0,0 +1,175 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Time provider
+
+use alloc::sync::Arc;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicI64;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+use super::Timestamp;
+use crate::signer::{SendOutsideWasm, SyncOutsideWasm};
+
+/// Time provider
+///
+/// Source of the current time used to compose the `created_at` of the events.
+///
+/// Unlike [`TimeSupplier`](super::TimeSupplier), it's object safe:
+/// can be shared (i.e. `Arc<dyn TimeProvider>`) to freeze the time in tests or to correct the device clock.
+pub trait TimeProvider: fmt::Debug + SendOutsideWasm + SyncOutsideWasm {
+    /// Current UNIX timestamp
+    fn now(&self) -> Timestamp;
+}
+
+impl<T> TimeProvider for Arc<T>
+where
+    T: TimeProvider + ?Sized,
+{
+    #[inline]
+    fn now(&self) -> Timestamp {
+        self.as_ref().now()
+    }
+}
+
+#[doc(hidden)]
+pub trait IntoTimeProvider {
+    fn into_time_provider(self) -> Arc<dyn TimeProvider>;
+}
+
+impl<T> IntoTimeProvider for T
+where
+    T: TimeProvider + 'static,
+{
+    fn into_time_provider(self) -> Arc<dyn TimeProvider> {
+        Arc::new(self)
+    }
+}
+
+/// System clock
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemTimeProvider;
+
+#[cfg(feature = "std")]
+impl TimeProvider for SystemTimeProvider {
+    #[inline]
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+/// Frozen clock
+///
+/// Return always the same timestamp, until moved with [`FixedTimeProvider::set`] or [`FixedTimeProvider::advance`].
+/// Useful for deterministic tests.
+#[derive(Debug, Default)]
+pub struct FixedTimeProvider {
+    secs: AtomicU64,
+}
+
+impl FixedTimeProvider {
+    /// Freeze the clock at `timestamp`
+    #[inline]
+    pub fn new(timestamp: Timestamp) -> Self {
+        Self {
+            secs: AtomicU64::new(timestamp.as_u64()),
+        }
+    }
+
+    /// Move the clock to `timestamp`
+    #[inline]
+    pub fn set(&self, timestamp: Timestamp) {
+        self.secs.store(timestamp.as_u64(), Ordering::SeqCst);
+    }
+
+    /// Move the clock forward
+    #[inline]
+    pub fn advance(&self, duration: Duration) {
+        self.secs.fetch_add(duration.as_secs(), Ordering::SeqCst);
+    }
+}
+
+impl TimeProvider for FixedTimeProvider {
+    #[inline]
+    fn now(&self) -> Timestamp {
+        Timestamp::from_secs(self.secs.load(Ordering::SeqCst))
+    }
+}
+
+/// System clock corrected by an offset
+///
+/// Useful when the device clock is known to be wrong (i.e. the skew has been measured against the relays).
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct OffsetTimeProvider {
+    offset: AtomicI64,
+}
+
+#[cfg(feature = "std")]
+impl OffsetTimeProvider {
+    /// New provider with the offset (seconds) to add to the system clock
+    #[inline]
+    pub fn new(offset: i64) -> Self {
+        Self {
+            offset: AtomicI64::new(offset),
+        }
+    }
+
+    /// Get the offset (seconds)
+    #[inline]
+    pub fn offset(&self) -> i64 {
+        self.offset.load(Ordering::SeqCst)
+    }
+
+    /// Update the offset (seconds)
+    #[inline]
+    pub fn set_offset(&self, offset: i64) {
+        self.offset.store(offset, Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "std")]
+impl TimeProvider for OffsetTimeProvider {
+    fn now(&self) -> Timestamp {
+        let now: u64 = Timestamp::now().as_u64();
+        let offset: i64 = self.offset();
+        let secs: u64 = if offset >= 0 {
+            now.saturating_add(offset.unsigned_abs())
+        } else {
+            now.saturating_sub(offset.unsigned_abs())
+        };
+        Timestamp::from_secs(secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_time_provider() {
+        let provider = FixedTimeProvider::new(Timestamp::from_secs(1_700_000_000));
+        assert_eq!(provider.now(), Timestamp::from_secs(1_700_000_000));
+
+        provider.advance(Duration::from_secs(60));
+        assert_eq!(provider.now(), Timestamp::from_secs(1_700_000_060));
+
+        provider.set(Timestamp::from_secs(10));
+        assert_eq!(provider.now(), Timestamp::from_secs(10));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_offset_time_provider() {
+        let provider = OffsetTimeProvider::new(-3600);
+        let diff: u64 = Timestamp::now().as_u64() - provider.now().as_u64();
+        assert!((3599..=3601).contains(&diff));
+
+        provider.set_offset(i64::MIN);
+        assert_eq!(provider.now(), Timestamp::zero());
+    }
+}
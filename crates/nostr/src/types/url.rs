@@ -5,8 +5,10 @@
 //! Urls
 
 use alloc::string::String;
+use core::cmp::Ordering;
 use core::convert::Infallible;
 use core::fmt;
+use core::hash::{Hash, Hasher};
 use core::str::FromStr;
 #[cfg(feature = "std")]
 use std::net::IpAddr; // TODO: use `core::net` when MSRV will be at 1.77.0
@@ -48,12 +50,47 @@ impl From<ParseError> for Error {
 }
 
 /// Relay URL
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// The URL is normalized when parsed: the scheme and the host are lowercase,
+/// the default port is removed, as well as the fragment and the empty query.
+///
+/// The trailing slash of the root path is kept in the serialization (check [`RelayUrl::as_str`])
+/// but is ignored in the comparisons: `wss://relay.damus.io` and `wss://relay.damus.io/` are the same relay.
+#[derive(Clone)]
 pub struct RelayUrl {
     url: Url,
     has_trailing_slash: bool,
 }
 
+impl PartialEq for RelayUrl {
+    fn eq(&self, other: &Self) -> bool {
+        self.url == other.url
+    }
+}
+
+impl Eq for RelayUrl {}
+
+impl PartialOrd for RelayUrl {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RelayUrl {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.url.cmp(&other.url)
+    }
+}
+
+impl Hash for RelayUrl {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        self.url.hash(state);
+    }
+}
+
 impl fmt::Debug for RelayUrl {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let url: &str = self.as_str();
@@ -70,20 +107,30 @@ impl RelayUrl {
             return Err(Error::MultipleSchemeSeparators);
         }
 
-        // Check if has trailing slash
-        let has_trailing_slash: bool = url.ends_with('/');
+        // Check if the path has trailing slash (ignoring the query and the fragment)
+        let path_end: usize = url
+            .find(|c: char| c == '?' || c == '#')
+            .unwrap_or(url.len());
+        let has_trailing_slash: bool = url[..path_end].ends_with('/');
 
-        // Parse URL
-        let url: Url = Url::parse(url)?;
+        // Parse URL (the host is lowercased and the default port removed by the parser)
+        let mut url: Url = Url::parse(url)?;
 
         // Check scheme
-        match url.scheme() {
-            "ws" | "wss" => Ok(Self {
-                url,
-                has_trailing_slash,
-            }),
-            _ => Err(Error::UnsupportedScheme),
+        if !matches!(url.scheme(), "ws" | "wss") {
+            return Err(Error::UnsupportedScheme);
+        }
+
+        // Remove fragment and empty query
+        url.set_fragment(None);
+        if url.query() == Some("") {
+            url.set_query(None);
         }
+
+        Ok(Self {
+            url,
+            has_trailing_slash,
+        })
     }
 
     /// Check if the host is a local network address.
@@ -120,9 +167,13 @@ impl RelayUrl {
 
     /// Return the serialization of this relay URL without the trailing slash.
     ///
-    /// This method will always remove the trailing slash.
+    /// This method will always remove the trailing slash of the path, if there is no query.
     #[inline]
     pub fn as_str_without_trailing_slash(&self) -> &str {
+        if self.url.query().is_some() {
+            return self.url.as_str();
+        }
+
         self.url.as_str().trim_end_matches('/')
     }
 
@@ -307,6 +358,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_relay_url_normalization() {
+        let url = RelayUrl::parse("wss://relay.damus.io").unwrap();
+        let url_with_slash = RelayUrl::parse("wss://relay.damus.io/").unwrap();
+        assert_eq!(url, url_with_slash);
+
+        let mut set = alloc::collections::BTreeSet::new();
+        set.insert(url.clone());
+        assert!(!set.insert(url_with_slash));
+
+        let relay_url = RelayUrl::parse("WSS://Relay.Damus.IO:443").unwrap();
+        assert_eq!(relay_url, url);
+        assert_eq!(relay_url.as_str(), "wss://relay.damus.io");
+
+        let relay_url = RelayUrl::parse("ws://example.com:80/?#fragment").unwrap();
+        assert_eq!(relay_url.as_str(), "ws://example.com/");
+
+        // Same serialization, with or without query and fragment
+        for (a, b) in [
+            ("ws://example.com", "ws://example.com#fragment"),
+            ("ws://example.com", "ws://example.com?"),
+            ("ws://example.com/", "ws://example.com/#fragment"),
+            ("ws://example.com/", "ws://example.com/?#"),
+            ("ws://example.com/path/", "ws://example.com/path/#fragment"),
+        ] {
+            let a = RelayUrl::parse(a).unwrap();
+            let b = RelayUrl::parse(b).unwrap();
+            assert_eq!(a, b);
+            assert_eq!(a.as_str(), b.as_str());
+        }
+
+        // The slashes of the query are kept
+        let relay_url = RelayUrl::parse("ws://example.com/?path=/").unwrap();
+        assert_eq!(relay_url.as_str(), "ws://example.com/?path=/");
+        let relay_url = RelayUrl::parse("ws://example.com?path=/").unwrap();
+        assert_eq!(relay_url.as_str(), "ws://example.com/?path=/");
+        assert_eq!(
+            relay_url.as_str_without_trailing_slash(),
+            "ws://example.com/?path=/"
+        );
+
+        let relay_url = RelayUrl::parse("ws://example.com:8080/path").unwrap();
+        assert_eq!(relay_url.as_str(), "ws://example.com:8080/path");
+        assert_ne!(relay_url, RelayUrl::parse("ws://example.com:8080").unwrap());
+    }
+
     #[test]
     fn test_relay_url_from_str() {
         let relay_url: Result<RelayUrl, _> = "ws://example.com".parse();
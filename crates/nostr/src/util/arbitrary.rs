@@ -0,0 +1,217 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! [`Arbitrary`] implementations
+//!
+//! Generate realistic nostr data (valid keys, signed events, common kinds and tags)
+//! for property-based testing and fuzzing.
+//!
+//! To use them with `proptest`, wrap them with the `proptest-arbitrary-interop` crate.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+
+use crate::{
+    Event, EventBuilder, EventId, Filter, Keys, Kind, PublicKey, SecretKey, SingleLetterTag, Tag,
+    TagKind, Timestamp, UnsignedEvent,
+};
+
+/// 2020-01-01T00:00:00Z
+const MIN_TIMESTAMP: u64 = 1_577_836_800;
+/// 2030-01-01T00:00:00Z
+const MAX_TIMESTAMP: u64 = 1_893_456_000;
+const MAX_ITEMS: usize = 4;
+
+const COMMON_KINDS: [Kind; 10] = [
+    Kind::Metadata,
+    Kind::TextNote,
+    Kind::ContactList,
+    Kind::EncryptedDirectMessage,
+    Kind::EventDeletion,
+    Kind::Repost,
+    Kind::Reaction,
+    Kind::ZapReceipt,
+    Kind::RelayList,
+    Kind::LongFormTextNote,
+];
+
+fn small_vec<'a, T>(u: &mut Unstructured<'a>) -> Result<Vec<T>>
+where
+    T: Arbitrary<'a>,
+{
+    let len: usize = u.int_in_range(0..=MAX_ITEMS)?;
+    (0..len).map(|_| T::arbitrary(u)).collect()
+}
+
+impl<'a> Arbitrary<'a> for Timestamp {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let secs: u64 = u.int_in_range(MIN_TIMESTAMP..=MAX_TIMESTAMP)?;
+        Ok(Self::from(secs))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Kind {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        if u.ratio(3, 4)? {
+            Ok(*u.choose(&COMMON_KINDS)?)
+        } else {
+            Ok(Self::from(u16::arbitrary(u)?))
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for EventId {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self::from_byte_array(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for SecretKey {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut bytes: [u8; 32] = u.arbitrary()?;
+
+        // Keep the key lower than the curve order and not zero
+        bytes[0] &= 0x7f;
+        bytes[31] |= 0x01;
+
+        Self::from_slice(&bytes).map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Keys {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self::new(SecretKey::arbitrary(u)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for PublicKey {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Keys::arbitrary(u)?.public_key())
+    }
+}
+
+impl<'a> Arbitrary<'a> for SingleLetterTag {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let c: char = u.choose(&('a'..='z').collect::<Vec<char>>())?.to_owned();
+        let tag: SingleLetterTag = Self::from_char(c).map_err(|_| Error::IncorrectFormat)?;
+        if u.ratio(1, 8)? {
+            Ok(Self::uppercase(tag.character))
+        } else {
+            Ok(tag)
+        }
+    }
+}
+
+impl<'a> Arbitrary<'a> for Tag {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=5)? {
+            0 => Self::event(EventId::arbitrary(u)?),
+            1 => Self::public_key(PublicKey::arbitrary(u)?),
+            2 => Self::hashtag(String::arbitrary(u)?),
+            3 => Self::identifier(String::arbitrary(u)?),
+            4 => Self::expiration(Timestamp::arbitrary(u)?),
+            _ => {
+                let kind: String = String::arbitrary(u)?;
+                let values: Vec<String> = small_vec(u)?;
+                Self::custom(TagKind::from(kind.as_str()), values)
+            }
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Filter {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut filter: Filter = Filter::new();
+
+        if u.arbitrary()? {
+            filter = filter.ids(small_vec::<EventId>(u)?);
+        }
+
+        if u.arbitrary()? {
+            filter = filter.authors(small_vec::<PublicKey>(u)?);
+        }
+
+        if u.arbitrary()? {
+            filter = filter.kinds(small_vec::<Kind>(u)?);
+        }
+
+        if u.arbitrary()? {
+            filter = filter.custom_tag(SingleLetterTag::arbitrary(u)?, small_vec::<String>(u)?);
+        }
+
+        if u.arbitrary()? {
+            filter = filter.since(Timestamp::arbitrary(u)?);
+        }
+
+        if u.arbitrary()? {
+            filter = filter.until(Timestamp::arbitrary(u)?);
+        }
+
+        if u.arbitrary()? {
+            filter = filter.limit(u.int_in_range(0..=500)?);
+        }
+
+        Ok(filter)
+    }
+}
+
+fn arbitrary_builder(u: &mut Unstructured<'_>) -> Result<EventBuilder> {
+    Ok(
+        EventBuilder::new(Kind::arbitrary(u)?, String::arbitrary(u)?)
+            .tags(small_vec::<Tag>(u)?)
+            .custom_created_at(Timestamp::arbitrary(u)?),
+    )
+}
+
+impl<'a> Arbitrary<'a> for UnsignedEvent {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let public_key: PublicKey = PublicKey::arbitrary(u)?;
+        let mut unsigned: UnsignedEvent = arbitrary_builder(u)?.build(public_key);
+        unsigned.ensure_id();
+        Ok(unsigned)
+    }
+}
+
+/// Signed event (valid ID and signature)
+impl<'a> Arbitrary<'a> for Event {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let keys: Keys = Keys::arbitrary(u)?;
+        arbitrary_builder(u)?
+            .sign_with_keys(&keys)
+            .map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arbitrary_event() {
+        let data: Vec<u8> = (0..4096).map(|i| (i * 7 + 3) as u8).collect();
+        let mut u = Unstructured::new(&data);
+
+        for _ in 0..8 {
+            let event = Event::arbitrary(&mut u).unwrap();
+            assert!(event.verify().is_ok());
+
+            let filter = Filter::new()
+                .author(event.pubkey)
+                .kind(event.kind)
+                .since(event.created_at);
+            assert!(filter.match_event(&event));
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_from_exhausted_data() {
+        // Must still produce valid data
+        let mut u = Unstructured::new(&[]);
+        let event = Event::arbitrary(&mut u).unwrap();
+        assert!(event.verify().is_ok());
+        Filter::arbitrary(&mut u).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Binary formats
+//!
+//! Serialize events, filters and messages as CBOR and/or MessagePack.
+//!
+//! The binary forms carry the same data model of the JSON ones:
+//! they can be used for compact local storage or for experimental binary transports,
+//! but they aren't part of the nostr protocol.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::JsonUtil;
+
+/// Binary format error
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// CBOR error
+    #[cfg(feature = "cbor")]
+    Cbor(String),
+    /// MessagePack error
+    #[cfg(feature = "msgpack")]
+    MsgPack(String),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "cbor")]
+            Self::Cbor(e) => write!(f, "CBOR: {e}"),
+            #[cfg(feature = "msgpack")]
+            Self::MsgPack(e) => write!(f, "MessagePack: {e}"),
+        }
+    }
+}
+
+/// Binary util
+///
+/// Automatically implemented for every type that implements [`JsonUtil`].
+pub trait BinaryUtil: JsonUtil {
+    /// Deserialize CBOR
+    #[cfg(feature = "cbor")]
+    fn from_cbor<T>(cbor: T) -> Result<Self, Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        ciborium::from_reader(cbor.as_ref()).map_err(|e| Error::Cbor(e.to_string()))
+    }
+
+    /// Serialize as CBOR
+    #[cfg(feature = "cbor")]
+    fn try_as_cbor(&self) -> Result<Vec<u8>, Error> {
+        let mut buf: Vec<u8> = Vec::new();
+        ciborium::into_writer(self, &mut buf).map_err(|e| Error::Cbor(e.to_string()))?;
+        Ok(buf)
+    }
+
+    /// Deserialize MessagePack
+    #[cfg(feature = "msgpack")]
+    fn from_msgpack<T>(msgpack: T) -> Result<Self, Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        rmp_serde::from_slice(msgpack.as_ref()).map_err(|e| Error::MsgPack(e.to_string()))
+    }
+
+    /// Serialize as MessagePack
+    ///
+    /// Structs are encoded as maps, to keep the same layout of the JSON form.
+    #[cfg(feature = "msgpack")]
+    fn try_as_msgpack(&self) -> Result<Vec<u8>, Error> {
+        rmp_serde::to_vec_named(self).map_err(|e| Error::MsgPack(e.to_string()))
+    }
+}
+
+impl<T> BinaryUtil for T where T: JsonUtil {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientMessage, Event, Filter, Kind, RelayMessage, SubscriptionId, Timestamp};
+
+    const EVENT: &str = r#"{"content":"uRuvYr585B80L6rSJiHocw==?iv=oh6LVqdsYYol3JfFnXTbPA==","created_at":1640839235,"id":"2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45","kind":4,"pubkey":"f86c44a2de95d9149b51c6a29afeabba264c18e2fa7c49de93424a0c56947785","sig":"a5d9290ef9659083c490b303eb7ee41356d8778ff19f2f91776c8dc4443388a64ffcf336e61af4c25c05ac3ae952d1ced889ed655b67790891222aaa15b99fdd","tags":[["p","13adc511de7e1cfcf1c6b7f6365fb5a03442d7bcacf565ea57fa7770912c023d"]]}"#;
+
+    fn messages() -> Vec<ClientMessage> {
+        let event = Event::from_json(EVENT).unwrap();
+        let filter = Filter::new()
+            .kinds([Kind::TextNote, Kind::Metadata])
+            .author(event.pubkey)
+            .hashtag("nostr")
+            .since(Timestamp::from(1_700_000_000))
+            .limit(10);
+        vec![
+            ClientMessage::event(event),
+            ClientMessage::req(SubscriptionId::new("test"), vec![filter]),
+            ClientMessage::close(SubscriptionId::new("test")),
+        ]
+    }
+
+    #[test]
+    #[cfg(feature = "cbor")]
+    fn test_cbor_roundtrip() {
+        let event = Event::from_json(EVENT).unwrap();
+        let cbor = event.try_as_cbor().unwrap();
+        let decoded = Event::from_cbor(&cbor).unwrap();
+        assert_eq!(decoded, event);
+        assert_eq!(decoded.as_json(), EVENT);
+        assert!(decoded.verify().is_ok());
+
+        for msg in messages() {
+            let cbor = msg.try_as_cbor().unwrap();
+            let decoded = ClientMessage::from_cbor(cbor).unwrap();
+            assert_eq!(decoded.as_json(), msg.as_json());
+        }
+
+        let msg = RelayMessage::eose(SubscriptionId::new("test"));
+        let cbor = msg.try_as_cbor().unwrap();
+        assert_eq!(RelayMessage::from_cbor(cbor).unwrap(), msg);
+
+        assert!(Event::from_cbor([0xff, 0x00]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "msgpack")]
+    fn test_msgpack_roundtrip() {
+        let event = Event::from_json(EVENT).unwrap();
+        let msgpack = event.try_as_msgpack().unwrap();
+        let decoded = Event::from_msgpack(&msgpack).unwrap();
+        assert_eq!(decoded, event);
+        assert_eq!(decoded.as_json(), EVENT);
+        assert!(decoded.verify().is_ok());
+
+        for msg in messages() {
+            let msgpack = msg.try_as_msgpack().unwrap();
+            let decoded = ClientMessage::from_msgpack(msgpack).unwrap();
+            assert_eq!(decoded.as_json(), msg.as_json());
+        }
+
+        let msg = RelayMessage::eose(SubscriptionId::new("test"));
+        let msgpack = msg.try_as_msgpack().unwrap();
+        assert_eq!(RelayMessage::from_msgpack(msgpack).unwrap(), msg);
+
+        assert!(Event::from_msgpack([0xc1]).is_err());
+    }
+}
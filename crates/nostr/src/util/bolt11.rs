@@ -0,0 +1,348 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! BOLT11 invoice
+//!
+//! Lightweight parser that extracts only the fields needed to validate the zaps:
+//! the amount, the payment hash and the description (hash).
+//!
+//! **The signature of the invoice is NOT verified!** Use a lightning library to pay the invoices.
+//!
+//! <https://github.com/lightning/bolts/blob/master/11-payment-encoding.md>
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin::hashes::Hash;
+
+use crate::Timestamp;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_LEN: usize = 6;
+const TIMESTAMP_LEN: usize = 7;
+const SIGNATURE_LEN: usize = 104;
+const HASH_LEN: usize = 52;
+const DEFAULT_EXPIRY: u64 = 3600;
+
+// Tagged field types
+const TAG_PAYMENT_HASH: u8 = 1; // p
+const TAG_DESCRIPTION: u8 = 13; // d
+const TAG_DESCRIPTION_HASH: u8 = 23; // h
+const TAG_EXPIRY: u8 = 6; // x
+
+/// BOLT11 error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Mixed upper and lower case
+    MixedCase,
+    /// Missing `1` separator
+    MissingSeparator,
+    /// Invalid character
+    InvalidChar(char),
+    /// Invalid checksum
+    InvalidChecksum,
+    /// Invalid prefix (must start with `ln`)
+    InvalidPrefix,
+    /// Invalid amount
+    InvalidAmount,
+    /// Invoice too short
+    TooShort,
+    /// Invalid tagged field
+    InvalidField,
+    /// Missing payment hash
+    MissingPaymentHash,
+    /// Description isn't valid UTF-8
+    InvalidDescription,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MixedCase => write!(f, "mixed case"),
+            Self::MissingSeparator => write!(f, "missing separator"),
+            Self::InvalidChar(c) => write!(f, "invalid char: {c}"),
+            Self::InvalidChecksum => write!(f, "invalid checksum"),
+            Self::InvalidPrefix => write!(f, "invalid prefix"),
+            Self::InvalidAmount => write!(f, "invalid amount"),
+            Self::TooShort => write!(f, "invoice too short"),
+            Self::InvalidField => write!(f, "invalid tagged field"),
+            Self::MissingPaymentHash => write!(f, "missing payment hash"),
+            Self::InvalidDescription => write!(f, "invalid description"),
+        }
+    }
+}
+
+/// BOLT11 invoice
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bolt11Invoice {
+    /// Currency prefix (i.e. `bc` for mainnet, `tb` for testnet, `bcrt` for regtest)
+    pub currency: String,
+    /// Amount (millisats)
+    ///
+    /// `None` if the invoice has no amount.
+    pub amount_msats: Option<u64>,
+    /// Creation timestamp
+    pub timestamp: Timestamp,
+    /// Payment hash
+    pub payment_hash: Sha256Hash,
+    /// Description
+    pub description: Option<String>,
+    /// Description hash
+    pub description_hash: Option<Sha256Hash>,
+    /// Expiry (seconds)
+    pub expiry: Option<u64>,
+}
+
+impl Bolt11Invoice {
+    /// Parse BOLT11 invoice (the `lightning:` prefix is removed, if any)
+    pub fn parse(invoice: &str) -> Result<Self, Error> {
+        let invoice: &str = invoice.trim();
+        let invoice: &str = invoice
+            .strip_prefix("lightning:")
+            .or_else(|| invoice.strip_prefix("LIGHTNING:"))
+            .unwrap_or(invoice);
+
+        let has_lower: bool = invoice.bytes().any(|b| b.is_ascii_lowercase());
+        let has_upper: bool = invoice.bytes().any(|b| b.is_ascii_uppercase());
+        if has_lower && has_upper {
+            return Err(Error::MixedCase);
+        }
+
+        let invoice: String = invoice.to_ascii_lowercase();
+        let (hrp, data) = invoice.rsplit_once('1').ok_or(Error::MissingSeparator)?;
+
+        let data: Vec<u8> = data
+            .chars()
+            .map(|c| {
+                CHARSET
+                    .iter()
+                    .position(|x| *x as char == c)
+                    .map(|pos| pos as u8)
+                    .ok_or(Error::InvalidChar(c))
+            })
+            .collect::<Result<_, _>>()?;
+
+        if data.len() < CHECKSUM_LEN + TIMESTAMP_LEN + SIGNATURE_LEN {
+            return Err(Error::TooShort);
+        }
+
+        if !verify_checksum(hrp, &data) {
+            return Err(Error::InvalidChecksum);
+        }
+
+        let (currency, amount_msats) = parse_hrp(hrp)?;
+
+        // Remove checksum and signature
+        let data: &[u8] = &data[..data.len() - CHECKSUM_LEN - SIGNATURE_LEN];
+
+        let timestamp: Timestamp = Timestamp::from_secs(to_u64(&data[..TIMESTAMP_LEN]));
+
+        let mut payment_hash: Option<Sha256Hash> = None;
+        let mut description: Option<String> = None;
+        let mut description_hash: Option<Sha256Hash> = None;
+        let mut expiry: Option<u64> = None;
+
+        let mut fields: &[u8] = &data[TIMESTAMP_LEN..];
+        while !fields.is_empty() {
+            if fields.len() < 3 {
+                return Err(Error::InvalidField);
+            }
+
+            let tag: u8 = fields[0];
+            let len: usize = fields[1] as usize * 32 + fields[2] as usize;
+            let value: &[u8] = fields.get(3..3 + len).ok_or(Error::InvalidField)?;
+            fields = &fields[3 + len..];
+
+            // Readers must use the first field of each type and skip the ones with unexpected length
+            match tag {
+                TAG_PAYMENT_HASH if payment_hash.is_none() && len == HASH_LEN => {
+                    payment_hash = Sha256Hash::from_slice(&to_bytes(value)).ok();
+                }
+                TAG_DESCRIPTION_HASH if description_hash.is_none() && len == HASH_LEN => {
+                    description_hash = Sha256Hash::from_slice(&to_bytes(value)).ok();
+                }
+                TAG_DESCRIPTION if description.is_none() => {
+                    description = Some(
+                        String::from_utf8(to_bytes(value))
+                            .map_err(|_| Error::InvalidDescription)?,
+                    );
+                }
+                TAG_EXPIRY if expiry.is_none() && len <= 12 => {
+                    expiry = Some(to_u64(value));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            currency,
+            amount_msats,
+            timestamp,
+            payment_hash: payment_hash.ok_or(Error::MissingPaymentHash)?,
+            description,
+            description_hash,
+            expiry,
+        })
+    }
+
+    /// Expiration timestamp
+    #[inline]
+    pub fn expires_at(&self) -> Timestamp {
+        self.timestamp + self.expiry.unwrap_or(DEFAULT_EXPIRY)
+    }
+}
+
+impl FromStr for Bolt11Invoice {
+    type Err = Error;
+
+    fn from_str(invoice: &str) -> Result<Self, Self::Err> {
+        Self::parse(invoice)
+    }
+}
+
+/// Parse the human readable part (`ln` + currency + optional amount)
+fn parse_hrp(hrp: &str) -> Result<(String, Option<u64>), Error> {
+    let hrp: &str = hrp.strip_prefix("ln").ok_or(Error::InvalidPrefix)?;
+
+    let amount_start: usize = hrp.find(|c: char| c.is_ascii_digit()).unwrap_or(hrp.len());
+    let (currency, amount) = hrp.split_at(amount_start);
+
+    if currency.is_empty() {
+        return Err(Error::InvalidPrefix);
+    }
+
+    if amount.is_empty() {
+        return Ok((String::from(currency), None));
+    }
+
+    let (value, multiplier) = match amount.chars().last() {
+        Some(c) if c.is_ascii_digit() => (amount, None),
+        Some(c) => (&amount[..amount.len() - 1], Some(c)),
+        None => return Err(Error::InvalidAmount),
+    };
+
+    let value: u64 = value.parse().map_err(|_| Error::InvalidAmount)?;
+
+    // 1 BTC = 100_000_000_000 msat
+    let msats: u64 = match multiplier {
+        None => value.checked_mul(100_000_000_000),
+        Some('m') => value.checked_mul(100_000_000),
+        Some('u') => value.checked_mul(100_000),
+        Some('n') => value.checked_mul(100),
+        Some('p') if value % 10 == 0 => Some(value / 10),
+        _ => None,
+    }
+    .ok_or(Error::InvalidAmount)?;
+
+    Ok((String::from(currency), Some(msats)))
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    let mut chk: u32 = 1;
+    for v in values.iter() {
+        let b: u32 = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ (*v as u32);
+        for (i, g) in GEN.iter().enumerate() {
+            if (b >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+/// Verify bech32 checksum (without the length limit of BIP173)
+fn verify_checksum(hrp: &str, data: &[u8]) -> bool {
+    let mut values: Vec<u8> = Vec::with_capacity(hrp.len() * 2 + 1 + data.len());
+    values.extend(hrp.bytes().map(|b| b >> 5));
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 31));
+    values.extend_from_slice(data);
+    polymod(&values) == 1
+}
+
+/// Convert 5-bit groups to bytes, dropping the padding
+fn to_bytes(data: &[u8]) -> Vec<u8> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut bytes: Vec<u8> = Vec::with_capacity(data.len() * 5 / 8);
+    for v in data.iter() {
+        acc = (acc << 5) | *v as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+        acc &= (1 << bits) - 1;
+    }
+    bytes
+}
+
+/// Convert big-endian 5-bit groups to integer
+fn to_u64(data: &[u8]) -> u64 {
+    data.iter().fold(0, |acc, v| (acc << 5) | *v as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_invoice() {
+        // BOLT11 example
+        let invoice = Bolt11Invoice::parse("lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh").unwrap();
+        assert_eq!(invoice.currency, "bc");
+        assert_eq!(invoice.amount_msats, Some(250_000_000));
+        assert_eq!(invoice.timestamp, Timestamp::from_secs(1496314658));
+        assert_eq!(
+            invoice.payment_hash.to_string(),
+            "0001020304050607080900010203040506070809000102030405060708090102"
+        );
+        assert_eq!(invoice.description.as_deref(), Some("1 cup coffee"));
+        assert_eq!(invoice.description_hash, None);
+        assert_eq!(invoice.expiry, Some(60));
+        assert_eq!(invoice.expires_at(), Timestamp::from_secs(1496314718));
+
+        let invoice = Bolt11Invoice::parse("lnbc10u1p3unwfusp5t9r3yymhpfqculx78u027lxspgxcr2n2987mx2j55nnfs95nxnzqpp5jmrh92pfld78spqs78v9euf2385t83uvpwk9ldrlvf6ch7tpascqhp5zvkrmemgth3tufcvflmzjzfvjt023nazlhljz2n9hattj4f8jq8qxqyjw5qcqpjrzjqtc4fc44feggv7065fqe5m4ytjarg3repr5j9el35xhmtfexc42yczarjuqqfzqqqqqqqqlgqqqqqqgq9q9qxpqysgq079nkq507a5tw7xgttmj4u990j7wfggtrasah5gd4ywfr2pjcn29383tphp4t48gquelz9z78p4cq7ml3nrrphw5w6eckhjwmhezhnqpy6gyf0").unwrap();
+        assert_eq!(invoice.amount_msats, Some(1_000_000));
+        assert_eq!(
+            invoice.payment_hash.to_string(),
+            "96c772a829fb7c780410f1d85cf12a89e8b3c78c0bac5fb47f62758bf961ec30"
+        );
+        assert_eq!(
+            invoice.description_hash.unwrap().to_string(),
+            "132c3de7685de2be270c4ff629092c92dea8cfa2fdff212a65bf56b95527900e"
+        );
+        assert_eq!(invoice.expiry, Some(604800));
+    }
+
+    #[test]
+    fn test_parse_hrp() {
+        assert_eq!(parse_hrp("lnbc").unwrap(), (String::from("bc"), None));
+        assert_eq!(
+            parse_hrp("lnbcrt2m").unwrap(),
+            (String::from("bcrt"), Some(200_000_000))
+        );
+        assert_eq!(parse_hrp("lntb10p").unwrap(), (String::from("tb"), Some(1)));
+        assert_eq!(parse_hrp("lnbc1p").unwrap_err(), Error::InvalidAmount);
+        assert_eq!(parse_hrp("lnbc1x").unwrap_err(), Error::InvalidAmount);
+        assert_eq!(parse_hrp("bc10u").unwrap_err(), Error::InvalidPrefix);
+    }
+
+    #[test]
+    fn test_invalid_checksum() {
+        assert_eq!(
+            Bolt11Invoice::parse("lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rq").unwrap_err(),
+            Error::InvalidChecksum
+        );
+    }
+}
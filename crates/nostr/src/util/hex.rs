@@ -39,6 +39,7 @@ impl fmt::Display for Error {
     }
 }
 
+#[cfg(not(feature = "performance"))]
 #[inline]
 const fn from_digit(num: u8) -> u8 {
     if num < 10 {
@@ -49,6 +50,17 @@ const fn from_digit(num: u8) -> u8 {
 }
 
 /// Hex encode
+#[cfg(feature = "performance")]
+#[inline]
+pub fn encode<T>(data: T) -> String
+where
+    T: AsRef<[u8]>,
+{
+    faster_hex::hex_string(data.as_ref())
+}
+
+/// Hex encode
+#[cfg(not(feature = "performance"))]
 #[inline]
 pub fn encode<T>(data: T) -> String
 where
@@ -94,6 +106,14 @@ where
         return Err(Error::InvalidLength);
     }
 
+    // SIMD fast path: on failure, fallback to the scalar decoding to get the invalid char
+    #[cfg(feature = "performance")]
+    {
+        if faster_hex::hex_decode(hex, out).is_ok() {
+            return Ok(());
+        }
+    }
+
     for (i, byte) in out.iter_mut().enumerate() {
         let high_idx: usize = i * 2;
         let high: u8 = val(hex[high_idx], high_idx)?;
@@ -150,6 +170,70 @@ mod tests {
             Error::InvalidHexCharacter { c: 'g', index: 3 }
         );
     }
+
+    /// Reference scalar implementation, to check the `performance` fast paths
+    fn scalar_decode(hex: &str) -> Result<Vec<u8>, Error> {
+        let hex: &[u8] = hex.as_bytes();
+        if hex.len() % 2 != 0 {
+            return Err(Error::OddLength);
+        }
+        hex.chunks(2)
+            .enumerate()
+            .map(|(i, pair)| Ok(val(pair[0], i * 2)? << 4 | val(pair[1], i * 2 + 1)?))
+            .collect()
+    }
+
+    #[test]
+    fn test_same_as_scalar() {
+        let data: Vec<u8> = (0..=255).collect();
+        let hex: String = encode(&data);
+        assert_eq!(hex.len(), 512);
+        assert!(hex.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f')));
+        assert_eq!(scalar_decode(&hex).unwrap(), data);
+        assert_eq!(decode(&hex).unwrap(), data);
+
+        // Lengths around the SIMD blocks
+        for len in 0..=70 {
+            let hex: String = encode(&data[..len]);
+            assert_eq!(decode(&hex), scalar_decode(&hex));
+        }
+
+        for hex in [
+            // Mixed case
+            "ABCDEF0123456789abcdef",
+            "2bE17Aa3031bDcB006F0fCe80C146dEa9C1c0268B0aF2398bB673365c6444D45",
+            // Invalid chars, also after a valid SIMD block
+            "zz",
+            "0x1234",
+            "2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d4g",
+            "2be17aa3031bdcb006f0fce80c146dea 9c1c0268b0af2398bb673365c6444d4",
+            "2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444dé",
+            // Odd length
+            "2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d4",
+        ] {
+            assert_eq!(decode(hex), scalar_decode(hex), "{hex}");
+        }
+    }
+
+    #[test]
+    fn test_decode_to_slice_wrong_length() {
+        let hex: &str = "2be17aa3031bdcb006f0fce80c146dea9c1c0268b0af2398bb673365c6444d45";
+
+        let mut out = [0u8; 32];
+        decode_to_slice(hex, &mut out).unwrap();
+        assert_eq!(out.to_vec(), scalar_decode(hex).unwrap());
+
+        let mut out = [0u8; 31];
+        assert_eq!(
+            decode_to_slice(hex, &mut out).unwrap_err(),
+            Error::InvalidLength
+        );
+        let mut out = [0u8; 33];
+        assert_eq!(
+            decode_to_slice(hex, &mut out).unwrap_err(),
+            Error::InvalidLength
+        );
+    }
 }
 
 #[cfg(bench)]
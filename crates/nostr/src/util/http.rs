@@ -0,0 +1,326 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! HTTP client
+//!
+//! Keep all the HTTP requests (i.e. NIP05 and NIP11 fetches) behind a single seam,
+//! so they can run over a custom client (i.e. with a proxy or the platform HTTP stack).
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+
+use crate::signer::{SendOutsideWasm, SyncOutsideWasm};
+use crate::Url;
+
+/// HTTP error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HttpError {
+    /// Request timed out
+    Timeout,
+    /// Can't connect to the server
+    Connection(String),
+    /// Server replied with an error status code
+    Status(u16),
+    /// Other backend error
+    Backend(String),
+}
+
+impl std::error::Error for HttpError {}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => write!(f, "request timed out"),
+            Self::Connection(e) => write!(f, "connection failed: {e}"),
+            Self::Status(code) => write!(f, "HTTP status {code}"),
+            Self::Backend(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl HttpError {
+    /// Check if the request may succeed if retried
+    ///
+    /// Timeouts, connection failures, `429 Too Many Requests` and server errors.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Timeout | Self::Connection(..) => true,
+            Self::Status(code) => *code == 429 || (500..600).contains(code),
+            Self::Backend(..) => false,
+        }
+    }
+}
+
+/// HTTP method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HttpMethod {
+    /// GET
+    Get,
+    /// POST
+    Post,
+}
+
+/// HTTP request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// Method
+    pub method: HttpMethod,
+    /// URL
+    pub url: Url,
+    /// Headers
+    pub headers: Vec<(String, String)>,
+    /// Body
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    /// GET request
+    #[inline]
+    pub fn get(url: Url) -> Self {
+        Self {
+            method: HttpMethod::Get,
+            url,
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    /// POST request
+    #[inline]
+    pub fn post(url: Url, body: Vec<u8>) -> Self {
+        Self {
+            method: HttpMethod::Post,
+            url,
+            headers: Vec::new(),
+            body: Some(body),
+        }
+    }
+
+    /// Add header
+    #[inline]
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.headers.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// HTTP response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// Status code
+    pub status: u16,
+    /// Headers
+    pub headers: Vec<(String, String)>,
+    /// Body
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Get the first header with the name (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, ..)| key.eq_ignore_ascii_case(name))
+            .map(|(.., value)| value.as_str())
+    }
+
+    /// Check if the status code is `2xx`
+    #[inline]
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Return [`HttpError::Status`] if the status code isn't `2xx`
+    #[inline]
+    pub fn error_for_status(self) -> Result<Self, HttpError> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(HttpError::Status(self.status))
+        }
+    }
+}
+
+/// HTTP client
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+pub trait HttpClient: fmt::Debug + SendOutsideWasm + SyncOutsideWasm {
+    /// Send the request and read the whole response
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpError>;
+
+    /// Get a client that sends the requests through the SOCKS5 proxy
+    ///
+    /// Used for the requests to the relays behind a proxy.
+    /// Return `None` if the proxies aren't supported (default): the default client is used in that case.
+    fn proxied(&self, proxy: SocketAddr) -> Option<Arc<dyn HttpClient>> {
+        let _ = proxy;
+        None
+    }
+}
+
+#[doc(hidden)]
+pub trait IntoHttpClient {
+    fn into_http_client(self) -> Arc<dyn HttpClient>;
+}
+
+impl IntoHttpClient for Arc<dyn HttpClient> {
+    fn into_http_client(self) -> Arc<dyn HttpClient> {
+        self
+    }
+}
+
+impl<T> IntoHttpClient for T
+where
+    T: HttpClient + 'static,
+{
+    fn into_http_client(self) -> Arc<dyn HttpClient> {
+        Arc::new(self)
+    }
+}
+
+impl<T> IntoHttpClient for Arc<T>
+where
+    T: HttpClient + 'static,
+{
+    fn into_http_client(self) -> Arc<dyn HttpClient> {
+        self
+    }
+}
+
+/// Default HTTP client, based on `reqwest`
+///
+/// Use the native stack or the `fetch()` API on WASM.
+#[cfg(any(feature = "nip05", feature = "nip11", feature = "lnurl"))]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestHttpClient {
+    client: reqwest::Client,
+}
+
+#[cfg(any(feature = "nip05", feature = "nip11", feature = "lnurl"))]
+impl ReqwestHttpClient {
+    /// New HTTP client
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// New HTTP client that connects through a SOCKS5 proxy
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_proxy(proxy: SocketAddr) -> Result<Self, HttpError> {
+        let proxy = reqwest::Proxy::all(format!("socks5h://{proxy}")).map_err(from_reqwest)?;
+        let client = reqwest::Client::builder()
+            .proxy(proxy)
+            .build()
+            .map_err(from_reqwest)?;
+        Ok(Self { client })
+    }
+
+    /// New HTTP client, with the SOCKS5 proxy if any
+    ///
+    /// **Proxy is ignored for WASM targets!**
+    pub fn with_optional_proxy(_proxy: Option<SocketAddr>) -> Result<Self, HttpError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(proxy) = _proxy {
+            return Self::with_proxy(proxy);
+        }
+
+        Ok(Self::new())
+    }
+
+    /// Use an existing `reqwest` client
+    #[inline]
+    pub fn from_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(any(feature = "nip05", feature = "nip11", feature = "lnurl"))]
+fn from_reqwest(e: reqwest::Error) -> HttpError {
+    if e.is_timeout() {
+        return HttpError::Timeout;
+    }
+
+    if let Some(status) = e.status() {
+        return HttpError::Status(status.as_u16());
+    }
+
+    if e.is_request() {
+        return HttpError::Connection(e.to_string());
+    }
+
+    HttpError::Backend(e.to_string())
+}
+
+#[cfg(any(feature = "nip05", feature = "nip11", feature = "lnurl"))]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+impl HttpClient for ReqwestHttpClient {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, HttpError> {
+        let mut builder = match request.method {
+            HttpMethod::Get => self.client.get(request.url),
+            HttpMethod::Post => self.client.post(request.url),
+        };
+
+        for (key, value) in request.headers.into_iter() {
+            builder = builder.header(key, value);
+        }
+
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+
+        let response = builder.send().await.map_err(from_reqwest)?;
+
+        let status: u16 = response.status().as_u16();
+        let headers: Vec<(String, String)> = response
+            .headers()
+            .iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body: Vec<u8> = response.bytes().await.map_err(from_reqwest)?.to_vec();
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_header() {
+        let response = HttpResponse {
+            status: 200,
+            headers: vec![(
+                String::from("Content-Type"),
+                String::from("application/json"),
+            )],
+            body: Vec::new(),
+        };
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert_eq!(response.header("date"), None);
+        assert!(response.is_success());
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(HttpError::Timeout.is_transient());
+        assert!(HttpError::Status(429).is_transient());
+        assert!(HttpError::Status(503).is_transient());
+        assert!(!HttpError::Status(404).is_transient());
+    }
+}
@@ -0,0 +1,719 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! LNURL-pay
+//!
+//! Resolve the lightning addresses (LUD16) and the LNURLs (LUD06), fetch the pay parameters and request the invoices.
+//!
+//! <https://github.com/lnurl/luds/blob/luds/06.md>
+//! <https://github.com/lnurl/luds/blob/luds/16.md>
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use bech32::{Bech32, Hrp};
+use bitcoin::hashes::sha256::Hash as Sha256Hash;
+use bitcoin::hashes::Hash;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::util::bolt11::{self, Bolt11Invoice};
+use crate::util::http::{HttpClient, HttpError, HttpRequest, HttpResponse};
+use crate::{Metadata, PublicKey, Url};
+
+const HRP_LNURL: Hrp = Hrp::parse_unchecked("lnurl");
+const PAY_REQUEST_TAG: &str = "payRequest";
+
+/// LNURL error
+#[derive(Debug)]
+pub enum Error {
+    /// Invalid lightning address
+    InvalidLightningAddress,
+    /// Invalid LNURL
+    InvalidLnUrl,
+    /// Bech32 decode error
+    Bech32Decode(bech32::DecodeError),
+    /// Bech32 encode error
+    Bech32Encode(bech32::EncodeError),
+    /// Url error
+    Url(crate::types::url::ParseError),
+    /// HTTP error
+    Http(HttpError),
+    /// JSON error
+    Json(serde_json::Error),
+    /// Error returned by the LNURL service
+    Service(String),
+    /// Not a pay request
+    NotPayRequest,
+    /// Invalid metadata
+    InvalidMetadata,
+    /// Amount out of the allowed range
+    AmountOutOfRange {
+        /// Requested amount (millisats)
+        amount: u64,
+        /// Min sendable (millisats)
+        min: u64,
+        /// Max sendable (millisats)
+        max: u64,
+    },
+    /// Comment too long
+    CommentTooLong {
+        /// Max allowed length
+        max: u16,
+    },
+    /// The LNURL service doesn't support zaps
+    ZapsNotSupported,
+    /// BOLT11 error
+    Bolt11(bolt11::Error),
+    /// The invoice doesn't match the request (amount or description hash)
+    InvoiceMismatch,
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLightningAddress => write!(f, "invalid lightning address"),
+            Self::InvalidLnUrl => write!(f, "invalid LNURL"),
+            Self::Bech32Decode(e) => write!(f, "{e}"),
+            Self::Bech32Encode(e) => write!(f, "{e}"),
+            Self::Url(e) => write!(f, "{e}"),
+            Self::Http(e) => write!(f, "{e}"),
+            Self::Json(e) => write!(f, "{e}"),
+            Self::Service(reason) => write!(f, "LNURL service error: {reason}"),
+            Self::NotPayRequest => write!(f, "not a pay request"),
+            Self::InvalidMetadata => write!(f, "invalid metadata"),
+            Self::AmountOutOfRange { amount, min, max } => write!(
+                f,
+                "amount out of range: amount={amount} msat, min={min} msat, max={max} msat"
+            ),
+            Self::CommentTooLong { max } => write!(f, "comment too long: max={max} chars"),
+            Self::ZapsNotSupported => write!(f, "zaps not supported"),
+            Self::Bolt11(e) => write!(f, "{e}"),
+            Self::InvoiceMismatch => write!(f, "invoice doesn't match the request"),
+        }
+    }
+}
+
+impl From<bech32::DecodeError> for Error {
+    fn from(e: bech32::DecodeError) -> Self {
+        Self::Bech32Decode(e)
+    }
+}
+
+impl From<bech32::EncodeError> for Error {
+    fn from(e: bech32::EncodeError) -> Self {
+        Self::Bech32Encode(e)
+    }
+}
+
+impl From<crate::types::url::ParseError> for Error {
+    fn from(e: crate::types::url::ParseError) -> Self {
+        Self::Url(e)
+    }
+}
+
+impl From<HttpError> for Error {
+    fn from(e: HttpError) -> Self {
+        Self::Http(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+impl From<bolt11::Error> for Error {
+    fn from(e: bolt11::Error) -> Self {
+        Self::Bolt11(e)
+    }
+}
+
+/// Lightning address (LUD16)
+///
+/// <https://github.com/lnurl/luds/blob/luds/16.md>
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LightningAddress {
+    username: String,
+    domain: String,
+}
+
+impl LightningAddress {
+    /// Parse lightning address (i.e. `pay@yukikishimoto.com`)
+    pub fn parse(address: &str) -> Result<Self, Error> {
+        let (username, domain) = address
+            .trim()
+            .split_once('@')
+            .ok_or(Error::InvalidLightningAddress)?;
+
+        if username.is_empty() || domain.is_empty() || domain.contains('@') {
+            return Err(Error::InvalidLightningAddress);
+        }
+
+        let address = Self {
+            username: username.to_lowercase(),
+            domain: domain.to_lowercase(),
+        };
+
+        // Check that compose a valid URL
+        address.url()?;
+
+        Ok(address)
+    }
+
+    /// Get username
+    #[inline]
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Get domain
+    #[inline]
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Get the URL of the pay endpoint
+    ///
+    /// The `.onion` domains use `http`.
+    pub fn url(&self) -> Result<Url, Error> {
+        let scheme: &str = if self.domain.ends_with(".onion") {
+            "http"
+        } else {
+            "https"
+        };
+        Ok(Url::parse(&format!(
+            "{scheme}://{}/.well-known/lnurlp/{}",
+            self.domain, self.username
+        ))?)
+    }
+}
+
+impl fmt::Display for LightningAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.username, self.domain)
+    }
+}
+
+impl FromStr for LightningAddress {
+    type Err = Error;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        Self::parse(address)
+    }
+}
+
+/// LNURL (LUD01)
+///
+/// Bech32-encoded URL with the `lnurl` prefix.
+///
+/// <https://github.com/lnurl/luds/blob/luds/01.md>
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LnUrl {
+    url: Url,
+}
+
+impl LnUrl {
+    /// Construct from URL
+    #[inline]
+    pub fn new(url: Url) -> Self {
+        Self { url }
+    }
+
+    /// Decode bech32 LNURL (the `lightning:` prefix is removed, if any)
+    pub fn decode(lnurl: &str) -> Result<Self, Error> {
+        let lnurl: &str = lnurl.trim();
+        let lnurl: &str = lnurl
+            .strip_prefix("lightning:")
+            .or_else(|| lnurl.strip_prefix("LIGHTNING:"))
+            .unwrap_or(lnurl);
+
+        let (hrp, data) = bech32::decode(lnurl)?;
+
+        if hrp != HRP_LNURL {
+            return Err(Error::InvalidLnUrl);
+        }
+
+        let url: String = String::from_utf8(data).map_err(|_| Error::InvalidLnUrl)?;
+        Ok(Self::new(Url::parse(&url)?))
+    }
+
+    /// Encode as bech32 LNURL
+    pub fn encode(&self) -> Result<String, Error> {
+        Ok(bech32::encode::<Bech32>(
+            HRP_LNURL,
+            self.url.as_str().as_bytes(),
+        )?)
+    }
+
+    /// Get URL
+    #[inline]
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+}
+
+impl FromStr for LnUrl {
+    type Err = Error;
+
+    fn from_str(lnurl: &str) -> Result<Self, Self::Err> {
+        Self::decode(lnurl)
+    }
+}
+
+/// LUD06 or LUD16
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lud06OrLud16 {
+    /// LNURL (LUD06)
+    Lud06(LnUrl),
+    /// Lightning address (LUD16)
+    Lud16(LightningAddress),
+}
+
+impl From<LnUrl> for Lud06OrLud16 {
+    fn from(lnurl: LnUrl) -> Self {
+        Self::Lud06(lnurl)
+    }
+}
+
+impl From<LightningAddress> for Lud06OrLud16 {
+    fn from(address: LightningAddress) -> Self {
+        Self::Lud16(address)
+    }
+}
+
+impl Lud06OrLud16 {
+    /// Extract from profile metadata
+    ///
+    /// The lightning address (LUD16) is preferred over the LNURL (LUD06).
+    /// Return `Ok(None)` if both are missing.
+    pub fn from_metadata(metadata: &Metadata) -> Result<Option<Self>, Error> {
+        if let Some(lud16) = &metadata.lud16 {
+            return Ok(Some(Self::Lud16(LightningAddress::parse(lud16)?)));
+        }
+
+        if let Some(lud06) = &metadata.lud06 {
+            return Ok(Some(Self::Lud06(LnUrl::decode(lud06)?)));
+        }
+
+        Ok(None)
+    }
+
+    /// Get the URL of the pay endpoint
+    pub fn url(&self) -> Result<Url, Error> {
+        match self {
+            Self::Lud06(lnurl) => Ok(lnurl.url().clone()),
+            Self::Lud16(address) => address.url(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawPayParams {
+    tag: String,
+    callback: Url,
+    min_sendable: u64,
+    max_sendable: u64,
+    metadata: String,
+    #[serde(default)]
+    comment_allowed: Option<u16>,
+    #[serde(default)]
+    allows_nostr: Option<bool>,
+    #[serde(default)]
+    nostr_pubkey: Option<PublicKey>,
+}
+
+/// LNURL-pay parameters
+///
+/// <https://github.com/lnurl/luds/blob/luds/06.md>
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PayParams {
+    /// Callback URL where to request the invoice
+    pub callback: Url,
+    /// Min sendable amount (millisats)
+    pub min_sendable: u64,
+    /// Max sendable amount (millisats)
+    pub max_sendable: u64,
+    /// Raw metadata (JSON array of `[mime, content]` entries)
+    pub metadata: String,
+    /// Max length of the comment (LUD12)
+    pub comment_allowed: Option<u16>,
+    /// If the service supports the zaps (NIP57)
+    pub allows_nostr: bool,
+    /// Public key used by the service to sign the zap receipts (NIP57)
+    pub nostr_pubkey: Option<PublicKey>,
+}
+
+impl PayParams {
+    /// Parse pay parameters from JSON, checking the tag and the metadata
+    pub fn from_json(json: &[u8]) -> Result<Self, Error> {
+        let value: Value = serde_json::from_slice(json)?;
+        Self::from_value(value)
+    }
+
+    fn from_value(value: Value) -> Result<Self, Error> {
+        check_service_error(&value)?;
+
+        let raw: RawPayParams = serde_json::from_value(value)?;
+
+        if raw.tag != PAY_REQUEST_TAG {
+            return Err(Error::NotPayRequest);
+        }
+
+        let params = Self {
+            callback: raw.callback,
+            min_sendable: raw.min_sendable,
+            max_sendable: raw.max_sendable,
+            metadata: raw.metadata,
+            comment_allowed: raw.comment_allowed.filter(|len| *len > 0),
+            allows_nostr: raw.allows_nostr.unwrap_or_default(),
+            nostr_pubkey: raw.nostr_pubkey,
+        };
+
+        params.validate_metadata()?;
+
+        Ok(params)
+    }
+
+    /// Get metadata entries (`[mime, content]`)
+    pub fn metadata_entries(&self) -> Result<Vec<(String, String)>, Error> {
+        let entries: Vec<Vec<Value>> =
+            serde_json::from_str(&self.metadata).map_err(|_| Error::InvalidMetadata)?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| {
+                let mime: &str = entry.first()?.as_str()?;
+                let content: String = match entry.get(1)? {
+                    Value::String(content) => content.clone(),
+                    other => other.to_string(),
+                };
+                Some((mime.to_string(), content))
+            })
+            .collect())
+    }
+
+    /// Get the `text/plain` description
+    pub fn description(&self) -> Option<String> {
+        self.metadata_entries()
+            .ok()?
+            .into_iter()
+            .find(|(mime, ..)| mime == "text/plain")
+            .map(|(.., content)| content)
+    }
+
+    /// Check that the metadata is a valid JSON array with the mandatory `text/plain` entry
+    pub fn validate_metadata(&self) -> Result<(), Error> {
+        match self.description() {
+            Some(..) => Ok(()),
+            None => Err(Error::InvalidMetadata),
+        }
+    }
+
+    /// SHA256 of the metadata
+    ///
+    /// Must match the description hash of the invoices, except for the zaps (check [`PayParams::expected_description_hash`]).
+    #[inline]
+    pub fn metadata_hash(&self) -> Sha256Hash {
+        Sha256Hash::hash(self.metadata.as_bytes())
+    }
+
+    /// Expected description hash of the invoice
+    ///
+    /// The SHA256 of the zap request JSON for the zaps (NIP57), otherwise the [`PayParams::metadata_hash`].
+    pub fn expected_description_hash(&self, zap_request: Option<&str>) -> Sha256Hash {
+        match zap_request {
+            Some(zap_request) => Sha256Hash::hash(zap_request.as_bytes()),
+            None => self.metadata_hash(),
+        }
+    }
+
+    /// Check if the amount (millisats) is in the allowed range
+    pub fn check_amount(&self, msats: u64) -> Result<(), Error> {
+        if msats < self.min_sendable || msats > self.max_sendable {
+            return Err(Error::AmountOutOfRange {
+                amount: msats,
+                min: self.min_sendable,
+                max: self.max_sendable,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Invoice request
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct InvoiceRequest {
+    msats: u64,
+    comment: Option<String>,
+    zap_request: Option<String>,
+    lnurl: Option<String>,
+}
+
+impl InvoiceRequest {
+    /// New invoice request for the amount (millisats)
+    #[inline]
+    pub fn new(msats: u64) -> Self {
+        Self {
+            msats,
+            ..Default::default()
+        }
+    }
+
+    /// Comment (LUD12)
+    #[inline]
+    pub fn comment<S>(mut self, comment: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Zap request JSON (NIP57)
+    #[inline]
+    pub fn zap_request<S>(mut self, zap_request: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.zap_request = Some(zap_request.into());
+        self
+    }
+
+    /// Bech32 LNURL of the recipient (NIP57)
+    #[inline]
+    pub fn lnurl<S>(mut self, lnurl: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.lnurl = Some(lnurl.into());
+        self
+    }
+
+    /// Get zap request JSON
+    #[inline]
+    pub fn get_zap_request(&self) -> Option<&str> {
+        self.zap_request.as_deref()
+    }
+
+    /// Check that the invoice has the requested amount and the expected description hash
+    ///
+    /// Check [`PayParams::expected_description_hash`].
+    pub fn check_invoice(&self, params: &PayParams, invoice: &Bolt11Invoice) -> Result<(), Error> {
+        let expected: Sha256Hash = params.expected_description_hash(self.get_zap_request());
+
+        if invoice.amount_msats != Some(self.msats) || invoice.description_hash != Some(expected) {
+            return Err(Error::InvoiceMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// Check the request against the pay parameters and compose the callback URL
+    pub fn callback_url(&self, params: &PayParams) -> Result<Url, Error> {
+        params.check_amount(self.msats)?;
+
+        if let Some(comment) = &self.comment {
+            let max: u16 = params.comment_allowed.unwrap_or_default();
+            if comment.chars().count() > max as usize {
+                return Err(Error::CommentTooLong { max });
+            }
+        }
+
+        if self.zap_request.is_some() && !params.allows_nostr {
+            return Err(Error::ZapsNotSupported);
+        }
+
+        let mut url: Url = params.callback.clone();
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("amount", &self.msats.to_string());
+
+            if let Some(comment) = &self.comment {
+                query.append_pair("comment", comment);
+            }
+
+            if let Some(zap_request) = &self.zap_request {
+                query.append_pair("nostr", zap_request);
+            }
+
+            if let Some(lnurl) = &self.lnurl {
+                query.append_pair("lnurl", lnurl);
+            }
+        }
+
+        Ok(url)
+    }
+}
+
+/// Check if the JSON is an error response (`{"status": "ERROR", "reason": "..."}`)
+fn check_service_error(value: &Value) -> Result<(), Error> {
+    if value.get("status").and_then(Value::as_str) == Some("ERROR") {
+        let reason: String = value
+            .get("reason")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        return Err(Error::Service(reason));
+    }
+
+    Ok(())
+}
+
+/// Parse the response body, surfacing the service errors before the HTTP ones
+fn parse_response(response: HttpResponse) -> Result<Value, Error> {
+    match serde_json::from_slice::<Value>(&response.body) {
+        Ok(value) => {
+            check_service_error(&value)?;
+            response.error_for_status()?;
+            Ok(value)
+        }
+        Err(e) => {
+            response.error_for_status()?;
+            Err(Error::Json(e))
+        }
+    }
+}
+
+/// Fetch the LNURL-pay parameters
+pub async fn fetch_pay_params(
+    client: &dyn HttpClient,
+    target: &Lud06OrLud16,
+) -> Result<PayParams, Error> {
+    let response: HttpResponse = client.send(HttpRequest::get(target.url()?)).await?;
+    let value: Value = parse_response(response)?;
+    PayParams::from_value(value)
+}
+
+/// Request an invoice from the LNURL-pay callback
+///
+/// The amount and the description hash of the invoice are checked against the request.
+/// Return the BOLT11 invoice.
+pub async fn request_invoice(
+    client: &dyn HttpClient,
+    params: &PayParams,
+    request: &InvoiceRequest,
+) -> Result<String, Error> {
+    let url: Url = request.callback_url(params)?;
+    let response: HttpResponse = client.send(HttpRequest::get(url)).await?;
+    let value: Value = parse_response(response)?;
+    let pr: &str = value
+        .get("pr")
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::Service(String::from("missing invoice")))?;
+
+    let invoice: Bolt11Invoice = Bolt11Invoice::parse(pr)?;
+    request.check_invoice(params, &invoice)?;
+
+    Ok(pr.to_string())
+}
+
+/// Resolve the LUD06/LUD16, fetch the pay parameters and request the invoice
+pub async fn get_invoice(
+    client: &dyn HttpClient,
+    target: &Lud06OrLud16,
+    request: &InvoiceRequest,
+) -> Result<String, Error> {
+    let params: PayParams = fetch_pay_params(client, target).await?;
+    request_invoice(client, &params, request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAY_PARAMS: &str = r#"{"callback":"https://yukikishimoto.com/lnurlp/pay/callback","maxSendable":100000000,"minSendable":1000,"metadata":"[[\"text/plain\",\"Payment to pay\"],[\"text/identifier\",\"pay@yukikishimoto.com\"]]","commentAllowed":255,"tag":"payRequest","allowsNostr":true,"nostrPubkey":"79dff8f82963424e0bb02708a22e44b4980893e3a4be0fa3cb60a43b946764e3"}"#;
+
+    #[test]
+    fn test_lightning_address() {
+        let address = LightningAddress::parse("Pay@YukiKishimoto.com").unwrap();
+        assert_eq!(address.to_string(), "pay@yukikishimoto.com");
+        assert_eq!(
+            address.url().unwrap().as_str(),
+            "https://yukikishimoto.com/.well-known/lnurlp/pay"
+        );
+
+        let address = LightningAddress::parse("pay@example.onion").unwrap();
+        assert_eq!(
+            address.url().unwrap().as_str(),
+            "http://example.onion/.well-known/lnurlp/pay"
+        );
+
+        assert!(LightningAddress::parse("yukikishimoto.com").is_err());
+        assert!(LightningAddress::parse("@yukikishimoto.com").is_err());
+    }
+
+    #[test]
+    fn test_lnurl() {
+        // LUD01 example
+        let lnurl = "LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS";
+        let decoded = LnUrl::decode(lnurl).unwrap();
+        assert_eq!(
+            decoded.url().as_str(),
+            "https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd340693afabe04be7b0ccd178df"
+        );
+
+        let encoded = decoded.encode().unwrap();
+        assert_eq!(LnUrl::decode(&encoded).unwrap(), decoded);
+        assert_eq!(
+            LnUrl::decode(&format!("lightning:{encoded}")).unwrap(),
+            decoded
+        );
+    }
+
+    #[test]
+    fn test_pay_params() {
+        let params = PayParams::from_json(PAY_PARAMS.as_bytes()).unwrap();
+        assert_eq!(params.min_sendable, 1000);
+        assert_eq!(params.comment_allowed, Some(255));
+        assert!(params.allows_nostr);
+        assert!(params.nostr_pubkey.is_some());
+        assert_eq!(params.description().as_deref(), Some("Payment to pay"));
+        assert_eq!(
+            params.metadata_hash(),
+            Sha256Hash::hash(params.metadata.as_bytes())
+        );
+        assert_eq!(
+            params.expected_description_hash(Some("{}")),
+            Sha256Hash::hash(b"{}")
+        );
+
+        // Service error
+        let err = PayParams::from_json(br#"{"status":"ERROR","reason":"not found"}"#).unwrap_err();
+        assert!(matches!(err, Error::Service(reason) if reason == "not found"));
+
+        // Invalid metadata
+        let json = PAY_PARAMS.replace(r#"\"text/plain\""#, r#"\"text/html\""#);
+        assert!(matches!(
+            PayParams::from_json(json.as_bytes()).unwrap_err(),
+            Error::InvalidMetadata
+        ));
+    }
+
+    #[test]
+    fn test_callback_url() {
+        let params = PayParams::from_json(PAY_PARAMS.as_bytes()).unwrap();
+
+        let url = InvoiceRequest::new(21_000)
+            .comment("hello")
+            .zap_request("{}")
+            .callback_url(&params)
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://yukikishimoto.com/lnurlp/pay/callback?amount=21000&comment=hello&nostr=%7B%7D"
+        );
+
+        assert!(matches!(
+            InvoiceRequest::new(1).callback_url(&params).unwrap_err(),
+            Error::AmountOutOfRange { .. }
+        ));
+    }
+}
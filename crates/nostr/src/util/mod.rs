@@ -17,10 +17,23 @@ use once_cell::sync::Lazy; // TODO: use `std::sync::LazyLock` when MSRV >= 1.80.
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+#[cfg(feature = "test-utils")]
+pub mod arbitrary;
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub mod binary;
+pub mod bolt11;
 pub mod hex;
 #[cfg(feature = "nip44")]
 pub mod hkdf;
-
+#[cfg(feature = "std")]
+pub mod http;
+#[cfg(all(feature = "std", feature = "lnurl"))]
+pub mod lnurl;
+pub mod preview;
+
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+pub use self::binary::BinaryUtil;
+pub use self::preview::ContentPreview;
 use crate::nips::nip01::Coordinate;
 use crate::{EventBuilder, EventId, PublicKey, SecretKey, Tag, UnsignedEvent};
 
@@ -0,0 +1,84 @@
+// Copyright (c) 2022-2023 Yuki Kishimoto
+// Copyright (c) 2023-2024 Rust Nostr Developers
+// Distributed under the MIT software license
+
+//! Content preview
+//!
+//! Safely include the event content in the logs.
+
+use core::fmt;
+
+const DEFAULT_MAX_CHARS: usize = 64;
+
+/// Content preview
+///
+/// Display the content on a single line (newlines and other control chars replaced with spaces),
+/// truncated to a max number of chars (default: 64).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentPreview<'a> {
+    content: &'a str,
+    max_chars: usize,
+}
+
+impl<'a> ContentPreview<'a> {
+    /// New content preview
+    #[inline]
+    pub fn new(content: &'a str) -> Self {
+        Self {
+            content,
+            max_chars: DEFAULT_MAX_CHARS,
+        }
+    }
+
+    /// Max number of chars to display, excluding the ellipsis (default: 64)
+    #[inline]
+    pub fn max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    /// Check if the content is longer than the max number of chars
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        self.content.chars().nth(self.max_chars).is_some()
+    }
+}
+
+impl fmt::Display for ContentPreview<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.content.chars().take(self.max_chars) {
+            if c.is_control() {
+                f.write_str(" ")?;
+            } else {
+                write!(f, "{c}")?;
+            }
+        }
+
+        if self.is_truncated() {
+            f.write_str("…")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+
+    #[test]
+    fn test_content_preview() {
+        let preview = ContentPreview::new("Hello\nworld!\r\n");
+        assert!(!preview.is_truncated());
+        assert_eq!(preview.to_string(), "Hello world!  ");
+
+        let preview = ContentPreview::new("Hello world!").max_chars(5);
+        assert!(preview.is_truncated());
+        assert_eq!(preview.to_string(), "Hello…");
+
+        let preview = ContentPreview::new("ñañaña").max_chars(4);
+        assert_eq!(preview.to_string(), "ñaña…");
+    }
+}